@@ -0,0 +1,75 @@
+/// Release of tonlib-sys whose `tonlib_api.tl` the types in `tl` are generated from
+const TL_SCHEMA_VERSION: &str = "2024.10.1";
+
+/// Version and features of the crate, to be included into bug reports
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BuildInfo {
+    pub crate_version: &'static str,
+    pub enabled_features: Vec<&'static str>,
+    /// Commit of the linked tonlibjson, `None` if the library doesn't report it
+    pub tonlib_commit: Option<String>,
+    /// Release of tonlib whose TL schema the crate uses
+    pub tl_schema_version: Option<String>,
+}
+
+/// Returns the version of the crate and the features it was built with.
+///
+/// tonlibjson doesn't expose its version or commit neither via TL functions nor exported symbols,
+/// so `tonlib_commit` is always `None` for the tonlib-sys releases supported now.
+pub fn build_info() -> BuildInfo {
+    BuildInfo {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        enabled_features: enabled_features(),
+        tonlib_commit: None,
+        tl_schema_version: Some(TL_SCHEMA_VERSION.to_string()),
+    }
+}
+
+fn enabled_features() -> Vec<&'static str> {
+    let features = [
+        ("state_cache", cfg!(feature = "state_cache")),
+        ("emulate_get_method", cfg!(feature = "emulate_get_method")),
+        ("no_avx512", cfg!(feature = "no_avx512")),
+        ("with_debug_info", cfg!(feature = "with_debug_info")),
+        ("liteapi", cfg!(feature = "liteapi")),
+        ("http", cfg!(feature = "http")),
+        ("webhooks", cfg!(feature = "webhooks")),
+        ("runtime-tokio", cfg!(feature = "runtime-tokio")),
+        ("runtime-async-std", cfg!(feature = "runtime-async-std")),
+    ];
+    features
+        .into_iter()
+        .filter(|(_, enabled)| *enabled)
+        .map(|(name, _)| name)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info() {
+        let info = build_info();
+        assert_eq!(info.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(info.tonlib_commit, None);
+        assert_eq!(info.tl_schema_version.as_deref(), Some(TL_SCHEMA_VERSION));
+        let has = |name: &str| info.enabled_features.contains(&name);
+        assert_eq!(has("state_cache"), cfg!(feature = "state_cache"));
+        assert_eq!(
+            has("emulate_get_method"),
+            cfg!(feature = "emulate_get_method")
+        );
+        assert_eq!(has("no_avx512"), cfg!(feature = "no_avx512"));
+        assert_eq!(has("with_debug_info"), cfg!(feature = "with_debug_info"));
+        assert_eq!(has("http"), cfg!(feature = "http"));
+        assert_eq!(has("webhooks"), cfg!(feature = "webhooks"));
+        assert_eq!(has("runtime-tokio"), cfg!(feature = "runtime-tokio"));
+        assert_eq!(
+            has("runtime-async-std"),
+            cfg!(feature = "runtime-async-std")
+        );
+        // dev-dependencies always enable liteapi
+        assert!(has("liteapi"));
+    }
+}
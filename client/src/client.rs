@@ -1,37 +1,109 @@
 use std::fs;
+use std::future::Future;
 use std::ops::Deref;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
 
+pub use account_history::*;
 use async_trait::async_trait;
 pub use block_functions::*;
 pub use block_stream::*;
 pub use builder::*;
 pub use callback::*;
+pub use client_pool::*;
+pub use config_signature::{ConfigVote, CONFIG_VOTE_TAG};
+pub use connect_phase::{
+    aggregate_connect_phases, ConnectPhase, ConnectPhaseStats, ConnectPhaseTiming,
+};
 pub use connection::*;
+pub use consistent_states::{ConsistentStates, PinnedAccountState, CONSISTENT_STATES_CONCURRENCY};
+pub use deadline::*;
+#[cfg(feature = "liteapi")]
+pub use diagnostics::*;
 pub use error::*;
+pub use failover::*;
+pub use fallback::*;
+pub use head_watcher::*;
 pub use interface::*;
+pub use mc_info_cache::CachedMasterchainInfo;
+use mc_info_cache::{McInfoCache, DEFAULT_MC_INFO_CACHE_TTL};
+pub use network_addresses::NetworkAddresses;
+use network_addresses::NetworkAddressesCache;
+pub use network_topology::{NetworkTopology, WorkchainDescr, WorkchainFormat};
+use network_tps::TpsCache;
+use num_traits::ToPrimitive;
+pub use pager::*;
 use rand::Rng;
+use retry_budget::{retry_with_budget, RetryBudget};
+pub use retry_budget::{
+    RetryBudgetParams, RetryBudgetStats, DEFAULT_RETRY_BUDGET_MAX_TOKENS,
+    DEFAULT_RETRY_BUDGET_RATIO,
+};
 use serde::{Deserialize, Serialize};
-use tokio::sync::Mutex;
-use tokio_retry::strategy::FixedInterval;
-use tokio_retry::RetryIf;
+#[cfg(feature = "liteapi")]
+pub use supply_info::{SupplyInfo, BURN_ADDRESS};
+use tonlib_core::cell::{ArcCell, BagOfCells, Cell, CellParser, StateInit, TonCellError};
+use tonlib_core::message::{CommonMsgInfo, InternalMessage};
+use tonlib_core::types::{Coins, ConfigValidatorSet};
+use tonlib_core::TonAddress;
+#[cfg(feature = "liteapi")]
+use tonlib_core::TonHash;
+pub use transaction_functions::*;
+pub use transaction_stream::{RawTransactionStream, TRANSACTION_STREAM_BUFFER};
+use transport::report_unsupported_transport_options;
+pub use transport::TransportOptions;
 pub use types::*;
+pub use validator_functions::*;
+pub use wallet_functions::*;
+pub use wallet_sequencer::*;
 
+use crate::emulator::{TvmEmulator, TvmEmulatorC7Builder};
+use crate::rt::{self, Mutex};
 use crate::tl::*;
+use crate::types::{TvmExecutionResult, TvmMsgSuccess};
 
+mod account_history;
 mod block_functions;
 mod block_stream;
 mod builder;
 mod callback;
+mod client_pool;
+mod config_signature;
+mod connect_phase;
 mod connection;
+mod consistent_states;
+mod deadline;
+#[cfg(feature = "liteapi")]
+mod diagnostics;
 mod error;
+mod failover;
+mod fallback;
+mod head_watcher;
 mod interface;
+mod mc_info_cache;
+mod network_addresses;
+mod network_topology;
+mod network_tps;
+mod pager;
+mod retry_budget;
+#[cfg(feature = "liteapi")]
+mod shard_sync;
+#[cfg(feature = "liteapi")]
+mod supply_info;
+mod transaction_functions;
+mod transaction_stream;
+mod transport;
 mod types;
+mod validator_functions;
+mod wallet_functions;
+mod wallet_sequencer;
 
+#[cfg(test)]
+pub(crate) mod mock;
 #[cfg(feature = "liteapi")]
-mod recent_init_block;
+pub(crate) mod recent_init_block;
 
 /// Check on perform upon connection
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -52,6 +124,16 @@ pub struct TonClient {
 struct Inner {
     retry_strategy: RetryStrategy,
     connections: Vec<PoolConnection>,
+    fallback_connections: Vec<PoolConnection>,
+    failover: Option<FailoverState>,
+    callback: Arc<dyn TonConnectionCallback>,
+    pool_params: PoolParams,
+    pool_size: AtomicUsize,
+    created_at: Instant,
+    mc_info_cache: McInfoCache,
+    network_addresses: NetworkAddressesCache,
+    tps: TpsCache,
+    retry_budget: RetryBudget,
 }
 
 impl TonClient {
@@ -63,34 +145,144 @@ impl TonClient {
         callback: Arc<dyn TonConnectionCallback>,
         connection_check: ConnectionCheck,
     ) -> Result<TonClient, TonClientError> {
-        let patched_params = if params.update_init_block {
-            patch_init_block(params).await?
-        } else {
-            params.clone()
-        };
-        let mut connections = Vec::with_capacity(pool_size);
-        for i in 0..pool_size {
-            let mut conn_params = patched_params.clone();
-            if let Some(dir) = &patched_params.keystore_dir {
-                let keystore_prefix = Path::new(dir.as_str());
-                let keystore_dir = keystore_prefix.join(format!("{}", i));
-                fs::create_dir_all(&keystore_dir)?;
-                let path_str = keystore_dir.into_os_string().into_string().map_err(|_| {
-                    TonClientError::InternalError("Error constructing keystore path".to_string())
-                })?;
-                conn_params.keystore_dir = Some(path_str)
-            };
-            let entry = PoolConnection {
-                params: conn_params,
-                callback: callback.clone(),
-                conn: Mutex::new(None),
-                connection_check: connection_check.clone(),
-            };
-            connections.push(entry);
+        Self::with_pool_params(
+            &PoolParams::fixed(pool_size),
+            params,
+            retry_strategy,
+            callback,
+            connection_check,
+        )
+        .await
+    }
+
+    /// Creates a new TonClient with connection pool growing & shrinking according to `pool_params`
+    pub async fn with_pool_params(
+        pool_params: &PoolParams,
+        params: &TonConnectionParams,
+        retry_strategy: &RetryStrategy,
+        callback: Arc<dyn TonConnectionCallback>,
+        connection_check: ConnectionCheck,
+    ) -> Result<TonClient, TonClientError> {
+        Self::create(
+            pool_params,
+            params,
+            None,
+            retry_strategy,
+            callback,
+            connection_check,
+            None,
+        )
+        .await
+    }
+
+    /// Creates a new TonClient which uses connections with `params` until they fail
+    /// `failover_params.failover_threshold` consecutive times within `failover_params.failover_window`.
+    ///
+    /// After that the pool of connections with `fallback_params` serves all the requests,
+    /// `TonConnectionCallback::on_failover` is invoked and primary connections are closed.
+    /// Switching back to the primary config isn't supported, recreate the client for that.
+    pub async fn with_failover(
+        pool_params: &PoolParams,
+        params: &TonConnectionParams,
+        fallback_params: &TonConnectionParams,
+        failover_params: &FailoverParams,
+        retry_strategy: &RetryStrategy,
+        callback: Arc<dyn TonConnectionCallback>,
+        connection_check: ConnectionCheck,
+    ) -> Result<TonClient, TonClientError> {
+        Self::create(
+            pool_params,
+            params,
+            Some((fallback_params, failover_params)),
+            retry_strategy,
+            callback,
+            connection_check,
+            None,
+        )
+        .await
+    }
+
+    /// Creates the client, `keystore_dir_fn` overrides keystore directories of the connections
+    /// with indexes of the fallback pool following the primary ones.
+    pub(crate) async fn create(
+        pool_params: &PoolParams,
+        params: &TonConnectionParams,
+        failover: Option<(&TonConnectionParams, &FailoverParams)>,
+        retry_strategy: &RetryStrategy,
+        callback: Arc<dyn TonConnectionCallback>,
+        connection_check: ConnectionCheck,
+        keystore_dir_fn: Option<&KeystoreDirFn>,
+    ) -> Result<TonClient, TonClientError> {
+        // an empty pool would panic when selecting a connection
+        if pool_params.min_size == 0 {
+            return Err(TonClientError::InternalError(format!(
+                "Pool size must be at least 1, got pool params: {:?}",
+                pool_params
+            )));
+        }
+        if pool_params.max_size < pool_params.min_size {
+            return Err(TonClientError::InternalError(format!(
+                "Invalid pool params: {:?}",
+                pool_params
+            )));
         }
+        if let Some((_, failover_params)) = failover {
+            if failover_params.failover_threshold == 0 {
+                return Err(TonClientError::InternalError(format!(
+                    "Invalid failover params: {:?}",
+                    failover_params
+                )));
+            }
+        }
+        log::info!("Creating TonClient: {:?}", crate::build_info());
+        let keystore_dir = KeystoreDir {
+            prefix: "",
+            dir_fn: keystore_dir_fn,
+            first_index: 0,
+        };
+        let connections = create_pool_connections(
+            pool_params,
+            params,
+            &keystore_dir,
+            &callback,
+            &connection_check,
+        )
+        .await?;
+        let (fallback_connections, failover) = match failover {
+            Some((fallback_params, failover_params)) => {
+                let keystore_dir = KeystoreDir {
+                    prefix: "fallback_",
+                    dir_fn: keystore_dir_fn,
+                    first_index: pool_params.max_size,
+                };
+                let fallback_connections = create_pool_connections(
+                    pool_params,
+                    fallback_params,
+                    &keystore_dir,
+                    &callback,
+                    &connection_check,
+                )
+                .await?;
+                (
+                    fallback_connections,
+                    Some(FailoverState::new(failover_params)),
+                )
+            }
+            None => (vec![], None),
+        };
         let inner = Inner {
             retry_strategy: retry_strategy.clone(),
             connections,
+            fallback_connections,
+            failover,
+            callback,
+            pool_params: pool_params.clone(),
+            pool_size: AtomicUsize::new(pool_params.min_size),
+            created_at: Instant::now(),
+            mc_info_cache: McInfoCache::new(DEFAULT_MC_INFO_CACHE_TTL),
+            network_addresses: NetworkAddressesCache::new(),
+            tps: TpsCache::new(),
+            retry_budget: RetryBudget::new(None),
         };
         Ok(TonClient {
             inner: Arc::new(inner),
@@ -105,27 +297,110 @@ impl TonClient {
         Self::builder().build().await
     }
 
-    #[allow(clippy::let_and_return)]
     async fn retrying_invoke(
         &self,
         function: &TonFunction,
+        retry_strategy: &RetryStrategy,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        retry_with_strategy(retry_strategy, || self.do_invoke(function)).await
+    }
+
+    /// Retries according to the client strategy, limited by the retry budget
+    async fn budgeted_invoke(
+        &self,
+        function: &TonFunction,
     ) -> Result<(TonConnection, TonResult), TonClientError> {
-        let fi = FixedInterval::from_millis(self.inner.retry_strategy.interval_ms);
-        let strategy = fi.take(self.inner.retry_strategy.max_retries);
-        let result = RetryIf::spawn(strategy, || self.do_invoke(function), retry_condition).await;
-        result
+        retry_with_budget(&self.inner.retry_strategy, &self.inner.retry_budget, || {
+            self.do_invoke(function)
+        })
+        .await
+    }
+
+    async fn invoke_with_retry_override(
+        &self,
+        function: &TonFunction,
+        retry: &RetryOverride,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        match retry {
+            RetryOverride::Inherit => self.budgeted_invoke(function).await,
+            RetryOverride::None => self.do_invoke(function).await,
+            RetryOverride::Custom(retry_strategy) => {
+                self.retrying_invoke(function, retry_strategy).await
+            }
+        }
     }
 
     async fn do_invoke(
         &self,
         function: &TonFunction,
     ) -> Result<(TonConnection, TonResult), TonClientError> {
+        self.adjust_pool_size().await;
         let item = self.random_item();
-        let conn = item.get_connection().await?;
-        let res = conn.invoke(function).await;
-        match res {
-            Ok(result) => Ok((conn, result)),
+        let res = match item.get_connection().await {
+            Ok(conn) => {
+                let _in_flight = item.start_request(self.elapsed_ms());
+                conn.invoke(function).await.map(|result| (conn, result))
+            }
             Err(error) => Err(error),
+        };
+        match &res {
+            Ok((_, result)) => {
+                self.inner.mc_info_cache.observe(result);
+                self.inner.retry_budget.on_success();
+                if let Some(failover) = &self.inner.failover {
+                    failover.on_success();
+                }
+            }
+            Err(error) => self.observe_failure(error).await,
+        }
+        res
+    }
+
+    /// Counts outages of the primary pool and activates the fallback one if configured.
+    async fn observe_failure(&self, error: &TonClientError) {
+        let failover = match &self.inner.failover {
+            Some(failover) if fallback::is_outage_error(error) => failover,
+            _ => return,
+        };
+        if let Some(consecutive_failures) = failover.on_failure(Instant::now()) {
+            log::warn!(
+                "Primary config failed {} consecutive times, switching to fallback config",
+                consecutive_failures
+            );
+            self.inner.callback.on_failover(consecutive_failures, error);
+            for conn in self.inner.connections.iter() {
+                conn.close().await;
+            }
+        }
+    }
+
+    /// Connects all connections of the current pool size concurrently
+    /// and returns percentiles of the connect phases across them.
+    pub async fn connect_eagerly(&self) -> Result<Vec<ConnectPhaseStats>, TonClientError> {
+        let size = self.inner.pool_size.load(Ordering::Relaxed);
+        let connects = self.connections()[..size]
+            .iter()
+            .map(|entry| entry.get_connection());
+        futures::future::try_join_all(connects).await?;
+        Ok(self.connect_report().await)
+    }
+
+    /// Returns percentiles of the connect phases across the connected pool entries
+    pub async fn connect_report(&self) -> Vec<ConnectPhaseStats> {
+        let mut connects = vec![];
+        for entry in self.connections() {
+            if let Some(phases) = entry.connect_phases().await {
+                connects.push(phases);
+            }
+        }
+        aggregate_connect_phases(connects.iter().map(|phases| phases.as_slice()))
+    }
+
+    /// Returns connections of the currently used config
+    fn connections(&self) -> &[PoolConnection] {
+        match &self.inner.failover {
+            Some(failover) if failover.is_active() => &self.inner.fallback_connections,
+            _ => &self.inner.connections,
         }
     }
 
@@ -133,12 +408,405 @@ impl TonClient {
     fn random_item(&self) -> &PoolConnection {
         let i = {
             let mut rng = rand::thread_rng();
-            rng.gen_range(0..self.inner.connections.len())
+            rng.gen_range(0..self.inner.pool_size.load(Ordering::Acquire))
         };
-        let entry = &self.inner.connections[i];
+        let entry = &self.connections()[i];
         entry
     }
 
+    /// Grows the pool when it's overloaded and closes idle connections above `min_size`.
+    async fn adjust_pool_size(&self) {
+        let pool_params = &self.inner.pool_params;
+        if pool_params.min_size == pool_params.max_size {
+            return;
+        }
+        let connections = self.connections();
+        let now = self.elapsed_ms();
+        let size = self.inner.pool_size.load(Ordering::Acquire);
+        let in_flight: usize = connections[..size]
+            .iter()
+            .map(|c| c.in_flight.load(Ordering::Relaxed))
+            .sum();
+        if size < pool_params.max_size && in_flight >= size * pool_params.grow_threshold {
+            // mark new connection as used, so that it's not reaped before it gets any load
+            connections[size].last_used_ms.store(now, Ordering::Relaxed);
+            if self
+                .inner
+                .pool_size
+                .compare_exchange(size, size + 1, Ordering::AcqRel, Ordering::Relaxed)
+                .is_ok()
+            {
+                log::info!("Connection pool grown to {} connections", size + 1);
+            }
+        } else if size > pool_params.min_size {
+            let last = &connections[size - 1];
+            let idle_ms = now.saturating_sub(last.last_used_ms.load(Ordering::Relaxed));
+            if last.in_flight.load(Ordering::Relaxed) == 0
+                && idle_ms >= pool_params.idle_ttl_ms
+                && self
+                    .inner
+                    .pool_size
+                    .compare_exchange(size, size - 1, Ordering::AcqRel, Ordering::Relaxed)
+                    .is_ok()
+            {
+                log::info!("Connection pool shrunk to {} connections", size - 1);
+                last.close().await;
+            }
+        }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.inner.created_at.elapsed().as_millis() as u64
+    }
+
+    /// Invokes `function` on a random connection and returns the response as received from tonlib.
+    ///
+    /// Intended for debugging only, see `TonConnection::invoke_raw_bytes`.
+    pub async fn invoke_raw_bytes(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, Vec<u8>), TonClientError> {
+        let conn = self.get_connection().await?;
+        conn.invoke_raw_bytes(function).await
+    }
+
+    /// Returns masterchain info, reusing the cached one if it's younger than configured TTL
+    /// and no newer masterchain block has been observed in other responses since then.
+    ///
+    /// Concurrent calls with expired cache issue a single request.
+    pub async fn masterchain_info_cached(&self) -> Result<CachedMasterchainInfo, TonClientError> {
+        let cache = &self.inner.mc_info_cache;
+        if let Some(cached) = cache.get() {
+            return Ok(cached);
+        }
+        let _lock = cache.fetch_lock().lock().await;
+        if let Some(cached) = cache.get() {
+            return Ok(cached);
+        }
+        let info = self.masterchain_info_fresh().await?;
+        Ok(CachedMasterchainInfo {
+            info,
+            age: Duration::ZERO,
+        })
+    }
+
+    /// Requests masterchain info bypassing the cache. The cache is updated with the result.
+    pub async fn masterchain_info_fresh(&self) -> Result<BlocksMasterchainInfo, TonClientError> {
+        self.get_masterchain_info().await.map(|(_, info)| info)
+    }
+
+    /// Starts tracking the latest masterchain block, see `HeadWatcher`.
+    ///
+    /// Requests of the watcher go through this client, so the masterchain info cache
+    /// is refreshed on every detected block as well.
+    pub fn watch_head(&self, params: &HeadWatcherParams) -> HeadWatcher {
+        HeadWatcher::start(self.clone(), params)
+    }
+
+    /// Returns the hash of the shard state after applying the block `block_id`.
+    ///
+    /// Only the block header with the Merkle update of the state is requested from liteservers
+    /// of the current config, so the block body isn't downloaded. The header proof is checked
+    /// against `block_id.root_hash`.
+    #[cfg(feature = "liteapi")]
+    pub async fn get_block_state_hash(
+        &self,
+        block_id: &BlockIdExt,
+    ) -> Result<TonHash, TonClientError> {
+        use crate::config::TonConfig;
+
+        let ton_config = TonConfig::from_json(&self.connections()[0].params.config)
+            .map_err(|e| TonClientError::InternalError(format!("Fail to parse config: {}", e)))?;
+        let mut last_error = None;
+        for endpoint in ton_config.liteservers.iter() {
+            match recent_init_block::get_block_state_hash(endpoint.clone(), block_id).await {
+                Ok(hash) => return Ok(hash),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to get state hash of block {} from liteserver {}: {}",
+                        block_id.seqno,
+                        endpoint.ip,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(TonClientError::InternalError(format!(
+            "Failed to get state hash of block {}: {}",
+            block_id.seqno,
+            last_error.map_or_else(|| "no liteservers".to_string(), |e| e.to_string())
+        )))
+    }
+
+    /// Downloads the shard state after applying the block `block_id` as a BoC.
+    ///
+    /// Liteservers of the current config are tried in order, old states are kept only by archive
+    /// nodes. States take several MB, so `max_size_bytes` limits the accepted response size:
+    /// the download is dropped with `TonClientError::ResponseTooLarge` as soon as it is exceeded,
+    /// without trying other liteservers.
+    #[cfg(feature = "liteapi")]
+    pub async fn get_shard_state_raw(
+        &self,
+        block_id: &BlockIdExt,
+        max_size_bytes: Option<usize>,
+    ) -> Result<Vec<u8>, TonClientError> {
+        use crate::config::TonConfig;
+
+        let ton_config = TonConfig::from_json(&self.connections()[0].params.config)
+            .map_err(|e| TonClientError::InternalError(format!("Fail to parse config: {}", e)))?;
+        let mut last_error = None;
+        for endpoint in ton_config.liteservers.iter() {
+            let state =
+                recent_init_block::get_shard_state(endpoint.clone(), block_id, max_size_bytes);
+            match state.await.map_err(|e| e.downcast::<TonClientError>()) {
+                Ok(state) => return Ok(state),
+                // the state is as large on other liteservers
+                Err(Ok(e)) => return Err(e),
+                Err(Err(e)) => {
+                    log::warn!(
+                        "Failed to get shard state of block {} from liteserver {}: {}",
+                        block_id.seqno,
+                        endpoint.ip,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(TonClientError::InternalError(format!(
+            "Failed to get shard state of block {}: {}",
+            block_id.seqno,
+            last_error.map_or_else(|| "no liteservers".to_string(), |e| e.to_string())
+        )))
+    }
+
+    /// Computes the state of the shard after `end_block` from `base_state`, the state after
+    /// `start_block`, applying state updates of all blocks in between.
+    ///
+    /// Both blocks must belong to the same shard with no splits or merges in between.
+    /// Blocks are downloaded from liteservers of the current config, the state is recomputed
+    /// with the first liteserver having all of them. The hash of the state after each block,
+    /// `end_block` included, is checked against the state update of the block.
+    #[cfg(feature = "liteapi")]
+    pub async fn sync_shard_state(
+        &self,
+        start_block: &BlockIdExt,
+        end_block: &BlockIdExt,
+        base_state: Cell,
+    ) -> Result<Cell, TonClientError> {
+        use crate::config::TonConfig;
+
+        let blocks = shard_sync::block_chain_between(self, start_block, end_block).await?;
+        let base_state = Arc::new(base_state);
+        let ton_config = TonConfig::from_json(&self.connections()[0].params.config)
+            .map_err(|e| TonClientError::InternalError(format!("Fail to parse config: {}", e)))?;
+        let mut last_error = None;
+        for endpoint in ton_config.liteservers.iter() {
+            match recent_init_block::apply_state_updates(endpoint.clone(), &blocks, &base_state)
+                .await
+            {
+                Ok(state) => return Ok(state),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to sync shard state to block {} with liteserver {}: {}",
+                        end_block.seqno,
+                        endpoint.ip,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(TonClientError::InternalError(format!(
+            "Failed to sync shard state to block {}: {}",
+            end_block.seqno,
+            last_error.map_or_else(|| "no liteservers".to_string(), |e| e.to_string())
+        )))
+    }
+
+    /// Returns the supply of Toncoin at the latest masterchain block.
+    ///
+    /// The total supply is `global_balance` read from the proof of the masterchain state extra,
+    /// requested from liteservers of the current config and checked against the block, so
+    /// the state itself isn't downloaded. Balances of the burn address and of the config & minter
+    /// contracts are read at the same block, see `SupplyInfo` for the meaning of the amounts.
+    #[cfg(feature = "liteapi")]
+    pub async fn get_supply_info(&self) -> Result<SupplyInfo, TonClientError> {
+        use crate::config::TonConfig;
+
+        let block = self.masterchain_info_cached().await?.info.last;
+        let ton_config = TonConfig::from_json(&self.connections()[0].params.config)
+            .map_err(|e| TonClientError::InternalError(format!("Fail to parse config: {}", e)))?;
+        let mut total_supply = None;
+        let mut last_error = None;
+        for endpoint in ton_config.liteservers.iter() {
+            match recent_init_block::get_global_balance(endpoint.clone(), &block).await {
+                Ok(balance) => {
+                    total_supply = Some(balance);
+                    break;
+                }
+                Err(e) => {
+                    log::warn!(
+                        "Failed to get global balance at block {} from liteserver {}: {}",
+                        block.seqno,
+                        endpoint.ip,
+                        e
+                    );
+                    last_error = Some(e);
+                }
+            }
+        }
+        let total_supply = total_supply.ok_or_else(|| {
+            TonClientError::InternalError(format!(
+                "Failed to get global balance at block {}: {}",
+                block.seqno,
+                last_error.map_or_else(|| "no liteservers".to_string(), |e| e.to_string())
+            ))
+        })?;
+        let addresses = self.get_network_addresses().await?;
+        supply_info::supply_at_block(self, block, total_supply, &addresses).await
+    }
+
+    /// Enables the retry budget shared by all calls of the client and its clones,
+    /// or disables it if `params` is `None`. Calls with `RetryOverride::Custom` bypass the budget.
+    pub fn set_retry_budget(&self, params: Option<&RetryBudgetParams>) {
+        self.inner.retry_budget.set_params(params)
+    }
+
+    pub fn retry_budget_stats(&self) -> RetryBudgetStats {
+        self.inner.retry_budget.stats()
+    }
+
+    /// Sets for how long masterchain info is reused by `masterchain_info_cached`.
+    pub fn set_masterchain_info_ttl(&self, ttl: Duration) {
+        self.inner.mc_info_cache.set_ttl(ttl)
+    }
+
+    /// Returns transactions per second of the whole network over the last `window_blocks`
+    /// masterchain blocks, counting transactions of the masterchain blocks and of the shard blocks
+    /// finalized in them. Elapsed time is measured by `gen_utime` of the blocks.
+    ///
+    /// Transaction counts are cached per block, so repeated calls only fetch new blocks.
+    pub async fn get_tps(&self, window_blocks: u32) -> Result<f64, TonClientError> {
+        self.inner.tps.get_tps(self, window_blocks).await
+    }
+
+    /// Executes `code` with `data` on an internal message with `message` body and no value
+    /// attached, as if the contract was deployed to `workchain` with `balance`.
+    ///
+    /// The contract doesn't have to exist on chain, only the latest config is fetched.
+    pub async fn execute_tvm(
+        &self,
+        code: &Cell,
+        data: &Cell,
+        message: &Cell,
+        balance: &Coins,
+        workchain: i8,
+    ) -> Result<TvmExecutionResult, TonClientError> {
+        let code = Arc::new(code.clone());
+        let data = Arc::new(data.clone());
+        let account_id = StateInit::create_account_id(&code, &data).map_err(map_error)?;
+        let address = TonAddress::new(workchain as i32, &account_id);
+        let balance = balance.nanotons().to_u64().ok_or_else(|| {
+            TonClientError::InternalError(format!("Balance {} exceeds u64", balance.nanotons()))
+        })?;
+        let code_boc = BagOfCells::from_root(code.as_ref().clone())
+            .serialize(false)
+            .map_err(map_error)?;
+        let data_boc = BagOfCells::from_root(data.as_ref().clone())
+            .serialize(false)
+            .map_err(map_error)?;
+        let config = self.get_config_all(0).await?;
+        let c7 = TvmEmulatorC7Builder::new(&address, &config.config.bytes, balance).build();
+        let message = message.clone();
+        let result = rt::spawn_blocking(move || {
+            let mut emulator = TvmEmulator::new(&code_boc, &data_boc)?;
+            emulator.set_c7(&c7)?;
+            emulator.send_internal_message(message, 0)
+        })
+        .await
+        .map_err(|e| TonClientError::InternalError(e.to_string()))?
+        .map_err(map_error)?;
+        Ok(TvmExecutionResult {
+            exit_code: result.vm_exit_code,
+            gas_used: result.gas_used,
+            out_messages: result.out_messages().map_err(map_error)?,
+            new_data: result.new_data.as_ref().clone(),
+        })
+    }
+
+    /// Emulates processing of the internal `message` by its destination account in the current
+    /// state, e.g. to preview the result of a swap before sending the message triggering it.
+    ///
+    /// See `emulate_internal_message_on_state` for details.
+    pub async fn emulate_internal_message(
+        &self,
+        message: &Cell,
+    ) -> Result<TvmMsgSuccess, TonClientError> {
+        let (info, _, _) = parse_internal_message(message).map_err(map_error)?;
+        let state = self.get_raw_account_state(&info.dest).await?;
+        self.emulate_internal_message_on_state(&state, message)
+            .await
+    }
+
+    /// Emulates processing of the internal `message` by its destination account in `state`.
+    ///
+    /// `message` is the complete internal message, e.g. built by `TransferMessage`.
+    /// The new code & data of the account are returned in `new_code` & `new_data`,
+    /// the messages it sends in `out_messages()`. If the account isn't deployed,
+    /// the state init attached to the message is emulated.
+    ///
+    /// Only the compute phase of the destination is emulated: fees, bounces and the action phase
+    /// are not, and the contract receives the message body only, without the headers.
+    /// The sender and the bounce flag of `message` don't reach the contract, so contracts
+    /// checking the sender, e.g. jetton wallets accepting transfers from their master only,
+    /// can't be emulated this way.
+    /// The messages sent by the destination are not processed either. To follow a chain of
+    /// messages A -> B -> C, emulate an out message on the state of its destination,
+    /// which doesn't reflect the effects of the previous hops.
+    pub async fn emulate_internal_message_on_state(
+        &self,
+        state: &RawFullAccountState,
+        message: &Cell,
+    ) -> Result<TvmMsgSuccess, TonClientError> {
+        let (info, state_init, body) = parse_internal_message(message).map_err(map_error)?;
+        let (code_boc, data_boc) = account_code_data(&info.dest, state, state_init)?;
+        let value = info.value.to_u64().ok_or_else(|| {
+            TonClientError::InternalError(format!("Message value {} exceeds u64", info.value))
+        })?;
+        // the incoming value is credited before the compute phase
+        let balance = (state.balance.max(0) as u64).saturating_add(value);
+        let config = self.get_config_all(0).await?;
+        let c7 = TvmEmulatorC7Builder::from_account_state(&info.dest, &config.config.bytes, state)
+            .with_balance(balance)
+            .build();
+        rt::spawn_blocking(move || {
+            let mut emulator = TvmEmulator::new(&code_boc, &data_boc)?;
+            emulator.set_c7(&c7)?;
+            emulator.send_internal_message(body, value)
+        })
+        .await
+        .map_err(|e| TonClientError::InternalError(e.to_string()))?
+        .map_err(map_error)
+    }
+
+    /// Verifies votes of validators from `validator_set` for the config change `proposal`,
+    /// a `ConfigProposal` cell submitted to the config contract.
+    ///
+    /// `votes` are bodies of the external messages voting at the config contract, see `ConfigVote`.
+    /// Votes refer to validators by index in the current validator set, so `validator_set` must be
+    /// the one of config param 34 at the time of voting, see `get_current_validator_set`.
+    /// Returns `true` if validators holding more than 3/4 of the total weight signed a vote
+    /// for the proposal.
+    pub fn verify_config_signature(
+        proposal: &Cell,
+        votes: &[Cell],
+        validator_set: &ConfigValidatorSet,
+    ) -> Result<bool, TonClientError> {
+        config_signature::verify_config_signature(proposal, votes, validator_set)
+    }
+
     pub fn set_log_verbosity_level(verbosity_level: u32) {
         TlTonClient::set_log_verbosity_level(verbosity_level)
     }
@@ -156,7 +824,29 @@ impl TonClientInterface for TonClient {
         &self,
         function: &TonFunction,
     ) -> Result<(TonConnection, TonResult), TonClientError> {
-        self.retrying_invoke(function).await
+        self.budgeted_invoke(function).await
+    }
+
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        let method = function.into();
+        let timeout = options.call_timeout(method)?;
+        let call = self.invoke_with_retry_override(function, &options.retry);
+        connection::with_timeout(timeout, method, "call", call)
+            .await?
+            .map(|(_, r)| r)
+    }
+
+    async fn masterchain_info_cached(&self) -> Result<CachedMasterchainInfo, TonClientError> {
+        TonClient::masterchain_info_cached(self).await
+    }
+
+    /// Returns addresses cached until the next key block
+    async fn get_network_addresses(&self) -> Result<Arc<NetworkAddresses>, TonClientError> {
+        self.inner.network_addresses.get(self).await
     }
 }
 
@@ -169,10 +859,47 @@ impl Clone for TonClient {
 }
 
 fn maybe_error_code(error: &TonClientError) -> Option<i32> {
-    if let TonClientError::TonlibError { code, .. } = error {
-        Some(*code)
-    } else {
-        None
+    match error {
+        TonClientError::TonlibError { code, .. } => Some(*code),
+        TonClientError::ConnectFailed { source, .. } => maybe_error_code(source),
+        _ => None,
+    }
+}
+
+/// Calls `action` until it succeeds or fails with an error which isn't worth retrying,
+/// making at most `retry_strategy.max_retries` retries.
+pub(crate) async fn retry_with_strategy<T, A, F>(
+    retry_strategy: &RetryStrategy,
+    action: A,
+) -> Result<T, TonClientError>
+where
+    A: FnMut() -> F,
+    F: Future<Output = Result<T, TonClientError>>,
+{
+    retry_with_condition(retry_strategy, action, retry_condition).await
+}
+
+/// Same as `retry_with_strategy`, retrying only the errors accepted by `condition`
+pub(crate) async fn retry_with_condition<T, A, F, C>(
+    retry_strategy: &RetryStrategy,
+    mut action: A,
+    mut condition: C,
+) -> Result<T, TonClientError>
+where
+    A: FnMut() -> F,
+    F: Future<Output = Result<T, TonClientError>>,
+    C: FnMut(&TonClientError) -> bool,
+{
+    let interval = Duration::from_millis(retry_strategy.interval_ms);
+    let mut retries = 0;
+    loop {
+        match action().await {
+            Err(e) if retries < retry_strategy.max_retries && condition(&e) => {
+                retries += 1;
+                rt::sleep(interval).await;
+            }
+            result => return result,
+        }
     }
 }
 
@@ -184,6 +911,167 @@ fn retry_condition(error: &TonClientError) -> bool {
     }
 }
 
+/// Keystore directories of a pool
+struct KeystoreDir<'a> {
+    /// Prefix of `{prefix}{i}` subdirectories of `TonConnectionParams::keystore_dir`
+    prefix: &'static str,
+    /// Overrides the directory of the connection with index `first_index + i`
+    dir_fn: Option<&'a KeystoreDirFn>,
+    first_index: usize,
+}
+
+impl KeystoreDir<'_> {
+    fn path(&self, params: &TonConnectionParams, i: usize) -> Option<PathBuf> {
+        match (self.dir_fn, &params.keystore_dir) {
+            (Some(dir_fn), _) => Some(dir_fn(self.first_index + i)),
+            (None, Some(dir)) => {
+                Some(Path::new(dir.as_str()).join(format!("{}{}", self.prefix, i)))
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// Creates `pool_params.max_size` lazily connected pool entries,
+/// each one using its own keystore directory.
+async fn create_pool_connections(
+    pool_params: &PoolParams,
+    params: &TonConnectionParams,
+    keystore_dir: &KeystoreDir<'_>,
+    callback: &Arc<dyn TonConnectionCallback>,
+    connection_check: &ConnectionCheck,
+) -> Result<Vec<PoolConnection>, TonClientError> {
+    report_unsupported_transport_options(params, callback);
+    let patched_params = if params.update_init_block {
+        patch_init_block(params).await?
+    } else {
+        params.clone()
+    };
+    let mut connections = Vec::with_capacity(pool_params.max_size);
+    for i in 0..pool_params.max_size {
+        let mut conn_params = patched_params.clone();
+        if let Some(keystore_dir) = keystore_dir.path(&patched_params, i) {
+            fs::create_dir_all(&keystore_dir)?;
+            let path_str = keystore_dir.into_os_string().into_string().map_err(|_| {
+                TonClientError::InternalError("Error constructing keystore path".to_string())
+            })?;
+            conn_params.keystore_dir = Some(path_str)
+        };
+        let entry = PoolConnection {
+            params: conn_params,
+            callback: callback.clone(),
+            conn: Mutex::new(None),
+            connection_check: connection_check.clone(),
+            in_flight: AtomicUsize::new(0),
+            last_used_ms: AtomicU64::new(0),
+        };
+        connections.push(entry);
+    }
+    Ok(connections)
+}
+
+/// Splits external inbound message into code & data of its state init and its body.
+///
+/// ```raw
+/// message$_ {X:Type} info:CommonMsgInfo
+///   init:(Maybe (Either StateInit ^StateInit))
+///   body:(Either X ^X) = Message X;
+/// ```
+#[allow(clippy::type_complexity)]
+pub(crate) fn parse_external_message(
+    message: &Cell,
+) -> Result<(Option<(ArcCell, ArcCell)>, Cell), TonCellError> {
+    let mut parser = message.parser();
+    match CommonMsgInfo::parse(&mut parser)? {
+        CommonMsgInfo::ExternalIncomingMessage(_) => {}
+        info => {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Expected external inbound message, got {:?}",
+                info
+            )))
+        }
+    }
+    load_state_init_and_body(&mut parser)
+}
+
+/// Returns the header, code & data of the state init, if any, and the body of internal `message`
+#[allow(clippy::type_complexity)]
+fn parse_internal_message(
+    message: &Cell,
+) -> Result<(InternalMessage, Option<(ArcCell, ArcCell)>, Cell), TonCellError> {
+    let mut parser = message.parser();
+    let info = match CommonMsgInfo::parse(&mut parser)? {
+        CommonMsgInfo::InternalMessage(info) => info,
+        info => {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Expected internal message, got {:?}",
+                info
+            )))
+        }
+    };
+    let (state_init, body) = load_state_init_and_body(&mut parser)?;
+    Ok((info, state_init, body))
+}
+
+/// Reads `init:(Maybe (Either StateInit ^StateInit)) body:(Either X ^X)` of a message
+pub(crate) fn load_state_init_and_body(
+    parser: &mut CellParser,
+) -> Result<(Option<(ArcCell, ArcCell)>, Cell), TonCellError> {
+    let state_init = if parser.load_bit()? {
+        if parser.load_bit()? {
+            let state_init = parser.next_reference()?;
+            load_state_init_code_data(&mut state_init.parser())?
+        } else {
+            load_state_init_code_data(parser)?
+        }
+    } else {
+        None
+    };
+    let body = parser.load_either_cell_or_cell_ref()?;
+    Ok((state_init, body.as_ref().clone()))
+}
+
+/// Returns serialized code & data of the account, or of `state_init` if it isn't deployed
+pub(crate) fn account_code_data(
+    address: &TonAddress,
+    account_state: &RawFullAccountState,
+    state_init: Option<(ArcCell, ArcCell)>,
+) -> Result<(Vec<u8>, Vec<u8>), TonClientError> {
+    if !account_state.code.is_empty() {
+        return Ok((account_state.code.clone(), account_state.data.clone()));
+    }
+    match state_init {
+        Some((code, data)) => Ok((
+            BagOfCells::from_root(code.as_ref().clone())
+                .serialize(false)
+                .map_err(map_error)?,
+            BagOfCells::from_root(data.as_ref().clone())
+                .serialize(false)
+                .map_err(map_error)?,
+        )),
+        None => Err(TonClientError::InternalError(format!(
+            "Account {} is not deployed and message has no state init",
+            address
+        ))),
+    }
+}
+
+/// Reads `StateInit`, returning its code & data if both are present
+fn load_state_init_code_data(
+    parser: &mut CellParser,
+) -> Result<Option<(ArcCell, ArcCell)>, TonCellError> {
+    if parser.load_bit()? {
+        parser.skip_bits(5)?; // split_depth
+    }
+    if parser.load_bit()? {
+        parser.skip_bits(2)?; // special
+    }
+    let code = parser.load_maybe_cell_ref()?;
+    let data = parser.load_maybe_cell_ref()?;
+    let _library = parser.load_maybe_cell_ref()?;
+    Ok(code.zip(data))
+}
+
 #[cfg(not(feature = "liteapi"))]
 async fn patch_init_block(
     params: &TonConnectionParams,
@@ -203,8 +1091,12 @@ async fn patch_init_block(
         TonClientError::InternalError(msg)
     })?;
 
-    let recent_init_block = match recent_init_block::get_recent_init_block(&ton_config.liteservers)
-        .await
+    let transport_options = TransportOptions::from_params(params);
+    let recent_init_block = match recent_init_block::get_recent_init_block(
+        &ton_config.liteservers,
+        &transport_options,
+    )
+    .await
     {
         Some(block) => block,
         None => {
@@ -239,15 +1131,48 @@ async fn patch_init_block(
 struct PoolConnection {
     params: TonConnectionParams,
     callback: Arc<dyn TonConnectionCallback>,
-    conn: Mutex<Option<(TonConnection, JoinHandle<()>)>>,
+    conn: Mutex<Option<TimedConnection>>,
     connection_check: ConnectionCheck,
+    in_flight: AtomicUsize,
+    last_used_ms: AtomicU64,
+}
+
+/// Decrements the number of in-flight requests of the connection on drop
+struct InFlightGuard<'a> {
+    in_flight: &'a AtomicUsize,
+}
+
+impl Drop for InFlightGuard<'_> {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
 }
 
 impl PoolConnection {
+    fn start_request(&self, now_ms: u64) -> InFlightGuard<'_> {
+        self.last_used_ms.store(now_ms, Ordering::Relaxed);
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlightGuard {
+            in_flight: &self.in_flight,
+        }
+    }
+
+    /// Drops the connection, it will be re-established on next use.
+    async fn close(&self) {
+        let mut guard = self.conn.lock().await;
+        *guard = None;
+    }
+
+    /// Returns durations of the phases of the last connect, `None` if not connected
+    async fn connect_phases(&self) -> Option<Vec<ConnectPhaseTiming>> {
+        let guard = self.conn.lock().await;
+        guard.as_ref().map(|(_, _, phases)| phases.clone())
+    }
+
     async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
         let mut guard = self.conn.lock().await;
         match guard.deref() {
-            Some((conn, join_handle)) => {
+            Some((conn, join_handle, _)) => {
                 if join_handle.is_finished() {
                     // TODO: This is temporary implementation.
                     // At the moment, only report dead connections, in the future need to recover
@@ -256,9 +1181,9 @@ impl PoolConnection {
                 Ok(conn.clone())
             }
             None => {
-                let (conn, join_handle) = match self.connection_check {
+                let (conn, join_handle, phases) = match self.connection_check {
                     ConnectionCheck::None => {
-                        TonConnection::connect_joinable(&self.params, self.callback.clone()).await?
+                        TonConnection::connect_plain(&self.params, self.callback.clone()).await?
                     }
                     ConnectionCheck::Health => {
                         TonConnection::connect_healthy(&self.params, self.callback.clone()).await?
@@ -267,9 +1192,85 @@ impl PoolConnection {
                         TonConnection::connect_archive(&self.params, self.callback.clone()).await?
                     }
                 };
-                *guard = Some((conn.clone(), join_handle));
+                *guard = Some((conn.clone(), join_handle, phases));
                 Ok(conn)
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    /// Callback recording `on_failover` calls
+    #[derive(Default)]
+    struct FailoverLog(std::sync::Mutex<Vec<u32>>);
+
+    impl TonConnectionCallback for FailoverLog {
+        fn on_failover(&self, consecutive_failures: u32, _last_error: &TonClientError) {
+            self.0.lock().unwrap().push(consecutive_failures);
+        }
+    }
+
+    impl FailoverLog {
+        fn calls(&self) -> Vec<u32> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failover_to_fallback_pool() -> anyhow::Result<()> {
+        let log = Arc::new(FailoverLog::default());
+        let params = TonConnectionParams {
+            update_init_block: false,
+            ..Default::default()
+        };
+        let failover_params = FailoverParams {
+            failover_threshold: 2,
+            failover_window: Duration::from_secs(60),
+        };
+        let client = TonClient::with_failover(
+            &PoolParams::fixed(2),
+            &params,
+            &params,
+            &failover_params,
+            &RetryStrategy::default(),
+            log.clone(),
+            ConnectionCheck::None,
+        )
+        .await?;
+        // the primary connection established before the outage
+        let (conn, join_handle) = TonConnection::new_joinable(log.clone(), &params)?;
+        *client.inner.connections[0].conn.lock().await = Some((conn, join_handle, vec![]));
+        assert!(ptr::eq(client.connections(), &client.inner.connections[..]));
+
+        let outage = TonClientError::InternalError("Connection lost".to_string());
+        client.observe_failure(&outage).await;
+        // errors caused by the request aren't counted
+        let rejected = TonClientError::tonlib_error("RawSendMessage", 400, "INVALID_MESSAGE");
+        client.observe_failure(&rejected).await;
+        assert!(log.calls().is_empty());
+        assert!(client.inner.connections[0].conn.lock().await.is_some());
+
+        client.observe_failure(&outage).await;
+        assert_eq!(log.calls(), vec![2]);
+        assert!(ptr::eq(
+            client.connections(),
+            &client.inner.fallback_connections[..]
+        ));
+        assert!(client.inner.connections[0].conn.lock().await.is_none());
+
+        // failover is one-way, failures of the fallback pool don't trigger it again
+        client.observe_failure(&outage).await;
+        client.observe_failure(&outage).await;
+        assert_eq!(log.calls(), vec![2]);
+        assert!(ptr::eq(
+            client.connections(),
+            &client.inner.fallback_connections[..]
+        ));
+        Ok(())
+    }
+}
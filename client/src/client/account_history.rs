@@ -0,0 +1,422 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+use tonlib_core::types::Transaction;
+use tonlib_core::TonAddress;
+
+use crate::client::{decode_transaction, TonClientError, TonClientInterface};
+use crate::tl::{InternalTransactionId, NULL_TRANSACTION_ID};
+
+pub const DEFAULT_HISTORY_PAGE_SIZE: usize = 16;
+
+/// Persistent storage of sync cursors, keyed by account.
+///
+/// `save` must not return before the cursor is durably stored.
+pub trait CheckpointStore: Send + Sync {
+    fn load(&self, address: &TonAddress) -> Result<Option<InternalTransactionId>, TonClientError>;
+
+    fn save(
+        &self,
+        address: &TonAddress,
+        cursor: &InternalTransactionId,
+    ) -> Result<(), TonClientError>;
+}
+
+/// `CheckpointStore` keeping cursors of all accounts in a single JSON file
+pub struct FileCheckpointStore {
+    path: PathBuf,
+    lock: std::sync::Mutex<()>,
+}
+
+impl FileCheckpointStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileCheckpointStore {
+        FileCheckpointStore {
+            path: path.into(),
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    fn read(&self) -> Result<HashMap<String, InternalTransactionId>, TonClientError> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read(&self.path)?;
+        serde_json::from_slice(&content).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to parse checkpoint store: {}", e))
+        })
+    }
+}
+
+impl CheckpointStore for FileCheckpointStore {
+    fn load(&self, address: &TonAddress) -> Result<Option<InternalTransactionId>, TonClientError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.read()?.remove(&address.to_hex()))
+    }
+
+    fn save(
+        &self,
+        address: &TonAddress,
+        cursor: &InternalTransactionId,
+    ) -> Result<(), TonClientError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cursors = self.read()?;
+        cursors.insert(address.to_hex(), cursor.clone());
+        let content = serde_json::to_vec(&cursors).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to serialize checkpoint store: {}", e))
+        })?;
+        // write to temporary file first, so that the store is never left half-written
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Stored cursor is not in the transaction chain of the account anymore,
+/// e.g. because the account was deleted and deployed again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainBreak {
+    /// Cursor loaded from the store
+    pub cursor: InternalTransactionId,
+    /// Transaction the chain continues with instead of `cursor`,
+    /// `lt` is 0 if the chain starts after the cursor.
+    pub found: InternalTransactionId,
+}
+
+/// Result of `AccountHistorySyncer::sync_once`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncReport {
+    /// Transactions after the stored cursor, ordered from the oldest to the newest.
+    ///
+    /// Empty if `chain_break` is set.
+    pub new_transactions: Vec<Transaction>,
+    /// Cursor to be stored once the report is acknowledged
+    pub cursor: InternalTransactionId,
+    pub chain_break: Option<ChainBreak>,
+}
+
+/// Incrementally syncs the transaction history of a single account.
+///
+/// `sync_once` returns transactions after the stored cursor without storing anything,
+/// so until `acknowledge` is called the same transactions are returned again.
+pub struct AccountHistorySyncer<'a, C: TonClientInterface> {
+    client: C,
+    address: TonAddress,
+    store: &'a dyn CheckpointStore,
+    page_size: usize,
+}
+
+impl<'a, C: TonClientInterface> AccountHistorySyncer<'a, C> {
+    pub fn new(
+        client: C,
+        address: TonAddress,
+        store: &'a dyn CheckpointStore,
+    ) -> AccountHistorySyncer<'a, C> {
+        AccountHistorySyncer {
+            client,
+            address,
+            store,
+            page_size: DEFAULT_HISTORY_PAGE_SIZE,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn address(&self) -> &TonAddress {
+        &self.address
+    }
+
+    /// Walks transactions from the latest one back to the stored cursor.
+    ///
+    /// Transactions are linked by their `prev_trans_lt` & `prev_trans_hash`, so a chain
+    /// that doesn't lead to the cursor is reported as `ChainBreak` instead of being merged.
+    pub async fn sync_once(&self) -> Result<SyncReport, TonClientError> {
+        let cursor = self
+            .store
+            .load(&self.address)?
+            .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+        let last = self
+            .client
+            .get_raw_account_state(&self.address)
+            .await?
+            .last_transaction_id;
+
+        let mut transactions: BTreeMap<u64, Transaction> = BTreeMap::new();
+        let mut next = last.clone();
+        while next.lt > cursor.lt {
+            let requested = next.clone();
+            let page = self
+                .client
+                .get_raw_transactions_v2(&self.address, &next, self.page_size, false)
+                .await?;
+            if page.transactions.is_empty() {
+                return Err(TonClientError::InternalError(format!(
+                    "Transaction {} of {} is not found",
+                    next, self.address
+                )));
+            }
+            for raw in page.transactions.iter() {
+                // pages may overlap, skip what has already been processed
+                if raw.transaction_id.lt > next.lt {
+                    continue;
+                }
+                if raw.transaction_id != next {
+                    return Err(TonClientError::InternalError(format!(
+                        "Expected transaction {} of {}, got {}",
+                        next, self.address, raw.transaction_id
+                    )));
+                }
+                let tx = decode_transaction(raw)?;
+                next = InternalTransactionId {
+                    lt: tx.prev_trans_lt as i64,
+                    hash: tx.prev_trans_hash.to_vec(),
+                };
+                transactions.insert(tx.lt, tx);
+                if next.lt <= cursor.lt {
+                    break;
+                }
+            }
+            // a page of only newer transactions would be requested again forever
+            if next == requested {
+                return Err(TonClientError::InternalError(format!(
+                    "Transaction {} of {} is not found in the returned page",
+                    requested, self.address
+                )));
+            }
+        }
+
+        if next != cursor {
+            return Ok(SyncReport {
+                new_transactions: vec![],
+                cursor: cursor.clone(),
+                chain_break: Some(ChainBreak {
+                    cursor,
+                    found: next,
+                }),
+            });
+        }
+        Ok(SyncReport {
+            new_transactions: transactions.into_values().collect(),
+            cursor: last,
+            chain_break: None,
+        })
+    }
+
+    /// Stores the cursor of `report`, so that its transactions aren't returned again.
+    pub fn acknowledge(&self, report: &SyncReport) -> Result<(), TonClientError> {
+        self.store.save(&self.address, &report.cursor)
+    }
+
+    /// Stores `transaction` from the last report as the cursor, acknowledging only
+    /// the transactions up to it.
+    pub fn acknowledge_up_to(&self, transaction: &Transaction) -> Result<(), TonClientError> {
+        let cursor = InternalTransactionId {
+            lt: transaction.lt as i64,
+            hash: transaction.hash().to_vec(),
+        };
+        self.store.save(&self.address, &cursor)
+    }
+
+    /// Resets the cursor, so that the whole history is synced again, e.g. after `ChainBreak`.
+    pub fn reset(&self) -> Result<(), TonClientError> {
+        self.store.save(&self.address, &NULL_TRANSACTION_ID)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, MockClient, TransactionFixture};
+    use crate::tl::{RawFullAccountState, RawTransaction, RawTransactions, TonFunction, TonResult};
+
+    const ACCOUNT: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x11; 32],
+    };
+
+    /// Account history, newest transaction last; pages overlap by one transaction
+    #[derive(Default)]
+    struct MockHistory {
+        transactions: Mutex<Vec<RawTransaction>>,
+        requests: Mutex<usize>,
+    }
+
+    impl MockHistory {
+        fn push(&self, salt: u8) -> anyhow::Result<()> {
+            let mut transactions = self.transactions.lock().unwrap();
+            let prev = transactions
+                .last()
+                .map(|tx| tx.transaction_id.clone())
+                .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+            let lt = prev.lt as u64 + 10;
+            let fixture = TransactionFixture {
+                prev,
+                now: salt as u32,
+                ..TransactionFixture::new(&ACCOUNT, lt)
+            };
+            transactions.push(fixture.raw()?);
+            Ok(())
+        }
+
+        fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+            let transactions = self.transactions.lock().unwrap();
+            match function {
+                TonFunction::RawGetAccountState { .. } => {
+                    let last_transaction_id = transactions
+                        .last()
+                        .map(|tx| tx.transaction_id.clone())
+                        .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+                    Ok(TonResult::RawFullAccountState(RawFullAccountState {
+                        last_transaction_id,
+                        ..account_state(&mc_block(1))
+                    }))
+                }
+                TonFunction::RawGetTransactionsV2 {
+                    from_transaction_id,
+                    count,
+                    ..
+                } => {
+                    *self.requests.lock().unwrap() += 1;
+                    // starts one transaction later than requested to produce overlapping pages
+                    let page: Vec<_> = transactions
+                        .iter()
+                        .rev()
+                        .skip_while(|tx| tx.transaction_id.lt > from_transaction_id.lt + 10)
+                        .take(*count as usize)
+                        .cloned()
+                        .collect();
+                    let previous_transaction_id = page
+                        .last()
+                        .map(|tx| tx.transaction_id.clone())
+                        .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+                    Ok(TonResult::RawTransactions(RawTransactions {
+                        transactions: page,
+                        previous_transaction_id,
+                    }))
+                }
+                f => panic!("Unexpected function: {:?}", f),
+            }
+        }
+    }
+
+    fn client(history: &Arc<MockHistory>) -> MockClient {
+        let history = history.clone();
+        MockClient::new(move |function| history.invoke(function))
+    }
+
+    fn store(name: &str) -> FileCheckpointStore {
+        let path = std::env::temp_dir().join(format!(
+            "tonlib_checkpoints_{}_{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        FileCheckpointStore::new(path)
+    }
+
+    fn lts(report: &SyncReport) -> Vec<u64> {
+        report.new_transactions.iter().map(|tx| tx.lt).collect()
+    }
+
+    #[tokio::test]
+    async fn test_sync_progress() -> anyhow::Result<()> {
+        let history = Arc::new(MockHistory::default());
+        for salt in 0..5 {
+            history.push(salt)?;
+        }
+        let store = store("progress");
+        let syncer = AccountHistorySyncer::new(client(&history), ACCOUNT, &store).with_page_size(2);
+
+        let report = syncer.sync_once().await?;
+        assert_eq!(report.chain_break, None);
+        assert_eq!(lts(&report), vec![10, 20, 30, 40, 50]);
+        assert_eq!(report.cursor.lt, 50);
+        // not acknowledged, so the same transactions are returned again
+        assert_eq!(syncer.sync_once().await?, report);
+        syncer.acknowledge(&report)?;
+        assert_eq!(store.load(&ACCOUNT)?, Some(report.cursor.clone()));
+
+        history.push(5)?;
+        history.push(6)?;
+        let report = syncer.sync_once().await?;
+        assert_eq!(lts(&report), vec![60, 70]);
+        syncer.acknowledge(&report)?;
+
+        // empty poll
+        *history.requests.lock().unwrap() = 0;
+        let report = syncer.sync_once().await?;
+        assert!(report.new_transactions.is_empty());
+        assert_eq!(report.chain_break, None);
+        assert_eq!(report.cursor.lt, 70);
+        assert_eq!(*history.requests.lock().unwrap(), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_no_progress() -> anyhow::Result<()> {
+        let history = Arc::new(MockHistory::default());
+        for salt in 0..3 {
+            history.push(salt)?;
+        }
+        // the liteserver keeps returning the newest transaction whatever is requested
+        let stale = history.clone();
+        let client = MockClient::new(move |function| match function {
+            TonFunction::RawGetTransactionsV2 { .. } => {
+                *stale.requests.lock().unwrap() += 1;
+                let newest = stale.transactions.lock().unwrap().last().cloned().unwrap();
+                Ok(TonResult::RawTransactions(RawTransactions {
+                    previous_transaction_id: newest.transaction_id.clone(),
+                    transactions: vec![newest],
+                }))
+            }
+            f => stale.invoke(f),
+        });
+        let store = store("no_progress");
+        let syncer = AccountHistorySyncer::new(client, ACCOUNT, &store);
+        assert!(syncer.sync_once().await.is_err());
+        assert_eq!(*history.requests.lock().unwrap(), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sync_chain_break() -> anyhow::Result<()> {
+        let history = Arc::new(MockHistory::default());
+        for salt in 0..3 {
+            history.push(salt)?;
+        }
+        let store = store("chain_break");
+        let syncer = AccountHistorySyncer::new(client(&history), ACCOUNT, &store);
+        let report = syncer.sync_once().await?;
+        syncer.acknowledge(&report)?;
+        let cursor = report.cursor;
+
+        // account is redeployed with a history of the same length
+        history.transactions.lock().unwrap().clear();
+        for salt in 10..14 {
+            history.push(salt)?;
+        }
+        let report = syncer.sync_once().await?;
+        let chain_break = report.chain_break.clone().unwrap();
+        assert_eq!(chain_break.cursor, cursor);
+        assert_eq!(chain_break.found.lt, 30);
+        assert_ne!(chain_break.found, cursor);
+        assert!(report.new_transactions.is_empty());
+        syncer.acknowledge(&report)?;
+        assert_eq!(store.load(&ACCOUNT)?, Some(cursor));
+
+        syncer.reset()?;
+        let report = syncer.sync_once().await?;
+        assert_eq!(report.chain_break, None);
+        assert_eq!(lts(&report), vec![10, 20, 30, 40]);
+        Ok(())
+    }
+}
@@ -1,41 +1,51 @@
 use async_trait::async_trait;
 use futures::future::try_join_all;
-use futures::FutureExt;
+use futures::stream::{self, BoxStream};
+use futures::{FutureExt, StreamExt, TryFutureExt, TryStreamExt};
 use tonlib_core::TonAddress;
 
-use crate::client::{TonClientError, TonClientInterface, TxId};
-use crate::tl::{
-    BlockIdExt, BlocksAccountTransactionId, BlocksTransactions, RawTransaction,
-    NULL_BLOCKS_ACCOUNT_TRANSACTION_ID,
+use crate::client::{
+    BlockTransactionIdsSource, BlockTransactionsSource, Pager, TonClientError, TonClientInterface,
+    TxId,
 };
+use crate::tl::{BlockId, BlockIdExt, BlocksShortTxId, RawTransaction};
+
+const LOOKUP_BY_SEQNO_MODE: i32 = 1;
+const LOOKUP_BY_LT_MODE: i32 = 2;
 
 /// High-level functions for working with blocks & shards
 #[async_trait]
 pub trait TonBlockFunctions: TonClientInterface + Send + Sync {
+    /// Returns IDs of transactions of block `block_id`, `BLOCK_TRANSACTIONS_PAGE_SIZE` per request
+    fn block_transaction_ids_pager(&self, block_id: &BlockIdExt) -> Pager<'_, BlocksShortTxId> {
+        Pager::new(BlockTransactionIdsSource::new(self, block_id))
+    }
+
     /// Returns the list of all transaction IDs in specified shard.
     async fn get_shard_tx_ids(&self, shard_id: &BlockIdExt) -> Result<Vec<TxId>, TonClientError> {
-        let mut after: BlocksAccountTransactionId = NULL_BLOCKS_ACCOUNT_TRANSACTION_ID.clone();
-        let mut transactions: Vec<TxId> = Vec::new();
-        loop {
-            let mode = if after.lt == 0 { 7 } else { 128 + 7 };
-            let txs: BlocksTransactions = self
-                .get_block_transactions(shard_id, mode, 256, &after)
-                .await?;
-            if let Some(last) = txs.transactions.last() {
-                after = BlocksAccountTransactionId {
-                    account: last.account.clone(),
-                    lt: last.lt,
-                };
-            }
-            transactions.reserve(txs.transactions.len());
-            for tx in txs.transactions {
-                transactions.push(TxId::new(shard_id.workchain, &tx)?)
-            }
-            if !txs.incomplete {
-                break;
-            }
+        let tx_ids = self
+            .block_transaction_ids_pager(shard_id)
+            .collect_all(None)
+            .await?;
+        tx_ids
+            .iter()
+            .map(|tx| TxId::new(shard_id.workchain, tx))
+            .collect()
+    }
+
+    /// Returns the number of transactions in specified shard block.
+    ///
+    /// Only transaction IDs are fetched, which is faster than fetching the transactions.
+    async fn count_transactions_in_block(
+        &self,
+        block_id: &BlockIdExt,
+    ) -> Result<u32, TonClientError> {
+        let mut pager = self.block_transaction_ids_pager(block_id);
+        let mut count = 0;
+        while let Some(page) = pager.next_page().await? {
+            count += page.len() as u32;
         }
-        Ok(transactions)
+        Ok(count)
     }
 
     async fn get_shards_tx_ids(
@@ -50,35 +60,19 @@ pub trait TonBlockFunctions: TonClientInterface + Send + Sync {
         Ok(txs)
     }
 
+    /// Returns transactions of block `block_id`, `BLOCK_TRANSACTIONS_PAGE_SIZE` per request
+    fn block_transactions_pager(&self, block_id: &BlockIdExt) -> Pager<'_, RawTransaction> {
+        Pager::new(BlockTransactionsSource::new(self, block_id))
+    }
+
     /// Returns all transactions from specified shard
     async fn get_shard_transactions(
         &self,
         shard_id: &BlockIdExt,
     ) -> Result<Vec<RawTransaction>, TonClientError> {
-        let mut after: BlocksAccountTransactionId = NULL_BLOCKS_ACCOUNT_TRANSACTION_ID.clone();
-        let mut raw_txs: Vec<RawTransaction> = Vec::new();
-        loop {
-            let mode = if after.lt == 0 { 7 } else { 128 + 7 };
-            let txs = self
-                .get_block_transactions_ext(shard_id, mode, 256, &after)
-                .await?;
-            if let Some(last) = txs.transactions.last() {
-                let account = last
-                    .address
-                    .account_address
-                    .parse::<TonAddress>()?
-                    .hash_part
-                    .to_vec();
-                let lt = last.transaction_id.lt;
-                after = BlocksAccountTransactionId { account, lt };
-            }
-            raw_txs.reserve(txs.transactions.len());
-            raw_txs.extend(txs.transactions);
-            if !txs.incomplete {
-                break;
-            }
-        }
-        Ok(raw_txs)
+        self.block_transactions_pager(shard_id)
+            .collect_all(None)
+            .await
     }
     /// Returns all transactions from specified shards
     async fn get_shards_transactions(
@@ -92,6 +86,473 @@ pub trait TonBlockFunctions: TonClientInterface + Send + Sync {
         let txs: Vec<_> = try_join_all(f).await?;
         Ok(txs)
     }
+
+    /// Returns all transactions of masterchain block `mc_block_id` and of the shard blocks
+    /// finalized in it, sorted by lt.
+    ///
+    /// Transactions of the blocks are fetched concurrently. Since sorting requires all of them,
+    /// the first transaction is yielded once every block is fetched.
+    fn get_all_transactions_in_block<'a>(
+        &'a self,
+        mc_block_id: &'a BlockIdExt,
+    ) -> BoxStream<'a, Result<RawTransaction, TonClientError>> {
+        let transactions = async move {
+            let mut blocks = vec![mc_block_id.clone()];
+            blocks.extend(self.get_shards_at_mc_block(mc_block_id).await?);
+            let mut transactions: Vec<RawTransaction> = self
+                .get_shards_transactions(&blocks)
+                .await?
+                .into_iter()
+                .flat_map(|(_, txs)| txs)
+                .collect();
+            transactions.sort_by_key(|tx| tx.transaction_id.lt);
+            Ok::<_, TonClientError>(transactions)
+        };
+        stream::once(transactions.map_ok(|txs| stream::iter(txs.into_iter().map(Ok))))
+            .try_flatten()
+            .boxed()
+    }
+
+    /// Returns the block containing the last transaction of `address`,
+    /// or `None` if the account has no transactions.
+    async fn get_last_transaction_block(
+        &self,
+        address: &TonAddress,
+    ) -> Result<Option<BlockIdExt>, TonClientError> {
+        let state = self.get_raw_account_state(address).await?;
+        let lt = state.last_transaction_id.lt;
+        if lt == 0 {
+            return Ok(None);
+        }
+        // lookup by lt treats the shard as account id prefix and resolves the containing shard
+        let block = self
+            .get_shard_block_by_lt(address.workchain, account_prefix(address), lt as u64)
+            .await?;
+        Ok(Some(block))
+    }
+
+    /// Returns the block of shard `shard` in `workchain` containing logical time `lt`,
+    /// e.g. the block of a transaction with this lt.
+    ///
+    /// `shard` may be a shard id or any account id prefix within it,
+    /// the shard is resolved as of the time of `lt`.
+    async fn get_shard_block_by_lt(
+        &self,
+        workchain: i32,
+        shard: i64,
+        lt: u64,
+    ) -> Result<BlockIdExt, TonClientError> {
+        let lt = i64::try_from(lt)
+            .map_err(|_| TonClientError::InternalError(format!("Invalid lt: {}", lt)))?;
+        let block_id = BlockId {
+            workchain,
+            shard,
+            seqno: 0,
+        };
+        self.lookup_block(LOOKUP_BY_LT_MODE, &block_id, lt, 0).await
+    }
+
+    /// Returns up to `count` first masterchain key blocks with seqno not less than `from_seqno`,
+    /// oldest first.
+    ///
+    /// Headers only link to the previous key block, so each key block is found by a binary
+    /// search over masterchain blocks up to the latest one, checking `prev_key_block_seqno`
+    /// of their headers. It takes about `2 * log2(latest seqno)` requests per returned key block.
+    async fn get_key_blocks(
+        &self,
+        from_seqno: u32,
+        count: u32,
+    ) -> Result<Vec<BlockIdExt>, TonClientError> {
+        let last = self.masterchain_info_cached().await?.info.last.seqno as i64;
+        let mut key_blocks = vec![];
+        let mut from = from_seqno as i64;
+        while key_blocks.len() < count as usize && from <= last {
+            // the first key block in [from, last], narrowed down by the key blocks preceding
+            // the blocks in between
+            let (mut low, mut high) = (from, last);
+            let mut first = None;
+            while low <= high {
+                let mid = low + (high - low) / 2;
+                let block_id = BlockId {
+                    workchain: -1,
+                    shard: i64::MIN,
+                    seqno: mid as i32,
+                };
+                let block = self
+                    .lookup_block(LOOKUP_BY_SEQNO_MODE, &block_id, 0, 0)
+                    .await?;
+                let header = self.get_block_header(&block).await?;
+                if mid > 0 && header.prev_key_block_seqno as i64 >= from {
+                    high = header.prev_key_block_seqno as i64 - 1;
+                    first = Some(header.prev_key_block_seqno as i64);
+                } else if header.is_key_block {
+                    first = Some(mid);
+                    break;
+                } else {
+                    low = mid + 1;
+                }
+            }
+            let Some(seqno) = first else {
+                break;
+            };
+            let block_id = BlockId {
+                workchain: -1,
+                shard: i64::MIN,
+                seqno: seqno as i32,
+            };
+            key_blocks.push(
+                self.lookup_block(LOOKUP_BY_SEQNO_MODE, &block_id, 0, 0)
+                    .await?,
+            );
+            from = seqno + 1;
+        }
+        Ok(key_blocks)
+    }
+
+    /// Returns blocks preceding `block_id`: two blocks if it follows a shard merge, one otherwise.
+    async fn get_prev_blocks(
+        &self,
+        block_id: &BlockIdExt,
+    ) -> Result<Vec<BlockIdExt>, TonClientError> {
+        let header = self.get_block_header(block_id).await?;
+        header.prev_blocks.ok_or_else(|| {
+            TonClientError::InternalError(format!(
+                "Header of block {} has no prev blocks",
+                block_id.seqno
+            ))
+        })
+    }
+
+    /// Returns blocks following `block_id`: two blocks if the shard splits after it, one otherwise.
+    ///
+    /// The blocks must be known to the liteserver, so old blocks require an archive liteserver.
+    async fn get_next_blocks(
+        &self,
+        block_id: &BlockIdExt,
+    ) -> Result<Vec<BlockIdExt>, TonClientError> {
+        let header = self.get_block_header(block_id).await?;
+        let next = if header.before_split {
+            let (left, right) = child_shards(block_id.shard);
+            let mut blocks = Vec::with_capacity(2);
+            for shard in [left, right] {
+                let child = BlockId {
+                    workchain: block_id.workchain,
+                    shard,
+                    seqno: block_id.seqno + 1,
+                };
+                blocks.push(
+                    self.lookup_block(LOOKUP_BY_SEQNO_MODE, &child, 0, 0)
+                        .await?,
+                );
+            }
+            blocks
+        } else {
+            // the first block ending after this one: in the same shard, or in the parent one after a merge
+            let block = self
+                .get_shard_block_by_lt(block_id.workchain, block_id.shard, header.end_lt as u64)
+                .await?;
+            vec![block]
+        };
+        for block in &next {
+            if !self.get_prev_blocks(block).await?.contains(block_id) {
+                return Err(TonClientError::InternalError(format!(
+                    "Block {} doesn't follow block {}",
+                    block.seqno, block_id.seqno
+                )));
+            }
+        }
+        Ok(next)
+    }
 }
 
 impl<T> TonBlockFunctions for T where T: TonClientInterface + Send + Sync {}
+
+/// Shards resulting from the split of `shard`
+fn child_shards(shard: i64) -> (i64, i64) {
+    let shard = shard as u64;
+    let step = (shard & shard.wrapping_neg()) >> 1;
+    (
+        shard.wrapping_sub(step) as i64,
+        shard.wrapping_add(step) as i64,
+    )
+}
+
+/// Returns `true` if `child` is `parent` or one of the shards resulting from its splits
+pub(crate) fn shard_is_ancestor(parent: i64, child: i64) -> bool {
+    let (parent, child) = (parent as u64, child as u64);
+    let parent_tag = parent & parent.wrapping_neg();
+    let child_tag = child & child.wrapping_neg();
+    parent_tag >= child_tag && (parent ^ child) & (parent_tag.wrapping_neg() << 1) == 0
+}
+
+/// First 64 bits of account id, with the shard tag bit set
+fn account_prefix(address: &TonAddress) -> i64 {
+    let mut prefix = [0u8; 8];
+    prefix.copy_from_slice(&address.hash_part[..8]);
+    (u64::from_be_bytes(prefix) | 1) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlib_core::constants::MASTERCHAIN_ID;
+
+    use super::*;
+    use crate::client::mock::{
+        block_header, block_id, mc_block, mc_info, raw_transaction, MockClient,
+    };
+    use crate::tl::{
+        BlocksHeader, BlocksShards, BlocksTransactions, BlocksTransactionsExt, TonFunction,
+        TonResult,
+    };
+
+    const SHARDS: [u64; 2] = [0x4000000000000000, 0xc000000000000000];
+
+    fn block(workchain: i32, shard: u64) -> BlockIdExt {
+        block_id(workchain, shard, 1)
+    }
+
+    fn transaction(workchain: i32, lt: i64) -> RawTransaction {
+        raw_transaction(&TonAddress::new(workchain, &[lt as u8; 32]), lt)
+    }
+
+    /// Client serving a masterchain block with two basechain shards, each block with
+    /// transactions interleaving by lt, and 600 transaction IDs of any block
+    fn block_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::BlocksGetShards { .. } => Ok(TonResult::BlocksShards(BlocksShards {
+                shards: SHARDS.iter().map(|s| block(0, *s)).collect(),
+            })),
+            TonFunction::BlocksGetTransactions {
+                id, count, after, ..
+            } => {
+                // 600 transactions of a single account, served in pages of `count`
+                let start = after.lt + 1;
+                let end = (start + *count as i64).min(601);
+                Ok(TonResult::BlocksTransactions(BlocksTransactions {
+                    id: id.clone(),
+                    req_count: *count as i32,
+                    incomplete: end <= 600,
+                    transactions: (start..end)
+                        .map(|lt| BlocksShortTxId {
+                            mode: 7,
+                            account: vec![1; 32],
+                            lt,
+                            hash: vec![0; 32],
+                        })
+                        .collect(),
+                }))
+            }
+            TonFunction::BlocksGetTransactionsExt { id, .. } => {
+                let lts: &[i64] = match id.shard as u64 {
+                    _ if id.workchain == MASTERCHAIN_ID => &[2, 7],
+                    0x4000000000000000 => &[3, 5, 9],
+                    _ => &[1, 8],
+                };
+                Ok(TonResult::BlocksTransactionsExt(BlocksTransactionsExt {
+                    id: id.clone(),
+                    req_count: 256,
+                    incomplete: false,
+                    transactions: lts
+                        .iter()
+                        .map(|lt| transaction(id.workchain, *lt))
+                        .collect(),
+                }))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_all_transactions_in_block() -> anyhow::Result<()> {
+        let mc_block = block(MASTERCHAIN_ID, 0x8000000000000000);
+        let transactions: Vec<RawTransaction> = block_client()
+            .get_all_transactions_in_block(&mc_block)
+            .try_collect()
+            .await?;
+        let lts: Vec<i64> = transactions.iter().map(|tx| tx.transaction_id.lt).collect();
+        assert_eq!(lts, vec![1, 2, 3, 5, 7, 8, 9]);
+        Ok(())
+    }
+
+    /// Client with masterchain head 100 and key blocks 0, 10, 40, 70 and 100
+    fn key_blocks_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::BlocksGetMasterchainInfo {} => {
+                Ok(TonResult::BlocksMasterchainInfo(mc_info(100)))
+            }
+            TonFunction::BlocksLookupBlock { id, .. } => {
+                Ok(TonResult::BlockIdExt(mc_block(id.seqno)))
+            }
+            TonFunction::GetBlockHeader { id } => {
+                let header = BlocksHeader {
+                    is_key_block: id.seqno % 30 == 10 || id.seqno == 0,
+                    prev_key_block_seqno: ((id.seqno - 1) / 30 * 30 - 20).max(0),
+                    ..block_header(id, vec![mc_block(id.seqno - 1)])
+                };
+                Ok(TonResult::BlocksHeader(header))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_key_blocks() -> anyhow::Result<()> {
+        let seqnos =
+            |blocks: Vec<BlockIdExt>| -> Vec<i32> { blocks.iter().map(|b| b.seqno).collect() };
+
+        let client = key_blocks_client();
+        assert_eq!(
+            seqnos(client.get_key_blocks(0, 10).await?),
+            vec![0, 10, 40, 70, 100]
+        );
+        assert_eq!(
+            seqnos(client.get_key_blocks(20, 10).await?),
+            vec![40, 70, 100]
+        );
+        assert!(client.get_key_blocks(101, 10).await?.is_empty());
+
+        // the key blocks right after `from_seqno` are returned, not the latest ones
+        assert_eq!(seqnos(client.get_key_blocks(0, 2).await?), vec![0, 10]);
+        assert_eq!(seqnos(client.get_key_blocks(11, 1).await?), vec![40]);
+        assert_eq!(seqnos(client.get_key_blocks(40, 2).await?), vec![40, 70]);
+        assert_eq!(seqnos(client.get_key_blocks(100, 10).await?), vec![100]);
+        assert!(client.get_key_blocks(0, 0).await?.is_empty());
+
+        // each key block is found in a logarithmic number of requests
+        let client = key_blocks_client();
+        client.get_key_blocks(41, 1).await?;
+        assert!(client.calls() <= 1 + 2 * 8);
+        Ok(())
+    }
+
+    const PARENT: u64 = 0x4000000000000000;
+    const LEFT: u64 = 0x2000000000000000;
+    const RIGHT: u64 = 0x6000000000000000;
+
+    /// Client serving blocks 1 to 10 of shard `PARENT`, which splits into `LEFT` and `RIGHT`
+    /// producing blocks 11 to 20, which merge back into block 21 of `PARENT`.
+    /// Block `seqno` spans lts from `seqno * 100` to `seqno * 100 + 100`.
+    fn split_merge_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::BlocksLookupBlock { mode, id, lt, .. } => {
+                let block = match *mode {
+                    LOOKUP_BY_SEQNO_MODE => block_id(id.workchain, id.shard as u64, id.seqno),
+                    _ => {
+                        let seqno = (*lt / 100) as i32;
+                        let shard = match seqno {
+                            11..=20 if (id.shard as u64) < PARENT => LEFT,
+                            11..=20 => RIGHT,
+                            _ => PARENT,
+                        };
+                        block_id(id.workchain, shard, seqno)
+                    }
+                };
+                Ok(TonResult::BlockIdExt(block))
+            }
+            TonFunction::GetBlockHeader { id } => {
+                let prev = match (id.shard as u64, id.seqno) {
+                    (PARENT, 21) => vec![block_id(0, LEFT, 20), block_id(0, RIGHT, 20)],
+                    (_, 11) => vec![block_id(0, PARENT, 10)],
+                    (shard, seqno) => vec![block_id(0, shard, seqno - 1)],
+                };
+                let header = BlocksHeader {
+                    before_split: id.shard as u64 == PARENT && id.seqno == 10,
+                    start_lt: id.seqno as i64 * 100,
+                    end_lt: id.seqno as i64 * 100 + 100,
+                    ..block_header(id, prev)
+                };
+                Ok(TonResult::BlocksHeader(header))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[test]
+    fn test_child_shards() {
+        let (left, right) = child_shards(0x8000000000000000u64 as i64);
+        assert_eq!(
+            (left as u64, right as u64),
+            (0x4000000000000000, 0xc000000000000000)
+        );
+        let (left, right) = child_shards(PARENT as i64);
+        assert_eq!((left as u64, right as u64), (LEFT, RIGHT));
+    }
+
+    #[test]
+    fn test_shard_is_ancestor() {
+        let root = 0x8000000000000000u64 as i64;
+        for shard in [root, PARENT as i64, LEFT as i64, RIGHT as i64] {
+            assert!(shard_is_ancestor(root, shard));
+            assert!(shard_is_ancestor(shard, shard));
+        }
+        assert!(shard_is_ancestor(PARENT as i64, LEFT as i64));
+        assert!(shard_is_ancestor(PARENT as i64, RIGHT as i64));
+        assert!(!shard_is_ancestor(LEFT as i64, PARENT as i64));
+        assert!(!shard_is_ancestor(LEFT as i64, RIGHT as i64));
+        assert!(!shard_is_ancestor(
+            PARENT as i64,
+            0xc000000000000000u64 as i64
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_prev_blocks() -> anyhow::Result<()> {
+        let client = split_merge_client();
+        let prev = client.get_prev_blocks(&block_id(0, PARENT, 5)).await?;
+        assert_eq!(prev, vec![block_id(0, PARENT, 4)]);
+        // after split
+        let prev = client.get_prev_blocks(&block_id(0, RIGHT, 11)).await?;
+        assert_eq!(prev, vec![block_id(0, PARENT, 10)]);
+        // after merge
+        let merged = block_id(0, PARENT, 21);
+        assert!(client.get_block_header(&merged).await?.after_merge);
+        let prev = client.get_prev_blocks(&merged).await?;
+        assert_eq!(prev, vec![block_id(0, LEFT, 20), block_id(0, RIGHT, 20)]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_next_blocks() -> anyhow::Result<()> {
+        let client = split_merge_client();
+        // found by end_lt in the same shard
+        let next = client.get_next_blocks(&block_id(0, PARENT, 5)).await?;
+        assert_eq!(next, vec![block_id(0, PARENT, 6)]);
+        let next = client.get_next_blocks(&block_id(0, LEFT, 15)).await?;
+        assert_eq!(next, vec![block_id(0, LEFT, 16)]);
+        // split
+        let next = client.get_next_blocks(&block_id(0, PARENT, 10)).await?;
+        assert_eq!(next, vec![block_id(0, LEFT, 11), block_id(0, RIGHT, 11)]);
+        // merge, found by end_lt in the parent shard
+        for shard in [LEFT, RIGHT] {
+            let next = client.get_next_blocks(&block_id(0, shard, 20)).await?;
+            assert_eq!(next, vec![block_id(0, PARENT, 21)]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_next_blocks_checks_links() {
+        // a liteserver returning a block of another shard
+        let client = MockClient::new(|function| match function {
+            TonFunction::BlocksLookupBlock { .. } => {
+                Ok(TonResult::BlockIdExt(block_id(0, RIGHT, 6)))
+            }
+            TonFunction::GetBlockHeader { id } => Ok(TonResult::BlocksHeader(block_header(
+                id,
+                vec![block_id(0, id.shard as u64, id.seqno - 1)],
+            ))),
+            _ => unimplemented!("{:?}", function),
+        });
+        let result = client.get_next_blocks(&block_id(0, LEFT, 5)).await;
+        assert!(matches!(result, Err(TonClientError::InternalError(_))));
+    }
+
+    #[tokio::test]
+    async fn test_count_transactions_in_block() -> anyhow::Result<()> {
+        let count = block_client()
+            .count_transactions_in_block(&block(0, SHARDS[0]))
+            .await?;
+        assert_eq!(count, 600);
+        Ok(())
+    }
+}
@@ -1,16 +1,59 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
 use std::time::Duration;
 
 use futures::future::try_join_all;
-use tokio::time;
+use serde::{Deserialize, Serialize};
 
-use crate::client::{TonClientError, TonClientInterface, TonConnection};
-use crate::tl::{BlockId, BlockIdExt, BlocksHeader, BlocksShards};
+use crate::client::{
+    HeadWatcher, InvokeOptions, RetryStrategy, TonClientError, TonClientInterface,
+};
+use crate::rt;
+use crate::tl::{
+    BlockId, BlockIdExt, BlocksHeader, BlocksShards, TonFunction, TonResult, TonResultDiscriminants,
+};
+
+/// Headers of freshly finalized shard blocks may be missing on some liteservers for a while,
+/// so they are requested more patiently than with the default strategy.
+const HEADER_RETRY_STRATEGY: RetryStrategy = RetryStrategy {
+    interval_ms: 100,
+    max_retries: 20,
+};
 
 #[derive(Debug, Clone)]
 pub struct BlockStreamItem {
     pub master_shard: BlockIdExt,
     pub shards: Vec<BlockIdExt>,
+    /// `true` if the block replaces one that was emitted before a reorg.
+    pub reemitted: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum BlockStreamEvent {
+    Block(BlockStreamItem),
+    /// Previously emitted masterchain blocks starting from `from_seqno` were replaced.
+    ///
+    /// The corrected chain is emitted starting from `from_seqno` with `reemitted` flag set.
+    Reorg {
+        from_seqno: i32,
+    },
+}
+
+/// Position of the stream, suitable for persisting and resuming the stream after restart.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BlockStreamCheckpoint {
+    /// Seqno of the last emitted masterchain block
+    pub seqno: i32,
+    /// Root hash of the last emitted masterchain block
+    pub root_hash: Vec<u8>,
+}
+
+impl From<&BlockIdExt> for BlockStreamCheckpoint {
+    fn from(value: &BlockIdExt) -> Self {
+        BlockStreamCheckpoint {
+            seqno: value.seqno,
+            root_hash: value.root_hash.clone(),
+        }
+    }
 }
 
 /// Allows to sequentially retrieve all shards in all workchains.
@@ -18,10 +61,15 @@ pub struct BlockStreamItem {
 /// The result of `next` call is the height of next masterchain block together with
 /// all shards in all workchains that were finalized in corresponding masterchain block.
 ///
+/// Optionally, the stream verifies that each masterchain block links to the previously
+/// emitted one (see `with_reorg_verification`).
 pub struct BlockStream<C: TonClientInterface + Clone> {
     client: C,
     next_seqno: i32,
     prev_block_set: HashSet<BlockId>,
+    verify_chain: bool,
+    chain: ChainHistory,
+    head_watcher: Option<HeadWatcher>,
 }
 
 impl<C: TonClientInterface + Clone> BlockStream<C> {
@@ -30,13 +78,54 @@ impl<C: TonClientInterface + Clone> BlockStream<C> {
             client: client.clone(),
             next_seqno: from_seqno,
             prev_block_set: Default::default(),
+            verify_chain: false,
+            chain: ChainHistory::new(1),
+            head_watcher: None,
         }
     }
 
+    /// Creates the stream continuing right after the block recorded in `checkpoint`.
+    pub fn from_checkpoint(client: &C, checkpoint: &BlockStreamCheckpoint) -> BlockStream<C> {
+        let mut stream = Self::new(client, checkpoint.seqno + 1);
+        stream.chain.history.push_back(checkpoint.clone());
+        stream
+    }
+
+    /// Enables verification of `prev_blocks` links of masterchain blocks.
+    ///
+    /// * `max_depth`: Maximum number of emitted blocks that can be replaced by reorg.
+    pub fn with_reorg_verification(&mut self, max_depth: usize) -> &mut Self {
+        self.verify_chain = true;
+        self.chain.max_depth = max_depth.max(1);
+        self
+    }
+
+    /// Waits for the next masterchain block with `watcher` instead of polling masterchain info,
+    /// so that several streams share a single outstanding request.
+    pub fn with_head_watcher(&mut self, watcher: &HeadWatcher) -> &mut Self {
+        self.head_watcher = Some(watcher.clone());
+        self
+    }
+
+    /// Returns the checkpoint of the last emitted masterchain block
+    pub fn checkpoint(&self) -> Option<BlockStreamCheckpoint> {
+        self.chain.last().cloned()
+    }
+
     /// Retrieves the next masterchain block together with all shards finalized in this block
     ///
     /// If the next block is not yet available, the returned future resolves when it's added to masterchain.
+    /// Reorgs are not reported, use `next_event` to observe them.
     pub async fn next(&mut self) -> Result<BlockStreamItem, TonClientError> {
+        loop {
+            if let BlockStreamEvent::Block(item) = self.next_event().await? {
+                return Ok(item);
+            }
+        }
+    }
+
+    /// Same as `next`, but also reports detected reorgs.
+    pub async fn next_event(&mut self) -> Result<BlockStreamEvent, TonClientError> {
         if self.prev_block_set.is_empty() {
             let (prev_block_shards, _) =
                 get_master_block_shards(&self.client, self.next_seqno - 1).await?;
@@ -44,16 +133,24 @@ impl<C: TonClientInterface + Clone> BlockStream<C> {
                 self.prev_block_set.insert(shard.to_block_id());
             }
         };
-        let connection = loop {
-            let (conn, masterchain_info) = self.client.get_masterchain_info().await?;
-            if masterchain_info.last.seqno < self.next_seqno {
-                time::sleep(Duration::from_millis(100)).await;
-            } else {
-                break conn;
-            }
-        };
+        if let Some(watcher) = &self.head_watcher {
+            watcher.wait_seqno(self.next_seqno).await?;
+        }
+        while self.client.masterchain_info_cached().await?.info.last.seqno < self.next_seqno {
+            rt::sleep(Duration::from_millis(100)).await;
+        }
         let (block_shards, master_block) =
-            get_master_block_shards(&connection, self.next_seqno).await?;
+            get_master_block_shards(&self.client, self.next_seqno).await?;
+        if self.verify_chain && self.chain.last().is_some() {
+            let header = self.retrying_get_block_header(&master_block).await?;
+            if !self
+                .chain
+                .links_to_last(header.prev_blocks.as_deref().unwrap_or_default())
+            {
+                let from_seqno = self.rollback().await?;
+                return Ok(BlockStreamEvent::Reorg { from_seqno });
+            }
+        }
         let mut result_shards: HashSet<BlockIdExt> = Default::default();
         let mut unprocessed_shards: Vec<BlockIdExt> = Default::default();
         unprocessed_shards.extend(block_shards.shards.clone());
@@ -70,9 +167,7 @@ impl<C: TonClientInterface + Clone> BlockStream<C> {
                 shards_to_process.insert(s);
             }
             unprocessed_shards = Default::default();
-            let headers = self
-                .get_block_headers(&connection, &shards_to_process)
-                .await?;
+            let headers = self.get_block_headers(&shards_to_process).await?;
             for h in headers {
                 if let Some(prev_blocks) = h.prev_blocks {
                     unprocessed_shards.extend(prev_blocks)
@@ -86,20 +181,40 @@ impl<C: TonClientInterface + Clone> BlockStream<C> {
             .into_iter()
             .map(|shard| shard.to_block_id())
             .collect();
-        Ok(BlockStreamItem {
+        let reemitted = self.chain.record(&master_block);
+        Ok(BlockStreamEvent::Block(BlockStreamItem {
             shards: result_shards.into_iter().collect(),
             master_shard: master_block,
-        })
+            reemitted,
+        }))
+    }
+
+    /// Finds the point of divergence with the chain served by `conn` and rewinds the stream to it.
+    async fn rollback(&mut self) -> Result<i32, TonClientError> {
+        let futures: Vec<_> = self
+            .chain
+            .history
+            .iter()
+            .map(|c| lookup_master_block(&self.client, c.seqno))
+            .collect();
+        let canonical = try_join_all(futures).await?;
+        let from_seqno = self.chain.rollback(&canonical)?;
+        log::warn!(
+            "Masterchain reorg detected, re-emitting from seqno {}",
+            from_seqno
+        );
+        self.next_seqno = from_seqno;
+        self.prev_block_set.clear();
+        Ok(from_seqno)
     }
 
     async fn get_block_headers(
         &self,
-        conn: &TonConnection,
         shards: &HashSet<BlockIdExt>,
     ) -> Result<Vec<BlocksHeader>, TonClientError> {
         let futures: Vec<_> = shards
             .iter()
-            .map(|id| self.retrying_get_block_header(conn, id))
+            .map(|id| self.retrying_get_block_header(id))
             .collect();
         let r = try_join_all(futures).await?;
         Ok(r)
@@ -107,30 +222,230 @@ impl<C: TonClientInterface + Clone> BlockStream<C> {
 
     async fn retrying_get_block_header(
         &self,
-        conn: &TonConnection,
         block_id: &BlockIdExt,
     ) -> Result<BlocksHeader, TonClientError> {
-        let r = conn.get_block_header(block_id).await;
-        // Fallback to random connection on client
-        match r {
-            Ok(bh) => Ok(bh),
-            Err(_) => self.client.get_block_header(block_id).await,
+        let func = TonFunction::GetBlockHeader {
+            id: block_id.clone(),
+        };
+        let options = InvokeOptions::with_retry(&HEADER_RETRY_STRATEGY);
+        match self.client.invoke_with_options(&func, &options).await? {
+            TonResult::BlocksHeader(header) => Ok(header),
+            r => Err(TonClientError::unexpected_ton_result(
+                TonResultDiscriminants::BlocksHeader,
+                r,
+            )),
         }
     }
 }
 
-async fn get_master_block_shards<C: TonClientInterface>(
+async fn lookup_master_block<C: TonClientInterface>(
     conn: &C,
     seqno: i32,
-) -> Result<(BlocksShards, BlockIdExt), TonClientError> {
+) -> Result<BlockIdExt, TonClientError> {
     let master_block = BlockId {
         workchain: -1,
         shard: i64::MIN,
         seqno,
     };
-    let master_block_ext = conn.lookup_block(1, &master_block, 0, 0).await?;
+    conn.lookup_block(1, &master_block, 0, 0).await
+}
+
+async fn get_master_block_shards<C: TonClientInterface>(
+    conn: &C,
+    seqno: i32,
+) -> Result<(BlocksShards, BlockIdExt), TonClientError> {
+    let master_block_ext = lookup_master_block(conn, seqno).await?;
     Ok((
         conn.get_block_shards(&master_block_ext).await?,
         master_block_ext,
     ))
 }
+
+/// Recently emitted masterchain blocks, oldest first.
+struct ChainHistory {
+    max_depth: usize,
+    history: VecDeque<BlockStreamCheckpoint>,
+    reemit_until: Option<i32>,
+}
+
+impl ChainHistory {
+    fn new(max_depth: usize) -> ChainHistory {
+        ChainHistory {
+            max_depth,
+            history: Default::default(),
+            reemit_until: None,
+        }
+    }
+
+    fn last(&self) -> Option<&BlockStreamCheckpoint> {
+        self.history.back()
+    }
+
+    /// Checks that `prev_blocks` of the next block contain the last emitted block.
+    fn links_to_last(&self, prev_blocks: &[BlockIdExt]) -> bool {
+        match self.last() {
+            None => true,
+            Some(last) => prev_blocks
+                .iter()
+                .any(|p| p.seqno == last.seqno && p.root_hash == last.root_hash),
+        }
+    }
+
+    /// Records emitted block, returns `true` if the block replaces one emitted before reorg.
+    fn record(&mut self, block: &BlockIdExt) -> bool {
+        self.history.push_back(block.into());
+        while self.history.len() > self.max_depth {
+            self.history.pop_front();
+        }
+        match self.reemit_until {
+            Some(until) if block.seqno <= until => true,
+            Some(_) => {
+                self.reemit_until = None;
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Drops the blocks not matching `canonical` ones (given in the same order as history).
+    ///
+    /// Returns the seqno of the first replaced block.
+    fn rollback(&mut self, canonical: &[BlockIdExt]) -> Result<i32, TonClientError> {
+        let divergence = self
+            .history
+            .iter()
+            .zip(canonical)
+            .position(|(emitted, actual)| emitted.root_hash != actual.root_hash);
+        match divergence {
+            None => Err(TonClientError::InternalError(
+                "Masterchain block doesn't link to the previous one, while previous blocks are unchanged"
+                    .to_string(),
+            )),
+            Some(0) if self.history.len() >= self.max_depth => {
+                Err(TonClientError::InternalError(format!(
+                    "Masterchain reorg is deeper than {} blocks",
+                    self.max_depth
+                )))
+            }
+            Some(index) => {
+                let from_seqno = self.history[index].seqno;
+                if let Some(last) = self.last() {
+                    let until = self.reemit_until.unwrap_or(last.seqno).max(last.seqno);
+                    self.reemit_until = Some(until);
+                }
+                self.history.truncate(index);
+                Ok(from_seqno)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{block_header, block_id, mc_info, MockClient};
+
+    fn block(seqno: i32, fork: u8) -> BlockIdExt {
+        BlockIdExt {
+            workchain: -1,
+            shard: i64::MIN,
+            seqno,
+            root_hash: vec![fork; 32],
+            file_hash: vec![fork; 32],
+        }
+    }
+
+    #[test]
+    fn test_chain_history_one_block_rollback() {
+        let mut chain = ChainHistory::new(4);
+        for seqno in 1..=3 {
+            assert!(chain.links_to_last(&[block(seqno - 1, 0)]));
+            assert!(!chain.record(&block(seqno, 0)));
+        }
+
+        // block 4 is built on top of replaced block 3
+        assert!(!chain.links_to_last(&[block(3, 1)]));
+        let canonical = [block(1, 0), block(2, 0), block(3, 1)];
+        assert_eq!(chain.rollback(&canonical).unwrap(), 3);
+        assert_eq!(
+            chain.last(),
+            Some(&BlockStreamCheckpoint::from(&block(2, 0)))
+        );
+
+        // corrected chain is re-emitted
+        assert!(chain.links_to_last(&[block(2, 0)]));
+        assert!(chain.record(&block(3, 1)));
+        assert!(chain.links_to_last(&[block(3, 1)]));
+        assert!(!chain.record(&block(4, 1)));
+    }
+
+    #[test]
+    fn test_chain_history_reorg_too_deep() {
+        let mut chain = ChainHistory::new(2);
+        for seqno in 1..=3 {
+            chain.record(&block(seqno, 0));
+        }
+        let canonical = [block(2, 1), block(3, 1)];
+        assert!(chain.rollback(&canonical).is_err());
+    }
+
+    #[test]
+    fn test_chain_history_stale_block() {
+        let mut chain = ChainHistory::new(2);
+        chain.record(&block(1, 0));
+        chain.record(&block(2, 0));
+        assert!(!chain.links_to_last(&[block(1, 1)]));
+        let canonical = [block(1, 0), block(2, 0)];
+        assert!(chain.rollback(&canonical).is_err());
+        assert_eq!(chain.history.len(), 2);
+    }
+    /// Client serving masterchain blocks of fork 1 with a single basechain shard,
+    /// its block `seqno` finalized in masterchain block `seqno`
+    fn fork_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::BlocksGetMasterchainInfo {} => {
+                Ok(TonResult::BlocksMasterchainInfo(mc_info(10)))
+            }
+            TonFunction::BlocksLookupBlock { id, .. } => {
+                Ok(TonResult::BlockIdExt(block(id.seqno, 1)))
+            }
+            TonFunction::BlocksGetShards { id } => Ok(TonResult::BlocksShards(BlocksShards {
+                shards: vec![block_id(0, 0x8000000000000000, id.seqno)],
+            })),
+            TonFunction::GetBlockHeader { id } => {
+                let prev = match id.workchain {
+                    -1 => block(id.seqno - 1, 1),
+                    _ => block_id(0, 0x8000000000000000, id.seqno - 1),
+                };
+                Ok(TonResult::BlocksHeader(block_header(id, vec![prev])))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_stream_reports_reorg() -> anyhow::Result<()> {
+        // block 2 of fork 0 was emitted before the restart, while fork 1 became canonical
+        let checkpoint = BlockStreamCheckpoint::from(&block(2, 0));
+        let client = fork_client();
+        let mut stream = BlockStream::from_checkpoint(&client, &checkpoint);
+        stream.with_reorg_verification(4);
+
+        match stream.next_event().await? {
+            BlockStreamEvent::Reorg { from_seqno } => assert_eq!(from_seqno, 2),
+            event => panic!("Unexpected event: {:?}", event),
+        }
+        let item = stream.next().await?;
+        assert_eq!(item.master_shard, block(2, 1));
+        assert_eq!(item.shards, vec![block_id(0, 0x8000000000000000, 2)]);
+        assert!(item.reemitted);
+        let item = stream.next().await?;
+        assert_eq!(item.master_shard, block(3, 1));
+        assert!(!item.reemitted);
+        assert_eq!(
+            stream.checkpoint(),
+            Some(BlockStreamCheckpoint::from(&block(3, 1)))
+        );
+        Ok(())
+    }
+}
@@ -1,32 +1,63 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use super::TonConnectionCallback;
 use crate::client::{
-    error, ConnectionCheck, MultiConnectionCallback, RetryStrategy, TonClient, TonConnectionParams,
-    LOGGING_CONNECTION_CALLBACK, NOOP_CONNECTION_CALLBACK,
+    error, ConnectionCheck, FailoverParams, KeystoreDirFn, MultiConnectionCallback, PoolParams,
+    RetryBudgetParams, RetryStrategy, TonClient, TonConnectionParams, LOGGING_CONNECTION_CALLBACK,
+    NOOP_CONNECTION_CALLBACK,
 };
+use crate::config::TonConfig;
 
 pub struct TonClientBuilder {
-    pool_size: usize,
+    pool_params: PoolParams,
     connection_params: TonConnectionParams,
+    extra_configs: Vec<String>,
+    #[cfg(feature = "liteapi")]
+    probe_timeout: Option<Duration>,
+    #[cfg(feature = "liteapi")]
+    latency_sample_count: Option<usize>,
     retry_strategy: RetryStrategy,
     callback: Arc<dyn TonConnectionCallback>,
     connection_check: ConnectionCheck,
+    masterchain_info_ttl: Option<Duration>,
+    fallback_config: Option<String>,
+    failover_params: FailoverParams,
+    retry_budget: Option<RetryBudgetParams>,
+    keystore_dir_fn: Option<KeystoreDirFn>,
+    eager_connect: bool,
 }
 
 impl TonClientBuilder {
     pub fn new() -> Self {
         TonClientBuilder {
-            pool_size: 1,
+            pool_params: PoolParams::default(),
             connection_params: TonConnectionParams::default(),
+            extra_configs: vec![],
+            #[cfg(feature = "liteapi")]
+            probe_timeout: None,
+            #[cfg(feature = "liteapi")]
+            latency_sample_count: None,
             retry_strategy: RetryStrategy::default(),
             callback: LOGGING_CONNECTION_CALLBACK.clone(),
             connection_check: ConnectionCheck::None,
+            masterchain_info_ttl: None,
+            fallback_config: None,
+            failover_params: FailoverParams::default(),
+            retry_budget: None,
+            keystore_dir_fn: None,
+            eager_connect: false,
         }
     }
 
     pub fn with_pool_size(&mut self, pool_size: usize) -> &mut Self {
-        self.pool_size = pool_size;
+        self.pool_params = PoolParams::fixed(pool_size);
+        self
+    }
+
+    pub fn with_pool_params(&mut self, pool_params: &PoolParams) -> &mut Self {
+        self.pool_params = pool_params.clone();
         self
     }
 
@@ -40,6 +71,30 @@ impl TonClientBuilder {
         self
     }
 
+    /// Adds liteservers of `config` to the ones of the main config, see `TonConfig::merge`.
+    ///
+    /// `build` fails if the configs belong to different networks.
+    pub fn with_extra_config(&mut self, config: &str) -> &mut Self {
+        self.extra_configs.push(config.to_string());
+        self
+    }
+
+    /// Drops liteservers which don't answer within `timeout` from the config on `build`,
+    /// see `TonConfig::prune_unresponsive`.
+    #[cfg(feature = "liteapi")]
+    pub fn with_liteserver_probe(&mut self, timeout: Duration) -> &mut Self {
+        self.probe_timeout = Some(timeout);
+        self
+    }
+
+    /// Orders liteservers of the config by the median of `sample_count` round-trips on `build`,
+    /// see `TonConfig::sort_by_latency`.
+    #[cfg(feature = "liteapi")]
+    pub fn with_latency_sort(&mut self, sample_count: usize) -> &mut Self {
+        self.latency_sample_count = Some(sample_count);
+        self
+    }
+
     pub fn with_retry_strategy(&mut self, retry_strategy: &RetryStrategy) -> &mut Self {
         self.retry_strategy = retry_strategy.clone();
         self
@@ -75,21 +130,129 @@ impl TonClientBuilder {
         self
     }
 
+    /// Sets the keystore directory of each connection instead of `{keystore_dir}/{i}`.
+    ///
+    /// `keystore_dir_fn` is called with the index of the connection in the pool, connections
+    /// of the fallback pool get indexes following the primary ones. Missing directories are created.
+    pub fn with_keystore_dir_fn<F>(&mut self, keystore_dir_fn: F) -> &mut Self
+    where
+        F: Fn(usize) -> PathBuf + Send + Sync + 'static,
+    {
+        self.keystore_dir_fn = Some(Arc::new(keystore_dir_fn));
+        self
+    }
+
     pub fn with_connection_check(&mut self, connection_check: ConnectionCheck) -> &mut Self {
         self.connection_check = connection_check;
         self
     }
 
+    /// Sets for how long masterchain info is reused by `TonClient::masterchain_info_cached` (1s by default)
+    pub fn with_masterchain_info_ttl(&mut self, ttl: Duration) -> &mut Self {
+        self.masterchain_info_ttl = Some(ttl);
+        self
+    }
+
+    /// Sets config which is used when the primary one keeps failing, see `TonClient::with_failover`
+    pub fn with_failover_config(&mut self, config: &str) -> &mut Self {
+        self.fallback_config = Some(config.to_string());
+        self
+    }
+
+    pub fn with_failover_params(&mut self, failover_params: &FailoverParams) -> &mut Self {
+        self.failover_params = failover_params.clone();
+        self
+    }
+
+    /// Limits retries of all calls to a share of successful requests, see `TonClient::set_retry_budget`
+    pub fn with_retry_budget(&mut self, retry_budget: &RetryBudgetParams) -> &mut Self {
+        self.retry_budget = Some(retry_budget.clone());
+        self
+    }
+
+    /// Connects the initial pool in `build` instead of on first use,
+    /// logging percentiles of the connect phases across the pool.
+    pub fn with_eager_connect(&mut self) -> &mut Self {
+        self.eager_connect = true;
+        self
+    }
+
     pub async fn build(&self) -> Result<TonClient, error::TonClientError> {
-        TonClient::new(
-            self.pool_size,
-            &self.connection_params,
+        let connection_params = self.connection_params().await?;
+        let fallback_params = self.fallback_config.as_ref().map(|config| {
+            let mut fallback_params = connection_params.clone();
+            fallback_params.config = config.clone();
+            fallback_params
+        });
+        let failover = fallback_params
+            .as_ref()
+            .map(|fallback_params| (fallback_params, &self.failover_params));
+        let client = TonClient::create(
+            &self.pool_params,
+            &connection_params,
+            failover,
             &self.retry_strategy,
             self.callback.clone(),
             self.connection_check.clone(),
+            self.keystore_dir_fn.as_ref(),
         )
-        .await
+        .await?;
+        if let Some(ttl) = self.masterchain_info_ttl {
+            client.set_masterchain_info_ttl(ttl);
+        }
+        if let Some(retry_budget) = &self.retry_budget {
+            client.set_retry_budget(Some(retry_budget));
+        }
+        if self.eager_connect {
+            let report = client.connect_eagerly().await?;
+            log::info!("Connected TonClient pool, connect phases: {:?}", report);
+        }
+        Ok(client)
+    }
+
+    /// Connection params with the extra configs merged into the config,
+    /// unresponsive liteservers dropped and the rest ordered by latency
+    async fn connection_params(&self) -> Result<TonConnectionParams, error::TonClientError> {
+        if self.extra_configs.is_empty() && !self.probes_liteservers() {
+            return Ok(self.connection_params.clone());
+        }
+        let mut config = parse_config(&self.connection_params.config)?;
+        for extra_config in self.extra_configs.iter() {
+            config = config.merge(parse_config(extra_config)?).map_err(|e| {
+                error::TonClientError::InternalError(format!("Fail to merge config: {}", e))
+            })?;
+        }
+        #[cfg(feature = "liteapi")]
+        if let Some(timeout) = self.probe_timeout {
+            config = config.prune_unresponsive(timeout).await.map_err(|e| {
+                error::TonClientError::InternalError(format!("Fail to probe liteservers: {}", e))
+            })?;
+        }
+        #[cfg(feature = "liteapi")]
+        if let Some(sample_count) = self.latency_sample_count {
+            config = config.sort_by_latency(sample_count).await;
+        }
+        let mut params = self.connection_params.clone();
+        params.config = config.to_json().map_err(|e| {
+            error::TonClientError::InternalError(format!("Fail to serialize config: {}", e))
+        })?;
+        Ok(params)
     }
+
+    #[cfg(feature = "liteapi")]
+    fn probes_liteservers(&self) -> bool {
+        self.probe_timeout.is_some() || self.latency_sample_count.is_some()
+    }
+
+    #[cfg(not(feature = "liteapi"))]
+    fn probes_liteservers(&self) -> bool {
+        false
+    }
+}
+
+fn parse_config(config: &str) -> Result<TonConfig, error::TonClientError> {
+    TonConfig::from_json(config)
+        .map_err(|e| error::TonClientError::InternalError(format!("Fail to parse config: {}", e)))
 }
 
 impl Default for TonClientBuilder {
@@ -97,3 +260,30 @@ impl Default for TonClientBuilder {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{MAINNET_CONFIG, TESTNET_CONFIG};
+
+    #[tokio::test]
+    async fn test_extra_config() -> anyhow::Result<()> {
+        let mainnet = TonConfig::from_json(MAINNET_CONFIG)?;
+        let mut builder = TonClientBuilder::new();
+        builder.with_config(MAINNET_CONFIG);
+        assert_eq!(builder.connection_params().await?.config, MAINNET_CONFIG);
+
+        let mut extra = TonConfig::from_json(MAINNET_CONFIG)?;
+        extra.liteservers.truncate(1);
+        let mut custom = extra.liteservers[0].clone();
+        custom.id.key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string();
+        extra.liteservers.push(custom);
+        builder.with_extra_config(&extra.to_json()?);
+        let config = TonConfig::from_json(&builder.connection_params().await?.config)?;
+        assert_eq!(config.liteservers.len(), mainnet.liteservers.len() + 1);
+
+        builder.with_extra_config(TESTNET_CONFIG);
+        assert!(builder.connection_params().await.is_err());
+        Ok(())
+    }
+}
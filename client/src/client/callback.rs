@@ -3,7 +3,7 @@ use std::time::Duration;
 
 use lazy_static::lazy_static;
 
-use crate::client::TonClientError;
+use crate::client::{ConnectPhase, TonClientError};
 use crate::tl::{TonFunction, TonNotification, TonResult};
 
 /// The callback methods invoked by TonConnection
@@ -58,6 +58,18 @@ pub trait TonConnectionCallback: Send + Sync {
 
     /// Method `on_connection_loop_exit` gets called when new connection loop stops and connection is dropped
     fn on_connection_loop_exit(&self, tag: &str) {}
+
+    /// Method `on_failover` gets called when `TonClient` created with a fallback config
+    /// switches to the fallback connection pool after `consecutive_failures` of the primary one.
+    fn on_failover(&self, consecutive_failures: u32, last_error: &TonClientError) {}
+
+    /// Method `on_connect_phase` gets called when a phase of establishing the connection
+    /// completes or fails after `elapsed`.
+    fn on_connect_phase(&self, tag: &str, phase: ConnectPhase, elapsed: &Duration) {}
+
+    /// Method `on_unsupported_transport_option` gets called once per connection pool for each
+    /// option of `TonConnectionParams` which is set but ignored by its connections.
+    fn on_unsupported_transport_option(&self, option: &str) {}
 }
 
 /// An implementation of TonConnectionCallback that does nothing
@@ -136,6 +148,25 @@ impl TonConnectionCallback for LoggingConnectionCallback {
     fn on_connection_loop_exit(&self, tag: &str) {
         log::info!("[{}] Exiting event loop", tag);
     }
+
+    fn on_failover(&self, consecutive_failures: u32, last_error: &TonClientError) {
+        log::warn!(
+            "Switching to fallback config after {} consecutive failures, last error: {}",
+            consecutive_failures,
+            last_error
+        );
+    }
+
+    fn on_connect_phase(&self, tag: &str, phase: ConnectPhase, elapsed: &Duration) {
+        log::debug!("[{}] Connect phase {:?} took {:?}", tag, phase, elapsed);
+    }
+
+    fn on_unsupported_transport_option(&self, option: &str) {
+        log::warn!(
+            "Transport option {} is not supported by tonlibjson, ignored",
+            option
+        );
+    }
 }
 
 /// An implementation of TonConnectionCallback that invokes corresponding functions on
@@ -210,6 +241,24 @@ impl TonConnectionCallback for MultiConnectionCallback {
             c.on_connection_loop_exit(tag)
         }
     }
+
+    fn on_failover(&self, consecutive_failures: u32, last_error: &TonClientError) {
+        for c in self.callbacks.iter() {
+            c.on_failover(consecutive_failures, last_error)
+        }
+    }
+
+    fn on_connect_phase(&self, tag: &str, phase: ConnectPhase, elapsed: &Duration) {
+        for c in self.callbacks.iter() {
+            c.on_connect_phase(tag, phase, elapsed)
+        }
+    }
+
+    fn on_unsupported_transport_option(&self, option: &str) {
+        for c in self.callbacks.iter() {
+            c.on_unsupported_transport_option(option)
+        }
+    }
 }
 
 lazy_static! {
@@ -0,0 +1,211 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use futures::future::join_all;
+use rand::Rng;
+
+use crate::client::{InvokeOptions, TonClient, TonClientError, TonClientInterface, TonConnection};
+use crate::tl::{TonFunction, TonResult};
+
+/// Order in which `TonClientPool` routes requests to its clients
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum SelectionStrategy {
+    /// Pick a random client for each request, same as connections are picked within `TonClient`
+    #[default]
+    Random,
+    /// Pick clients one after another
+    RoundRobin,
+}
+
+/// Status of a single client reported by `TonClientPool::health_check`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHealth {
+    /// Index of the client in the pool
+    pub index: usize,
+    /// Seqno of the last masterchain block known to the client, `None` if the request failed
+    pub last_seqno: Option<i32>,
+    pub latency: Duration,
+    pub error: Option<String>,
+}
+
+impl ClientHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Load balancer distributing requests among several `TonClient` instances,
+/// e.g. connected to liteservers in different regions.
+///
+/// Each request is served by a single client, retries are performed by that client.
+pub struct TonClientPool {
+    clients: RwLock<Vec<TonClient>>,
+    strategy: SelectionStrategy,
+    next: AtomicUsize,
+}
+
+impl TonClientPool {
+    pub fn new(clients: Vec<TonClient>, strategy: SelectionStrategy) -> TonClientPool {
+        TonClientPool {
+            clients: RwLock::new(clients),
+            strategy,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn add_client(&self, client: TonClient) {
+        self.write_clients().push(client)
+    }
+
+    /// Removes the client at `index`, shifting the following clients to the left.
+    ///
+    /// Requests already routed to the client are completed by it.
+    pub fn remove_client(&self, index: usize) -> Option<TonClient> {
+        let mut clients = self.write_clients();
+        if index < clients.len() {
+            Some(clients.remove(index))
+        } else {
+            None
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.read_clients().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.read_clients().is_empty()
+    }
+
+    /// Requests masterchain info from all clients concurrently, without retries.
+    pub async fn health_check(&self) -> Vec<ClientHealth> {
+        let clients = self.read_clients().clone();
+        let options = InvokeOptions::no_retry();
+        let checks = clients.iter().enumerate().map(|(index, client)| {
+            let options = &options;
+            async move {
+                let started_at = Instant::now();
+                let result = client
+                    .invoke_with_options(&TonFunction::BlocksGetMasterchainInfo {}, options)
+                    .await;
+                let latency = started_at.elapsed();
+                let (last_seqno, error) = match result {
+                    Ok(TonResult::BlocksMasterchainInfo(info)) => (Some(info.last.seqno), None),
+                    Ok(r) => (None, Some(format!("Unexpected result: {:?}", r))),
+                    Err(e) => (None, Some(e.to_string())),
+                };
+                ClientHealth {
+                    index,
+                    last_seqno,
+                    latency,
+                    error,
+                }
+            }
+        });
+        join_all(checks).await
+    }
+
+    fn select_client(&self) -> Result<TonClient, TonClientError> {
+        let clients = self.read_clients();
+        if clients.is_empty() {
+            return Err(TonClientError::InternalError(
+                "TonClientPool has no clients".to_string(),
+            ));
+        }
+        let i = select_index(self.strategy, &self.next, clients.len());
+        Ok(clients[i].clone())
+    }
+
+    fn read_clients(&self) -> std::sync::RwLockReadGuard<'_, Vec<TonClient>> {
+        self.clients.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write_clients(&self) -> std::sync::RwLockWriteGuard<'_, Vec<TonClient>> {
+        self.clients.write().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn select_index(strategy: SelectionStrategy, next: &AtomicUsize, len: usize) -> usize {
+    match strategy {
+        SelectionStrategy::Random => rand::thread_rng().gen_range(0..len),
+        SelectionStrategy::RoundRobin => next.fetch_add(1, Ordering::Relaxed) % len,
+    }
+}
+
+#[async_trait]
+impl TonClientInterface for TonClientPool {
+    async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+        self.select_client()?.get_connection().await
+    }
+
+    async fn invoke_on_connection(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        self.select_client()?.invoke_on_connection(function).await
+    }
+
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        self.select_client()?
+            .invoke_with_options(function, options)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        ConnectionCheck, RetryStrategy, TonConnectionParams, NOOP_CONNECTION_CALLBACK,
+    };
+
+    async fn client() -> anyhow::Result<TonClient> {
+        let params = TonConnectionParams {
+            update_init_block: false,
+            ..TonConnectionParams::default()
+        };
+        let client = TonClient::new(
+            1,
+            &params,
+            &RetryStrategy::default(),
+            NOOP_CONNECTION_CALLBACK.clone(),
+            ConnectionCheck::None,
+        )
+        .await?;
+        Ok(client)
+    }
+
+    #[test]
+    fn test_round_robin_selection() {
+        let next = AtomicUsize::new(0);
+        let selected: Vec<_> = (0..5)
+            .map(|_| select_index(SelectionStrategy::RoundRobin, &next, 3))
+            .collect();
+        assert_eq!(selected, vec![0, 1, 2, 0, 1]);
+        assert!((0..100).all(|_| select_index(SelectionStrategy::Random, &next, 3) < 3));
+    }
+
+    #[tokio::test]
+    async fn test_add_remove_client() -> anyhow::Result<()> {
+        let pool = TonClientPool::new(vec![], SelectionStrategy::RoundRobin);
+        assert!(matches!(
+            pool.get_connection().await,
+            Err(TonClientError::InternalError(_))
+        ));
+        assert!(pool.health_check().await.is_empty());
+
+        pool.add_client(client().await?);
+        pool.add_client(client().await?);
+        assert_eq!(pool.len(), 2);
+        assert!(pool.remove_client(2).is_none());
+        assert!(pool.remove_client(0).is_some());
+        assert_eq!(pool.len(), 1);
+        Ok(())
+    }
+}
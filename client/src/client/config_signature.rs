@@ -0,0 +1,237 @@
+use std::collections::HashSet;
+
+use num_bigint::BigUint;
+use tonlib_core::cell::{Cell, CellBuilder, TonCellError};
+use tonlib_core::types::ConfigValidatorSet;
+use tonlib_core::TonHash;
+
+use crate::client::TonClientError;
+
+/// Tag of a vote for a config proposal, `"Vote"`
+pub const CONFIG_VOTE_TAG: u32 = 0x566f7465;
+
+/// Vote of a validator for a config proposal, the body of an external message
+/// to the config contract
+///
+/// ```raw
+/// signature:bits512 tag:#566f7465 idx:uint16 phash:uint256
+/// ```
+///
+/// `idx` is the index of the validator in the current validator set and `phash` is the hash
+/// of the `ConfigProposal` cell. The validator signs the hash of the body after the signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigVote {
+    pub signature: [u8; 64],
+    pub idx: u16,
+    pub phash: TonHash,
+}
+
+impl ConfigVote {
+    pub fn parse(cell: &Cell) -> Result<ConfigVote, TonCellError> {
+        let mut parser = cell.parser();
+        let mut signature = [0u8; 64];
+        parser.load_slice(&mut signature)?;
+        let tag = parser.load_u32(32)?;
+        if tag != CONFIG_VOTE_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid config vote tag: {:#x}",
+                tag
+            )));
+        }
+        let idx = parser.load_u16(16)?;
+        let mut phash = [0u8; 32];
+        parser.load_slice(&mut phash)?;
+        parser.ensure_empty()?;
+        Ok(ConfigVote {
+            signature,
+            idx,
+            phash,
+        })
+    }
+
+    /// Hash signed by the validator
+    pub fn signed_hash(&self) -> Result<TonHash, TonCellError> {
+        let cell = CellBuilder::new()
+            .store_u32(32, CONFIG_VOTE_TAG)?
+            .store_u32(16, self.idx as u32)?
+            .store_slice(&self.phash)?
+            .build()?;
+        Ok(cell.cell_hash())
+    }
+}
+
+/// Checks votes for the config proposal `proposal` against `validator_set`, the current
+/// validator set from config param 34.
+///
+/// Returns `true` if validators holding more than 3/4 of the total weight voted for the proposal,
+/// the share the config contract requires to win a voting round.
+/// Votes for other proposals, with `idx` out of the set or not signed by the validator
+/// with that index are ignored.
+pub(crate) fn verify_config_signature(
+    proposal: &Cell,
+    votes: &[Cell],
+    validator_set: &ConfigValidatorSet,
+) -> Result<bool, TonClientError> {
+    let phash = proposal.cell_hash();
+    let mut voted_weight = BigUint::from(0u32);
+    let mut voted = HashSet::new();
+    for vote in votes {
+        let vote = ConfigVote::parse(vote).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to parse config vote: {}", e))
+        })?;
+        if vote.phash != phash {
+            continue;
+        }
+        let Some(validator) = validator_set.validator(vote.idx) else {
+            log::warn!(
+                "Config vote of validator {} is out of the set of {} validators",
+                vote.idx,
+                validator_set.validators.len()
+            );
+            continue;
+        };
+        let message = vote.signed_hash().map_err(|e| {
+            TonClientError::InternalError(format!("Failed to hash config vote: {}", e))
+        })?;
+        let valid = nacl::sign::verify(&vote.signature, &message, &validator.public_key)
+            .map_err(|e| TonClientError::InternalError(e.message))?;
+        if !valid {
+            log::warn!(
+                "Config vote of validator {} has no valid signature",
+                vote.idx
+            );
+        } else if voted.insert(vote.idx) {
+            voted_weight += validator.weight;
+        }
+    }
+    Ok(voted_weight * 4u32 > BigUint::from(validator_set.total_weight) * 3u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use nacl::sign::{generate_keypair, signature, Keypair};
+
+    use tonlib_core::types::ValidatorDescr;
+
+    use super::*;
+
+    fn validator_set(pubkeys: &[TonHash], weights: &[u64]) -> ConfigValidatorSet {
+        let validators: Vec<_> = pubkeys
+            .iter()
+            .zip(weights)
+            .map(|(pubkey, weight)| ValidatorDescr {
+                public_key: *pubkey,
+                weight: *weight,
+                adnl_addr: None,
+            })
+            .collect();
+        ConfigValidatorSet {
+            utime_since: 0,
+            utime_until: 0,
+            total: validators.len() as u16,
+            main: validators.len() as u16,
+            total_weight: weights.iter().sum(),
+            validators,
+        }
+    }
+
+    /// `cfg_proposal#f3 param_id:int32 param_value:(Maybe ^Cell) if_hash_equal:(Maybe uint256)`
+    fn config_proposal(param_id: i32, value: u32) -> anyhow::Result<Cell> {
+        let value = CellBuilder::new().store_u32(32, value)?.build()?;
+        let cell = CellBuilder::new()
+            .store_u8(8, 0xf3)?
+            .store_i32(32, param_id)?
+            .store_bit(true)?
+            .store_child(value)?
+            .store_bit(false)?
+            .build()?;
+        Ok(cell)
+    }
+
+    fn vote(key: &Keypair, idx: u16, proposal: &Cell) -> anyhow::Result<Cell> {
+        let unsigned = ConfigVote {
+            signature: [0; 64],
+            idx,
+            phash: proposal.cell_hash(),
+        };
+        let sig = signature(&unsigned.signed_hash()?, &key.skey)
+            .map_err(|e| anyhow::anyhow!(e.message))?;
+        vote_cell(&sig, idx, &unsigned.phash)
+    }
+
+    fn vote_cell(signature: &[u8], idx: u16, phash: &TonHash) -> anyhow::Result<Cell> {
+        let cell = CellBuilder::new()
+            .store_slice(signature)?
+            .store_u32(32, CONFIG_VOTE_TAG)?
+            .store_u32(16, idx as u32)?
+            .store_slice(phash)?
+            .build()?;
+        Ok(cell)
+    }
+
+    #[test]
+    fn test_parse_config_vote() -> anyhow::Result<()> {
+        let key = generate_keypair(&[1; 32]);
+        let proposal = config_proposal(13, 1)?;
+        let parsed = ConfigVote::parse(&vote(&key, 7, &proposal)?)?;
+        assert_eq!(parsed.idx, 7);
+        assert_eq!(parsed.phash, proposal.cell_hash());
+        assert!(ConfigVote::parse(&proposal).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_verify_config_signature() -> anyhow::Result<()> {
+        let keys: Vec<_> = (1u8..=3).map(|i| generate_keypair(&[i; 32])).collect();
+        let pubkeys: Vec<TonHash> = keys.iter().map(|k| k.pkey).collect();
+        let set = validator_set(&pubkeys, &[50, 30, 20]);
+        let proposal = config_proposal(13, 1)?;
+        let other = config_proposal(13, 2)?;
+
+        // 80 of 100
+        let votes = [vote(&keys[0], 0, &proposal)?, vote(&keys[1], 1, &proposal)?];
+        assert!(verify_config_signature(&proposal, &votes, &set)?);
+        // exactly 3/4 isn't enough, duplicates aren't counted twice
+        let set = validator_set(&pubkeys, &[45, 30, 25]);
+        let votes = [
+            vote(&keys[0], 0, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+        ];
+        assert!(!verify_config_signature(&proposal, &votes, &set)?);
+        assert!(!verify_config_signature(&proposal, &[], &set)?);
+        // votes for another proposal aren't counted
+        let votes = [
+            vote(&keys[0], 0, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+            vote(&keys[2], 2, &other)?,
+        ];
+        assert!(!verify_config_signature(&proposal, &votes, &set)?);
+
+        let mut corrupted = ConfigVote::parse(&vote(&keys[2], 2, &proposal)?)?;
+        corrupted.signature[0] ^= 1;
+        let corrupted = vote_cell(&corrupted.signature, 2, &corrupted.phash)?;
+        let votes = [
+            vote(&keys[0], 0, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+            corrupted,
+        ];
+        // vote with a corrupted signature isn't counted
+        assert!(!verify_config_signature(&proposal, &votes, &set)?);
+        // the signer is looked up by index, votes with a wrong or out of range index aren't counted
+        let votes = [
+            vote(&keys[0], 0, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+            vote(&keys[2], 1, &proposal)?,
+            vote(&keys[2], 3, &proposal)?,
+        ];
+        assert!(!verify_config_signature(&proposal, &votes, &set)?);
+        let votes = [
+            vote(&keys[0], 0, &proposal)?,
+            vote(&keys[1], 1, &proposal)?,
+            vote(&keys[2], 2, &proposal)?,
+        ];
+        assert!(verify_config_signature(&proposal, &votes, &set)?);
+        Ok(())
+    }
+}
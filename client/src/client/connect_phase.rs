@@ -0,0 +1,234 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+use crate::client::{TonClientError, TonConnectionCallback};
+
+/// Phase of establishing a `TonConnection`, in the order of execution
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectPhase {
+    /// Creation of the keystore directory
+    KeystoreSetup,
+    /// Initialization of tonlib with the network config
+    InitOptions,
+    /// The first liteserver query, establishing the connection to it.
+    /// Reported by `ConnectionCheck::None` only.
+    LiteserverHandshake,
+    /// Synchronization from the init block, downloading block proofs.
+    /// Not reported by `ConnectionCheck::Health`, whose check includes it.
+    ProofDownload,
+    /// Check of `ConnectionCheck::Health`, including the handshake and the proof download
+    HealthCheck,
+    /// Check of `ConnectionCheck::Archive`
+    ArchiveCheck,
+}
+
+/// Duration of a completed connect phase
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectPhaseTiming {
+    pub phase: ConnectPhase,
+    pub elapsed: Duration,
+}
+
+/// Percentiles of a connect phase duration across the connections of a pool
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConnectPhaseStats {
+    pub phase: ConnectPhase,
+    pub count: usize,
+    pub p50: Duration,
+    pub p95: Duration,
+}
+
+/// Aggregates phase timings of several connects, phases are sorted in the order of execution
+pub fn aggregate_connect_phases<'a, I>(connects: I) -> Vec<ConnectPhaseStats>
+where
+    I: IntoIterator<Item = &'a [ConnectPhaseTiming]>,
+{
+    let mut durations: HashMap<ConnectPhase, Vec<Duration>> = HashMap::new();
+    for timing in connects.into_iter().flatten() {
+        durations
+            .entry(timing.phase)
+            .or_default()
+            .push(timing.elapsed);
+    }
+    let mut stats: Vec<_> = durations
+        .into_iter()
+        .map(|(phase, mut durations)| {
+            durations.sort();
+            ConnectPhaseStats {
+                phase,
+                count: durations.len(),
+                p50: percentile(&durations, 50),
+                p95: percentile(&durations, 95),
+            }
+        })
+        .collect();
+    stats.sort_by_key(|s| s.phase);
+    stats
+}
+
+/// Nearest-rank percentile of non-empty sorted `durations`
+fn percentile(durations: &[Duration], percent: usize) -> Duration {
+    let rank = (durations.len() * percent).div_ceil(100).max(1);
+    durations[rank - 1]
+}
+
+/// Runs phases of a connect, reporting them to the callback and recording their durations
+pub(crate) struct ConnectTimer {
+    tag: String,
+    callback: Arc<dyn TonConnectionCallback>,
+    phases: Vec<ConnectPhaseTiming>,
+}
+
+impl ConnectTimer {
+    pub(crate) fn new(tag: &str, callback: Arc<dyn TonConnectionCallback>) -> ConnectTimer {
+        ConnectTimer {
+            tag: tag.to_string(),
+            callback,
+            phases: vec![],
+        }
+    }
+
+    /// Runs `phase`, wrapping its error into `TonClientError::ConnectFailed`
+    pub(crate) async fn run<T, F>(
+        &mut self,
+        phase: ConnectPhase,
+        future: F,
+    ) -> Result<T, TonClientError>
+    where
+        F: Future<Output = Result<T, TonClientError>>,
+    {
+        let start = Instant::now();
+        let result = future.await;
+        let elapsed = start.elapsed();
+        self.callback.on_connect_phase(&self.tag, phase, &elapsed);
+        match result {
+            Ok(value) => {
+                self.phases.push(ConnectPhaseTiming { phase, elapsed });
+                Ok(value)
+            }
+            Err(source) => Err(TonClientError::ConnectFailed {
+                phase,
+                elapsed,
+                phases: self.phases.clone(),
+                source: Box::new(source),
+            }),
+        }
+    }
+
+    pub(crate) fn into_phases(self) -> Vec<ConnectPhaseTiming> {
+        self.phases
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::client::connection::with_timeout;
+
+    #[derive(Default)]
+    struct PhaseRecorder {
+        phases: Mutex<Vec<(String, ConnectPhase)>>,
+    }
+
+    impl TonConnectionCallback for PhaseRecorder {
+        fn on_connect_phase(&self, tag: &str, phase: ConnectPhase, _elapsed: &Duration) {
+            self.phases.lock().unwrap().push((tag.to_string(), phase));
+        }
+    }
+
+    /// Mock transport completing every phase but `stalled` one immediately
+    async fn mock_phase(phase: ConnectPhase, stalled: ConnectPhase) -> Result<(), TonClientError> {
+        if phase == stalled {
+            let stall = tokio::time::sleep(Duration::from_secs(60));
+            with_timeout(Duration::from_millis(20), "mock", "connect", stall).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stalled_phase_is_identified() {
+        let recorder = Arc::new(PhaseRecorder::default());
+        let mut timer = ConnectTimer::new("conn-0", recorder.clone());
+        let all = [
+            ConnectPhase::KeystoreSetup,
+            ConnectPhase::InitOptions,
+            ConnectPhase::LiteserverHandshake,
+            ConnectPhase::ProofDownload,
+            ConnectPhase::HealthCheck,
+        ];
+        let mut result = Ok(());
+        for phase in all {
+            result = timer
+                .run(phase, mock_phase(phase, ConnectPhase::ProofDownload))
+                .await;
+            if result.is_err() {
+                break;
+            }
+        }
+
+        match result {
+            Err(TonClientError::ConnectFailed {
+                phase,
+                elapsed,
+                phases,
+                source,
+            }) => {
+                assert_eq!(phase, ConnectPhase::ProofDownload);
+                assert!(elapsed >= Duration::from_millis(20));
+                let completed: Vec<_> = phases.iter().map(|t| t.phase).collect();
+                assert_eq!(completed, all[..3]);
+                assert!(matches!(*source, TonClientError::Timeout { .. }));
+            }
+            r => panic!("Unexpected result: {:?}", r),
+        }
+        let reported = recorder.phases.lock().unwrap().clone();
+        assert_eq!(reported.len(), 4);
+        assert_eq!(
+            reported.last(),
+            Some(&("conn-0".to_string(), ConnectPhase::ProofDownload))
+        );
+    }
+
+    #[test]
+    fn test_aggregate_connect_phases() {
+        let connects: Vec<Vec<ConnectPhaseTiming>> = (1..=20)
+            .map(|i| {
+                vec![
+                    ConnectPhaseTiming {
+                        phase: ConnectPhase::InitOptions,
+                        elapsed: Duration::from_millis(i),
+                    },
+                    ConnectPhaseTiming {
+                        phase: ConnectPhase::KeystoreSetup,
+                        elapsed: Duration::from_millis(1),
+                    },
+                ]
+            })
+            .collect();
+        let stats = aggregate_connect_phases(connects.iter().map(|c| c.as_slice()));
+        assert_eq!(
+            stats,
+            vec![
+                ConnectPhaseStats {
+                    phase: ConnectPhase::KeystoreSetup,
+                    count: 20,
+                    p50: Duration::from_millis(1),
+                    p95: Duration::from_millis(1),
+                },
+                ConnectPhaseStats {
+                    phase: ConnectPhase::InitOptions,
+                    count: 20,
+                    p50: Duration::from_millis(10),
+                    p95: Duration::from_millis(19),
+                },
+            ]
+        );
+    }
+}
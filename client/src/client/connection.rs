@@ -1,31 +1,42 @@
-use std::sync::atomic::{AtomicU32, Ordering};
+use std::fs;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
 use std::sync::{Arc, Weak};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use dashmap::DashMap;
-use tokio::sync::{broadcast, oneshot, Semaphore, SemaphorePermit};
 
+use crate::client::connect_phase::ConnectTimer;
 use crate::client::{
-    TonClientError, TonClientInterface, TonConnectionCallback, TonConnectionParams,
-    TonNotificationReceiver,
+    ConnectPhase, ConnectPhaseTiming, TonClientError, TonClientInterface, TonConnectionCallback,
+    TonConnectionParams, TonNotificationReceiver,
 };
+use crate::rt::{self, broadcast, oneshot, Semaphore, SemaphorePermit};
 use crate::tl::{
-    BlockId, Config, KeyStoreType, Options, OptionsInfo, SmcRunResult, TlTonClient, TonFunction,
-    TonNotification, TonResult, TonResultDiscriminants, TvmStackEntry,
+    deserialize_error_extra, deserialize_header, deserialize_response, deserialize_result_bytes,
+    BlockId, Config, KeyStoreType, Options, OptionsInfo, ResponseHeader, SmcRunResult, TlTonClient,
+    TonFunction, TonNotification, TonResult, TonResultDiscriminants, TvmStackEntry,
 };
 use crate::types::TonMethodId;
 
 pub const DEFAULT_NOTIFICATION_QUEUE_LENGTH: usize = 10000;
 pub const DEFAULT_CONNECTION_CONCURRENCY_LIMIT: usize = 100;
 pub const DEFAULT_UPDATE_INIT_BLOCK: bool = true;
+pub const DEFAULT_POOL_GROW_THRESHOLD: usize = 50;
+pub const DEFAULT_POOL_IDLE_TTL_MS: u64 = 60_000;
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+pub const DEFAULT_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(120);
 
 struct RequestData {
     method: &'static str,
     send_time: Instant,
-    sender: oneshot::Sender<Result<TonResult, TonClientError>>,
+    /// `None` if the response is to be deserialized by the caller from `raw_sender`
+    sender: Option<oneshot::Sender<Result<TonResult, TonClientError>>>,
+    raw_sender: Option<oneshot::Sender<Vec<u8>>>,
 }
 
 type RequestMap = DashMap<u32, RequestData>;
@@ -35,10 +46,31 @@ struct Inner {
     tl_client: TlTonClient,
     counter: AtomicU32,
     request_map: RequestMap,
+    /// Number of requests in `request_map` whose body is deserialized by the caller
+    body_requests: AtomicUsize,
     notification_sender: TonNotificationSender,
     callback: Arc<dyn TonConnectionCallback>,
     _notification_receiver: TonNotificationReceiver,
     semaphore: Option<Semaphore>,
+    write_timeout: Duration,
+    read_timeout: Duration,
+}
+
+impl Inner {
+    fn insert_request(&self, id: u32, data: RequestData) {
+        if data.sender.is_none() {
+            self.body_requests.fetch_add(1, Ordering::SeqCst);
+        }
+        self.request_map.insert(id, data);
+    }
+
+    fn remove_request(&self, id: u32) -> Option<RequestData> {
+        let (_, data) = self.request_map.remove(&id)?;
+        if data.sender.is_none() {
+            self.body_requests.fetch_sub(1, Ordering::SeqCst);
+        }
+        Some(data)
+    }
 }
 
 pub struct TonConnection {
@@ -47,6 +79,9 @@ pub struct TonConnection {
 
 static CONNECTION_COUNTER: AtomicU32 = AtomicU32::new(0);
 
+/// Connection with its run loop thread and the durations of its connect phases
+pub(crate) type TimedConnection = (TonConnection, JoinHandle<()>, Vec<ConnectPhaseTiming>);
+
 impl TonConnection {
     /// Creates a new uninitialized TonConnection.
     ///
@@ -89,10 +124,13 @@ impl TonConnection {
             tl_client: TlTonClient::new(tag.as_str()),
             counter: AtomicU32::new(0),
             request_map: RequestMap::new(),
+            body_requests: AtomicUsize::new(0),
             notification_sender: sender,
             callback,
             _notification_receiver: receiver,
             semaphore,
+            write_timeout: params.write_timeout,
+            read_timeout: params.read_timeout,
         };
         let inner_arc = Arc::new(inner);
         let inner_weak: Weak<Inner> = Arc::downgrade(&inner_arc);
@@ -116,42 +154,61 @@ impl TonConnection {
         params: &TonConnectionParams,
         callback: Arc<dyn TonConnectionCallback>,
     ) -> Result<(TonConnection, JoinHandle<()>), TonClientError> {
-        let (conn, join_handle) = Self::new_joinable(callback, params)?;
+        Self::connect_timed(params, callback)
+            .await
+            .map(|(conn, join_handle, _)| (conn, join_handle))
+    }
+
+    /// Creates a new initialized TonConnection, reporting the connect phases to `callback`
+    async fn connect_timed(
+        params: &TonConnectionParams,
+        callback: Arc<dyn TonConnectionCallback>,
+    ) -> Result<(TonConnection, JoinHandle<()>, ConnectTimer), TonClientError> {
+        let (conn, join_handle) = Self::new_joinable(callback.clone(), params)?;
+        let mut timer = ConnectTimer::new(conn.tag(), callback);
         let keystore_type = if let Some(directory) = &params.keystore_dir {
+            let setup = async { fs::create_dir_all(directory).map_err(TonClientError::from) };
+            timer.run(ConnectPhase::KeystoreSetup, setup).await?;
             KeyStoreType::Directory {
                 directory: directory.clone(),
             }
         } else {
             KeyStoreType::InMemory
         };
-        let _ = conn
-            .init(
-                params.config.as_str(),
-                params.blockchain_name.as_deref(),
-                params.use_callbacks_for_network,
-                params.ignore_cache,
-                keystore_type,
-            )
-            .await?;
-        Ok((conn, join_handle))
+        let init = conn.init(
+            params.config.as_str(),
+            params.blockchain_name.as_deref(),
+            params.use_callbacks_for_network,
+            params.ignore_cache,
+            keystore_type,
+        );
+        let init = async { with_timeout(params.connect_timeout, "init", "connect", init).await? };
+        timer.run(ConnectPhase::InitOptions, init).await?;
+        Ok((conn, join_handle, timer))
     }
 
     pub(crate) async fn connect_archive(
         params: &TonConnectionParams,
         callback: Arc<dyn TonConnectionCallback>,
-    ) -> Result<(TonConnection, JoinHandle<()>), TonClientError> {
+    ) -> Result<TimedConnection, TonClientError> {
         // connect to other node until it will be able to fetch the very first block
         loop {
-            let (conn, join_handle) = Self::connect_joinable(params, callback.clone()).await?;
+            let (conn, join_handle, mut timer) =
+                Self::connect_timed(params, callback.clone()).await?;
             let info = BlockId {
                 workchain: -1,
                 shard: i64::MIN,
                 seqno: 1,
             };
-            conn.sync().await?;
-            let r = conn.lookup_block(1, &info, 0, 0).await;
+            timer.run(ConnectPhase::ProofDownload, conn.sync()).await?;
+            let r = timer
+                .run(
+                    ConnectPhase::ArchiveCheck,
+                    conn.lookup_block(1, &info, 0, 0),
+                )
+                .await;
             if r.is_ok() {
-                break Ok((conn, join_handle));
+                break Ok((conn, join_handle, timer.into_phases()));
             } else {
                 log::info!("Dropping connection to non-archive node");
             }
@@ -161,28 +218,60 @@ impl TonConnection {
     pub(crate) async fn connect_healthy(
         params: &TonConnectionParams,
         callback: Arc<dyn TonConnectionCallback>,
-    ) -> Result<(TonConnection, JoinHandle<()>), TonClientError> {
+    ) -> Result<TimedConnection, TonClientError> {
         // connect to other node until it will be able to fetch the very first block
         loop {
-            let (conn, join_handle) =
-                TonConnection::connect_joinable(params, callback.clone()).await?;
-            let info_result = conn.get_masterchain_info().await;
-            match info_result {
-                Ok((_, info)) => {
-                    let block_result = conn.get_block_header(&info.last).await;
-                    if let Err(err) = block_result {
-                        log::info!("Dropping connection to unhealthy node: {:?}", err);
-                    } else {
-                        break Ok((conn, join_handle));
-                    }
-                }
-                Err(err) => {
-                    log::info!("Dropping connection to unhealthy node: {:?}", err);
-                }
+            let (conn, join_handle, mut timer) =
+                Self::connect_timed(params, callback.clone()).await?;
+            let health = async {
+                let info = conn.masterchain_info_cached().await?.info;
+                conn.get_block_header(&info.last).await
+            };
+            match timer.run(ConnectPhase::HealthCheck, health).await {
+                Ok(_) => break Ok((conn, join_handle, timer.into_phases())),
+                Err(err) => log::info!("Dropping connection to unhealthy node: {}", err),
             }
         }
     }
 
+    /// Creates a new initialized TonConnection without checks, returning durations of the phases
+    pub(crate) async fn connect_plain(
+        params: &TonConnectionParams,
+        callback: Arc<dyn TonConnectionCallback>,
+    ) -> Result<TimedConnection, TonClientError> {
+        let (conn, join_handle, mut timer) = Self::connect_timed(params, callback).await?;
+        timer
+            .run(
+                ConnectPhase::LiteserverHandshake,
+                conn.lite_server_get_info(),
+            )
+            .await?;
+        timer.run(ConnectPhase::ProofDownload, conn.sync()).await?;
+        Ok((conn, join_handle, timer.into_phases()))
+    }
+
+    /// Checks that the last masterchain block of the node is at most `max_lag` old.
+    ///
+    /// The age is measured against the time reported by the liteserver, so the skew
+    /// of the local clock doesn't affect the result. Returns `false` if any query fails.
+    pub async fn is_synced(&self, max_lag: Duration) -> bool {
+        match self.sync_lag().await {
+            Ok(lag) => lag <= max_lag,
+            Err(err) => {
+                log::info!("Failed to check sync state of node: {:?}", err);
+                false
+            }
+        }
+    }
+
+    /// Returns the age of the last masterchain block according to the liteserver time
+    pub async fn sync_lag(&self) -> Result<Duration, TonClientError> {
+        let info = self.masterchain_info_cached().await?.info;
+        let header = self.get_block_header(&info.last).await?;
+        let server_info = self.lite_server_get_info().await?;
+        Ok(block_lag(server_info.now, header.gen_utime))
+    }
+
     /// Attempts to initialize an existing TonConnection
     pub async fn init(
         &self,
@@ -238,6 +327,120 @@ impl TonConnection {
         }
     }
 
+    /// Invokes `function` and returns the response as received from tonlib.
+    ///
+    /// Intended for debugging only: the bytes are the JSON response of tonlib (not the wire TL
+    /// serialization), which is returned even if it contains an error.
+    pub async fn invoke_raw_bytes(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, Vec<u8>), TonClientError> {
+        let (raw_tx, raw_rx) = oneshot::channel::<Vec<u8>>();
+        let result = self.do_invoke(function, Some(raw_tx)).await;
+        match raw_rx.await {
+            Ok(raw) => Ok((self.clone(), raw)),
+            Err(_) => Err(result.err().unwrap_or_else(|| {
+                TonClientError::InternalError("Raw response is missing".to_string())
+            })),
+        }
+    }
+
+    /// Invokes `function` and returns the response body, leaving its deserialization to the caller
+    pub(crate) async fn invoke_body(
+        &self,
+        function: &TonFunction,
+    ) -> Result<Vec<u8>, TonClientError> {
+        let method: &'static str = function.into();
+        let (tx, rx) = oneshot::channel::<Vec<u8>>();
+        let data = RequestData {
+            method,
+            send_time: Instant::now(),
+            sender: None,
+            raw_sender: Some(tx),
+        };
+        let cnt = self.submit(function, data).await?;
+        let body = with_timeout(self.inner.read_timeout, method, "read", rx)
+            .await
+            .inspect_err(|_| {
+                // the late response is dropped instead of being passed to nobody
+                self.inner.remove_request(cnt);
+            })?;
+        body.map_err(|_| {
+            TonClientError::InternalError("Sender dropped without sending".to_string())
+        })
+    }
+
+    async fn do_invoke(
+        &self,
+        function: &TonFunction,
+        raw_sender: Option<oneshot::Sender<Vec<u8>>>,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        let method: &'static str = function.into();
+        let (tx, rx) = oneshot::channel::<Result<TonResult, TonClientError>>();
+        let data = RequestData {
+            method,
+            send_time: Instant::now(),
+            sender: Some(tx),
+            raw_sender,
+        };
+        let cnt = self.submit(function, data).await?;
+        let maybe_result = with_timeout(self.inner.read_timeout, method, "read", rx)
+            .await
+            .inspect_err(|_| {
+                // the late response is dropped instead of being passed to nobody
+                self.inner.remove_request(cnt);
+            })?;
+        let result = match maybe_result {
+            Ok(result) => result,
+            Err(_) => {
+                return Err(TonClientError::InternalError(
+                    "Sender dropped without sending".to_string(),
+                ));
+            }
+        };
+        result.map(|r| (self.clone(), r))
+    }
+
+    /// Registers the request and sends `function` within `write_timeout`, returning the request id.
+    ///
+    /// The timeout covers both waiting for `concurrency_limit` and the send itself. The request
+    /// is removed if the send fails or completes too late, so its response is dropped.
+    async fn submit(
+        &self,
+        function: &TonFunction,
+        data: RequestData,
+    ) -> Result<u32, TonClientError> {
+        let method = data.method;
+        let write_timeout = self.inner.write_timeout;
+        let started = Instant::now();
+        // take the semaphore to limit number of simultaneous invokes being processed
+        let _permit = with_timeout(write_timeout, method, "write", self.limit_rate()).await??;
+        let cnt = self.inner.counter.fetch_add(1, Ordering::SeqCst);
+        self.inner.insert_request(cnt, data);
+        let tag = self.inner.tl_client.get_tag();
+        self.inner.callback.on_invoke(tag, cnt, function);
+
+        if let Err(e) = self.inner.tl_client.send(function, &cnt.to_string()) {
+            let data = self.inner.remove_request(cnt).unwrap();
+            let duration = Instant::now().duration_since(data.send_time);
+            let res = Err(TonClientError::TlError(e));
+            self.inner
+                .callback
+                .on_invoke_result(tag, cnt, data.method, &duration, &res);
+            return res.map(|_| cnt);
+        }
+        // the send is blocking, so it can only be checked against the timeout once it returns
+        if !write_timeout.is_zero() && started.elapsed() > write_timeout {
+            self.inner.remove_request(cnt);
+            return Err(TonClientError::Timeout {
+                method,
+                stage: "write",
+                timeout: write_timeout,
+            });
+        }
+        Ok(cnt)
+    }
+
     async fn limit_rate(&self) -> Result<Option<SemaphorePermit>, TonClientError> {
         Ok(if let Some(semaphore) = &self.inner.semaphore {
             Some(
@@ -252,6 +455,11 @@ impl TonConnection {
     }
 }
 
+/// Time between `gen_utime` of a block and `now`, blocks from the future have zero lag
+fn block_lag(now: i64, gen_utime: i64) -> Duration {
+    Duration::from_secs(now.saturating_sub(gen_utime).max(0) as u64)
+}
+
 #[async_trait]
 impl TonClientInterface for TonConnection {
     async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
@@ -262,41 +470,7 @@ impl TonClientInterface for TonConnection {
         &self,
         function: &TonFunction,
     ) -> Result<(TonConnection, TonResult), TonClientError> {
-        self.limit_rate().await?; // take the semaphore to limit number of simultaneous invokes being processed
-        let cnt = self.inner.counter.fetch_add(1, Ordering::SeqCst);
-        let extra = cnt.to_string();
-        let (tx, rx) = oneshot::channel::<Result<TonResult, TonClientError>>();
-        let data = RequestData {
-            method: function.into(),
-            send_time: Instant::now(),
-            sender: tx,
-        };
-        self.inner.request_map.insert(cnt, data);
-        self.inner
-            .callback
-            .on_invoke(self.inner.tl_client.get_tag(), cnt, function);
-
-        let res = self.inner.tl_client.send(function, extra.as_str());
-        if let Err(e) = res {
-            let (_, data) = self.inner.request_map.remove(&cnt).unwrap();
-            let tag = self.inner.tl_client.get_tag();
-            let duration = Instant::now().duration_since(data.send_time);
-            let res = Err(TonClientError::TlError(e));
-            self.inner
-                .callback
-                .on_invoke_result(tag, cnt, data.method, &duration, &res);
-            data.sender.send(res).unwrap(); // Send should always succeed, so something went terribly wrong
-        }
-        let maybe_result = rx.await;
-        let result = match maybe_result {
-            Ok(result) => result,
-            Err(_) => {
-                return Err(TonClientError::InternalError(
-                    "Sender dropped without sending".to_string(),
-                ));
-            }
-        };
-        result.map(|r| (self.clone(), r))
+        self.do_invoke(function, None).await
     }
 }
 
@@ -309,44 +483,96 @@ impl Clone for TonConnection {
 
 static NOT_AVAILABLE: &str = "N/A";
 
+/// Awaits `future` for at most `timeout`, zero `timeout` means no limit
+pub(super) async fn with_timeout<F: Future>(
+    timeout: Duration,
+    method: &'static str,
+    stage: &'static str,
+    future: F,
+) -> Result<F::Output, TonClientError> {
+    if timeout.is_zero() {
+        return Ok(future.await);
+    }
+    rt::timeout(timeout, future)
+        .await
+        .map_err(|_| TonClientError::Timeout {
+            method,
+            stage,
+            timeout,
+        })
+}
+
 /// Client run loop
 fn run_loop(tag: String, weak_inner: Weak<Inner>, callback: Arc<dyn TonConnectionCallback>) {
     callback.on_connection_loop_start(&tag);
 
     loop {
         if let Some(inner) = weak_inner.upgrade() {
-            let recv = inner.tl_client.receive(1.0);
-            if let Some((ton_result, maybe_extra)) = recv {
+            let recv = inner.tl_client.receive_bytes(1.0);
+            if let Some(raw) = recv {
+                // The request has to be found before deserializing a response which may be
+                // left to the caller, so the header is read first only while there are such
+                // requests. Otherwise the response is deserialized in a single pass.
+                let (header, ton_result) = if inner.body_requests.load(Ordering::SeqCst) > 0 {
+                    (deserialize_header(&raw).ok(), None)
+                } else {
+                    let (ton_result, extra) = deserialize_response(&raw);
+                    let header = ResponseHeader {
+                        extra,
+                        result_type: None,
+                    };
+                    (Some(header), Some(ton_result))
+                };
+                let maybe_extra = header.as_ref().and_then(|h| h.extra.clone());
                 let maybe_request_id = if let Some(s) = &maybe_extra {
                     s.parse::<u32>().ok()
                 } else {
                     None
                 };
-                let maybe_data = maybe_request_id.and_then(|i| inner.request_map.remove(&i));
+                let maybe_data = maybe_request_id.and_then(|i| inner.remove_request(i));
+                let body_only = matches!(&maybe_data, Some(data) if data.sender.is_none());
+                let ton_result = match (ton_result, header) {
+                    (Some(ton_result), _) => ton_result,
+                    // The body is deserialized by the caller, so only errors are parsed here
+                    (None, Some(header)) if body_only && !header.is_error() => Ok(TonResult::Ok {}),
+                    (None, _) => deserialize_result_bytes(&raw),
+                };
                 let result: Result<TonResult, TonClientError> = match ton_result {
                     Ok(TonResult::Error { code, message }) => {
                         let method = maybe_data
                             .as_ref()
-                            .map(|d| d.1.method)
+                            .map(|d| d.method)
                             .unwrap_or(NOT_AVAILABLE);
                         Err(TonClientError::TonlibError {
                             method,
                             code,
                             message,
+                            extra: deserialize_error_extra(&raw),
                         })
                     }
                     Err(e) => Err(e.into()),
                     Ok(r) => Ok(r),
                 };
 
-                if let Some((_, data)) = maybe_data {
+                if let Some(data) = maybe_data {
                     // Found corresponding request, reply to it
                     let request_id = maybe_request_id.unwrap(); // Can't be empty if data is not empty
                     let now = Instant::now();
                     let duration = now.duration_since(data.send_time);
                     callback.on_invoke_result(&tag, request_id, data.method, &duration, &result);
 
-                    if data.sender.send(result).is_err() {
+                    let delivered = match data.sender {
+                        Some(sender) => {
+                            if let Some(raw_sender) = data.raw_sender {
+                                let _ = raw_sender.send(raw);
+                            }
+                            sender.send(result).is_ok()
+                        }
+                        None => data
+                            .raw_sender
+                            .is_some_and(|raw_sender| raw_sender.send(raw).is_ok()),
+                    };
+                    if !delivered {
                         callback.on_cancelled_invoke(&tag, request_id, data.method, &duration);
                     }
                 } else {
@@ -372,3 +598,17 @@ fn run_loop(tag: String, weak_inner: Weak<Inner>, callback: Arc<dyn TonConnectio
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_lag() {
+        assert_eq!(
+            block_lag(1_700_000_030, 1_700_000_000),
+            Duration::from_secs(30)
+        );
+        assert_eq!(block_lag(1_700_000_000, 1_700_000_005), Duration::ZERO);
+    }
+}
@@ -0,0 +1,235 @@
+use std::time::Duration;
+
+use futures::stream::{self, StreamExt};
+use tonlib_core::TonAddress;
+
+use crate::client::{TonClientError, TonClientInterface};
+use crate::rt;
+use crate::tl::{
+    AccountAddress, BlockIdExt, RawFullAccountState, TonFunction, TonResult, TonResultDiscriminants,
+};
+
+/// Max number of account states requested at once
+pub const CONSISTENT_STATES_CONCURRENCY: usize = 16;
+/// Attempts to read a state whose shard block isn't available yet
+const UNAVAILABLE_ATTEMPTS: usize = 3;
+const UNAVAILABLE_RETRY_INTERVAL: Duration = Duration::from_millis(100);
+/// Liteserver error code of a block which isn't applied yet
+const LITE_SERVER_NOTREADY: i32 = 651;
+
+/// State of an account read at the pinned masterchain block
+#[derive(Debug)]
+pub enum PinnedAccountState {
+    Loaded(RawFullAccountState),
+    /// The state couldn't be read at the pinned block, e.g. because the shard block
+    /// isn't available yet on the liteserver
+    Unavailable(TonClientError),
+}
+
+/// Account states read as of the same masterchain block
+#[derive(Debug)]
+pub struct ConsistentStates {
+    /// Masterchain block all the states are read at
+    pub block: BlockIdExt,
+    /// States in the order of requested addresses
+    pub states: Vec<(TonAddress, PinnedAccountState)>,
+}
+
+impl ConsistentStates {
+    /// Returns loaded states, skipping unavailable ones
+    pub fn loaded(&self) -> impl Iterator<Item = (&TonAddress, &RawFullAccountState)> {
+        self.states
+            .iter()
+            .filter_map(|(address, state)| match state {
+                PinnedAccountState::Loaded(state) => Some((address, state)),
+                PinnedAccountState::Unavailable(_) => None,
+            })
+    }
+
+    pub fn unavailable(&self) -> impl Iterator<Item = (&TonAddress, &TonClientError)> {
+        self.states
+            .iter()
+            .filter_map(|(address, state)| match state {
+                PinnedAccountState::Unavailable(error) => Some((address, error)),
+                PinnedAccountState::Loaded(_) => None,
+            })
+    }
+}
+
+pub(crate) async fn get_account_states_at_block<C: TonClientInterface + ?Sized>(
+    client: &C,
+    addresses: &[TonAddress],
+    block: Option<&BlockIdExt>,
+) -> Result<ConsistentStates, TonClientError> {
+    let block = match block {
+        Some(block) => block.clone(),
+        None => client.masterchain_info_cached().await?.info.last,
+    };
+    let states = stream::iter(addresses.iter().cloned())
+        .map(|address| get_pinned_state(client, address, &block))
+        .buffered(CONSISTENT_STATES_CONCURRENCY)
+        .collect()
+        .await;
+    Ok(ConsistentStates { block, states })
+}
+
+async fn get_pinned_state<C: TonClientInterface + ?Sized>(
+    client: &C,
+    address: TonAddress,
+    block: &BlockIdExt,
+) -> (TonAddress, PinnedAccountState) {
+    let state = match get_state_with_retries(client, &address, block).await {
+        Ok(state) => PinnedAccountState::Loaded(state),
+        Err(error) => {
+            log::warn!(
+                "State of {} is unavailable at block {}: {}",
+                address,
+                block.seqno,
+                error
+            );
+            PinnedAccountState::Unavailable(error)
+        }
+    };
+    (address, state)
+}
+
+async fn get_state_with_retries<C: TonClientInterface + ?Sized>(
+    client: &C,
+    address: &TonAddress,
+    block: &BlockIdExt,
+) -> Result<RawFullAccountState, TonClientError> {
+    let mut attempt = 1;
+    loop {
+        match get_state_at_block(client, address, block).await {
+            Err(error) if attempt < UNAVAILABLE_ATTEMPTS && is_not_ready_error(&error) => {
+                attempt += 1;
+                rt::sleep(UNAVAILABLE_RETRY_INTERVAL).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+async fn get_state_at_block<C: TonClientInterface + ?Sized>(
+    client: &C,
+    address: &TonAddress,
+    block: &BlockIdExt,
+) -> Result<RawFullAccountState, TonClientError> {
+    let func = TonFunction::WithBlock {
+        id: block.clone(),
+        function: Box::new(TonFunction::RawGetAccountState {
+            account_address: AccountAddress {
+                account_address: address.to_hex(),
+            },
+        }),
+    };
+    match client.invoke(&func).await? {
+        TonResult::RawFullAccountState(state) => Ok(state),
+        r => Err(TonClientError::unexpected_ton_result(
+            TonResultDiscriminants::RawFullAccountState,
+            r,
+        )),
+    }
+}
+
+fn is_not_ready_error(error: &TonClientError) -> bool {
+    match error {
+        TonClientError::TonlibError { code, message, .. } => {
+            *code == LITE_SERVER_NOTREADY || *code == 500 || message.contains("NOTREADY")
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, mc_info, tonlib_error, MockClient};
+
+    /// Client serving states of accounts at any block, with scripted failures per account
+    /// and the log of requested blocks
+    fn mock_client(failures: HashMap<String, usize>) -> (MockClient, Arc<Mutex<Vec<BlockIdExt>>>) {
+        let failures = Mutex::new(failures);
+        let requested_blocks = Arc::new(Mutex::new(vec![]));
+        let requested = requested_blocks.clone();
+        let client = MockClient::new(move |function| {
+            let (id, account_address) = match function {
+                TonFunction::BlocksGetMasterchainInfo {} => {
+                    return Ok(TonResult::BlocksMasterchainInfo(mc_info(42)))
+                }
+                TonFunction::WithBlock { id, function } => match function.as_ref() {
+                    TonFunction::RawGetAccountState { account_address } => (id, account_address),
+                    f => panic!("Unexpected function: {:?}", f),
+                },
+                f => panic!("Unexpected function: {:?}", f),
+            };
+            requested.lock().unwrap().push(id.clone());
+            if let Some(left) = failures
+                .lock()
+                .unwrap()
+                .get_mut(&account_address.account_address)
+            {
+                if *left > 0 {
+                    *left -= 1;
+                    return Err(tonlib_error(
+                        function,
+                        LITE_SERVER_NOTREADY,
+                        "LITE_SERVER_NOTREADY: block is not applied",
+                    ));
+                }
+            }
+            Ok(TonResult::RawFullAccountState(RawFullAccountState {
+                balance: id.seqno as i64,
+                ..account_state(id)
+            }))
+        });
+        (client, requested_blocks)
+    }
+
+    #[tokio::test]
+    async fn test_states_are_read_at_pinned_block() -> anyhow::Result<()> {
+        let addresses: Vec<TonAddress> = (0..20u8).map(|i| TonAddress::new(0, &[i; 32])).collect();
+        // the first address becomes available after a retry, the second one never does
+        let failures = HashMap::from([
+            (addresses[3].to_hex(), 1),
+            (addresses[7].to_hex(), UNAVAILABLE_ATTEMPTS),
+        ]);
+        let (client, requested_blocks) = mock_client(failures);
+        let pinned = mc_block(42);
+
+        let result = client
+            .get_account_states_at_block(&addresses, Some(&pinned))
+            .await?;
+
+        assert_eq!(result.block, pinned);
+        assert_eq!(result.states.len(), 20);
+        for ((address, _), expected) in result.states.iter().zip(addresses.iter()) {
+            assert_eq!(address, expected);
+        }
+        assert_eq!(result.loaded().count(), 19);
+        assert!(result.loaded().all(|(_, state)| state.block_id == pinned));
+        let unavailable: Vec<_> = result.unavailable().map(|(a, _)| a.clone()).collect();
+        assert_eq!(unavailable, vec![addresses[7].clone()]);
+
+        let requested = requested_blocks.lock().unwrap();
+        assert_eq!(requested.len(), 20 + 1 + (UNAVAILABLE_ATTEMPTS - 1));
+        assert!(requested.iter().all(|id| id == &pinned));
+        Ok(())
+    }
+    #[tokio::test]
+    async fn test_states_are_read_at_latest_block() -> anyhow::Result<()> {
+        let addresses: Vec<TonAddress> = (0..3u8).map(|i| TonAddress::new(0, &[i; 32])).collect();
+        let (client, requested_blocks) = mock_client(HashMap::new());
+
+        let result = client.get_account_states_at_block(&addresses, None).await?;
+
+        // masterchain info comes from `masterchain_info_cached` in a single request
+        assert_eq!(result.block, mc_block(42));
+        assert_eq!(client.calls(), 1 + addresses.len());
+        assert_eq!(requested_blocks.lock().unwrap().len(), addresses.len());
+        Ok(())
+    }
+}
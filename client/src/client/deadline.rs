@@ -0,0 +1,184 @@
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use super::connection::with_timeout;
+use crate::client::{InvokeOptions, TonClientError, TonClientInterface, TonConnection};
+use crate::tl::{TonFunction, TonResult};
+
+/// Point in time by which an operation must complete
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline {
+    instant: Instant,
+}
+
+impl Deadline {
+    pub fn at(instant: Instant) -> Deadline {
+        Deadline { instant }
+    }
+
+    /// Deadline `timeout` from now
+    pub fn from_timeout(timeout: Duration) -> Deadline {
+        Self::at(Instant::now() + timeout)
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.instant
+    }
+
+    /// Time left until the deadline, zero if it has passed
+    pub fn remaining(&self) -> Duration {
+        self.instant.saturating_duration_since(Instant::now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining().is_zero()
+    }
+
+    /// Returns the time left for a call of `method`, fails if there is none.
+    pub fn call_timeout(&self, method: &'static str) -> Result<Duration, TonClientError> {
+        let remaining = self.remaining();
+        if remaining.is_zero() {
+            return Err(TonClientError::DeadlineExceeded { method });
+        }
+        Ok(remaining)
+    }
+}
+
+/// Client applying `deadline` to every call made through it.
+///
+/// Wrap a client into it before passing to high-level helpers (e.g. `WalletSequencer`,
+/// `AccountHistorySyncer` or functions of `TonClientInterface` making several calls),
+/// so that the whole operation fits into the caller budget: each nested call is limited
+/// by the remaining time, and calls aren't issued at all once the deadline has passed.
+///
+/// Note that tokio timeouts wrapping the caller future can't be observed by the client,
+/// so they should be converted with `Deadline::from_timeout` explicitly.
+pub struct DeadlineClient<C> {
+    client: C,
+    deadline: Deadline,
+}
+
+impl<C: TonClientInterface> DeadlineClient<C> {
+    pub fn new(client: C, deadline: Deadline) -> DeadlineClient<C> {
+        DeadlineClient { client, deadline }
+    }
+
+    pub fn deadline(&self) -> Deadline {
+        self.deadline
+    }
+
+    pub fn into_inner(self) -> C {
+        self.client
+    }
+}
+
+#[async_trait]
+impl<C: TonClientInterface> TonClientInterface for DeadlineClient<C> {
+    async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+        let timeout = self.deadline.call_timeout("get_connection")?;
+        with_timeout(
+            timeout,
+            "get_connection",
+            "connect",
+            self.client.get_connection(),
+        )
+        .await?
+    }
+
+    async fn invoke_on_connection(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        let method = function.into();
+        let timeout = self.deadline.call_timeout(method)?;
+        with_timeout(
+            timeout,
+            method,
+            "call",
+            self.client.invoke_on_connection(function),
+        )
+        .await?
+    }
+
+    async fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+        let method = function.into();
+        let timeout = self.deadline.call_timeout(method)?;
+        with_timeout(timeout, method, "call", self.client.invoke(function)).await?
+    }
+
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        let deadline = match options.deadline {
+            Some(deadline) => deadline.min(self.deadline),
+            None => self.deadline,
+        };
+        let options = options.clone().with_deadline(deadline);
+        self.client.invoke_with_options(function, &options).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlib_core::TonAddress;
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, MockClient};
+    use crate::rt;
+
+    /// Helper polling the account three times with pauses in between
+    async fn poll_three_times<C: TonClientInterface>(
+        client: &C,
+        pause: Duration,
+    ) -> Result<(), TonClientError> {
+        for step in 0..3 {
+            if step > 0 {
+                rt::sleep(pause).await;
+            }
+            client.get_raw_account_state(&TonAddress::NULL).await?;
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_stops_nested_calls() {
+        let counting =
+            MockClient::new(|_| Ok(TonResult::RawFullAccountState(account_state(&mc_block(1)))));
+        // steps are issued at 0ms, 100ms and 200ms
+        let client = DeadlineClient::new(
+            counting.clone(),
+            Deadline::from_timeout(Duration::from_millis(150)),
+        );
+        let result = poll_three_times(&client, Duration::from_millis(100)).await;
+        assert!(matches!(
+            result,
+            Err(TonClientError::DeadlineExceeded {
+                method: "RawGetAccountState"
+            })
+        ));
+        assert_eq!(counting.calls(), 2);
+    }
+
+    #[test]
+    fn test_invoke_options_deadline() -> Result<(), TonClientError> {
+        let options = InvokeOptions::no_retry().with_timeout(Duration::from_secs(1));
+        assert_eq!(options.call_timeout("test")?, Duration::from_secs(1));
+
+        let options = options.with_deadline(Deadline::from_timeout(Duration::from_millis(500)));
+        assert!(options.call_timeout("test")? <= Duration::from_millis(500));
+
+        let options = InvokeOptions::default().with_deadline(Deadline::at(Instant::now()));
+        assert!(matches!(
+            options.call_timeout("test"),
+            Err(TonClientError::DeadlineExceeded { method: "test" })
+        ));
+        assert_eq!(
+            InvokeOptions::default().call_timeout("test")?,
+            Duration::ZERO
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,450 @@
+use std::fmt;
+use std::future::Future;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine;
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use super::recent_init_block::lite::Connection;
+use crate::client::{TonClient, TonClientError};
+use crate::config::{LiteEndpoint, TonConfig};
+
+pub const DEFAULT_DIAGNOSTICS_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+pub const DEFAULT_DIAGNOSTICS_MAX_LAG: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticsOptions {
+    /// Timeout of every single probe step (TCP connect, handshake, request)
+    pub probe_timeout: Duration,
+    /// Servers whose last block is older than the freshest one by more than this are flagged as lagging
+    pub max_lag: Duration,
+}
+
+impl Default for DiagnosticsOptions {
+    fn default() -> Self {
+        DiagnosticsOptions {
+            probe_timeout: DEFAULT_DIAGNOSTICS_PROBE_TIMEOUT,
+            max_lag: DEFAULT_DIAGNOSTICS_MAX_LAG,
+        }
+    }
+}
+
+/// Raw result of probing a single liteserver. Hashes are base64 encoded, as in the global config.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiteserverProbe {
+    pub address: String,
+    pub key: String,
+    pub tcp_reachable: bool,
+    pub adnl_handshake: bool,
+    pub last_seqno: Option<u32>,
+    pub last_utime: Option<u32>,
+    pub zero_state_hash: Option<String>,
+    pub init_block_hash: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LiteserverIssue {
+    Unreachable,
+    HandshakeFailed,
+    RequestFailed,
+    WrongNetwork,
+    InitBlockMismatch,
+    Lagging { lag_secs: u64 },
+}
+
+impl fmt::Display for LiteserverIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LiteserverIssue::Unreachable => write!(f, "unreachable"),
+            LiteserverIssue::HandshakeFailed => write!(f, "handshake failed"),
+            LiteserverIssue::RequestFailed => write!(f, "request failed"),
+            LiteserverIssue::WrongNetwork => write!(f, "wrong network"),
+            LiteserverIssue::InitBlockMismatch => write!(f, "init block mismatch"),
+            LiteserverIssue::Lagging { lag_secs } => write!(f, "lagging by {}s", lag_secs),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiteserverDiagnostics {
+    pub probe: LiteserverProbe,
+    /// Lag behind the freshest server of the same network, in seconds
+    pub lag_secs: Option<u64>,
+    pub issues: Vec<LiteserverIssue>,
+}
+
+impl LiteserverDiagnostics {
+    pub fn is_healthy(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Result of [`diagnose_config`]
+///
+/// `config_*_matches` fields are `None` when no server reported the corresponding value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiagnosticsReport {
+    pub liteservers: Vec<LiteserverDiagnostics>,
+    /// Zero state root hash reported by the majority of servers
+    pub zero_state_hash: Option<String>,
+    pub config_zero_state_matches: Option<bool>,
+    /// Init block root hash reported by the majority of servers
+    pub init_block_hash: Option<String>,
+    pub config_init_block_matches: Option<bool>,
+    pub max_seqno: Option<u32>,
+}
+
+impl DiagnosticsReport {
+    pub fn healthy_count(&self) -> usize {
+        self.liteservers.iter().filter(|l| l.is_healthy()).count()
+    }
+
+    /// Returns `true` if every server is healthy and the config agrees with the network.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy_count() == self.liteservers.len()
+            && self.config_zero_state_matches == Some(true)
+            && self.config_init_block_matches != Some(false)
+    }
+}
+
+impl fmt::Display for DiagnosticsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} liteservers healthy, zero state: {}, init block: {}",
+            self.healthy_count(),
+            self.liteservers.len(),
+            match_status(self.config_zero_state_matches),
+            match_status(self.config_init_block_matches),
+        )?;
+        if let Some(seqno) = self.max_seqno {
+            write!(f, ", last seqno: {}", seqno)?;
+        }
+        for liteserver in self.liteservers.iter().filter(|l| !l.is_healthy()) {
+            let issues: Vec<String> = liteserver.issues.iter().map(|i| i.to_string()).collect();
+            write!(f, "\n  {}: {}", liteserver.probe.address, issues.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn match_status(matches: Option<bool>) -> &'static str {
+    match matches {
+        Some(true) => "ok",
+        Some(false) => "MISMATCH",
+        None => "unknown",
+    }
+}
+
+impl TonClient {
+    /// Probes every liteserver of the config, see [`diagnose_config`].
+    pub async fn diagnose(config: &TonConfig, opts: &DiagnosticsOptions) -> DiagnosticsReport {
+        diagnose_config(config, opts).await
+    }
+
+    /// Same as [`TonClient::diagnose`], but takes the config as JSON.
+    pub async fn diagnose_json(
+        config: &str,
+        opts: &DiagnosticsOptions,
+    ) -> Result<DiagnosticsReport, TonClientError> {
+        let ton_config = TonConfig::from_json(config).map_err(|e| {
+            let msg = format!("Fail to parse config: {}", e);
+            TonClientError::InternalError(msg)
+        })?;
+        Ok(Self::diagnose(&ton_config, opts).await)
+    }
+}
+
+/// Concurrently probes every liteserver of the config (TCP reachability, ADNL handshake,
+/// last masterchain block) and checks that the servers agree with each other and with the config.
+///
+/// Doesn't require a running `TonClient`, so it can be used to validate a config before startup.
+pub async fn diagnose_config(config: &TonConfig, opts: &DiagnosticsOptions) -> DiagnosticsReport {
+    let init_seqno = config.get_init_block_seqno() as u32;
+    let probes = join_all(
+        config
+            .liteservers
+            .iter()
+            .map(|endpoint| probe_liteserver(endpoint.clone(), init_seqno, opts.probe_timeout)),
+    )
+    .await;
+    build_report(
+        probes,
+        config.zero_state_root_hash(),
+        config.init_block_root_hash(),
+        opts,
+    )
+}
+
+async fn probe_liteserver(
+    endpoint: LiteEndpoint,
+    init_seqno: u32,
+    timeout: Duration,
+) -> LiteserverProbe {
+    let mut probe = LiteserverProbe {
+        address: format!("{}:{}", Ipv4Addr::from(endpoint.ip as u32), endpoint.port),
+        key: endpoint.id.key.clone(),
+        ..Default::default()
+    };
+    let mut conn = match Connection::new(endpoint) {
+        Ok(conn) => conn,
+        Err(e) => {
+            probe.error = Some(e.to_string());
+            return probe;
+        }
+    };
+
+    if let Err(e) = with_timeout(timeout, conn.probe_tcp()).await {
+        probe.error = Some(e.to_string());
+        return probe;
+    }
+    probe.tcp_reachable = true;
+
+    if let Err(e) = with_timeout(timeout, conn.handshake()).await {
+        probe.error = Some(e.to_string());
+        return probe;
+    }
+    probe.adnl_handshake = true;
+
+    match with_timeout(timeout, conn.get_mc_info_ext()).await {
+        Ok(info) => {
+            probe.last_seqno = Some(info.last.seqno);
+            probe.last_utime = Some(info.last_utime);
+            probe.zero_state_hash = Some(BASE64_STANDARD.encode(info.init.root_hash.0));
+        }
+        Err(e) => {
+            probe.error = Some(e.to_string());
+            return probe;
+        }
+    }
+
+    // old init blocks may be unavailable on non-archive servers, so this is not treated as an issue
+    match with_timeout(timeout, conn.get_mc_header(init_seqno)).await {
+        Ok(header) => probe.init_block_hash = Some(BASE64_STANDARD.encode(header.id.root_hash.0)),
+        Err(e) => log::warn!(
+            "Failed to lookup init block {} on {}: {}",
+            init_seqno,
+            probe.address,
+            e
+        ),
+    }
+    probe
+}
+
+async fn with_timeout<T, F>(timeout: Duration, future: F) -> anyhow::Result<T>
+where
+    F: Future<Output = anyhow::Result<T>>,
+{
+    crate::rt::timeout(timeout, future).await?
+}
+
+fn build_report(
+    probes: Vec<LiteserverProbe>,
+    config_zero_state: Option<&str>,
+    config_init_block: Option<&str>,
+    opts: &DiagnosticsOptions,
+) -> DiagnosticsReport {
+    let zero_state_hash = majority(probes.iter().filter_map(|p| p.zero_state_hash.as_deref()));
+    let same_network =
+        |p: &&LiteserverProbe| zero_state_hash.is_some() && p.zero_state_hash == zero_state_hash;
+    let init_block_hash = majority(
+        probes
+            .iter()
+            .filter(same_network)
+            .filter_map(|p| p.init_block_hash.as_deref()),
+    );
+    let freshest_utime = probes
+        .iter()
+        .filter(same_network)
+        .filter_map(|p| p.last_utime)
+        .max();
+    let max_seqno = probes
+        .iter()
+        .filter(same_network)
+        .filter_map(|p| p.last_seqno)
+        .max();
+
+    let liteservers = probes
+        .iter()
+        .map(|probe| {
+            let mut issues = vec![];
+            let mut lag_secs = None;
+            if !probe.tcp_reachable {
+                issues.push(LiteserverIssue::Unreachable);
+            } else if !probe.adnl_handshake {
+                issues.push(LiteserverIssue::HandshakeFailed);
+            } else if probe.zero_state_hash.is_none() {
+                issues.push(LiteserverIssue::RequestFailed);
+            } else if !same_network(&probe) {
+                issues.push(LiteserverIssue::WrongNetwork);
+            } else {
+                if probe.init_block_hash.is_some() && probe.init_block_hash != init_block_hash {
+                    issues.push(LiteserverIssue::InitBlockMismatch);
+                }
+                if let (Some(freshest), Some(utime)) = (freshest_utime, probe.last_utime) {
+                    let lag = freshest.saturating_sub(utime) as u64;
+                    lag_secs = Some(lag);
+                    if lag > opts.max_lag.as_secs() {
+                        issues.push(LiteserverIssue::Lagging { lag_secs: lag });
+                    }
+                }
+            }
+            LiteserverDiagnostics {
+                probe: probe.clone(),
+                lag_secs,
+                issues,
+            }
+        })
+        .collect();
+
+    DiagnosticsReport {
+        liteservers,
+        config_zero_state_matches: zero_state_hash
+            .as_deref()
+            .map(|hash| config_zero_state == Some(hash)),
+        zero_state_hash,
+        config_init_block_matches: init_block_hash
+            .as_deref()
+            .map(|hash| config_init_block == Some(hash)),
+        init_block_hash,
+        max_seqno,
+    }
+}
+
+/// Returns the most frequent value, preferring the first seen one on ties.
+fn majority<'a>(values: impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut counts: Vec<(&str, usize)> = vec![];
+    for value in values {
+        match counts.iter_mut().find(|(v, _)| *v == value) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((value, 1)),
+        }
+    }
+    let mut best: Option<(&str, usize)> = None;
+    for (value, count) in counts {
+        if best.map(|(_, c)| count > c).unwrap_or(true) {
+            best = Some((value, count));
+        }
+    }
+    best.map(|(value, _)| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAINNET_ZERO_STATE: &str = "F6OpKZKqvqeFp6CQmFomXNMfMj2EnaUSOXN+Mh+wVWk=";
+    const TESTNET_ZERO_STATE: &str = "gj+B8wb/AmlPk1z1AhVI484rhrUpgSr2oSFIh56VoSg=";
+    const INIT_BLOCK: &str = "YRkrcmZMvLBvjanwKCyL3w4oceGPtFfgx8ym1QKCK/4=";
+
+    fn probe(address: &str, seqno: u32, utime: u32, zero_state: &str) -> LiteserverProbe {
+        LiteserverProbe {
+            address: address.to_string(),
+            key: format!("key-{}", address),
+            tcp_reachable: true,
+            adnl_handshake: true,
+            last_seqno: Some(seqno),
+            last_utime: Some(utime),
+            zero_state_hash: Some(zero_state.to_string()),
+            init_block_hash: Some(INIT_BLOCK.to_string()),
+            error: None,
+        }
+    }
+
+    fn mock_probes() -> Vec<LiteserverProbe> {
+        let unreachable = LiteserverProbe {
+            address: "10.0.0.5:4924".to_string(),
+            error: Some("connection refused".to_string()),
+            ..Default::default()
+        };
+        let mut wrong_network = probe("10.0.0.4:4924", 100, 1700000000, TESTNET_ZERO_STATE);
+        wrong_network.init_block_hash = None;
+        vec![
+            probe("10.0.0.1:4924", 1000, 1700000100, MAINNET_ZERO_STATE),
+            probe("10.0.0.2:4924", 999, 1700000095, MAINNET_ZERO_STATE),
+            probe("10.0.0.3:4924", 900, 1700000000, MAINNET_ZERO_STATE),
+            wrong_network,
+            unreachable,
+        ]
+    }
+
+    #[test]
+    fn test_build_report() {
+        let opts = DiagnosticsOptions::default();
+        let report = build_report(
+            mock_probes(),
+            Some(MAINNET_ZERO_STATE),
+            Some(INIT_BLOCK),
+            &opts,
+        );
+        assert_eq!(report.zero_state_hash.as_deref(), Some(MAINNET_ZERO_STATE));
+        assert_eq!(report.config_zero_state_matches, Some(true));
+        assert_eq!(report.config_init_block_matches, Some(true));
+        assert_eq!(report.max_seqno, Some(1000));
+        assert_eq!(report.healthy_count(), 2);
+        assert!(!report.is_healthy());
+
+        let issues: Vec<_> = report.liteservers.iter().map(|l| &l.issues).collect();
+        assert!(issues[0].is_empty());
+        assert!(issues[1].is_empty());
+        assert_eq!(issues[2], &vec![LiteserverIssue::Lagging { lag_secs: 100 }]);
+        assert_eq!(issues[3], &vec![LiteserverIssue::WrongNetwork]);
+        assert_eq!(issues[4], &vec![LiteserverIssue::Unreachable]);
+        assert_eq!(report.liteservers[1].lag_secs, Some(5));
+        assert_eq!(report.liteservers[3].lag_secs, None);
+
+        let summary = report.to_string();
+        assert!(summary.starts_with(
+            "2/5 liteservers healthy, zero state: ok, init block: ok, last seqno: 1000"
+        ));
+        assert!(summary.contains("10.0.0.3:4924: lagging by 100s"));
+        assert!(summary.contains("10.0.0.4:4924: wrong network"));
+        assert!(summary.contains("10.0.0.5:4924: unreachable"));
+    }
+
+    #[test]
+    fn test_build_report_config_mismatch() {
+        let opts = DiagnosticsOptions {
+            max_lag: Duration::from_secs(200),
+            ..Default::default()
+        };
+        let mut probes = mock_probes();
+        probes[1].init_block_hash = Some(MAINNET_ZERO_STATE.to_string());
+        let report = build_report(probes, Some(TESTNET_ZERO_STATE), None, &opts);
+        assert_eq!(report.config_zero_state_matches, Some(false));
+        assert_eq!(report.config_init_block_matches, Some(false));
+        assert_eq!(report.healthy_count(), 2);
+        assert_eq!(
+            report.liteservers[1].issues,
+            vec![LiteserverIssue::InitBlockMismatch]
+        );
+        assert!(report.to_string().contains("zero state: MISMATCH"));
+
+        let report = build_report(vec![], None, None, &opts);
+        assert_eq!(report.config_zero_state_matches, None);
+        assert!(report.to_string().starts_with("0/0 liteservers healthy"));
+    }
+
+    #[test]
+    fn test_report_serde() -> anyhow::Result<()> {
+        let report = build_report(
+            mock_probes(),
+            Some(MAINNET_ZERO_STATE),
+            Some(INIT_BLOCK),
+            &DiagnosticsOptions::default(),
+        );
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(
+            json["liteservers"][2]["issues"][0],
+            serde_json::json!({"kind": "lagging", "lag_secs": 100})
+        );
+        let restored: DiagnosticsReport = serde_json::from_value(json)?;
+        assert_eq!(restored, report);
+        Ok(())
+    }
+}
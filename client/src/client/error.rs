@@ -1,8 +1,12 @@
 use std::io;
+use std::time::Duration;
 
 use thiserror::Error;
-use tonlib_core::TonAddressParseError;
+use tonlib_core::cell::TonCellError;
+use tonlib_core::types::Coins;
+use tonlib_core::{TonAddress, TonAddressParseError};
 
+use crate::client::{ConnectPhase, ConnectPhaseTiming};
 use crate::tl::{TlError, TonResult, TonResultDiscriminants};
 
 #[derive(Error, Debug)]
@@ -10,13 +14,72 @@ pub enum TonClientError {
     #[error("Internal error ({0})")]
     InternalError(String),
 
+    /// Error returned by tonlib. Construct it with `TonClientError::tonlib_error`.
     #[error("Tonlib error (Method: {method}, code: {code}, message: {message})")]
+    #[non_exhaustive]
     TonlibError {
         method: &'static str,
         code: i32,
         message: String,
+        /// Fields of the error response besides code & message, if any
+        extra: Option<serde_json::Value>,
     },
 
+    #[error("Timeout (Method: {method}, stage: {stage}, timeout: {timeout:?})")]
+    Timeout {
+        method: &'static str,
+        stage: &'static str,
+        timeout: Duration,
+    },
+
+    #[error("Bounceable message to uninitialized account {destination} would bounce back")]
+    BounceToUninit { destination: TonAddress },
+
+    #[error(
+        "Non-bounceable message to uninitialized account {destination} would leave funds on it"
+    )]
+    NonBounceableToUninit { destination: TonAddress },
+
+    #[error("Workchain of {address} is not enabled in the network")]
+    WorkchainNotEnabled { address: TonAddress },
+
+    #[error("Extra currency {currency_id} is not registered in the network")]
+    ExtraCurrencyNotRegistered { currency_id: u32 },
+
+    #[error("Insufficient balance (balance: {balance}, required: {required})")]
+    InsufficientBalance { balance: Coins, required: Coins },
+
+    #[error("Deadline exceeded before the call (Method: {method})")]
+    DeadlineExceeded { method: &'static str },
+
+    #[error("Response is too large (Method: {method}, size: {size}, limit: {limit})")]
+    ResponseTooLarge {
+        method: &'static str,
+        size: usize,
+        limit: usize,
+    },
+
+    #[error(
+        "Connect failed (phase: {phase:?}, elapsed: {elapsed:?}, completed phases: {phases:?}): {source}"
+    )]
+    ConnectFailed {
+        phase: ConnectPhase,
+        elapsed: Duration,
+        /// Phases completed before `phase`
+        phases: Vec<ConnectPhaseTiming>,
+        #[source]
+        source: Box<TonClientError>,
+    },
+
+    #[error("Retry budget exhausted, last error: {0}")]
+    RetryBudgetExhausted(#[source] Box<TonClientError>),
+
+    #[error("Waiting for masterchain seqno is not supported by the client")]
+    MasterchainWaitUnsupported,
+
+    #[error("Function is not supported by fallback client (Method: {method})")]
+    UnsupportedOnFallback { method: &'static str },
+
     #[error("Unexpected TonResult (Actual: {actual}, expected: {expected})")]
     UnexpectedTonResult {
         actual: TonResultDiscriminants,
@@ -34,6 +97,16 @@ pub enum TonClientError {
 }
 
 impl TonClientError {
+    /// `TonlibError` without extra fields
+    pub fn tonlib_error(method: &'static str, code: i32, message: &str) -> TonClientError {
+        TonClientError::TonlibError {
+            method,
+            code,
+            message: message.to_string(),
+            extra: None,
+        }
+    }
+
     pub fn unexpected_ton_result(
         expected: TonResultDiscriminants,
         actual: TonResult,
@@ -44,3 +117,15 @@ impl TonClientError {
         }
     }
 }
+
+/// Maps errors of parsing cells received from the network to `TonClientError::InternalError`
+pub(crate) trait MapParseError<R> {
+    /// `what` names the parsed object in the error message, e.g. `"config param 12"`
+    fn map_parse_error(self, what: &str) -> Result<R, TonClientError>;
+}
+
+impl<R> MapParseError<R> for Result<R, TonCellError> {
+    fn map_parse_error(self, what: &str) -> Result<R, TonClientError> {
+        self.map_err(|e| TonClientError::InternalError(format!("Failed to parse {}: {}", what, e)))
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FAILOVER_THRESHOLD: u32 = 3;
+pub const DEFAULT_FAILOVER_WINDOW: Duration = Duration::from_secs(60);
+
+/// Parameters of switching `TonClient` to the fallback config, see `TonClient::with_failover`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FailoverParams {
+    /// Number of consecutive failures of the primary config which activates the fallback one
+    pub failover_threshold: u32,
+    /// Failures are counted only if all of them happen within this window
+    pub failover_window: Duration,
+}
+
+impl Default for FailoverParams {
+    fn default() -> Self {
+        FailoverParams {
+            failover_threshold: DEFAULT_FAILOVER_THRESHOLD,
+            failover_window: DEFAULT_FAILOVER_WINDOW,
+        }
+    }
+}
+
+/// Consecutive failures of the primary pool, counted from the first one in the window
+struct FailureWindow {
+    count: u32,
+    started_at: Option<Instant>,
+}
+
+/// Tracks failures of the primary pool and decides when to activate the fallback one.
+///
+/// Failover is one-way: once activated, the fallback pool serves all the requests.
+pub(crate) struct FailoverState {
+    params: FailoverParams,
+    active: AtomicBool,
+    failures: Mutex<FailureWindow>,
+}
+
+impl FailoverState {
+    pub(crate) fn new(params: &FailoverParams) -> FailoverState {
+        FailoverState {
+            params: params.clone(),
+            active: AtomicBool::new(false),
+            failures: Mutex::new(FailureWindow {
+                count: 0,
+                started_at: None,
+            }),
+        }
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.active.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn on_success(&self) {
+        if self.is_active() {
+            return;
+        }
+        let mut failures = self.lock_failures();
+        failures.count = 0;
+        failures.started_at = None;
+    }
+
+    /// Records a failure of the primary pool.
+    ///
+    /// Returns the number of consecutive failures if this failure activates the fallback pool.
+    pub(crate) fn on_failure(&self, now: Instant) -> Option<u32> {
+        if self.is_active() {
+            return None;
+        }
+        let mut failures = self.lock_failures();
+        match failures.started_at {
+            Some(started_at)
+                if now.saturating_duration_since(started_at) <= self.params.failover_window =>
+            {
+                failures.count += 1
+            }
+            _ => {
+                failures.count = 1;
+                failures.started_at = Some(now);
+            }
+        }
+        if failures.count >= self.params.failover_threshold
+            && self
+                .active
+                .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            Some(failures.count)
+        } else {
+            None
+        }
+    }
+
+    fn lock_failures(&self) -> std::sync::MutexGuard<'_, FailureWindow> {
+        self.failures.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(failover_threshold: u32, failover_window_secs: u64) -> FailoverParams {
+        FailoverParams {
+            failover_threshold,
+            failover_window: Duration::from_secs(failover_window_secs),
+        }
+    }
+
+    #[test]
+    fn test_failover_after_consecutive_failures() {
+        let state = FailoverState::new(&params(3, 60));
+        let now = Instant::now();
+        assert_eq!(state.on_failure(now), None);
+        assert_eq!(state.on_failure(now + Duration::from_secs(1)), None);
+        assert!(!state.is_active());
+        assert_eq!(state.on_failure(now + Duration::from_secs(2)), Some(3));
+        assert!(state.is_active());
+
+        // further failures and successes don't change the state
+        assert_eq!(state.on_failure(now + Duration::from_secs(3)), None);
+        state.on_success();
+        assert!(state.is_active());
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let state = FailoverState::new(&params(2, 60));
+        let now = Instant::now();
+        assert_eq!(state.on_failure(now), None);
+        state.on_success();
+        assert_eq!(state.on_failure(now), None);
+        assert_eq!(state.on_failure(now), Some(2));
+    }
+
+    #[test]
+    fn test_failures_outside_window_are_not_counted() {
+        let state = FailoverState::new(&params(2, 10));
+        let now = Instant::now();
+        assert_eq!(state.on_failure(now), None);
+        assert_eq!(state.on_failure(now + Duration::from_secs(11)), None);
+        assert!(!state.is_active());
+        assert_eq!(state.on_failure(now + Duration::from_secs(12)), Some(2));
+    }
+}
@@ -0,0 +1,434 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+
+use crate::client::{
+    InvokeOptions, RetryOverride, TonClientError, TonClientInterface, TonConnection,
+};
+use crate::tl::{TonFunction, TonResult};
+
+pub const DEFAULT_FALLBACK_FAILURE_THRESHOLD: u32 = 3;
+pub const DEFAULT_FALLBACK_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Client which currently serves requests of `FallbackClient`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FallbackState {
+    Primary,
+    Fallback,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FallbackParams {
+    /// Number of consecutive primary failures which triggers failover
+    pub failure_threshold: u32,
+    /// Minimal interval between attempts to switch back to primary
+    pub probe_interval: Duration,
+}
+
+impl Default for FallbackParams {
+    fn default() -> Self {
+        FallbackParams {
+            failure_threshold: DEFAULT_FALLBACK_FAILURE_THRESHOLD,
+            probe_interval: DEFAULT_FALLBACK_PROBE_INTERVAL,
+        }
+    }
+}
+
+/// The callback methods invoked by FallbackClient on state transitions
+#[allow(unused_variables)]
+pub trait FallbackCallback: Send + Sync {
+    /// Method `on_failover` gets called when requests are switched to fallback client
+    /// after `consecutive_failures` failures of primary client.
+    fn on_failover(&self, consecutive_failures: u32, error: &TonClientError) {}
+
+    /// Method `on_failback` gets called when a probe request to primary client succeeds
+    /// and requests are switched back to primary.
+    fn on_failback(&self, fallback_duration: &Duration) {}
+
+    /// Method `on_probe_failed` gets called when a probe request to primary client fails
+    /// and the request is served by fallback client.
+    fn on_probe_failed(&self, error: &TonClientError) {}
+}
+
+/// An implementation of FallbackCallback that does nothing
+pub struct NoopFallbackCallback {}
+
+impl FallbackCallback for NoopFallbackCallback {}
+
+type SupportedFunctions = dyn Fn(&TonFunction) -> bool + Send + Sync;
+
+struct FallbackInner {
+    state: FallbackState,
+    consecutive_failures: u32,
+    fallback_since: Instant,
+    last_probe: Instant,
+}
+
+/// Client which routes requests to `primary` and fails over to `fallback` once `primary`
+/// fails `failure_threshold` times in a row.
+///
+/// While in fallback state, a request is sent to `primary` at most once per `probe_interval`
+/// and the client switches back once such probe succeeds.
+/// Functions not supported by `fallback` fail with `TonClientError::UnsupportedOnFallback`.
+pub struct FallbackClient<P, F> {
+    primary: P,
+    fallback: F,
+    params: FallbackParams,
+    supported_functions: Box<SupportedFunctions>,
+    callback: Arc<dyn FallbackCallback>,
+    inner: Mutex<FallbackInner>,
+}
+
+impl<P, F> FallbackClient<P, F>
+where
+    P: TonClientInterface,
+    F: TonClientInterface,
+{
+    pub fn new(primary: P, fallback: F) -> FallbackClient<P, F> {
+        let now = Instant::now();
+        FallbackClient {
+            primary,
+            fallback,
+            params: FallbackParams::default(),
+            supported_functions: Box::new(|_| true),
+            callback: Arc::new(NoopFallbackCallback {}),
+            inner: Mutex::new(FallbackInner {
+                state: FallbackState::Primary,
+                consecutive_failures: 0,
+                fallback_since: now,
+                last_probe: now,
+            }),
+        }
+    }
+
+    pub fn with_params(mut self, params: FallbackParams) -> Self {
+        self.params = params;
+        self
+    }
+
+    /// Restricts functions which are routed to fallback client, e.g. to the subset supported
+    /// by an HTTP API.
+    pub fn with_supported_functions(
+        mut self,
+        supported: impl Fn(&TonFunction) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.supported_functions = Box::new(supported);
+        self
+    }
+
+    pub fn with_callback(mut self, callback: Arc<dyn FallbackCallback>) -> Self {
+        self.callback = callback;
+        self
+    }
+
+    pub fn state(&self) -> FallbackState {
+        self.lock().state
+    }
+
+    /// Returns `true` if the request should be sent to primary client
+    fn use_primary(&self) -> bool {
+        let mut inner = self.lock();
+        match inner.state {
+            FallbackState::Primary => true,
+            FallbackState::Fallback => {
+                let now = Instant::now();
+                if now.duration_since(inner.last_probe) >= self.params.probe_interval {
+                    inner.last_probe = now;
+                    true
+                } else {
+                    false
+                }
+            }
+        }
+    }
+
+    /// Updates the state with result of primary client.
+    /// Returns `true` if the request should be retried on fallback client.
+    fn handle_primary_result<T>(&self, result: &Result<T, TonClientError>) -> bool {
+        let mut inner = self.lock();
+        let error = match result {
+            Ok(_) => {
+                inner.consecutive_failures = 0;
+                if inner.state == FallbackState::Fallback {
+                    inner.state = FallbackState::Primary;
+                    let fallback_duration = inner.fallback_since.elapsed();
+                    drop(inner);
+                    self.callback.on_failback(&fallback_duration);
+                }
+                return false;
+            }
+            Err(e) if is_outage_error(e) => e,
+            Err(_) => return false,
+        };
+        match inner.state {
+            FallbackState::Primary => {
+                inner.consecutive_failures += 1;
+                if inner.consecutive_failures < self.params.failure_threshold {
+                    return false;
+                }
+                let now = Instant::now();
+                inner.state = FallbackState::Fallback;
+                inner.fallback_since = now;
+                inner.last_probe = now;
+                let consecutive_failures = inner.consecutive_failures;
+                drop(inner);
+                self.callback.on_failover(consecutive_failures, error);
+            }
+            FallbackState::Fallback => {
+                drop(inner);
+                self.callback.on_probe_failed(error);
+            }
+        }
+        true
+    }
+
+    fn ensure_supported(&self, function: &TonFunction) -> Result<(), TonClientError> {
+        if (self.supported_functions)(function) {
+            Ok(())
+        } else {
+            Err(TonClientError::UnsupportedOnFallback {
+                method: function.into(),
+            })
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FallbackInner> {
+        // the state is always consistent, so poisoning can be ignored
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[async_trait]
+impl<P, F> TonClientInterface for FallbackClient<P, F>
+where
+    P: TonClientInterface,
+    F: TonClientInterface,
+{
+    async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+        match self.state() {
+            FallbackState::Primary => self.primary.get_connection().await,
+            FallbackState::Fallback => self.fallback.get_connection().await,
+        }
+    }
+
+    async fn invoke_on_connection(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        if self.use_primary() {
+            let result = self.primary.invoke_on_connection(function).await;
+            if !self.handle_primary_result(&result) {
+                return result;
+            }
+        }
+        self.ensure_supported(function)?;
+        self.fallback.invoke_on_connection(function).await
+    }
+
+    async fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+        if self.use_primary() {
+            let result = self.primary.invoke(function).await;
+            if !self.handle_primary_result(&result) {
+                return result;
+            }
+        }
+        self.ensure_supported(function)?;
+        self.fallback.invoke(function).await
+    }
+
+    /// Forwards `options` to the active client. A failed call with `RetryOverride::None`
+    /// is not repeated on the fallback client, though it still counts towards failover.
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        if self.use_primary() {
+            let result = self.primary.invoke_with_options(function, options).await;
+            if !self.handle_primary_result(&result) || options.retry == RetryOverride::None {
+                return result;
+            }
+        }
+        self.ensure_supported(function)?;
+        self.fallback.invoke_with_options(function, options).await
+    }
+}
+
+/// Errors caused by unavailability of the client rather than by the request itself
+pub(super) fn is_outage_error(error: &TonClientError) -> bool {
+    match error {
+        TonClientError::TonlibError { code, .. } => *code >= 500,
+        TonClientError::ConnectFailed { source, .. } => is_outage_error(source),
+        TonClientError::Timeout { .. }
+        | TonClientError::InternalError(_)
+        | TonClientError::Io(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use super::*;
+    use crate::client::mock::{tonlib_error, MockClient, RetryingClient};
+
+    /// Client logging requests as `<name> <method>`, failing them while `down` is set
+    fn node(name: &'static str, log: &Arc<Mutex<Vec<String>>>) -> (MockClient, Arc<AtomicBool>) {
+        let down = Arc::new(AtomicBool::new(false));
+        let log = log.clone();
+        let is_down = down.clone();
+        let client = MockClient::new(move |function| {
+            let method: &'static str = function.into();
+            log.lock().unwrap().push(format!("{} {}", name, method));
+            if is_down.load(Ordering::SeqCst) {
+                Err(tonlib_error(function, 500, "LITE_SERVER_NETWORK"))
+            } else {
+                Ok(TonResult::Ok {})
+            }
+        });
+        (client, down)
+    }
+
+    #[derive(Default)]
+    struct RecordingCallback {
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl FallbackCallback for RecordingCallback {
+        fn on_failover(&self, consecutive_failures: u32, _error: &TonClientError) {
+            let event = format!("failover after {}", consecutive_failures);
+            self.log.lock().unwrap().push(event);
+        }
+
+        fn on_failback(&self, _fallback_duration: &Duration) {
+            self.log.lock().unwrap().push("failback".to_string());
+        }
+
+        fn on_probe_failed(&self, _error: &TonClientError) {
+            self.log.lock().unwrap().push("probe failed".to_string());
+        }
+    }
+
+    fn take(log: &Arc<Mutex<Vec<String>>>) -> Vec<String> {
+        std::mem::take(&mut *log.lock().unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_failover_and_failback() -> anyhow::Result<()> {
+        let log = Arc::new(Mutex::new(vec![]));
+        let (primary, primary_down) = node("primary", &log);
+        let (fallback, _) = node("fallback", &log);
+        let callback = Arc::new(RecordingCallback { log: log.clone() });
+        let client = FallbackClient::new(primary, fallback)
+            .with_params(FallbackParams {
+                failure_threshold: 2,
+                probe_interval: Duration::ZERO,
+            })
+            .with_callback(callback);
+        let sync = TonFunction::Sync {};
+
+        client.invoke(&sync).await?;
+        assert_eq!(take(&log), vec!["primary Sync"]);
+
+        primary_down.store(true, Ordering::SeqCst);
+        assert!(client.invoke(&sync).await.is_err());
+        assert_eq!(client.state(), FallbackState::Primary);
+        client.invoke(&sync).await?;
+        assert_eq!(client.state(), FallbackState::Fallback);
+        assert_eq!(
+            take(&log),
+            vec![
+                "primary Sync",
+                "primary Sync",
+                "failover after 2",
+                "fallback Sync"
+            ]
+        );
+
+        // probe is sent to primary before every request with zero probe interval
+        client.invoke(&sync).await?;
+        assert_eq!(
+            take(&log),
+            vec!["primary Sync", "probe failed", "fallback Sync"]
+        );
+
+        primary_down.store(false, Ordering::SeqCst);
+        client.invoke(&sync).await?;
+        assert_eq!(client.state(), FallbackState::Primary);
+        assert_eq!(take(&log), vec!["primary Sync", "failback"]);
+
+        // failure counter is reset after failback
+        primary_down.store(true, Ordering::SeqCst);
+        assert!(client.invoke(&sync).await.is_err());
+        assert_eq!(client.state(), FallbackState::Primary);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_no_retry_is_forwarded() -> anyhow::Result<()> {
+        let primary = MockClient::failing();
+        let fallback = MockClient::failing();
+        let client = FallbackClient::new(RetryingClient(primary.clone()), fallback.clone())
+            .with_params(FallbackParams {
+                failure_threshold: 1,
+                probe_interval: Duration::from_secs(3600),
+            });
+        let send = TonFunction::RawSendMessageReturnHash { body: vec![] };
+
+        let result = client
+            .invoke_with_options(&send, &InvokeOptions::no_retry())
+            .await;
+        assert!(result.is_err());
+        assert_eq!(primary.calls(), 1);
+        // the send isn't repeated on fallback though the client has failed over
+        assert_eq!(fallback.calls(), 0);
+        assert_eq!(client.state(), FallbackState::Fallback);
+
+        let result = client
+            .invoke_with_options(&send, &InvokeOptions::no_retry())
+            .await;
+        assert!(result.is_err());
+        assert_eq!(primary.calls(), 1);
+        assert_eq!(fallback.calls(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_on_fallback() -> anyhow::Result<()> {
+        let log = Arc::new(Mutex::new(vec![]));
+        let (primary, primary_down) = node("primary", &log);
+        let (fallback, _) = node("fallback", &log);
+        let client = FallbackClient::new(primary, fallback)
+            .with_params(FallbackParams {
+                failure_threshold: 1,
+                probe_interval: Duration::from_secs(3600),
+            })
+            .with_supported_functions(|f| matches!(f, TonFunction::Sync {}));
+        let config = TonFunction::GetConfigAll { mode: 0 };
+
+        // unsupported function is served by primary while it's available
+        client.invoke(&config).await?;
+
+        primary_down.store(true, Ordering::SeqCst);
+        let result = client.invoke(&config).await;
+        assert!(matches!(
+            result,
+            Err(TonClientError::UnsupportedOnFallback {
+                method: "GetConfigAll"
+            })
+        ));
+        assert_eq!(client.state(), FallbackState::Fallback);
+        client.invoke(&TonFunction::Sync {}).await?;
+        assert_eq!(
+            take(&log),
+            vec![
+                "primary GetConfigAll",
+                "primary GetConfigAll",
+                "fallback Sync"
+            ]
+        );
+        Ok(())
+    }
+}
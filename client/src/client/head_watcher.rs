@@ -0,0 +1,337 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::future;
+
+use crate::client::{TonClientError, TonClientInterface};
+use crate::rt::{self, watch};
+use crate::tl::{BlocksMasterchainInfo, TonFunction, TonResult, TonResultDiscriminants};
+
+pub const DEFAULT_HEAD_POLL_INTERVAL: Duration = Duration::from_millis(500);
+pub const DEFAULT_HEAD_WAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HeadWatcherParams {
+    /// Interval between `getMasterchainInfo` requests when the client doesn't support waits
+    pub poll_interval: Duration,
+    /// How long a single wait for the next seqno may be outstanding
+    pub wait_timeout: Duration,
+}
+
+impl Default for HeadWatcherParams {
+    fn default() -> Self {
+        HeadWatcherParams {
+            poll_interval: DEFAULT_HEAD_POLL_INTERVAL,
+            wait_timeout: DEFAULT_HEAD_WAIT_TIMEOUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeadWatcherStats {
+    /// `false` once the client rejected a wait and the watcher switched to polling
+    pub long_polling: bool,
+    pub wait_requests: u64,
+    pub poll_requests: u64,
+    pub head_updates: u64,
+    /// Estimated delay between a new masterchain block becoming available and its detection.
+    ///
+    /// For polling it's the time since the previous poll was sent, for waits it's
+    /// half of the round-trip of the initial `getMasterchainInfo` request.
+    pub last_detection_latency: Option<Duration>,
+    /// Average of `last_detection_latency` over all head updates
+    pub avg_detection_latency: Duration,
+}
+
+impl HeadWatcherStats {
+    fn record_update(&mut self, latency: Duration) {
+        let total = self.avg_detection_latency * self.head_updates as u32 + latency;
+        self.head_updates += 1;
+        self.avg_detection_latency = total / self.head_updates as u32;
+        self.last_detection_latency = Some(latency);
+    }
+}
+
+/// Tracks the latest masterchain block with a single outstanding request shared by all subscribers.
+///
+/// The watcher waits for the next seqno with `TonClientInterface::wait_masterchain_seqno`
+/// and falls back to polling `getMasterchainInfo` once the client rejects a wait.
+/// The background task stops when the watcher and all its receivers are dropped.
+#[derive(Clone)]
+pub struct HeadWatcher {
+    receiver: watch::Receiver<Option<BlocksMasterchainInfo>>,
+    stats: Arc<Mutex<HeadWatcherStats>>,
+}
+
+impl HeadWatcher {
+    /// Spawns the watching task on the async runtime of the client.
+    pub fn start<C>(client: C, params: &HeadWatcherParams) -> HeadWatcher
+    where
+        C: TonClientInterface + 'static,
+    {
+        let (sender, receiver) = watch::channel(None);
+        let stats = Arc::new(Mutex::new(HeadWatcherStats {
+            long_polling: true,
+            ..Default::default()
+        }));
+        let task_stats = stats.clone();
+        let params = params.clone();
+        rt::spawn(async move {
+            let closed = Box::pin(sender.closed());
+            let watching = Box::pin(watch_head(&client, &params, &sender, &task_stats));
+            future::select(closed, watching).await;
+        });
+        HeadWatcher { receiver, stats }
+    }
+
+    /// Returns a receiver of the latest masterchain info, `None` until the first one is received.
+    pub fn subscribe(&self) -> watch::Receiver<Option<BlocksMasterchainInfo>> {
+        self.receiver.clone()
+    }
+
+    /// Returns the latest masterchain info received so far.
+    pub fn latest(&self) -> Option<BlocksMasterchainInfo> {
+        self.receiver.borrow().clone()
+    }
+
+    /// Resolves as soon as masterchain block `seqno` is detected.
+    pub async fn wait_seqno(&self, seqno: i32) -> Result<BlocksMasterchainInfo, TonClientError> {
+        let mut receiver = self.receiver.clone();
+        loop {
+            if let Some(info) = receiver.borrow_and_update().as_ref() {
+                if info.last.seqno >= seqno {
+                    return Ok(info.clone());
+                }
+            }
+            receiver.changed().await.map_err(|_| {
+                TonClientError::InternalError("Head watcher is stopped".to_string())
+            })?;
+        }
+    }
+
+    pub fn stats(&self) -> HeadWatcherStats {
+        self.stats.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+async fn watch_head<C: TonClientInterface>(
+    client: &C,
+    params: &HeadWatcherParams,
+    sender: &watch::Sender<Option<BlocksMasterchainInfo>>,
+    stats: &Mutex<HeadWatcherStats>,
+) {
+    let lock_stats = || stats.lock().unwrap_or_else(|e| e.into_inner());
+    let mut last_seqno: Option<i32> = None;
+    let mut long_polling = true;
+    let mut wait_latency = None;
+    let mut prev_poll_sent_at: Option<Instant> = None;
+    loop {
+        let sent_at = Instant::now();
+        let waiting = long_polling && last_seqno.is_some();
+        let result = match last_seqno {
+            Some(seqno) if waiting => {
+                lock_stats().wait_requests += 1;
+                client
+                    .wait_masterchain_seqno(seqno + 1, params.wait_timeout)
+                    .await
+            }
+            _ => {
+                lock_stats().poll_requests += 1;
+                poll_masterchain_info(client).await
+            }
+        };
+        let latency = if waiting {
+            wait_latency
+        } else {
+            prev_poll_sent_at.map(|t| t.elapsed())
+        };
+        if !waiting {
+            wait_latency.get_or_insert(sent_at.elapsed() / 2);
+            prev_poll_sent_at = Some(sent_at);
+        }
+
+        match result {
+            Ok(info) if last_seqno.is_none_or(|seqno| info.last.seqno > seqno) => {
+                if last_seqno.is_some() {
+                    lock_stats().record_update(latency.unwrap_or_default());
+                }
+                last_seqno = Some(info.last.seqno);
+                sender.send_replace(Some(info));
+                // The next wait can be sent right away, including after the initial poll
+                if long_polling {
+                    continue;
+                }
+            }
+            Ok(_) => {
+                if waiting {
+                    continue;
+                }
+            }
+            Err(e) if waiting && is_wait_rejected(&e) => {
+                log::warn!(
+                    "Waiting for masterchain seqno is rejected, falling back to polling: {}",
+                    e
+                );
+                long_polling = false;
+                lock_stats().long_polling = false;
+                continue;
+            }
+            Err(TonClientError::Timeout { .. }) if waiting => continue,
+            Err(e) => log::warn!("Failed to get masterchain info: {}", e),
+        }
+        rt::sleep(params.poll_interval).await;
+    }
+}
+
+async fn poll_masterchain_info<C: TonClientInterface>(
+    client: &C,
+) -> Result<BlocksMasterchainInfo, TonClientError> {
+    match client
+        .invoke(&TonFunction::BlocksGetMasterchainInfo {})
+        .await?
+    {
+        TonResult::BlocksMasterchainInfo(info) => Ok(info),
+        r => Err(TonClientError::unexpected_ton_result(
+            TonResultDiscriminants::BlocksMasterchainInfo,
+            r,
+        )),
+    }
+}
+
+/// Errors meaning that the client or the server doesn't serve waits at all
+fn is_wait_rejected(error: &TonClientError) -> bool {
+    match error {
+        TonClientError::MasterchainWaitUnsupported => true,
+        TonClientError::TonlibError { code, .. } => *code < 500,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::client::mock::mc_info;
+    use crate::client::TonConnection;
+
+    struct MockChain {
+        head: watch::Sender<i32>,
+        supports_waits: bool,
+        polls: AtomicUsize,
+        waits: AtomicUsize,
+        outstanding_waits: AtomicUsize,
+        max_outstanding_waits: AtomicUsize,
+    }
+
+    impl MockChain {
+        fn new(seqno: i32, supports_waits: bool) -> Arc<MockChain> {
+            Arc::new(MockChain {
+                head: watch::channel(seqno).0,
+                supports_waits,
+                polls: AtomicUsize::new(0),
+                waits: AtomicUsize::new(0),
+                outstanding_waits: AtomicUsize::new(0),
+                max_outstanding_waits: AtomicUsize::new(0),
+            })
+        }
+    }
+
+    #[async_trait]
+    impl TonClientInterface for Arc<MockChain> {
+        async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+            unimplemented!()
+        }
+
+        async fn invoke_on_connection(
+            &self,
+            _function: &TonFunction,
+        ) -> Result<(TonConnection, TonResult), TonClientError> {
+            unimplemented!()
+        }
+
+        async fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+            match function {
+                TonFunction::BlocksGetMasterchainInfo {} => {
+                    self.polls.fetch_add(1, Ordering::SeqCst);
+                    let seqno = *self.head.borrow();
+                    Ok(TonResult::BlocksMasterchainInfo(mc_info(seqno)))
+                }
+                f => panic!("Unexpected function: {:?}", f),
+            }
+        }
+
+        async fn wait_masterchain_seqno(
+            &self,
+            seqno: i32,
+            _timeout: Duration,
+        ) -> Result<BlocksMasterchainInfo, TonClientError> {
+            if !self.supports_waits {
+                return Err(TonClientError::MasterchainWaitUnsupported);
+            }
+            self.waits.fetch_add(1, Ordering::SeqCst);
+            let outstanding = self.outstanding_waits.fetch_add(1, Ordering::SeqCst) + 1;
+            self.max_outstanding_waits
+                .fetch_max(outstanding, Ordering::SeqCst);
+            let mut head = self.head.subscribe();
+            while *head.borrow_and_update() < seqno {
+                head.changed().await.unwrap();
+            }
+            self.outstanding_waits.fetch_sub(1, Ordering::SeqCst);
+            let seqno = *head.borrow();
+            Ok(mc_info(seqno))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_single_wait_serves_all_subscribers() -> anyhow::Result<()> {
+        let chain = MockChain::new(10, true);
+        let watcher = HeadWatcher::start(chain.clone(), &HeadWatcherParams::default());
+        assert_eq!(watcher.wait_seqno(10).await?.last.seqno, 10);
+
+        let subscribers: Vec<_> = (0..3)
+            .map(|_| {
+                let watcher = watcher.clone();
+                tokio::spawn(async move { watcher.wait_seqno(11).await })
+            })
+            .collect();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(chain.max_outstanding_waits.load(Ordering::SeqCst), 1);
+        chain.head.send_replace(11);
+        for subscriber in subscribers {
+            assert_eq!(subscriber.await??.last.seqno, 11);
+        }
+
+        assert_eq!(chain.polls.load(Ordering::SeqCst), 1);
+        assert_eq!(chain.max_outstanding_waits.load(Ordering::SeqCst), 1);
+        let stats = watcher.stats();
+        assert!(stats.long_polling);
+        assert_eq!(stats.poll_requests, 1);
+        assert_eq!(stats.head_updates, 1);
+        assert!(stats.last_detection_latency.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_polling() -> anyhow::Result<()> {
+        let chain = MockChain::new(10, false);
+        let params = HeadWatcherParams {
+            poll_interval: Duration::from_millis(5),
+            ..Default::default()
+        };
+        let watcher = HeadWatcher::start(chain.clone(), &params);
+        assert_eq!(watcher.wait_seqno(10).await?.last.seqno, 10);
+        chain.head.send_replace(12);
+        assert_eq!(watcher.wait_seqno(11).await?.last.seqno, 12);
+
+        let stats = watcher.stats();
+        assert!(!stats.long_polling);
+        assert_eq!(stats.wait_requests, 1);
+        assert!(stats.poll_requests >= 2);
+        assert_eq!(stats.head_updates, 1);
+        assert_eq!(chain.waits.load(Ordering::SeqCst), 0);
+        Ok(())
+    }
+}
@@ -1,16 +1,42 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
 use async_trait::async_trait;
+use futures::stream::{self, BoxStream};
+use futures::StreamExt;
+use num_bigint::BigUint;
+use tonlib_core::cell::dict::CellDictionary;
+use tonlib_core::cell::{BagOfCells, Cell};
+use tonlib_core::constants::MASTERCHAIN_ID;
+use tonlib_core::types::{
+    ConfigValidatorSet, ExtraCurrency, ExtraCurrencyCollection, StoragePricesHistory,
+};
 use tonlib_core::TonAddress;
 
-use super::{SmcLibraryQueryExt, SmcLibraryResult, SmcLibraryResultExt, TonLibraryId};
-use crate::client::{TonClientError, TonConnection};
+use super::connection::with_timeout;
+use super::consistent_states;
+use super::{
+    SmcLibraryEntry, SmcLibraryQueryExt, SmcLibraryResult, SmcLibraryResultExt, TonLibraryId,
+};
+use crate::client::{
+    retry_condition, retry_with_strategy, CachedMasterchainInfo, ConsistentStates, InvokeOptions,
+    LibrariesSource, NetworkAddresses, NetworkTopology, Pager, RawTransactionStream, RetryOverride,
+    TonClientError, TonConnection, WorkchainDescr,
+};
 use crate::contract::LoadedSmcState;
+use crate::rt;
 use crate::tl::{
     AccountAddress, BlockId, BlockIdExt, BlocksAccountTransactionId, BlocksHeader,
     BlocksMasterchainInfo, BlocksShards, BlocksTransactions, BlocksTransactionsExt, ConfigInfo,
-    FullAccountState, InternalTransactionId, LiteServerInfo, RawFullAccountState, RawTransactions,
-    TonFunction, TonResult, TonResultDiscriminants, TvmCell,
+    FullAccountState, InternalTransactionId, LiteServerInfo, QueryFees, RawFullAccountState,
+    RawTransactions, TonFunction, TonResult, TonResultDiscriminants, TvmCell,
 };
 
+const CONFIG_PARAM_EXTRA_CURRENCIES: u32 = 7;
+const CONFIG_PARAM_STORAGE_PRICES: u32 = 18;
+const CONFIG_PARAM_CURRENT_VALIDATORS: u32 = 34;
+
 #[async_trait]
 pub trait TonClientInterface: Send + Sync {
     async fn get_connection(&self) -> Result<TonConnection, TonClientError>;
@@ -24,6 +50,92 @@ pub trait TonClientInterface: Send + Sync {
         self.invoke_on_connection(function).await.map(|(_, r)| r)
     }
 
+    /// Invokes `function` overriding retry behavior & timeout of the client for this call only.
+    ///
+    /// The default implementation can't disable retries made by `invoke` itself,
+    /// so `RetryOverride::None` invokes it once and `RetryOverride::Custom` retries on top of it.
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        let call = async {
+            match &options.retry {
+                RetryOverride::Custom(strategy) => {
+                    retry_with_strategy(strategy, || self.invoke(function)).await
+                }
+                RetryOverride::Inherit | RetryOverride::None => self.invoke(function).await,
+            }
+        };
+        let method = function.into();
+        let timeout = options.call_timeout(method)?;
+        with_timeout(timeout, method, "call", call).await?
+    }
+
+    /// Invokes the function returned by `function_fn` every `interval`, yielding the results.
+    ///
+    /// Each call is retried by `invoke` as usual. Transient errors left after retries,
+    /// such as network errors, are skipped and polling goes on. The stream ends with
+    /// the first error which isn't worth retrying, since polling won't make it go away.
+    fn poll_stream<'a, FF>(
+        &'a self,
+        function_fn: FF,
+        interval: Duration,
+    ) -> BoxStream<'a, Result<TonResult, TonClientError>>
+    where
+        FF: FnMut() -> TonFunction + Send + 'a,
+    {
+        stream::unfold(Some((function_fn, true)), move |state| async move {
+            let (mut function_fn, mut first) = state?;
+            loop {
+                if !first {
+                    rt::sleep(interval).await;
+                }
+                first = false;
+                match self.invoke(&function_fn()).await {
+                    Err(e) if retry_condition(&e) => {
+                        log::debug!("Polling goes on after a transient error: {}", e)
+                    }
+                    Err(e) => return Some((Err(e), None)),
+                    Ok(result) => return Some((Ok(result), Some((function_fn, false)))),
+                }
+            }
+        })
+        .boxed()
+    }
+
+    /// Polls the function returned by `function_fn` every `interval` until `predicate` accepts
+    /// the result, e.g. to wait for the seqno of a wallet to advance after sending a message.
+    ///
+    /// Returns the accepted result. Transient errors are skipped as in `poll_stream`,
+    /// other errors are returned immediately. Fails with `TonClientError::Timeout` if no result is accepted within
+    /// `timeout`, zero meaning no limit.
+    async fn poll_until<FF, P>(
+        &self,
+        function_fn: FF,
+        mut predicate: P,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<TonResult, TonClientError>
+    where
+        FF: FnMut() -> TonFunction + Send,
+        P: FnMut(&TonResult) -> bool + Send,
+    {
+        let mut results = self.poll_stream(function_fn, interval);
+        let polling = async {
+            while let Some(result) = results.next().await {
+                let result = result?;
+                if predicate(&result) {
+                    return Ok(result);
+                }
+            }
+            Err(TonClientError::InternalError(
+                "Polling stopped without a result".to_string(),
+            ))
+        };
+        with_timeout(timeout, "poll_until", "poll", polling).await?
+    }
+
     async fn get_raw_account_state(
         &self,
         account_address: &TonAddress,
@@ -43,6 +155,20 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Reads states of `addresses` as of the same masterchain `block`, the latest one if `None`.
+    ///
+    /// At most `CONSISTENT_STATES_CONCURRENCY` states are requested at once. A state whose shard
+    /// block isn't available at `block` yet is retried briefly and then reported as
+    /// `PinnedAccountState::Unavailable`. States at old blocks are kept only by archive nodes,
+    /// so read them with a client created with `ConnectionCheck::Archive`.
+    async fn get_account_states_at_block(
+        &self,
+        addresses: &[TonAddress],
+        block: Option<&BlockIdExt>,
+    ) -> Result<ConsistentStates, TonClientError> {
+        consistent_states::get_account_states_at_block(self, addresses, block).await
+    }
+
     async fn get_raw_account_state_by_transaction(
         &self,
         account_address: &TonAddress,
@@ -110,6 +236,33 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Same as `get_raw_transactions_v2`, but deserializes transactions one by one
+    /// as they are consumed.
+    ///
+    /// The response is passed to the caller as is instead of being deserialized on the thread
+    /// routing responses, so huge pages (e.g. of exchange hot wallets) neither block other
+    /// requests nor get deserialized into memory at once. The raw response is still buffered
+    /// in full, see `RawTransactionStream`. The request is sent to a single connection
+    /// without retries.
+    async fn get_raw_transactions_v2_stream(
+        &self,
+        account_address: &TonAddress,
+        from_transaction_id: &InternalTransactionId,
+        count: usize,
+        try_decode_messages: bool,
+    ) -> Result<RawTransactionStream, TonClientError> {
+        let func = TonFunction::RawGetTransactionsV2 {
+            account_address: AccountAddress {
+                account_address: account_address.to_hex(),
+            },
+            from_transaction_id: from_transaction_id.clone(),
+            count: count as u32,
+            try_decode_messages,
+        };
+        let body = self.get_connection().await?.invoke_body(&func).await?;
+        Ok(RawTransactionStream::from_body(body))
+    }
+
     async fn send_raw_message(&self, body: &[u8]) -> Result<(), TonClientError> {
         let func = TonFunction::RawSendMessage {
             body: body.to_vec(),
@@ -118,10 +271,23 @@ pub trait TonClientInterface: Send + Sync {
     }
 
     async fn send_raw_message_return_hash(&self, body: &[u8]) -> Result<Vec<u8>, TonClientError> {
+        self.send_raw_message_return_hash_with_options(body, &InvokeOptions::default())
+            .await
+    }
+
+    /// Same as `send_raw_message_return_hash`, but with per-call retry & timeout overrides.
+    ///
+    /// Retrying a message after an ambiguous failure may send it twice, so use
+    /// `InvokeOptions::no_retry()` unless the contract rejects duplicates (e.g. by seqno).
+    async fn send_raw_message_return_hash_with_options(
+        &self,
+        body: &[u8],
+        options: &InvokeOptions,
+    ) -> Result<Vec<u8>, TonClientError> {
         let func = TonFunction::RawSendMessageReturnHash {
             body: body.to_vec(),
         };
-        let result = self.invoke(&func).await?;
+        let result = self.invoke_with_options(&func, options).await?;
         match result {
             TonResult::RawExtMessageInfo(info) => Ok(info.hash),
             r => Err(TonClientError::unexpected_ton_result(
@@ -131,6 +297,56 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Estimates fees of processing external message with `body` by `destination` account.
+    ///
+    /// `init_code` & `init_data` are the state init of the account (empty if it's deployed).
+    async fn estimate_fees(
+        &self,
+        destination: &TonAddress,
+        body: &[u8],
+        init_code: &[u8],
+        init_data: &[u8],
+        ignore_chksig: bool,
+    ) -> Result<QueryFees, TonClientError> {
+        let func = TonFunction::RawCreateQuery {
+            destination: AccountAddress {
+                account_address: destination.to_hex(),
+            },
+            init_code: init_code.to_vec(),
+            init_data: init_data.to_vec(),
+            body: body.to_vec(),
+        };
+        let (conn, result) = self.invoke_on_connection(&func).await?;
+        let query_id = match result {
+            TonResult::QueryInfo(info) => info.id,
+            r => {
+                return Err(TonClientError::unexpected_ton_result(
+                    TonResultDiscriminants::QueryInfo,
+                    r,
+                ))
+            }
+        };
+        // query is bound to connection it was created on
+        let func = TonFunction::QueryEstimateFees {
+            id: query_id,
+            ignore_chksig,
+        };
+        let result = conn.invoke(&func).await;
+        if let Err(e) = conn
+            .invoke(&TonFunction::QueryForget { id: query_id })
+            .await
+        {
+            log::warn!("Failed to forget query {}: {:?}", query_id, e);
+        }
+        match result? {
+            TonResult::QueryFees(fees) => Ok(fees),
+            r => Err(TonClientError::unexpected_ton_result(
+                TonResultDiscriminants::QueryFees,
+                r,
+            )),
+        }
+    }
+
     async fn sync(&self) -> Result<(TonConnection, BlockIdExt), TonClientError> {
         let func = TonFunction::Sync {};
         let (conn, result) = self.invoke_on_connection(&func).await?;
@@ -266,6 +482,24 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Returns libraries of `library_list`, requesting `LIBRARIES_PAGE_SIZE` of them at a time.
+    ///
+    /// Libraries which are not found are skipped.
+    fn smc_libraries_pager(&self, library_list: &[TonLibraryId]) -> Pager<'_, SmcLibraryEntry> {
+        Pager::new(LibrariesSource::new(self, library_list))
+    }
+
+    /// Same as `smc_get_libraries`, but splits `library_list` into requests
+    /// accepted by liteservers
+    async fn get_libraries(
+        &self,
+        library_list: &[TonLibraryId],
+    ) -> Result<Vec<SmcLibraryEntry>, TonClientError> {
+        self.smc_libraries_pager(library_list)
+            .collect_all(None)
+            .await
+    }
+
     async fn smc_get_libraries_ext(
         &self,
         list: &[SmcLibraryQueryExt],
@@ -297,6 +531,38 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Returns masterchain info, possibly reused from an earlier call.
+    ///
+    /// The default implementation requests it on every call,
+    /// `TonClient` serves it from its cache, see `TonClient::masterchain_info_cached`.
+    async fn masterchain_info_cached(&self) -> Result<CachedMasterchainInfo, TonClientError> {
+        let func = TonFunction::BlocksGetMasterchainInfo {};
+        match self.invoke(&func).await? {
+            TonResult::BlocksMasterchainInfo(info) => Ok(CachedMasterchainInfo {
+                info,
+                age: Duration::ZERO,
+            }),
+            r => Err(TonClientError::unexpected_ton_result(
+                TonResultDiscriminants::BlocksMasterchainInfo,
+                r,
+            )),
+        }
+    }
+
+    /// Waits until masterchain block `seqno` is available on the liteserver
+    /// and returns masterchain info, resolving with `TonClientError::Timeout` after `timeout`.
+    ///
+    /// tonlib doesn't expose liteserver waits, so the default implementation
+    /// returns `TonClientError::MasterchainWaitUnsupported`.
+    #[allow(unused_variables)]
+    async fn wait_masterchain_seqno(
+        &self,
+        seqno: i32,
+        timeout: Duration,
+    ) -> Result<BlocksMasterchainInfo, TonClientError> {
+        Err(TonClientError::MasterchainWaitUnsupported)
+    }
+
     async fn get_block_shards(
         &self,
         block_id: &BlockIdExt,
@@ -314,6 +580,21 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Returns IDs of all shard blocks referenced in `ShardHashes` of masterchain block
+    /// `mc_block_id`, one per shard of every basechain-like workchain.
+    async fn get_shards_at_mc_block(
+        &self,
+        mc_block_id: &BlockIdExt,
+    ) -> Result<Vec<BlockIdExt>, TonClientError> {
+        if mc_block_id.workchain != MASTERCHAIN_ID {
+            return Err(TonClientError::InternalError(format!(
+                "Block {}:{:016x}:{} is not a masterchain block",
+                mc_block_id.workchain, mc_block_id.shard, mc_block_id.seqno
+            )));
+        }
+        Ok(self.get_block_shards(mc_block_id).await?.shards)
+    }
+
     /// Attempts to find block by specified query.
     ///
     /// * `mode`: Lookup mode: `1` - by `block_id.seqno`, `2` - by `lt`, `4` - by `utime`.
@@ -447,6 +728,127 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 
+    /// Returns the value of config param `param_id`, `None` if it isn't set.
+    ///
+    /// Unlike typed accessors, works for any param, including ones unknown to the crate.
+    async fn get_raw_config_param(&self, param_id: u32) -> Result<Option<Cell>, TonClientError> {
+        let param = self.get_config_param(0, param_id).await?;
+        if param.config.bytes.is_empty() {
+            return Ok(None);
+        }
+        let cell = BagOfCells::parse(&param.config.bytes)
+            .and_then(|boc| boc.single_root().map(|root| root.as_ref().clone()))
+            .map_err(|e| {
+                TonClientError::InternalError(format!(
+                    "Failed to parse config param {}: {}",
+                    param_id, e
+                ))
+            })?;
+        if cell.bit_len() == 0 && cell.references().is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(cell))
+    }
+
+    /// Returns the config dictionary (`Hashmap 32 ^Cell`) keyed by param ids.
+    ///
+    /// Each value slice holds a single reference to the param cell.
+    async fn get_raw_config_all(&self) -> Result<CellDictionary, TonClientError> {
+        let config = self.get_config_all(0).await?;
+        BagOfCells::parse(&config.config.bytes)
+            .and_then(|boc| CellDictionary::from_cell(boc.single_root()?, 32))
+            .map_err(|e| TonClientError::InternalError(format!("Failed to parse config: {}", e)))
+    }
+
+    /// Returns cells of config params `ids` fetched with a single `get_config_all` call.
+    ///
+    /// Params missing in the config are absent from the result.
+    async fn get_config_params(&self, ids: &[i32]) -> Result<HashMap<i32, Cell>, TonClientError> {
+        let config = self.get_raw_config_all().await?;
+        let mut params = HashMap::with_capacity(ids.len());
+        for id in ids {
+            // keys are int32, negative ids are stored in two's complement
+            let value = match config.get_uint(&BigUint::from(*id as u32)) {
+                Some(value) => value,
+                None => continue,
+            };
+            let cell = value
+                .into_cell()
+                .and_then(|cell| cell.reference(0).map(|param| param.as_ref().clone()))
+                .map_err(|e| {
+                    TonClientError::InternalError(format!(
+                        "Failed to parse config param {}: {}",
+                        id, e
+                    ))
+                })?;
+            params.insert(*id, cell);
+        }
+        Ok(params)
+    }
+
+    /// Returns storage prices history defined by config param 18
+    async fn get_storage_prices(&self) -> Result<StoragePricesHistory, TonClientError> {
+        let param = self
+            .get_config_param(0, CONFIG_PARAM_STORAGE_PRICES)
+            .await?;
+        BagOfCells::parse(&param.config.bytes)
+            .and_then(|boc| StoragePricesHistory::parse(boc.single_root()?))
+            .map_err(|e| {
+                TonClientError::InternalError(format!("Failed to parse config param 18: {}", e))
+            })
+    }
+
+    /// Returns the current validator set defined by config param 34
+    async fn get_current_validator_set(&self) -> Result<ConfigValidatorSet, TonClientError> {
+        let param = self
+            .get_config_param(0, CONFIG_PARAM_CURRENT_VALIDATORS)
+            .await?;
+        BagOfCells::parse(&param.config.bytes)
+            .and_then(|boc| ConfigValidatorSet::parse(boc.single_root()?))
+            .map_err(|e| {
+                TonClientError::InternalError(format!("Failed to parse config param 34: {}", e))
+            })
+    }
+
+    /// Returns extra currency `currency_id` registered by config param 7
+    async fn get_extra_currency(&self, currency_id: u32) -> Result<ExtraCurrency, TonClientError> {
+        let to_mint = match self
+            .get_raw_config_param(CONFIG_PARAM_EXTRA_CURRENCIES)
+            .await?
+        {
+            Some(param) => ExtraCurrencyCollection::parse(&mut param.parser()).map_err(|e| {
+                TonClientError::InternalError(format!("Failed to parse config param 7: {}", e))
+            })?,
+            None => ExtraCurrencyCollection::default(),
+        };
+        match to_mint.get(currency_id) {
+            Some(amount) => Ok(ExtraCurrency {
+                id: currency_id,
+                to_mint: amount.clone(),
+            }),
+            None => Err(TonClientError::ExtraCurrencyNotRegistered { currency_id }),
+        }
+    }
+
+    /// Returns addresses of system contracts defined by the latest config
+    async fn get_network_addresses(&self) -> Result<Arc<NetworkAddresses>, TonClientError> {
+        NetworkAddresses::from_config(self).await.map(Arc::new)
+    }
+
+    /// Returns workchains defined by the latest config.
+    ///
+    /// Use `NetworkTopology::validate_address` to check destinations before sending messages.
+    async fn get_network_topology(&self) -> Result<NetworkTopology, TonClientError> {
+        NetworkTopology::from_config(self).await
+    }
+
+    /// Returns active workchains defined by config param 12, ordered by id.
+    /// The masterchain isn't included.
+    async fn get_workchain_info(&self) -> Result<Vec<WorkchainDescr>, TonClientError> {
+        let topology = self.get_network_topology().await?;
+        Ok(topology.active_workchains().cloned().collect())
+    }
+
     async fn get_log_verbosity_level(&self) -> Result<u32, TonClientError> {
         let func = TonFunction::GetLogVerbosityLevel {};
         let result = self.invoke(&func).await?;
@@ -461,3 +863,242 @@ pub trait TonClientInterface: Send + Sync {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tonlib_core::cell::dict::predefined_writers::val_writer_ref_cell;
+    use tonlib_core::cell::CellBuilder;
+
+    use super::*;
+    use crate::client::mock::{block_id, mc_info, tonlib_error, MockClient};
+    use crate::client::{send_boc, RetryStrategy};
+
+    #[tokio::test]
+    async fn test_send_message_is_not_retried() {
+        let client = MockClient::failing();
+        let result = send_boc(
+            &client,
+            &[0xb5, 0xee, 0x9c, 0x72],
+            &InvokeOptions::no_retry(),
+        )
+        .await;
+        assert!(result.is_err());
+        assert_eq!(client.calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_custom_retry() {
+        let client = MockClient::failing();
+        let options = InvokeOptions::with_retry(&RetryStrategy {
+            interval_ms: 1,
+            max_retries: 5,
+        });
+        let result = client
+            .invoke_with_options(&TonFunction::BlocksGetMasterchainInfo {}, &options)
+            .await;
+        assert!(matches!(
+            result,
+            Err(TonClientError::TonlibError { code: 500, .. })
+        ));
+        // the first attempt and 5 retries
+        assert_eq!(client.calls(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_invoke_timeout() {
+        let client = MockClient::failing();
+        let options = InvokeOptions::with_retry(&RetryStrategy {
+            interval_ms: 1000,
+            max_retries: 5,
+        })
+        .with_timeout(Duration::from_millis(50));
+        let result = client
+            .invoke_with_options(&TonFunction::BlocksGetMasterchainInfo {}, &options)
+            .await;
+        assert!(matches!(result, Err(TonClientError::Timeout { .. })));
+        assert_eq!(client.calls(), 1);
+    }
+
+    /// Client whose masterchain seqno advances on every call
+    fn advancing_client() -> MockClient {
+        let calls = AtomicUsize::new(0);
+        MockClient::new(move |_| {
+            let seqno = calls.fetch_add(1, Ordering::SeqCst) as i32 + 1;
+            Ok(TonResult::BlocksMasterchainInfo(mc_info(seqno)))
+        })
+    }
+
+    fn seqno_reached(seqno: i32) -> impl FnMut(&TonResult) -> bool + Send {
+        move |result| matches!(result, TonResult::BlocksMasterchainInfo(info) if info.last.seqno >= seqno)
+    }
+
+    #[tokio::test]
+    async fn test_poll_until() -> anyhow::Result<()> {
+        let client = advancing_client();
+        let result = client
+            .poll_until(
+                || TonFunction::BlocksGetMasterchainInfo {},
+                seqno_reached(3),
+                Duration::from_millis(1),
+                Duration::from_secs(5),
+            )
+            .await?;
+        assert!(seqno_reached(3)(&result));
+        assert_eq!(client.calls(), 3);
+
+        let result = client
+            .poll_until(
+                || TonFunction::BlocksGetMasterchainInfo {},
+                seqno_reached(i32::MAX),
+                Duration::from_millis(5),
+                Duration::from_millis(50),
+            )
+            .await;
+        assert!(matches!(result, Err(TonClientError::Timeout { .. })));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_skips_transient_errors() -> anyhow::Result<()> {
+        // every other call fails with a retryable error
+        let calls = AtomicUsize::new(0);
+        let client = MockClient::new(move |function| {
+            let call = calls.fetch_add(1, Ordering::SeqCst) as i32;
+            if call % 2 == 0 {
+                Err(tonlib_error(function, 500, "LITE_SERVER_NETWORK"))
+            } else {
+                Ok(TonResult::BlocksMasterchainInfo(mc_info(call)))
+            }
+        });
+        let result = client
+            .poll_until(
+                || TonFunction::BlocksGetMasterchainInfo {},
+                seqno_reached(5),
+                Duration::from_millis(1),
+                Duration::from_secs(5),
+            )
+            .await?;
+        assert!(seqno_reached(5)(&result));
+        assert_eq!(client.calls(), 6);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_propagates_errors() {
+        let client =
+            MockClient::new(|function| Err(tonlib_error(function, 400, "INVALID_ARGUMENT")));
+        let result = client
+            .poll_until(
+                || TonFunction::BlocksGetMasterchainInfo {},
+                |_| true,
+                Duration::from_millis(1),
+                Duration::from_secs(5),
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(TonClientError::TonlibError { code: 400, .. })
+        ));
+        // not polled through the error
+        assert_eq!(client.calls(), 1);
+    }
+
+    /// Client serving `param` as config param 12 and `config` as the whole config
+    fn config_client(param: Vec<u8>, config: Vec<u8>) -> MockClient {
+        MockClient::new(move |function| {
+            let bytes = match function {
+                TonFunction::GetConfigParam { param: 12, .. } => param.clone(),
+                TonFunction::GetConfigParam { .. } => vec![],
+                TonFunction::GetConfigAll { .. } => config.clone(),
+                _ => unimplemented!("{:?}", function),
+            };
+            Ok(TonResult::ConfigInfo(ConfigInfo {
+                config: TvmCell { bytes },
+            }))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_raw_config() -> anyhow::Result<()> {
+        let param = CellBuilder::new().store_u32(32, 0xabcd)?.build()?;
+        let params = HashMap::from([(12u32, Arc::new(param.clone()))]);
+        let config = CellBuilder::new()
+            .store_dict(32, val_writer_ref_cell, params)?
+            .build()?;
+        let client = config_client(
+            BagOfCells::from_root(param.clone()).serialize(false)?,
+            BagOfCells::from_root(config).serialize(false)?,
+        );
+        assert_eq!(client.get_raw_config_param(12).await?, Some(param.clone()));
+        assert_eq!(client.get_raw_config_param(100).await?, None);
+
+        let config = client.get_raw_config_all().await?;
+        assert_eq!(config.len(), 1);
+        let value = config.get_uint(&12u32.into()).unwrap().into_cell()?;
+        assert_eq!(value.reference(0)?.as_ref(), &param);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_config_params() -> anyhow::Result<()> {
+        let params: HashMap<u32, _> = [18u32, 20, 21, 24, 25, (-999i32) as u32]
+            .into_iter()
+            .map(|id| {
+                let cell = CellBuilder::new().store_u32(32, id)?.build()?;
+                Ok((id, Arc::new(cell)))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let config = CellBuilder::new()
+            .store_dict(32, val_writer_ref_cell, params.clone())?
+            .build()?;
+        let client = config_client(vec![], BagOfCells::from_root(config).serialize(false)?);
+        let result = client
+            .get_config_params(&[18, 20, 21, 24, 25, 100, -999])
+            .await?;
+        assert_eq!(result.len(), 6);
+        assert!(!result.contains_key(&100));
+        assert_eq!(&result[&24], params[&24].as_ref());
+        assert_eq!(&result[&-999], params[&((-999i32) as u32)].as_ref());
+        Ok(())
+    }
+
+    /// Client serving two shards of the basechain at any masterchain block
+    fn shards_client() -> MockClient {
+        MockClient::new(|function| {
+            let id = match function {
+                TonFunction::BlocksGetShards { id } => id,
+                _ => unimplemented!("{:?}", function),
+            };
+            let shards = [0x4000000000000000u64, 0xc000000000000000u64]
+                .into_iter()
+                .map(|shard| block_id(0, shard, id.seqno * 2))
+                .collect();
+            Ok(TonResult::BlocksShards(BlocksShards { shards }))
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_shards_at_mc_block() -> anyhow::Result<()> {
+        let mc_block = BlockIdExt {
+            workchain: MASTERCHAIN_ID,
+            shard: i64::MIN,
+            seqno: 100,
+            root_hash: vec![1; 32],
+            file_hash: vec![2; 32],
+        };
+        let client = shards_client();
+        let shards = client.get_shards_at_mc_block(&mc_block).await?;
+        assert_eq!(shards.len(), 2);
+        assert!(shards.iter().all(|s| s.workchain == 0 && s.seqno == 200));
+
+        let shard_block = BlockIdExt {
+            workchain: 0,
+            ..mc_block
+        };
+        let result = client.get_shards_at_mc_block(&shard_block).await;
+        assert!(matches!(result, Err(TonClientError::InternalError(_))));
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+use std::sync::atomic::{AtomicI32, AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use crate::rt::Mutex;
+use crate::tl::{BlocksMasterchainInfo, TonResult};
+
+pub const DEFAULT_MC_INFO_CACHE_TTL: Duration = Duration::from_secs(1);
+
+/// Masterchain info returned from cache
+#[derive(Debug, Clone)]
+pub struct CachedMasterchainInfo {
+    pub info: BlocksMasterchainInfo,
+    /// Time elapsed since `info` was received
+    pub age: Duration,
+}
+
+/// Cache of the latest masterchain info with bounded staleness.
+///
+/// The entry expires after `ttl` or as soon as any response reveals a newer masterchain seqno.
+pub(crate) struct McInfoCache {
+    ttl_ms: AtomicU64,
+    entry: RwLock<Option<(Instant, BlocksMasterchainInfo)>>,
+    latest_seqno: AtomicI32,
+    fetch_lock: Mutex<()>,
+}
+
+impl McInfoCache {
+    pub(crate) fn new(ttl: Duration) -> McInfoCache {
+        McInfoCache {
+            ttl_ms: AtomicU64::new(ttl.as_millis() as u64),
+            entry: RwLock::new(None),
+            latest_seqno: AtomicI32::new(0),
+            fetch_lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn set_ttl(&self, ttl: Duration) {
+        self.ttl_ms.store(ttl.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Lock to be held while fetching fresh info, so that concurrent callers wait for single request.
+    pub(crate) fn fetch_lock(&self) -> &Mutex<()> {
+        &self.fetch_lock
+    }
+
+    /// Returns cached info if it is neither expired nor outdated
+    pub(crate) fn get(&self) -> Option<CachedMasterchainInfo> {
+        let guard = self.entry.read().ok()?;
+        let (received_at, info) = guard.as_ref()?;
+        let age = received_at.elapsed();
+        let ttl = Duration::from_millis(self.ttl_ms.load(Ordering::Relaxed));
+        if age >= ttl || info.last.seqno < self.latest_seqno.load(Ordering::Relaxed) {
+            None
+        } else {
+            Some(CachedMasterchainInfo {
+                info: info.clone(),
+                age,
+            })
+        }
+    }
+
+    /// Updates the cache with masterchain seqno or info found in `result`
+    pub(crate) fn observe(&self, result: &TonResult) {
+        match result {
+            TonResult::BlocksMasterchainInfo(info) => self.update(info),
+            TonResult::BlockIdExt(id) if id.workchain == -1 => self.observe_seqno(id.seqno),
+            TonResult::BlocksHeader(header) if header.id.workchain == -1 => {
+                self.observe_seqno(header.id.seqno)
+            }
+            TonResult::RawFullAccountState(state) => self.observe_seqno(state.block_id.seqno),
+            TonResult::FullAccountState(state) => self.observe_seqno(state.block_id.seqno),
+            _ => {}
+        }
+    }
+
+    fn observe_seqno(&self, seqno: i32) {
+        self.latest_seqno.fetch_max(seqno, Ordering::Relaxed);
+    }
+
+    fn update(&self, info: &BlocksMasterchainInfo) {
+        self.observe_seqno(info.last.seqno);
+        if let Ok(mut guard) = self.entry.write() {
+            let is_newer = match guard.as_ref() {
+                Some((_, cached)) => cached.last.seqno <= info.last.seqno,
+                None => true,
+            };
+            if is_newer {
+                *guard = Some((Instant::now(), info.clone()));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::mc_info;
+
+    #[test]
+    fn test_mc_info_cache_expires() {
+        let cache = McInfoCache::new(Duration::from_millis(50));
+        assert!(cache.get().is_none());
+        cache.observe(&TonResult::BlocksMasterchainInfo(mc_info(10)));
+        let cached = cache.get().unwrap();
+        assert_eq!(cached.info.last.seqno, 10);
+        assert!(cached.age < Duration::from_millis(50));
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(cache.get().is_none());
+    }
+
+    #[test]
+    fn test_mc_info_cache_invalidated_by_newer_seqno() {
+        let cache = McInfoCache::new(Duration::from_secs(60));
+        cache.observe(&TonResult::BlocksMasterchainInfo(mc_info(10)));
+        cache.observe(&TonResult::BlockIdExt(mc_info(9).last));
+        assert!(cache.get().is_some());
+        cache.observe(&TonResult::BlockIdExt(mc_info(11).last));
+        assert!(cache.get().is_none());
+        cache.observe(&TonResult::BlocksMasterchainInfo(mc_info(11)));
+        assert_eq!(cache.get().unwrap().info.last.seqno, 11);
+    }
+}
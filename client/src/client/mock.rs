@@ -0,0 +1,306 @@
+//! Test doubles shared by unit tests: a scripted client and fixtures of TL objects and
+//! transaction cells.
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use num_bigint::BigUint;
+use tonlib_core::cell::dict::predefined_writers::val_writer_ref_cell;
+use tonlib_core::cell::{BagOfCells, Cell, CellBuilder, TonCellError};
+use tonlib_core::TonAddress;
+
+use crate::client::{
+    InvokeOptions, RetryOverride, TonClientError, TonClientInterface, TonConnection,
+};
+use crate::tl::{
+    AccountAddress, BlockIdExt, BlocksHeader, BlocksMasterchainInfo, InternalTransactionId,
+    MsgData, RawFullAccountState, RawMessage, RawTransaction, TonFunction, TonResult,
+    NULL_TRANSACTION_ID,
+};
+
+type Handler = dyn Fn(&TonFunction) -> Result<TonResult, TonClientError> + Send + Sync;
+
+/// Client answering every `invoke` with `handler` and counting the calls.
+///
+/// Clones share the handler and the counter.
+#[derive(Clone)]
+pub(crate) struct MockClient {
+    handler: Arc<Handler>,
+    calls: Arc<AtomicUsize>,
+}
+
+impl MockClient {
+    pub(crate) fn new<F>(handler: F) -> MockClient
+    where
+        F: Fn(&TonFunction) -> Result<TonResult, TonClientError> + Send + Sync + 'static,
+    {
+        MockClient {
+            handler: Arc::new(handler),
+            calls: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Client failing every call with a retryable error
+    pub(crate) fn failing() -> MockClient {
+        MockClient::new(|function| Err(tonlib_error(function, 500, "LITE_SERVER_NETWORK")))
+    }
+
+    /// Number of `invoke` calls made so far
+    pub(crate) fn calls(&self) -> usize {
+        self.calls.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl TonClientInterface for MockClient {
+    async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+        unimplemented!()
+    }
+
+    async fn invoke_on_connection(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        unimplemented!("{:?}", function)
+    }
+
+    async fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+        self.calls.fetch_add(1, Ordering::SeqCst);
+        (self.handler)(function)
+    }
+}
+
+/// Client repeating failed `invoke` calls like `TonClient` does, once with no retries
+pub(crate) struct RetryingClient(pub(crate) MockClient);
+
+#[async_trait]
+impl TonClientInterface for RetryingClient {
+    async fn get_connection(&self) -> Result<TonConnection, TonClientError> {
+        self.0.get_connection().await
+    }
+
+    async fn invoke_on_connection(
+        &self,
+        function: &TonFunction,
+    ) -> Result<(TonConnection, TonResult), TonClientError> {
+        self.0.invoke_on_connection(function).await
+    }
+
+    async fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+        let mut result = self.0.invoke(function).await;
+        for _ in 0..2 {
+            if result.is_ok() {
+                break;
+            }
+            result = self.0.invoke(function).await;
+        }
+        result
+    }
+
+    async fn invoke_with_options(
+        &self,
+        function: &TonFunction,
+        options: &InvokeOptions,
+    ) -> Result<TonResult, TonClientError> {
+        match options.retry {
+            RetryOverride::None => self.0.invoke(function).await,
+            _ => self.invoke(function).await,
+        }
+    }
+}
+
+pub(crate) fn tonlib_error(function: &TonFunction, code: i32, message: &str) -> TonClientError {
+    TonClientError::TonlibError {
+        method: function.into(),
+        code,
+        message: message.to_string(),
+        extra: None,
+    }
+}
+
+pub(crate) fn block_id(workchain: i32, shard: u64, seqno: i32) -> BlockIdExt {
+    BlockIdExt {
+        workchain,
+        shard: shard as i64,
+        seqno,
+        root_hash: vec![0; 32],
+        file_hash: vec![0; 32],
+    }
+}
+
+pub(crate) fn mc_block(seqno: i32) -> BlockIdExt {
+    block_id(-1, 0x8000000000000000, seqno)
+}
+
+pub(crate) fn mc_info(seqno: i32) -> BlocksMasterchainInfo {
+    let block = mc_block(seqno);
+    BlocksMasterchainInfo {
+        last: block.clone(),
+        state_root_hash: vec![0; 32],
+        init: block,
+    }
+}
+
+/// Header of `id` following `prev_blocks`, generated at `id.seqno` seconds
+pub(crate) fn block_header(id: &BlockIdExt, prev_blocks: Vec<BlockIdExt>) -> BlocksHeader {
+    BlocksHeader {
+        id: id.clone(),
+        global_id: -239,
+        version: 0,
+        flags: 0,
+        after_merge: prev_blocks.len() == 2,
+        after_split: false,
+        before_split: false,
+        want_merge: false,
+        want_split: false,
+        validator_list_hash_short: 0,
+        catchain_seqno: 0,
+        min_ref_mc_seqno: 0,
+        is_key_block: false,
+        prev_key_block_seqno: 0,
+        start_lt: 0,
+        end_lt: 0,
+        gen_utime: id.seqno as i64,
+        vert_seqno: None,
+        prev_blocks: Some(prev_blocks),
+    }
+}
+
+/// Empty active account at `block_id`
+pub(crate) fn account_state(block_id: &BlockIdExt) -> RawFullAccountState {
+    RawFullAccountState {
+        balance: 0,
+        code: vec![],
+        data: vec![],
+        last_transaction_id: NULL_TRANSACTION_ID.clone(),
+        block_id: block_id.clone(),
+        frozen_hash: vec![],
+        sync_utime: 0,
+    }
+}
+
+/// Message carrying `body`, external if `source` is `None`
+pub(crate) fn raw_message(
+    source: Option<&TonAddress>,
+    destination: &TonAddress,
+    body: &Cell,
+) -> RawMessage {
+    RawMessage {
+        source: AccountAddress {
+            account_address: source.map(|a| a.to_base64_url()).unwrap_or_default(),
+        },
+        destination: AccountAddress {
+            account_address: destination.to_base64_url(),
+        },
+        value: 0,
+        fwd_fee: 0,
+        ihr_fee: 0,
+        created_lt: 0,
+        body_hash: body.cell_hash().to_vec(),
+        msg_data: MsgData::Raw {
+            body: BagOfCells::from_root(body.clone())
+                .serialize(false)
+                .unwrap(),
+            init_state: vec![],
+        },
+    }
+}
+
+/// Transaction without messages and cell data
+pub(crate) fn raw_transaction(address: &TonAddress, lt: i64) -> RawTransaction {
+    RawTransaction {
+        address: AccountAddress {
+            account_address: address.to_base64_url(),
+        },
+        utime: 0,
+        data: vec![],
+        transaction_id: InternalTransactionId {
+            lt,
+            hash: vec![0; 32],
+        },
+        fee: 0,
+        storage_fee: 0,
+        other_fee: 0,
+        in_msg: None,
+        out_msgs: vec![],
+    }
+}
+
+/// Ordinary transaction cell with empty state update and description.
+pub(crate) struct TransactionFixture {
+    pub account: TonAddress,
+    pub lt: u64,
+    pub prev: InternalTransactionId,
+    pub now: u32,
+    pub in_msg: Option<Cell>,
+    pub out_msgs: Vec<Cell>,
+    pub total_fees: u64,
+}
+
+impl TransactionFixture {
+    pub(crate) fn new(account: &TonAddress, lt: u64) -> TransactionFixture {
+        TransactionFixture {
+            account: account.clone(),
+            lt,
+            prev: NULL_TRANSACTION_ID.clone(),
+            now: 1700000000,
+            in_msg: None,
+            out_msgs: vec![],
+            total_fees: 0,
+        }
+    }
+
+    pub(crate) fn cell(&self) -> Result<Cell, TonCellError> {
+        let mut msgs = CellBuilder::new();
+        match &self.in_msg {
+            Some(in_msg) => msgs.store_bit(true)?.store_child(in_msg.clone())?,
+            None => msgs.store_bit(false)?,
+        };
+        if self.out_msgs.is_empty() {
+            msgs.store_bit(false)?;
+        } else {
+            let dict: HashMap<u16, _> = self
+                .out_msgs
+                .iter()
+                .enumerate()
+                .map(|(index, msg)| (index as u16, Arc::new(msg.clone())))
+                .collect();
+            let mut root = CellBuilder::new();
+            root.store_dict(15, val_writer_ref_cell, dict)?;
+            msgs.store_bit(true)?.store_child(root.build()?)?;
+        }
+        let mut prev_hash = [0u8; 32];
+        prev_hash.copy_from_slice(&self.prev.hash);
+        CellBuilder::new()
+            .store_u8(4, 0b0111)?
+            .store_slice(&self.account.hash_part)?
+            .store_u64(64, self.lt)?
+            .store_slice(&prev_hash)?
+            .store_u64(64, self.prev.lt as u64)?
+            .store_u32(32, self.now)?
+            .store_u32(15, self.out_msgs.len() as u32)?
+            // active -> active
+            .store_u8(4, 0b1010)?
+            .store_child(msgs.build()?)?
+            .store_coins(&BigUint::from(self.total_fees))?
+            .store_bit(false)?
+            .store_child(Cell::default())?
+            .store_child(Cell::default())?
+            .build()
+    }
+
+    /// `RawTransaction` with the cell as data, identified by the cell hash
+    pub(crate) fn raw(&self) -> Result<RawTransaction, TonCellError> {
+        let cell = self.cell()?;
+        Ok(RawTransaction {
+            transaction_id: InternalTransactionId {
+                lt: self.lt as i64,
+                hash: cell.cell_hash().to_vec(),
+            },
+            utime: self.now as i64,
+            data: BagOfCells::from_root(cell).serialize(false)?,
+            ..raw_transaction(&self.account, self.lt as i64)
+        })
+    }
+}
@@ -0,0 +1,247 @@
+use std::sync::{Arc, RwLock};
+
+use tonlib_core::cell::dict::predefined_readers::{key_reader_u32, val_reader_ref_cell};
+use tonlib_core::cell::{BagOfCells, Cell, TonCellError};
+use tonlib_core::TonAddress;
+
+use crate::client::{MapParseError, TonClient, TonClientError, TonClientInterface};
+use crate::rt::Mutex;
+
+const CONFIG_PARAM_CONFIG_ADDRESS: u32 = 0;
+const CONFIG_PARAM_ELECTOR_ADDRESS: u32 = 1;
+const CONFIG_PARAM_MINTER_ADDRESS: u32 = 2;
+const CONFIG_PARAM_FEE_COLLECTOR_ADDRESS: u32 = 3;
+const CONFIG_PARAM_DNS_ROOT_ADDRESS: u32 = 4;
+
+/// Addresses of system contracts defined by config params 0-4
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkAddresses {
+    config: TonAddress,
+    elector: TonAddress,
+    minter: TonAddress,
+    fee_collector: TonAddress,
+    dns_root: Option<TonAddress>,
+}
+
+impl NetworkAddresses {
+    /// Loads the latest config from `client`, bypassing any cache
+    pub async fn from_config<C: TonClientInterface + ?Sized>(
+        client: &C,
+    ) -> Result<NetworkAddresses, TonClientError> {
+        let config = client.get_config_all(0).await?;
+        Self::from_config_boc(&config.config.bytes)
+    }
+
+    /// Parses serialized config dictionary, as returned by `get_config_all`
+    pub fn from_config_boc(boc: &[u8]) -> Result<NetworkAddresses, TonClientError> {
+        let root = BagOfCells::parse(boc)
+            .and_then(|boc| boc.single_root().cloned())
+            .map_parse_error("config")?;
+        Self::from_config_cell(&root)
+    }
+
+    /// Parses config dictionary (`Hashmap 32 ^Cell`)
+    pub fn from_config_cell(config: &Cell) -> Result<NetworkAddresses, TonClientError> {
+        let params = config
+            .parser()
+            .load_dict(32, key_reader_u32, val_reader_ref_cell)
+            .map_parse_error("config")?;
+        let param = |index: u32| -> Result<Option<TonAddress>, TonClientError> {
+            params
+                .get(&index)
+                .map(|cell| parse_address_param(cell).map_parse_error("config"))
+                .transpose()
+        };
+        let config = param(CONFIG_PARAM_CONFIG_ADDRESS)?.ok_or_else(|| {
+            TonClientError::InternalError("Config param 0 is missing".to_string())
+        })?;
+        let elector = param(CONFIG_PARAM_ELECTOR_ADDRESS)?.ok_or_else(|| {
+            TonClientError::InternalError("Config param 1 is missing".to_string())
+        })?;
+        // Absent minter and fee collector are substituted as in the block validation rules
+        let minter = param(CONFIG_PARAM_MINTER_ADDRESS)?.unwrap_or_else(|| config.clone());
+        let fee_collector =
+            param(CONFIG_PARAM_FEE_COLLECTOR_ADDRESS)?.unwrap_or_else(|| elector.clone());
+        Ok(NetworkAddresses {
+            config,
+            elector,
+            minter,
+            fee_collector,
+            dns_root: param(CONFIG_PARAM_DNS_ROOT_ADDRESS)?,
+        })
+    }
+
+    pub fn config_address(&self) -> &TonAddress {
+        &self.config
+    }
+
+    pub fn elector_address(&self) -> &TonAddress {
+        &self.elector
+    }
+
+    pub fn minter_address(&self) -> &TonAddress {
+        &self.minter
+    }
+
+    pub fn fee_collector_address(&self) -> &TonAddress {
+        &self.fee_collector
+    }
+
+    /// Root DNS contract, `None` if the network has no DNS
+    pub fn dns_root_address(&self) -> Option<&TonAddress> {
+        self.dns_root.as_ref()
+    }
+}
+
+/// Reads `bits256` address of masterchain contract
+fn parse_address_param(cell: &Cell) -> Result<TonAddress, TonCellError> {
+    let mut hash = [0u8; 32];
+    cell.parser().load_slice(&mut hash)?;
+    Ok(TonAddress::new(-1, &hash))
+}
+
+struct CacheEntry {
+    mc_seqno: i32,
+    key_block_seqno: i32,
+    addresses: Arc<NetworkAddresses>,
+}
+
+/// Cache of `NetworkAddresses`, which is refreshed once a new key block appears.
+pub(crate) struct NetworkAddressesCache {
+    entry: RwLock<Option<CacheEntry>>,
+    fetch_lock: Mutex<()>,
+}
+
+impl NetworkAddressesCache {
+    pub(crate) fn new() -> NetworkAddressesCache {
+        NetworkAddressesCache {
+            entry: RwLock::new(None),
+            fetch_lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) async fn get(
+        &self,
+        client: &TonClient,
+    ) -> Result<Arc<NetworkAddresses>, TonClientError> {
+        let last = client.masterchain_info_cached().await?.info.last;
+        if let Some(addresses) = self.get_for_seqno(last.seqno) {
+            return Ok(addresses);
+        }
+        let _lock = self.fetch_lock.lock().await;
+        if let Some(addresses) = self.get_for_seqno(last.seqno) {
+            return Ok(addresses);
+        }
+        // config can only be changed by a key block
+        let header = client.get_block_header(&last).await?;
+        let key_block_seqno = if header.is_key_block {
+            last.seqno
+        } else {
+            header.prev_key_block_seqno
+        };
+        {
+            let mut entry = self.entry.write().map_err(|_| {
+                TonClientError::InternalError("Network addresses cache is poisoned".to_string())
+            })?;
+            if let Some(e) = entry.as_mut() {
+                if e.key_block_seqno == key_block_seqno {
+                    e.mc_seqno = e.mc_seqno.max(last.seqno);
+                    return Ok(e.addresses.clone());
+                }
+            }
+        }
+        let addresses = Arc::new(NetworkAddresses::from_config(client).await?);
+        let mut entry = self.entry.write().map_err(|_| {
+            TonClientError::InternalError("Network addresses cache is poisoned".to_string())
+        })?;
+        *entry = Some(CacheEntry {
+            mc_seqno: last.seqno,
+            key_block_seqno,
+            addresses: addresses.clone(),
+        });
+        Ok(addresses)
+    }
+
+    fn get_for_seqno(&self, mc_seqno: i32) -> Option<Arc<NetworkAddresses>> {
+        let guard = self.entry.read().ok()?;
+        let entry = guard.as_ref()?;
+        (entry.mc_seqno >= mc_seqno).then(|| entry.addresses.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use tonlib_core::cell::dict::predefined_writers::val_writer_ref_cell;
+    use tonlib_core::cell::CellBuilder;
+
+    use super::*;
+
+    fn config_cell(params: &[(u32, Cell)]) -> anyhow::Result<Cell> {
+        let data = params
+            .iter()
+            .map(|(index, cell)| (*index, Arc::new(cell.clone())))
+            .collect::<HashMap<_, _>>();
+        Ok(CellBuilder::new()
+            .store_dict(32, val_writer_ref_cell, data)?
+            .build()?)
+    }
+
+    fn address_param(byte: u8) -> anyhow::Result<Cell> {
+        Ok(CellBuilder::new().store_slice(&[byte; 32])?.build()?)
+    }
+
+    #[test]
+    fn test_network_addresses_from_config() -> anyhow::Result<()> {
+        let burning_config = CellBuilder::new()
+            .store_u8(8, 0x01)?
+            .store_bit(false)?
+            .build()?;
+        let config = config_cell(&[
+            (0, address_param(0x55)?),
+            (1, address_param(0x33)?),
+            (2, address_param(0x00)?),
+            (3, address_param(0x33)?),
+            (4, address_param(0xe5)?),
+            (5, burning_config),
+        ])?;
+        let boc = BagOfCells::from_root(config).serialize(false)?;
+        let addresses = NetworkAddresses::from_config_boc(&boc)?;
+        assert_eq!(
+            addresses.config_address(),
+            &TonAddress::from_base64_url("Ef9VVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVVbxn")?
+        );
+        assert_eq!(
+            addresses.elector_address(),
+            &TonAddress::from_base64_url("Ef8zMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzMzM0vF")?
+        );
+        assert_eq!(addresses.minter_address(), &TonAddress::new(-1, &[0; 32]));
+        assert_eq!(
+            addresses.fee_collector_address(),
+            addresses.elector_address()
+        );
+        assert_eq!(
+            addresses.dns_root_address(),
+            Some(&TonAddress::new(-1, &[0xe5; 32]))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_network_addresses_defaults() -> anyhow::Result<()> {
+        let config = config_cell(&[(0, address_param(0x11)?), (1, address_param(0x22)?)])?;
+        let addresses = NetworkAddresses::from_config_cell(&config)?;
+        assert_eq!(addresses.minter_address(), addresses.config_address());
+        assert_eq!(
+            addresses.fee_collector_address(),
+            addresses.elector_address()
+        );
+        assert_eq!(addresses.dns_root_address(), None);
+
+        let config = config_cell(&[(0, address_param(0x11)?)])?;
+        assert!(NetworkAddresses::from_config_cell(&config).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use std::collections::BTreeMap;
+
+use tonlib_core::cell::dict::predefined_readers::{key_reader_u32, val_reader_ref_cell};
+use tonlib_core::cell::dict::CellDictionary;
+use tonlib_core::cell::{BagOfCells, Cell, CellParser, TonCellError};
+use tonlib_core::constants::MASTERCHAIN_ID;
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::{MapParseError, TonClientError, TonClientInterface};
+
+const CONFIG_PARAM_WORKCHAINS: u32 = 12;
+const WORKCHAIN_DESCR_TAG: u8 = 0xa6;
+const WORKCHAIN_DESCR_V2_TAG: u8 = 0xa7;
+
+/// Format of addresses and VM of a workchain
+///
+/// ```raw
+/// wfmt_basic#1 vm_version:int32 vm_mode:uint64 = WorkchainFormat 1;
+/// wfmt_ext#0 min_addr_len:(## 12) max_addr_len:(## 12) addr_len_step:(## 12)
+///   workchain_type_id:(## 32) = WorkchainFormat 0;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum WorkchainFormat {
+    Basic {
+        vm_version: i32,
+        vm_mode: u64,
+    },
+    Extended {
+        min_addr_len: u16,
+        max_addr_len: u16,
+        addr_len_step: u16,
+        workchain_type_id: u32,
+    },
+}
+
+/// Description of a workchain from config param 12
+///
+/// ```raw
+/// workchain#a6 enabled_since:uint32 actual_min_split:(## 8) min_split:(## 8) max_split:(## 8)
+///   basic:(## 1) active:Bool accept_msgs:Bool flags:(## 13)
+///   zerostate_root_hash:bits256 zerostate_file_hash:bits256
+///   version:uint32 format:(WorkchainFormat basic) = WorkchainDescr;
+/// ```
+///
+/// `workchain_v2#a7` is read the same way, split-merge timings following the format are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WorkchainDescr {
+    pub workchain_id: i32,
+    pub enabled_since: u32,
+    pub actual_min_split: u8,
+    pub min_split: u8,
+    pub max_split: u8,
+    pub basic: bool,
+    pub active: bool,
+    pub accept_msgs: bool,
+    pub zerostate_root_hash: TonHash,
+    pub zerostate_file_hash: TonHash,
+    pub version: u32,
+    pub format: WorkchainFormat,
+}
+
+impl WorkchainDescr {
+    fn parse(workchain_id: i32, parser: &mut CellParser) -> Result<WorkchainDescr, TonCellError> {
+        let tag = parser.load_u8(8)?;
+        if tag != WORKCHAIN_DESCR_TAG && tag != WORKCHAIN_DESCR_V2_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid workchain description tag: {:#x}",
+                tag
+            )));
+        }
+        let enabled_since = parser.load_u32(32)?;
+        let actual_min_split = parser.load_u8(8)?;
+        let min_split = parser.load_u8(8)?;
+        let max_split = parser.load_u8(8)?;
+        let basic = parser.load_bit()?;
+        let active = parser.load_bit()?;
+        let accept_msgs = parser.load_bit()?;
+        let _flags = parser.load_u16(13)?;
+        let mut zerostate_root_hash = [0u8; 32];
+        parser.load_slice(&mut zerostate_root_hash)?;
+        let mut zerostate_file_hash = [0u8; 32];
+        parser.load_slice(&mut zerostate_file_hash)?;
+        let version = parser.load_u32(32)?;
+        let format = match parser.load_u8(4)? {
+            1 => WorkchainFormat::Basic {
+                vm_version: parser.load_i32(32)?,
+                vm_mode: parser.load_u64(64)?,
+            },
+            0 => WorkchainFormat::Extended {
+                min_addr_len: parser.load_u16(12)?,
+                max_addr_len: parser.load_u16(12)?,
+                addr_len_step: parser.load_u16(12)?,
+                workchain_type_id: parser.load_u32(32)?,
+            },
+            tag => {
+                return Err(TonCellError::InvalidCellData(format!(
+                    "Invalid workchain format tag: {:#x}",
+                    tag
+                )))
+            }
+        };
+        Ok(WorkchainDescr {
+            workchain_id,
+            enabled_since,
+            actual_min_split,
+            min_split,
+            max_split,
+            basic,
+            active,
+            accept_msgs,
+            zerostate_root_hash,
+            zerostate_file_hash,
+            version,
+            format,
+        })
+    }
+
+    /// Returns `true` if the workchain is active and accepts messages.
+    pub fn is_enabled(&self) -> bool {
+        self.active && self.accept_msgs
+    }
+}
+
+/// Workchains of the network defined by config param 12
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NetworkTopology {
+    workchains: BTreeMap<i32, WorkchainDescr>,
+}
+
+impl NetworkTopology {
+    /// Loads config param 12 from `client`
+    pub async fn from_config<C: TonClientInterface + ?Sized>(
+        client: &C,
+    ) -> Result<NetworkTopology, TonClientError> {
+        let param = client.get_config_param(0, CONFIG_PARAM_WORKCHAINS).await?;
+        let root = BagOfCells::parse(&param.config.bytes)
+            .and_then(|boc| boc.single_root().cloned())
+            .map_parse_error("config param 12")?;
+        Self::from_param_cell(&root)
+    }
+
+    /// Parses serialized config dictionary, as returned by `get_config_all`
+    pub fn from_config_boc(boc: &[u8]) -> Result<NetworkTopology, TonClientError> {
+        let root = BagOfCells::parse(boc)
+            .and_then(|boc| boc.single_root().cloned())
+            .map_parse_error("config param 12")?;
+        Self::from_config_cell(&root)
+    }
+
+    /// Parses config dictionary (`Hashmap 32 ^Cell`)
+    pub fn from_config_cell(config: &Cell) -> Result<NetworkTopology, TonClientError> {
+        let params = config
+            .parser()
+            .load_dict(32, key_reader_u32, val_reader_ref_cell)
+            .map_parse_error("config param 12")?;
+        let param = params.get(&CONFIG_PARAM_WORKCHAINS).ok_or_else(|| {
+            TonClientError::InternalError("Config param 12 is missing".to_string())
+        })?;
+        Self::from_param_cell(param)
+    }
+
+    /// Parses the value of config param 12 (`HashmapE 32 WorkchainDescr`)
+    pub fn from_param_cell(param: &Cell) -> Result<NetworkTopology, TonClientError> {
+        let dict = CellDictionary::load_hashmap_e(&mut param.parser(), 32)
+            .map_parse_error("config param 12")?;
+        let mut workchains = BTreeMap::new();
+        for (key, value) in dict.iter() {
+            let workchain_id = key.iter().fold(0u32, |acc, bit| (acc << 1) | *bit as u32) as i32;
+            let descr = value
+                .into_cell()
+                .and_then(|cell| WorkchainDescr::parse(workchain_id, &mut cell.parser()))
+                .map_parse_error("config param 12")?;
+            workchains.insert(workchain_id, descr);
+        }
+        Ok(NetworkTopology { workchains })
+    }
+
+    /// Workchains ordered by id, the masterchain isn't included
+    pub fn workchains(&self) -> impl Iterator<Item = &WorkchainDescr> {
+        self.workchains.values()
+    }
+
+    /// Active workchains ordered by id, including those which don't accept messages yet
+    pub fn active_workchains(&self) -> impl Iterator<Item = &WorkchainDescr> {
+        self.workchains().filter(|w| w.active)
+    }
+
+    pub fn workchain(&self, workchain_id: i32) -> Option<&WorkchainDescr> {
+        self.workchains.get(&workchain_id)
+    }
+
+    /// Checks that messages to `address` can be delivered: its workchain is either
+    /// the masterchain or a workchain that is active and accepts messages.
+    pub fn validate_address(&self, address: &TonAddress) -> Result<(), TonClientError> {
+        if address.workchain == MASTERCHAIN_ID {
+            return Ok(());
+        }
+        match self.workchain(address.workchain) {
+            Some(descr) if descr.is_enabled() => Ok(()),
+            _ => Err(TonClientError::WorkchainNotEnabled {
+                address: address.clone(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use tonlib_core::cell::dict::predefined_writers::val_writer_ref_cell;
+    use tonlib_core::cell::CellBuilder;
+
+    use super::*;
+
+    fn store_workchain(
+        builder: &mut CellBuilder,
+        (active, accept_msgs): (bool, bool),
+    ) -> Result<(), TonCellError> {
+        builder
+            .store_u8(8, WORKCHAIN_DESCR_TAG)?
+            .store_u32(32, 1573821854)?
+            .store_u8(8, 0)?
+            .store_u8(8, 0)?
+            .store_u8(8, 8)?
+            .store_bit(true)?
+            .store_bit(active)?
+            .store_bit(accept_msgs)?
+            .store_u32(13, 0)?
+            .store_slice(&[0x11; 32])?
+            .store_slice(&[0x22; 32])?
+            .store_u32(32, 0)?
+            .store_u8(4, 1)?
+            .store_i32(32, 0)?
+            .store_u64(64, 0)?;
+        Ok(())
+    }
+
+    fn config_cell(workchains: HashMap<u32, (bool, bool)>) -> anyhow::Result<Cell> {
+        let param = CellBuilder::new()
+            .store_bit(true)?
+            .store_child(
+                CellBuilder::new()
+                    .store_dict(32, store_workchain, workchains)?
+                    .build()?,
+            )?
+            .build()?;
+        let params = HashMap::from([(CONFIG_PARAM_WORKCHAINS, Arc::new(param))]);
+        Ok(CellBuilder::new()
+            .store_dict(32, val_writer_ref_cell, params)?
+            .build()?)
+    }
+
+    #[test]
+    fn test_network_topology_from_config() -> anyhow::Result<()> {
+        let config = config_cell(HashMap::from([
+            (0, (true, true)),
+            (1, (true, true)),
+            (3, (true, false)),
+        ]))?;
+        let boc = BagOfCells::from_root(config).serialize(false)?;
+        let topology = NetworkTopology::from_config_boc(&boc)?;
+        let ids: Vec<_> = topology.workchains().map(|w| w.workchain_id).collect();
+        assert_eq!(ids, vec![0, 1, 3]);
+        let basechain = topology.workchain(0).unwrap();
+        assert_eq!(basechain.max_split, 8);
+        assert_eq!(basechain.zerostate_root_hash, [0x11; 32]);
+        assert_eq!(
+            basechain.format,
+            WorkchainFormat::Basic {
+                vm_version: 0,
+                vm_mode: 0
+            }
+        );
+
+        let address = |workchain: i32| TonAddress::new(workchain, &[0xab; 32]);
+        assert!(topology.validate_address(&address(1)).is_ok());
+        assert!(topology.validate_address(&address(-1)).is_ok());
+        assert!(matches!(
+            topology.validate_address(&address(2)),
+            Err(TonClientError::WorkchainNotEnabled { .. })
+        ));
+        assert!(topology.validate_address(&address(3)).is_err());
+        assert!(address(1).is_in_shard(1, i64::MIN));
+        Ok(())
+    }
+
+    #[test]
+    fn test_active_workchains() -> anyhow::Result<()> {
+        let config = config_cell(HashMap::from([
+            (0, (true, true)),
+            (2, (false, false)),
+            (3, (true, false)),
+        ]))?;
+        let topology = NetworkTopology::from_config_cell(&config)?;
+        let ids: Vec<_> = topology
+            .active_workchains()
+            .map(|w| w.workchain_id)
+            .collect();
+        assert_eq!(ids, vec![0, 3]);
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+use std::collections::{BTreeMap, HashSet};
+use std::sync::RwLock;
+
+use futures::{stream, StreamExt, TryFutureExt, TryStreamExt};
+
+use crate::client::block_functions::shard_is_ancestor;
+use crate::client::{TonBlockFunctions, TonClient, TonClientError, TonClientInterface};
+use crate::tl::{BlockId, BlockIdExt};
+
+/// Max number of masterchain blocks kept in `TpsCache`
+const MAX_TPS_CACHE_BLOCKS: usize = 4096;
+
+/// Max number of blocks fetched concurrently, applies separately to masterchain blocks
+/// of the window and to the shard blocks of each of them
+const TPS_FETCH_CONCURRENCY: usize = 8;
+
+/// Transactions of masterchain block and of the shard blocks finalized in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BlockTransactions {
+    gen_utime: i64,
+    transactions: u64,
+}
+
+/// Cache of per-block transaction counts used to compute TPS.
+///
+/// Blocks never change once finalized, so consecutive windows only fetch blocks appeared since
+/// the previous call.
+pub(crate) struct TpsCache {
+    blocks: RwLock<BTreeMap<i32, BlockTransactions>>,
+}
+
+impl TpsCache {
+    pub(crate) fn new() -> TpsCache {
+        TpsCache {
+            blocks: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    pub(crate) async fn get_tps(
+        &self,
+        client: &TonClient,
+        window_blocks: u32,
+    ) -> Result<f64, TonClientError> {
+        if window_blocks == 0 {
+            return Err(TonClientError::InternalError(
+                "TPS window must contain at least one block".to_string(),
+            ));
+        }
+        let last = client.masterchain_info_cached().await?.info.last;
+        // the block preceding the window is only used for its gen_utime
+        let first_seqno = (last.seqno - window_blocks as i32).max(1);
+        let seqnos: Vec<i32> = (first_seqno + 1..=last.seqno).collect();
+
+        let (baseline, missing) = {
+            let blocks = self.blocks.read().map_err(|_| poisoned())?;
+            let baseline = blocks.get(&first_seqno).map(|block| block.gen_utime);
+            let missing: Vec<i32> = seqnos
+                .iter()
+                .copied()
+                .filter(|seqno| !blocks.contains_key(seqno))
+                .collect();
+            (baseline, missing)
+        };
+        let baseline = match baseline {
+            Some(gen_utime) => gen_utime,
+            None => {
+                let mc_block = lookup_mc_block(client, first_seqno).await?;
+                client.get_block_header(&mc_block).await?.gen_utime
+            }
+        };
+        let fetched: Vec<(i32, BlockTransactions)> = stream::iter(missing)
+            .map(|seqno| fetch_block_transactions(client, seqno).map_ok(move |b| (seqno, b)))
+            .buffer_unordered(TPS_FETCH_CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut blocks = self.blocks.write().map_err(|_| poisoned())?;
+        blocks.extend(fetched);
+        let mut window = vec![BlockTransactions {
+            gen_utime: baseline,
+            transactions: 0,
+        }];
+        window.extend(seqnos.iter().map(|seqno| blocks[seqno]));
+        while blocks.len() > MAX_TPS_CACHE_BLOCKS {
+            blocks.pop_first();
+        }
+        Ok(compute_tps(&window))
+    }
+}
+
+async fn lookup_mc_block<C: TonClientInterface>(
+    client: &C,
+    seqno: i32,
+) -> Result<BlockIdExt, TonClientError> {
+    let block_id = BlockId {
+        workchain: -1,
+        shard: i64::MIN,
+        seqno,
+    };
+    client.lookup_block(1, &block_id, 0, 0).await
+}
+
+async fn fetch_block_transactions<C: TonClientInterface>(
+    client: &C,
+    seqno: i32,
+) -> Result<BlockTransactions, TonClientError> {
+    let mc_block = lookup_mc_block(client, seqno).await?;
+    let header = client.get_block_header(&mc_block).await?;
+    let prev_mc_block = header
+        .prev_blocks
+        .as_ref()
+        .and_then(|prev_blocks| prev_blocks.first())
+        .ok_or_else(|| {
+            TonClientError::InternalError(format!("Header of block {} has no prev blocks", seqno))
+        })?;
+    let prev_tops = client.get_shards_at_mc_block(prev_mc_block).await?;
+    let tops = client.get_shards_at_mc_block(&mc_block).await?;
+    let mut blocks = new_shard_blocks(client, tops, &prev_tops).await?;
+    blocks.push(mc_block);
+    let counts: Vec<u32> = stream::iter(&blocks)
+        .map(|block| client.count_transactions_in_block(block))
+        .buffer_unordered(TPS_FETCH_CONCURRENCY)
+        .try_collect()
+        .await?;
+    Ok(BlockTransactions {
+        gen_utime: header.gen_utime,
+        transactions: counts.into_iter().map(u64::from).sum(),
+    })
+}
+
+/// Returns shard blocks finalized since the shard tops `prev_tops` up to the shard tops `tops`.
+///
+/// A shard may produce several blocks between masterchain blocks, or none, so the shards
+/// are walked back from `tops` until `prev_tops`, following splits and merges.
+async fn new_shard_blocks<C: TonClientInterface>(
+    client: &C,
+    tops: Vec<BlockIdExt>,
+    prev_tops: &[BlockIdExt],
+) -> Result<Vec<BlockIdExt>, TonClientError> {
+    // each new block descends from a previous top of the same, parent or child shard, so it has
+    // a greater seqno than that top. Seqnos of unrelated shards are independent.
+    let is_new = |block: &BlockIdExt| {
+        !prev_tops.contains(block)
+            && prev_tops
+                .iter()
+                .filter(|top| {
+                    top.workchain == block.workchain
+                        && (shard_is_ancestor(top.shard, block.shard)
+                            || shard_is_ancestor(block.shard, top.shard))
+                })
+                .all(|top| block.seqno > top.seqno)
+    };
+    let mut seen = HashSet::new();
+    let mut pending = tops;
+    let mut blocks = vec![];
+    while let Some(block) = pending.pop() {
+        if !is_new(&block) || !seen.insert(block.clone()) {
+            continue;
+        }
+        pending.extend(client.get_prev_blocks(&block).await?);
+        blocks.push(block);
+    }
+    Ok(blocks)
+}
+
+/// Transactions per second of `blocks` sorted by seqno, excluding transactions of the first one,
+/// which only marks the start of the window
+fn compute_tps(blocks: &[BlockTransactions]) -> f64 {
+    let (Some(first), Some(last)) = (blocks.first(), blocks.last()) else {
+        return 0.0;
+    };
+    let elapsed = last.gen_utime - first.gen_utime;
+    if elapsed <= 0 {
+        return 0.0;
+    }
+    let transactions: u64 = blocks[1..].iter().map(|b| b.transactions).sum();
+    transactions as f64 / elapsed as f64
+}
+
+fn poisoned() -> TonClientError {
+    TonClientError::InternalError("TPS cache is poisoned".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::mock::{block_header, block_id, mc_block, MockClient};
+    use crate::tl::{BlocksShards, BlocksShortTxId, BlocksTransactions, TonFunction, TonResult};
+
+    const LEFT: u64 = 0x2000000000000000;
+    const PARENT: u64 = 0x4000000000000000;
+    const RIGHT: u64 = 0x6000000000000000;
+    const IDLE: u64 = 0xc000000000000000;
+
+    /// Client serving masterchain block 2, after which the shard of masterchain block 1
+    /// has split and its right half has produced two blocks, while the other shard has
+    /// produced none. Each block holds `seqno` transactions.
+    fn shards_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::BlocksLookupBlock { id, .. } => {
+                Ok(TonResult::BlockIdExt(mc_block(id.seqno)))
+            }
+            TonFunction::GetBlockHeader { id } => {
+                let prev = match (id.workchain, id.shard as u64, id.seqno) {
+                    (-1, _, seqno) => mc_block(seqno - 1),
+                    (0, RIGHT, 12) => block_id(0, RIGHT, 11),
+                    (0, LEFT | RIGHT, 11) => block_id(0, PARENT, 10),
+                    _ => unimplemented!("{:?}", id),
+                };
+                Ok(TonResult::BlocksHeader(block_header(id, vec![prev])))
+            }
+            TonFunction::BlocksGetShards { id } => {
+                let shards = match id.seqno {
+                    1 => vec![block_id(0, PARENT, 10), block_id(0, IDLE, 7)],
+                    _ => vec![
+                        block_id(0, LEFT, 11),
+                        block_id(0, RIGHT, 12),
+                        block_id(0, IDLE, 7),
+                    ],
+                };
+                Ok(TonResult::BlocksShards(BlocksShards { shards }))
+            }
+            TonFunction::BlocksGetTransactions { id, .. } => {
+                Ok(TonResult::BlocksTransactions(BlocksTransactions {
+                    id: id.clone(),
+                    req_count: 256,
+                    incomplete: false,
+                    transactions: (0..id.seqno as i64)
+                        .map(|lt| BlocksShortTxId {
+                            mode: 7,
+                            account: vec![lt as u8; 32],
+                            lt,
+                            hash: vec![0; 32],
+                        })
+                        .collect(),
+                }))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_fetch_block_transactions() -> anyhow::Result<()> {
+        let fetched = fetch_block_transactions(&shards_client(), 2).await?;
+        // masterchain block 2, left 11, right 11 & 12, the idle shard isn't counted again
+        assert_eq!(fetched, block(2, 2 + 11 + 11 + 12));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_new_shard_blocks_independent_seqnos() -> anyhow::Result<()> {
+        // the shards of the split workchain are far apart in seqnos
+        let client = MockClient::new(|function| match function {
+            TonFunction::GetBlockHeader { id } => {
+                let prev = block_id(id.workchain, id.shard as u64, id.seqno - 1);
+                Ok(TonResult::BlocksHeader(block_header(id, vec![prev])))
+            }
+            _ => unimplemented!("{:?}", function),
+        });
+        let prev_tops = [block_id(0, PARENT, 1000), block_id(0, IDLE, 5)];
+        let tops = vec![block_id(0, PARENT, 1001), block_id(0, IDLE, 7)];
+        let mut blocks = new_shard_blocks(&client, tops, &prev_tops).await?;
+        blocks.sort_by_key(|block| block.seqno);
+        assert_eq!(
+            blocks,
+            vec![
+                block_id(0, IDLE, 6),
+                block_id(0, IDLE, 7),
+                block_id(0, PARENT, 1001)
+            ]
+        );
+        Ok(())
+    }
+
+    fn block(gen_utime: i64, transactions: u64) -> BlockTransactions {
+        BlockTransactions {
+            gen_utime,
+            transactions,
+        }
+    }
+
+    #[test]
+    fn test_compute_tps() {
+        let blocks = [block(100, 1000), block(102, 30), block(105, 50)];
+        assert_eq!(compute_tps(&blocks), 16.0);
+        assert_eq!(compute_tps(&blocks[..1]), 0.0);
+        assert_eq!(compute_tps(&[]), 0.0);
+        assert_eq!(compute_tps(&[block(100, 1), block(100, 5)]), 0.0);
+    }
+}
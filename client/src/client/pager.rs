@@ -0,0 +1,490 @@
+use std::mem;
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt, TryStreamExt};
+use tonlib_core::TonAddress;
+
+use crate::client::{TonClientError, TonClientInterface};
+use crate::tl::{
+    BlockIdExt, BlocksAccountTransactionId, BlocksShortTxId, InternalTransactionId, RawTransaction,
+    SmcLibraryEntry, TonLibraryId, NULL_BLOCKS_ACCOUNT_TRANSACTION_ID,
+};
+
+/// Max number of transactions in a page of `blocks.getTransactions` & `blocks.getTransactionsExt`
+pub const BLOCK_TRANSACTIONS_PAGE_SIZE: u32 = 256;
+/// Max number of libraries requested by a single `smc.getLibraries`, as limited by liteservers
+pub const LIBRARIES_PAGE_SIZE: usize = 16;
+
+/// Pages fetched one after another, each request continuing from the previous page
+#[async_trait]
+pub trait PageSource<T>: Send {
+    /// Fetches the next page, `None` once there are no more pages
+    async fn fetch_page(&mut self) -> Result<Option<Vec<T>>, TonClientError>;
+
+    /// Expected total number of items, if it's known upfront
+    fn total_hint(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Paginated results, fetched lazily page by page.
+///
+/// Pages are requested only when more items are consumed, so stopping early,
+/// e.g. with `take_items`, doesn't fetch further pages.
+pub struct Pager<'a, T> {
+    source: Box<dyn PageSource<T> + 'a>,
+    /// Items of the last page not consumed by `take_items`
+    buffered: Vec<T>,
+    exhausted: bool,
+}
+
+impl<'a, T: Send + 'a> Pager<'a, T> {
+    pub fn new<S: PageSource<T> + 'a>(source: S) -> Pager<'a, T> {
+        Pager {
+            source: Box::new(source),
+            buffered: vec![],
+            exhausted: false,
+        }
+    }
+
+    /// Returns the next page, `None` once all pages are consumed
+    pub async fn next_page(&mut self) -> Result<Option<Vec<T>>, TonClientError> {
+        if !self.buffered.is_empty() {
+            return Ok(Some(mem::take(&mut self.buffered)));
+        }
+        if self.exhausted {
+            return Ok(None);
+        }
+        let page = self.source.fetch_page().await?;
+        self.exhausted = page.is_none();
+        Ok(page)
+    }
+
+    /// Expected total number of items, if the source knows it upfront
+    pub fn total_hint(&self) -> Option<usize> {
+        self.source.total_hint()
+    }
+
+    /// Returns up to `n` next items, fetching no more pages than needed.
+    ///
+    /// Items of the last fetched page beyond `n` are returned by subsequent calls.
+    pub async fn take_items(&mut self, n: usize) -> Result<Vec<T>, TonClientError> {
+        let mut items = Vec::new();
+        while items.len() < n {
+            let Some(mut page) = self.next_page().await? else {
+                break;
+            };
+            let wanted = n - items.len();
+            if page.len() > wanted {
+                self.buffered = page.split_off(wanted);
+            }
+            items.append(&mut page);
+        }
+        Ok(items)
+    }
+
+    /// Collects all remaining items, failing as soon as more than `limit` are fetched, if it's set
+    pub async fn collect_all(mut self, limit: Option<usize>) -> Result<Vec<T>, TonClientError> {
+        let mut items = Vec::new();
+        while let Some(mut page) = self.next_page().await? {
+            items.append(&mut page);
+            if let Some(limit) = limit.filter(|limit| items.len() > *limit) {
+                return Err(TonClientError::InternalError(format!(
+                    "Pager has more than {} items",
+                    limit
+                )));
+            }
+        }
+        Ok(items)
+    }
+
+    /// Returns the remaining items one by one, fetching pages as they are consumed
+    pub fn into_stream(self) -> BoxStream<'a, Result<T, TonClientError>> {
+        stream::try_unfold(self, |mut pager| async move {
+            let page = pager.next_page().await?;
+            Ok::<_, TonClientError>(
+                page.map(|page| (stream::iter(page.into_iter().map(Ok)), pager)),
+            )
+        })
+        .try_flatten()
+        .boxed()
+    }
+}
+
+/// Transactions of an account from the newest to the oldest, linked by `previous_transaction_id`
+pub(crate) struct AccountTransactionsSource<'a, C: ?Sized> {
+    pub(crate) client: &'a C,
+    pub(crate) address: TonAddress,
+    pub(crate) next: InternalTransactionId,
+    pub(crate) page_size: usize,
+}
+
+#[async_trait]
+impl<C: TonClientInterface + ?Sized> PageSource<RawTransaction>
+    for AccountTransactionsSource<'_, C>
+{
+    async fn fetch_page(&mut self) -> Result<Option<Vec<RawTransaction>>, TonClientError> {
+        if self.next.lt == 0 {
+            return Ok(None);
+        }
+        let page = self
+            .client
+            .get_raw_transactions_v2(&self.address, &self.next, self.page_size, false)
+            .await?;
+        if page.transactions.is_empty() {
+            return Ok(None);
+        }
+        self.next = page.previous_transaction_id;
+        Ok(Some(page.transactions))
+    }
+}
+
+/// Transactions of a block in the order of accounts, continuing after the last returned one
+pub(crate) struct BlockTransactionsSource<'a, C: ?Sized> {
+    pub(crate) client: &'a C,
+    pub(crate) block_id: BlockIdExt,
+    pub(crate) after: BlocksAccountTransactionId,
+    pub(crate) incomplete: bool,
+}
+
+impl<'a, C: ?Sized> BlockTransactionsSource<'a, C> {
+    pub(crate) fn new(client: &'a C, block_id: &BlockIdExt) -> BlockTransactionsSource<'a, C> {
+        BlockTransactionsSource {
+            client,
+            block_id: block_id.clone(),
+            after: NULL_BLOCKS_ACCOUNT_TRANSACTION_ID.clone(),
+            incomplete: true,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: TonClientInterface + ?Sized> PageSource<RawTransaction> for BlockTransactionsSource<'_, C> {
+    async fn fetch_page(&mut self) -> Result<Option<Vec<RawTransaction>>, TonClientError> {
+        if !self.incomplete {
+            return Ok(None);
+        }
+        let mode = if self.after.lt == 0 { 7 } else { 128 + 7 };
+        let page = self
+            .client
+            .get_block_transactions_ext(
+                &self.block_id,
+                mode,
+                BLOCK_TRANSACTIONS_PAGE_SIZE,
+                &self.after,
+            )
+            .await?;
+        if let Some(last) = page.transactions.last() {
+            let account = last
+                .address
+                .account_address
+                .parse::<TonAddress>()?
+                .hash_part
+                .to_vec();
+            self.after = BlocksAccountTransactionId {
+                account,
+                lt: last.transaction_id.lt,
+            };
+        }
+        self.incomplete = page.incomplete;
+        Ok(Some(page.transactions))
+    }
+}
+
+/// IDs of transactions of a block in the order of accounts, continuing after the last returned one
+pub(crate) struct BlockTransactionIdsSource<'a, C: ?Sized> {
+    pub(crate) client: &'a C,
+    pub(crate) block_id: BlockIdExt,
+    pub(crate) after: BlocksAccountTransactionId,
+    pub(crate) incomplete: bool,
+}
+
+impl<'a, C: ?Sized> BlockTransactionIdsSource<'a, C> {
+    pub(crate) fn new(client: &'a C, block_id: &BlockIdExt) -> BlockTransactionIdsSource<'a, C> {
+        BlockTransactionIdsSource {
+            client,
+            block_id: block_id.clone(),
+            after: NULL_BLOCKS_ACCOUNT_TRANSACTION_ID.clone(),
+            incomplete: true,
+        }
+    }
+}
+
+#[async_trait]
+impl<C: TonClientInterface + ?Sized> PageSource<BlocksShortTxId>
+    for BlockTransactionIdsSource<'_, C>
+{
+    async fn fetch_page(&mut self) -> Result<Option<Vec<BlocksShortTxId>>, TonClientError> {
+        if !self.incomplete {
+            return Ok(None);
+        }
+        let mode = if self.after.lt == 0 { 7 } else { 128 + 7 };
+        let page = self
+            .client
+            .get_block_transactions(
+                &self.block_id,
+                mode,
+                BLOCK_TRANSACTIONS_PAGE_SIZE,
+                &self.after,
+            )
+            .await?;
+        if let Some(last) = page.transactions.last() {
+            self.after = BlocksAccountTransactionId {
+                account: last.account.clone(),
+                lt: last.lt,
+            };
+        }
+        self.incomplete = page.incomplete;
+        Ok(Some(page.transactions))
+    }
+}
+
+/// Libraries requested in chunks of `LIBRARIES_PAGE_SIZE`
+pub(crate) struct LibrariesSource<'a, C: ?Sized> {
+    pub(crate) client: &'a C,
+    pub(crate) remaining: Vec<TonLibraryId>,
+    pub(crate) total: usize,
+}
+
+impl<'a, C: ?Sized> LibrariesSource<'a, C> {
+    pub(crate) fn new(client: &'a C, library_list: &[TonLibraryId]) -> LibrariesSource<'a, C> {
+        LibrariesSource {
+            client,
+            remaining: library_list.to_vec(),
+            total: library_list.len(),
+        }
+    }
+}
+
+#[async_trait]
+impl<C: TonClientInterface + ?Sized> PageSource<SmcLibraryEntry> for LibrariesSource<'_, C> {
+    async fn fetch_page(&mut self) -> Result<Option<Vec<SmcLibraryEntry>>, TonClientError> {
+        if self.remaining.is_empty() {
+            return Ok(None);
+        }
+        let rest = self
+            .remaining
+            .split_off(LIBRARIES_PAGE_SIZE.min(self.remaining.len()));
+        let chunk = mem::replace(&mut self.remaining, rest);
+        let result = self.client.smc_get_libraries(&chunk).await?;
+        Ok(Some(result.result))
+    }
+
+    /// Number of requested libraries, missing ones are not returned
+    fn total_hint(&self) -> Option<usize> {
+        Some(self.total)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::client::mock::{block_id, raw_transaction, MockClient};
+    use crate::client::{TonBlockFunctions, TonTransactionFunctions};
+    use crate::tl::{
+        BlocksTransactions, BlocksTransactionsExt, RawTransactions, SmcLibraryResult, TonFunction,
+        TonResult,
+    };
+
+    /// Pages of consecutive numbers, counting fetched pages
+    struct NumberSource {
+        fetched: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl PageSource<u32> for NumberSource {
+        async fn fetch_page(&mut self) -> Result<Option<Vec<u32>>, TonClientError> {
+            let pages = [vec![1, 2, 3], vec![4, 5, 6], vec![7]];
+            let page = pages.get(self.fetched.load(Ordering::SeqCst)).cloned();
+            if page.is_some() {
+                self.fetched.fetch_add(1, Ordering::SeqCst);
+            }
+            Ok(page)
+        }
+    }
+
+    fn number_pager() -> (Pager<'static, u32>, Arc<AtomicUsize>) {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let source = NumberSource {
+            fetched: fetched.clone(),
+        };
+        (Pager::new(source), fetched)
+    }
+
+    fn transaction(lt: i64) -> RawTransaction {
+        raw_transaction(&TonAddress::new(0, &[lt as u8; 32]), lt)
+    }
+
+    /// Client serving 10 account transactions, 600 block transactions with their IDs and any library
+    fn paged_client() -> MockClient {
+        MockClient::new(|function| match function {
+            TonFunction::RawGetTransactionsV2 {
+                from_transaction_id,
+                count,
+                ..
+            } => {
+                let from = from_transaction_id.lt;
+                let end = (from - *count as i64).max(0);
+                Ok(TonResult::RawTransactions(RawTransactions {
+                    transactions: (end + 1..=from).rev().map(transaction).collect(),
+                    previous_transaction_id: InternalTransactionId {
+                        lt: end,
+                        hash: vec![0; 32],
+                    },
+                }))
+            }
+            TonFunction::BlocksGetTransactionsExt {
+                id, count, after, ..
+            } => {
+                let start = after.lt + 1;
+                let end = (start + *count as i64).min(601);
+                Ok(TonResult::BlocksTransactionsExt(BlocksTransactionsExt {
+                    id: id.clone(),
+                    req_count: *count as i32,
+                    incomplete: end <= 600,
+                    transactions: (start..end).map(transaction).collect(),
+                }))
+            }
+            TonFunction::BlocksGetTransactions {
+                id, count, after, ..
+            } => {
+                let start = after.lt + 1;
+                let end = (start + *count as i64).min(601);
+                Ok(TonResult::BlocksTransactions(BlocksTransactions {
+                    id: id.clone(),
+                    req_count: *count as i32,
+                    incomplete: end <= 600,
+                    transactions: (start..end)
+                        .map(|lt| BlocksShortTxId {
+                            mode: 7,
+                            account: vec![lt as u8; 32],
+                            lt,
+                            hash: vec![0; 32],
+                        })
+                        .collect(),
+                }))
+            }
+            TonFunction::SmcGetLibraries { library_list } => {
+                Ok(TonResult::SmcLibraryResult(SmcLibraryResult {
+                    result: library_list
+                        .iter()
+                        .map(|id| SmcLibraryEntry {
+                            hash: id.id.clone(),
+                            data: vec![],
+                        })
+                        .collect(),
+                }))
+            }
+            _ => unimplemented!("{:?}", function),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_pager() -> anyhow::Result<()> {
+        let (mut pager, fetched) = number_pager();
+        assert_eq!(pager.take_items(4).await?, vec![1, 2, 3, 4]);
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+        assert_eq!(pager.next_page().await?, Some(vec![5, 6]));
+        assert_eq!(pager.take_items(5).await?, vec![7]);
+        assert_eq!(pager.next_page().await?, None);
+        assert_eq!(fetched.load(Ordering::SeqCst), 3);
+
+        let (mut pager, _) = number_pager();
+        assert_eq!(pager.take_items(2).await?, vec![1, 2]);
+        let rest: Vec<u32> = pager.into_stream().try_collect().await?;
+        assert_eq!(rest, vec![3, 4, 5, 6, 7]);
+
+        let (pager, fetched) = number_pager();
+        assert!(pager.collect_all(Some(5)).await.is_err());
+        // fails on the page exceeding the limit
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+        let (pager, _) = number_pager();
+        assert_eq!(pager.collect_all(Some(7)).await?.len(), 7);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_account_transactions_pager() -> anyhow::Result<()> {
+        let client = paged_client();
+        let from = InternalTransactionId {
+            lt: 10,
+            hash: vec![0; 32],
+        };
+        let mut pager = client.account_transactions_pager(&TonAddress::NULL, &from, 4);
+        let lts: Vec<i64> = pager
+            .take_items(6)
+            .await?
+            .iter()
+            .map(|tx| tx.transaction_id.lt)
+            .collect();
+        assert_eq!(lts, vec![10, 9, 8, 7, 6, 5]);
+        assert_eq!(client.calls(), 2);
+
+        let rest = pager.collect_all(None).await?;
+        assert_eq!(rest.len(), 4);
+        assert_eq!(client.calls(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_transactions_pager() -> anyhow::Result<()> {
+        let client = paged_client();
+        let block = block_id(0, 0x8000000000000000, 1);
+        let first: Vec<RawTransaction> = client
+            .block_transactions_pager(&block)
+            .into_stream()
+            .take(10)
+            .try_collect()
+            .await?;
+        assert_eq!(first.len(), 10);
+        assert_eq!(client.calls(), 1);
+
+        let all = client.get_shard_transactions(&block).await?;
+        assert_eq!(all.len(), 600);
+        assert_eq!(client.calls(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_transaction_ids_pager() -> anyhow::Result<()> {
+        let client = paged_client();
+        let block = block_id(0, 0x8000000000000000, 1);
+        let mut pager = client.block_transaction_ids_pager(&block);
+        let first = pager.next_page().await?.unwrap_or_default();
+        assert_eq!(first.len(), 256);
+        assert_eq!(client.calls(), 1);
+
+        let ids = client.get_shard_tx_ids(&block).await?;
+        assert_eq!(ids.len(), 600);
+        assert_eq!(
+            ids.iter()
+                .map(|id| id.internal_transaction_id.lt)
+                .collect::<Vec<_>>(),
+            (1..=600).collect::<Vec<_>>()
+        );
+        assert_eq!(client.calls(), 4);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_libraries_pager() -> anyhow::Result<()> {
+        let client = paged_client();
+        let ids: Vec<TonLibraryId> = (0..40u8)
+            .map(|i| TonLibraryId { id: vec![i; 32] })
+            .collect();
+        let mut pager = client.smc_libraries_pager(&ids);
+        assert_eq!(pager.total_hint(), Some(40));
+        assert_eq!(pager.take_items(5).await?.len(), 5);
+        assert_eq!(client.calls(), 1);
+
+        let libraries = client.get_libraries(&ids).await?;
+        assert_eq!(libraries.len(), 40);
+        assert_eq!(libraries[39].hash, vec![39; 32]);
+        assert_eq!(client.calls(), 4);
+        Ok(())
+    }
+}
@@ -1,20 +1,34 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
 use anyhow::bail;
 use futures::future::join_all;
+use sha2::{Digest, Sha256};
 use ton_liteapi::tl::response::BlockData;
-use tonlib_core::cell::BagOfCells;
+use tonlib_core::cell::{ArcCell, BagOfCells, Cell};
 use tonlib_core::constants::{MASTERCHAIN_ID, SHARD_FULL};
+use tonlib_core::types::Coins;
+use tonlib_core::TonHash;
 
 use crate::client::recent_init_block::lite::Connection;
+use crate::client::shard_sync::{apply_block_state_update, MERKLE_UPDATE_TYPE, STATE_UPDATE_REF};
+use crate::client::supply_info::parse_global_balance_proof;
+use crate::client::{TonClientError, TransportOptions};
 use crate::config::LiteEndpoint;
 use crate::tl::BlockIdExt;
 
 const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+/// Bytes received for a shard state besides its BoC: the ADNL handshake, framing and TL fields
+const STATE_RESPONSE_OVERHEAD: usize = 4096;
 
-pub(crate) async fn get_recent_init_block(endpoints: &[LiteEndpoint]) -> Option<BlockIdExt> {
+pub(crate) async fn get_recent_init_block(
+    endpoints: &[LiteEndpoint],
+    options: &TransportOptions,
+) -> Option<BlockIdExt> {
     log::info!("Trying to update init_block...");
     let keyblocks_f = endpoints
         .iter()
-        .map(|endpoint| get_last_keyblock(endpoint.clone()));
+        .map(|endpoint| get_last_keyblock(endpoint.clone(), options));
 
     let keyblocks_res = join_all(keyblocks_f).await;
 
@@ -35,8 +49,141 @@ pub(crate) async fn get_recent_init_block(endpoints: &[LiteEndpoint]) -> Option<
         .max_by_key(|block| block.seqno)
 }
 
-async fn get_last_keyblock(endpoint: LiteEndpoint) -> anyhow::Result<BlockIdExt> {
+/// Checks that the liteserver answers a single `getMasterchainInfo` request.
+pub(crate) async fn probe_liteserver(endpoint: LiteEndpoint) -> anyhow::Result<()> {
+    let mut conn = Connection::new(endpoint)?;
+    conn.get_mc_info().await?;
+    Ok(())
+}
+
+/// Measures round-trips of `sample_count` sequential `getMasterchainInfo` requests.
+///
+/// The connection is established before the first sample, so the handshake is not measured.
+pub(crate) async fn measure_liteserver_latency(
+    endpoint: LiteEndpoint,
+    sample_count: usize,
+) -> anyhow::Result<Vec<Duration>> {
+    let mut conn = Connection::new(endpoint)?;
+    conn.handshake().await?;
+    let mut samples = Vec::with_capacity(sample_count);
+    for _ in 0..sample_count {
+        let start = Instant::now();
+        conn.get_mc_info().await?;
+        samples.push(start.elapsed());
+    }
+    Ok(samples)
+}
+
+/// Downloads the shard state after applying `block_id`, returns its BoC.
+///
+/// The BoC is checked against the file hash reported by the liteserver. The download is dropped
+/// with `TonClientError::ResponseTooLarge` as soon as more than `max_size_bytes` are received.
+pub(crate) async fn get_shard_state(
+    endpoint: LiteEndpoint,
+    block_id: &BlockIdExt,
+    max_size_bytes: Option<usize>,
+) -> anyhow::Result<Vec<u8>> {
     let mut conn = Connection::new(endpoint)?;
+    if let Some(limit) = max_size_bytes {
+        // the response carries the BoC together with ids & hashes of the block
+        conn = conn.with_read_limit(limit.saturating_add(STATE_RESPONSE_OVERHEAD));
+    }
+    let too_large = |size| TonClientError::ResponseTooLarge {
+        method: "liteServer.getState",
+        size,
+        limit: max_size_bytes.unwrap_or_default(),
+    };
+    let state = match conn.get_state(block_id).await {
+        Ok(state) => state,
+        Err(_) if conn.read_limit_exceeded() => return Err(too_large(conn.received()).into()),
+        Err(e) => return Err(e),
+    };
+    if max_size_bytes.is_some_and(|limit| state.data.len() > limit) {
+        return Err(too_large(state.data.len()).into());
+    }
+    if Sha256::digest(&state.data).as_slice() != state.file_hash.0 {
+        bail!(
+            "Shard state of block {} doesn't match its file hash",
+            block_id.seqno
+        );
+    }
+    Ok(state.data)
+}
+
+/// Downloads `blocks` one by one and applies their state updates to `base_state`,
+/// returns the state after the last block. Hashes of the computed states are checked
+/// against the state updates.
+pub(crate) async fn apply_state_updates(
+    endpoint: LiteEndpoint,
+    blocks: &[BlockIdExt],
+    base_state: &ArcCell,
+) -> anyhow::Result<Cell> {
+    let mut conn = Connection::new(endpoint)?;
+    let mut state = base_state.clone();
+    for block_id in blocks {
+        let block = conn.get_block_by_id(block_id).await?;
+        state = apply_block_state_update(&state, &block.data, &block_id.root_hash)?;
+    }
+    Ok(state.as_ref().clone())
+}
+
+/// Requests the proof of the masterchain state extra at `block_id` and returns `global_balance`.
+///
+/// Only the path from the state root to `McStateExtra` is proven, so the response stays small
+/// unlike the full state. The proof is checked against the state hash proven for the block.
+pub(crate) async fn get_global_balance(
+    endpoint: LiteEndpoint,
+    block_id: &BlockIdExt,
+) -> anyhow::Result<Coins> {
+    let mut conn = Connection::new(endpoint)?;
+    let config = conn.get_state_extra_proof(block_id).await?;
+    let state_hash = parse_state_hash(&config.state_proof, &block_id.root_hash)?;
+    Ok(parse_global_balance_proof(
+        &config.config_proof,
+        &state_hash,
+    )?)
+}
+
+/// Requests the header of `block_id` with the state update and returns the new state hash.
+pub(crate) async fn get_block_state_hash(
+    endpoint: LiteEndpoint,
+    block_id: &BlockIdExt,
+) -> anyhow::Result<TonHash> {
+    let mut conn = Connection::new(endpoint)?;
+    let proof = conn.get_block_header_proof(block_id).await?;
+    parse_state_hash(&proof, &block_id.root_hash)
+}
+
+/// Reads the new state hash from the Merkle update in the block header proof.
+///
+/// The proof must be built for the block with `root_hash`.
+fn parse_state_hash(header_proof: &[u8], root_hash: &[u8]) -> anyhow::Result<TonHash> {
+    let boc = BagOfCells::parse(header_proof)?;
+    let proof = boc.single_root()?;
+    let block = proof.reference(0)?;
+    if block.get_hash(0).as_slice() != root_hash {
+        bail!(
+            "Header proof is built for block {}, expected {}",
+            hex::encode(block.get_hash(0)),
+            hex::encode(root_hash)
+        );
+    }
+    let state_update = block.reference(STATE_UPDATE_REF)?;
+    let data = state_update.data();
+    if !state_update.is_exotic() || data.first() != Some(&MERKLE_UPDATE_TYPE) {
+        bail!("State update is missing in the header proof");
+    }
+    // type(8), old_hash(256), new_hash(256), old_depth(16), new_depth(16)
+    let mut new_hash = [0u8; 32];
+    new_hash.copy_from_slice(&data[33..65]);
+    Ok(new_hash)
+}
+
+async fn get_last_keyblock(
+    endpoint: LiteEndpoint,
+    options: &TransportOptions,
+) -> anyhow::Result<BlockIdExt> {
+    let mut conn = Connection::new(endpoint)?.with_transport_options(options);
     let mc_info = conn.get_mc_info().await?;
     let block = conn.get_block(mc_info.last).await?;
     let seqno = parse_key_block_seqno(&block)?;
@@ -71,45 +218,59 @@ fn parse_key_block_seqno(block: &BlockData) -> anyhow::Result<u32> {
     Ok(key_block_seqno)
 }
 
-mod lite {
+pub(crate) mod lite {
     use std::error::Error;
     use std::net::{Ipv4Addr, SocketAddrV4};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
     use std::time::Duration;
 
     use adnl::AdnlPeer;
     use base64::prelude::BASE64_STANDARD;
     use base64::Engine;
     use tokio::net::TcpStream;
-    use tokio::time::timeout;
     use tokio_tower::multiplex::Client;
     use ton_liteapi::layers::{WrapMessagesLayer, WrapService};
     use ton_liteapi::peer::LitePeer;
     use ton_liteapi::tl::adnl::Message;
-    use ton_liteapi::tl::common::BlockIdExt as BlockIdExtLite;
+    use ton_liteapi::tl::common::{BlockIdExt as BlockIdExtLite, Int256};
     use ton_liteapi::tl::request::{
-        GetBlock, LookupBlock, Request, WaitMasterchainSeqno, WrappedRequest,
+        GetBlock, GetBlockHeader, GetConfigParams, GetMasterchainInfoExt, GetState, LookupBlock,
+        Request, WaitMasterchainSeqno, WrappedRequest,
+    };
+    use ton_liteapi::tl::response::{
+        BlockData, BlockHeader, BlockState, ConfigInfo, MasterchainInfo, MasterchainInfoExt,
+        Response,
     };
-    use ton_liteapi::tl::response::{BlockData, BlockHeader, MasterchainInfo, Response};
     use ton_liteapi::types::LiteError;
     use tonlib_core::constants::{MASTERCHAIN_ID, SHARD_FULL};
     use tower::{Service, ServiceBuilder, ServiceExt};
 
+    use crate::client::transport::{apply_transport_options, ReadLimit, TransportOptions};
     use crate::config::LiteEndpoint;
+    use crate::rt::timeout;
+    use crate::tl::BlockIdExt;
 
     const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
     const REQ_TIMEOUT: Duration = Duration::from_secs(10);
+    /// Shard states take several MB, so they are given more time than other requests
+    const STATE_REQ_TIMEOUT: Duration = Duration::from_secs(120);
 
-    type ConnService =
-        WrapService<Client<LitePeer<AdnlPeer<TcpStream>>, Box<dyn Error + Sync + Send>, Message>>;
+    type ConnService = WrapService<
+        Client<LitePeer<AdnlPeer<ReadLimit<TcpStream>>>, Box<dyn Error + Sync + Send>, Message>,
+    >;
 
-    pub(super) struct Connection {
+    pub(crate) struct Connection {
         public: Vec<u8>,
         addr: SocketAddrV4,
+        options: TransportOptions,
+        read_limit: usize,
+        received: Arc<AtomicUsize>,
         service: Option<ConnService>,
     }
 
     impl Connection {
-        pub(super) fn new(endpoint: LiteEndpoint) -> anyhow::Result<Self> {
+        pub(crate) fn new(endpoint: LiteEndpoint) -> anyhow::Result<Self> {
             let LiteEndpoint { ip, port, id } = endpoint;
             let ip_addr = Ipv4Addr::from(ip as u32);
             let public = BASE64_STANDARD.decode(id.key)?;
@@ -117,11 +278,36 @@ mod lite {
             let conn = Self {
                 public,
                 addr,
+                options: TransportOptions::default(),
+                read_limit: usize::MAX,
+                received: Arc::new(AtomicUsize::new(0)),
                 service: None,
             };
             Ok(conn)
         }
 
+        /// Makes reads fail once more than `limit` bytes are received over the connection,
+        /// including the handshake
+        pub(crate) fn with_read_limit(mut self, limit: usize) -> Self {
+            self.read_limit = limit;
+            self
+        }
+
+        /// Number of bytes received over the connection
+        pub(crate) fn received(&self) -> usize {
+            self.received.load(Ordering::SeqCst)
+        }
+
+        pub(crate) fn read_limit_exceeded(&self) -> bool {
+            self.received() > self.read_limit
+        }
+
+        /// Sets options applied to the socket of the connection
+        pub(crate) fn with_transport_options(mut self, options: &TransportOptions) -> Self {
+            self.options = *options;
+            self
+        }
+
         pub(super) async fn get_block(
             &mut self,
             block_id: BlockIdExtLite,
@@ -139,7 +325,24 @@ mod lite {
             }
         }
 
-        pub(super) async fn get_mc_header(&mut self, seqno: u32) -> anyhow::Result<BlockHeader> {
+        /// Returns the block `block_id`, which may belong to any shard.
+        pub(crate) async fn get_block_by_id(
+            &mut self,
+            block_id: &BlockIdExt,
+        ) -> anyhow::Result<BlockData> {
+            let req = WrappedRequest {
+                wait_masterchain_seqno: None,
+                request: Request::GetBlock(GetBlock {
+                    id: to_lite_block_id(block_id)?,
+                }),
+            };
+            match self.execute(req).await? {
+                Response::BlockData(block) => Ok(block),
+                _ => Err(LiteError::UnexpectedMessage)?,
+            }
+        }
+
+        pub(crate) async fn get_mc_header(&mut self, seqno: u32) -> anyhow::Result<BlockHeader> {
             let req = WrappedRequest {
                 wait_masterchain_seqno: None,
                 request: Request::LookupBlock(LookupBlock {
@@ -165,6 +368,78 @@ mod lite {
             }
         }
 
+        /// Returns the BoC of the header proof of `block_id` including its state update.
+        pub(crate) async fn get_block_header_proof(
+            &mut self,
+            block_id: &BlockIdExt,
+        ) -> anyhow::Result<Vec<u8>> {
+            let id = to_lite_block_id(block_id)?;
+            let req = WrappedRequest {
+                wait_masterchain_seqno: None,
+                request: Request::GetBlockHeader(GetBlockHeader {
+                    id,
+                    mode: (),
+                    with_state_update: Some(()),
+                    with_value_flow: None,
+                    with_extra: None,
+                    with_shard_hashes: None,
+                    with_prev_blk_signatures: None,
+                }),
+            };
+            match self.execute(req).await? {
+                Response::BlockHeader(header) => Ok(header.header_proof),
+                _ => Err(LiteError::UnexpectedMessage)?,
+            }
+        }
+
+        /// Returns the full shard state after applying `block_id`.
+        pub(crate) async fn get_state(
+            &mut self,
+            block_id: &BlockIdExt,
+        ) -> anyhow::Result<BlockState> {
+            let req = WrappedRequest {
+                wait_masterchain_seqno: None,
+                request: Request::GetState(GetState {
+                    id: to_lite_block_id(block_id)?,
+                }),
+            };
+            match self.execute_with_timeout(req, STATE_REQ_TIMEOUT).await? {
+                Response::BlockState(state) => Ok(state),
+                _ => Err(LiteError::UnexpectedMessage)?,
+            }
+        }
+
+        /// Returns proofs of the masterchain state root & of `McStateExtra` at `block_id`,
+        /// no config params are requested.
+        pub(crate) async fn get_state_extra_proof(
+            &mut self,
+            block_id: &BlockIdExt,
+        ) -> anyhow::Result<ConfigInfo> {
+            let req = WrappedRequest {
+                wait_masterchain_seqno: None,
+                request: Request::GetConfigParams(GetConfigParams {
+                    mode: (),
+                    id: to_lite_block_id(block_id)?,
+                    param_list: vec![],
+                    with_state_root: None,
+                    with_libraries: None,
+                    with_state_extra_root: Some(()),
+                    with_shard_hashes: None,
+                    with_validator_set: None,
+                    with_special_smc: None,
+                    with_accounts_root: None,
+                    with_prev_blocks: None,
+                    with_workchain_info: None,
+                    with_capabilities: None,
+                    extract_from_key_block: None,
+                }),
+            };
+            match self.execute(req).await? {
+                Response::ConfigInfo(config) => Ok(config),
+                _ => Err(LiteError::UnexpectedMessage)?,
+            }
+        }
+
         pub(super) async fn get_mc_info(&mut self) -> anyhow::Result<MasterchainInfo> {
             let req = WrappedRequest {
                 wait_masterchain_seqno: None,
@@ -176,18 +451,56 @@ mod lite {
             }
         }
 
+        pub(crate) async fn get_mc_info_ext(&mut self) -> anyhow::Result<MasterchainInfoExt> {
+            let req = WrappedRequest {
+                wait_masterchain_seqno: None,
+                request: Request::GetMasterchainInfoExt(GetMasterchainInfoExt { mode: 0 }),
+            };
+            match self.execute(req).await? {
+                Response::MasterchainInfoExt(info) => Ok(info),
+                _ => Err(LiteError::UnexpectedMessage)?,
+            }
+        }
+
+        /// Establishes the ADNL session without sending any request.
+        pub(crate) async fn handshake(&mut self) -> anyhow::Result<()> {
+            self.connect().await?;
+            Ok(())
+        }
+        /// Checks that a TCP connection to the server can be opened, without an ADNL handshake.
+        pub(crate) async fn probe_tcp(&self) -> anyhow::Result<()> {
+            let stream = TcpStream::connect(self.addr).await?;
+            apply_transport_options(&stream, &self.options)?;
+            Ok(())
+        }
+
         async fn execute(&mut self, req: WrappedRequest) -> anyhow::Result<Response> {
+            self.execute_with_timeout(req, REQ_TIMEOUT).await
+        }
+
+        async fn execute_with_timeout(
+            &mut self,
+            req: WrappedRequest,
+            req_timeout: Duration,
+        ) -> anyhow::Result<Response> {
             let ready_service = self.connect().await?.ready().await?;
-            Ok(timeout(REQ_TIMEOUT, ready_service.call(req)).await??)
+            Ok(timeout(req_timeout, ready_service.call(req)).await??)
         }
 
         async fn connect(&mut self) -> anyhow::Result<&mut ConnService> {
             if self.service.is_none() {
-                let adnl = timeout(
-                    CONNECTION_TIMEOUT,
-                    AdnlPeer::connect(&self.public, self.addr),
-                )
-                .await??;
+                let connect = async {
+                    let stream = TcpStream::connect(self.addr).await?;
+                    apply_transport_options(&stream, &self.options)?;
+                    let stream = ReadLimit::new(stream, self.read_limit, self.received.clone());
+                    anyhow::Ok(AdnlPeer::handshake(stream, &self.public).await?)
+                };
+                let connect_timeout = self.options.connect_timeout.unwrap_or(CONNECTION_TIMEOUT);
+                let adnl = if connect_timeout.is_zero() {
+                    connect.await?
+                } else {
+                    timeout(connect_timeout, connect).await??
+                };
 
                 let lite = LitePeer::new(adnl);
                 let service = ServiceBuilder::new()
@@ -200,4 +513,67 @@ mod lite {
             Ok(self.service.as_mut().unwrap()) // unwrap is safe: we initialized it in branch above
         }
     }
+
+    fn to_lite_block_id(block_id: &BlockIdExt) -> anyhow::Result<BlockIdExtLite> {
+        Ok(BlockIdExtLite {
+            workchain: block_id.workchain,
+            shard: block_id.shard as u64,
+            seqno: block_id.seqno as u32,
+            root_hash: Int256(block_id.root_hash.as_slice().try_into()?),
+            file_hash: Int256(block_id.file_hash.as_slice().try_into()?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonlib_core::cell::{Cell, CellBuilder};
+
+    use super::*;
+
+    fn exotic(
+        cell_type: u8,
+        hashes: &[(TonHash, u16)],
+        references: Vec<Arc<Cell>>,
+    ) -> anyhow::Result<Cell> {
+        let mut data = vec![cell_type];
+        for (hash, _) in hashes {
+            data.extend_from_slice(hash);
+        }
+        for (_, depth) in hashes {
+            data.extend_from_slice(&depth.to_be_bytes());
+        }
+        let bit_len = data.len() * 8;
+        Ok(Cell::new(data, bit_len, references, true)?)
+    }
+
+    #[test]
+    fn test_parse_state_hash() -> anyhow::Result<()> {
+        let old_state = Arc::new(CellBuilder::new().store_u32(32, 1)?.build()?);
+        let new_state = Arc::new(CellBuilder::new().store_u32(32, 2)?.build()?);
+        let state_update = exotic(
+            MERKLE_UPDATE_TYPE,
+            &[
+                (old_state.get_hash(0), old_state.get_depth(0)),
+                (new_state.get_hash(0), new_state.get_depth(0)),
+            ],
+            vec![old_state, new_state.clone()],
+        )?;
+        let block = CellBuilder::new()
+            .store_u32(32, 0x11ef55aa)?
+            .store_child(CellBuilder::new().store_u32(32, BLOCK_INFO_TAG)?.build()?)?
+            .store_child(Cell::default())?
+            .store_child(state_update)?
+            .store_child(Cell::default())?
+            .build()?;
+        let root_hash = block.get_hash(0);
+        let proof = exotic(3, &[(root_hash, block.get_depth(0))], vec![Arc::new(block)])?;
+        let boc = BagOfCells::from_root(proof).serialize(false)?;
+
+        assert_eq!(parse_state_hash(&boc, &root_hash)?, new_state.cell_hash());
+        assert!(parse_state_hash(&boc, &[0; 32]).is_err());
+        Ok(())
+    }
 }
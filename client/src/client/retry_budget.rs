@@ -0,0 +1,226 @@
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crate::client::{retry_condition, retry_with_condition, RetryStrategy, TonClientError};
+
+pub const DEFAULT_RETRY_BUDGET_RATIO: f64 = 0.1;
+pub const DEFAULT_RETRY_BUDGET_MAX_TOKENS: u32 = 10;
+/// Tokens are counted in thousandths, so that fractions earned by successes add up exactly
+const TOKEN_UNITS: u64 = 1000;
+
+/// Parameters of the retry budget shared by all calls of `TonClient`
+///
+/// Each successful request earns `retry_ratio` retries, so during an outage retries
+/// can't multiply the load on liteservers beyond this share of the recent traffic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryBudgetParams {
+    /// Retries earned by a successful request, e.g. `0.1` for one retry per 10 requests
+    pub retry_ratio: f64,
+    /// Maximum number of accumulated retries, the budget starts full
+    pub max_tokens: u32,
+}
+
+impl Default for RetryBudgetParams {
+    fn default() -> Self {
+        RetryBudgetParams {
+            retry_ratio: DEFAULT_RETRY_BUDGET_RATIO,
+            max_tokens: DEFAULT_RETRY_BUDGET_MAX_TOKENS,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetryBudgetStats {
+    /// `false` if retries aren't limited by the budget
+    pub enabled: bool,
+    /// Retries currently available
+    pub tokens: f64,
+    pub max_tokens: u32,
+    pub retries_allowed: u64,
+    /// Retries rejected because the budget was exhausted
+    pub retries_rejected: u64,
+}
+
+struct BudgetState {
+    params: Option<RetryBudgetParams>,
+    token_units: u64,
+    retries_allowed: u64,
+    retries_rejected: u64,
+}
+
+/// Token bucket of retries, refilled by successful requests. Disabled by default.
+pub(crate) struct RetryBudget {
+    state: Mutex<BudgetState>,
+}
+
+impl RetryBudget {
+    pub(crate) fn new(params: Option<&RetryBudgetParams>) -> RetryBudget {
+        let budget = RetryBudget {
+            state: Mutex::new(BudgetState {
+                params: None,
+                token_units: 0,
+                retries_allowed: 0,
+                retries_rejected: 0,
+            }),
+        };
+        budget.set_params(params);
+        budget
+    }
+
+    /// Enables the budget with `params`, filling it up, or disables it if `params` is `None`.
+    pub(crate) fn set_params(&self, params: Option<&RetryBudgetParams>) {
+        let mut state = self.lock_state();
+        state.token_units = params.map_or(0, |params| params.max_tokens as u64 * TOKEN_UNITS);
+        state.params = params.cloned();
+    }
+
+    pub(crate) fn on_success(&self) {
+        let mut state = self.lock_state();
+        if let Some(params) = &state.params {
+            let earned = (params.retry_ratio * TOKEN_UNITS as f64).round() as u64;
+            let max_units = params.max_tokens as u64 * TOKEN_UNITS;
+            state.token_units = (state.token_units + earned).min(max_units);
+        }
+    }
+
+    /// Takes a token for a retry, returns `false` if the budget is exhausted.
+    pub(crate) fn try_acquire(&self) -> bool {
+        let mut state = self.lock_state();
+        if state.params.is_none() {
+            return true;
+        }
+        if state.token_units >= TOKEN_UNITS {
+            state.token_units -= TOKEN_UNITS;
+            state.retries_allowed += 1;
+            true
+        } else {
+            state.retries_rejected += 1;
+            false
+        }
+    }
+
+    pub(crate) fn stats(&self) -> RetryBudgetStats {
+        let state = self.lock_state();
+        RetryBudgetStats {
+            enabled: state.params.is_some(),
+            tokens: state.token_units as f64 / TOKEN_UNITS as f64,
+            max_tokens: state.params.as_ref().map_or(0, |params| params.max_tokens),
+            retries_allowed: state.retries_allowed,
+            retries_rejected: state.retries_rejected,
+        }
+    }
+
+    fn lock_state(&self) -> std::sync::MutexGuard<'_, BudgetState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Calls `action` like `retry_with_strategy`, taking a token from `budget` before each retry.
+///
+/// If the budget is exhausted, fails with `TonClientError::RetryBudgetExhausted`
+/// wrapping the last error.
+pub(crate) async fn retry_with_budget<T, A, F>(
+    retry_strategy: &RetryStrategy,
+    budget: &RetryBudget,
+    action: A,
+) -> Result<T, TonClientError>
+where
+    A: FnMut() -> F,
+    F: Future<Output = Result<T, TonClientError>>,
+{
+    let retries = AtomicUsize::new(0);
+    let exhausted = AtomicBool::new(false);
+    let condition = |error: &TonClientError| {
+        // the strategy stops after max_retries, so the last failure must not take a token
+        if !retry_condition(error)
+            || retries.fetch_add(1, Ordering::Relaxed) >= retry_strategy.max_retries
+        {
+            return false;
+        }
+        let allowed = budget.try_acquire();
+        exhausted.store(!allowed, Ordering::Relaxed);
+        allowed
+    };
+    match retry_with_condition(retry_strategy, action, condition).await {
+        Err(error) if exhausted.load(Ordering::Relaxed) => {
+            Err(TonClientError::RetryBudgetExhausted(Box::new(error)))
+        }
+        result => result,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn failing_call(attempts: &AtomicUsize) -> impl Future<Output = Result<(), TonClientError>> {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        async {
+            Err(TonClientError::TonlibError {
+                method: "test",
+                code: 500,
+                message: "LITE_SERVER_NETWORK".to_string(),
+                extra: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_bounds_attempts() {
+        let strategy = RetryStrategy {
+            interval_ms: 0,
+            max_retries: 10,
+        };
+        let calls = 1000;
+
+        let disabled = RetryBudget::new(None);
+        let attempts = AtomicUsize::new(0);
+        for _ in 0..calls {
+            let result = retry_with_budget(&strategy, &disabled, || failing_call(&attempts)).await;
+            assert!(matches!(result, Err(TonClientError::TonlibError { .. })));
+        }
+        assert_eq!(attempts.load(Ordering::Relaxed), calls * 11);
+
+        let budget = RetryBudget::new(Some(&RetryBudgetParams::default()));
+        let attempts = AtomicUsize::new(0);
+        let mut exhausted = 0;
+        for _ in 0..calls {
+            let result = retry_with_budget(&strategy, &budget, || failing_call(&attempts)).await;
+            if matches!(result, Err(TonClientError::RetryBudgetExhausted(_))) {
+                exhausted += 1;
+            }
+        }
+        // the initial 10 tokens are spent by the first call, the rest fail fast
+        assert_eq!(attempts.load(Ordering::Relaxed), calls + 10);
+        assert_eq!(exhausted, calls - 1);
+        let stats = budget.stats();
+        assert!(stats.enabled);
+        assert_eq!(stats.retries_allowed, 10);
+        assert_eq!(stats.retries_rejected, calls as u64 - 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry_budget_refilled_by_successes() {
+        let budget = RetryBudget::new(Some(&RetryBudgetParams {
+            retry_ratio: 0.1,
+            max_tokens: 2,
+        }));
+        assert!(budget.try_acquire());
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+        for _ in 0..10 {
+            budget.on_success();
+        }
+        assert!(budget.try_acquire());
+        assert!(!budget.try_acquire());
+        for _ in 0..100 {
+            budget.on_success();
+        }
+        assert_eq!(budget.stats().tokens, 2.0);
+
+        budget.set_params(None);
+        assert!(budget.try_acquire());
+        assert!(!budget.stats().enabled);
+    }
+}
@@ -0,0 +1,216 @@
+use std::sync::Arc;
+
+use anyhow::bail;
+use tonlib_core::cell::{apply_merkle_update, ArcCell, BagOfCells};
+
+use crate::client::{TonClientError, TonClientInterface};
+use crate::tl::BlockIdExt;
+
+pub(crate) const MERKLE_UPDATE_TYPE: u8 = 4;
+/// Index of `state_update:^(MERKLE_UPDATE ShardState)` among references of `Block`
+pub(crate) const STATE_UPDATE_REF: usize = 2;
+
+/// Returns blocks following `start_block` up to `end_block` inclusive, oldest first,
+/// following links to previous blocks from `end_block`.
+///
+/// Both blocks must belong to the same shard with no splits or merges in between.
+pub(crate) async fn block_chain_between<C: TonClientInterface + ?Sized>(
+    client: &C,
+    start_block: &BlockIdExt,
+    end_block: &BlockIdExt,
+) -> Result<Vec<BlockIdExt>, TonClientError> {
+    if start_block.workchain != end_block.workchain || start_block.shard != end_block.shard {
+        return Err(TonClientError::InternalError(format!(
+            "Blocks {} and {} belong to different shards",
+            start_block.seqno, end_block.seqno
+        )));
+    }
+    let mut blocks = vec![];
+    let mut current = end_block.clone();
+    while current.seqno > start_block.seqno {
+        let header = client.get_block_header(&current).await?;
+        let prev = match header.prev_blocks.as_deref() {
+            Some([prev]) if prev.shard == current.shard => prev.clone(),
+            _ => {
+                return Err(TonClientError::InternalError(format!(
+                    "Block {} follows a shard split or merge",
+                    current.seqno
+                )))
+            }
+        };
+        blocks.push(current);
+        current = prev;
+    }
+    if &current != start_block {
+        return Err(TonClientError::InternalError(format!(
+            "Block {} is not an ancestor of block {}",
+            start_block.seqno, end_block.seqno
+        )));
+    }
+    blocks.reverse();
+    Ok(blocks)
+}
+
+/// Applies the state update of the block with `root_hash` (BoC `block`) to `state`,
+/// the state before the block, and returns the state after it.
+///
+/// The hash of the returned state is checked against the new state hash of the update.
+pub(crate) fn apply_block_state_update(
+    state: &ArcCell,
+    block: &[u8],
+    root_hash: &[u8],
+) -> anyhow::Result<ArcCell> {
+    let update = parse_state_update(block, root_hash)?;
+    let data = update.data();
+    if !update.is_exotic() || data.first() != Some(&MERKLE_UPDATE_TYPE) {
+        bail!(
+            "State update of block {} is not a Merkle update",
+            hex::encode(root_hash)
+        );
+    }
+    let new_state = apply_merkle_update(state, &update)?;
+    // type(8), old_hash(256), new_hash(256), old_depth(16), new_depth(16)
+    if new_state.cell_hash().as_slice() != &data[33..65] {
+        bail!(
+            "State {} is computed for block {}, expected {}",
+            hex::encode(new_state.cell_hash()),
+            hex::encode(root_hash),
+            hex::encode(&data[33..65])
+        );
+    }
+    Ok(Arc::new(new_state))
+}
+
+/// Returns `state_update:^(MERKLE_UPDATE ShardState)` of the block with `root_hash`.
+fn parse_state_update(block: &[u8], root_hash: &[u8]) -> anyhow::Result<ArcCell> {
+    let boc = BagOfCells::parse(block)?;
+    let block = boc.single_root()?;
+    if block.cell_hash().as_slice() != root_hash {
+        bail!(
+            "Block {} is received instead of {}",
+            hex::encode(block.cell_hash()),
+            hex::encode(root_hash)
+        );
+    }
+    Ok(block.reference(STATE_UPDATE_REF)?.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlib_core::cell::{Cell, CellBuilder};
+    use tonlib_core::TonHash;
+
+    use super::*;
+    use crate::client::mock::{block_header, block_id, MockClient};
+    use crate::tl::{TonFunction, TonResult};
+
+    const SHARD: u64 = 0x8000000000000000;
+
+    /// Client answering headers of shard blocks, each following the block with the previous seqno
+    /// of the shard returned by `prev_shard`
+    fn chain_client(prev_shard: fn(i32) -> u64) -> MockClient {
+        MockClient::new(move |function| match function {
+            TonFunction::GetBlockHeader { id } => {
+                let prev = block_id(id.workchain, prev_shard(id.seqno), id.seqno - 1);
+                Ok(TonResult::BlocksHeader(block_header(id, vec![prev])))
+            }
+            f => unimplemented!("{:?}", f),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_block_chain_between() -> anyhow::Result<()> {
+        let client = chain_client(|_| SHARD);
+        let blocks =
+            block_chain_between(&client, &block_id(0, SHARD, 10), &block_id(0, SHARD, 13)).await?;
+        let seqnos: Vec<_> = blocks.iter().map(|b| b.seqno).collect();
+        assert_eq!(seqnos, vec![11, 12, 13]);
+        assert_eq!(client.calls(), 3);
+
+        let blocks =
+            block_chain_between(&client, &block_id(0, SHARD, 10), &block_id(0, SHARD, 10)).await?;
+        assert!(blocks.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_chain_between_errors() -> anyhow::Result<()> {
+        let client = chain_client(|_| SHARD);
+        let other_shard = block_id(0, 0x4000000000000000, 13);
+        assert!(
+            block_chain_between(&client, &block_id(0, SHARD, 10), &other_shard)
+                .await
+                .is_err()
+        );
+        // the start block is ahead of the end block
+        assert!(
+            block_chain_between(&client, &block_id(0, SHARD, 13), &block_id(0, SHARD, 10))
+                .await
+                .is_err()
+        );
+        assert_eq!(client.calls(), 0);
+
+        // block 12 follows a block of the parent shard
+        let client = chain_client(|seqno| if seqno == 12 { 0 } else { SHARD });
+        assert!(
+            block_chain_between(&client, &block_id(0, SHARD, 10), &block_id(0, SHARD, 13))
+                .await
+                .is_err()
+        );
+
+        let client = MockClient::failing();
+        assert!(
+            block_chain_between(&client, &block_id(0, SHARD, 10), &block_id(0, SHARD, 13))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    fn merkle_update(old: &ArcCell, new: &ArcCell) -> anyhow::Result<Cell> {
+        let mut data = vec![MERKLE_UPDATE_TYPE];
+        data.extend_from_slice(&old.get_hash(0));
+        data.extend_from_slice(&new.get_hash(0));
+        data.extend_from_slice(&old.get_depth(0).to_be_bytes());
+        data.extend_from_slice(&new.get_depth(0).to_be_bytes());
+        let bit_len = data.len() * 8;
+        Ok(Cell::new(
+            data,
+            bit_len,
+            vec![old.clone(), new.clone()],
+            true,
+        )?)
+    }
+
+    /// BoC of a block with the state update from `old` to `new` and its root hash
+    fn block(old: &ArcCell, new: &ArcCell) -> anyhow::Result<(Vec<u8>, TonHash)> {
+        let block = CellBuilder::new()
+            .store_u32(32, 0x11ef55aa)?
+            .store_child(Cell::default())?
+            .store_child(Cell::default())?
+            .store_child(merkle_update(old, new)?)?
+            .store_child(Cell::default())?
+            .build()?;
+        let root_hash = block.cell_hash();
+        Ok((BagOfCells::from_root(block).serialize(false)?, root_hash))
+    }
+
+    #[test]
+    fn test_apply_block_state_update() -> anyhow::Result<()> {
+        let state = |n: u32| -> anyhow::Result<ArcCell> {
+            Ok(Arc::new(CellBuilder::new().store_u32(32, n)?.build()?))
+        };
+        let (first, first_hash) = block(&state(1)?, &state(2)?)?;
+        let (second, second_hash) = block(&state(2)?, &state(3)?)?;
+
+        let synced = apply_block_state_update(&state(1)?, &first, &first_hash)?;
+        let synced = apply_block_state_update(&synced, &second, &second_hash)?;
+        assert_eq!(synced.cell_hash(), state(3)?.cell_hash());
+
+        // the update is built for another state
+        assert!(apply_block_state_update(&state(1)?, &second, &second_hash).is_err());
+        // the block doesn't match the requested one
+        assert!(apply_block_state_update(&state(1)?, &first, &second_hash).is_err());
+        Ok(())
+    }
+}
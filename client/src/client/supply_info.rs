@@ -0,0 +1,261 @@
+use tonlib_core::cell::dict::AugValue;
+use tonlib_core::cell::{BagOfCells, Cell, CellParser, TonCellError};
+use tonlib_core::types::{CoinBalance, Coins};
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::{MapParseError, NetworkAddresses, TonClientError, TonClientInterface};
+use crate::tl::BlockIdExt;
+
+const SHARD_STATE_TAG: u32 = 0x9023afe2;
+const MC_STATE_EXTRA_TAG: u16 = 0xcc26;
+/// Bits of `ShardStateUnsplit` between the tag and `before_split`
+const SHARD_STATE_HEADER_BITS: usize = 328;
+/// Index of `custom:(Maybe ^McStateExtra)` among references of `ShardStateUnsplit`
+const MC_STATE_EXTRA_REF: usize = 3;
+
+/// Address conventionally used to burn Toncoin: the zero account of the basechain,
+/// which is never deployed, so funds sent to it can't be spent
+pub const BURN_ADDRESS: TonAddress = TonAddress::NULL;
+
+/// Supply of Toncoin at a masterchain block
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SupplyInfo {
+    /// Masterchain block the supply is read at
+    pub block: BlockIdExt,
+    /// Balance of all accounts & messages in flight: `global_balance` of the masterchain state
+    pub total_supply: Coins,
+    /// `total_supply` less `burned` and balances of the config and minter contracts,
+    /// which hold Toncoin not issued yet.
+    ///
+    /// Stakes held by the elector belong to validators & nominators and are counted
+    /// as circulating, as well as funds of the fee collector.
+    pub circulating: Coins,
+    /// Balance of `BURN_ADDRESS`. Funds burned by the protocol, e.g. a part of fees,
+    /// are already excluded from `total_supply`.
+    pub burned: Coins,
+}
+
+/// Reads balances of the burn address and of the config & minter contracts at `block`
+/// and computes the supply from `total_supply` of the masterchain state
+pub(crate) async fn supply_at_block<C: TonClientInterface + ?Sized>(
+    client: &C,
+    block: BlockIdExt,
+    total_supply: Coins,
+    addresses: &NetworkAddresses,
+) -> Result<SupplyInfo, TonClientError> {
+    let mut accounts = vec![
+        addresses.config_address().clone(),
+        addresses.minter_address().clone(),
+    ];
+    // minter may be substituted by config
+    accounts.dedup();
+    accounts.push(BURN_ADDRESS);
+    let states = client
+        .get_account_states_at_block(&accounts, Some(&block))
+        .await?;
+    if let Some((address, e)) = states.unavailable().next() {
+        return Err(TonClientError::InternalError(format!(
+            "State of {} is unavailable at block {}: {}",
+            address, block.seqno, e
+        )));
+    }
+    let mut burned = Coins::zero();
+    let mut not_issued = Coins::zero();
+    for (address, state) in states.loaded() {
+        let balance = Coins::from(state.balance.max(0) as u64);
+        if *address == BURN_ADDRESS {
+            burned = balance;
+        } else {
+            not_issued = not_issued.saturating_add(&balance);
+        }
+    }
+    let circulating = total_supply.saturating_sub(&burned.saturating_add(&not_issued));
+    Ok(SupplyInfo {
+        block,
+        total_supply,
+        circulating,
+        burned,
+    })
+}
+
+/// Reads `global_balance` from the BoC of the Merkle proof of the masterchain state
+/// with `state_hash`.
+///
+/// Only the cells on the path from the state root to `McStateExtra` are read,
+/// other branches may be pruned.
+pub(crate) fn parse_global_balance_proof(
+    proof: &[u8],
+    state_hash: &TonHash,
+) -> Result<Coins, TonClientError> {
+    let proof = BagOfCells::parse(proof)
+        .and_then(|boc| boc.single_root().cloned())
+        .map_parse_error("masterchain state")?;
+    let state = proof.reference(0).map_parse_error("masterchain state")?;
+    if !proof.is_exotic() || state.get_hash(0) != *state_hash {
+        return Err(TonClientError::InternalError(format!(
+            "State proof is built for state {}, expected {}",
+            hex::encode(state.get_hash(0)),
+            hex::encode(state_hash)
+        )));
+    }
+    parse_global_balance(state)
+}
+
+/// ```raw
+/// shard_state#9023afe2 global_id:int32 shard_id:ShardIdent seq_no:uint32 vert_seq_no:#
+///   gen_utime:uint32 gen_lt:uint64 min_ref_mc_seqno:uint32
+///   out_msg_queue_info:^OutMsgQueueInfo before_split:(## 1) accounts:^ShardAccounts
+///   ^[ ... ] custom:(Maybe ^McStateExtra) = ShardStateUnsplit;
+///
+/// masterchain_state_extra#cc26 shard_hashes:ShardHashes config:ConfigParams
+///   ^[ ... ] global_balance:CurrencyCollection = McStateExtra;
+/// _ config_addr:bits256 config:^(Hashmap 32 ^Cell) = ConfigParams;
+/// ```
+fn parse_global_balance(state: &Cell) -> Result<Coins, TonClientError> {
+    let mut parser = state.parser();
+    let tag = parser.load_u32(32).map_parse_error("masterchain state")?;
+    if tag != SHARD_STATE_TAG {
+        return Err(TonClientError::InternalError(format!(
+            "Invalid shard state tag: {:#x}",
+            tag
+        )));
+    }
+    parser
+        .skip_bits(SHARD_STATE_HEADER_BITS + 1)
+        .map_parse_error("masterchain state")?;
+    if !parser.load_bit().map_parse_error("masterchain state")? {
+        return Err(TonClientError::InternalError(
+            "Shard state is not a masterchain state".to_string(),
+        ));
+    }
+    let extra = state
+        .reference(MC_STATE_EXTRA_REF)
+        .map_parse_error("masterchain state")?;
+    let mut parser = extra.parser();
+    let tag = parser.load_u16(16).map_parse_error("masterchain state")?;
+    if tag != MC_STATE_EXTRA_TAG {
+        return Err(TonClientError::InternalError(format!(
+            "Invalid masterchain state extra tag: {:#x}",
+            tag
+        )));
+    }
+    let global_balance = load_global_balance(&mut parser).map_parse_error("masterchain state")?;
+    Ok(global_balance.grams)
+}
+
+/// Reads `McStateExtra` following the tag, the skipped references may be pruned
+fn load_global_balance(parser: &mut CellParser) -> Result<CoinBalance, TonCellError> {
+    let _shard_hashes = parser.load_maybe_cell_ref()?;
+    parser.skip_bits(256)?; // config_addr
+    let _config = parser.next_reference()?;
+    let _misc = parser.next_reference()?;
+    CoinBalance::parse(parser)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+    use tonlib_core::cell::dict::predefined_writers::val_writer_ref_cell;
+    use tonlib_core::cell::CellBuilder;
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, MockClient};
+    use crate::tl::{RawFullAccountState, TonFunction, TonResult};
+
+    fn config_cell() -> anyhow::Result<Cell> {
+        let params = [(0u32, 0x55u8), (1, 0x33), (2, 0x22)]
+            .into_iter()
+            .map(|(index, byte)| -> anyhow::Result<(u32, Arc<Cell>)> {
+                let param = CellBuilder::new().store_slice(&[byte; 32])?.build()?;
+                Ok((index, Arc::new(param)))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        Ok(CellBuilder::new()
+            .store_dict(32, val_writer_ref_cell, params)?
+            .build()?)
+    }
+
+    fn mc_state(global_balance: u64) -> anyhow::Result<Cell> {
+        let empty = Arc::new(CellBuilder::new().build()?);
+        let extra = CellBuilder::new()
+            .store_u32(16, MC_STATE_EXTRA_TAG as u32)?
+            .store_bit(false)? // shard_hashes
+            .store_slice(&[0x55; 32])?
+            .store_child(config_cell()?)?
+            .store_reference(&empty)?
+            .store_coins(&BigUint::from(global_balance))?
+            .store_bit(false)? // other currencies
+            .build()?;
+        Ok(CellBuilder::new()
+            .store_u32(32, SHARD_STATE_TAG)?
+            .store_uint(SHARD_STATE_HEADER_BITS, &BigUint::default())?
+            .store_bit(false)? // before_split
+            .store_bit(true)? // custom
+            .store_reference(&empty)?
+            .store_reference(&empty)?
+            .store_reference(&empty)?
+            .store_child(extra)?
+            .build()?)
+    }
+
+    /// Merkle proof of `state`
+    fn proof(state: Cell) -> anyhow::Result<Cell> {
+        let mut data = vec![3];
+        data.extend_from_slice(&state.get_hash(0));
+        data.extend_from_slice(&state.get_depth(0).to_be_bytes());
+        let bit_len = data.len() * 8;
+        Ok(Cell::new(data, bit_len, vec![Arc::new(state)], true)?)
+    }
+
+    #[test]
+    fn test_parse_global_balance_proof() -> anyhow::Result<()> {
+        let state = mc_state(5_000_000_000_000_000_000)?;
+        let state_hash = state.cell_hash();
+        let boc = BagOfCells::from_root(proof(state)?).serialize(false)?;
+        let total_supply = parse_global_balance_proof(&boc, &state_hash)?;
+        assert_eq!(total_supply, Coins::from(5_000_000_000_000_000_000));
+        // the proof is built for another state
+        assert!(parse_global_balance_proof(&boc, &[0; 32]).is_err());
+        // the state isn't wrapped into a proof
+        let boc = BagOfCells::from_root(mc_state(1)?).serialize(false)?;
+        assert!(parse_global_balance_proof(&boc, &state_hash).is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_supply_at_block() -> anyhow::Result<()> {
+        let addresses = NetworkAddresses::from_config_cell(&config_cell()?)?;
+        let balances = HashMap::from([
+            (addresses.config_address().to_hex(), 100),
+            (addresses.minter_address().to_hex(), 200),
+            (addresses.elector_address().to_hex(), 1000),
+            (BURN_ADDRESS.to_hex(), 10),
+        ]);
+        let client = MockClient::new(move |function| match function {
+            TonFunction::WithBlock { id, function } => match function.as_ref() {
+                TonFunction::RawGetAccountState { account_address } => {
+                    assert_eq!(id.seqno, 42);
+                    Ok(TonResult::RawFullAccountState(RawFullAccountState {
+                        balance: balances[&account_address.account_address],
+                        ..account_state(id)
+                    }))
+                }
+                f => panic!("Unexpected function: {:?}", f),
+            },
+            f => panic!("Unexpected function: {:?}", f),
+        });
+
+        let supply =
+            supply_at_block(&client, mc_block(42), Coins::from(10_000u64), &addresses).await?;
+        assert_eq!(supply.block, mc_block(42));
+        assert_eq!(supply.total_supply, Coins::from(10_000u64));
+        assert_eq!(supply.burned, Coins::from(10u64));
+        // the stake held by the elector is circulating
+        assert_eq!(supply.circulating, Coins::from(9_690u64));
+        assert_eq!(client.calls(), 3);
+        Ok(())
+    }
+}
@@ -0,0 +1,481 @@
+use async_trait::async_trait;
+use tonlib_core::cell::{BagOfCells, Cell, TonCellError};
+use tonlib_core::types::Transaction;
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::{
+    AccountTransactionsSource, PageSource, Pager, TonClientError, TonClientInterface,
+};
+use crate::tl::{InternalTransactionId, MsgData, RawMessage, RawTransaction};
+
+/// Op code of bounced message body
+pub const BOUNCE_OP: u32 = 0xffffffff;
+
+/// Max number of original body bits copied to bounced message
+const BOUNCED_BODY_BITS: usize = 256;
+
+const TRANSACTIONS_BATCH_SIZE: usize = 16;
+
+/// Page of parsed account transactions returned by `get_transactions_decoded`
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedTransactions {
+    /// Transactions ordered from the newest to the oldest
+    pub transactions: Vec<Transaction>,
+    /// Transaction preceding the oldest one in the page, to request the next page from.
+    ///
+    /// `lt` is 0 once the first transaction of the account is reached.
+    pub previous_transaction_id: InternalTransactionId,
+}
+
+/// High-level functions for working with account transactions
+#[async_trait]
+pub trait TonTransactionFunctions: TonClientInterface + Send + Sync {
+    /// Returns transactions of `address` from `from` back to the first one, `page_size` per request
+    fn account_transactions_pager(
+        &self,
+        address: &TonAddress,
+        from: &InternalTransactionId,
+        page_size: usize,
+    ) -> Pager<'_, RawTransaction> {
+        Pager::new(AccountTransactionsSource {
+            client: self,
+            address: address.clone(),
+            next: from.clone(),
+            page_size: page_size.max(1),
+        })
+    }
+
+    /// Finds the transaction in which a message sent by `sender` bounced back to it.
+    ///
+    /// The original message is identified by `body_hash`. Transactions of `sender` are scanned
+    /// from the latest one back to the transaction which sent the original message, but no more
+    /// than `max_transactions`. Returns `None` if the message hasn't bounced (yet)
+    /// or the sending transaction is not found within the limit.
+    async fn find_bounce_transaction(
+        &self,
+        sender: &TonAddress,
+        body_hash: &TonHash,
+        max_transactions: usize,
+    ) -> Result<Option<RawTransaction>, TonClientError> {
+        let state = self.get_raw_account_state(sender).await?;
+        let mut pager = self.account_transactions_pager(
+            sender,
+            &state.last_transaction_id,
+            TRANSACTIONS_BATCH_SIZE,
+        );
+        // bounce candidates, newest first
+        let mut candidates: Vec<RawTransaction> = Vec::new();
+        let mut scanned = 0;
+        while scanned < max_transactions {
+            let batch_size = (max_transactions - scanned).min(TRANSACTIONS_BATCH_SIZE);
+            let txs = pager.take_items(batch_size).await?;
+            if txs.is_empty() {
+                break;
+            }
+            for tx in txs {
+                scanned += 1;
+                let original = tx
+                    .out_msgs
+                    .iter()
+                    .find(|msg| msg.body_hash.as_slice() == body_hash.as_slice());
+                if let Some(original) = original {
+                    // the closest bounce happened after the original message was sent
+                    return Ok(candidates
+                        .into_iter()
+                        .rev()
+                        .find(|candidate| is_bounce_of(candidate, original)));
+                }
+                if tx.in_msg.as_ref().map(is_bounced_message).unwrap_or(false) {
+                    candidates.push(tx);
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Fetches up to `count` transactions of `address` starting from `from`, or from the latest
+    /// transaction if it's `None`, and parses them together with their in and out messages.
+    ///
+    /// Infos of all out messages are decoded, see `Transaction::parse`. Pass
+    /// `previous_transaction_id` of the result as `from` to fetch the next page.
+    async fn get_transactions_decoded(
+        &self,
+        address: &TonAddress,
+        count: usize,
+        from: Option<&InternalTransactionId>,
+    ) -> Result<DecodedTransactions, TonClientError> {
+        let mut next = match from {
+            Some(from) => from.clone(),
+            None => {
+                self.get_raw_account_state(address)
+                    .await?
+                    .last_transaction_id
+            }
+        };
+        let mut source = AccountTransactionsSource {
+            client: self,
+            address: address.clone(),
+            next: next.clone(),
+            page_size: TRANSACTIONS_BATCH_SIZE,
+        };
+        let mut raw_transactions = Vec::with_capacity(count);
+        while raw_transactions.len() < count {
+            // the last page requests only what is still needed
+            source.page_size = (count - raw_transactions.len()).min(TRANSACTIONS_BATCH_SIZE);
+            match source.fetch_page().await? {
+                Some(page) => raw_transactions.extend(page),
+                None => break,
+            }
+        }
+        raw_transactions.truncate(count);
+        let mut transactions = Vec::with_capacity(raw_transactions.len());
+        for raw in raw_transactions.iter() {
+            let tx = decode_transaction(raw)?;
+            // a page may end in the middle of a batch, so continue from the oldest parsed one
+            next = InternalTransactionId {
+                lt: tx.prev_trans_lt as i64,
+                hash: tx.prev_trans_hash.to_vec(),
+            };
+            transactions.push(tx);
+        }
+        Ok(DecodedTransactions {
+            transactions,
+            previous_transaction_id: next,
+        })
+    }
+}
+
+impl<T> TonTransactionFunctions for T where T: TonClientInterface + Send + Sync {}
+
+/// Checks whether in-message of `tx` is the bounced `original` message
+pub fn is_bounce_of(tx: &RawTransaction, original: &RawMessage) -> bool {
+    let in_msg = match &tx.in_msg {
+        Some(in_msg) => in_msg,
+        None => return false,
+    };
+    if in_msg.created_lt <= original.created_lt
+        || !same_address(
+            &in_msg.source.account_address,
+            &original.destination.account_address,
+        )
+    {
+        return false;
+    }
+    match (message_body(in_msg), message_body(original)) {
+        (Ok(bounced), Ok(original)) => bounced_body_matches(&bounced, &original).unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Parses `data` of the raw transaction
+pub fn decode_transaction(raw: &RawTransaction) -> Result<Transaction, TonClientError> {
+    BagOfCells::parse(&raw.data)
+        .and_then(|boc| Transaction::parse(boc.single_root()?))
+        .map_err(|e| {
+            TonClientError::InternalError(format!(
+                "Failed to parse transaction {}: {}",
+                raw.transaction_id.lt, e
+            ))
+        })
+}
+
+fn is_bounced_message(msg: &RawMessage) -> bool {
+    message_body(msg)
+        .and_then(|body| body.parser().load_u32(32))
+        .map(|op| op == BOUNCE_OP)
+        .unwrap_or(false)
+}
+
+/// Checks that `bounced` is `0xffffffff` followed by the first 256 bits of `original`
+fn bounced_body_matches(bounced: &Cell, original: &Cell) -> Result<bool, TonCellError> {
+    let mut bounced_parser = bounced.parser();
+    if bounced_parser.load_u32(32)? != BOUNCE_OP {
+        return Ok(false);
+    }
+    let len = original.bit_len().min(BOUNCED_BODY_BITS);
+    if bounced_parser.remaining_bits() < len {
+        return Ok(false);
+    }
+    let bounced_bits = bounced_parser.load_bits(len)?;
+    let original_bits = original.parser().load_bits(len)?;
+    Ok(bounced_bits == original_bits)
+}
+
+fn message_body(msg: &RawMessage) -> Result<Cell, TonCellError> {
+    match &msg.msg_data {
+        MsgData::Raw { body, .. } => {
+            let root = BagOfCells::parse(body)?.single_root()?.clone();
+            Ok(root.as_ref().clone())
+        }
+        _ => Err(TonCellError::InvalidInput(
+            "Message body is not raw".to_string(),
+        )),
+    }
+}
+
+fn same_address(a: &str, b: &str) -> bool {
+    match (a.parse::<TonAddress>(), b.parse::<TonAddress>()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use num_bigint::BigUint;
+    use tonlib_core::cell::CellBuilder;
+    use tonlib_core::message::{CommonMsgInfo, InternalMessage, TonMessage, TransferMessage};
+
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::client::mock::{raw_message, raw_transaction, MockClient, TransactionFixture};
+    use crate::tl::{RawTransactions, TonFunction, TonResult};
+
+    const WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x11; 32],
+    };
+    const CONTRACT: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x22; 32],
+    };
+
+    fn message(
+        source: &TonAddress,
+        destination: &TonAddress,
+        created_lt: i64,
+        body: &Cell,
+    ) -> RawMessage {
+        RawMessage {
+            created_lt,
+            ..raw_message(Some(source), destination, body)
+        }
+    }
+
+    fn transaction(in_msg: RawMessage) -> RawTransaction {
+        RawTransaction {
+            address: in_msg.destination.clone(),
+            transaction_id: InternalTransactionId {
+                lt: in_msg.created_lt + 1,
+                hash: vec![0; 32],
+            },
+            in_msg: Some(in_msg),
+            ..raw_transaction(&TonAddress::NULL, 0)
+        }
+    }
+
+    #[test]
+    fn test_is_bounce_of() -> anyhow::Result<()> {
+        let body = CellBuilder::new()
+            .store_u32(32, 0x0f8a7ea5)?
+            .store_u64(64, 42)?
+            .store_slice(&[0xAB; 40])?
+            .build()?;
+        let original = message(&WALLET, &CONTRACT, 100, &body);
+        let bounced_body = CellBuilder::new()
+            .store_u32(32, BOUNCE_OP)?
+            .store_u32(32, 0x0f8a7ea5)?
+            .store_u64(64, 42)?
+            .store_slice(&[0xAB; 20])?
+            .build()?;
+        let bounce = transaction(message(&CONTRACT, &WALLET, 200, &bounced_body));
+        assert!(is_bounced_message(bounce.in_msg.as_ref().unwrap()));
+        assert!(is_bounce_of(&bounce, &original));
+
+        // bounced before the message was sent
+        let early = transaction(message(&CONTRACT, &WALLET, 50, &bounced_body));
+        assert!(!is_bounce_of(&early, &original));
+
+        // bounced from another contract
+        let other = transaction(message(&WALLET, &WALLET, 200, &bounced_body));
+        assert!(!is_bounce_of(&other, &original));
+
+        // bounce of another message
+        let other_body = CellBuilder::new()
+            .store_u32(32, BOUNCE_OP)?
+            .store_u32(32, 0x0f8a7ea5)?
+            .store_u64(64, 43)?
+            .store_slice(&[0xAB; 20])?
+            .build()?;
+        let other = transaction(message(&CONTRACT, &WALLET, 200, &other_body));
+        assert!(!is_bounce_of(&other, &original));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_transaction() -> anyhow::Result<()> {
+        let info = CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: true,
+            bounced: false,
+            src: WALLET,
+            dest: CONTRACT,
+            value: BigUint::from(1000u32),
+            ihr_fee: BigUint::from(0u32),
+            fwd_fee: BigUint::from(0u32),
+            created_lt: 40,
+            created_at: 1700000000,
+        });
+        let in_msg = TransferMessage::new(info.clone()).build()?;
+        let fixture = TransactionFixture {
+            prev: InternalTransactionId {
+                lt: 41,
+                hash: vec![0x33; 32],
+            },
+            in_msg: Some(in_msg),
+            total_fees: 12345,
+            ..TransactionFixture::new(&CONTRACT, 42)
+        };
+        let raw = RawTransaction {
+            data: fixture.raw()?.data,
+            ..transaction(message(&WALLET, &CONTRACT, 40, &Cell::default()))
+        };
+
+        let tx = decode_transaction(&raw)?;
+        assert_eq!(tx.lt, 42);
+        assert_eq!(tx.in_msg_info, Some(info));
+        assert_eq!(tx.prev_trans_lt, 41);
+        assert_eq!(tx.prev_trans_hash, [0x33; 32]);
+
+        let broken = RawTransaction {
+            data: vec![1, 2, 3],
+            ..raw
+        };
+        assert!(decode_transaction(&broken).is_err());
+        Ok(())
+    }
+    fn transaction_id(lt: i64) -> InternalTransactionId {
+        InternalTransactionId {
+            lt,
+            hash: vec![lt as u8; 32],
+        }
+    }
+
+    /// Internal message from `CONTRACT` to `WALLET` created at `lt`
+    fn out_message(lt: i64) -> anyhow::Result<Cell> {
+        let info = CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: false,
+            bounced: false,
+            src: CONTRACT,
+            dest: WALLET,
+            value: BigUint::from(lt as u64),
+            ihr_fee: BigUint::from(0u32),
+            fwd_fee: BigUint::from(0u32),
+            created_lt: lt as u64,
+            created_at: 1700000000,
+        });
+        Ok(TransferMessage::new(info).build()?)
+    }
+
+    /// Client serving transactions 1 to 40 of `CONTRACT`, each sending a message,
+    /// and the log of requested page sizes
+    fn history_client() -> (MockClient, Arc<Mutex<Vec<u32>>>) {
+        let page_sizes = Arc::new(Mutex::new(vec![]));
+        let requested = page_sizes.clone();
+        let client = MockClient::new(move |function| match function {
+            TonFunction::RawGetTransactionsV2 {
+                from_transaction_id,
+                count,
+                ..
+            } => {
+                requested.lock().unwrap().push(*count);
+                let from = from_transaction_id.lt;
+                let end = (from - *count as i64).max(0);
+                let transactions = (end + 1..=from)
+                    .rev()
+                    .map(|lt| {
+                        let fixture = TransactionFixture {
+                            prev: transaction_id(lt - 1),
+                            out_msgs: vec![out_message(lt).unwrap()],
+                            ..TransactionFixture::new(&CONTRACT, lt as u64)
+                        };
+                        fixture.raw().unwrap()
+                    })
+                    .collect();
+                Ok(TonResult::RawTransactions(RawTransactions {
+                    transactions,
+                    previous_transaction_id: transaction_id(end),
+                }))
+            }
+            _ => unimplemented!("{:?}", function),
+        });
+        (client, page_sizes)
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions_decoded_page_sizes() -> anyhow::Result<()> {
+        let (client, page_sizes) = history_client();
+        let page = client
+            .get_transactions_decoded(&CONTRACT, 20, Some(&transaction_id(40)))
+            .await?;
+        let lts: Vec<u64> = page.transactions.iter().map(|tx| tx.lt).collect();
+        assert_eq!(lts, (21..=40).rev().collect::<Vec<_>>());
+        assert_eq!(page.previous_transaction_id, transaction_id(20));
+        assert_eq!(*page_sizes.lock().unwrap(), vec![16, 4]);
+
+        // the first transaction of the account ends the history before `count`
+        let (client, page_sizes) = history_client();
+        let page = client
+            .get_transactions_decoded(&CONTRACT, 30, Some(&transaction_id(10)))
+            .await?;
+        assert_eq!(page.transactions.len(), 10);
+        assert_eq!(page.previous_transaction_id.lt, 0);
+        assert_eq!(*page_sizes.lock().unwrap(), vec![16]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions_decoded_out_messages() -> anyhow::Result<()> {
+        let (client, _) = history_client();
+        let page = client
+            .get_transactions_decoded(&CONTRACT, 2, Some(&transaction_id(40)))
+            .await?;
+        for tx in page.transactions.iter() {
+            assert_eq!(tx.out_msgs_count, 1);
+            let info = tx.out_msgs[0].info.as_ref().unwrap();
+            match info {
+                CommonMsgInfo::InternalMessage(msg) => {
+                    assert_eq!(msg.dest, WALLET);
+                    assert_eq!(msg.created_lt, tx.lt);
+                }
+                info => panic!("unexpected message info: {:?}", info),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions_decoded_paging() -> anyhow::Result<()> {
+        // pages of 10 end in the middle of the 16-transaction batches
+        let (client, page_sizes) = history_client();
+        let mut lts = vec![];
+        let mut from = transaction_id(40);
+        while from.lt != 0 {
+            let page = client
+                .get_transactions_decoded(&CONTRACT, 10, Some(&from))
+                .await?;
+            assert_eq!(page.transactions.len(), 10);
+            lts.extend(page.transactions.iter().map(|tx| tx.lt));
+            from = page.previous_transaction_id;
+        }
+        assert_eq!(lts, (1..=40).rev().collect::<Vec<_>>());
+        assert_eq!(*page_sizes.lock().unwrap(), vec![10; 4]);
+
+        // a page spanning two batches continues where the previous one stopped
+        let (client, page_sizes) = history_client();
+        let first = client
+            .get_transactions_decoded(&CONTRACT, 25, Some(&transaction_id(40)))
+            .await?;
+        let second = client
+            .get_transactions_decoded(&CONTRACT, 25, Some(&first.previous_transaction_id))
+            .await?;
+        assert_eq!(first.transactions.last().unwrap().lt, 16);
+        let lts: Vec<u64> = second.transactions.iter().map(|tx| tx.lt).collect();
+        assert_eq!(lts, (1..=15).rev().collect::<Vec<_>>());
+        assert_eq!(second.previous_transaction_id.lt, 0);
+        assert_eq!(*page_sizes.lock().unwrap(), vec![16, 9, 16]);
+        Ok(())
+    }
+}
@@ -0,0 +1,259 @@
+use std::fmt;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures::Stream;
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+
+use crate::client::TonClientError;
+use crate::rt::{self, mpsc};
+use crate::tl::{InternalTransactionId, RawTransaction, TlError};
+
+/// Max number of parsed transactions waiting to be consumed
+pub const TRANSACTION_STREAM_BUFFER: usize = 16;
+/// Method reported in `TonClientError::TonlibError` of error responses
+const METHOD: &str = "RawGetTransactionsV2";
+
+enum StreamItem {
+    Transaction(Box<RawTransaction>),
+    End(InternalTransactionId),
+}
+
+type ItemSender = mpsc::Sender<Result<StreamItem, TonClientError>>;
+
+/// Transactions of a `raw.getTransactionsV2` response, deserialized one by one as they are consumed.
+///
+/// The response body is parsed on a blocking thread, which is suspended while
+/// `TRANSACTION_STREAM_BUFFER` transactions are waiting. The body itself is held in memory
+/// in full until the stream ends, so memory use is the body size plus up to
+/// `TRANSACTION_STREAM_BUFFER` deserialized transactions, rather than bounded.
+pub struct RawTransactionStream {
+    receiver: mpsc::Receiver<Result<StreamItem, TonClientError>>,
+    previous_transaction_id: Option<InternalTransactionId>,
+}
+
+impl RawTransactionStream {
+    /// Starts deserializing `body`, a tonlib JSON response of `raw.getTransactionsV2`,
+    /// e.g. as returned by `TonConnection::invoke_raw_bytes`.
+    ///
+    /// Must be called within the async runtime of the client.
+    pub fn from_body(body: Vec<u8>) -> RawTransactionStream {
+        let (sender, receiver) = mpsc::channel(TRANSACTION_STREAM_BUFFER);
+        drop(rt::spawn_blocking(move || {
+            if let Err(e) = parse_body(&body, &sender) {
+                // fails only if the stream is dropped
+                let _ = sender.blocking_send(Err(e));
+            }
+        }));
+        RawTransactionStream {
+            receiver,
+            previous_transaction_id: None,
+        }
+    }
+
+    /// Transaction preceding the oldest one in the page, to request the next page from.
+    ///
+    /// `None` until the stream is exhausted.
+    pub fn previous_transaction_id(&self) -> Option<&InternalTransactionId> {
+        self.previous_transaction_id.as_ref()
+    }
+}
+
+impl Stream for RawTransactionStream {
+    type Item = Result<RawTransaction, TonClientError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match ready!(self.receiver.poll_recv(cx)) {
+                Some(Ok(StreamItem::Transaction(tx))) => return Poll::Ready(Some(Ok(*tx))),
+                Some(Ok(StreamItem::End(id))) => self.previous_transaction_id = Some(id),
+                Some(Err(e)) => return Poll::Ready(Some(Err(e))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+fn parse_body(body: &[u8], sender: &ItemSender) -> Result<(), TonClientError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(body);
+    let response = deserializer
+        .deserialize_map(ResponseVisitor { sender })
+        .map_err(|e| TonClientError::TlError(TlError::SerdeJsonError(e)))?;
+    match response {
+        Response::Transactions(previous_transaction_id) => {
+            // fails only if the stream is dropped
+            let _ = sender.blocking_send(Ok(StreamItem::End(previous_transaction_id)));
+            Ok(())
+        }
+        Response::Error {
+            code,
+            message,
+            extra,
+        } => Err(TonClientError::TonlibError {
+            method: METHOD,
+            code,
+            message,
+            extra,
+        }),
+    }
+}
+
+enum Response {
+    Transactions(InternalTransactionId),
+    Error {
+        code: i32,
+        message: String,
+        extra: Option<Value>,
+    },
+}
+
+/// Visits `raw.transactions` or `error` object, sending transactions as they are deserialized
+struct ResponseVisitor<'a> {
+    sender: &'a ItemSender,
+}
+
+impl<'de> Visitor<'de> for ResponseVisitor<'_> {
+    type Value = Response;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("raw.transactions or error object")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Response, A::Error> {
+        let mut result_type: Option<String> = None;
+        let mut previous_transaction_id = None;
+        let mut code = 0;
+        let mut message = String::new();
+        // unknown fields are only kept as extra fields of an error
+        let mut extra = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "@type" => result_type = Some(map.next_value()?),
+                "transactions" => map.next_value_seed(TransactionsSeed {
+                    sender: self.sender,
+                })?,
+                "previous_transaction_id" => previous_transaction_id = Some(map.next_value()?),
+                "code" => code = map.next_value()?,
+                "message" => message = map.next_value()?,
+                "@extra" => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+                _ => {
+                    extra.insert(key, map.next_value()?);
+                }
+            }
+        }
+        match result_type.as_deref() {
+            Some("raw.transactions") => previous_transaction_id
+                .map(Response::Transactions)
+                .ok_or_else(|| de::Error::missing_field("previous_transaction_id")),
+            Some("error") => Ok(Response::Error {
+                code,
+                message,
+                extra: (!extra.is_empty()).then_some(Value::Object(extra)),
+            }),
+            Some(other) => Err(de::Error::custom(format!("unexpected @type: {}", other))),
+            None => Err(de::Error::missing_field("@type")),
+        }
+    }
+}
+
+struct TransactionsSeed<'a> {
+    sender: &'a ItemSender,
+}
+
+impl<'de> DeserializeSeed<'de> for TransactionsSeed<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for TransactionsSeed<'_> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("array of raw.transaction")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(tx) = seq.next_element::<RawTransaction>()? {
+            if self
+                .sender
+                .blocking_send(Ok(StreamItem::Transaction(Box::new(tx))))
+                .is_err()
+            {
+                return Err(de::Error::custom("stream is dropped"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn transaction_json(lt: i64) -> String {
+        format!(
+            r#"{{"@type":"raw.transaction","address":{{"@type":"accountAddress","account_address":"EQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM9c"}},"utime":{lt},"data":"","transaction_id":{{"@type":"internal.transactionId","lt":"{lt}","hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}},"fee":"0","storage_fee":"0","other_fee":"0","in_msg":null,"out_msgs":[]}}"#
+        )
+    }
+
+    #[tokio::test]
+    async fn test_stream_transactions() -> anyhow::Result<()> {
+        let transactions: Vec<String> = (1..=40).rev().map(transaction_json).collect();
+        let body = format!(
+            r#"{{"@type":"raw.transactions","transactions":[{}],"previous_transaction_id":{{"@type":"internal.transactionId","lt":"0","hash":"AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA="}},"@extra":"3"}}"#,
+            transactions.join(",")
+        );
+        let mut stream = RawTransactionStream::from_body(body.into_bytes());
+        let mut lts = vec![];
+        while let Some(tx) = stream.next().await {
+            lts.push(tx?.transaction_id.lt);
+        }
+        assert_eq!(lts, (1..=40).rev().collect::<Vec<_>>());
+        assert_eq!(stream.previous_transaction_id().map(|id| id.lt), Some(0));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_stream_error_response() {
+        let body = br#"{"@type":"error","code":500,"message":"LITE_SERVER_NETWORK","@extra":"3"}"#;
+        let mut stream = RawTransactionStream::from_body(body.to_vec());
+        match stream.next().await {
+            Some(Err(TonClientError::TonlibError {
+                code,
+                message,
+                extra,
+                ..
+            })) => {
+                assert_eq!(code, 500);
+                assert_eq!(message, "LITE_SERVER_NETWORK");
+                assert_eq!(extra, None);
+            }
+            r => panic!("Unexpected result: {:?}", r.map(|r| r.map(|_| ()))),
+        }
+        assert!(stream.next().await.is_none());
+        assert_eq!(stream.previous_transaction_id(), None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_malformed_transaction() {
+        let body = format!(
+            r#"{{"@type":"raw.transactions","transactions":[{},{{"utime":"x"}}]}}"#,
+            transaction_json(2)
+        );
+        let mut stream = RawTransactionStream::from_body(body.into_bytes());
+        assert!(matches!(stream.next().await, Some(Ok(_))));
+        assert!(matches!(
+            stream.next().await,
+            Some(Err(TonClientError::TlError(_)))
+        ));
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,265 @@
+#[cfg(feature = "liteapi")]
+use std::io;
+#[cfg(feature = "liteapi")]
+use std::pin::Pin;
+#[cfg(feature = "liteapi")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "liteapi")]
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+#[cfg(feature = "liteapi")]
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::client::{TonConnectionCallback, TonConnectionParams};
+
+/// Options of `TonConnectionParams` tuning the transport to liteservers
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct TransportOptions {
+    /// Max time to establish the connection, `None` keeps the default of the transport.
+    /// Zero disables the timeout.
+    pub connect_timeout: Option<Duration>,
+    /// Interval of TCP keepalive probes, `None` keeps the system default
+    pub keepalive: Option<Duration>,
+    /// `true` disables coalescing of small packets (Nagle's algorithm), `None` keeps the system default
+    pub nodelay: Option<bool>,
+}
+
+impl TransportOptions {
+    pub fn from_params(params: &TonConnectionParams) -> TransportOptions {
+        TransportOptions {
+            connect_timeout: Some(params.connect_timeout),
+            keepalive: params.tcp_keepalive,
+            nodelay: params.tcp_nodelay,
+        }
+    }
+
+    /// Names of the options which are set but not supported by tonlibjson,
+    /// which manages its sockets itself
+    pub fn unsupported_by_tonlib(&self) -> Vec<&'static str> {
+        let mut unsupported = vec![];
+        if self.keepalive.is_some() {
+            unsupported.push("tcp_keepalive");
+        }
+        if self.nodelay.is_some() {
+            unsupported.push("tcp_nodelay");
+        }
+        unsupported
+    }
+}
+
+/// Reports options of `params` ignored by tonlibjson connections via `callback`.
+///
+/// Called once per connection pool rather than per connection.
+pub(crate) fn report_unsupported_transport_options(
+    params: &TonConnectionParams,
+    callback: &Arc<dyn TonConnectionCallback>,
+) {
+    for option in TransportOptions::from_params(params).unsupported_by_tonlib() {
+        callback.on_unsupported_transport_option(option);
+    }
+}
+
+/// Socket accepting `TransportOptions`
+#[cfg(feature = "liteapi")]
+pub(crate) trait TransportSocket {
+    fn set_nodelay(&self, nodelay: bool) -> io::Result<()>;
+
+    fn set_keepalive(&self, interval: Duration) -> io::Result<()>;
+}
+
+#[cfg(feature = "liteapi")]
+impl TransportSocket for tokio::net::TcpStream {
+    fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+        tokio::net::TcpStream::set_nodelay(self, nodelay)
+    }
+
+    fn set_keepalive(&self, interval: Duration) -> io::Result<()> {
+        let keepalive = socket2::TcpKeepalive::new()
+            .with_time(interval)
+            .with_interval(interval);
+        socket2::SockRef::from(self).set_tcp_keepalive(&keepalive)
+    }
+}
+
+/// Applies socket options of `options` to `socket`, leaving unset ones intact
+#[cfg(feature = "liteapi")]
+pub(crate) fn apply_transport_options<S: TransportSocket>(
+    socket: &S,
+    options: &TransportOptions,
+) -> io::Result<()> {
+    if let Some(nodelay) = options.nodelay {
+        socket.set_nodelay(nodelay)?;
+    }
+    if let Some(interval) = options.keepalive {
+        socket.set_keepalive(interval)?;
+    }
+    Ok(())
+}
+
+/// Stream failing reads once more than `limit` bytes have been received,
+/// so that a huge response is dropped while being downloaded rather than after it.
+///
+/// The number of received bytes is shared with clones of `received`.
+#[cfg(feature = "liteapi")]
+pub(crate) struct ReadLimit<S> {
+    inner: S,
+    limit: usize,
+    received: Arc<AtomicUsize>,
+}
+
+#[cfg(feature = "liteapi")]
+impl<S> ReadLimit<S> {
+    pub(crate) fn new(inner: S, limit: usize, received: Arc<AtomicUsize>) -> ReadLimit<S> {
+        ReadLimit {
+            inner,
+            limit,
+            received,
+        }
+    }
+}
+
+#[cfg(feature = "liteapi")]
+impl<S: AsyncRead + Unpin> AsyncRead for ReadLimit<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let read = buf.filled().len() - filled;
+        let received = self.received.fetch_add(read, Ordering::SeqCst) + read;
+        if received > self.limit {
+            return Poll::Ready(Err(io::Error::other(format!(
+                "Received {} bytes, more than the limit of {}",
+                received, self.limit
+            ))));
+        }
+        result
+    }
+}
+
+#[cfg(feature = "liteapi")]
+impl<S: AsyncWrite + Unpin> AsyncWrite for ReadLimit<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    #[cfg(feature = "liteapi")]
+    #[derive(Default)]
+    struct MockSocket {
+        nodelay: Mutex<Option<bool>>,
+        keepalive: Mutex<Option<Duration>>,
+    }
+
+    #[cfg(feature = "liteapi")]
+    impl TransportSocket for MockSocket {
+        fn set_nodelay(&self, nodelay: bool) -> io::Result<()> {
+            *self.nodelay.lock().unwrap() = Some(nodelay);
+            Ok(())
+        }
+
+        fn set_keepalive(&self, interval: Duration) -> io::Result<()> {
+            *self.keepalive.lock().unwrap() = Some(interval);
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingCallback {
+        options: Mutex<Vec<String>>,
+    }
+
+    impl TonConnectionCallback for RecordingCallback {
+        fn on_unsupported_transport_option(&self, option: &str) {
+            self.options.lock().unwrap().push(option.to_string());
+        }
+    }
+
+    #[cfg(feature = "liteapi")]
+    #[test]
+    fn test_apply_transport_options() -> anyhow::Result<()> {
+        let socket = MockSocket::default();
+        apply_transport_options(&socket, &TransportOptions::default())?;
+        assert_eq!(*socket.nodelay.lock().unwrap(), None);
+        assert_eq!(*socket.keepalive.lock().unwrap(), None);
+
+        let params = TonConnectionParams {
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            tcp_nodelay: Some(true),
+            ..Default::default()
+        };
+        apply_transport_options(&socket, &TransportOptions::from_params(&params))?;
+        assert_eq!(*socket.nodelay.lock().unwrap(), Some(true));
+        assert_eq!(
+            *socket.keepalive.lock().unwrap(),
+            Some(Duration::from_secs(15))
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "liteapi")]
+    #[tokio::test]
+    async fn test_read_limit() {
+        let read = |data: &'static [u8], limit: usize| async move {
+            let received = Arc::new(AtomicUsize::new(0));
+            let mut stream = ReadLimit::new(data, limit, received.clone());
+            let mut result = vec![];
+            let mut chunk = [0u8; 4];
+            loop {
+                let mut buf = ReadBuf::new(&mut chunk);
+                let poll =
+                    futures::future::poll_fn(|cx| Pin::new(&mut stream).poll_read(cx, &mut buf));
+                match poll.await {
+                    Ok(()) if buf.filled().is_empty() => break Ok(result),
+                    Ok(()) => result.extend_from_slice(buf.filled()),
+                    Err(e) => break Err((e, received.load(Ordering::SeqCst))),
+                }
+            }
+        };
+        assert_eq!(read(b"0123456789", 10).await.unwrap(), b"0123456789");
+        // reading stops at the first chunk over the limit
+        let (_, received) = read(b"0123456789", 5).await.unwrap_err();
+        assert_eq!(received, 8);
+    }
+
+    #[test]
+    fn test_report_unsupported_transport_options() {
+        let recording = Arc::new(RecordingCallback::default());
+        let callback: Arc<dyn TonConnectionCallback> = recording.clone();
+        report_unsupported_transport_options(&TonConnectionParams::default(), &callback);
+        assert!(recording.options.lock().unwrap().is_empty());
+
+        let params = TonConnectionParams {
+            tcp_keepalive: Some(Duration::from_secs(15)),
+            tcp_nodelay: Some(false),
+            ..Default::default()
+        };
+        report_unsupported_transport_options(&params, &callback);
+        assert_eq!(
+            *recording.options.lock().unwrap(),
+            vec!["tcp_keepalive", "tcp_nodelay"]
+        );
+    }
+}
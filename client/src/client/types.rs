@@ -1,15 +1,19 @@
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use serde::{Deserialize, Serialize};
-use tokio::sync::broadcast;
 use tonlib_core::TonAddress;
 
 use super::{
-    BlocksShortTxId, TonClientError, DEFAULT_CONNECTION_CONCURRENCY_LIMIT,
-    DEFAULT_NOTIFICATION_QUEUE_LENGTH, DEFAULT_UPDATE_INIT_BLOCK,
+    BlocksShortTxId, Deadline, TonClientError, DEFAULT_CONNECTION_CONCURRENCY_LIMIT,
+    DEFAULT_CONNECT_TIMEOUT, DEFAULT_NOTIFICATION_QUEUE_LENGTH, DEFAULT_POOL_GROW_THRESHOLD,
+    DEFAULT_POOL_IDLE_TTL_MS, DEFAULT_READ_TIMEOUT, DEFAULT_UPDATE_INIT_BLOCK,
+    DEFAULT_WRITE_TIMEOUT,
 };
 use crate::config::MAINNET_CONFIG;
+use crate::rt::broadcast;
 use crate::tl::{InternalTransactionId, TonNotification};
 
 pub type TonNotificationReceiver = broadcast::Receiver<Arc<TonNotification>>;
@@ -39,6 +43,10 @@ impl TxId {
     }
 }
 
+/// Keystore directory of the connection with the given index in the pool,
+/// see `TonClientBuilder::with_keystore_dir_fn`
+pub type KeystoreDirFn = Arc<dyn Fn(usize) -> PathBuf + Send + Sync>;
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TonConnectionParams {
     pub config: String,
@@ -56,6 +64,34 @@ pub struct TonConnectionParams {
     pub concurrency_limit: usize,
     #[serde(default = "default_update_init_block")]
     pub update_init_block: bool,
+    /// Max time to initialize the connection. Zero disables the timeout.
+    #[serde(default = "default_connect_timeout")]
+    pub connect_timeout: Duration,
+    /// Max time to submit a request to the connection: waiting for `concurrency_limit` and
+    /// sending the request.
+    /// Zero disables the timeout.
+    #[serde(default = "default_write_timeout")]
+    pub write_timeout: Duration,
+    /// Max time to wait for the response of a submitted request. Zero disables the timeout.
+    #[serde(default = "default_read_timeout")]
+    pub read_timeout: Duration,
+    /// Interval of TCP keepalive probes of liteserver connections, `None` keeps the system default.
+    ///
+    /// Applied only to the connection refreshing the init block (`update_init_block` with the
+    /// `liteapi` feature). tonlibjson manages sockets of the pool connections itself, so the
+    /// option is reported via `TonConnectionCallback::on_unsupported_transport_option`, and on
+    /// the default build that report is its only effect.
+    #[serde(default)]
+    pub tcp_keepalive: Option<Duration>,
+    /// `true` disables coalescing of small packets (Nagle's algorithm) on liteserver connections,
+    /// `None` keeps the system default.
+    ///
+    /// Applied only to the connection refreshing the init block (`update_init_block` with the
+    /// `liteapi` feature). tonlibjson manages sockets of the pool connections itself, so the
+    /// option is reported via `TonConnectionCallback::on_unsupported_transport_option`, and on
+    /// the default build that report is its only effect.
+    #[serde(default)]
+    pub tcp_nodelay: Option<bool>,
 }
 
 impl Default for TonConnectionParams {
@@ -69,6 +105,11 @@ impl Default for TonConnectionParams {
             notification_queue_length: DEFAULT_NOTIFICATION_QUEUE_LENGTH,
             concurrency_limit: DEFAULT_CONNECTION_CONCURRENCY_LIMIT,
             update_init_block: DEFAULT_UPDATE_INIT_BLOCK,
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            write_timeout: DEFAULT_WRITE_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            tcp_keepalive: None,
+            tcp_nodelay: None,
         }
     }
 }
@@ -84,6 +125,18 @@ fn default_update_init_block() -> bool {
     DEFAULT_UPDATE_INIT_BLOCK
 }
 
+fn default_connect_timeout() -> Duration {
+    DEFAULT_CONNECT_TIMEOUT
+}
+
+fn default_write_timeout() -> Duration {
+    DEFAULT_WRITE_TIMEOUT
+}
+
+fn default_read_timeout() -> Duration {
+    DEFAULT_READ_TIMEOUT
+}
+
 lazy_static! {
     pub static ref DEFAULT_CONNECTION_PARAMS: TonConnectionParams = TonConnectionParams::default();
 }
@@ -103,6 +156,116 @@ impl Default for RetryStrategy {
     }
 }
 
+/// Retry behavior of a single call, see `TonClientInterface::invoke_with_options`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum RetryOverride {
+    /// Retry according to the strategy of the client
+    #[default]
+    Inherit,
+    /// Never retry the call
+    None,
+    /// Retry according to the specified strategy instead of the client one
+    Custom(RetryStrategy),
+}
+
+/// Per-call overrides of the client behavior
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct InvokeOptions {
+    pub retry: RetryOverride,
+    /// Limit of the total time of the call including retries, `None` means no limit
+    pub timeout: Option<Duration>,
+    /// Deadline of the operation the call belongs to, the call is limited by the time left
+    pub deadline: Option<Deadline>,
+}
+
+impl InvokeOptions {
+    /// Options of calls which must not be repeated, e.g. sending messages
+    pub fn no_retry() -> InvokeOptions {
+        InvokeOptions {
+            retry: RetryOverride::None,
+            timeout: None,
+            deadline: None,
+        }
+    }
+
+    pub fn with_retry(retry_strategy: &RetryStrategy) -> InvokeOptions {
+        InvokeOptions {
+            retry: RetryOverride::Custom(retry_strategy.clone()),
+            timeout: None,
+            deadline: None,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> InvokeOptions {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Deadline) -> InvokeOptions {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Returns the time limit of a call of `method`, zero meaning no limit.
+    ///
+    /// Fails with `TonClientError::DeadlineExceeded` if the deadline has already passed.
+    pub fn call_timeout(&self, method: &'static str) -> Result<Duration, TonClientError> {
+        let remaining = match &self.deadline {
+            Some(deadline) => Some(deadline.call_timeout(method)?),
+            None => None,
+        };
+        let timeout = match (self.timeout, remaining) {
+            (Some(timeout), Some(remaining)) => timeout.min(remaining),
+            (timeout, remaining) => timeout.or(remaining).unwrap_or(Duration::ZERO),
+        };
+        Ok(timeout)
+    }
+}
+
+/// Sizing policy of the connection pool.
+///
+/// The pool starts with `min_size` connections and grows up to `max_size` connections
+/// when the average number of in-flight requests per connection reaches `grow_threshold`.
+/// Connections above `min_size` are closed after being idle for `idle_ttl_ms`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolParams {
+    pub min_size: usize,
+    pub max_size: usize,
+    #[serde(default = "default_pool_grow_threshold")]
+    pub grow_threshold: usize,
+    #[serde(default = "default_pool_idle_ttl_ms")]
+    pub idle_ttl_ms: u64,
+}
+
+impl PoolParams {
+    pub fn fixed(size: usize) -> PoolParams {
+        Self::elastic(size, size)
+    }
+
+    pub fn elastic(min_size: usize, max_size: usize) -> PoolParams {
+        PoolParams {
+            min_size,
+            max_size,
+            grow_threshold: DEFAULT_POOL_GROW_THRESHOLD,
+            idle_ttl_ms: DEFAULT_POOL_IDLE_TTL_MS,
+        }
+    }
+}
+
+impl Default for PoolParams {
+    fn default() -> Self {
+        Self::fixed(1)
+    }
+}
+
+fn default_pool_grow_threshold() -> usize {
+    DEFAULT_POOL_GROW_THRESHOLD
+}
+
+fn default_pool_idle_ttl_ms() -> u64 {
+    DEFAULT_POOL_IDLE_TTL_MS
+}
+
 lazy_static! {
     pub static ref DEFAULT_RETRY_STRATEGY: RetryStrategy = RetryStrategy::default();
 }
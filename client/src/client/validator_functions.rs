@@ -0,0 +1,243 @@
+use std::cmp::Reverse;
+
+use async_trait::async_trait;
+use num_bigint::BigUint;
+use tonlib_core::cell::dict::predefined_readers::key_reader_256bit;
+use tonlib_core::cell::{BagOfCells, CellParser, TonCellError};
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::{MapParseError, TonClientError, TonClientInterface};
+use crate::tl::{TvmStackEntry, TvmTuple};
+
+/// Address of the elector smart contract (config param 1) in mainnet and testnet.
+///
+/// Use `TonClientInterface::get_network_addresses` to get the address in any network.
+pub const ELECTOR_ADDRESS: TonAddress = TonAddress {
+    workchain: -1,
+    hash_part: [0x33; 32],
+};
+
+/// Validator participating in an election.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorInfo {
+    /// Account id of the validator wallet in masterchain.
+    pub address: TonHash,
+    pub pubkey: TonHash,
+    pub weight: u64,
+    /// ADNL address of the validator.
+    ///
+    /// Elector doesn't keep ADNL addresses of frozen stakes, so it is `None` for past elections.
+    pub adnl: Option<TonHash>,
+    pub stake: BigUint,
+    pub banned: bool,
+}
+
+/// Validator set of a single election, as stored in elector's `past_elections`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidatorSet {
+    pub election_id: u32,
+    pub unfreeze_at: u32,
+    pub stake_held: u32,
+    pub vset_hash: TonHash,
+    pub total_stake: BigUint,
+    pub bonuses: BigUint,
+    pub validators: Vec<ValidatorInfo>,
+}
+
+/// High-level functions for working with validators & elections
+#[async_trait]
+pub trait TonValidatorFunctions: TonClientInterface + Send + Sync {
+    /// Returns up to `count` most recent past elections, newest first.
+    async fn get_past_validator_sets(
+        &self,
+        count: u32,
+    ) -> Result<Vec<ValidatorSet>, TonClientError> {
+        let addresses = self.get_network_addresses().await?;
+        let state = self.smc_load(addresses.elector_address()).await?;
+        let result = state
+            .conn
+            .smc_run_get_method(state.id, &"past_elections".into(), &[])
+            .await?;
+        if result.exit_code != 0 && result.exit_code != 1 {
+            return Err(TonClientError::InternalError(format!(
+                "past_elections failed with exit code {}",
+                result.exit_code
+            )));
+        }
+        let elections = match result.stack.elements.first() {
+            Some(TvmStackEntry::List { list }) => &list.elements,
+            Some(TvmStackEntry::Tuple { tuple }) if tuple.elements.is_empty() => &tuple.elements,
+            e => {
+                return Err(TonClientError::InternalError(format!(
+                    "Unexpected past_elections result: {:?}",
+                    e
+                )))
+            }
+        };
+
+        let mut sets = elections
+            .iter()
+            .map(parse_past_election)
+            .collect::<Result<Vec<_>, _>>()?;
+        sets.sort_by_key(|set| Reverse(set.election_id));
+        sets.truncate(count as usize);
+        Ok(sets)
+    }
+}
+
+impl<T> TonValidatorFunctions for T where T: TonClientInterface + Send + Sync {}
+
+/// Parses `[election_id, unfreeze_at, stake_held, vset_hash, frozen_dict, total_stake, bonuses, complaints]`
+fn parse_past_election(entry: &TvmStackEntry) -> Result<ValidatorSet, TonClientError> {
+    let elements = match entry {
+        TvmStackEntry::Tuple {
+            tuple: TvmTuple { elements },
+        } if elements.len() >= 7 => elements,
+        e => {
+            return Err(TonClientError::InternalError(format!(
+                "Unexpected past election entry: {:?}",
+                e
+            )))
+        }
+    };
+    let frozen_dict = match &elements[4] {
+        TvmStackEntry::Cell { cell } => {
+            let boc = BagOfCells::parse(&cell.bytes).map_parse_error("frozen stakes")?;
+            let root = boc.single_root().map_parse_error("frozen stakes")?;
+            root.parser()
+                .load_dict(256, key_reader_256bit, val_reader_frozen_stake)
+                .map_parse_error("frozen stakes")?
+        }
+        // empty dictionary is returned as null
+        _ => Default::default(),
+    };
+    let mut validators = frozen_dict
+        .into_iter()
+        .map(|(address, mut info)| {
+            info.address = address;
+            info
+        })
+        .collect::<Vec<_>>();
+    validators.sort_by_key(|v| Reverse(v.weight));
+
+    Ok(ValidatorSet {
+        election_id: get_number(&elements[0])?,
+        unfreeze_at: get_number(&elements[1])?,
+        stake_held: get_number(&elements[2])?,
+        vset_hash: get_hash(&elements[3])?,
+        total_stake: get_number(&elements[5])?,
+        bonuses: get_number(&elements[6])?,
+        validators,
+    })
+}
+
+/// Reads `pubkey:bits256 weight:uint64 true_stake:Grams banned:Bool`
+fn val_reader_frozen_stake(parser: &mut CellParser) -> Result<ValidatorInfo, TonCellError> {
+    let mut pubkey = [0u8; 32];
+    parser.load_slice(&mut pubkey)?;
+    let weight = parser.load_u64(64)?;
+    let stake = parser.load_coins()?;
+    let banned = parser.load_bit()?;
+    Ok(ValidatorInfo {
+        address: [0u8; 32],
+        pubkey,
+        weight,
+        adnl: None,
+        stake,
+        banned,
+    })
+}
+
+fn get_number<T: std::str::FromStr>(entry: &TvmStackEntry) -> Result<T, TonClientError> {
+    match entry {
+        TvmStackEntry::Number { number } => number.number.parse().map_err(|_| {
+            TonClientError::InternalError(format!("Unexpected number: {}", number.number))
+        }),
+        e => Err(TonClientError::InternalError(format!(
+            "Expected number, got {:?}",
+            e
+        ))),
+    }
+}
+
+fn get_hash(entry: &TvmStackEntry) -> Result<TonHash, TonClientError> {
+    let value: BigUint = get_number(entry)?;
+    let bytes = value.to_bytes_be();
+    if bytes.len() > 32 {
+        return Err(TonClientError::InternalError(format!(
+            "Value {} exceeds 256 bits",
+            value
+        )));
+    }
+    let mut hash = [0u8; 32];
+    hash[32 - bytes.len()..].copy_from_slice(&bytes);
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use num_bigint::BigUint;
+    use tonlib_core::cell::{BagOfCells, CellBuilder};
+
+    use super::*;
+    use crate::tl::{TvmCell, TvmNumber};
+
+    fn number(n: &str) -> TvmStackEntry {
+        TvmStackEntry::Number {
+            number: TvmNumber {
+                number: n.to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_parse_past_election() -> anyhow::Result<()> {
+        let writer = |builder: &mut CellBuilder, weight: u64| {
+            builder
+                .store_slice(&[0xAA; 32])?
+                .store_u64(64, weight)?
+                .store_coins(&BigUint::from(weight * 1000))?
+                .store_bit(false)?;
+            Ok(())
+        };
+        let data = HashMap::from([
+            (BigUint::from_bytes_be(&[1; 32]), 10u64),
+            (BigUint::from_bytes_be(&[2; 32]), 20u64),
+        ]);
+        let dict = CellBuilder::new().store_dict(256, writer, data)?.build()?;
+        let dict_boc = BagOfCells::from_root(dict).serialize(false)?;
+
+        let entry = TvmStackEntry::Tuple {
+            tuple: TvmTuple {
+                elements: vec![
+                    number("1700000000"),
+                    number("1700100000"),
+                    number("32768"),
+                    number("255"),
+                    TvmStackEntry::Cell {
+                        cell: TvmCell { bytes: dict_boc },
+                    },
+                    number("30000"),
+                    number("0"),
+                    TvmStackEntry::Unsupported {},
+                ],
+            },
+        };
+
+        let set = parse_past_election(&entry)?;
+        assert_eq!(set.election_id, 1700000000);
+        assert_eq!(set.unfreeze_at, 1700100000);
+        assert_eq!(set.stake_held, 32768);
+        assert_eq!(set.vset_hash[31], 0xFF);
+        assert_eq!(set.total_stake, BigUint::from(30000u32));
+        assert_eq!(set.validators.len(), 2);
+        assert_eq!(set.validators[0].address, [2; 32]);
+        assert_eq!(set.validators[0].weight, 20);
+        assert_eq!(set.validators[0].stake, BigUint::from(20000u32));
+        assert_eq!(set.validators[1].pubkey, [0xAA; 32]);
+        assert_eq!(set.validators[1].adnl, None);
+        Ok(())
+    }
+}
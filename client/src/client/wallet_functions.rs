@@ -0,0 +1,908 @@
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use num_bigint::BigUint;
+use tonlib_core::cell::{
+    BagOfCells, BocSerializeOptions, Cell, CellBuilder, StateInit, StateInitBuilder, TonCellError,
+};
+use tonlib_core::message::{CommonMsgInfo, InternalMessage, TonMessage, TransferMessage};
+use tonlib_core::types::Coins;
+use tonlib_core::wallet::{TonWallet, WalletDataV4, WalletVersion};
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::{
+    account_code_data, parse_external_message, InvokeOptions, TonClientError, TonClientInterface,
+};
+use crate::emulator::{TvmEmulator, TvmEmulatorC7Builder};
+use crate::rt;
+use crate::tl::{Fees, RawFullAccountState};
+use crate::types::TvmMsgSuccess;
+
+/// Interval between checks of the account state while waiting for confirmation
+pub(crate) const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Op code of the standard `set_code` upgrade message
+pub const SET_CODE_OP: u32 = 0x9903d56d;
+/// Amount of nanotons attached to the upgrade message to cover its processing
+const UPGRADE_MESSAGE_VALUE: u64 = 50_000_000;
+
+/// Expected outcome of a transfer, see `TonWalletFunctions::simulate_transfer`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferSimulation {
+    pub gas_fee: Coins,
+    pub storage_fee: Coins,
+    /// All fees paid by the sender: import, storage, gas & forwarding fees
+    pub total_fee: Coins,
+    /// Transferred amount minus storage & gas fees paid by the recipient
+    pub recipient_receives: Coins,
+    pub sender_remaining_balance: Coins,
+}
+
+/// Pre-send check of the bounce flag performed by `TonWalletFunctions::safe_send`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BounceCheck {
+    /// Fail if the message can't be delivered to an uninitialized destination as intended
+    #[default]
+    Strict,
+    /// Log a warning instead of failing
+    Warn,
+    /// Allow non-bounceable messages to uninitialized destinations, e.g. funding an address
+    /// before its deployment, but still fail on bounceable ones
+    AllowNonBounceableToUninit,
+}
+
+/// High-level functions sending messages via wallet
+#[async_trait]
+pub trait TonWalletFunctions: TonClientInterface + Send + Sync {
+    /// Deploys contract with specified code & data from `wallet` and waits until it becomes active.
+    ///
+    /// Returns the address of the contract. If the contract is already deployed,
+    /// returns its address without sending anything.
+    ///
+    /// The message is sent with `send_mode` & lifetime set in `wallet.config()`.
+    ///
+    /// Only `WalletVersion::V4R2` wallets are supported.
+    async fn deploy_contract(
+        &self,
+        wallet: &TonWallet,
+        code: &Cell,
+        data: &Cell,
+        initial_balance: &BigUint,
+        workchain: i8,
+    ) -> Result<TonAddress, TonClientError> {
+        let code = Arc::new(code.clone());
+        let data = Arc::new(data.clone());
+        let account_id = StateInit::create_account_id(&code, &data).map_err(map_error)?;
+        let address = TonAddress::new(workchain as i32, &account_id);
+        if is_active(self, &address).await? {
+            log::info!("Contract {} is already deployed", address);
+            return Ok(address);
+        }
+
+        let state_init = StateInitBuilder::new(&code, &data)
+            .build()
+            .map_err(map_error)?;
+        let internal_message =
+            TransferMessage::new(internal_msg_info(&address, initial_balance, false))
+                .with_state_init(state_init)
+                .build()
+                .map_err(map_error)?;
+        let expire_at = send_from_wallet(self, wallet, internal_message, wallet.config().send_mode)
+            .await?
+            .1;
+
+        loop {
+            if is_active(self, &address).await? {
+                return Ok(address);
+            }
+            if now_secs() > expire_at {
+                return Err(TonClientError::InternalError(format!(
+                    "Deployment of {} is not confirmed before message expiration",
+                    address
+                )));
+            }
+            rt::sleep(CONFIRMATION_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Emulates processing of the external `message` by the account at `address` and broadcasts
+    /// the message only if the emulated compute phase succeeded, or regardless of it if `force` is set.
+    ///
+    /// `message` is the complete external inbound message, as passed to `send_raw_message`.
+    /// If the account isn't deployed, the state init attached to the message is emulated.
+    /// Returns the emulation result and the hash of the message, `None` if it wasn't broadcasted.
+    /// The message is sent once, without retries.
+    ///
+    /// Emulation runs on the current account state and config without libraries, while the
+    /// transaction is executed on the state at the moment of inclusion. Another message
+    /// changing seqno or balance in between, or time-dependent checks may make the transaction
+    /// fail despite successful emulation, so only messages failing already are filtered out.
+    async fn send_with_simulation(
+        &self,
+        address: &TonAddress,
+        message: &Cell,
+        force: bool,
+    ) -> Result<(TvmMsgSuccess, Option<TonHash>), TonClientError> {
+        let (state_init, body) = parse_external_message(message).map_err(map_error)?;
+        let account_state = self.get_raw_account_state(address).await?;
+        let (code_boc, data_boc) = account_code_data(address, &account_state, state_init)?;
+        let balance = account_state.balance.max(0) as u64;
+        let config = self.get_config_all(0).await?;
+        let c7 = TvmEmulatorC7Builder::new(address, &config.config.bytes, balance).build();
+        let emulation = rt::spawn_blocking(move || {
+            let mut emulator = TvmEmulator::new(&code_boc, &data_boc)?;
+            emulator.set_c7(&c7)?;
+            emulator.send_external_message(body)
+        })
+        .await
+        .map_err(|e| TonClientError::InternalError(e.to_string()))?
+        .map_err(map_error)?;
+
+        let succeeded =
+            emulation.accepted && (emulation.vm_exit_code == 0 || emulation.vm_exit_code == 1);
+        if !succeeded && !force {
+            log::info!(
+                "Message to {} is not sent: emulation exited with code {}, accepted: {}",
+                address,
+                emulation.vm_exit_code,
+                emulation.accepted
+            );
+            return Ok((emulation, None));
+        }
+        let boc = BagOfCells::from_root(message.clone())
+            .serialize(true)
+            .map_err(map_error)?;
+        let hash = send_boc(self, &boc, &InvokeOptions::no_retry()).await?;
+        Ok((emulation, Some(hash)))
+    }
+
+    /// Estimates fees & balances of transferring `amount` from wallet `from` to `to`.
+    ///
+    /// `message` is the body of the external message to `from` performing the transfer.
+    /// It doesn't have to be signed, since signature check is skipped during estimation.
+    async fn simulate_transfer(
+        &self,
+        from: &TonAddress,
+        to: &TonAddress,
+        amount: &Coins,
+        message: &Cell,
+    ) -> Result<TransferSimulation, TonClientError> {
+        let body = BagOfCells::from_root(message.clone())
+            .serialize(true)
+            .map_err(map_error)?;
+        let fees = self.estimate_fees(from, &body, &[], &[], true).await?;
+        let sender_state = self.get_raw_account_state(from).await?;
+        let recipient_fees = fees.destination_fees.first();
+        if recipient_fees.is_none() {
+            log::warn!("No destination fees estimated for transfer to {}", to);
+        }
+        Ok(transfer_simulation(
+            amount,
+            sender_state.balance,
+            &fees.source_fees,
+            recipient_fees,
+        ))
+    }
+
+    /// Sends `internal_message` from `wallet` after checking its bounce flag against
+    /// the state of the destination account.
+    ///
+    /// If the destination is not deployed and the message has no state init, a bounceable
+    /// message would bounce back and a non-bounceable one would leave the funds on an account
+    /// which might never be deployed. Both are reported according to `check`, see `BounceCheck`.
+    /// Returns the hash of the external message sent to the wallet.
+    ///
+    /// Only `WalletVersion::V4R2` wallets are supported.
+    async fn safe_send(
+        &self,
+        wallet: &TonWallet,
+        internal_message: &Cell,
+        send_mode: u8,
+        check: BounceCheck,
+    ) -> Result<TonHash, TonClientError> {
+        let mut parser = internal_message.parser();
+        let (destination, bounce) = match CommonMsgInfo::parse(&mut parser).map_err(map_error)? {
+            CommonMsgInfo::InternalMessage(info) => (info.dest, info.bounce),
+            _ => {
+                return Err(TonClientError::InternalError(
+                    "safe_send expects an internal message".to_string(),
+                ))
+            }
+        };
+        let has_state_init = parser.load_bit().map_err(map_error)?;
+        if !has_state_init {
+            let active = is_active(self, &destination).await?;
+            check_bounce(&destination, bounce, active, check)?;
+        }
+        let hash = send_from_wallet(self, wallet, internal_message.clone(), send_mode)
+            .await?
+            .0;
+        Ok(hash)
+    }
+
+    /// Sends `set_code` message (op `0x9903d56d`) from `wallet` to `contract`.
+    ///
+    /// The message body is `op:uint32 query_id:uint64 new_code:^Cell new_data:(Maybe ^Cell)`.
+    /// Returns the hash of the external message sent to the wallet.
+    ///
+    /// Only `WalletVersion::V4R2` wallets are supported.
+    async fn upgrade_contract(
+        &self,
+        wallet: &TonWallet,
+        contract: &TonAddress,
+        new_code: &Cell,
+        new_data: Option<&Cell>,
+        send_mode: u8,
+    ) -> Result<TonHash, TonClientError> {
+        if new_code.is_exotic() {
+            return Err(TonClientError::InternalError(
+                "New code must be an ordinary cell".to_string(),
+            ));
+        }
+        if new_code.bit_len() == 0 && new_code.references().is_empty() {
+            return Err(TonClientError::InternalError(
+                "New code cell is empty".to_string(),
+            ));
+        }
+        // ensure that the code can be deployed at all
+        BagOfCells::from_root(new_code.clone())
+            .serialize(false)
+            .map_err(map_error)?;
+
+        let body = build_set_code_body(new_code, new_data, now_secs()).map_err(map_error)?;
+        let msg_info = internal_msg_info(contract, &BigUint::from(UPGRADE_MESSAGE_VALUE), true);
+        let internal_message = TransferMessage::new(msg_info)
+            .with_data(Arc::new(body))
+            .build()
+            .map_err(map_error)?;
+        let hash = send_from_wallet(self, wallet, internal_message, send_mode)
+            .await?
+            .0;
+        Ok(hash)
+    }
+
+    /// Transfers the balance of `wallet` to `destination`, leaving `reserve` on the wallet.
+    ///
+    /// Unlike sweeping with send mode 128, which often destroys the account, the message is sent
+    /// with send mode 0 and value `balance - reserve - fee`, where `fee` is estimated for the
+    /// transfer itself. Fails with `TonClientError::InsufficientBalance` if the balance doesn't
+    /// exceed `reserve + fee`. Returns the hash of the external message sent to the wallet.
+    ///
+    /// The wallet must be deployed. Only `WalletVersion::V4R2` wallets are supported.
+    async fn sweep_with_reserve(
+        &self,
+        wallet: &TonWallet,
+        destination: &TonAddress,
+        reserve: &Coins,
+        bounce: bool,
+    ) -> Result<TonHash, TonClientError> {
+        let state = get_wallet_state(self, wallet).await?;
+        let seqno = state.seqno.ok_or_else(|| {
+            TonClientError::InternalError(format!("Wallet {} is not deployed", wallet.address))
+        })?;
+        let balance = nanotons(state.balance);
+        // the fee is estimated for the largest value the message can carry
+        let estimated_message = transfer_message(destination, &balance, bounce)?;
+        let expire_at = wallet.valid_until(state.node_time);
+        let body = wallet
+            .create_external_body_with_send_mode(expire_at, seqno, [Arc::new(estimated_message)], 0)
+            .and_then(|body| BagOfCells::from_root(body).serialize(true))
+            .map_err(map_error)?;
+        let fees = self
+            .estimate_fees(&wallet.address, &body, &[], &[], true)
+            .await?;
+        let amount = sweep_amount(&balance, reserve, &total_source_fee(&fees.source_fees))?;
+        let internal_message = transfer_message(destination, amount.nanotons(), bounce)?;
+        let hash = send_from_wallet(self, wallet, internal_message, 0).await?.0;
+        Ok(hash)
+    }
+}
+
+impl<T> TonWalletFunctions for T where T: TonClientInterface + Send + Sync {}
+
+/// Signs & sends `internal_message` via `wallet`.
+///
+/// Returns the hash of the external message and its expiration time.
+async fn send_from_wallet<C: TonClientInterface + ?Sized>(
+    client: &C,
+    wallet: &TonWallet,
+    internal_message: Cell,
+    send_mode: u8,
+) -> Result<(TonHash, u64), TonClientError> {
+    let state = get_wallet_state(client, wallet).await?;
+    let expire_at = wallet.valid_until(state.node_time) as u64;
+    let message =
+        build_external_message(wallet, state.seqno, expire_at, internal_message, send_mode)?;
+    let hash = send_boc(client, &message.boc, &InvokeOptions::no_retry()).await?;
+    Ok((hash, expire_at))
+}
+
+/// Signed external message ready to be sent
+pub(crate) struct ExternalMessage {
+    pub(crate) boc: Vec<u8>,
+    pub(crate) hash: TonHash,
+    /// Hash of the message body, as seen in `in_msg.body_hash` of the wallet transaction
+    pub(crate) body_hash: TonHash,
+}
+
+pub(crate) struct WalletState {
+    /// `None` if the wallet is not deployed yet
+    pub(crate) seqno: Option<u32>,
+    /// Unix time of the node, or the local time if the node doesn't report it
+    pub(crate) node_time: u64,
+    /// Balance of the wallet in nanotons
+    pub(crate) balance: i64,
+}
+
+/// Returns seqno of the wallet together with the current time of the node.
+///
+/// Only `WalletVersion::V4R2` wallets are supported.
+pub(crate) async fn get_wallet_state<C: TonClientInterface + ?Sized>(
+    client: &C,
+    wallet: &TonWallet,
+) -> Result<WalletState, TonClientError> {
+    if wallet.version != WalletVersion::V4R2 {
+        return Err(TonClientError::InternalError(
+            "Only V4R2 wallets are supported".to_string(),
+        ));
+    }
+    let wallet_state = client.get_raw_account_state(&wallet.address).await?;
+    let node_time = if wallet_state.sync_utime > 0 {
+        wallet_state.sync_utime as u64
+    } else {
+        now_secs()
+    };
+    Ok(WalletState {
+        seqno: parse_wallet_seqno(&wallet_state)?,
+        node_time,
+        balance: wallet_state.balance,
+    })
+}
+
+/// Reads seqno from the data of `WalletVersion::V4R2` wallet, `None` if the wallet is not deployed
+pub(crate) fn parse_wallet_seqno(
+    wallet_state: &RawFullAccountState,
+) -> Result<Option<u32>, TonClientError> {
+    if wallet_state.data.is_empty() {
+        return Ok(None);
+    }
+    let data = BagOfCells::parse(&wallet_state.data)
+        .and_then(|boc| boc.single_root().cloned())
+        .map_err(map_error)?;
+    let data = WalletDataV4::try_from(data.as_ref().clone()).map_err(map_error)?;
+    Ok(Some(data.seqno))
+}
+
+/// Builds external message with `internal_message`, attaching state init if `seqno` is `None`
+pub(crate) fn build_external_message(
+    wallet: &TonWallet,
+    seqno: Option<u32>,
+    expire_at: u64,
+    internal_message: Cell,
+    send_mode: u8,
+) -> Result<ExternalMessage, TonClientError> {
+    let body = wallet
+        .create_external_body_with_send_mode(
+            expire_at as u32,
+            seqno.unwrap_or(0),
+            [Arc::new(internal_message)],
+            send_mode,
+        )
+        .map_err(map_error)?;
+    let signed = wallet.sign_external_body(&body).map_err(map_error)?;
+    let body_hash = signed.cell_hash();
+    let external_message = wallet
+        .wrap_signed_body(signed, seqno.is_none())
+        .map_err(map_error)?;
+    let hash = external_message.cell_hash();
+    let boc = BagOfCells::from_root(external_message);
+    let boc = if wallet.config().compress_boc {
+        boc.to_compressed(&BocSerializeOptions {
+            has_idx: false,
+            has_crc32c: true,
+        })
+    } else {
+        boc.serialize(true)
+    }
+    .map_err(map_error)?;
+    Ok(ExternalMessage {
+        boc,
+        hash,
+        body_hash,
+    })
+}
+
+/// Sends the external message, retrying only if `options` explicitly allow it
+pub(crate) async fn send_boc<C: TonClientInterface + ?Sized>(
+    client: &C,
+    boc: &[u8],
+    options: &InvokeOptions,
+) -> Result<TonHash, TonClientError> {
+    let hash = client
+        .send_raw_message_return_hash_with_options(boc, options)
+        .await?;
+    hash.as_slice()
+        .try_into()
+        .map_err(|_| TonClientError::InternalError(format!("Invalid message hash: {:?}", hash)))
+}
+
+fn transfer_simulation(
+    amount: &Coins,
+    sender_balance: i64,
+    source_fees: &Fees,
+    recipient_fees: Option<&Fees>,
+) -> TransferSimulation {
+    let total_fee = total_source_fee(source_fees);
+    let recipient_fee = recipient_fees
+        .map(|f| nanotons(f.storage_fee) + nanotons(f.gas_fee))
+        .unwrap_or_default();
+    let spent = amount.nanotons() + &total_fee;
+    TransferSimulation {
+        gas_fee: nanotons(source_fees.gas_fee).into(),
+        storage_fee: nanotons(source_fees.storage_fee).into(),
+        total_fee: total_fee.into(),
+        recipient_receives: saturating_sub(amount.nanotons(), &recipient_fee).into(),
+        sender_remaining_balance: saturating_sub(&nanotons(sender_balance), &spent).into(),
+    }
+}
+
+/// Fees paid by the sender: import, storage, gas & forwarding fees
+fn total_source_fee(source_fees: &Fees) -> BigUint {
+    nanotons(source_fees.in_fwd_fee)
+        + nanotons(source_fees.storage_fee)
+        + nanotons(source_fees.gas_fee)
+        + nanotons(source_fees.fwd_fee)
+}
+
+/// Amount left to transfer after keeping `reserve` and paying `fee`
+fn sweep_amount(
+    balance: &BigUint,
+    reserve: &Coins,
+    fee: &BigUint,
+) -> Result<Coins, TonClientError> {
+    let required = reserve.nanotons() + fee;
+    if balance <= &required {
+        return Err(TonClientError::InsufficientBalance {
+            balance: balance.clone().into(),
+            required: required.into(),
+        });
+    }
+    Ok((balance - required).into())
+}
+
+fn transfer_message(
+    destination: &TonAddress,
+    value: &BigUint,
+    bounce: bool,
+) -> Result<Cell, TonClientError> {
+    TransferMessage::new(internal_msg_info(destination, value, bounce))
+        .build()
+        .map_err(map_error)
+}
+
+fn nanotons(value: i64) -> BigUint {
+    BigUint::from(value.max(0) as u64)
+}
+
+fn saturating_sub(a: &BigUint, b: &BigUint) -> BigUint {
+    if a > b {
+        a - b
+    } else {
+        BigUint::default()
+    }
+}
+
+fn build_set_code_body(
+    new_code: &Cell,
+    new_data: Option<&Cell>,
+    query_id: u64,
+) -> Result<Cell, TonCellError> {
+    let new_data = new_data.map(|d| Arc::new(d.clone()));
+    CellBuilder::new()
+        .store_u32(32, SET_CODE_OP)?
+        .store_u64(64, query_id)?
+        .store_child(new_code.clone())?
+        .store_maybe_cell_ref(&new_data)?
+        .build()
+}
+
+/// Checks the bounce flag of a message without state init sent to `destination`
+fn check_bounce(
+    destination: &TonAddress,
+    bounce: bool,
+    destination_active: bool,
+    check: BounceCheck,
+) -> Result<(), TonClientError> {
+    if destination_active {
+        return Ok(());
+    }
+    let error = if bounce {
+        TonClientError::BounceToUninit {
+            destination: destination.clone(),
+        }
+    } else if check == BounceCheck::AllowNonBounceableToUninit {
+        return Ok(());
+    } else {
+        TonClientError::NonBounceableToUninit {
+            destination: destination.clone(),
+        }
+    };
+    match check {
+        BounceCheck::Warn => {
+            log::warn!("{}", error);
+            Ok(())
+        }
+        _ => Err(error),
+    }
+}
+
+async fn is_active<C: TonClientInterface + ?Sized>(
+    client: &C,
+    address: &TonAddress,
+) -> Result<bool, TonClientError> {
+    let state = client.get_raw_account_state(address).await?;
+    Ok(!state.code.is_empty())
+}
+
+fn internal_msg_info(dest: &TonAddress, value: &BigUint, bounce: bool) -> CommonMsgInfo {
+    CommonMsgInfo::InternalMessage(InternalMessage {
+        ihr_disabled: true,
+        bounce,
+        bounced: false,
+        src: TonAddress::NULL,
+        dest: dest.clone(),
+        value: value.clone(),
+        ihr_fee: BigUint::default(),
+        fwd_fee: BigUint::default(),
+        created_lt: 0,
+        created_at: 0,
+    })
+}
+
+pub(crate) fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+pub(crate) fn map_error<E: std::error::Error>(e: E) -> TonClientError {
+    TonClientError::InternalError(format!("Failed to build message: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    use tonlib_core::testkit::config_params_boc;
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, tonlib_error, MockClient, RetryingClient};
+    use crate::client::parse_internal_message;
+    use crate::tl::{ConfigInfo, RawExtMessageInfo, TonFunction, TonResult, TvmCell};
+
+    fn test_wallet() -> anyhow::Result<TonWallet> {
+        let key_pair = tonlib_core::mnemonic::Mnemonic::from_str(
+            "fancy carpet hello mandate penalty trial consider property top vicious exit rebuild \
+            tragic profit urban major total month holiday sudden rib gather media vicious",
+            &None,
+        )?
+        .to_key_pair()?;
+        Ok(TonWallet::derive_default(WalletVersion::V4R2, &key_pair)?)
+    }
+
+    #[test]
+    fn test_transfer_simulation() {
+        let source_fees = Fees {
+            in_fwd_fee: 1_000,
+            storage_fee: 10,
+            gas_fee: 3_000,
+            fwd_fee: 500,
+        };
+        let recipient_fees = Fees {
+            in_fwd_fee: 0,
+            storage_fee: 1,
+            gas_fee: 300,
+            fwd_fee: 0,
+        };
+        let amount = Coins::from(1_000_000u64);
+
+        let simulation =
+            transfer_simulation(&amount, 2_000_000, &source_fees, Some(&recipient_fees));
+        assert_eq!(simulation.gas_fee, Coins::from(3_000u64));
+        assert_eq!(simulation.storage_fee, Coins::from(10u64));
+        assert_eq!(simulation.total_fee, Coins::from(4_510u64));
+        assert_eq!(simulation.recipient_receives, Coins::from(999_699u64));
+        assert_eq!(simulation.sender_remaining_balance, Coins::from(995_490u64));
+
+        let simulation = transfer_simulation(&amount, 1_000_000, &source_fees, None);
+        assert_eq!(simulation.recipient_receives, amount);
+        assert!(simulation.sender_remaining_balance.is_zero());
+    }
+
+    #[test]
+    fn test_sweep_amount() {
+        let reserve = Coins::from(10_000_000u64);
+        let fee = BigUint::from(4_510u32);
+        let amount = sweep_amount(&BigUint::from(1_000_000_000u64), &reserve, &fee).unwrap();
+        assert_eq!(amount, Coins::from(989_995_490u64));
+
+        for balance in [10_004_510u64, 5_000_000, 0] {
+            match sweep_amount(&BigUint::from(balance), &reserve, &fee) {
+                Err(TonClientError::InsufficientBalance {
+                    balance: b,
+                    required,
+                }) => {
+                    assert_eq!(b, Coins::from(balance));
+                    assert_eq!(required, Coins::from(10_004_510u64));
+                }
+                r => panic!("Unexpected result: {:?}", r),
+            }
+        }
+    }
+
+    #[test]
+    fn test_check_bounce() {
+        let destination = TonAddress::new(0, &[0x11; 32]);
+        for check in [
+            BounceCheck::Strict,
+            BounceCheck::Warn,
+            BounceCheck::AllowNonBounceableToUninit,
+        ] {
+            assert!(check_bounce(&destination, true, true, check).is_ok());
+            assert!(check_bounce(&destination, false, true, check).is_ok());
+        }
+
+        assert!(matches!(
+            check_bounce(&destination, true, false, BounceCheck::Strict),
+            Err(TonClientError::BounceToUninit { .. })
+        ));
+        assert!(matches!(
+            check_bounce(&destination, false, false, BounceCheck::Strict),
+            Err(TonClientError::NonBounceableToUninit { .. })
+        ));
+        assert!(matches!(
+            check_bounce(
+                &destination,
+                true,
+                false,
+                BounceCheck::AllowNonBounceableToUninit
+            ),
+            Err(TonClientError::BounceToUninit { .. })
+        ));
+        assert!(check_bounce(
+            &destination,
+            false,
+            false,
+            BounceCheck::AllowNonBounceableToUninit
+        )
+        .is_ok());
+        assert!(check_bounce(&destination, true, false, BounceCheck::Warn).is_ok());
+        assert!(check_bounce(&destination, false, false, BounceCheck::Warn).is_ok());
+    }
+
+    #[test]
+    fn test_build_set_code_body() -> anyhow::Result<()> {
+        let code = CellBuilder::new().store_u32(32, 0xC0DE)?.build()?;
+        let data = CellBuilder::new().store_u32(32, 0xDA7A)?.build()?;
+
+        let body = build_set_code_body(&code, Some(&data), 42)?;
+        let mut parser = body.parser();
+        assert_eq!(parser.load_u32(32)?, SET_CODE_OP);
+        assert_eq!(parser.load_u64(64)?, 42);
+        assert_eq!(parser.next_reference()?.as_ref(), &code);
+        assert_eq!(
+            parser.load_maybe_cell_ref()?.map(|c| c.as_ref().clone()),
+            Some(data)
+        );
+
+        let body = build_set_code_body(&code, None, 42)?;
+        let mut parser = body.parser();
+        parser.skip_bits(96)?;
+        assert_eq!(parser.next_reference()?.as_ref(), &code);
+        assert_eq!(parser.load_maybe_cell_ref()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_build_compressed_external_message() -> anyhow::Result<()> {
+        let wallet = test_wallet()?;
+        let transfer = CellBuilder::new().store_u32(32, 0)?.build()?;
+
+        let plain = build_external_message(&wallet, Some(1), 1700000000, transfer.clone(), 3)?;
+        assert!(!BagOfCells::is_compressed(&plain.boc));
+
+        let mut config = *wallet.config();
+        config.compress_boc = true;
+        let wallet = wallet.with_config(config);
+        let compressed = build_external_message(&wallet, Some(1), 1700000000, transfer, 3)?;
+        assert!(BagOfCells::is_compressed(&compressed.boc));
+        assert_eq!(compressed.hash, plain.hash);
+        let root = BagOfCells::parse(&compressed.boc)?
+            .single_root()?
+            .cell_hash();
+        assert_eq!(root, plain.hash);
+        Ok(())
+    }
+
+    /// Client serving an undeployed account holding 1 TON and the config, counting sent messages.
+    /// Sends fail with a retryable error if `send_fails` is set.
+    fn simulation_client(sends: &Arc<AtomicUsize>, send_fails: bool) -> RetryingClient {
+        let sends = sends.clone();
+        RetryingClient(MockClient::new(move |function| match function {
+            TonFunction::RawGetAccountState { .. } => {
+                Ok(TonResult::RawFullAccountState(RawFullAccountState {
+                    balance: 1_000_000_000,
+                    ..account_state(&mc_block(1))
+                }))
+            }
+            TonFunction::GetConfigAll { .. } => Ok(TonResult::ConfigInfo(ConfigInfo {
+                config: TvmCell {
+                    bytes: config_params_boc().unwrap(),
+                },
+            })),
+            TonFunction::RawSendMessageReturnHash { body } => {
+                sends.fetch_add(1, Ordering::SeqCst);
+                if send_fails {
+                    return Err(tonlib_error(function, 500, "LITE_SERVER_NETWORK"));
+                }
+                let hash = BagOfCells::parse(body)
+                    .unwrap()
+                    .single_root()
+                    .unwrap()
+                    .cell_hash();
+                Ok(TonResult::RawExtMessageInfo(RawExtMessageInfo {
+                    hash: hash.to_vec(),
+                }))
+            }
+            f => unimplemented!("{:?}", f),
+        }))
+    }
+
+    /// Deploying external message of `wallet` transferring 1000 nanotons
+    fn deploy_message(wallet: &TonWallet, expire_at: u64) -> anyhow::Result<(Cell, TonHash)> {
+        let destination = TonAddress::new(0, &[0x11; 32]);
+        let transfer = transfer_message(&destination, &BigUint::from(1000u32), false)?;
+        let message = build_external_message(wallet, None, expire_at, transfer, 3)?;
+        let cell = BagOfCells::parse(&message.boc)?
+            .single_root()?
+            .as_ref()
+            .clone();
+        Ok((cell, message.hash))
+    }
+
+    #[tokio::test]
+    async fn test_send_with_simulation() -> anyhow::Result<()> {
+        let wallet = test_wallet()?;
+        let sends = Arc::new(AtomicUsize::new(0));
+        let client = simulation_client(&sends, false);
+
+        let (message, hash) = deploy_message(&wallet, now_secs() + 60)?;
+        let (emulation, sent) = client
+            .send_with_simulation(&wallet.address, &message, false)
+            .await?;
+        assert!(emulation.accepted);
+        assert_eq!(sent, Some(hash));
+        assert_eq!(sends.load(Ordering::SeqCst), 1);
+
+        // the expired message is rejected by the wallet, so it's sent only if forced
+        let (message, hash) = deploy_message(&wallet, 1)?;
+        let (emulation, sent) = client
+            .send_with_simulation(&wallet.address, &message, false)
+            .await?;
+        assert!(!emulation.accepted);
+        assert_eq!(sent, None);
+        assert_eq!(sends.load(Ordering::SeqCst), 1);
+        let (_, sent) = client
+            .send_with_simulation(&wallet.address, &message, true)
+            .await?;
+        assert_eq!(sent, Some(hash));
+        assert_eq!(sends.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_send_with_simulation_no_retry() -> anyhow::Result<()> {
+        let wallet = test_wallet()?;
+        let sends = Arc::new(AtomicUsize::new(0));
+        let client = simulation_client(&sends, true);
+
+        let (message, _) = deploy_message(&wallet, now_secs() + 60)?;
+        let result = client
+            .send_with_simulation(&wallet.address, &message, false)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(sends.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    /// Client serving the contract as deployed once `deployed` is set, and sending messages
+    /// setting it. The wallet is not deployed. Sent messages are collected into `sent`.
+    fn deploy_client(
+        contract: TonAddress,
+        deployed: &Arc<AtomicBool>,
+        sent: &Arc<Mutex<Vec<Cell>>>,
+    ) -> MockClient {
+        let deployed = deployed.clone();
+        let sent = sent.clone();
+        MockClient::new(move |function| match function {
+            TonFunction::RawGetAccountState { account_address } => {
+                let address: TonAddress = account_address.account_address.parse().unwrap();
+                let mut state = account_state(&mc_block(1));
+                if address == contract && deployed.load(Ordering::SeqCst) {
+                    state.code = BagOfCells::from_root(Cell::default())
+                        .serialize(false)
+                        .unwrap();
+                }
+                Ok(TonResult::RawFullAccountState(state))
+            }
+            TonFunction::RawSendMessageReturnHash { body } => {
+                let message = BagOfCells::parse(body)
+                    .unwrap()
+                    .single_root()
+                    .unwrap()
+                    .clone();
+                sent.lock().unwrap().push(message.as_ref().clone());
+                deployed.store(true, Ordering::SeqCst);
+                Ok(TonResult::RawExtMessageInfo(RawExtMessageInfo {
+                    hash: message.cell_hash().to_vec(),
+                }))
+            }
+            f => unimplemented!("{:?}", f),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_deploy_contract() -> anyhow::Result<()> {
+        let wallet = test_wallet()?;
+        let code = CellBuilder::new().store_u32(32, 0xC0DE)?.build()?;
+        let data = CellBuilder::new().store_u32(32, 0xDA7A)?.build()?;
+        let account_id =
+            StateInit::create_account_id(&Arc::new(code.clone()), &Arc::new(data.clone()))?;
+        let contract = TonAddress::new(0, &account_id);
+        let deployed = Arc::new(AtomicBool::new(false));
+        let sent = Arc::new(Mutex::new(vec![]));
+        let client = deploy_client(contract.clone(), &deployed, &sent);
+
+        let balance = BigUint::from(50_000_000u32);
+        let address = client
+            .deploy_contract(&wallet, &code, &data, &balance, 0)
+            .await?;
+        assert_eq!(address, contract);
+        let sent = sent.lock().unwrap().clone();
+        assert_eq!(sent.len(), 1);
+        // the external message deploys the wallet as well, since it's not deployed yet
+        let (wallet_init, body) = parse_external_message(&sent[0])?;
+        assert!(wallet_init.is_some());
+        let (info, state_init, _) = parse_internal_message(body.reference(0)?)?;
+        assert_eq!(info.dest, contract);
+        assert_eq!(info.value, balance);
+        assert!(!info.bounce);
+        let (init_code, init_data) = state_init.unwrap();
+        assert_eq!(init_code.as_ref(), &code);
+        assert_eq!(init_data.as_ref(), &data);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deploy_contract_already_deployed() -> anyhow::Result<()> {
+        let wallet = test_wallet()?;
+        let code = CellBuilder::new().store_u32(32, 0xC0DE)?.build()?;
+        let data = CellBuilder::new().store_u32(32, 0xDA7A)?.build()?;
+        let account_id =
+            StateInit::create_account_id(&Arc::new(code.clone()), &Arc::new(data.clone()))?;
+        let contract = TonAddress::new(-1, &account_id);
+        let deployed = Arc::new(AtomicBool::new(true));
+        let sent = Arc::new(Mutex::new(vec![]));
+        let client = deploy_client(contract.clone(), &deployed, &sent);
+
+        let address = client
+            .deploy_contract(&wallet, &code, &data, &BigUint::from(1u32), -1)
+            .await?;
+        assert_eq!(address, contract);
+        assert!(sent.lock().unwrap().is_empty());
+        assert_eq!(client.calls(), 1);
+        Ok(())
+    }
+}
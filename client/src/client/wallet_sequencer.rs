@@ -0,0 +1,537 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tonlib_core::cell::{BagOfCells, Cell};
+use tonlib_core::wallet::TonWallet;
+use tonlib_core::TonHash;
+
+use crate::client::{
+    build_external_message, get_wallet_state, map_error, now_secs, parse_wallet_seqno, send_boc,
+    InvokeOptions, TonClientError, TonClientInterface, CONFIRMATION_POLL_INTERVAL,
+};
+use crate::rt::{self, Mutex};
+use crate::tl::{Base64Standard, MsgData, RawTransaction};
+
+/// Number of the latest wallet transactions scanned when looking for a sent message
+const MAX_SCANNED_TRANSACTIONS: usize = 16;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum SendStatus {
+    /// Message is persisted, but might not have been sent
+    Pending,
+    /// Message is sent, but not confirmed yet
+    Sent,
+    /// Message is executed by the wallet
+    Confirmed,
+    /// Message expired or its seqno has been used by another message, so it will never be executed
+    Expired,
+}
+
+/// Outcome of sending a message with an idempotency key
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IdempotencyRecord {
+    /// Hash of the external message
+    pub message_hash: TonHash,
+    /// Hash of the external message body, as seen in the wallet transaction
+    pub body_hash: TonHash,
+    pub seqno_used: u32,
+    pub expire_at: u64,
+    pub status: SendStatus,
+    /// Serialized external message, resent as is if it might have not reached the network
+    #[serde(with = "Base64Standard")]
+    pub boc: Vec<u8>,
+}
+
+/// Persistent storage of idempotency records.
+///
+/// `put` must not return before the record is durably stored.
+pub trait IdempotencyStore: Send + Sync {
+    fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, TonClientError>;
+
+    fn put(&self, key: &str, record: &IdempotencyRecord) -> Result<(), TonClientError>;
+}
+
+/// `IdempotencyStore` keeping all records in a single JSON file
+pub struct FileIdempotencyStore {
+    path: PathBuf,
+    lock: std::sync::Mutex<()>,
+}
+
+impl FileIdempotencyStore {
+    pub fn new<P: Into<PathBuf>>(path: P) -> FileIdempotencyStore {
+        FileIdempotencyStore {
+            path: path.into(),
+            lock: std::sync::Mutex::new(()),
+        }
+    }
+
+    fn load(&self) -> Result<HashMap<String, IdempotencyRecord>, TonClientError> {
+        if !self.path.exists() {
+            return Ok(HashMap::new());
+        }
+        let content = fs::read(&self.path)?;
+        serde_json::from_slice(&content).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to parse idempotency store: {}", e))
+        })
+    }
+}
+
+impl IdempotencyStore for FileIdempotencyStore {
+    fn get(&self, key: &str) -> Result<Option<IdempotencyRecord>, TonClientError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.remove(key))
+    }
+
+    fn put(&self, key: &str, record: &IdempotencyRecord) -> Result<(), TonClientError> {
+        let _lock = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut records = self.load()?;
+        records.insert(key.to_string(), record.clone());
+        let content = serde_json::to_vec(&records).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to serialize idempotency store: {}", e))
+        })?;
+        // write to temporary file first, so that the store is never left half-written
+        let tmp_path = self.path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp_path)?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+/// Sends messages from a single wallet one by one, so that they don't compete for seqno.
+///
+/// Unlike `TonWallet`, which only builds and signs messages with the given seqno, the sequencer
+/// owns the sending state of the wallet. It is `Send + Sync`, so a single instance can be shared
+/// across tasks (e.g. in an `Arc`): concurrent calls are serialized and never reuse a seqno.
+///
+/// Only `WalletVersion::V4R2` wallets are supported.
+pub struct WalletSequencer<C: TonClientInterface> {
+    client: C,
+    wallet: TonWallet,
+    poll_interval: Duration,
+    send_options: InvokeOptions,
+    lock: Mutex<()>,
+}
+
+/// State of a previously built message on chain
+#[derive(Debug, PartialEq, Eq)]
+enum ChainState {
+    Executed,
+    /// Seqno of the message has been used by another message
+    Replaced,
+    NotExecuted,
+}
+
+impl<C: TonClientInterface> WalletSequencer<C> {
+    pub fn new(client: C, wallet: TonWallet) -> WalletSequencer<C> {
+        WalletSequencer {
+            client,
+            wallet,
+            poll_interval: CONFIRMATION_POLL_INTERVAL,
+            send_options: InvokeOptions::no_retry(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn with_poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Sets options of sending messages, which are never retried by default.
+    ///
+    /// Retries are safe for seqno-based wallets, since a duplicate message is rejected by the wallet.
+    pub fn with_send_options(mut self, send_options: &InvokeOptions) -> Self {
+        self.send_options = send_options.clone();
+        self
+    }
+
+    /// Sends `transfer` (internal message) from the wallet and waits for its confirmation
+    /// at most once per `key`.
+    ///
+    /// The message is persisted in `store` before sending. When called again with the same key
+    /// (e.g. after a crash), the outcome of the previous attempt is looked up on chain first:
+    /// confirmed messages are not sent again, and messages which might have not reached the
+    /// network are resent as is, so that the wallet seqno prevents double execution.
+    /// A new message is built only if the previous one can't be executed anymore.
+    pub async fn send_idempotent(
+        &self,
+        key: &str,
+        transfer: &Cell,
+        store: &dyn IdempotencyStore,
+    ) -> Result<IdempotencyRecord, TonClientError> {
+        let _lock = self.lock.lock().await;
+        if let Some(mut record) = store.get(key)? {
+            if record.status == SendStatus::Confirmed {
+                return Ok(record);
+            }
+            match self.chain_state(&record).await? {
+                ChainState::Executed => {
+                    record.status = SendStatus::Confirmed;
+                    store.put(key, &record)?;
+                    return Ok(record);
+                }
+                ChainState::NotExecuted if now_secs() < record.expire_at => {
+                    log::info!(
+                        "Resending message {} for key {}",
+                        hex::encode(record.message_hash),
+                        key
+                    );
+                    send_boc(&self.client, &record.boc, &self.send_options).await?;
+                    record.status = SendStatus::Sent;
+                    store.put(key, &record)?;
+                    return self.wait_confirmation(key, record, store).await;
+                }
+                _ => {
+                    log::info!(
+                        "Message {} for key {} will never be executed, sending a new one",
+                        hex::encode(record.message_hash),
+                        key
+                    );
+                }
+            }
+        }
+
+        let state = get_wallet_state(&self.client, &self.wallet).await?;
+        let expire_at = self.wallet.valid_until(state.node_time) as u64;
+        let message = build_external_message(
+            &self.wallet,
+            state.seqno,
+            expire_at,
+            transfer.clone(),
+            self.wallet.config().send_mode,
+        )?;
+        let mut record = IdempotencyRecord {
+            message_hash: message.hash,
+            body_hash: message.body_hash,
+            seqno_used: state.seqno.unwrap_or(0),
+            expire_at,
+            status: SendStatus::Pending,
+            boc: message.boc,
+        };
+        store.put(key, &record)?;
+        send_boc(&self.client, &record.boc, &self.send_options).await?;
+        record.status = SendStatus::Sent;
+        store.put(key, &record)?;
+        self.wait_confirmation(key, record, store).await
+    }
+
+    async fn wait_confirmation(
+        &self,
+        key: &str,
+        mut record: IdempotencyRecord,
+        store: &dyn IdempotencyStore,
+    ) -> Result<IdempotencyRecord, TonClientError> {
+        loop {
+            let state = self.chain_state(&record).await?;
+            let expired = now_secs() > record.expire_at;
+            if state == ChainState::Executed {
+                record.status = SendStatus::Confirmed;
+                store.put(key, &record)?;
+                return Ok(record);
+            }
+            if state == ChainState::Replaced || expired {
+                record.status = SendStatus::Expired;
+                store.put(key, &record)?;
+                return Err(TonClientError::InternalError(format!(
+                    "Message {} is not executed before expiration",
+                    hex::encode(record.message_hash)
+                )));
+            }
+            rt::sleep(self.poll_interval).await;
+        }
+    }
+
+    async fn chain_state(&self, record: &IdempotencyRecord) -> Result<ChainState, TonClientError> {
+        let address = &self.wallet.address;
+        let state = self.client.get_raw_account_state(address).await?;
+        let current_seqno = parse_wallet_seqno(&state)?.unwrap_or(0);
+        if current_seqno <= record.seqno_used {
+            return Ok(ChainState::NotExecuted);
+        }
+        let txs = self
+            .client
+            .get_raw_transactions_v2(
+                address,
+                &state.last_transaction_id,
+                MAX_SCANNED_TRANSACTIONS,
+                false,
+            )
+            .await?;
+        for tx in txs.transactions.iter() {
+            let in_msg = match &tx.in_msg {
+                Some(in_msg) => in_msg,
+                None => continue,
+            };
+            if in_msg.body_hash == record.body_hash {
+                return Ok(ChainState::Executed);
+            }
+            if in_msg_seqno(tx) == Some(record.seqno_used) {
+                return Ok(ChainState::Replaced);
+            }
+        }
+        // Sending a new message here could lead to double transfer
+        Err(TonClientError::InternalError(format!(
+            "Seqno {} is used, but its transaction is not found among the latest {} transactions",
+            record.seqno_used, MAX_SCANNED_TRANSACTIONS
+        )))
+    }
+}
+
+/// Reads seqno from the body of external message of wallet transaction
+fn in_msg_seqno(tx: &RawTransaction) -> Option<u32> {
+    let body = match &tx.in_msg.as_ref()?.msg_data {
+        MsgData::Raw { body, .. } => body,
+        _ => return None,
+    };
+    let body = BagOfCells::parse(body)
+        .and_then(|boc| boc.single_root().cloned())
+        .map_err(map_error)
+        .ok()?;
+    let mut parser = body.parser();
+    // signature, wallet_id, valid_until
+    parser.skip_bits(512 + 32 + 32).ok()?;
+    parser.load_u32(32).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex as SyncMutex};
+
+    use tonlib_core::cell::CellBuilder;
+    use tonlib_core::mnemonic::Mnemonic;
+    use tonlib_core::wallet::{WalletDataV4, WalletVersion};
+    use tonlib_core::TonAddress;
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, raw_message, raw_transaction, MockClient};
+    use crate::tl::{
+        RawExtMessageInfo, RawFullAccountState, RawTransactions, TonFunction, TonResult,
+        NULL_TRANSACTION_ID,
+    };
+
+    #[derive(Clone, Copy, PartialEq)]
+    enum Crash {
+        /// Message is lost, e.g. the process died before sending
+        BeforeDelivery,
+        /// Message is delivered, but the process died before getting the result
+        AfterDelivery,
+    }
+
+    #[derive(Default)]
+    struct MockState {
+        seqno: u32,
+        transactions: Vec<RawTransaction>,
+        transfers: u32,
+        crash: Option<Crash>,
+    }
+
+    /// Wallet account executing external messages synchronously
+    #[derive(Default)]
+    struct MockChain {
+        state: SyncMutex<MockState>,
+    }
+
+    impl MockChain {
+        fn account_state(&self, state: &MockState) -> RawFullAccountState {
+            let data = Cell::try_from(WalletDataV4 {
+                seqno: state.seqno,
+                wallet_id: 0,
+                public_key: [0; 32],
+            })
+            .unwrap();
+            let last_transaction_id = state
+                .transactions
+                .last()
+                .map(|tx| tx.transaction_id.clone())
+                .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+            RawFullAccountState {
+                balance: 1_000_000_000,
+                code: vec![0],
+                data: BagOfCells::from_root(data).serialize(false).unwrap(),
+                last_transaction_id,
+                ..account_state(&mc_block(1))
+            }
+        }
+
+        fn execute(&self, state: &mut MockState, boc: &[u8]) -> TonHash {
+            let message = BagOfCells::parse(boc)
+                .unwrap()
+                .single_root()
+                .unwrap()
+                .clone();
+            let body = message.reference(0).unwrap().clone();
+            let mut parser = body.parser();
+            parser.skip_bits(512 + 32).unwrap();
+            let valid_until = parser.load_u32(32).unwrap() as u64;
+            let seqno = parser.load_u32(32).unwrap();
+            if seqno == state.seqno && valid_until >= now_secs() {
+                state.seqno += 1;
+                state.transfers += 1;
+                let lt = state.transactions.len() as i64 + 1;
+                state.transactions.push(RawTransaction {
+                    in_msg: Some(raw_message(None, &TonAddress::NULL, &body)),
+                    ..raw_transaction(&TonAddress::NULL, lt)
+                });
+            }
+            message.cell_hash()
+        }
+
+        fn invoke(&self, function: &TonFunction) -> Result<TonResult, TonClientError> {
+            let mut state = self.state.lock().unwrap();
+            match function {
+                TonFunction::RawGetAccountState { .. } => {
+                    Ok(TonResult::RawFullAccountState(self.account_state(&state)))
+                }
+                TonFunction::RawGetTransactionsV2 { .. } => {
+                    Ok(TonResult::RawTransactions(RawTransactions {
+                        transactions: state.transactions.iter().rev().cloned().collect(),
+                        previous_transaction_id: NULL_TRANSACTION_ID.clone(),
+                    }))
+                }
+                TonFunction::RawSendMessageReturnHash { body } => match state.crash.take() {
+                    Some(Crash::BeforeDelivery) => {
+                        Err(TonClientError::InternalError("crash".to_string()))
+                    }
+                    Some(Crash::AfterDelivery) => {
+                        self.execute(&mut state, body);
+                        Err(TonClientError::InternalError("crash".to_string()))
+                    }
+                    None => {
+                        let hash = self.execute(&mut state, body);
+                        Ok(TonResult::RawExtMessageInfo(RawExtMessageInfo {
+                            hash: hash.to_vec(),
+                        }))
+                    }
+                },
+                f => panic!("Unexpected function: {:?}", f),
+            }
+        }
+
+        fn transfers(&self) -> u32 {
+            self.state.lock().unwrap().transfers
+        }
+    }
+
+    fn sequencer(
+        crash: Option<Crash>,
+    ) -> anyhow::Result<(WalletSequencer<MockClient>, Arc<MockChain>)> {
+        let mnemonic = Mnemonic::from_str("mechanic sudden cannon bind monkey brown moment able street pride struggle team outdoor canyon coin tourist service second crazy tank sell regret sample attitude", &None)?;
+        let wallet = TonWallet::derive_default(WalletVersion::V4R2, &mnemonic.to_key_pair()?)?;
+        let chain = Arc::new(MockChain::default());
+        chain.state.lock().unwrap().crash = crash;
+        let client = {
+            let chain = chain.clone();
+            MockClient::new(move |function| chain.invoke(function))
+        };
+        let sequencer =
+            WalletSequencer::new(client, wallet).with_poll_interval(Duration::from_millis(1));
+        Ok((sequencer, chain))
+    }
+
+    fn store(name: &str) -> FileIdempotencyStore {
+        let path = std::env::temp_dir().join(format!(
+            "tonlib_idempotency_{}_{}.json",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_file(&path);
+        FileIdempotencyStore::new(path)
+    }
+
+    fn transfer() -> anyhow::Result<Cell> {
+        Ok(CellBuilder::new().store_u32(32, 0)?.build()?)
+    }
+
+    #[tokio::test]
+    async fn test_crash_between_persist_and_send() -> anyhow::Result<()> {
+        let (sequencer, chain) = sequencer(Some(Crash::BeforeDelivery))?;
+        let store = store("persist_send");
+        assert!(sequencer
+            .send_idempotent("key", &transfer()?, &store)
+            .await
+            .is_err());
+        assert_eq!(store.get("key")?.unwrap().status, SendStatus::Pending);
+        assert_eq!(chain.transfers(), 0);
+
+        let record = sequencer
+            .send_idempotent("key", &transfer()?, &store)
+            .await?;
+        assert_eq!(record.status, SendStatus::Confirmed);
+        assert_eq!(record.seqno_used, 0);
+        let record = sequencer
+            .send_idempotent("key", &transfer()?, &store)
+            .await?;
+        assert_eq!(record.status, SendStatus::Confirmed);
+        assert_eq!(chain.transfers(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_crash_between_send_and_confirm() -> anyhow::Result<()> {
+        let (sequencer, chain) = sequencer(Some(Crash::AfterDelivery))?;
+        let store = store("send_confirm");
+        assert!(sequencer
+            .send_idempotent("key", &transfer()?, &store)
+            .await
+            .is_err());
+        assert_eq!(chain.transfers(), 1);
+
+        let record = sequencer
+            .send_idempotent("key", &transfer()?, &store)
+            .await?;
+        assert_eq!(record.status, SendStatus::Confirmed);
+        assert_eq!(chain.transfers(), 1);
+
+        let record = sequencer
+            .send_idempotent("other", &transfer()?, &store)
+            .await?;
+        assert_eq!(record.seqno_used, 1);
+        assert_eq!(chain.transfers(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_sequencer_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<WalletSequencer<crate::client::TonClient>>();
+        assert_send_sync::<FileIdempotencyStore>();
+        assert_send_sync::<TonWallet>();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_sends_use_distinct_seqno() -> anyhow::Result<()> {
+        let (sequencer, chain) = sequencer(None)?;
+        let sequencer = Arc::new(sequencer);
+        let store = Arc::new(store("concurrent"));
+        let tasks: Vec<_> = (0..32u32)
+            .map(|i| {
+                let sequencer = sequencer.clone();
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let transfer = CellBuilder::new().store_u32(32, i)?.build()?;
+                    let record = sequencer
+                        .send_idempotent(&format!("key-{}", i), &transfer, store.as_ref())
+                        .await?;
+                    anyhow::Ok(record)
+                })
+            })
+            .collect();
+        let mut seqnos = vec![];
+        for task in tasks {
+            let record = task.await??;
+            assert_eq!(record.status, SendStatus::Confirmed);
+            seqnos.push(record.seqno_used);
+        }
+        seqnos.sort();
+        assert_eq!(seqnos, (0..32).collect::<Vec<_>>());
+        assert_eq!(chain.transfers(), 32);
+        Ok(())
+    }
+}
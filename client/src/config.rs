@@ -1,31 +1,90 @@
+#[cfg(feature = "liteapi")]
+use std::future::Future;
+#[cfg(feature = "liteapi")]
+use std::time::Duration;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use thiserror::Error;
 
 pub const MAINNET_CONFIG: &str = include_str!("../resources/config/global.config.json");
 pub const TESTNET_CONFIG: &str = include_str!("../resources/config/testnet-global.config.json");
 
+#[derive(Debug, Error)]
+pub enum TonConfigError {
+    #[error("Configs belong to different networks (zero states: {this}, {other})")]
+    NetworkMismatch { this: Value, other: Value },
+
+    #[error("All {count} liteservers are unresponsive")]
+    NoResponsiveLiteservers { count: usize },
+}
+
+/// Problem of a config skipped by `TonConfig::from_json_lenient`
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TonConfigWarning {
+    #[error("Malformed liteserver #{index} is skipped: {error}")]
+    MalformedLiteserver { index: usize, error: String },
+}
+
+/// Global network config, as used by `TonConnectionParams::config`
 #[derive(Serialize, Deserialize)]
-pub(crate) struct TonConfig {
+pub struct TonConfig {
     #[serde(rename = "@type")]
     conf_type: Value,
     dht: Value,
-    pub liteservers: Vec<LiteEndpoint>,
+    pub(crate) liteservers: Vec<LiteEndpoint>,
     validator: Validator,
 }
 
-#[cfg(feature = "liteapi")]
 impl TonConfig {
     pub fn from_json(config: &str) -> Result<Self, serde_json::Error> {
         serde_json::from_str(config)
     }
+
+    /// Parses the config skipping malformed liteserver entries, which are reported as warnings.
+    ///
+    /// Fails only if the JSON itself or any other section of the config is invalid.
+    pub fn from_json_lenient(
+        config: &str,
+    ) -> Result<(Self, Vec<TonConfigWarning>), serde_json::Error> {
+        let mut value: Value = serde_json::from_str(config)?;
+        let entries = match value.get_mut("liteservers") {
+            Some(Value::Array(entries)) => std::mem::take(entries),
+            _ => vec![],
+        };
+        let mut config: TonConfig = serde_json::from_value(value)?;
+        let mut warnings = vec![];
+        for (index, entry) in entries.into_iter().enumerate() {
+            match serde_json::from_value::<LiteEndpoint>(entry) {
+                Ok(liteserver) => config.liteservers.push(liteserver),
+                Err(e) => {
+                    let warning = TonConfigWarning::MalformedLiteserver {
+                        index,
+                        error: e.to_string(),
+                    };
+                    log::warn!("{}", warning);
+                    warnings.push(warning);
+                }
+            }
+        }
+        Ok((config, warnings))
+    }
     pub fn to_json(&self) -> Result<String, serde_json::Error> {
         serde_json::to_string(self)
     }
 
+    /// Returns seqno of the init block, 0 if it is missing.
     pub fn get_init_block_seqno(&self) -> i32 {
         self.validator.init_block["seqno"].as_i64().unwrap_or(0) as i32
     }
 
+    /// Returns the init block, the masterchain block tonlib starts syncing from.
+    ///
+    /// The older it is, the longer tonlib syncs on start, so it's worth monitoring.
+    pub fn init_block(&self) -> Result<crate::tl::BlockIdExt, serde_json::Error> {
+        crate::tl::BlockIdExt::deserialize(&self.validator.init_block)
+    }
+
     pub fn set_init_block(
         &mut self,
         block_id: &crate::tl::BlockIdExt,
@@ -33,6 +92,143 @@ impl TonConfig {
         self.validator.init_block = serde_json::to_value(block_id)?;
         Ok(())
     }
+
+    pub fn zero_state_root_hash(&self) -> Option<&str> {
+        self.validator.zero_state["root_hash"].as_str()
+    }
+
+    pub fn init_block_root_hash(&self) -> Option<&str> {
+        self.validator.init_block["root_hash"].as_str()
+    }
+
+    /// Combines liteservers of both configs, skipping duplicated ADNL keys,
+    /// and keeps the validator section with the later init block.
+    ///
+    /// Global configs carry no global_id, so configs with different zero states
+    /// are considered to belong to different networks and are not merged.
+    pub fn merge(mut self, other: TonConfig) -> Result<TonConfig, TonConfigError> {
+        if self.validator.zero_state != other.validator.zero_state {
+            return Err(TonConfigError::NetworkMismatch {
+                this: self.validator.zero_state,
+                other: other.validator.zero_state,
+            });
+        }
+        if other.get_init_block_seqno() > self.get_init_block_seqno() {
+            self.validator = other.validator;
+        }
+        for liteserver in other.liteservers {
+            if !self
+                .liteservers
+                .iter()
+                .any(|l| l.id.key == liteserver.id.key)
+            {
+                self.liteservers.push(liteserver);
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "liteapi")]
+impl TonConfig {
+    /// Probes every liteserver with a single `getMasterchainInfo` request and returns
+    /// a config containing only the ones that answered within `timeout`.
+    pub async fn prune_unresponsive(self, timeout: Duration) -> Result<TonConfig, TonConfigError> {
+        self.retain_responsive(|endpoint| async move {
+            let probe = crate::client::recent_init_block::probe_liteserver(endpoint.clone());
+            match crate::rt::timeout(timeout, probe).await {
+                Ok(Ok(())) => true,
+                Ok(Err(err)) => {
+                    log::warn!(
+                        "Liteserver {}:{} is unresponsive: {}",
+                        endpoint.ip,
+                        endpoint.port,
+                        err
+                    );
+                    false
+                }
+                Err(_) => {
+                    log::warn!(
+                        "Liteserver {}:{} timed out after {:?}",
+                        endpoint.ip,
+                        endpoint.port,
+                        timeout
+                    );
+                    false
+                }
+            }
+        })
+        .await
+    }
+
+    /// Measures `sample_count` round-trips to every liteserver and returns a config with liteservers
+    /// sorted by the median latency, fastest first.
+    ///
+    /// Liteservers which failed to answer are moved to the end, keeping their relative order.
+    pub async fn sort_by_latency(self, sample_count: usize) -> TonConfig {
+        let sample_count = sample_count.max(1);
+        self.sort_by_measured_latency(|endpoint| async move {
+            let samples = crate::client::recent_init_block::measure_liteserver_latency(
+                endpoint.clone(),
+                sample_count,
+            );
+            match samples.await {
+                Ok(samples) => median(samples),
+                Err(err) => {
+                    log::warn!(
+                        "Failed to measure latency of liteserver {}:{}: {}",
+                        endpoint.ip,
+                        endpoint.port,
+                        err
+                    );
+                    None
+                }
+            }
+        })
+        .await
+    }
+
+    async fn sort_by_measured_latency<F, Fut>(mut self, measure: F) -> TonConfig
+    where
+        F: Fn(LiteEndpoint) -> Fut,
+        Fut: Future<Output = Option<Duration>>,
+    {
+        let latencies =
+            futures::future::join_all(self.liteservers.iter().cloned().map(measure)).await;
+        let mut liteservers: Vec<_> = latencies.into_iter().zip(self.liteservers).collect();
+        // stable sort keeps the config order of liteservers with equal latency
+        liteservers.sort_by_key(|(latency, _)| (latency.is_none(), *latency));
+        self.liteservers = liteservers.into_iter().map(|(_, l)| l).collect();
+        self
+    }
+
+    async fn retain_responsive<F, Fut>(mut self, probe: F) -> Result<TonConfig, TonConfigError>
+    where
+        F: Fn(LiteEndpoint) -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let count = self.liteservers.len();
+        let responsive =
+            futures::future::join_all(self.liteservers.iter().cloned().map(probe)).await;
+        let mut responsive = responsive.into_iter();
+        self.liteservers
+            .retain(|_| responsive.next().unwrap_or(false));
+        if self.liteservers.is_empty() {
+            return Err(TonConfigError::NoResponsiveLiteservers { count });
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(feature = "liteapi")]
+fn median(mut samples: Vec<Duration>) -> Option<Duration> {
+    samples.sort();
+    let mid = samples.len() / 2;
+    match samples.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((samples[mid - 1] + samples[mid]) / 2),
+        _ => Some(samples[mid]),
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,3 +253,161 @@ pub(crate) struct Validator {
     pub init_block: Value,
     pub hardforks: Value,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_block() -> anyhow::Result<()> {
+        let mut config = TonConfig::from_json(MAINNET_CONFIG)?;
+        let init_block = config.init_block()?;
+        assert_eq!(init_block.workchain, -1);
+        assert_eq!(init_block.shard, i64::MIN);
+        assert_eq!(init_block.seqno, config.get_init_block_seqno());
+
+        let later = crate::tl::BlockIdExt {
+            seqno: init_block.seqno + 100,
+            ..init_block
+        };
+        config.set_init_block(&later)?;
+        let config = TonConfig::from_json(&config.to_json()?)?;
+        assert_eq!(config.init_block()?, later);
+        assert_eq!(config.get_init_block_seqno(), later.seqno);
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_json_lenient() -> anyhow::Result<()> {
+        let mainnet = TonConfig::from_json(MAINNET_CONFIG)?;
+        let mut value: Value = serde_json::from_str(MAINNET_CONFIG)?;
+        let liteservers = value["liteservers"].as_array_mut().unwrap();
+        liteservers[1]["port"] = "not a port".into();
+        liteservers.push(serde_json::json!({"ip": 1, "prot": 2}));
+        let json = serde_json::to_string(&value)?;
+        assert!(TonConfig::from_json(&json).is_err());
+
+        let (config, warnings) = TonConfig::from_json_lenient(&json)?;
+        assert_eq!(config.liteservers.len(), mainnet.liteservers.len() - 1);
+        assert_eq!(config.liteservers[1].id.key, mainnet.liteservers[2].id.key);
+        let indexes: Vec<_> = warnings
+            .iter()
+            .map(|w| match w {
+                TonConfigWarning::MalformedLiteserver { index, .. } => *index,
+            })
+            .collect();
+        assert_eq!(indexes, vec![1, mainnet.liteservers.len()]);
+
+        let (config, warnings) = TonConfig::from_json_lenient(MAINNET_CONFIG)?;
+        assert_eq!(config.liteservers.len(), mainnet.liteservers.len());
+        assert!(warnings.is_empty());
+        assert!(TonConfig::from_json_lenient("{").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge() -> anyhow::Result<()> {
+        let mainnet = TonConfig::from_json(MAINNET_CONFIG)?;
+        let liteservers = mainnet.liteservers.len();
+        let init_seqno = mainnet.get_init_block_seqno();
+
+        let mut regional = TonConfig::from_json(MAINNET_CONFIG)?;
+        regional.liteservers.truncate(1);
+        let mut custom = regional.liteservers[0].clone();
+        custom.id.key = "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA=".to_string();
+        custom.port = 12345;
+        regional.liteservers.push(custom);
+        let mut init_block = regional.validator.init_block.clone();
+        init_block["seqno"] = (init_seqno + 100).into();
+        regional.validator.init_block = init_block.clone();
+
+        let merged = mainnet.merge(regional)?;
+        assert_eq!(merged.liteservers.len(), liteservers + 1);
+        assert_eq!(merged.liteservers.last().map(|l| l.port), Some(12345));
+        assert_eq!(merged.get_init_block_seqno(), init_seqno + 100);
+        assert_eq!(merged.validator.init_block, init_block);
+
+        // the later init block is kept regardless of the order
+        let merged = TonConfig::from_json(&merged.to_json()?)?
+            .merge(TonConfig::from_json(MAINNET_CONFIG)?)?;
+        assert_eq!(merged.get_init_block_seqno(), init_seqno + 100);
+        assert_eq!(merged.liteservers.len(), liteservers + 1);
+
+        let testnet = TonConfig::from_json(TESTNET_CONFIG)?;
+        let result = TonConfig::from_json(MAINNET_CONFIG)?.merge(testnet);
+        assert!(matches!(
+            result,
+            Err(TonConfigError::NetworkMismatch { .. })
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "liteapi")]
+    #[tokio::test]
+    async fn test_sort_by_measured_latency() -> anyhow::Result<()> {
+        let config = TonConfig::from_json(MAINNET_CONFIG)?;
+        let keys: Vec<String> = config
+            .liteservers
+            .iter()
+            .map(|l| l.id.key.clone())
+            .collect();
+        assert!(keys.len() >= 3);
+        let dead = keys[0].clone();
+        let latency = |key: &str| -> Option<Duration> {
+            if key == dead {
+                return None;
+            }
+            let pos = keys.iter().position(|k| k == key)? as u64;
+            Some(Duration::from_millis(1000 - pos))
+        };
+
+        let sorted = config
+            .sort_by_measured_latency(|endpoint| async move { latency(&endpoint.id.key) })
+            .await;
+        let sorted_keys: Vec<String> = sorted
+            .liteservers
+            .iter()
+            .map(|l| l.id.key.clone())
+            .collect();
+        let mut expected: Vec<String> = keys[1..].iter().rev().cloned().collect();
+        expected.push(dead.clone());
+        assert_eq!(sorted_keys, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "liteapi")]
+    #[test]
+    fn test_median() {
+        let ms = Duration::from_millis;
+        assert_eq!(median(vec![]), None);
+        assert_eq!(median(vec![ms(30), ms(10), ms(20)]), Some(ms(20)));
+        assert_eq!(median(vec![ms(40), ms(10), ms(20), ms(1000)]), Some(ms(30)));
+    }
+
+    #[cfg(feature = "liteapi")]
+    #[tokio::test]
+    async fn test_retain_responsive() -> anyhow::Result<()> {
+        let config = TonConfig::from_json(MAINNET_CONFIG)?;
+        let dead_port = config.liteservers[0].port;
+        let alive = config
+            .liteservers
+            .iter()
+            .filter(|l| l.port != dead_port)
+            .count();
+
+        let pruned = config
+            .retain_responsive(|endpoint| async move { endpoint.port != dead_port })
+            .await?;
+        assert_eq!(pruned.liteservers.len(), alive);
+        assert!(pruned.liteservers.iter().all(|l| l.port != dead_port));
+
+        let config = TonConfig::from_json(MAINNET_CONFIG)?;
+        let count = config.liteservers.len();
+        let result = config.retain_responsive(|_| async { false }).await;
+        assert!(matches!(
+            result,
+            Err(TonConfigError::NoResponsiveLiteservers { count: c }) if c == count
+        ));
+        Ok(())
+    }
+}
@@ -1,12 +1,15 @@
 use std::sync::Arc;
 
+pub use account_events::*;
 use async_trait::async_trait;
 pub use error::*;
 pub use factory::*;
 pub use interface::*;
+pub use introspection::*;
 pub use jetton::*;
 pub use latest_transactions_cache::*;
 pub use nft::*;
+pub use snapshot::*;
 pub use state::*;
 use tonlib_core::TonAddress;
 pub use wallet::*;
@@ -15,12 +18,15 @@ use crate::client::TonClientInterface;
 use crate::tl::{InternalTransactionId, RawFullAccountState};
 use crate::types::{TonMethodId, TvmStackEntry, TvmSuccess};
 
+mod account_events;
 mod error;
 mod factory;
 mod interface;
+mod introspection;
 mod jetton;
 mod latest_transactions_cache;
 mod nft;
+mod snapshot;
 mod state;
 mod wallet;
 
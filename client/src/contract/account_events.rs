@@ -0,0 +1,620 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use moka::future::Cache;
+use num_bigint::BigUint;
+use tonlib_core::cell::BagOfCells;
+use tonlib_core::message::{MessageBodyKind, TEXT_COMMENT};
+use tonlib_core::TonAddress;
+
+use crate::contract::{
+    JettonWalletContract, NftItemContract, TonContractError, TonContractFactory,
+};
+use crate::tl::{MsgData, RawMessage, RawTransaction};
+
+pub const DEFAULT_MAX_LOOKUPS_PER_BLOCK: usize = 32;
+pub const DEFAULT_RESOLUTION_CACHE_CAPACITY: u64 = 10_000;
+const RESOLUTION_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Meaning of a message received or sent by the account
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SemanticEvent {
+    TonTransferIn {
+        from: TonAddress,
+        amount: i64,
+        comment: Option<String>,
+    },
+    TonTransferOut {
+        to: TonAddress,
+        amount: i64,
+        comment: Option<String>,
+    },
+    /// `master` is `None` if the jetton wallet couldn't be resolved
+    JettonTransferIn {
+        master: Option<TonAddress>,
+        amount: BigUint,
+        counterparty_owner: TonAddress,
+    },
+    JettonTransferOut {
+        master: Option<TonAddress>,
+        amount: BigUint,
+        counterparty_owner: TonAddress,
+    },
+    /// `collection` and `index` are `None` if the item couldn't be resolved
+    NftReceived {
+        item: TonAddress,
+        collection: Option<TonAddress>,
+        index: Option<BigUint>,
+        counterparty_owner: TonAddress,
+    },
+    NftSent {
+        item: TonAddress,
+        collection: Option<TonAddress>,
+        index: Option<BigUint>,
+        counterparty_owner: TonAddress,
+    },
+    ContractDeploy,
+    Unknown,
+}
+
+/// Transaction of the account annotated with semantic events:
+/// an optional `ContractDeploy`, the event of the inbound message and the events of outbound messages.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountEvent {
+    pub transaction: RawTransaction,
+    pub events: Vec<SemanticEvent>,
+}
+
+/// Collection and index of NFT item, as returned by `get_nft_data`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NftItemInfo {
+    /// `None` for collection-less items
+    pub collection: Option<TonAddress>,
+    pub index: BigUint,
+}
+
+/// Resolves contracts referenced by semantic events
+#[async_trait]
+pub trait SemanticResolver: Send + Sync {
+    /// Returns the jetton master of `jetton_wallet`
+    async fn jetton_master(
+        &self,
+        jetton_wallet: &TonAddress,
+    ) -> Result<TonAddress, TonContractError>;
+
+    async fn nft_item_info(&self, item: &TonAddress) -> Result<NftItemInfo, TonContractError>;
+}
+
+#[async_trait]
+impl SemanticResolver for TonContractFactory {
+    async fn jetton_master(
+        &self,
+        jetton_wallet: &TonAddress,
+    ) -> Result<TonAddress, TonContractError> {
+        let data = self.get_contract(jetton_wallet).get_wallet_data().await?;
+        Ok(data.master_address)
+    }
+
+    async fn nft_item_info(&self, item: &TonAddress) -> Result<NftItemInfo, TonContractError> {
+        let data = self.get_contract(item).get_nft_data().await?;
+        let collection = if data.collection_address == TonAddress::NULL {
+            None
+        } else {
+            Some(data.collection_address)
+        };
+        Ok(NftItemInfo {
+            collection,
+            index: data.index,
+        })
+    }
+}
+
+/// Annotates account transactions with `SemanticEvent`s.
+///
+/// Jetton masters and NFT item data are resolved via `resolver` and cached.
+/// At most `max_lookups_per_block` uncached resolutions are made per `enrich_block` call,
+/// unresolved fields are left `None`.
+pub struct AccountEventEnricher<R> {
+    resolver: R,
+    max_lookups_per_block: usize,
+    jetton_masters: Cache<TonAddress, TonAddress>,
+    nft_items: Cache<TonAddress, NftItemInfo>,
+}
+
+impl<R: SemanticResolver> AccountEventEnricher<R> {
+    pub fn new(resolver: R) -> AccountEventEnricher<R> {
+        Self::with_params(
+            resolver,
+            DEFAULT_MAX_LOOKUPS_PER_BLOCK,
+            DEFAULT_RESOLUTION_CACHE_CAPACITY,
+        )
+    }
+
+    pub fn with_params(
+        resolver: R,
+        max_lookups_per_block: usize,
+        cache_capacity: u64,
+    ) -> AccountEventEnricher<R> {
+        AccountEventEnricher {
+            resolver,
+            max_lookups_per_block,
+            jetton_masters: Cache::builder()
+                .max_capacity(cache_capacity)
+                .time_to_live(RESOLUTION_CACHE_TTL)
+                .build(),
+            nft_items: Cache::builder()
+                .max_capacity(cache_capacity)
+                .time_to_live(RESOLUTION_CACHE_TTL)
+                .build(),
+        }
+    }
+
+    /// Annotates `transactions` of `account` belonging to a single block
+    pub async fn enrich_block(
+        &self,
+        account: &TonAddress,
+        transactions: Vec<RawTransaction>,
+    ) -> Vec<AccountEvent> {
+        let mut lookups_left = self.max_lookups_per_block;
+        let mut result = Vec::with_capacity(transactions.len());
+        for transaction in transactions {
+            let events = self
+                .transaction_events(account, &transaction, &mut lookups_left)
+                .await;
+            result.push(AccountEvent {
+                transaction,
+                events,
+            });
+        }
+        result
+    }
+
+    async fn transaction_events(
+        &self,
+        account: &TonAddress,
+        tx: &RawTransaction,
+        lookups_left: &mut usize,
+    ) -> Vec<SemanticEvent> {
+        let mut events = vec![];
+        if let Some(in_msg) = &tx.in_msg {
+            if has_init_state(in_msg) {
+                events.push(SemanticEvent::ContractDeploy);
+            }
+            if let Some(event) = self.inbound_event(in_msg, lookups_left).await {
+                events.push(event);
+            }
+        }
+        for out_msg in &tx.out_msgs {
+            events.push(self.outbound_event(out_msg, lookups_left).await);
+        }
+        if events.is_empty() {
+            log::trace!("No semantic events for transaction of {}", account);
+            events.push(SemanticEvent::Unknown);
+        }
+        events
+    }
+
+    /// Returns `None` for external messages
+    async fn inbound_event(
+        &self,
+        msg: &RawMessage,
+        lookups_left: &mut usize,
+    ) -> Option<SemanticEvent> {
+        let source = parse_address(&msg.source.account_address)?;
+        let event = match body_kind(msg) {
+            MessageBodyKind::Empty
+            | MessageBodyKind::Unknown {
+                opcode: TEXT_COMMENT,
+            } => SemanticEvent::TonTransferIn {
+                from: source,
+                amount: msg.value,
+                comment: None,
+            },
+            MessageBodyKind::Comment(comment) => SemanticEvent::TonTransferIn {
+                from: source,
+                amount: msg.value,
+                comment: Some(comment),
+            },
+            MessageBodyKind::JettonTransferNotification(notification) => {
+                SemanticEvent::JettonTransferIn {
+                    master: self.jetton_master(&source, lookups_left).await,
+                    amount: notification.amount,
+                    counterparty_owner: notification.sender,
+                }
+            }
+            MessageBodyKind::NftOwnershipAssigned(assigned) => {
+                let info = self.nft_item_info(&source, lookups_left).await;
+                SemanticEvent::NftReceived {
+                    item: source,
+                    collection: info.clone().and_then(|i| i.collection),
+                    index: info.map(|i| i.index),
+                    counterparty_owner: assigned.prev_owner,
+                }
+            }
+            _ => SemanticEvent::Unknown,
+        };
+        Some(event)
+    }
+
+    async fn outbound_event(&self, msg: &RawMessage, lookups_left: &mut usize) -> SemanticEvent {
+        let destination = match parse_address(&msg.destination.account_address) {
+            Some(destination) => destination,
+            None => return SemanticEvent::Unknown,
+        };
+        match body_kind(msg) {
+            MessageBodyKind::Empty
+            | MessageBodyKind::Unknown {
+                opcode: TEXT_COMMENT,
+            } => SemanticEvent::TonTransferOut {
+                to: destination,
+                amount: msg.value,
+                comment: None,
+            },
+            MessageBodyKind::Comment(comment) => SemanticEvent::TonTransferOut {
+                to: destination,
+                amount: msg.value,
+                comment: Some(comment),
+            },
+            MessageBodyKind::JettonTransfer(transfer) => SemanticEvent::JettonTransferOut {
+                master: self.jetton_master(&destination, lookups_left).await,
+                amount: transfer.amount,
+                counterparty_owner: transfer.destination,
+            },
+            MessageBodyKind::NftTransfer(transfer) => {
+                let info = self.nft_item_info(&destination, lookups_left).await;
+                SemanticEvent::NftSent {
+                    item: destination,
+                    collection: info.clone().and_then(|i| i.collection),
+                    index: info.map(|i| i.index),
+                    counterparty_owner: transfer.new_owner,
+                }
+            }
+            _ => SemanticEvent::Unknown,
+        }
+    }
+
+    async fn jetton_master(
+        &self,
+        jetton_wallet: &TonAddress,
+        lookups_left: &mut usize,
+    ) -> Option<TonAddress> {
+        if let Some(master) = self.jetton_masters.get(jetton_wallet).await {
+            return Some(master);
+        }
+        if *lookups_left == 0 {
+            return None;
+        }
+        *lookups_left -= 1;
+        match self.resolver.jetton_master(jetton_wallet).await {
+            Ok(master) => {
+                self.jetton_masters
+                    .insert(jetton_wallet.clone(), master.clone())
+                    .await;
+                Some(master)
+            }
+            Err(e) => {
+                log::warn!("Failed to resolve jetton wallet {}: {}", jetton_wallet, e);
+                None
+            }
+        }
+    }
+
+    async fn nft_item_info(
+        &self,
+        item: &TonAddress,
+        lookups_left: &mut usize,
+    ) -> Option<NftItemInfo> {
+        if let Some(info) = self.nft_items.get(item).await {
+            return Some(info);
+        }
+        if *lookups_left == 0 {
+            return None;
+        }
+        *lookups_left -= 1;
+        match self.resolver.nft_item_info(item).await {
+            Ok(info) => {
+                self.nft_items.insert(item.clone(), info.clone()).await;
+                Some(info)
+            }
+            Err(e) => {
+                log::warn!("Failed to resolve NFT item {}: {}", item, e);
+                None
+            }
+        }
+    }
+}
+
+fn parse_address(address: &str) -> Option<TonAddress> {
+    if address.is_empty() {
+        None
+    } else {
+        address.parse().ok()
+    }
+}
+
+/// Messages without a parsable body are treated as `Empty`
+fn body_kind(msg: &RawMessage) -> MessageBodyKind {
+    let body = match &msg.msg_data {
+        MsgData::Raw { body, .. } => BagOfCells::parse(body)
+            .and_then(|boc| boc.single_root().map(|root| root.as_ref().clone()))
+            .ok(),
+        _ => None,
+    };
+    body.as_ref()
+        .map(MessageBodyKind::classify)
+        .unwrap_or(MessageBodyKind::Empty)
+}
+
+fn has_init_state(msg: &RawMessage) -> bool {
+    match &msg.msg_data {
+        MsgData::Raw { init_state, .. } => !init_state.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tonlib_core::cell::{Cell, CellBuilder};
+    use tonlib_core::message::{
+        JettonTransferMessage, JettonTransferNotificationMessage, NftOwnershipAssignedMessage,
+        NftTransferMessage, TonMessage, WithForwardPayload,
+    };
+
+    use super::*;
+    use crate::client::mock::{raw_message, raw_transaction};
+
+    const ACCOUNT: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x01; 32],
+    };
+    const PEER: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x02; 32],
+    };
+    const JETTON_WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x03; 32],
+    };
+    const OTHER_JETTON_WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x04; 32],
+    };
+    const JETTON_MASTER: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x05; 32],
+    };
+    const NFT_ITEM: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x06; 32],
+    };
+    const NFT_COLLECTION: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x07; 32],
+    };
+
+    #[derive(Default)]
+    struct MockResolver {
+        jetton_lookups: AtomicUsize,
+        nft_lookups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl SemanticResolver for MockResolver {
+        async fn jetton_master(
+            &self,
+            _jetton_wallet: &TonAddress,
+        ) -> Result<TonAddress, TonContractError> {
+            self.jetton_lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(JETTON_MASTER)
+        }
+
+        async fn nft_item_info(&self, _item: &TonAddress) -> Result<NftItemInfo, TonContractError> {
+            self.nft_lookups.fetch_add(1, Ordering::SeqCst);
+            Ok(NftItemInfo {
+                collection: Some(NFT_COLLECTION),
+                index: BigUint::from(42u32),
+            })
+        }
+    }
+
+    fn message(
+        source: Option<&TonAddress>,
+        destination: &TonAddress,
+        value: i64,
+        body: &Cell,
+        init_state: Vec<u8>,
+    ) -> RawMessage {
+        let mut message = raw_message(source, destination, body);
+        message.value = value;
+        if let MsgData::Raw {
+            init_state: state, ..
+        } = &mut message.msg_data
+        {
+            *state = init_state;
+        }
+        message
+    }
+
+    fn transaction(in_msg: RawMessage, out_msgs: Vec<RawMessage>) -> RawTransaction {
+        RawTransaction {
+            in_msg: Some(in_msg),
+            out_msgs,
+            ..raw_transaction(&ACCOUNT, 0)
+        }
+    }
+
+    fn comment(text: &str) -> anyhow::Result<Cell> {
+        Ok(CellBuilder::new()
+            .store_u32(32, TEXT_COMMENT)?
+            .store_slice(text.as_bytes())?
+            .build()?)
+    }
+
+    fn external(out_msgs: Vec<RawMessage>) -> RawTransaction {
+        let in_msg = message(None, &ACCOUNT, 0, &Cell::default(), vec![]);
+        transaction(in_msg, out_msgs)
+    }
+
+    #[tokio::test]
+    async fn test_enrich_block() -> anyhow::Result<()> {
+        let jetton_notification =
+            JettonTransferNotificationMessage::new(&PEER, &BigUint::from(100u32)).build()?;
+        let jetton_transfer = JettonTransferMessage::new(&PEER, &BigUint::from(50u32)).build()?;
+        let nft_assigned = NftOwnershipAssignedMessage::new(&PEER).build()?;
+        let nft_transfer = NftTransferMessage::new(&PEER)
+            .with_forward_payload(BigUint::from(1u32), Arc::new(comment("nft")?))
+            .build()?;
+        let unknown_op = CellBuilder::new().store_u32(32, 0x12345678)?.build()?;
+        let transactions = vec![
+            transaction(
+                message(Some(&PEER), &ACCOUNT, 1000, &comment("hello")?, vec![]),
+                vec![],
+            ),
+            external(vec![message(
+                Some(&ACCOUNT),
+                &PEER,
+                500,
+                &comment("bye")?,
+                vec![],
+            )]),
+            transaction(
+                message(
+                    Some(&JETTON_WALLET),
+                    &ACCOUNT,
+                    1,
+                    &jetton_notification,
+                    vec![],
+                ),
+                vec![],
+            ),
+            transaction(
+                message(
+                    Some(&JETTON_WALLET),
+                    &ACCOUNT,
+                    1,
+                    &jetton_notification,
+                    vec![],
+                ),
+                vec![],
+            ),
+            external(vec![message(
+                Some(&ACCOUNT),
+                &JETTON_WALLET,
+                1,
+                &jetton_transfer,
+                vec![],
+            )]),
+            transaction(
+                message(Some(&NFT_ITEM), &ACCOUNT, 1, &nft_assigned, vec![]),
+                vec![],
+            ),
+            external(vec![message(
+                Some(&ACCOUNT),
+                &NFT_ITEM,
+                1,
+                &nft_transfer,
+                vec![],
+            )]),
+            transaction(
+                message(None, &ACCOUNT, 0, &Cell::default(), vec![1, 2, 3]),
+                vec![],
+            ),
+            transaction(
+                message(Some(&PEER), &ACCOUNT, 0, &unknown_op, vec![]),
+                vec![],
+            ),
+        ];
+
+        let enricher = AccountEventEnricher::new(MockResolver::default());
+        let events = enricher.enrich_block(&ACCOUNT, transactions).await;
+        let events: Vec<_> = events.into_iter().map(|e| e.events).collect();
+        let jetton_in = SemanticEvent::JettonTransferIn {
+            master: Some(JETTON_MASTER),
+            amount: BigUint::from(100u32),
+            counterparty_owner: PEER,
+        };
+        assert_eq!(
+            events,
+            vec![
+                vec![SemanticEvent::TonTransferIn {
+                    from: PEER,
+                    amount: 1000,
+                    comment: Some("hello".to_string()),
+                }],
+                vec![SemanticEvent::TonTransferOut {
+                    to: PEER,
+                    amount: 500,
+                    comment: Some("bye".to_string()),
+                }],
+                vec![jetton_in.clone()],
+                vec![jetton_in],
+                vec![SemanticEvent::JettonTransferOut {
+                    master: Some(JETTON_MASTER),
+                    amount: BigUint::from(50u32),
+                    counterparty_owner: PEER,
+                }],
+                vec![SemanticEvent::NftReceived {
+                    item: NFT_ITEM,
+                    collection: Some(NFT_COLLECTION),
+                    index: Some(BigUint::from(42u32)),
+                    counterparty_owner: PEER,
+                }],
+                vec![SemanticEvent::NftSent {
+                    item: NFT_ITEM,
+                    collection: Some(NFT_COLLECTION),
+                    index: Some(BigUint::from(42u32)),
+                    counterparty_owner: PEER,
+                }],
+                vec![SemanticEvent::ContractDeploy],
+                vec![SemanticEvent::Unknown],
+            ]
+        );
+        // the second jetton event and the NFT transfer are served from cache
+        assert_eq!(enricher.resolver.jetton_lookups.load(Ordering::SeqCst), 1);
+        assert_eq!(enricher.resolver.nft_lookups.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_lookups_per_block() -> anyhow::Result<()> {
+        let notification =
+            JettonTransferNotificationMessage::new(&PEER, &BigUint::from(100u32)).build()?;
+        let block = || {
+            vec![
+                transaction(
+                    message(Some(&JETTON_WALLET), &ACCOUNT, 1, &notification, vec![]),
+                    vec![],
+                ),
+                transaction(
+                    message(
+                        Some(&OTHER_JETTON_WALLET),
+                        &ACCOUNT,
+                        1,
+                        &notification,
+                        vec![],
+                    ),
+                    vec![],
+                ),
+            ]
+        };
+        let master = |event: &AccountEvent| match &event.events[0] {
+            SemanticEvent::JettonTransferIn { master, .. } => master.clone(),
+            e => panic!("unexpected event {:?}", e),
+        };
+
+        let enricher = AccountEventEnricher::with_params(MockResolver::default(), 1, 100);
+        let events = enricher.enrich_block(&ACCOUNT, block()).await;
+        assert_eq!(master(&events[0]), Some(JETTON_MASTER));
+        assert_eq!(master(&events[1]), None);
+
+        // the budget is reset for the next block, cached wallet doesn't consume it
+        let events = enricher.enrich_block(&ACCOUNT, block()).await;
+        assert_eq!(master(&events[0]), Some(JETTON_MASTER));
+        assert_eq!(master(&events[1]), Some(JETTON_MASTER));
+        assert_eq!(enricher.resolver.jetton_lookups.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
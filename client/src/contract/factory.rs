@@ -5,18 +5,21 @@ use std::time::Duration;
 pub use builder::*;
 #[cfg(feature = "state_cache")]
 pub use cache::*;
+pub use cell_cache::*;
 pub use library_loader::*;
 pub use library_provider::*;
-use tokio::sync::OnceCell;
+use tonlib_core::cell::{ArcCell, BagOfCells, TonCellError};
 use tonlib_core::TonAddress;
 
 use crate::client::{TonClient, TonClientError, TonClientInterface};
 use crate::contract::{LoadedSmcState, TonContract, TonContractError, TonContractState};
+use crate::rt::OnceCell;
 use crate::tl::{ConfigInfo, InternalTransactionId, RawFullAccountState};
 
 mod builder;
 #[cfg(feature = "state_cache")]
 mod cache;
+mod cell_cache;
 mod library_loader;
 mod library_provider;
 
@@ -29,6 +32,7 @@ struct Inner {
     client: TonClient,
     config_info: OnceCell<ConfigInfo>,
     library_provider: LibraryProvider,
+    cell_cache: Option<CellCache>,
     #[cfg(feature = "state_cache")]
     cache: Option<ContractFactoryCache>,
 }
@@ -49,6 +53,7 @@ impl TonContractFactory {
         txid_cache_time_to_live: Duration,
         presync_blocks: i32,
         library_provider: LibraryProvider,
+        cell_cache: Option<CellCache>,
     ) -> Result<TonContractFactory, TonContractError> {
         let cache = if with_cache {
             let cache = ContractFactoryCache::new(
@@ -70,6 +75,7 @@ impl TonContractFactory {
             config_info,
             cache,
             library_provider,
+            cell_cache,
         };
 
         Ok(TonContractFactory {
@@ -80,12 +86,14 @@ impl TonContractFactory {
     pub(crate) async fn new(
         client: &TonClient,
         library_provider: &LibraryProvider,
+        cell_cache: Option<CellCache>,
     ) -> Result<TonContractFactory, TonContractError> {
         let config_info = OnceCell::const_new();
         let inner = Inner {
             client: client.clone(),
             config_info,
             library_provider: library_provider.clone(),
+            cell_cache,
         };
         Ok(TonContractFactory {
             inner: Arc::new(inner),
@@ -109,6 +117,18 @@ impl TonContractFactory {
         self.inner.library_provider.clone()
     }
 
+    pub fn cell_cache(&self) -> Option<&CellCache> {
+        self.inner.cell_cache.as_ref()
+    }
+
+    /// Returns the single root of `boc`, reusing the parsed cell if the factory has a cell cache
+    pub fn parse_cell(&self, boc: &[u8]) -> Result<ArcCell, TonCellError> {
+        match &self.inner.cell_cache {
+            Some(cache) => cache.get_or_parse(boc),
+            None => Ok(BagOfCells::parse(boc)?.single_root()?.clone()),
+        }
+    }
+
     pub fn get_contract(&self, address: &TonAddress) -> TonContract {
         TonContract::new(self, address)
     }
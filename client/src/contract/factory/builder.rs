@@ -2,7 +2,7 @@ use std::sync::Arc;
 #[cfg(feature = "state_cache")]
 use std::time::Duration;
 
-use super::{DefaultLibraryLoader, LibraryProvider};
+use super::{CellCache, DefaultLibraryLoader, LibraryProvider};
 use crate::client::TonClient;
 use crate::contract::{TonContractError, TonContractFactory};
 
@@ -16,6 +16,7 @@ pub struct TonContractFactoryBuilder {
     txid_cache_time_to_live: Duration,
     presync_blocks: i32,
     library_provider: LibraryProvider,
+    cell_cache: Option<CellCache>,
 }
 
 #[cfg(feature = "state_cache")]
@@ -40,6 +41,7 @@ impl TonContractFactoryBuilder {
             txid_cache_time_to_live: Duration::default(),
             presync_blocks: Self::DEFAULT_PRESYNC_BLOCKS,
             library_provider,
+            cell_cache: None,
         }
     }
 
@@ -97,6 +99,7 @@ impl TonContractFactoryBuilder {
             self.txid_cache_time_to_live,
             self.presync_blocks,
             self.library_provider.clone(),
+            self.cell_cache.clone(),
         )
         .await
     }
@@ -106,6 +109,7 @@ impl TonContractFactoryBuilder {
 pub struct TonContractFactoryBuilder {
     client: TonClient,
     library_provider: LibraryProvider,
+    cell_cache: Option<CellCache>,
 }
 
 #[cfg(not(feature = "state_cache"))]
@@ -116,11 +120,17 @@ impl TonContractFactoryBuilder {
         TonContractFactoryBuilder {
             client: client.clone(),
             library_provider,
+            cell_cache: None,
         }
     }
 
     pub async fn build(&self) -> Result<TonContractFactory, TonContractError> {
-        TonContractFactory::new(&self.client, &self.library_provider).await
+        TonContractFactory::new(
+            &self.client,
+            &self.library_provider,
+            self.cell_cache.clone(),
+        )
+        .await
     }
 }
 
@@ -136,4 +146,16 @@ impl TonContractFactoryBuilder {
         self.library_provider = library_provider.clone();
         self
     }
+
+    /// Caches up to `capacity` parsed code cells, see `CellCache`
+    pub fn with_cell_cache(&mut self, capacity: u64) -> &mut Self {
+        self.cell_cache = Some(CellCache::new(capacity));
+        self
+    }
+
+    /// Shares `cell_cache` with other factories, e.g. a process-wide one
+    pub fn with_shared_cell_cache(&mut self, cell_cache: &CellCache) -> &mut Self {
+        self.cell_cache = Some(cell_cache.clone());
+        self
+    }
 }
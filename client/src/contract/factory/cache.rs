@@ -12,6 +12,7 @@ use crate::client::{
     BlockStream, BlockStreamItem, TonBlockFunctions, TonClient, TonClientError, TonClientInterface,
 };
 use crate::contract::{LoadedSmcState, TonContractError};
+use crate::rt;
 use crate::tl::{InternalTransactionId, RawFullAccountState};
 
 type TxIdCache = Cache<TonAddress, Arc<InternalTransactionId>>;
@@ -54,7 +55,7 @@ impl ContractFactoryCache {
         let arc_inner = Arc::new(inner);
         let weak_inner = Arc::downgrade(&arc_inner);
 
-        tokio::task::spawn(async move { Self::run_loop(weak_inner).await });
+        rt::spawn(async move { Self::run_loop(weak_inner).await });
 
         let cache = ContractFactoryCache { inner: arc_inner };
         Ok(cache)
@@ -137,10 +138,10 @@ impl ContractFactoryCache {
         let mut block_stream = loop {
             if let Some(inner) = weak_inner.upgrade() {
                 let client = &inner.client;
-                let masterchain_info_result = client.get_masterchain_info().await;
+                let masterchain_info_result = client.masterchain_info_cached().await;
                 match masterchain_info_result {
-                    Ok((_, info)) => {
-                        let first_block_seqno = info.last.seqno - inner.presync_blocks;
+                    Ok(cached) => {
+                        let first_block_seqno = cached.info.last.seqno - inner.presync_blocks;
                         let block_stream = BlockStream::new(client, first_block_seqno);
                         break block_stream;
                     }
@@ -149,7 +150,7 @@ impl ContractFactoryCache {
                             "[ContractFactoryCache] Could not retrieve current block: {:?}",
                             e
                         );
-                        tokio::time::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
+                        rt::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
                     }
                 }
             } else {
@@ -174,7 +175,7 @@ impl ContractFactoryCache {
                         "[ContractFactoryCache] Could not retrieve next block: {:?}",
                         e
                     );
-                    tokio::time::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
+                    rt::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
                     continue;
                 }
             };
@@ -190,7 +191,7 @@ impl ContractFactoryCache {
                                 block.master_shard.seqno,
                                 e
                             );
-                            tokio::time::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
+                            rt::sleep(Duration::from_millis(DELAY_ON_TON_FAILURE)).await;
                         }
                     }
                 }
@@ -0,0 +1,130 @@
+use moka::policy::EvictionPolicy;
+use moka::sync::Cache;
+use sha2::{Digest, Sha256};
+use tonlib_core::cell::{ArcCell, BagOfCells, TonCellError};
+use tonlib_core::TonHash;
+
+/// Bounded cache of parsed cells, e.g. contract code shared by many accounts.
+///
+/// Cells are keyed by the representation hash of the root cell, so the same cell is shared
+/// regardless of the BoC flags (crc32, index) it was serialized with. Parsing is skipped
+/// for byte-identical BoCs, as tonlib returns for the same code. Least recently used
+/// cells are evicted once `capacity` is exceeded.
+#[derive(Clone)]
+pub struct CellCache {
+    cells: Cache<TonHash, ArcCell>,
+    /// SHA-256 of a BoC to the hash of its root cell
+    bocs: Cache<TonHash, TonHash>,
+}
+
+impl CellCache {
+    pub fn new(capacity: u64) -> CellCache {
+        CellCache {
+            cells: Cache::builder()
+                .max_capacity(capacity)
+                .eviction_policy(EvictionPolicy::lru())
+                .build(),
+            bocs: Cache::builder()
+                .max_capacity(capacity)
+                .eviction_policy(EvictionPolicy::lru())
+                .build(),
+        }
+    }
+
+    /// Returns the single root of `boc`, parsing it only if the same BoC isn't cached
+    pub fn get_or_parse(&self, boc: &[u8]) -> Result<ArcCell, TonCellError> {
+        let boc_hash: TonHash = Sha256::digest(boc).into();
+        if let Some(cell) = self
+            .bocs
+            .get(&boc_hash)
+            .and_then(|hash| self.cells.get(&hash))
+        {
+            return Ok(cell);
+        }
+        let parsed = BagOfCells::parse(boc)?.single_root()?.clone();
+        let hash = parsed.cell_hash();
+        let cell = self.cells.get_with(hash, || parsed);
+        self.bocs.insert(boc_hash, hash);
+        Ok(cell)
+    }
+
+    /// Returns the cached cell with the representation hash `hash`
+    pub fn get(&self, hash: &TonHash) -> Option<ArcCell> {
+        self.cells.get(hash)
+    }
+
+    /// Approximate number of cached cells
+    pub fn entry_count(&self) -> u64 {
+        self.cells.entry_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonlib_core::cell::{Cell, CellBuilder};
+
+    use super::*;
+
+    fn cell(value: u32) -> anyhow::Result<Cell> {
+        Ok(CellBuilder::new().store_u32(32, value)?.build()?)
+    }
+
+    fn boc(value: u32) -> anyhow::Result<Vec<u8>> {
+        Ok(BagOfCells::from_root(cell(value)?).serialize(false)?)
+    }
+
+    #[test]
+    fn test_cell_cache() -> anyhow::Result<()> {
+        let cache = CellCache::new(4);
+        let code = boc(1)?;
+        let first = cache.get_or_parse(&code)?;
+        let second = cache.get_or_parse(&code)?;
+        // the same cell is returned instead of parsing it again
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(first.parser().load_u32(32)?, 1);
+
+        for value in 2..100 {
+            cache.get_or_parse(&boc(value)?)?;
+        }
+        cache.cells.run_pending_tasks();
+        assert!(cache.entry_count() <= 4);
+
+        assert!(cache.get_or_parse(&[0xde, 0xad]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cell_cache_keyed_by_cell_hash() -> anyhow::Result<()> {
+        let cache = CellCache::new(4);
+        let first = cache.get_or_parse(&boc(1)?)?;
+        // the same cell serialized with crc32 is a different BoC
+        let with_crc = BagOfCells::from_root(cell(1)?).serialize(true)?;
+        assert_ne!(with_crc, boc(1)?);
+        let second = cache.get_or_parse(&with_crc)?;
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let hash = cell(1)?.cell_hash();
+        assert!(Arc::ptr_eq(&cache.get(&hash).unwrap(), &first));
+        assert!(cache.get(&cell(2)?.cell_hash()).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_cell_cache_evicts_least_recently_used() -> anyhow::Result<()> {
+        let cache = CellCache::new(2);
+        let key = |value: u32| -> anyhow::Result<TonHash> { Ok(cell(value)?.cell_hash()) };
+        cache.get_or_parse(&boc(1)?)?;
+        cache.get_or_parse(&boc(2)?)?;
+        cache.cells.run_pending_tasks();
+        cache.get_or_parse(&boc(1)?)?;
+        cache.get_or_parse(&boc(3)?)?;
+        cache.cells.run_pending_tasks();
+
+        assert!(cache.cells.contains_key(&key(1)?));
+        assert!(!cache.cells.contains_key(&key(2)?));
+        assert!(cache.cells.contains_key(&key(3)?));
+        Ok(())
+    }
+}
@@ -0,0 +1,338 @@
+use std::collections::{HashMap, HashSet};
+
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+use tonlib_core::cell::dict::predefined_readers::key_reader_u32;
+use tonlib_core::cell::{Cell, CellParser, TonCellError};
+use tonlib_core::wallet::WalletVersion;
+use tonlib_core::TonHash;
+
+use crate::contract::{TonContractError, TonContractInterface, TonContractState};
+use crate::types::{TonMethodId, TvmStackEntry};
+
+/// Confidence of an interface detected by exact match of the code hash.
+pub const KNOWN_CODE_CONFIDENCE: f32 = 1.0;
+/// Confidence of an interface declared by `supported_interfaces` get-method.
+pub const DECLARED_CONFIDENCE: f32 = 0.9;
+/// Confidence of an interface guessed by presence of its get-methods.
+pub const PROBED_CONFIDENCE: f32 = 0.6;
+
+/// Exit code thrown by the method selector when get-method is not found.
+const METHOD_NOT_FOUND_EXIT_CODE: i32 = 11;
+/// Number of bits in keys of the get-method dictionary of FunC contracts
+const METHOD_DICT_KEY_LEN: usize = 19;
+/// `SETCP0; DICTPUSHCONST 19` - standard prefix of FunC contract code
+const METHOD_SELECTOR_PREFIX: [u8; 5] = [0xFF, 0x00, 0xF4, 0xA4, 0x13];
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ContractInterface {
+    /// Contract implements `supported_interfaces` get-method
+    Introspection,
+    /// Standard wallet with known code
+    Wallet(WalletVersion),
+    /// Wallet-like contract exposing `seqno` & `get_public_key`
+    GenericWallet,
+    JettonMaster,
+    JettonWallet,
+    NftItem,
+    NftCollection,
+    /// Interface declared by `supported_interfaces`, but unknown to this crate
+    Other(BigUint),
+}
+
+impl ContractInterface {
+    /// Returns name used to compute interface id, if the interface has one.
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            ContractInterface::Introspection => Some("org.ton.introspection.v0"),
+            ContractInterface::GenericWallet => Some("org.ton.wallet"),
+            ContractInterface::JettonMaster => Some("org.ton.jetton.master"),
+            ContractInterface::JettonWallet => Some("org.ton.jetton.wallet"),
+            ContractInterface::NftItem => Some("org.ton.nft.item"),
+            ContractInterface::NftCollection => Some("org.ton.nft.collection"),
+            ContractInterface::Wallet(_) | ContractInterface::Other(_) => None,
+        }
+    }
+
+    /// Returns id of the interface as returned by `supported_interfaces`:
+    /// the first 128 bits of sha256 of the interface name.
+    pub fn id(&self) -> Option<BigUint> {
+        match self {
+            ContractInterface::Other(id) => Some(id.clone()),
+            _ => self.name().map(interface_id),
+        }
+    }
+
+    pub fn from_id(id: &BigUint) -> ContractInterface {
+        INTERFACES_BY_ID
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| ContractInterface::Other(id.clone()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedInterface {
+    pub interface: ContractInterface,
+    /// Value in range `0.0..=1.0`
+    pub confidence: f32,
+}
+
+lazy_static! {
+    static ref INTERFACES_BY_ID: HashMap<BigUint, ContractInterface> = [
+        ContractInterface::Introspection,
+        ContractInterface::GenericWallet,
+        ContractInterface::JettonMaster,
+        ContractInterface::JettonWallet,
+        ContractInterface::NftItem,
+        ContractInterface::NftCollection,
+    ]
+    .into_iter()
+    .filter_map(|i| i.id().map(|id| (id, i)))
+    .collect();
+    static ref KNOWN_CODES: HashMap<TonHash, ContractInterface> = [
+        WalletVersion::V1R1,
+        WalletVersion::V1R2,
+        WalletVersion::V1R3,
+        WalletVersion::V2R1,
+        WalletVersion::V2R2,
+        WalletVersion::V3R1,
+        WalletVersion::V3R2,
+        WalletVersion::V4R1,
+        WalletVersion::V4R2,
+        WalletVersion::HighloadV1R1,
+        WalletVersion::HighloadV1R2,
+        WalletVersion::HighloadV2,
+        WalletVersion::HighloadV2R1,
+        WalletVersion::HighloadV2R2,
+    ]
+    .into_iter()
+    .filter_map(|v| {
+        let hash = v.code().ok()?.cell_hash();
+        Some((hash, ContractInterface::Wallet(v)))
+    })
+    .collect();
+    /// Get-methods whose presence indicates the interface
+    static ref PROBED_METHODS: Vec<(ContractInterface, Vec<&'static str>)> = vec![
+        (ContractInterface::JettonMaster, vec!["get_jetton_data"]),
+        (ContractInterface::JettonWallet, vec!["get_wallet_data"]),
+        (ContractInterface::NftItem, vec!["get_nft_data"]),
+        (ContractInterface::NftCollection, vec!["get_collection_data"]),
+        (ContractInterface::GenericWallet, vec!["seqno", "get_public_key"]),
+    ];
+}
+
+#[async_trait]
+pub trait IntrospectionContract: TonContractInterface {
+    /// Detects interfaces implemented by the contract.
+    ///
+    /// Known code hashes are matched first. Otherwise interfaces are read from
+    /// `supported_interfaces` get-method and, if it's absent, guessed by presence of
+    /// well-known get-methods in the method dictionary of the code.
+    /// Returns empty vector for unknown or uninitialized contracts.
+    async fn detect_interfaces(&self) -> Result<Vec<DetectedInterface>, TonContractError> {
+        let account_state = self.get_account_state().await?;
+        if account_state.code.is_empty() {
+            return Ok(vec![]);
+        }
+        let code = self
+            .factory()
+            .parse_cell(&account_state.code)
+            .map_err(|error| TonContractError::CellError {
+                method: "detect_interfaces".to_string(),
+                address: self.address().clone(),
+                error,
+            })?;
+        if let Some(interface) = KNOWN_CODES.get(&code.cell_hash()) {
+            return Ok(vec![DetectedInterface {
+                interface: interface.clone(),
+                confidence: KNOWN_CODE_CONFIDENCE,
+            }]);
+        }
+
+        let state = TonContractState::new(self.factory(), self.address(), &account_state);
+        match state
+            .emulate_get_method("supported_interfaces", Vec::new())
+            .await
+        {
+            Ok(result) => {
+                let declared = interfaces_from_stack(&result.stack);
+                if !declared.is_empty() {
+                    return Ok(declared);
+                }
+            }
+            // method is absent or fails
+            Err(TonContractError::TvmRunError { .. }) => {}
+            Err(e) => return Err(e),
+        }
+
+        let method_ids = match code_method_ids(&code) {
+            Some(ids) => ids,
+            None => probe_method_ids(&state).await?,
+        };
+        Ok(interfaces_from_method_ids(&method_ids))
+    }
+}
+
+impl<T> IntrospectionContract for T where T: TonContractInterface {}
+
+fn interface_id(name: &str) -> BigUint {
+    let hash = Sha256::digest(name.as_bytes());
+    BigUint::from_bytes_be(&hash[..16])
+}
+
+fn interfaces_from_stack(stack: &[TvmStackEntry]) -> Vec<DetectedInterface> {
+    stack
+        .iter()
+        .filter_map(|e| e.get_biguint().ok())
+        .map(|id| DetectedInterface {
+            interface: ContractInterface::from_id(&id),
+            confidence: DECLARED_CONFIDENCE,
+        })
+        .collect()
+}
+
+fn interfaces_from_method_ids(method_ids: &HashSet<i32>) -> Vec<DetectedInterface> {
+    PROBED_METHODS
+        .iter()
+        .filter(|(_, methods)| {
+            methods
+                .iter()
+                .all(|m| method_ids.contains(&TonMethodId::from(*m).to_id()))
+        })
+        .map(|(interface, _)| DetectedInterface {
+            interface: interface.clone(),
+            confidence: PROBED_CONFIDENCE,
+        })
+        .collect()
+}
+
+/// Reads ids of get-methods from the method dictionary of the code compiled by FunC.
+///
+/// Returns `None` if the code has a different layout.
+fn code_method_ids(code: &Cell) -> Option<HashSet<i32>> {
+    if code.bit_len() < METHOD_SELECTOR_PREFIX.len() * 8
+        || !code.data().starts_with(&METHOD_SELECTOR_PREFIX)
+    {
+        return None;
+    }
+    let dict_cell = code.reference(0).ok()?;
+    let methods = dict_cell
+        .parser()
+        .load_dict(METHOD_DICT_KEY_LEN, key_reader_u32, val_reader_skip)
+        .ok()?;
+    Some(methods.into_keys().map(|id| id as i32).collect())
+}
+
+/// Checks presence of probed get-methods by running them in the emulator.
+async fn probe_method_ids(state: &TonContractState) -> Result<HashSet<i32>, TonContractError> {
+    let mut method_ids = HashSet::new();
+    let methods: HashSet<&'static str> = PROBED_METHODS
+        .iter()
+        .flat_map(|(_, methods)| methods.iter().copied())
+        .collect();
+    for method in methods {
+        match state.emulate_get_method(method, Vec::new()).await {
+            Ok(_) => {}
+            Err(TonContractError::TvmRunError { exit_code, .. })
+                if exit_code == METHOD_NOT_FOUND_EXIT_CODE =>
+            {
+                continue
+            }
+            // method is present, but fails without proper arguments or state
+            Err(TonContractError::TvmRunError { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        method_ids.insert(TonMethodId::from(method).to_id());
+    }
+    Ok(method_ids)
+}
+
+fn val_reader_skip(_parser: &mut CellParser) -> Result<(), TonCellError> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use tonlib_core::cell::CellBuilder;
+
+    use super::*;
+
+    fn func_code(methods: &[&'static str]) -> anyhow::Result<Cell> {
+        let writer = |builder: &mut CellBuilder, _: ()| {
+            builder.store_u8(8, 0)?;
+            Ok(())
+        };
+        let data: HashMap<BigUint, ()> = methods
+            .iter()
+            .map(|m| (BigUint::from(TonMethodId::from(*m).to_id() as u32), ()))
+            .collect();
+        let dict = CellBuilder::new()
+            .store_dict(METHOD_DICT_KEY_LEN, writer, data)?
+            .build()?;
+        let code = CellBuilder::new()
+            .store_slice(&[0xFF, 0x00, 0xF4, 0xA4, 0x13, 0xF4, 0xBC, 0xF2, 0xC8, 0x0B])?
+            .store_child(dict)?
+            .build()?;
+        Ok(code)
+    }
+
+    #[test]
+    fn test_interfaces_from_stack() {
+        let stack = vec![
+            TvmStackEntry::Int257(ContractInterface::Introspection.id().unwrap().into()),
+            TvmStackEntry::Int257(ContractInterface::NftItem.id().unwrap().into()),
+            TvmStackEntry::Int64(42),
+        ];
+        let interfaces = interfaces_from_stack(&stack);
+        let expected = [
+            ContractInterface::Introspection,
+            ContractInterface::NftItem,
+            ContractInterface::Other(BigUint::from(42u32)),
+        ];
+        assert_eq!(interfaces.len(), expected.len());
+        for (detected, expected) in interfaces.iter().zip(expected) {
+            assert_eq!(detected.interface, expected);
+            assert_eq!(detected.confidence, DECLARED_CONFIDENCE);
+        }
+    }
+
+    #[test]
+    fn test_jetton_wallet_detected_by_methods() -> anyhow::Result<()> {
+        let code = func_code(&["get_wallet_data", "seqno", "recv_internal_stub"])?;
+        let method_ids = code_method_ids(&code).unwrap();
+        assert_eq!(method_ids.len(), 3);
+        let interfaces = interfaces_from_method_ids(&method_ids);
+        assert_eq!(
+            interfaces,
+            vec![DetectedInterface {
+                interface: ContractInterface::JettonWallet,
+                confidence: PROBED_CONFIDENCE,
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_contract() -> anyhow::Result<()> {
+        let code = func_code(&["get_balance"])?;
+        let method_ids = code_method_ids(&code).unwrap();
+        assert!(interfaces_from_method_ids(&method_ids).is_empty());
+
+        let non_func_code = CellBuilder::new().store_u32(32, 0xDEADBEEF)?.build()?;
+        assert_eq!(code_method_ids(&non_func_code), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_known_wallet_code() -> anyhow::Result<()> {
+        let hash = WalletVersion::V4R2.code()?.cell_hash();
+        assert_eq!(
+            KNOWN_CODES.get(&hash),
+            Some(&ContractInterface::Wallet(WalletVersion::V4R2))
+        );
+        Ok(())
+    }
+}
@@ -1,3 +1,4 @@
+mod discovery;
 mod master_contract;
 mod wallet_contract;
 
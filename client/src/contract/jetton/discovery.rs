@@ -0,0 +1,263 @@
+use std::collections::HashSet;
+use std::future::Future;
+
+use num_bigint::BigUint;
+use tonlib_core::cell::BagOfCells;
+use tonlib_core::message::{JETTON_TRANSFER, JETTON_TRANSFER_NOTIFICATION};
+use tonlib_core::TonAddress;
+
+use crate::client::TonClientInterface;
+use crate::contract::{
+    JettonMasterContract, JettonWalletContract, TonContractError, TonContractFactory, WalletData,
+};
+use crate::tl::{MsgData, RawMessage, RawTransaction};
+
+/// Transactions requested per page while scanning the history
+const DISCOVERY_PAGE_SIZE: usize = 16;
+
+impl TonContractFactory {
+    /// Discovers jettons held by `owner` without an indexer, returns their jetton masters
+    /// in the order of discovery.
+    ///
+    /// The whole transaction history of `owner` is scanned for jetton transfer notifications
+    /// and outgoing jetton transfers, which reveal jetton wallets of the owner. Each wallet is
+    /// then queried, and its master is returned if the wallet belongs to `owner`, is confirmed
+    /// by the master and holds a non-zero balance.
+    ///
+    /// This is heavy and best-effort:
+    /// * the history beyond the last few days is only served by archive liteservers,
+    ///   so the client should be connected with `ConnectionCheck::Archive`;
+    /// * jettons received with zero `forward_ton_amount` produce no notification and are missed
+    ///   unless the owner has sent them since;
+    /// * wallets which fail to respond to `get_wallet_data`, or whose master doesn't confirm
+    ///   them as wallets of `owner` by `get_wallet_address`, are skipped with a warning.
+    pub async fn discover_jettons(
+        &self,
+        owner: &TonAddress,
+    ) -> Result<Vec<TonAddress>, TonContractError> {
+        let client = self.client();
+        let mut next = client
+            .get_raw_account_state(owner)
+            .await?
+            .last_transaction_id;
+        let mut wallets = vec![];
+        while next.lt != 0 {
+            let page = client
+                .get_raw_transactions_v2(owner, &next, DISCOVERY_PAGE_SIZE, false)
+                .await?;
+            if page.transactions.is_empty() {
+                break;
+            }
+            for wallet in jetton_wallet_candidates(&page.transactions) {
+                if !wallets.contains(&wallet) {
+                    wallets.push(wallet);
+                }
+            }
+            next = page.previous_transaction_id;
+        }
+        log::debug!(
+            "Found {} jetton wallet candidates of {}",
+            wallets.len(),
+            owner
+        );
+
+        let wallet_data =
+            |wallet: TonAddress| async move { self.get_contract(&wallet).get_wallet_data().await };
+        let wallet_address = |master: TonAddress| async move {
+            self.get_contract(&master).get_wallet_address(owner).await
+        };
+        Ok(verified_masters(owner, wallets, wallet_data, wallet_address).await)
+    }
+}
+
+/// Returns masters of `wallets` which belong to `owner` and hold a non-zero balance.
+///
+/// A wallet is accepted only if its master derives the same wallet address for `owner`,
+/// since any contract can report an arbitrary owner and master in `get_wallet_data`.
+async fn verified_masters<D, DF, A, AF>(
+    owner: &TonAddress,
+    wallets: Vec<TonAddress>,
+    wallet_data: D,
+    wallet_address: A,
+) -> Vec<TonAddress>
+where
+    D: Fn(TonAddress) -> DF,
+    DF: Future<Output = Result<WalletData, TonContractError>>,
+    A: Fn(TonAddress) -> AF,
+    AF: Future<Output = Result<TonAddress, TonContractError>>,
+{
+    let mut masters = vec![];
+    for wallet in wallets {
+        let data = match wallet_data(wallet.clone()).await {
+            Ok(data) => data,
+            Err(e) => {
+                log::warn!("Failed to get data of jetton wallet {}: {}", wallet, e);
+                continue;
+            }
+        };
+        if &data.owner_address != owner
+            || data.balance == BigUint::from(0u32)
+            || masters.contains(&data.master_address)
+        {
+            continue;
+        }
+        match wallet_address(data.master_address.clone()).await {
+            Ok(address) if address == wallet => masters.push(data.master_address),
+            Ok(address) => log::warn!(
+                "Jetton wallet {} is not the wallet of {} at {} ({})",
+                wallet,
+                owner,
+                data.master_address,
+                address
+            ),
+            Err(e) => log::warn!(
+                "Failed to get wallet address at jetton master {}: {}",
+                data.master_address,
+                e
+            ),
+        }
+    }
+    masters
+}
+
+/// Returns senders of jetton transfer notifications and destinations of jetton transfers,
+/// which are jetton wallets of the account unless forged
+pub(crate) fn jetton_wallet_candidates(transactions: &[RawTransaction]) -> Vec<TonAddress> {
+    let mut seen = HashSet::new();
+    let mut candidates = vec![];
+    for tx in transactions {
+        let inbound = tx
+            .in_msg
+            .iter()
+            .filter(|msg| message_op(msg) == Some(JETTON_TRANSFER_NOTIFICATION))
+            .map(|msg| &msg.source);
+        let outbound = tx
+            .out_msgs
+            .iter()
+            .filter(|msg| message_op(msg) == Some(JETTON_TRANSFER))
+            .map(|msg| &msg.destination);
+        for address in inbound.chain(outbound) {
+            if let Ok(address) = address.account_address.parse::<TonAddress>() {
+                if seen.insert(address.clone()) {
+                    candidates.push(address);
+                }
+            }
+        }
+    }
+    candidates
+}
+
+fn message_op(msg: &RawMessage) -> Option<u32> {
+    let body = match &msg.msg_data {
+        MsgData::Raw { body, .. } => body,
+        _ => return None,
+    };
+    let boc = BagOfCells::parse(body).ok()?;
+    let root = boc.single_root().ok()?;
+    root.parser().load_u32(32).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonlib_core::cell::{Cell, CellBuilder};
+    use tonlib_core::message::{
+        JettonTransferMessage, JettonTransferNotificationMessage, TonMessage,
+    };
+
+    use super::*;
+    use crate::client::mock::{raw_message, raw_transaction};
+
+    const OWNER: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x01; 32],
+    };
+    const PEER: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x02; 32],
+    };
+    const JETTON_WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x03; 32],
+    };
+    const OTHER_JETTON_WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x04; 32],
+    };
+
+    fn message(source: Option<&TonAddress>, destination: &TonAddress, body: &Cell) -> RawMessage {
+        RawMessage {
+            value: 1,
+            ..raw_message(source, destination, body)
+        }
+    }
+
+    fn transaction(in_msg: RawMessage, out_msgs: Vec<RawMessage>) -> RawTransaction {
+        RawTransaction {
+            in_msg: Some(in_msg),
+            out_msgs,
+            ..raw_transaction(&OWNER, 0)
+        }
+    }
+
+    #[test]
+    fn test_jetton_wallet_candidates() -> anyhow::Result<()> {
+        let notification =
+            JettonTransferNotificationMessage::new(&PEER, &BigUint::from(100u32)).build()?;
+        let transfer = JettonTransferMessage::new(&PEER, &BigUint::from(50u32)).build()?;
+        let comment = CellBuilder::new().store_u32(32, 0)?.build()?;
+        let external = message(None, &OWNER, &Cell::default());
+        let transactions = vec![
+            transaction(message(Some(&JETTON_WALLET), &OWNER, &notification), vec![]),
+            transaction(message(Some(&PEER), &OWNER, &comment), vec![]),
+            transaction(
+                external.clone(),
+                vec![
+                    message(Some(&OWNER), &OTHER_JETTON_WALLET, &transfer),
+                    message(Some(&OWNER), &PEER, &comment),
+                ],
+            ),
+            transaction(
+                external,
+                vec![message(Some(&OWNER), &JETTON_WALLET, &transfer)],
+            ),
+            // a transfer sent to the owner isn't a notification of its wallet
+            transaction(message(Some(&PEER), &OWNER, &transfer), vec![]),
+        ];
+        assert_eq!(
+            jetton_wallet_candidates(&transactions),
+            vec![JETTON_WALLET, OTHER_JETTON_WALLET]
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_spoofed_wallet_is_skipped() {
+        const MASTER: TonAddress = TonAddress {
+            workchain: 0,
+            hash_part: [0x05; 32],
+        };
+        // a forged contract reporting the owner and the master of a real jetton wallet
+        let wallet_data = |_wallet: TonAddress| async {
+            Ok(WalletData {
+                balance: BigUint::from(100u32),
+                owner_address: OWNER,
+                master_address: MASTER,
+                wallet_code: Arc::new(Cell::default()),
+            })
+        };
+        let wallet_address = |master: TonAddress| async move {
+            assert_eq!(master, MASTER);
+            Ok(JETTON_WALLET)
+        };
+
+        let wallets = vec![OTHER_JETTON_WALLET];
+        let masters = verified_masters(&OWNER, wallets, wallet_data, wallet_address).await;
+        assert!(masters.is_empty());
+
+        let wallets = vec![OTHER_JETTON_WALLET, JETTON_WALLET];
+        let masters = verified_masters(&OWNER, wallets, wallet_data, wallet_address).await;
+        assert_eq!(masters, vec![MASTER]);
+    }
+}
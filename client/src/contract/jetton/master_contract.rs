@@ -6,7 +6,7 @@ use tonlib_core::TonAddress;
 
 use crate::contract::{MapCellError, MapStackError, TonContractError, TonContractInterface};
 use crate::meta::MetaDataContent;
-use crate::types::TvmStackEntry;
+use crate::types::{TvmStackEntry, TvmStackParser};
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub struct JettonData {
@@ -27,35 +27,26 @@ enum JettonMasterMethods {
 #[async_trait]
 pub trait JettonMasterContract: TonContractInterface {
     async fn get_jetton_data(&self) -> Result<JettonData, TonContractError> {
-        const JETTON_DATA_STACK_ELEMENTS: usize = 5;
         let method = JettonMasterMethods::GetJettonData.into();
         let address = self.address().clone();
 
         let res = self.run_get_method(method, Vec::new()).await?;
 
-        let stack = res.stack;
-        if stack.len() == JETTON_DATA_STACK_ELEMENTS {
-            let total_supply = stack[0].get_biguint().map_stack_error(method, &address)?;
-            let mintable = stack[1].get_bool().map_stack_error(method, &address)?;
-            let admin_address = stack[2].get_address().map_stack_error(method, &address)?;
-            let cell = stack[3].get_cell().map_stack_error(method, &address)?;
-            let content = read_jetton_metadata_content(cell).map_cell_error(method, &address)?;
-            let wallet_code = stack[4].get_cell().map_stack_error(method, &address)?;
-            Ok(JettonData {
-                total_supply,
-                mintable,
-                admin_address,
-                content,
-                wallet_code,
-            })
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: JETTON_DATA_STACK_ELEMENTS,
-            })
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let total_supply = stack.pop_biguint().map_stack_error(method, &address)?;
+        let mintable = stack.pop_bool().map_stack_error(method, &address)?;
+        let admin_address = stack.pop_address().map_stack_error(method, &address)?;
+        let cell = stack.pop_cell().map_stack_error(method, &address)?;
+        let content = read_jetton_metadata_content(cell).map_cell_error(method, &address)?;
+        let wallet_code = stack.pop_cell().map_stack_error(method, &address)?;
+        stack.ensure_empty().map_stack_error(method, &address)?;
+        Ok(JettonData {
+            total_supply,
+            mintable,
+            admin_address,
+            content,
+            wallet_code,
+        })
     }
 
     async fn get_wallet_address(
@@ -72,17 +63,10 @@ pub trait JettonMasterContract: TonContractInterface {
         let cell_slice = CellSlice::full_cell(cell).map_cell_error(method, owner_address)?;
         let slice = TvmStackEntry::Slice(cell_slice);
         let res = self.run_get_method(method, vec![slice]).await?;
-        let stack = res.stack;
-        if stack.len() == 1 {
-            stack[0].get_address().map_stack_error(method, &address)
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: 1,
-            })
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let wallet_address = stack.pop_address().map_stack_error(method, &address)?;
+        stack.ensure_empty().map_stack_error(method, &address)?;
+        Ok(wallet_address)
     }
 }
 
@@ -5,6 +5,7 @@ use tonlib_core::cell::ArcCell;
 use tonlib_core::TonAddress;
 
 use crate::contract::{MapStackError, TonContractError, TonContractInterface};
+use crate::types::TvmStackParser;
 
 #[derive(Debug, Clone)]
 pub struct WalletData {
@@ -23,32 +24,23 @@ enum JettonWalletMethods {
 #[async_trait]
 pub trait JettonWalletContract: TonContractInterface {
     async fn get_wallet_data(&self) -> Result<WalletData, TonContractError> {
-        const WALLET_DATA_STACK_ELEMENTS: usize = 4;
         let method = JettonWalletMethods::GetWalletData.into();
         let address = self.address().clone();
 
         let res = self.run_get_method(method, Vec::new()).await?;
 
-        let stack = res.stack;
-        if stack.len() == WALLET_DATA_STACK_ELEMENTS {
-            let balance = stack[0].get_biguint().map_stack_error(method, &address)?;
-            let owner_address = stack[1].get_address().map_stack_error(method, &address)?;
-            let master_address = stack[2].get_address().map_stack_error(method, &address)?;
-            let wallet_code = stack[3].get_cell().map_stack_error(method, &address)?;
-            Ok(WalletData {
-                balance,
-                owner_address,
-                master_address,
-                wallet_code,
-            })
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: WALLET_DATA_STACK_ELEMENTS,
-            })
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let balance = stack.pop_biguint().map_stack_error(method, &address)?;
+        let owner_address = stack.pop_address().map_stack_error(method, &address)?;
+        let master_address = stack.pop_address().map_stack_error(method, &address)?;
+        let wallet_code = stack.pop_cell().map_stack_error(method, &address)?;
+        stack.ensure_empty().map_stack_error(method, &address)?;
+        Ok(WalletData {
+            balance,
+            owner_address,
+            master_address,
+            wallet_code,
+        })
     }
 }
 
@@ -3,11 +3,11 @@ use std::ops::Sub;
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
-use tokio::sync::Mutex;
 use tonlib_core::TonAddress;
 
 use crate::client::TonClientError;
 use crate::contract::{TonClientInterface, TonContractError, TonContractFactory};
+use crate::rt::Mutex;
 use crate::tl::{InternalTransactionId, RawTransaction, NULL_TRANSACTION_ID};
 
 pub struct LatestContractTransactionsCache {
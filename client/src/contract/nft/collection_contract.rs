@@ -10,7 +10,7 @@ use crate::contract::{
     MapCellError, MapStackError, NftItemContract, TonContractError, TonContractInterface,
 };
 use crate::meta::MetaDataContent;
-use crate::types::TvmStackEntry;
+use crate::types::{TvmStackEntry, TvmStackParser};
 
 /// Data returned by get_collection_data according to TEP-62
 #[derive(Debug, Clone)]
@@ -37,31 +37,23 @@ enum NftCollectionMethods {
 pub trait NftCollectionContract: TonContractInterface {
     /// Returns nft collection data.
     async fn get_collection_data(&self) -> Result<NftCollectionData, TonContractError> {
-        const NFT_COLLECTION_STACK_ELEMENTS: usize = 3;
         let method = NftCollectionMethods::GetCollectionData.into();
         let address = self.address().clone();
 
-        let stack = self.run_get_method(method, Vec::new()).await?.stack;
-        if stack.len() == NFT_COLLECTION_STACK_ELEMENTS {
-            let next_item_index = stack[0].get_i64().map_stack_error(method, &address)?;
-            let cell = stack[1].get_cell().map_stack_error(method, &address)?;
-            let collection_content =
-                read_collection_metadata_content(self.factory(), &address, cell).await?;
-            let owner_address = stack[2].get_address().map_stack_error(method, &address)?;
+        let res = self.run_get_method(method, Vec::new()).await?;
+        let mut stack = TvmStackParser::new(&res.stack);
+        let next_item_index = stack.pop_i64().map_stack_error(method, &address)?;
+        let cell = stack.pop_cell().map_stack_error(method, &address)?;
+        let owner_address = stack.pop_address().map_stack_error(method, &address)?;
+        stack.ensure_empty().map_stack_error(method, &address)?;
+        let collection_content =
+            read_collection_metadata_content(self.factory(), &address, cell).await?;
 
-            Ok(NftCollectionData {
-                next_item_index,
-                collection_content,
-                owner_address,
-            })
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: NFT_COLLECTION_STACK_ELEMENTS,
-            })
-        }
+        Ok(NftCollectionData {
+            next_item_index,
+            collection_content,
+            owner_address,
+        })
     }
 
     /// Gets the serial number of the NFT item of this collection and
@@ -69,20 +61,16 @@ pub trait NftCollectionContract: TonContractInterface {
     async fn get_nft_address_by_index(&self, index: i64) -> Result<TonAddress, TonContractError> {
         let method = NftCollectionMethods::GetNftAddressByIndex.into();
         let input_stack = vec![TvmStackEntry::Int64(index)];
-        let stack = self.run_get_method(method, &input_stack).await?.stack;
+        let res = self.run_get_method(method, &input_stack).await?;
 
-        if stack.len() == 1 {
-            stack[0]
-                .get_address()
-                .map_stack_error(method, self.address())
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: 1,
-            })
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let nft_address = stack
+            .pop_address()
+            .map_stack_error(method, self.address())?;
+        stack
+            .ensure_empty()
+            .map_stack_error(method, self.address())?;
+        Ok(nft_address)
     }
 }
 
@@ -9,7 +9,7 @@ use crate::contract::{
     factory, MapCellError, MapStackError, TonContractError, TonContractInterface,
 };
 use crate::meta::MetaDataContent;
-use crate::types::TvmStackEntry;
+use crate::types::{TvmStackEntry, TvmStackParser};
 
 /// Data returned by get_static_data according to TEP-62
 #[derive(Debug, Clone)]
@@ -40,41 +40,33 @@ enum NftItemContractMethods {
 pub trait NftItemContract: TonContractInterface {
     async fn get_nft_data(&self) -> Result<NftItemData, TonContractError> {
         let method = NftItemContractMethods::GetNftData.into();
-        const NFT_DATA_STACK_ELEMENTS: usize = 5;
         let address = self.address().clone();
 
-        let stack = self.run_get_method(method, Vec::new()).await?.stack;
-        if stack.len() == NFT_DATA_STACK_ELEMENTS {
-            let init = stack[0].get_bool().map_stack_error(method, &address)?;
-            let index = stack[1].get_biguint().map_stack_error(method, &address)?;
-            let collection_address = stack[2].get_address().map_stack_error(method, &address)?;
-            let owner_address = stack[3].get_address().map_stack_error(method, &address)?;
-            let cell = stack[4].get_cell().map_stack_error(method, &address)?;
+        let res = self.run_get_method(method, Vec::new()).await?;
+        let mut stack = TvmStackParser::new(&res.stack);
+        let init = stack.pop_bool().map_stack_error(method, &address)?;
+        let index = stack.pop_biguint().map_stack_error(method, &address)?;
+        let collection_address = stack.pop_address().map_stack_error(method, &address)?;
+        let owner_address = stack.pop_address().map_stack_error(method, &address)?;
+        let cell = stack.pop_cell().map_stack_error(method, &address)?;
+        stack.ensure_empty().map_stack_error(method, &address)?;
 
-            let individual_content = read_item_metadata_content(
-                self.factory(),
-                &index.clone(),
-                &collection_address.clone(),
-                &address,
-                cell,
-            )
-            .await?;
+        let individual_content = read_item_metadata_content(
+            self.factory(),
+            &index.clone(),
+            &collection_address.clone(),
+            &address,
+            cell,
+        )
+        .await?;
 
-            Ok(NftItemData {
-                init,
-                index,
-                collection_address,
-                owner_address,
-                individual_content,
-            })
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: NFT_DATA_STACK_ELEMENTS,
-            })
-        }
+        Ok(NftItemData {
+            init,
+            index,
+            collection_address,
+            owner_address,
+            individual_content,
+        })
     }
 
     /// Gets the serial number of the NFT item of this collection and
@@ -97,23 +89,16 @@ pub trait NftItemContract: TonContractInterface {
             TvmStackEntry::Int257(index),
             TvmStackEntry::Cell(cell.clone()),
         ];
-        let stack = self.run_get_method(method, &input_stack).await?.stack;
+        let res = self.run_get_method(method, &input_stack).await?;
 
-        if stack.len() == 1 {
-            let cell = stack[0]
-                .get_cell()
-                .map_stack_error(method, self.address())?;
-            let boc = BagOfCells::from_root(cell.as_ref().clone());
-            log::trace!("Got Boc: {:?}", boc);
-            Ok(boc)
-        } else {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: 1,
-            })?
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let cell = stack.pop_cell().map_stack_error(method, self.address())?;
+        stack
+            .ensure_empty()
+            .map_stack_error(method, self.address())?;
+        let boc = BagOfCells::from_root(cell.as_ref().clone());
+        log::trace!("Got Boc: {:?}", boc);
+        Ok(boc)
     }
 }
 
@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::sync::Arc;
+
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::Compression;
+use thiserror::Error;
+use tonlib_core::TonAddress;
+
+use crate::contract::{TonContractFactory, TonContractState};
+use crate::emulator::{TvmEmulator, TvmEmulatorC7Builder, TvmEmulatorError};
+use crate::tl::{BlockIdExt, InternalTransactionId, RawFullAccountState};
+use crate::types::{TonMethodId, TvmStackEntry, TvmSuccess};
+
+const SNAPSHOT_MAGIC: [u8; 4] = *b"TCS1";
+const ARCHIVE_MAGIC: [u8; 4] = *b"TCA1";
+/// Limit of a single length-prefixed field, protects from allocating memory for corrupted lengths
+const MAX_FIELD_LEN: usize = 1 << 30;
+
+#[derive(Error, Debug)]
+pub enum StateSnapshotError {
+    #[error("IO error ({0})")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid snapshot format ({0})")]
+    InvalidFormat(String),
+}
+
+/// Account state of a contract detached from `TonContractFactory`.
+///
+/// Snapshots can be saved to a compact binary envelope, loaded later and used to run
+/// get-methods in the emulator without a client. Libraries aren't part of the snapshot,
+/// so contracts relying on them can only be emulated with a client.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TonContractSnapshot {
+    pub address: TonAddress,
+    pub account_state: Arc<RawFullAccountState>,
+}
+
+impl TonContractSnapshot {
+    pub fn new(address: &TonAddress, account_state: &Arc<RawFullAccountState>) -> Self {
+        TonContractSnapshot {
+            address: address.clone(),
+            account_state: account_state.clone(),
+        }
+    }
+
+    /// Writes the envelope: address, block id, balance, sync time, last transaction id,
+    /// code and data BoCs and frozen hash. Integers are big-endian, byte fields are
+    /// prefixed with their `u32` length.
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<(), StateSnapshotError> {
+        let state = &self.account_state;
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        writer.write_all(&self.address.workchain.to_be_bytes())?;
+        writer.write_all(&self.address.hash_part)?;
+        writer.write_all(&state.block_id.workchain.to_be_bytes())?;
+        writer.write_all(&state.block_id.shard.to_be_bytes())?;
+        writer.write_all(&state.block_id.seqno.to_be_bytes())?;
+        write_bytes(writer, &state.block_id.root_hash)?;
+        write_bytes(writer, &state.block_id.file_hash)?;
+        writer.write_all(&state.balance.to_be_bytes())?;
+        writer.write_all(&state.sync_utime.to_be_bytes())?;
+        writer.write_all(&state.last_transaction_id.lt.to_be_bytes())?;
+        write_bytes(writer, &state.last_transaction_id.hash)?;
+        write_bytes(writer, &state.code)?;
+        write_bytes(writer, &state.data)?;
+        write_bytes(writer, &state.frozen_hash)?;
+        Ok(())
+    }
+
+    pub fn load<R: Read>(reader: &mut R) -> Result<Self, StateSnapshotError> {
+        let magic: [u8; 4] = read_array(reader)?;
+        if magic != SNAPSHOT_MAGIC {
+            return Err(StateSnapshotError::InvalidFormat(format!(
+                "Unexpected snapshot magic: {}",
+                hex::encode(magic)
+            )));
+        }
+        let workchain = i32::from_be_bytes(read_array(reader)?);
+        let hash_part = read_array(reader)?;
+        let block_id = BlockIdExt {
+            workchain: i32::from_be_bytes(read_array(reader)?),
+            shard: i64::from_be_bytes(read_array(reader)?),
+            seqno: i32::from_be_bytes(read_array(reader)?),
+            root_hash: read_bytes(reader)?,
+            file_hash: read_bytes(reader)?,
+        };
+        let balance = i64::from_be_bytes(read_array(reader)?);
+        let sync_utime = i64::from_be_bytes(read_array(reader)?);
+        let last_transaction_id = InternalTransactionId {
+            lt: i64::from_be_bytes(read_array(reader)?),
+            hash: read_bytes(reader)?,
+        };
+        let account_state = RawFullAccountState {
+            balance,
+            code: read_bytes(reader)?,
+            data: read_bytes(reader)?,
+            last_transaction_id,
+            block_id,
+            frozen_hash: read_bytes(reader)?,
+            sync_utime,
+        };
+        Ok(TonContractSnapshot {
+            address: TonAddress::new(workchain, &hash_part),
+            account_state: Arc::new(account_state),
+        })
+    }
+
+    /// Attaches the snapshot to `factory`, so it can be used as a regular contract state.
+    pub fn into_state(self, factory: &TonContractFactory) -> TonContractState {
+        TonContractState::new(factory, &self.address, &self.account_state)
+    }
+
+    /// Runs the get-method in the emulator without c7.
+    ///
+    /// Suitable for methods reading only the contract data. Methods accessing the address,
+    /// balance or config should be run with `run_get_method_with_config`.
+    pub fn run_get_method<M: Into<TonMethodId>>(
+        &self,
+        method: M,
+        stack: &[TvmStackEntry],
+    ) -> Result<TvmSuccess, TvmEmulatorError> {
+        let mut emulator = self.emulator()?;
+        emulator.run_get_method(&method.into(), stack)
+    }
+
+    /// Runs the get-method in the emulator with c7 derived from the snapshot and `config`,
+    /// see `TvmEmulatorC7Builder::from_account_state`.
+    pub fn run_get_method_with_config<M: Into<TonMethodId>>(
+        &self,
+        method: M,
+        stack: &[TvmStackEntry],
+        config: &[u8],
+    ) -> Result<TvmSuccess, TvmEmulatorError> {
+        let c7 =
+            TvmEmulatorC7Builder::from_account_state(&self.address, config, &self.account_state)
+                .build();
+        let mut emulator = self.emulator()?;
+        emulator.set_c7(&c7)?;
+        emulator.run_get_method(&method.into(), stack)
+    }
+
+    fn emulator(&self) -> Result<TvmEmulator, TvmEmulatorError> {
+        TvmEmulator::new(&self.account_state.code, &self.account_state.data)
+    }
+}
+
+/// Writes many snapshots into a single file readable with `StateArchive`.
+///
+/// Every entry is a snapshot envelope compressed with deflate separately, so entries
+/// can be read without decompressing the whole archive. The entries are followed by
+/// the index (number of entries, then address, offset and length of each entry),
+/// and the last 8 bytes are the offset of the index.
+pub struct StateArchiveWriter<W: Write> {
+    writer: W,
+    position: u64,
+    index: Vec<(TonAddress, u64, u64)>,
+}
+
+impl<W: Write> StateArchiveWriter<W> {
+    pub fn new(mut writer: W) -> Result<Self, StateSnapshotError> {
+        writer.write_all(&ARCHIVE_MAGIC)?;
+        Ok(StateArchiveWriter {
+            writer,
+            position: ARCHIVE_MAGIC.len() as u64,
+            index: vec![],
+        })
+    }
+
+    /// Appends the snapshot. If an address is added several times, the archive returns the last snapshot.
+    pub fn add(&mut self, snapshot: &TonContractSnapshot) -> Result<(), StateSnapshotError> {
+        let mut encoder = DeflateEncoder::new(vec![], Compression::default());
+        snapshot.save(&mut encoder)?;
+        let entry = encoder.finish()?;
+        self.writer.write_all(&entry)?;
+        let len = entry.len() as u64;
+        self.index
+            .push((snapshot.address.clone(), self.position, len));
+        self.position += len;
+        Ok(())
+    }
+
+    /// Writes the index and returns the underlying writer.
+    pub fn finish(mut self) -> Result<W, StateSnapshotError> {
+        let count = u32::try_from(self.index.len()).map_err(|_| {
+            StateSnapshotError::InvalidFormat(format!("Too many entries: {}", self.index.len()))
+        })?;
+        self.writer.write_all(&count.to_be_bytes())?;
+        for (address, offset, len) in self.index.iter() {
+            self.writer.write_all(&address.workchain.to_be_bytes())?;
+            self.writer.write_all(&address.hash_part)?;
+            self.writer.write_all(&offset.to_be_bytes())?;
+            self.writer.write_all(&len.to_be_bytes())?;
+        }
+        self.writer.write_all(&self.position.to_be_bytes())?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Archive of snapshots written by `StateArchiveWriter` with random access by address.
+///
+/// Only the index is read on opening, entries are read and decompressed on request.
+pub struct StateArchive<R: Read + Seek> {
+    reader: R,
+    index: HashMap<TonAddress, (u64, u64)>,
+}
+
+impl<R: Read + Seek> StateArchive<R> {
+    pub fn open(mut reader: R) -> Result<Self, StateSnapshotError> {
+        let magic: [u8; 4] = read_array(&mut reader)?;
+        if magic != ARCHIVE_MAGIC {
+            return Err(StateSnapshotError::InvalidFormat(format!(
+                "Unexpected archive magic: {}",
+                hex::encode(magic)
+            )));
+        }
+        let footer_offset = reader.seek(SeekFrom::End(-8))?;
+        let index_offset = u64::from_be_bytes(read_array(&mut reader)?);
+        if index_offset < ARCHIVE_MAGIC.len() as u64 || index_offset > footer_offset {
+            return Err(StateSnapshotError::InvalidFormat(format!(
+                "Index offset {} is out of bounds",
+                index_offset
+            )));
+        }
+        reader.seek(SeekFrom::Start(index_offset))?;
+        let count = u32::from_be_bytes(read_array(&mut reader)?);
+        let mut index = HashMap::new();
+        for _ in 0..count {
+            let workchain = i32::from_be_bytes(read_array(&mut reader)?);
+            let hash_part = read_array(&mut reader)?;
+            let offset = u64::from_be_bytes(read_array(&mut reader)?);
+            let len = u64::from_be_bytes(read_array(&mut reader)?);
+            if offset.checked_add(len).is_none_or(|end| end > index_offset) {
+                return Err(StateSnapshotError::InvalidFormat(format!(
+                    "Entry at offset {} with length {} is out of bounds",
+                    offset, len
+                )));
+            }
+            index.insert(TonAddress::new(workchain, &hash_part), (offset, len));
+        }
+        Ok(StateArchive { reader, index })
+    }
+
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    pub fn contains(&self, address: &TonAddress) -> bool {
+        self.index.contains_key(address)
+    }
+
+    pub fn addresses(&self) -> impl Iterator<Item = &TonAddress> {
+        self.index.keys()
+    }
+
+    /// Reads the snapshot of `address`, `None` if the archive has no such address.
+    pub fn get(
+        &mut self,
+        address: &TonAddress,
+    ) -> Result<Option<TonContractSnapshot>, StateSnapshotError> {
+        let Some(&(offset, len)) = self.index.get(address) else {
+            return Ok(None);
+        };
+        self.reader.seek(SeekFrom::Start(offset))?;
+        let mut decoder = DeflateDecoder::new((&mut self.reader).take(len));
+        let snapshot = TonContractSnapshot::load(&mut decoder)?;
+        if &snapshot.address != address {
+            return Err(StateSnapshotError::InvalidFormat(format!(
+                "Entry of {} contains snapshot of {}",
+                address, snapshot.address
+            )));
+        }
+        Ok(Some(snapshot))
+    }
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<(), StateSnapshotError> {
+    if bytes.len() > MAX_FIELD_LEN {
+        return Err(StateSnapshotError::InvalidFormat(format!(
+            "Field length {} exceeds {}",
+            bytes.len(),
+            MAX_FIELD_LEN
+        )));
+    }
+    writer.write_all(&(bytes.len() as u32).to_be_bytes())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> Result<Vec<u8>, StateSnapshotError> {
+    let len = u32::from_be_bytes(read_array(reader)?) as usize;
+    if len > MAX_FIELD_LEN {
+        return Err(StateSnapshotError::InvalidFormat(format!(
+            "Field length {} exceeds {}",
+            len, MAX_FIELD_LEN
+        )));
+    }
+    let mut bytes = vec![0; len];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> Result<[u8; N], StateSnapshotError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tonlib_core::cell::{BagOfCells, Cell};
+    use tonlib_core::wallet::{WalletDataV3, WalletDataV4, WalletVersion, DEFAULT_WALLET_ID};
+
+    use super::*;
+
+    const GET_METHODS: [&str; 2] = ["seqno", "get_public_key"];
+
+    fn wallet_snapshot(
+        version: WalletVersion,
+        data: Cell,
+        balance: i64,
+    ) -> anyhow::Result<TonContractSnapshot> {
+        let code = BagOfCells::from_root(version.code()?.as_ref().clone()).serialize(false)?;
+        let data_hash = data.cell_hash();
+        let data = BagOfCells::from_root(data).serialize(false)?;
+        let account_state = RawFullAccountState {
+            balance,
+            code,
+            data,
+            last_transaction_id: InternalTransactionId {
+                lt: 47000000000001,
+                hash: vec![0x33; 32],
+            },
+            block_id: BlockIdExt {
+                workchain: -1,
+                shard: i64::MIN,
+                seqno: 40000000,
+                root_hash: vec![0x44; 32],
+                file_hash: vec![0x55; 32],
+            },
+            frozen_hash: vec![],
+            sync_utime: 1700000000,
+        };
+        Ok(TonContractSnapshot::new(
+            &TonAddress::new(0, &data_hash),
+            &Arc::new(account_state),
+        ))
+    }
+
+    fn fixtures() -> anyhow::Result<Vec<TonContractSnapshot>> {
+        let v3 = WalletDataV3 {
+            seqno: 5,
+            wallet_id: DEFAULT_WALLET_ID,
+            public_key: [0x11; 32],
+        };
+        let v4 = WalletDataV4 {
+            seqno: 17,
+            wallet_id: DEFAULT_WALLET_ID,
+            public_key: [0x22; 32],
+        };
+        Ok(vec![
+            wallet_snapshot(WalletVersion::V3R2, v3.try_into()?, 1_000_000_000)?,
+            wallet_snapshot(WalletVersion::V4R2, v4.try_into()?, 0)?,
+        ])
+    }
+
+    fn run_get_methods(snapshot: &TonContractSnapshot) -> anyhow::Result<Vec<Vec<TvmStackEntry>>> {
+        GET_METHODS
+            .iter()
+            .map(|method| {
+                let result = snapshot.run_get_method(*method, &[])?;
+                assert!(result.exit_success(), "{}: {:?}", method, result);
+                Ok(result.stack)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_snapshot_save_load() -> anyhow::Result<()> {
+        for snapshot in fixtures()? {
+            let mut envelope = vec![];
+            snapshot.save(&mut envelope)?;
+            let loaded = TonContractSnapshot::load(&mut envelope.as_slice())?;
+            assert_eq!(loaded, snapshot);
+        }
+        let result = TonContractSnapshot::load(&mut b"TCS0".as_slice());
+        assert!(matches!(result, Err(StateSnapshotError::InvalidFormat(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_archive_get_methods_match() -> anyhow::Result<()> {
+        let snapshots = fixtures()?;
+        let expected = snapshots
+            .iter()
+            .map(run_get_methods)
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        assert_eq!(expected[0][0][0].get_i64()?, 5);
+        assert_eq!(expected[1][0][0].get_i64()?, 17);
+
+        let mut writer = StateArchiveWriter::new(Cursor::new(vec![]))?;
+        for snapshot in snapshots.iter() {
+            writer.add(snapshot)?;
+        }
+        let archive_bytes = writer.finish()?.into_inner();
+
+        let mut archive = StateArchive::open(Cursor::new(archive_bytes.clone()))?;
+        assert_eq!(archive.len(), 2);
+        // random access in reverse order
+        for (snapshot, expected) in snapshots.iter().zip(expected.iter()).rev() {
+            assert!(archive.contains(&snapshot.address));
+            let loaded = archive
+                .get(&snapshot.address)?
+                .ok_or_else(|| anyhow::anyhow!("{} is missing", snapshot.address))?;
+            assert_eq!(&loaded, snapshot);
+            assert_eq!(&run_get_methods(&loaded)?, expected);
+        }
+        assert!(archive.get(&TonAddress::NULL)?.is_none());
+
+        let truncated = archive_bytes[..archive_bytes.len() - 4].to_vec();
+        assert!(StateArchive::open(Cursor::new(truncated)).is_err());
+        Ok(())
+    }
+}
@@ -1,20 +1,39 @@
+use std::io::Write;
 use std::sync::Arc;
 
 use async_trait::async_trait;
-use tonlib_core::cell::Cell;
-use tonlib_core::TonAddress;
+use num_traits::ToPrimitive;
+use tonlib_core::cell::{ArcCell, BagOfCells, Cell};
+use tonlib_core::types::Coins;
+use tonlib_core::{TonAddress, TonHash};
 
 use crate::client::{TonClientError, TonClientInterface};
-use crate::contract::{TonContractError, TonContractFactory, TonContractInterface};
-use crate::emulator::{TvmEmulator, TvmEmulatorC7Builder};
-use crate::tl::{InternalTransactionId, RawFullAccountState};
-use crate::types::{TonMethodId, TvmMsgSuccess, TvmStackEntry, TvmSuccess};
+use crate::contract::{
+    StateSnapshotError, TonContractError, TonContractFactory, TonContractInterface,
+    TonContractSnapshot,
+};
+use crate::emulator::{TvmEmulator, TvmEmulatorC7, TvmEmulatorC7Builder};
+use crate::rt::OnceCell;
+use crate::tl::{InternalTransactionId, RawFullAccountState, TvmStackEntry as TlTvmStackEntry};
+use crate::types::{StackParseError, TonMethodId, TvmMsgSuccess, TvmStackEntry, TvmSuccess};
 
 #[derive(Clone)]
 pub struct TonContractState {
     factory: TonContractFactory,
     address: TonAddress,
     account_state: Arc<RawFullAccountState>,
+    c7_overrides: C7Overrides,
+    /// `gen_utime` of the block of `account_state`, fetched once and shared by clones
+    block_gen_utime: Arc<OnceCell<Option<u32>>>,
+}
+
+/// Fields of c7 set with `TonContractState::with_*`, replacing the defaults of `default_c7`
+#[derive(Clone, Default)]
+struct C7Overrides {
+    unix_time: Option<u32>,
+    seed: Option<TonHash>,
+    balance: Option<u64>,
+    config: Option<ArcCell>,
 }
 
 impl TonContractState {
@@ -27,6 +46,8 @@ impl TonContractState {
             factory: factory.clone(),
             address: address.clone(),
             account_state: account_state.clone(),
+            c7_overrides: C7Overrides::default(),
+            block_gen_utime: Arc::new(OnceCell::new()),
         }
     }
 
@@ -34,6 +55,114 @@ impl TonContractState {
         &self.account_state
     }
 
+    /// Detaches the state from the factory, see `TonContractSnapshot`.
+    pub fn snapshot(&self) -> TonContractSnapshot {
+        TonContractSnapshot::new(&self.address, &self.account_state)
+    }
+
+    /// Writes the state as a binary envelope readable with `TonContractSnapshot::load`.
+    pub fn save<W: Write>(&self, writer: &mut W) -> Result<(), StateSnapshotError> {
+        self.snapshot().save(writer)
+    }
+
+    /// Sets the current time returned by `now()` during emulation
+    pub fn with_unixtime(mut self, unix_time: u32) -> Self {
+        self.c7_overrides.unix_time = Some(unix_time);
+        self
+    }
+
+    /// Sets the seed of random numbers generated during emulation
+    pub fn with_rand_seed(mut self, seed: TonHash) -> Self {
+        self.c7_overrides.seed = Some(seed);
+        self
+    }
+
+    /// Sets the balance of the contract during emulation, saturating at `u64::MAX` nanotons
+    pub fn with_balance(mut self, balance: Coins) -> Self {
+        self.c7_overrides.balance = Some(balance.nanotons().to_u64().unwrap_or(u64::MAX));
+        self
+    }
+
+    /// Sets the blockchain config used during emulation instead of the current one
+    pub fn with_config_cell(mut self, config: ArcCell) -> Self {
+        self.c7_overrides.config = Some(config);
+        self
+    }
+
+    /// Pins the current time of emulation to `gen_utime` of the block the account state
+    /// is read at, fetching its header.
+    pub async fn freeze_time(self) -> Result<Self, TonContractError> {
+        let gen_utime = self.block_gen_utime().await?.ok_or_else(|| {
+            TonContractError::IllegalArgument(format!(
+                "Account state of {} has no block to freeze time at",
+                self.address
+            ))
+        })?;
+        Ok(self.with_unixtime(gen_utime))
+    }
+
+    /// Returns c7 used for emulation by default. Fields not set with `with_*` methods are:
+    /// - balance: the balance of the account state,
+    /// - time: `sync_utime` of the account state or, if it's not set, e.g. for a state read by
+    ///   transaction, `gen_utime` of its block. The header of the block is requested once per
+    ///   state; if the request fails, the time is left unset,
+    /// - random seed: the hash of the last transaction of the account,
+    /// - config: the current config of the factory.
+    ///
+    /// So the results of emulation are the same for the same account state.
+    /// The returned c7 can be modified and passed to `emulate_get_method_with_c7`
+    /// or `emulate_internal_message_with_c7` to emulate under hypothetical conditions.
+    pub async fn default_c7(&self) -> Result<TvmEmulatorC7, TonContractError> {
+        let config = match &self.c7_overrides.config {
+            Some(config) => BagOfCells::from_root(config.as_ref().clone())
+                .serialize(false)
+                .map_err(|e| TonContractError::InternalError(e.to_string()))?,
+            None => self.factory.get_config_cell_serial().await?.to_vec(),
+        };
+        let unix_time = match self.c7_overrides.unix_time {
+            None if self.account_state.sync_utime <= 0 => match self.block_gen_utime().await {
+                Ok(gen_utime) => gen_utime,
+                Err(e) => {
+                    log::warn!(
+                        "Emulating {} without time, block header is unavailable: {}",
+                        self.address,
+                        e
+                    );
+                    None
+                }
+            },
+            unix_time => unix_time,
+        };
+        let mut builder =
+            TvmEmulatorC7Builder::from_account_state(&self.address, &config, &self.account_state);
+        if let Some(unix_time) = unix_time {
+            builder.with_unix_time(unix_time as u64);
+        }
+        if let Some(seed) = self.c7_overrides.seed {
+            builder.with_seed(seed);
+        }
+        if let Some(balance) = self.c7_overrides.balance {
+            builder.with_balance(balance);
+        }
+        Ok(builder.build())
+    }
+
+    /// `gen_utime` of the block of the account state, `None` if the state has no block
+    async fn block_gen_utime(&self) -> Result<Option<u32>, TonContractError> {
+        let gen_utime = self
+            .block_gen_utime
+            .get_or_try_init(|| async {
+                let block_id = &self.account_state.block_id;
+                if block_id.seqno == 0 {
+                    return Ok::<_, TonContractError>(None);
+                }
+                let header = self.factory.client().get_block_header(block_id).await?;
+                Ok(Some(header.gen_utime as u32))
+            })
+            .await?;
+        Ok(*gen_utime)
+    }
+
     #[cfg(feature = "emulate_get_method")]
     async fn do_run_get_method<M, S>(
         &self,
@@ -76,6 +205,20 @@ impl TonContractState {
         method: M,
         stack: S,
     ) -> Result<TvmSuccess, TonContractError>
+    where
+        M: Into<TonMethodId> + Send + Copy,
+        S: AsRef<[TvmStackEntry]> + Send,
+    {
+        let c7 = self.default_c7().await?;
+        self.emulate_get_method_with_c7(method, stack, &c7).await
+    }
+
+    pub async fn emulate_get_method_with_c7<M, S>(
+        &self,
+        method: M,
+        stack: S,
+        c7: &TvmEmulatorC7,
+    ) -> Result<TvmSuccess, TonContractError>
     where
         M: Into<TonMethodId> + Send + Copy,
         S: AsRef<[TvmStackEntry]> + Send,
@@ -83,12 +226,7 @@ impl TonContractState {
         let method_id = &method.into();
         let stack_ref = stack.as_ref();
         let state = self.account_state.clone();
-        let c7 = TvmEmulatorC7Builder::new(
-            &self.address,
-            self.factory.get_config_cell_serial().await?,
-            0,
-        )
-        .build();
+        let c7 = c7.clone();
 
         let libs = self
             .factory
@@ -106,7 +244,7 @@ impl TonContractState {
             let static_method_id: &'static TonMethodId = std::mem::transmute(method_id);
             let static_stack: &'static [TvmStackEntry] = std::mem::transmute(stack_ref);
             #[allow(clippy::let_and_return)]
-            tokio::task::spawn_blocking(move || {
+            crate::rt::spawn_blocking(move || {
                 let code = state.code.as_slice();
                 let data = state.data.as_slice();
                 let mut emulator = TvmEmulator::new(code, data)?;
@@ -130,15 +268,21 @@ impl TonContractState {
         &self,
         message: Cell,
         amount: u64,
+    ) -> Result<TvmMsgSuccess, TonContractError> {
+        let c7 = self.default_c7().await?;
+        self.emulate_internal_message_with_c7(message, amount, &c7)
+            .await
+    }
+
+    pub async fn emulate_internal_message_with_c7(
+        &self,
+        message: Cell,
+        amount: u64,
+        c7: &TvmEmulatorC7,
     ) -> Result<TvmMsgSuccess, TonContractError> {
         let state = self.account_state.clone();
-        let c7 = TvmEmulatorC7Builder::new(
-            &self.address,
-            self.factory.get_config_cell_serial().await?,
-            0,
-        )
-        .build();
-        let run_result = tokio::task::spawn_blocking(move || {
+        let c7 = c7.clone();
+        let run_result = crate::rt::spawn_blocking(move || {
             let code = state.code.as_slice();
             let data = state.data.as_slice();
             let mut emulator = TvmEmulator::new(code, data)?;
@@ -199,7 +343,7 @@ impl TonContractState {
             .stack
             .elements
             .iter()
-            .map(|e| e.try_into())
+            .map(|e| self.parse_stack_entry(e))
             .collect::<Result<Vec<_>, _>>()
             .map_err(|e| TonContractError::TvmStackParseError {
                 method: method.into(),
@@ -216,6 +360,17 @@ impl TonContractState {
         Self::raise_exit_error(self.address(), &method.into(), result)
     }
 
+    /// Converts an entry of a get-method result, taking cells such as the code returned
+    /// by `get_jetton_data` from the cell cache of the factory
+    fn parse_stack_entry(&self, entry: &TlTvmStackEntry) -> Result<TvmStackEntry, StackParseError> {
+        match entry {
+            TlTvmStackEntry::Cell { cell } => {
+                Ok(TvmStackEntry::Cell(self.factory.parse_cell(&cell.bytes)?))
+            }
+            entry => entry.try_into(),
+        }
+    }
+
     fn raise_exit_error(
         address: &TonAddress,
         method: &TonMethodId,
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use strum::IntoStaticStr;
 
 use crate::contract::{MapStackError, TonContractError, TonContractInterface};
+use crate::types::TvmStackParser;
 
 #[derive(IntoStaticStr)]
 #[strum(serialize_all = "snake_case")]
@@ -15,37 +16,25 @@ pub trait TonWalletContract: TonContractInterface {
     async fn seqno(&self) -> Result<u32, TonContractError> {
         let method: &str = WalletContractMethods::Seqno.into();
         let res = self.run_get_method("seqno", Vec::new()).await?;
-        let stack = res.stack;
-        if stack.len() != 1 {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: 1,
-            })
-        } else {
-            let result = stack[0].get_i64().map_stack_error(method, self.address())? as u32;
-            Ok(result)
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let result = stack.pop_i64().map_stack_error(method, self.address())? as u32;
+        stack
+            .ensure_empty()
+            .map_stack_error(method, self.address())?;
+        Ok(result)
     }
 
     async fn get_public_key(&self) -> Result<Vec<u8>, TonContractError> {
         let method: &str = WalletContractMethods::GetPublicKey.into();
         let res = self.run_get_method(method, Vec::new()).await?;
-        let stack = res.stack;
-        if stack.len() != 1 {
-            Err(TonContractError::InvalidMethodResultStackSize {
-                method: method.to_string(),
-                address: self.address().clone(),
-                actual: stack.len(),
-                expected: 1,
-            })
-        } else {
-            let pub_key = stack[0]
-                .get_biguint()
-                .map_stack_error("get_public_key", self.address())?;
-            Ok(pub_key.to_bytes_be())
-        }
+        let mut stack = TvmStackParser::new(&res.stack);
+        let pub_key = stack
+            .pop_biguint()
+            .map_stack_error(method, self.address())?;
+        stack
+            .ensure_empty()
+            .map_stack_error(method, self.address())?;
+        Ok(pub_key.to_bytes_be())
     }
 }
 
@@ -9,8 +9,10 @@ pub use unsafe_emulator::*;
 
 use self::types::TvmEmulatorMessageResponse;
 use crate::emulator::types::TvmEmulatorResponse;
+use crate::tl::RawFullAccountState;
 use crate::types::{TonMethodId, TvmMsgSuccess, TvmStackEntry, TvmSuccess};
 
+pub mod audit;
 mod error;
 mod types;
 mod unsafe_emulator;
@@ -18,6 +20,7 @@ mod unsafe_emulator;
 #[derive(Debug)]
 pub struct TvmEmulator {
     emulator: TvmEmulatorUnsafe,
+    c7: Option<TvmEmulatorC7>,
 }
 
 const DEFAULT_VM_LOG_VERBOSITY: u32 = 1;
@@ -30,7 +33,8 @@ pub struct TvmEmulatorC7Builder<'a> {
     pub seed: TonHash,
 }
 
-#[derive(Clone)]
+/// Fields of the smart-contract info tuple (c7) passed to the emulator
+#[derive(Debug, Clone)]
 pub struct TvmEmulatorC7 {
     pub address: TonAddress,
     pub config: Vec<u8>,
@@ -55,6 +59,41 @@ impl<'a> TvmEmulatorC7Builder<'a> {
         }
     }
 
+    /// Creates the builder with defaults taken from the account state: its balance,
+    /// `sync_utime` as the current time and the hash of the last transaction as the random seed.
+    ///
+    /// Falls back to the current time and zero seed if the state has no such data.
+    pub fn from_account_state(
+        address: &'a TonAddress,
+        config: &'a [u8],
+        state: &RawFullAccountState,
+    ) -> Self {
+        let mut builder = Self::new(address, config, state.balance.max(0) as u64);
+        if state.sync_utime > 0 {
+            builder.with_unix_time(state.sync_utime as u64);
+        }
+        let seed: Result<TonHash, _> = state.last_transaction_id.hash.as_slice().try_into();
+        if let Ok(seed) = seed {
+            builder.with_seed(seed);
+        }
+        builder
+    }
+
+    pub fn with_address(&mut self, address: &'a TonAddress) -> &mut Self {
+        self.address = address;
+        self
+    }
+
+    pub fn with_config(&mut self, config: &'a [u8]) -> &mut Self {
+        self.config = config;
+        self
+    }
+
+    pub fn with_balance(&mut self, balance: u64) -> &mut Self {
+        self.balance = balance;
+        self
+    }
+
     pub fn with_seed(&mut self, seed: TonHash) -> &mut Self {
         self.seed = seed;
         self
@@ -79,7 +118,7 @@ impl<'a> TvmEmulatorC7Builder<'a> {
 impl TvmEmulator {
     pub fn new(code: &[u8], data: &[u8]) -> Result<TvmEmulator, TvmEmulatorError> {
         let emulator = TvmEmulatorUnsafe::create(code, data, DEFAULT_VM_LOG_VERBOSITY)?;
-        let ton_contract_emulator = TvmEmulator { emulator };
+        let ton_contract_emulator = TvmEmulator { emulator, c7: None };
         Ok(ton_contract_emulator)
     }
 
@@ -94,6 +133,7 @@ impl TvmEmulator {
             .emulator
             .set_c7(addr_str.as_bytes(), unix_time, balance, seed, config)?;
         if res {
+            self.c7 = Some(c7.clone());
             Ok(self)
         } else {
             Err(TvmEmulatorError::EmulatorError(
@@ -102,6 +142,47 @@ impl TvmEmulator {
         }
     }
 
+    /// Returns c7 fields set by the last successful `set_c7`, `None` if c7 wasn't set.
+    pub fn c7(&self) -> Option<&TvmEmulatorC7> {
+        self.c7.as_ref()
+    }
+
+    /// Replaces the balance in c7, keeping its other fields. `set_c7` must be called first.
+    pub fn set_balance(&mut self, balance: u64) -> Result<&mut Self, TvmEmulatorError> {
+        self.update_c7(|c7| c7.balance = balance)
+    }
+
+    /// Replaces the current time in c7, keeping its other fields. `set_c7` must be called first.
+    pub fn set_unix_time(&mut self, unix_time: u64) -> Result<&mut Self, TvmEmulatorError> {
+        self.update_c7(|c7| c7.unix_time = unix_time)
+    }
+
+    /// Replaces the random seed in c7, keeping its other fields. `set_c7` must be called first.
+    pub fn set_seed(&mut self, seed: TonHash) -> Result<&mut Self, TvmEmulatorError> {
+        self.update_c7(|c7| c7.seed = seed)
+    }
+
+    /// Replaces the contract address in c7, keeping its other fields. `set_c7` must be called first.
+    pub fn set_address(&mut self, address: &TonAddress) -> Result<&mut Self, TvmEmulatorError> {
+        self.update_c7(|c7| c7.address = address.clone())
+    }
+
+    /// Replaces the blockchain config in c7, keeping its other fields. `set_c7` must be called first.
+    pub fn set_config(&mut self, config: &[u8]) -> Result<&mut Self, TvmEmulatorError> {
+        self.update_c7(|c7| c7.config = config.to_vec())
+    }
+
+    fn update_c7<F>(&mut self, update: F) -> Result<&mut Self, TvmEmulatorError>
+    where
+        F: FnOnce(&mut TvmEmulatorC7),
+    {
+        let mut c7 = self.c7.clone().ok_or_else(|| {
+            TvmEmulatorError::InternalError("c7 must be set before updating its fields".to_string())
+        })?;
+        update(&mut c7);
+        self.set_c7(&c7)
+    }
+
     pub fn set_debug_enable(&mut self) -> Result<&mut Self, TvmEmulatorError> {
         let result = self.emulator.set_debug_enabled(true);
         match result {
@@ -0,0 +1,242 @@
+//! Audit of replay protection of contracts accepting external messages directly.
+
+use num_bigint::BigUint;
+use tonlib_core::cell::{BagOfCells, Cell, CellBuilder, TonCellError};
+use tonlib_core::TonHash;
+
+use crate::client::parse_external_message;
+use crate::emulator::{TvmEmulator, TvmEmulatorC7, TvmEmulatorError};
+use crate::types::TvmMsgSuccess;
+
+/// State of the audited contract
+#[derive(Debug, Clone)]
+pub struct ReplayAuditState {
+    /// Serialized code of the contract
+    pub code: Vec<u8>,
+    /// Serialized data of the contract
+    pub data: Vec<u8>,
+    /// Smart-contract info to run the contract with, e.g. the time checked against `valid_until`
+    pub c7: Option<TvmEmulatorC7>,
+}
+
+/// Unsigned integer field stored in the root cell of the message body, e.g. `seqno`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BodyField {
+    pub name: String,
+    /// Offset of the field in bits from the start of the root cell
+    pub offset: usize,
+    pub bit_len: usize,
+}
+
+impl BodyField {
+    pub fn new(name: &str, offset: usize, bit_len: usize) -> BodyField {
+        BodyField {
+            name: name.to_string(),
+            offset,
+            bit_len,
+        }
+    }
+}
+
+/// Application of the external following the application of the original one
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ReplayScenario {
+    /// The same external is applied again
+    Replay,
+    /// The external with `field` increased by `delta` (modulo the field size) is applied
+    Mutated { field: BodyField, delta: u64 },
+}
+
+/// Outcome of the emulated application of an external
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayRun {
+    pub accepted: bool,
+    pub exit_code: i32,
+    pub gas_used: i32,
+}
+
+impl ReplayRun {
+    /// `true` if the message is accepted and the compute phase succeeded,
+    /// i.e. state changes & actions of the run are committed
+    pub fn committed(&self) -> bool {
+        self.accepted && (self.exit_code == 0 || self.exit_code == 1)
+    }
+}
+
+impl From<&TvmMsgSuccess> for ReplayRun {
+    fn from(value: &TvmMsgSuccess) -> Self {
+        ReplayRun {
+            accepted: value.accepted,
+            exit_code: value.vm_exit_code,
+            gas_used: value.gas_used,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ReplayVerdict {
+    /// The external isn't committed the second time
+    Rejected,
+    /// The external is committed again with the same actions, i.e. its effect is replayed
+    Accepted,
+    /// The external is committed again, but with actions different from the original run
+    StateDiverged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ScenarioOutcome {
+    pub scenario: ReplayScenario,
+    pub run: ReplayRun,
+    pub verdict: ReplayVerdict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReplayAuditReport {
+    /// Application of the original external to the audited state
+    pub original: ReplayRun,
+    /// Outcomes in the order of the scenarios
+    pub outcomes: Vec<ScenarioOutcome>,
+}
+
+impl ReplayAuditReport {
+    /// `true` if any scenario wasn't rejected
+    pub fn is_vulnerable(&self) -> bool {
+        self.outcomes
+            .iter()
+            .any(|o| o.verdict != ReplayVerdict::Rejected)
+    }
+}
+
+/// Checks replay protection of the contract in `state` against the external message `external_boc`.
+///
+/// The external is applied to `state`, then each of `scenarios` is applied to the state resulting
+/// from it, starting from the same state. A scenario which isn't committed is rejected, while
+/// committed ones are reported as `Accepted` if they repeat the actions of the original run,
+/// or as `StateDiverged` otherwise. The original external must be committed.
+///
+/// Signatures are checked by the contract, so the fields of mutated externals are changed
+/// without re-signing them, as an attacker would do.
+///
+/// Externals are run by `TvmEmulator`, which emulates the compute phase only: fees, the action
+/// phase and the balance checks of the transaction emulator are not applied. A scenario is
+/// judged by the actions it produces, even if a real transaction would fail to execute them.
+pub fn external_replay_check(
+    state: &ReplayAuditState,
+    external_boc: &[u8],
+    scenarios: &[ReplayScenario],
+) -> Result<ReplayAuditReport, TvmEmulatorError> {
+    let message = BagOfCells::parse(external_boc)?
+        .single_root()?
+        .as_ref()
+        .clone();
+    let (_, body) = parse_external_message(&message)?;
+
+    let original = apply(&state.code, &state.data, state.c7.as_ref(), &body)?;
+    let original_run = ReplayRun::from(&original);
+    if !original_run.committed() {
+        return Err(TvmEmulatorError::InternalError(format!(
+            "Original external is not committed: accepted: {}, exit code: {}",
+            original_run.accepted, original_run.exit_code
+        )));
+    }
+    let code = BagOfCells::from_root(original.new_code.as_ref().clone()).serialize(false)?;
+    let data = BagOfCells::from_root(original.new_data.as_ref().clone()).serialize(false)?;
+
+    let mut outcomes = Vec::with_capacity(scenarios.len());
+    for scenario in scenarios {
+        let scenario_body = match scenario {
+            ReplayScenario::Replay => body.clone(),
+            ReplayScenario::Mutated { field, delta } => mutate_field(&body, field, *delta)?,
+        };
+        let result = apply(&code, &data, state.c7.as_ref(), &scenario_body)?;
+        let run = ReplayRun::from(&result);
+        let verdict = if !run.committed() {
+            ReplayVerdict::Rejected
+        } else if actions_hash(&result) == actions_hash(&original) {
+            ReplayVerdict::Accepted
+        } else {
+            ReplayVerdict::StateDiverged
+        };
+        log::debug!("Replay scenario {:?}: {:?}, {:?}", scenario, run, verdict);
+        outcomes.push(ScenarioOutcome {
+            scenario: scenario.clone(),
+            run,
+            verdict,
+        });
+    }
+    Ok(ReplayAuditReport {
+        original: original_run,
+        outcomes,
+    })
+}
+
+fn apply(
+    code: &[u8],
+    data: &[u8],
+    c7: Option<&TvmEmulatorC7>,
+    body: &Cell,
+) -> Result<TvmMsgSuccess, TvmEmulatorError> {
+    let mut emulator = TvmEmulator::new(code, data)?;
+    if let Some(c7) = c7 {
+        emulator.set_c7(c7)?;
+    }
+    emulator.send_external_message(body.clone())
+}
+
+fn actions_hash(result: &TvmMsgSuccess) -> Option<TonHash> {
+    result.actions.as_ref().map(|actions| actions.cell_hash())
+}
+
+/// Returns `body` with `field` of its root cell increased by `delta`, keeping other bits & references
+pub(crate) fn mutate_field(
+    body: &Cell,
+    field: &BodyField,
+    delta: u64,
+) -> Result<Cell, TonCellError> {
+    let mut parser = body.parser();
+    let prefix = parser.load_bits(field.offset)?;
+    let value = parser.load_uint(field.bit_len)?;
+    let rest_len = parser.remaining_bits();
+    let rest = parser.load_bits(rest_len)?;
+    let modulus = BigUint::from(1u32) << field.bit_len;
+    let mutated = (value + delta) % modulus;
+    CellBuilder::new()
+        .store_bits(field.offset, &prefix)?
+        .store_uint(field.bit_len, &mutated)?
+        .store_bits(rest_len, &rest)?
+        .store_references(body.references())?
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mutate_field() -> anyhow::Result<()> {
+        let child = CellBuilder::new().store_u8(8, 0xab)?.build()?;
+        let body = CellBuilder::new()
+            .store_u8(4, 0x5)?
+            .store_u32(32, 41)?
+            .store_u8(3, 0x7)?
+            .store_child(child)?
+            .build()?;
+
+        let field = BodyField::new("seqno", 4, 32);
+        let mutated = mutate_field(&body, &field, 1)?;
+        let mut parser = mutated.parser();
+        assert_eq!(parser.load_u8(4)?, 0x5);
+        assert_eq!(parser.load_u32(32)?, 42);
+        assert_eq!(parser.load_u8(3)?, 0x7);
+        assert_eq!(parser.remaining_bits(), 0);
+        assert_eq!(mutated.references(), body.references());
+
+        // the field wraps around at its size
+        let field = BodyField::new("flags", 36, 3);
+        let mutated = mutate_field(&body, &field, 1)?;
+        let mut parser = mutated.parser();
+        parser.skip_bits(36)?;
+        assert_eq!(parser.load_u8(3)?, 0);
+        Ok(())
+    }
+}
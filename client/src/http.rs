@@ -0,0 +1,111 @@
+use async_trait::async_trait;
+pub use http::StatusCode;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HttpError {
+    #[error("Transport error ({0})")]
+    Transport(String),
+
+    #[error("Http client error ({0})")]
+    Other(#[from] Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: StatusCode,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        self.status.is_success()
+    }
+}
+
+/// Minimal HTTP client used to load off-chain data (metadata, IPFS objects).
+///
+/// Implement it to route requests through your own connection pool, proxy or TLS setup.
+/// Non-success statuses must be returned as `Ok` responses, `Err` is reserved for transport failures.
+#[async_trait]
+pub trait HttpClient: Send + Sync {
+    async fn get(&self, url: &str) -> Result<HttpResponse, HttpError>;
+
+    async fn post(&self, url: &str, body: Vec<u8>) -> Result<HttpResponse, HttpError>;
+
+    /// Sends POST request with additional `headers`.
+    ///
+    /// The default implementation supports only requests without headers,
+    /// override it to make the client usable for webhooks.
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, HttpError> {
+        if !headers.is_empty() {
+            return Err(HttpError::Transport(
+                "Request headers are not supported by the HTTP client".to_string(),
+            ));
+        }
+        self.post(url, body).await
+    }
+}
+
+/// Default `HttpClient` backed by `reqwest`
+#[cfg(feature = "http")]
+#[derive(Debug, Clone, Default)]
+pub struct ReqwestHttpClient {
+    client: reqwest::Client,
+}
+
+#[cfg(feature = "http")]
+impl ReqwestHttpClient {
+    pub fn new() -> Result<ReqwestHttpClient, HttpError> {
+        let client = reqwest::Client::builder().build()?;
+        Ok(ReqwestHttpClient { client })
+    }
+
+    pub fn from_client(client: reqwest::Client) -> ReqwestHttpClient {
+        ReqwestHttpClient { client }
+    }
+
+    async fn execute(&self, request: reqwest::RequestBuilder) -> Result<HttpResponse, HttpError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let body = response.bytes().await?.to_vec();
+        Ok(HttpResponse { status, body })
+    }
+}
+
+#[cfg(feature = "http")]
+#[async_trait]
+impl HttpClient for ReqwestHttpClient {
+    async fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+        self.execute(self.client.get(url)).await
+    }
+
+    async fn post(&self, url: &str, body: Vec<u8>) -> Result<HttpResponse, HttpError> {
+        self.execute(self.client.post(url).body(body)).await
+    }
+
+    async fn post_with_headers(
+        &self,
+        url: &str,
+        headers: &[(&str, &str)],
+        body: Vec<u8>,
+    ) -> Result<HttpResponse, HttpError> {
+        let mut request = self.client.post(url).body(body);
+        for (name, value) in headers {
+            request = request.header(*name, *value);
+        }
+        self.execute(request).await
+    }
+}
+
+#[cfg(feature = "http")]
+impl From<reqwest::Error> for HttpError {
+    fn from(e: reqwest::Error) -> Self {
+        HttpError::Transport(e.to_string())
+    }
+}
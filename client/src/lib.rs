@@ -1,10 +1,18 @@
+mod build_info;
 pub mod client;
 pub mod config;
 pub mod contract;
 pub mod emulator;
+pub mod http;
 pub mod meta;
+pub mod preview;
+mod rt;
 pub mod tl;
 pub mod types;
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+
+pub use build_info::{build_info, BuildInfo};
 
 #[doc = include_str!("../README.md")]
 #[cfg(doctest)]
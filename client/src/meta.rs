@@ -9,6 +9,7 @@ mod ipfs_loader;
 mod loader;
 
 use std::fmt::Debug;
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use lazy_static::lazy_static;
@@ -16,6 +17,8 @@ use serde::de::DeserializeOwned;
 use sha2::{Digest, Sha256};
 use tonlib_core::cell::dict::SnakeFormatDict;
 use tonlib_core::types::ZERO_HASH;
+
+use crate::http::HttpClient;
 struct MetaDataField {
     pub(crate) key: TonHash,
 }
@@ -103,7 +106,7 @@ pub struct MetaLoader<MetaData>
 where
     MetaData: DeserializeOwned,
 {
-    http_client: reqwest::Client,
+    http_client: Arc<dyn HttpClient>,
     ipfs_loader: IpfsLoader,
     meta_data_marker: std::marker::PhantomData<MetaData>,
 }
@@ -115,27 +118,34 @@ impl<MetaData> MetaLoader<MetaData>
 where
     MetaData: DeserializeOwned,
 {
+    #[cfg(feature = "http")]
     pub fn new(
         ipfs_loader_config: &IpfsLoaderConfig,
     ) -> Result<MetaLoader<MetaData>, MetaLoaderError> {
-        let http_client = reqwest::Client::builder().build()?;
-        let ipfs_loader = IpfsLoader::new(ipfs_loader_config)?; // Replace with actual initialization
-        Ok(MetaLoader {
+        let http_client = crate::http::ReqwestHttpClient::new()?;
+        Ok(Self::with_http_client(
+            ipfs_loader_config,
+            Arc::new(http_client),
+        ))
+    }
+
+    /// Creates loader which sends all requests, including IPFS ones, through `http_client`
+    pub fn with_http_client(
+        ipfs_loader_config: &IpfsLoaderConfig,
+        http_client: Arc<dyn HttpClient>,
+    ) -> MetaLoader<MetaData> {
+        let ipfs_loader = IpfsLoader::with_http_client(ipfs_loader_config, http_client.clone());
+        MetaLoader {
             http_client,
             ipfs_loader,
             meta_data_marker: std::marker::PhantomData,
-        })
+        }
     }
 
+    #[cfg(feature = "http")]
     #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<MetaLoader<MetaData>, MetaLoaderError> {
-        let http_client = reqwest::Client::builder().build()?;
-        let ipfs_loader = IpfsLoader::new(&IpfsLoaderConfig::default())?; // Replace with actual initialization
-        Ok(MetaLoader {
-            http_client,
-            ipfs_loader,
-            meta_data_marker: std::marker::PhantomData,
-        })
+        Self::new(&IpfsLoaderConfig::default())
     }
 
     pub async fn load_meta_from_uri(&self, uri: &str) -> Result<MetaData, MetaLoaderError> {
@@ -144,13 +154,13 @@ where
             let path: String = uri.chars().skip(7).collect();
             self.ipfs_loader.load_utf8_lossy(path.as_str()).await?
         } else {
-            let resp = self.http_client.get(uri).send().await?;
-            if resp.status().is_success() {
-                resp.text().await?
+            let resp = self.http_client.get(uri).await?;
+            if resp.is_success() {
+                String::from_utf8_lossy(&resp.body).to_string()
             } else {
                 return Err(MetaLoaderError::LoadMetaDataFailed {
                     uri: uri.to_string(),
-                    status: resp.status(),
+                    status: resp.status,
                 });
             }
         };
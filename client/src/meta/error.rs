@@ -1,6 +1,6 @@
-use reqwest::StatusCode;
 use thiserror::Error;
 
+use crate::http::{HttpError, StatusCode};
 use crate::meta::{IpfsLoaderError, MetaDataContent};
 
 #[derive(Debug, Error)]
@@ -18,7 +18,7 @@ pub enum MetaLoaderError {
     SerdeJsonError(#[from] serde_json::Error),
 
     #[error("Transport error ({0})")]
-    TransportError(#[from] reqwest::Error),
+    TransportError(#[from] HttpError),
 
     #[error("Internal error ({0})")]
     InternalError(String),
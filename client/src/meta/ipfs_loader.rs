@@ -1,8 +1,11 @@
 use std::fmt::Debug;
+use std::sync::Arc;
 
 pub use error::*;
 use serde::{Deserialize, Serialize};
 
+use crate::http::HttpClient;
+
 mod error;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
@@ -48,18 +51,25 @@ impl Default for IpfsLoaderConfig {
 pub struct IpfsLoader {
     connection_type: IpfsConnectionType,
     base_url: String,
-    client: reqwest::Client,
+    client: Arc<dyn HttpClient>,
 }
 
 impl IpfsLoader {
+    #[cfg(feature = "http")]
     pub fn new(config: &IpfsLoaderConfig) -> Result<Self, IpfsLoaderError> {
-        Ok(Self {
+        let client = crate::http::ReqwestHttpClient::new()?;
+        Ok(Self::with_http_client(config, Arc::new(client)))
+    }
+
+    pub fn with_http_client(config: &IpfsLoaderConfig, client: Arc<dyn HttpClient>) -> Self {
+        Self {
             connection_type: config.connection_type.clone(),
             base_url: config.base_url.clone(),
-            client: reqwest::Client::builder().build()?,
-        })
+            client,
+        }
     }
 
+    #[cfg(feature = "http")]
     #[allow(clippy::should_implement_trait)]
     pub fn default() -> Result<Self, IpfsLoaderError> {
         Self::new(&IpfsLoaderConfig::default())
@@ -69,20 +79,18 @@ impl IpfsLoader {
         let response = match self.connection_type {
             IpfsConnectionType::HttpGateway => {
                 let full_url = format!("{}/{}", self.base_url, path);
-                self.client.get(full_url).send().await?
+                self.client.get(&full_url).await?
             }
             IpfsConnectionType::IpfsNode => {
                 let full_url = format!("{}/api/v0/cat?arg={}", self.base_url, path);
-                self.client.post(full_url).send().await?
+                self.client.post(&full_url, vec![]).await?
             }
         };
-        let status = response.status();
-        if status.is_success() {
-            let bytes = response.bytes().await?.to_vec();
-            Ok(bytes)
+        if response.is_success() {
+            Ok(response.body)
         } else {
             const MAX_MESSAGE_SIZE: usize = 200;
-            let body = String::from_utf8_lossy(&response.bytes().await?).to_string();
+            let body = String::from_utf8_lossy(&response.body).to_string();
             let message = if body.len() > MAX_MESSAGE_SIZE {
                 format!("{}...", &body[0..MAX_MESSAGE_SIZE - 3])
             } else {
@@ -91,7 +99,7 @@ impl IpfsLoader {
 
             Err(IpfsLoaderError::IpfsLoadObjectFailed {
                 path: path.to_string(),
-                status,
+                status: response.status,
                 message,
             })
         }
@@ -106,7 +114,15 @@ impl IpfsLoader {
 
 #[cfg(test)]
 mod tests {
-    use crate::meta::{IpfsConnectionType, IpfsLoaderConfig};
+    use std::sync::{Arc, Mutex};
+
+    use async_trait::async_trait;
+
+    use crate::http::{HttpClient, HttpError, HttpResponse, StatusCode};
+    use crate::meta::{
+        IpfsConnectionType, IpfsLoader, IpfsLoaderConfig, IpfsLoaderError, JettonMetaLoader,
+        MetaLoaderError,
+    };
 
     static CONFIG_JSON: &str = r#"
     {
@@ -122,4 +138,72 @@ mod tests {
         assert_eq!(config.base_url, "http://example.com/");
         Ok(())
     }
+
+    #[derive(Default)]
+    struct MockHttpClient {
+        requests: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for MockHttpClient {
+        async fn get(&self, url: &str) -> Result<HttpResponse, HttpError> {
+            self.requests.lock().unwrap().push(format!("GET {}", url));
+            let response = if url.ends_with("missing") {
+                HttpResponse {
+                    status: StatusCode::NOT_FOUND,
+                    body: b"not found".to_vec(),
+                }
+            } else {
+                HttpResponse {
+                    status: StatusCode::OK,
+                    body: br#"{"name": "Example Coin", "symbol": "XMPL"}"#.to_vec(),
+                }
+            };
+            Ok(response)
+        }
+
+        async fn post(&self, url: &str, _body: Vec<u8>) -> Result<HttpResponse, HttpError> {
+            self.requests.lock().unwrap().push(format!("POST {}", url));
+            Err(HttpError::Transport("connection refused".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_http_client() -> anyhow::Result<()> {
+        let http_client = Arc::new(MockHttpClient::default());
+        let config = IpfsLoaderConfig::http_gateway("http://gateway");
+        let loader = JettonMetaLoader::with_http_client(&config, http_client.clone());
+
+        let meta = loader
+            .load_meta_from_uri("https://example.com/meta")
+            .await?;
+        assert_eq!(meta.name.as_deref(), Some("Example Coin"));
+        let meta = loader.load_meta_from_uri("ipfs://QmHash").await?;
+        assert_eq!(meta.symbol.as_deref(), Some("XMPL"));
+        let err = loader
+            .load_meta_from_uri("https://example.com/missing")
+            .await;
+        assert!(matches!(
+            err,
+            Err(MetaLoaderError::LoadMetaDataFailed { status, .. }) if status == StatusCode::NOT_FOUND
+        ));
+
+        let node = IpfsLoader::with_http_client(
+            &IpfsLoaderConfig::ipfs_node("http://node"),
+            http_client.clone(),
+        );
+        let err = node.load("QmHash").await;
+        assert!(matches!(err, Err(IpfsLoaderError::TransportError(_))));
+
+        assert_eq!(
+            *http_client.requests.lock().unwrap(),
+            vec![
+                "GET https://example.com/meta",
+                "GET http://gateway/QmHash",
+                "GET https://example.com/missing",
+                "POST http://node/api/v0/cat?arg=QmHash",
+            ]
+        );
+        Ok(())
+    }
 }
@@ -1,5 +1,7 @@
-use reqwest::StatusCode;
 use thiserror::Error;
+
+use crate::http::{HttpError, StatusCode};
+
 #[derive(Debug, Error)]
 pub enum IpfsLoaderError {
     #[error("Failed to load IPFS object (path: {path}, status: {status}, message: {message})")]
@@ -10,5 +12,5 @@ pub enum IpfsLoaderError {
     },
 
     #[error("Transport error: {0}")]
-    TransportError(#[from] reqwest::Error),
+    TransportError(#[from] HttpError),
 }
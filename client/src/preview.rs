@@ -0,0 +1,523 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use moka::future::Cache;
+use num_bigint::BigUint;
+use serde::{Deserialize, Serialize};
+use tonlib_core::cell::{BagOfCells, Cell, TonCellError};
+use tonlib_core::message::{CommonMsgInfo, MessageBodyKind};
+use tonlib_core::types::{format_units, TON_DECIMALS};
+use tonlib_core::wallet::WalletVersion;
+use tonlib_core::{TonAddress, TonHash};
+
+use crate::client::load_state_init_and_body;
+use crate::contract::{
+    JettonMasterContract, JettonWalletContract, TonContractError, TonContractFactory,
+    TonContractInterface,
+};
+use crate::meta::{JettonMetaLoader, LoadMeta};
+
+/// Send mode flag carrying all remaining balance of the wallet
+pub const SEND_MODE_CARRY_ALL_BALANCE: u8 = 128;
+/// Decimals of jettons which metadata doesn't specify them
+pub const DEFAULT_JETTON_DECIMALS: u8 = 9;
+pub const DEFAULT_JETTON_CACHE_CAPACITY: u64 = 10_000;
+const JETTON_CACHE_TTL: Duration = Duration::from_secs(3600);
+
+/// Jetton transferred by a jetton wallet
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct JettonInfo {
+    pub master: TonAddress,
+    pub symbol: Option<String>,
+    pub decimals: u8,
+}
+
+/// Resolves contracts referenced by messages of a previewed external
+#[async_trait]
+pub trait PreviewResolver: Send + Sync {
+    /// Returns the jetton of `jetton_wallet`
+    async fn jetton_info(&self, jetton_wallet: &TonAddress)
+        -> Result<JettonInfo, TonContractError>;
+
+    /// Returns `true` if `address` is a deployed contract
+    async fn is_active_contract(&self, address: &TonAddress) -> Result<bool, TonContractError>;
+
+    /// Returns `true` if messages with unknown op codes to `address` must be flagged
+    fn is_denylisted(&self, _address: &TonAddress) -> bool {
+        false
+    }
+}
+
+/// `PreviewResolver` querying contracts via `TonContractFactory`.
+///
+/// Jettons are cached, metadata that fails to load leaves the symbol unset
+/// and the decimals at `DEFAULT_JETTON_DECIMALS`.
+pub struct ContractPreviewResolver {
+    factory: TonContractFactory,
+    meta_loader: JettonMetaLoader,
+    denylist: HashSet<TonAddress>,
+    jettons: Cache<TonAddress, JettonInfo>,
+}
+
+impl ContractPreviewResolver {
+    pub fn new(
+        factory: TonContractFactory,
+        meta_loader: JettonMetaLoader,
+    ) -> ContractPreviewResolver {
+        ContractPreviewResolver {
+            factory,
+            meta_loader,
+            denylist: HashSet::new(),
+            jettons: Cache::builder()
+                .max_capacity(DEFAULT_JETTON_CACHE_CAPACITY)
+                .time_to_live(JETTON_CACHE_TTL)
+                .build(),
+        }
+    }
+
+    pub fn with_denylist<I: IntoIterator<Item = TonAddress>>(mut self, denylist: I) -> Self {
+        self.denylist = denylist.into_iter().collect();
+        self
+    }
+}
+
+#[async_trait]
+impl PreviewResolver for ContractPreviewResolver {
+    async fn jetton_info(
+        &self,
+        jetton_wallet: &TonAddress,
+    ) -> Result<JettonInfo, TonContractError> {
+        if let Some(info) = self.jettons.get(jetton_wallet).await {
+            return Ok(info);
+        }
+        let master = self
+            .factory
+            .get_contract(jetton_wallet)
+            .get_wallet_data()
+            .await?
+            .master_address;
+        let data = self.factory.get_contract(&master).get_jetton_data().await?;
+        let (symbol, decimals) = match self.meta_loader.load(&data.content).await {
+            Ok(meta) => (meta.symbol, meta.decimals),
+            Err(e) => {
+                log::warn!("Failed to load metadata of jetton {}: {}", master, e);
+                (None, None)
+            }
+        };
+        let info = JettonInfo {
+            master,
+            symbol,
+            decimals: decimals.unwrap_or(DEFAULT_JETTON_DECIMALS),
+        };
+        self.jettons
+            .insert(jetton_wallet.clone(), info.clone())
+            .await;
+        Ok(info)
+    }
+
+    async fn is_active_contract(&self, address: &TonAddress) -> Result<bool, TonContractError> {
+        let state = self
+            .factory
+            .get_contract(address)
+            .get_account_state()
+            .await?;
+        Ok(!state.code.is_empty())
+    }
+
+    fn is_denylisted(&self, address: &TonAddress) -> bool {
+        self.denylist.contains(address)
+    }
+}
+
+/// What an internal message does, amounts are formatted with their decimals
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ActionKind {
+    TonTransfer {
+        comment: Option<String>,
+    },
+    /// `jetton` is `None` if the jetton wallet couldn't be resolved,
+    /// `amount` is formatted with `DEFAULT_JETTON_DECIMALS` then
+    JettonTransfer {
+        jetton: Option<JettonInfo>,
+        amount: String,
+        recipient: TonAddress,
+    },
+    NftTransfer {
+        new_owner: TonAddress,
+    },
+    /// Message carries a state init, deploying the contract at `address` unless it's active
+    Deploy {
+        address: TonAddress,
+        code_hash: TonHash,
+    },
+    Unknown {
+        opcode: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskFlag {
+    /// Send mode carries all remaining balance of the wallet
+    CarryAllBalance,
+    /// Message to a deployed contract isn't bounced back if the contract fails
+    NonBounceableToActiveContract,
+    /// Message with unknown op code is sent to a denylisted address
+    UnknownOpToDenylistedAddress,
+}
+
+/// Human-readable summary of an internal message sent by a wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionSummary {
+    pub destination: TonAddress,
+    pub send_mode: u8,
+    pub bounce: bool,
+    /// Attached TON, e.g. `1.5`
+    pub ton_amount: String,
+    pub kind: ActionKind,
+    pub risks: Vec<RiskFlag>,
+    /// e.g. `Send 10.5 USDT to EQ...`
+    pub description: String,
+}
+
+/// Summarizes internal messages of an unsigned external body of `version` wallet,
+/// as created by `TonWallet::create_external_body`.
+///
+/// Failed resolutions are logged and leave the related fields unset,
+/// only malformed bodies result in an error.
+pub async fn summarize_external(
+    body: &Cell,
+    version: &WalletVersion,
+    resolver: &dyn PreviewResolver,
+) -> Result<Vec<ActionSummary>, TonCellError> {
+    let mut parser = body.parser();
+    // wallet_id, expire_at and seqno
+    parser.skip_bits(96)?;
+    if version.has_op() {
+        parser.skip_bits(8)?;
+    }
+    let mut summaries = vec![];
+    while parser.remaining_bits() >= 8 {
+        let send_mode = parser.load_u8(8)?;
+        let message = parser.next_reference()?;
+        summaries.push(summarize_message(&message, send_mode, resolver).await?);
+    }
+    Ok(summaries)
+}
+
+/// Same as `summarize_external`, for a serialized body
+pub async fn summarize_external_boc(
+    boc: &[u8],
+    version: &WalletVersion,
+    resolver: &dyn PreviewResolver,
+) -> Result<Vec<ActionSummary>, TonCellError> {
+    let root = BagOfCells::parse(boc)?.single_root()?.clone();
+    summarize_external(&root, version, resolver).await
+}
+
+async fn summarize_message(
+    message: &Cell,
+    send_mode: u8,
+    resolver: &dyn PreviewResolver,
+) -> Result<ActionSummary, TonCellError> {
+    let mut parser = message.parser();
+    let info = match CommonMsgInfo::parse(&mut parser)? {
+        CommonMsgInfo::InternalMessage(info) => info,
+        _ => {
+            return Err(TonCellError::InvalidCellData(
+                "Wallet can send internal messages only".to_string(),
+            ))
+        }
+    };
+    let (state_init, body) = load_state_init_and_body(&mut parser)?;
+    let ton_amount = format_units(&info.value, TON_DECIMALS);
+    let destination = info.dest;
+
+    let (kind, description) = match state_init {
+        Some((code, _)) => (
+            ActionKind::Deploy {
+                address: destination.clone(),
+                code_hash: code.cell_hash(),
+            },
+            format!("Deploy contract {} with {} TON", destination, ton_amount),
+        ),
+        None => summarize_body(&body, &destination, &ton_amount, resolver).await,
+    };
+
+    let mut risks = vec![];
+    if send_mode & SEND_MODE_CARRY_ALL_BALANCE != 0 {
+        risks.push(RiskFlag::CarryAllBalance);
+    }
+    if !info.bounce {
+        match resolver.is_active_contract(&destination).await {
+            Ok(true) => risks.push(RiskFlag::NonBounceableToActiveContract),
+            Ok(false) => {}
+            Err(e) => log::warn!("Failed to get state of {}: {}", destination, e),
+        }
+    }
+    if matches!(kind, ActionKind::Unknown { .. }) && resolver.is_denylisted(&destination) {
+        risks.push(RiskFlag::UnknownOpToDenylistedAddress);
+    }
+
+    Ok(ActionSummary {
+        destination,
+        send_mode,
+        bounce: info.bounce,
+        ton_amount,
+        kind,
+        risks,
+        description,
+    })
+}
+
+/// Describes the action of an internal message by its body
+async fn summarize_body(
+    body: &Cell,
+    destination: &TonAddress,
+    ton_amount: &str,
+    resolver: &dyn PreviewResolver,
+) -> (ActionKind, String) {
+    match MessageBodyKind::classify(body) {
+        MessageBodyKind::Empty => (
+            ActionKind::TonTransfer { comment: None },
+            format!("Send {} TON to {}", ton_amount, destination),
+        ),
+        MessageBodyKind::Comment(comment) => {
+            let description = format!(
+                "Send {} TON to {} with comment {:?}",
+                ton_amount, destination, comment
+            );
+            let kind = ActionKind::TonTransfer {
+                comment: Some(comment),
+            };
+            (kind, description)
+        }
+        MessageBodyKind::JettonTransfer(transfer) => {
+            let jetton = match resolver.jetton_info(destination).await {
+                Ok(jetton) => Some(jetton),
+                Err(e) => {
+                    log::warn!("Failed to resolve jetton wallet {}: {}", destination, e);
+                    None
+                }
+            };
+            let (amount, symbol) = format_jetton_amount(&transfer.amount, jetton.as_ref());
+            let description = format!("Send {} {} to {}", amount, symbol, transfer.destination);
+            let kind = ActionKind::JettonTransfer {
+                jetton,
+                amount,
+                recipient: transfer.destination,
+            };
+            (kind, description)
+        }
+        MessageBodyKind::NftTransfer(transfer) => (
+            ActionKind::NftTransfer {
+                new_owner: transfer.new_owner.clone(),
+            },
+            format!("Transfer NFT {} to {}", destination, transfer.new_owner),
+        ),
+        // notifications are sent by contracts, a wallet has no reason to send them
+        other => {
+            let opcode = other.opcode().unwrap_or_default();
+            (
+                ActionKind::Unknown { opcode },
+                format!(
+                    "Send {} TON to {} with op {:#010x}",
+                    ton_amount, destination, opcode
+                ),
+            )
+        }
+    }
+}
+
+fn format_jetton_amount(amount: &BigUint, jetton: Option<&JettonInfo>) -> (String, String) {
+    let decimals = jetton.map_or(DEFAULT_JETTON_DECIMALS, |jetton| jetton.decimals);
+    let symbol = jetton
+        .and_then(|jetton| jetton.symbol.clone())
+        .unwrap_or_else(|| "jettons".to_string());
+    (format_units(amount, decimals as u32), symbol)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonlib_core::cell::{CellBuilder, StateInitBuilder};
+    use tonlib_core::message::{
+        InternalMessage, JettonTransferMessage, TonMessage, TransferMessage, TEXT_COMMENT,
+    };
+
+    use super::*;
+
+    const JETTON_WALLET: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x11; 32],
+    };
+    const RECIPIENT: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x22; 32],
+    };
+    const DENYLISTED: TonAddress = TonAddress {
+        workchain: 0,
+        hash_part: [0x33; 32],
+    };
+
+    struct MockResolver;
+
+    #[async_trait]
+    impl PreviewResolver for MockResolver {
+        async fn jetton_info(
+            &self,
+            _jetton_wallet: &TonAddress,
+        ) -> Result<JettonInfo, TonContractError> {
+            Ok(JettonInfo {
+                master: TonAddress::new(0, &[0x44; 32]),
+                symbol: Some("USDT".to_string()),
+                decimals: 6,
+            })
+        }
+
+        async fn is_active_contract(&self, address: &TonAddress) -> Result<bool, TonContractError> {
+            Ok(address != &RECIPIENT)
+        }
+
+        fn is_denylisted(&self, address: &TonAddress) -> bool {
+            address == &DENYLISTED
+        }
+    }
+
+    fn message(dest: &TonAddress, value: u64, bounce: bool, body: Cell) -> anyhow::Result<Cell> {
+        let info = CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce,
+            bounced: false,
+            src: TonAddress::NULL,
+            dest: dest.clone(),
+            value: BigUint::from(value),
+            ihr_fee: BigUint::from(0u32),
+            fwd_fee: BigUint::from(0u32),
+            created_lt: 0,
+            created_at: 0,
+        });
+        Ok(TransferMessage::new(info)
+            .with_data(Arc::new(body))
+            .build()?)
+    }
+
+    #[tokio::test]
+    async fn test_summarize_external() -> anyhow::Result<()> {
+        let comment = CellBuilder::new()
+            .store_u32(32, TEXT_COMMENT)?
+            .store_string("thanks")?
+            .build()?;
+        // non-bounceable, but the recipient isn't deployed
+        let ton_transfer = message(&RECIPIENT, 1_500_000_000, false, comment)?;
+        let jetton_transfer = message(
+            &JETTON_WALLET,
+            50_000_000,
+            true,
+            JettonTransferMessage::new(&RECIPIENT, &BigUint::from(10_500_000u64)).build()?,
+        )?;
+        let unknown_op = CellBuilder::new().store_u32(32, 0xdeadbeef)?.build()?;
+        let call = message(&DENYLISTED, 100_000_000, true, unknown_op)?;
+
+        let body = CellBuilder::new()
+            .store_i32(32, 698983191)?
+            .store_u32(32, u32::MAX)?
+            .store_u32(32, 7)?
+            .store_u8(8, 0)?
+            .store_u8(8, 3)?
+            .store_child(ton_transfer)?
+            .store_u8(8, 3)?
+            .store_child(jetton_transfer)?
+            .store_u8(8, 3)?
+            .store_child(call)?
+            .build()?;
+        let summaries = summarize_external(&body, &WalletVersion::V4R2, &MockResolver).await?;
+        assert_eq!(summaries.len(), 3);
+
+        assert_eq!(summaries[0].ton_amount, "1.5");
+        assert_eq!(
+            summaries[0].kind,
+            ActionKind::TonTransfer {
+                comment: Some("thanks".to_string())
+            }
+        );
+        match &summaries[1].kind {
+            ActionKind::JettonTransfer {
+                jetton,
+                amount,
+                recipient,
+            } => {
+                assert_eq!(jetton.as_ref().unwrap().symbol.as_deref(), Some("USDT"));
+                assert_eq!(amount, "10.5");
+                assert_eq!(recipient, &RECIPIENT);
+            }
+            kind => panic!("Unexpected action: {:?}", kind),
+        }
+        assert!(summaries[1].description.starts_with("Send 10.5 USDT to "));
+        assert_eq!(
+            summaries[2].kind,
+            ActionKind::Unknown { opcode: 0xdeadbeef }
+        );
+
+        let risks: Vec<_> = summaries.iter().flat_map(|s| s.risks.clone()).collect();
+        assert_eq!(risks, vec![RiskFlag::UnknownOpToDenylistedAddress]);
+
+        let json = serde_json::to_value(&summaries[1])?;
+        assert_eq!(json["kind"]["type"], "jetton_transfer");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_summarize_deploy() -> anyhow::Result<()> {
+        let code = Arc::new(CellBuilder::new().store_u32(32, 0xc0de)?.build()?);
+        let data = Arc::new(CellBuilder::new().store_u32(32, 0xda7a)?.build()?);
+        let state_init = StateInitBuilder::new(&code, &data).build()?;
+        // header of a message without state init and body, followed by the state init
+        let header = message(&RECIPIENT, 50_000_000, false, Cell::default())?;
+        let with_header = |builder: &mut CellBuilder| -> anyhow::Result<()> {
+            builder
+                .store_bits(header.bit_len() - 2, header.data())?
+                .store_bit(true)?;
+            Ok(())
+        };
+        let mut inline = CellBuilder::new();
+        with_header(&mut inline)?;
+        inline
+            .store_bit(false)?
+            .store_cell(&state_init)?
+            .store_bit(false)?;
+        let mut by_ref = CellBuilder::new();
+        with_header(&mut by_ref)?;
+        by_ref
+            .store_bit(true)?
+            .store_child(state_init)?
+            .store_bit(false)?;
+
+        let body = CellBuilder::new()
+            .store_i32(32, 698983191)?
+            .store_u32(32, u32::MAX)?
+            .store_u32(32, 7)?
+            .store_u8(8, 0)?
+            .store_u8(8, 3)?
+            .store_child(inline.build()?)?
+            .store_u8(8, 3)?
+            .store_child(by_ref.build()?)?
+            .build()?;
+        let summaries = summarize_external(&body, &WalletVersion::V4R2, &MockResolver).await?;
+        assert_eq!(summaries.len(), 2);
+        for summary in summaries {
+            assert_eq!(
+                summary.kind,
+                ActionKind::Deploy {
+                    address: RECIPIENT,
+                    code_hash: code.cell_hash(),
+                }
+            );
+            assert_eq!(summary.ton_amount, "0.05");
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+//! Async runtime touch points of the client.
+//!
+//! Timers and tasks are provided by tokio (`runtime-tokio`, default) or by async-std
+//! (`runtime-async-std`, which takes precedence if both features are enabled).
+//!
+//! Synchronization primitives and channels are those of `tokio::sync` on both runtimes:
+//! they don't depend on the tokio runtime and work with any executor. This also keeps
+//! the bridging of tonlib worker threads (`std::thread` + `oneshot` channels) the same.
+
+#[cfg(not(any(feature = "runtime-tokio", feature = "runtime-async-std")))]
+compile_error!(
+    "tonlib-client requires either the `runtime-tokio` or the `runtime-async-std` feature"
+);
+
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+
+pub(crate) use tokio::sync::{
+    broadcast, mpsc, oneshot, watch, Mutex, OnceCell, Semaphore, SemaphorePermit,
+};
+
+/// Error of `timeout` when the future didn't complete in time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Elapsed;
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl std::error::Error for Elapsed {}
+
+/// Error of a blocking task which panicked or was cancelled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JoinError(String);
+
+impl fmt::Display for JoinError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[cfg(feature = "runtime-async-std")]
+mod imp {
+    use super::*;
+
+    pub(crate) async fn sleep(duration: Duration) {
+        async_std::task::sleep(duration).await
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        async_std::future::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed)
+    }
+
+    pub(crate) fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        async_std::task::spawn(future);
+    }
+
+    pub(crate) fn spawn_blocking<F, R>(f: F) -> impl Future<Output = Result<R, JoinError>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        // a panic of the task is propagated to the caller by async-std
+        let handle = async_std::task::spawn_blocking(f);
+        async move { Ok(handle.await) }
+    }
+}
+
+#[cfg(not(feature = "runtime-async-std"))]
+mod imp {
+    use super::*;
+
+    pub(crate) async fn sleep(duration: Duration) {
+        tokio::time::sleep(duration).await
+    }
+
+    pub(crate) async fn timeout<F: Future>(
+        duration: Duration,
+        future: F,
+    ) -> Result<F::Output, Elapsed> {
+        tokio::time::timeout(duration, future)
+            .await
+            .map_err(|_| Elapsed)
+    }
+
+    pub(crate) fn spawn<F>(future: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        tokio::spawn(future);
+    }
+
+    pub(crate) fn spawn_blocking<F, R>(f: F) -> impl Future<Output = Result<R, JoinError>>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let handle = tokio::task::spawn_blocking(f);
+        async move { handle.await.map_err(|e| JoinError(e.to_string())) }
+    }
+}
+
+/// Waits until `duration` has elapsed
+pub(crate) async fn sleep(duration: Duration) {
+    imp::sleep(duration).await
+}
+
+/// Requires `future` to complete within `duration`
+pub(crate) async fn timeout<F: Future>(
+    duration: Duration,
+    future: F,
+) -> Result<F::Output, Elapsed> {
+    imp::timeout(duration, future).await
+}
+
+/// Runs `future` in the background, detached
+pub(crate) fn spawn<F>(future: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    imp::spawn(future)
+}
+
+/// Runs the blocking `f` on a thread pool of the runtime.
+///
+/// The task starts immediately, dropping the returned future detaches it.
+pub(crate) fn spawn_blocking<F, R>(f: F) -> impl Future<Output = Result<R, JoinError>>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    imp::spawn_blocking(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_timeout() {
+        assert_eq!(timeout(Duration::from_secs(5), async { 1 }).await, Ok(1));
+        let stalled = sleep(Duration::from_secs(60));
+        assert_eq!(
+            timeout(Duration::from_millis(10), stalled).await,
+            Err(Elapsed)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_spawn() {
+        let (sender, receiver) = oneshot::channel();
+        spawn(async move {
+            sleep(Duration::from_millis(1)).await;
+            let _ = sender.send(42);
+        });
+        assert_eq!(receiver.await, Ok(42));
+        assert_eq!(spawn_blocking(|| 2 + 2).await, Ok(4));
+    }
+}
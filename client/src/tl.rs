@@ -19,6 +19,10 @@ use tonlib_sys::*;
 pub use types::*;
 
 use self::serial::*;
+pub(crate) use self::serial::{
+    deserialize_error_extra, deserialize_header, deserialize_response, deserialize_result_bytes,
+    ResponseHeader,
+};
 
 base64_serde_type!(Base64Standard, STANDARD);
 
@@ -96,6 +100,26 @@ impl TlTonClient {
         }
     }
 
+    /// Returns the next response as received from tonlib (JSON), without deserializing it.
+    pub fn receive_bytes(&self, timeout: f64) -> Option<Vec<u8>> {
+        let c_str = unsafe { tonlib_client_json_receive(self.ptr, timeout) };
+        if c_str.is_null() {
+            None
+        } else {
+            let c_str_slice = unsafe { CStr::from_ptr(c_str) };
+            if log::log_enabled!(log::Level::Trace) {
+                log::trace!(
+                    "[{}] receive: {}",
+                    self.tag,
+                    c_str_slice
+                        .to_str()
+                        .unwrap_or("<Error decoding string as UTF-8>")
+                );
+            }
+            Some(c_str_slice.to_bytes().to_vec())
+        }
+    }
+
     pub fn set_log_verbosity_level(verbosity_level: u32) {
         unsafe { tonlib_sys::tonlib_client_set_verbosity_level(verbosity_level) }
     }
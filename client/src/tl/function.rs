@@ -104,12 +104,37 @@ pub enum TonFunction {
         body: Vec<u8>,
     },
 
+    // tonlib_api.tl, line 271
+    #[serde(rename = "raw.createQuery")]
+    RawCreateQuery {
+        destination: AccountAddress,
+        #[serde(with = "Base64Standard")]
+        init_code: Vec<u8>,
+        #[serde(with = "Base64Standard")]
+        init_data: Vec<u8>,
+        #[serde(with = "Base64Standard")]
+        body: Vec<u8>,
+    },
+
     // tonlib_api.tl, line 288
     #[serde(rename = "getAccountState")]
     GetAccountState {
         account_address: AccountAddress,
     },
 
+    // tonlib_api.tl, line 291
+    #[serde(rename = "query.forget")]
+    QueryForget {
+        id: i64,
+    },
+
+    // tonlib_api.tl, line 292
+    #[serde(rename = "query.estimateFees")]
+    QueryEstimateFees {
+        id: i64,
+        ignore_chksig: bool,
+    },
+
     // tonlib_api.tl, line 294
     #[serde(rename = "getConfigParam")]
     GetConfigParam {
@@ -227,6 +252,13 @@ pub enum TonFunction {
     #[serde(rename = "liteServer.getInfo")]
     LiteServerGetInfo {},
 
+    // tonlib_api.tl, line 345
+    #[serde(rename = "withBlock")]
+    WithBlock {
+        id: BlockIdExt,
+        function: Box<TonFunction>,
+    },
+
     // tonlib_api.tl, line 352
     SetLogVerbosityLevel {
         new_verbosity_level: u32,
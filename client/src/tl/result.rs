@@ -8,8 +8,8 @@ use crate::tl::stack::TvmCell;
 use crate::tl::types::{
     BlockIdExt, BlocksHeader, BlocksMasterchainInfo, BlocksShards, BlocksTransactions,
     BlocksTransactionsExt, ConfigInfo, FullAccountState, LiteServerInfo, LogVerbosityLevel,
-    OptionsInfo, RawExtMessageInfo, RawFullAccountState, RawTransactions, SmcInfo,
-    SmcLibraryResult, SmcLibraryResultExt, SmcRunResult, UpdateSyncState,
+    OptionsInfo, QueryFees, QueryInfo, RawExtMessageInfo, RawFullAccountState, RawTransactions,
+    SmcInfo, SmcLibraryResult, SmcLibraryResultExt, SmcRunResult, UpdateSyncState,
 };
 
 #[derive(
@@ -46,6 +46,12 @@ pub enum TonResult {
     // tonlib_api.tl, line 167
     #[serde(rename = "tvm.cell")]
     TvmCell(TvmCell),
+    // tonlib_api.tl, line 175
+    #[serde(rename = "query.fees")]
+    QueryFees(QueryFees),
+    // tonlib_api.tl, line 177
+    #[serde(rename = "query.info")]
+    QueryInfo(QueryInfo),
     // tonlib_api.tl, line 179
     #[serde(rename = "smc.info")]
     SmcInfo(SmcInfo),
@@ -143,6 +149,12 @@ impl fmt::Display for TonResult {
                 full_account_state.address.account_address
             ),
 
+            TonResult::QueryFees(_) => write!(f, "TonResult::QueryFees"),
+
+            TonResult::QueryInfo(query_info) => {
+                write!(f, "TonResult::QueryInfo: id {}", query_info.id)
+            }
+
             TonResult::SmcInfo(_) => write!(f, "TonResult::SmcInfo"),
 
             TonResult::SmcRunResult(smc_run_result) => {
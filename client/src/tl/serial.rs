@@ -1,6 +1,7 @@
 use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
+use serde::Deserialize;
 use serde_json::Value;
 
 use crate::tl::function::TonFunction;
@@ -39,27 +40,59 @@ pub(crate) unsafe fn deserialize_result_extra(
     c_str: *const c_char,
 ) -> (Result<TonResult, TlError>, Option<String>) {
     let cstr = CStr::from_ptr(c_str);
-    // TODO: Optimize to avoid copying
-    let str_result = cstr.to_str();
-    if let Err(err) = str_result {
+    if let Err(err) = cstr.to_str() {
         return (Err(TlError::Utf8Error(err)), None);
     }
-    let str = str_result.unwrap();
-    let value_result: Result<Value, serde_json::Error> = serde_json::from_str(str);
-    if let Err(err) = value_result {
-        return (Err(TlError::SerdeJsonError(err)), None);
-    }
-    let value = value_result.unwrap();
-    let extra: Option<String> = value
-        .as_object()
-        .and_then(|m| m.get("@extra"))
+    deserialize_response(cstr.to_bytes())
+}
+
+/// Deserializes a response in a single pass over its text, also returning its `@extra` tag
+pub(crate) fn deserialize_response(bytes: &[u8]) -> (Result<TonResult, TlError>, Option<String>) {
+    let value: Value = match serde_json::from_slice(bytes) {
+        Ok(value) => value,
+        Err(err) => return (Err(TlError::SerdeJsonError(err)), None),
+    };
+    let extra = value
+        .get("@extra")
         .and_then(|v| v.as_str())
         .map(|s| s.to_string());
-    let result: Result<TonResult, TlError> =
-        serde_json::from_value(value).map_err(TlError::SerdeJsonError);
+    let result = serde_json::from_value(value).map_err(TlError::SerdeJsonError);
     (result, extra)
 }
 
+/// Service fields of a tonlib response, read without deserializing the rest of it
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ResponseHeader {
+    #[serde(rename = "@extra")]
+    pub extra: Option<String>,
+    #[serde(rename = "@type")]
+    pub result_type: Option<String>,
+}
+
+impl ResponseHeader {
+    pub fn is_error(&self) -> bool {
+        self.result_type.as_deref() == Some("error")
+    }
+}
+
+pub(crate) fn deserialize_header(bytes: &[u8]) -> Result<ResponseHeader, TlError> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+pub(crate) fn deserialize_result_bytes(bytes: &[u8]) -> Result<TonResult, TlError> {
+    Ok(serde_json::from_slice(bytes)?)
+}
+
+/// Fields of an error response other than its type, code, message & `@extra` tag,
+/// `None` if there are none
+pub(crate) fn deserialize_error_extra(bytes: &[u8]) -> Option<Value> {
+    let mut fields: serde_json::Map<String, Value> = serde_json::from_slice(bytes).ok()?;
+    for key in ["@type", "@extra", "code", "message"] {
+        fields.remove(key);
+    }
+    (!fields.is_empty()).then_some(Value::Object(fields))
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -67,7 +100,10 @@ mod tests {
 
     use crate::tl::function::TonFunction;
     use crate::tl::result::TonResult;
-    use crate::tl::serial::{deserialize_result_extra, serialize_function_extra};
+    use crate::tl::serial::{
+        deserialize_error_extra, deserialize_header, deserialize_response,
+        deserialize_result_extra, serialize_function_extra,
+    };
 
     #[test]
     fn it_serializes_function_extra() {
@@ -102,4 +138,46 @@ mod tests {
         let (_, extra) = unsafe { deserialize_result_extra(cstr.as_ptr()) };
         assert_eq!(extra, Some(String::from("0")));
     }
+
+    #[test]
+    fn it_deserializes_response() {
+        let (result, extra) = deserialize_response(br#"{"@type":"ok","@extra":"5"}"#);
+        assert_eq!(result.unwrap(), TonResult::Ok {});
+        assert_eq!(extra, Some(String::from("5")));
+
+        let (result, extra) = deserialize_response(b"{");
+        assert!(result.is_err());
+        assert_eq!(extra, None);
+    }
+
+    #[test]
+    fn it_deserializes_header() {
+        let header =
+            deserialize_header(br#"{"@type":"raw.fullAccountState","code":"te6c","@extra":"7"}"#)
+                .unwrap();
+        assert_eq!(header.extra, Some(String::from("7")));
+        assert!(!header.is_error());
+
+        let header =
+            deserialize_header(br#"{"@type":"error","code":500,"message":"LITE_SERVER_NETWORK"}"#)
+                .unwrap();
+        assert_eq!(header.extra, None);
+        assert!(header.is_error());
+    }
+
+    #[test]
+    fn it_deserializes_error_extra() {
+        let extra = deserialize_error_extra(
+            br#"{"@type":"error","code":500,"message":"LITE_SERVER_UNKNOWN","@extra":"3","details":{"shard":"8000000000000000"}}"#,
+        );
+        assert_eq!(
+            extra,
+            Some(serde_json::json!({"details": {"shard": "8000000000000000"}}))
+        );
+
+        let extra = deserialize_error_extra(
+            br#"{"@type":"error","code":500,"message":"LITE_SERVER_NETWORK","@extra":"3"}"#,
+        );
+        assert_eq!(extra, None);
+    }
 }
@@ -5,6 +5,7 @@ use std::hash::Hash;
 
 use num_bigint::{BigInt, BigUint};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_aux::prelude::deserialize_string_from_number;
 use tonlib_core::cell::dict::{KeyReader, ValReader};
 use tonlib_core::cell::BagOfCells;
 use tonlib_core::TonAddress;
@@ -57,6 +58,8 @@ impl Debug for TvmCell {
 // tonlib_api.tl, line 168
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TvmNumber {
+    /// Decimal representation of up to 257-bit signed integer
+    #[serde(deserialize_with = "deserialize_string_from_number")]
     pub number: String,
 }
 
@@ -333,4 +336,17 @@ mod tests {
         assert_eq!(stack.elements.len(), 1);
         assert_eq!(100500, stack.get_i32(0).unwrap());
     }
+
+    #[test]
+    fn deserialize_int257_works() {
+        let min_int257 =
+            "-115792089237316195423570985008687907853269984665640564039457584007913129639936";
+        let serial = format!(
+            r#"[{{"@type":"tvm.stackEntryNumber","number":{{"number":"{}"}}}},{{"@type":"tvm.stackEntryNumber","number":{{"number":42}}}}]"#,
+            min_int257
+        );
+        let stack: TvmStack = serde_json::from_str(&serial).unwrap();
+        assert_eq!(stack.get_bigint(0).unwrap().to_string(), min_int257);
+        assert_eq!(stack.get_i64(1).unwrap(), 42);
+    }
 }
@@ -396,6 +396,41 @@ pub enum MsgData {
     },
 }
 
+// tonlib_api.tl, line 174
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Fees {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub in_fwd_fee: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub storage_fee: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub gas_fee: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub fwd_fee: i64,
+}
+
+// tonlib_api.tl, line 175
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryFees {
+    pub source_fees: Fees,
+    pub destination_fees: Vec<Fees>,
+}
+
+// tonlib_api.tl, line 177
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct QueryInfo {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub id: i64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub valid_until: i64,
+    #[serde(with = "Base64Standard")]
+    pub body_hash: Vec<u8>,
+    #[serde(with = "Base64Standard")]
+    pub body: Vec<u8>,
+    #[serde(with = "Base64Standard")]
+    pub init_state: Vec<u8>,
+}
+
 // tonlib_api.tl, line 179
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SmcInfo {
@@ -475,11 +510,11 @@ pub struct LogVerbosityLevel {
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct LiteServerInfo {
     #[serde(deserialize_with = "deserialize_number_from_string")]
-    now: i64,
+    pub now: i64,
     #[serde(deserialize_with = "deserialize_number_from_string")]
-    version: i32,
+    pub version: i32,
     #[serde(deserialize_with = "deserialize_number_from_string")]
-    capabilities: i64,
+    pub capabilities: i64,
 }
 
 // tonlib_api.tl, line 219
@@ -16,6 +16,9 @@ pub enum StackParseError {
     #[error("Invalid stack size({0})")]
     InvalidStackSize(usize),
 
+    #[error("Stack underflow{{index: {index}, len: {len}}}")]
+    StackUnderflow { index: usize, len: usize },
+
     #[error("Invalid stack entry({0})")]
     InvalidEntryValue(String),
 
@@ -1,8 +1,10 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::hash::Hash;
 use std::sync::Arc;
 
 use num_bigint::{BigInt, BigUint};
+use num_traits::ToPrimitive;
 use strum::Display;
 use tonlib_core::cell::dict::{KeyReader, ValReader};
 use tonlib_core::cell::{ArcCell, BagOfCells, Cell, CellBuilder, CellSlice};
@@ -79,6 +81,33 @@ impl TvmStackEntry {
         }
     }
 
+    /// Returns the number if it fits into `i128`, `None` if it doesn't or the entry isn't a number
+    pub fn as_i128(&self) -> Option<i128> {
+        match self {
+            TvmStackEntry::Int64(number) => Some(*number as i128),
+            TvmStackEntry::Int257(number) => number.to_i128(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number if it fits into `u128`, `None` if it doesn't or the entry isn't a number
+    pub fn as_u128(&self) -> Option<u128> {
+        match self {
+            TvmStackEntry::Int64(number) => u128::try_from(*number).ok(),
+            TvmStackEntry::Int257(number) => number.to_u128(),
+            _ => None,
+        }
+    }
+
+    /// Returns the number of `Int64` or `Int257` entry, borrowing the latter without copying.
+    pub fn as_bigint(&self) -> Option<Cow<'_, BigInt>> {
+        match self {
+            TvmStackEntry::Int64(number) => Some(Cow::Owned(BigInt::from(*number))),
+            TvmStackEntry::Int257(number) => Some(Cow::Borrowed(number)),
+            _ => None,
+        }
+    }
+
     pub fn get_biguint(&self) -> Result<BigUint, StackParseError> {
         self.get_bigint()?
             .try_into()
@@ -279,3 +308,45 @@ impl TryFrom<&TlTvmStackEntry> for TvmStackEntry {
         Ok(entry)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_number(number: &str) -> Result<TvmStackEntry, StackParseError> {
+        let entry = TlTvmStackEntry::Number {
+            number: TvmNumber {
+                number: number.to_string(),
+            },
+        };
+        TvmStackEntry::try_from(&entry)
+    }
+
+    #[test]
+    fn test_parse_int257() -> anyhow::Result<()> {
+        let max_int257: BigInt = (BigInt::from(1u8) << 256u32) - 1;
+        let entry = parse_number(&max_int257.to_string())?;
+        assert_eq!(entry.as_bigint().as_deref(), Some(&max_int257));
+        assert_eq!(entry.as_i128(), None);
+        assert_eq!(entry.as_u128(), None);
+        assert_eq!(entry.get_biguint()?, BigUint::try_from(max_int257)?);
+
+        let entry = parse_number(&i128::MIN.to_string())?;
+        assert_eq!(entry.as_i128(), Some(i128::MIN));
+        assert_eq!(entry.as_u128(), None);
+
+        let entry = parse_number(&u128::MAX.to_string())?;
+        assert_eq!(entry.as_u128(), Some(u128::MAX));
+        assert_eq!(entry.as_i128(), None);
+
+        assert_eq!(TvmStackEntry::Int64(-5).as_i128(), Some(-5));
+        assert_eq!(TvmStackEntry::Int64(-5).as_u128(), None);
+        assert_eq!(
+            TvmStackEntry::Int64(-5).as_bigint().as_deref(),
+            Some(&BigInt::from(-5))
+        );
+        assert_eq!(TvmStackEntry::Null.as_i128(), None);
+        assert!(parse_number("0x10").is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use num_bigint::{BigInt, BigUint};
+use tonlib_core::cell::ArcCell;
+use tonlib_core::TonAddress;
+
+use crate::types::{StackParseError, TvmStackEntry};
+
+/// Reads get-method results one by one, starting from `stack[0]`.
+///
+/// Popping from an exhausted stack fails with `StackParseError::StackUnderflow`, while an entry
+/// of unexpected type fails with `StackParseError::InvalidEntryType`, so callers can treat
+/// an empty result of a method as valid.
+#[derive(Debug, Clone)]
+pub struct TvmStackParser<'a> {
+    stack: &'a [TvmStackEntry],
+    position: usize,
+}
+
+impl<'a> TvmStackParser<'a> {
+    pub fn new(stack: &'a [TvmStackEntry]) -> TvmStackParser<'a> {
+        TvmStackParser { stack, position: 0 }
+    }
+
+    /// Number of entries not read yet
+    pub fn remaining(&self) -> usize {
+        self.stack.len() - self.position
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.remaining() == 0
+    }
+
+    /// Returns the next entry, `None` if the stack is exhausted.
+    pub fn try_pop_entry(&mut self) -> Option<&'a TvmStackEntry> {
+        let entry = self.stack.get(self.position)?;
+        self.position += 1;
+        Some(entry)
+    }
+
+    pub fn pop_entry(&mut self) -> Result<&'a TvmStackEntry, StackParseError> {
+        let index = self.position;
+        self.try_pop_entry().ok_or(StackParseError::StackUnderflow {
+            index,
+            len: self.stack.len(),
+        })
+    }
+
+    pub fn pop_bool(&mut self) -> Result<bool, StackParseError> {
+        self.pop_entry()?.get_bool()
+    }
+
+    pub fn pop_i64(&mut self) -> Result<i64, StackParseError> {
+        self.pop_entry()?.get_i64()
+    }
+
+    pub fn pop_bigint(&mut self) -> Result<BigInt, StackParseError> {
+        self.pop_entry()?.get_bigint()
+    }
+
+    pub fn pop_biguint(&mut self) -> Result<BigUint, StackParseError> {
+        self.pop_entry()?.get_biguint()
+    }
+
+    pub fn pop_cell(&mut self) -> Result<ArcCell, StackParseError> {
+        self.pop_entry()?.get_cell()
+    }
+
+    pub fn pop_address(&mut self) -> Result<TonAddress, StackParseError> {
+        self.pop_entry()?.get_address()
+    }
+
+    pub fn pop_string(&mut self) -> Result<String, StackParseError> {
+        self.pop_entry()?.get_string()
+    }
+
+    /// Fails with `StackParseError::InvalidStackSize` if some entries are not read.
+    pub fn ensure_empty(&self) -> Result<(), StackParseError> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(StackParseError::InvalidStackSize(self.stack.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_underflow_and_type_mismatch() -> anyhow::Result<()> {
+        let stack = vec![TvmStackEntry::Int64(-1), TvmStackEntry::Int64(42)];
+        let mut parser = TvmStackParser::new(&stack);
+        assert!(parser.ensure_empty().is_err());
+        assert!(parser.pop_bool()?);
+        assert!(matches!(
+            parser.clone().pop_cell(),
+            Err(StackParseError::InvalidEntryType { .. })
+        ));
+        assert_eq!(parser.pop_i64()?, 42);
+        assert!(parser.is_empty());
+        parser.ensure_empty()?;
+        assert!(matches!(
+            parser.pop_biguint(),
+            Err(StackParseError::StackUnderflow { index: 2, len: 2 })
+        ));
+        assert_eq!(parser.try_pop_entry(), None);
+
+        let mut parser = TvmStackParser::new(&[]);
+        assert!(matches!(
+            parser.pop_address(),
+            Err(StackParseError::StackUnderflow { index: 0, len: 0 })
+        ));
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
-use tonlib_core::cell::ArcCell;
+use tonlib_core::cell::{ArcCell, Cell, TonCellError};
+use tonlib_core::message::OutAction;
 
 use crate::types::TvmStackEntry;
 
@@ -34,3 +35,76 @@ pub struct TvmMsgSuccess {
     pub gas_used: i32,
     pub actions: Option<ArcCell>,
 }
+
+impl TvmMsgSuccess {
+    /// Returns messages sent by `action_send_msg` actions, in order of sending
+    pub fn out_messages(&self) -> Result<Vec<Cell>, TonCellError> {
+        let actions = match &self.actions {
+            Some(actions) => OutAction::parse_list(actions)?,
+            None => return Ok(Vec::new()),
+        };
+        let messages = actions
+            .into_iter()
+            .filter_map(|action| match action {
+                OutAction::SendMessage { message, .. } => Some(message.as_ref().clone()),
+                _ => None,
+            })
+            .collect();
+        Ok(messages)
+    }
+}
+
+/// Result of `TonClient::execute_tvm`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TvmExecutionResult {
+    pub exit_code: i32,
+    pub gas_used: i32,
+    pub out_messages: Vec<Cell>,
+    pub new_data: Cell,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tonlib_core::cell::CellBuilder;
+    use tonlib_core::message::{ACTION_RESERVE_CURRENCY, ACTION_SEND_MSG};
+
+    use super::*;
+
+    #[test]
+    fn test_out_messages() -> anyhow::Result<()> {
+        let message =
+            |n: u32| -> Result<Cell, TonCellError> { CellBuilder::new().store_u32(32, n)?.build() };
+        let send_msg = |prev: Cell, n: u32| -> Result<Cell, TonCellError> {
+            CellBuilder::new()
+                .store_child(prev)?
+                .store_u32(32, ACTION_SEND_MSG)?
+                .store_u8(8, 3)?
+                .store_child(message(n)?)?
+                .build()
+        };
+        let reserve = |prev: Cell| -> Result<Cell, TonCellError> {
+            CellBuilder::new()
+                .store_child(prev)?
+                .store_u32(32, ACTION_RESERVE_CURRENCY)?
+                .store_u8(8, 0)?
+                .store_coins(&0u32.into())?
+                .store_bit(false)?
+                .build()
+        };
+        let actions = send_msg(reserve(send_msg(Cell::default(), 1)?)?, 2)?;
+        let success = TvmMsgSuccess {
+            new_code: Arc::new(Cell::default()),
+            new_data: Arc::new(Cell::default()),
+            accepted: true,
+            vm_exit_code: 0,
+            vm_log: None,
+            missing_library: None,
+            gas_used: 0,
+            actions: Some(Arc::new(actions)),
+        };
+        assert_eq!(success.out_messages()?, vec![message(1)?, message(2)?]);
+        Ok(())
+    }
+}
@@ -0,0 +1,679 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tonlib_core::cell::BagOfCells;
+use tonlib_core::types::Transaction;
+use tonlib_core::TonAddress;
+
+use crate::client::{
+    AccountHistorySyncer, ChainBreak, SyncReport, TonClientError, TonClientInterface,
+};
+use crate::http::{HttpClient, ReqwestHttpClient};
+use crate::rt;
+
+/// Version of `WebhookPayload` schema, incremented on incompatible changes
+pub const WEBHOOK_PAYLOAD_VERSION: u32 = 1;
+/// Header with hex-encoded HMAC-SHA256 of the request body, prefixed with `sha256=`
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Tonlib-Signature";
+
+pub const DEFAULT_WEBHOOK_BATCH_SIZE: usize = 100;
+pub const DEFAULT_WEBHOOK_MAX_RETRIES: usize = 5;
+pub const DEFAULT_WEBHOOK_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+pub const DEFAULT_WEBHOOK_MAX_BACKOFF: Duration = Duration::from_secs(30);
+pub const DEFAULT_DEAD_LETTER_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhookConfig {
+    /// Maximal number of transactions in a single request
+    pub max_batch_size: usize,
+    /// Number of retries of requests failed with 5xx status or transport error
+    pub max_retries: usize,
+    /// Delay before the first retry, doubled for each next one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Number of undelivered payloads kept for redelivery, the oldest ones are dropped first
+    pub dead_letter_capacity: usize,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            max_batch_size: DEFAULT_WEBHOOK_BATCH_SIZE,
+            max_retries: DEFAULT_WEBHOOK_MAX_RETRIES,
+            initial_backoff: DEFAULT_WEBHOOK_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_WEBHOOK_MAX_BACKOFF,
+            dead_letter_capacity: DEFAULT_DEAD_LETTER_CAPACITY,
+        }
+    }
+}
+
+/// Position of a batch in the transaction chain of the account.
+///
+/// `prev_lt` of a batch equals `last_lt` of the previous batch of the same account,
+/// so consumers can detect lost batches.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhookCheckpoint {
+    /// Transaction preceding the batch, zero for the first transaction of the account
+    pub prev_lt: u64,
+    pub prev_hash: String,
+    /// The last transaction of the batch
+    pub last_lt: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhookTransaction {
+    pub lt: u64,
+    pub now: u32,
+    pub prev_trans_lt: u64,
+    pub prev_trans_hash: String,
+    /// In nanotons
+    pub total_fees: String,
+    pub out_msgs_count: usize,
+    /// Base64-encoded BoC of the inbound message
+    pub in_msg: Option<String>,
+}
+
+/// Body of a webhook request
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WebhookPayload {
+    pub version: u32,
+    /// Account in raw form
+    pub account: String,
+    pub checkpoint: WebhookCheckpoint,
+    /// Transactions ordered from the oldest to the newest
+    pub transactions: Vec<WebhookTransaction>,
+}
+
+/// Result of `WebhookSink::deliver_once`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeliveryReport {
+    pub delivered_batches: usize,
+    /// Batches not delivered: moved to the dead-letter buffer by `deliver_report`,
+    /// left for the next call by `deliver_once`
+    pub failed_batches: usize,
+    /// Nothing is delivered if the history of the account is broken,
+    /// see `AccountHistorySyncer::reset`
+    pub chain_break: Option<ChainBreak>,
+}
+
+/// Pushes new transactions of accounts to an HTTP endpoint as signed JSON payloads.
+///
+/// Transactions are taken from `AccountHistorySyncer` and posted in batches of at most
+/// `max_batch_size` transactions, one batch at a time, so batches of an account are delivered
+/// in order. Each request carries `WEBHOOK_SIGNATURE_HEADER` with HMAC-SHA256 of the body
+/// keyed by `secret`. Requests failed with 5xx status or transport errors are retried with
+/// exponential backoff. Then `deliver_once` stops, leaving the failed batch and the following
+/// ones unacknowledged, while `deliver_report` moves the payload to the dead-letter buffer
+/// and goes on.
+pub struct WebhookSink {
+    url: String,
+    secret: Vec<u8>,
+    config: WebhookConfig,
+    http_client: Arc<dyn HttpClient>,
+    dead_letters: Mutex<VecDeque<WebhookPayload>>,
+}
+
+impl WebhookSink {
+    pub fn new(url: &str, secret: &[u8], config: WebhookConfig) -> WebhookSink {
+        WebhookSink {
+            url: url.to_string(),
+            secret: secret.to_vec(),
+            config,
+            http_client: Arc::new(ReqwestHttpClient::default()),
+            dead_letters: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Uses `http_client` instead of the default `ReqwestHttpClient`.
+    ///
+    /// The client must support `HttpClient::post_with_headers`.
+    pub fn with_http_client(mut self, http_client: Arc<dyn HttpClient>) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    /// Delivers transactions returned by `syncer` and acknowledges them.
+    ///
+    /// Delivery stops at the first batch which fails after retries, and only the transactions
+    /// of the batches delivered before it are acknowledged, so the failed batch is sent again
+    /// by the next call. Batches are never moved to the dead-letter buffer.
+    pub async fn deliver_once<C: TonClientInterface>(
+        &self,
+        syncer: &AccountHistorySyncer<'_, C>,
+    ) -> Result<DeliveryReport, TonClientError> {
+        let report = syncer.sync_once().await?;
+        let mut delivery = DeliveryReport {
+            delivered_batches: 0,
+            failed_batches: 0,
+            chain_break: report.chain_break.clone(),
+        };
+        if delivery.chain_break.is_some() {
+            return Ok(delivery);
+        }
+        let mut delivered = None;
+        for batch in self.batches(&report) {
+            let payload = build_payload(syncer.address(), batch)?;
+            if !self.post(&payload).await? {
+                delivery.failed_batches += 1;
+                break;
+            }
+            delivery.delivered_batches += 1;
+            delivered = batch.last();
+        }
+        match delivered {
+            _ if delivery.failed_batches == 0 => syncer.acknowledge(&report)?,
+            Some(transaction) => syncer.acknowledge_up_to(transaction)?,
+            None => {}
+        }
+        Ok(delivery)
+    }
+
+    /// Calls `deliver_once` every `poll_interval` until it fails.
+    pub async fn run<C: TonClientInterface>(
+        &self,
+        syncer: &AccountHistorySyncer<'_, C>,
+        poll_interval: Duration,
+    ) -> Result<(), TonClientError> {
+        loop {
+            let report = self.deliver_once(syncer).await?;
+            if let Some(chain_break) = report.chain_break {
+                return Err(TonClientError::InternalError(format!(
+                    "Transaction chain of {} is broken at {}",
+                    syncer.address(),
+                    chain_break.cursor
+                )));
+            }
+            rt::sleep(poll_interval).await;
+        }
+    }
+
+    /// Posts transactions of `report` in batches.
+    pub async fn deliver_report(
+        &self,
+        address: &TonAddress,
+        report: &SyncReport,
+    ) -> Result<DeliveryReport, TonClientError> {
+        let mut delivery = DeliveryReport {
+            delivered_batches: 0,
+            failed_batches: 0,
+            chain_break: report.chain_break.clone(),
+        };
+        if delivery.chain_break.is_some() {
+            return Ok(delivery);
+        }
+        for batch in self.batches(report) {
+            let payload = build_payload(address, batch)?;
+            if self.post(&payload).await? {
+                delivery.delivered_batches += 1;
+            } else {
+                delivery.failed_batches += 1;
+                self.push_dead_letter(payload);
+            }
+        }
+        Ok(delivery)
+    }
+
+    fn batches<'r>(&self, report: &'r SyncReport) -> std::slice::Chunks<'r, Transaction> {
+        report
+            .new_transactions
+            .chunks(self.config.max_batch_size.max(1))
+    }
+
+    /// Payloads which couldn't be delivered, the oldest first
+    pub fn dead_letters(&self) -> Vec<WebhookPayload> {
+        let dead_letters = self.dead_letters.lock().unwrap_or_else(|e| e.into_inner());
+        dead_letters.iter().cloned().collect()
+    }
+
+    /// Tries to deliver payloads from the dead-letter buffer again, in their original order.
+    ///
+    /// Returns the number of delivered payloads, the rest are kept in the buffer.
+    pub async fn redeliver_dead_letters(&self) -> Result<usize, TonClientError> {
+        let payloads: Vec<_> = {
+            let mut dead_letters = self.dead_letters.lock().unwrap_or_else(|e| e.into_inner());
+            dead_letters.drain(..).collect()
+        };
+        let mut delivered = 0;
+        for payload in payloads {
+            if self.post(&payload).await? {
+                delivered += 1;
+            } else {
+                self.push_dead_letter(payload);
+            }
+        }
+        Ok(delivered)
+    }
+
+    fn push_dead_letter(&self, payload: WebhookPayload) {
+        let mut dead_letters = self.dead_letters.lock().unwrap_or_else(|e| e.into_inner());
+        dead_letters.push_back(payload);
+        while dead_letters.len() > self.config.dead_letter_capacity {
+            if let Some(dropped) = dead_letters.pop_front() {
+                log::warn!(
+                    "Dead-letter buffer is full, dropping payload of {} up to lt {}",
+                    dropped.account,
+                    dropped.checkpoint.last_lt
+                );
+            }
+        }
+    }
+
+    /// Posts `payload` retrying on server errors, returns `false` if it isn't delivered.
+    async fn post(&self, payload: &WebhookPayload) -> Result<bool, TonClientError> {
+        let body = serde_json::to_vec(payload).map_err(|e| {
+            TonClientError::InternalError(format!("Failed to serialize webhook payload: {}", e))
+        })?;
+        let signature = format!("sha256={}", sign(&self.secret, &body)?);
+        let headers = [
+            ("Content-Type", "application/json"),
+            (WEBHOOK_SIGNATURE_HEADER, signature.as_str()),
+        ];
+        let mut backoff = self.config.initial_backoff;
+        for attempt in 0..=self.config.max_retries {
+            if attempt > 0 {
+                rt::sleep(backoff).await;
+                backoff = (backoff * 2).min(self.config.max_backoff);
+            }
+            match self
+                .http_client
+                .post_with_headers(&self.url, &headers, body.clone())
+                .await
+            {
+                Ok(response) if response.is_success() => return Ok(true),
+                Ok(response) if response.status.is_server_error() => {
+                    log::warn!("Webhook {} responded with {}", self.url, response.status);
+                }
+                Ok(response) => {
+                    log::warn!(
+                        "Webhook {} rejected payload with {}",
+                        self.url,
+                        response.status
+                    );
+                    return Ok(false);
+                }
+                Err(e) => log::warn!("Failed to post to webhook {}: {}", self.url, e),
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Returns hex-encoded HMAC-SHA256 of `body`.
+pub fn sign(secret: &[u8], body: &[u8]) -> Result<String, TonClientError> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret)
+        .map_err(|e| TonClientError::InternalError(format!("Invalid webhook secret: {}", e)))?;
+    mac.update(body);
+    Ok(hex::encode(mac.finalize().into_bytes()))
+}
+
+fn build_payload(
+    address: &TonAddress,
+    batch: &[Transaction],
+) -> Result<WebhookPayload, TonClientError> {
+    // batches are never empty, as they are produced by `chunks`
+    let first = &batch[0];
+    let last = &batch[batch.len() - 1];
+    let transactions = batch
+        .iter()
+        .map(|tx| {
+            let in_msg = match &tx.in_msg {
+                Some(msg) => Some(
+                    BagOfCells::from_root(msg.as_ref().clone())
+                        .serialize(false)
+                        .map(|boc| STANDARD.encode(boc))
+                        .map_err(|e| TonClientError::InternalError(e.to_string()))?,
+                ),
+                None => None,
+            };
+            Ok(WebhookTransaction {
+                lt: tx.lt,
+                now: tx.now,
+                prev_trans_lt: tx.prev_trans_lt,
+                prev_trans_hash: hex::encode(tx.prev_trans_hash),
+                total_fees: tx.total_fees.to_string(),
+                out_msgs_count: tx.out_msgs_count,
+                in_msg,
+            })
+        })
+        .collect::<Result<Vec<_>, TonClientError>>()?;
+    Ok(WebhookPayload {
+        version: WEBHOOK_PAYLOAD_VERSION,
+        account: address.to_hex(),
+        checkpoint: WebhookCheckpoint {
+            prev_lt: first.prev_trans_lt,
+            prev_hash: hex::encode(first.prev_trans_hash),
+            last_lt: last.lt,
+        },
+        transactions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::client::mock::{account_state, mc_block, MockClient, TransactionFixture};
+    use crate::client::CheckpointStore;
+    use crate::http::{HttpError, HttpResponse, StatusCode};
+    use crate::tl::{
+        InternalTransactionId, RawFullAccountState, RawTransaction, RawTransactions, TonFunction,
+        TonResult, NULL_TRANSACTION_ID,
+    };
+
+    /// Endpoint failing the first `failures` requests with 503
+    struct StubEndpoint {
+        failures: Mutex<usize>,
+        requests: Mutex<Vec<(String, Vec<u8>)>>,
+    }
+
+    #[async_trait]
+    impl HttpClient for StubEndpoint {
+        async fn get(&self, _url: &str) -> Result<HttpResponse, HttpError> {
+            unimplemented!()
+        }
+
+        async fn post(&self, _url: &str, _body: Vec<u8>) -> Result<HttpResponse, HttpError> {
+            unimplemented!()
+        }
+
+        async fn post_with_headers(
+            &self,
+            _url: &str,
+            headers: &[(&str, &str)],
+            body: Vec<u8>,
+        ) -> Result<HttpResponse, HttpError> {
+            let signature = headers
+                .iter()
+                .find(|(name, _)| *name == WEBHOOK_SIGNATURE_HEADER)
+                .map(|(_, value)| value.to_string())
+                .unwrap_or_default();
+            self.requests.lock().unwrap().push((signature, body));
+            let mut failures = self.failures.lock().unwrap();
+            let status = if *failures > 0 {
+                *failures -= 1;
+                StatusCode::SERVICE_UNAVAILABLE
+            } else {
+                StatusCode::OK
+            };
+            Ok(HttpResponse {
+                status,
+                body: vec![],
+            })
+        }
+    }
+
+    fn transaction(lt: u64) -> anyhow::Result<Transaction> {
+        let fixture = TransactionFixture {
+            prev: InternalTransactionId {
+                lt: lt as i64 - 1,
+                hash: vec![lt as u8 - 1; 32],
+            },
+            total_fees: 1000,
+            ..TransactionFixture::new(&TonAddress::new(0, &[0x11; 32]), lt)
+        };
+        Ok(Transaction::parse(&fixture.cell()?)?)
+    }
+
+    #[tokio::test]
+    async fn test_webhook_delivery() -> anyhow::Result<()> {
+        let endpoint = Arc::new(StubEndpoint {
+            failures: Mutex::new(2),
+            requests: Mutex::new(vec![]),
+        });
+        let config = WebhookConfig {
+            max_batch_size: 2,
+            initial_backoff: Duration::from_millis(1),
+            ..WebhookConfig::default()
+        };
+        let sink = WebhookSink::new("http://localhost/hook", b"secret", config)
+            .with_http_client(endpoint.clone());
+        let address = TonAddress::new(0, &[0x11; 32]);
+        let report = SyncReport {
+            new_transactions: vec![transaction(11)?, transaction(12)?, transaction(13)?],
+            cursor: NULL_TRANSACTION_ID.clone(),
+            chain_break: None,
+        };
+
+        let delivery = sink.deliver_report(&address, &report).await?;
+        assert_eq!(delivery.delivered_batches, 2);
+        assert_eq!(delivery.failed_batches, 0);
+        assert!(sink.dead_letters().is_empty());
+
+        // the first batch is retried twice, then the second one is sent
+        let requests = endpoint.requests.lock().unwrap();
+        assert_eq!(requests.len(), 4);
+        let mut lts = vec![];
+        for (i, (signature, body)) in requests.iter().enumerate() {
+            assert_eq!(signature, &format!("sha256={}", sign(b"secret", body)?));
+            let payload: WebhookPayload = serde_json::from_slice(body)?;
+            assert_eq!(payload.version, WEBHOOK_PAYLOAD_VERSION);
+            if i >= 2 {
+                lts.extend(payload.transactions.iter().map(|tx| tx.lt));
+                if i == 3 {
+                    assert_eq!(payload.checkpoint.prev_lt, 12);
+                    assert_eq!(payload.checkpoint.last_lt, 13);
+                }
+            }
+        }
+        assert_eq!(lts, vec![11, 12, 13]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_webhook_dead_letters() -> anyhow::Result<()> {
+        let endpoint = Arc::new(StubEndpoint {
+            failures: Mutex::new(usize::MAX),
+            requests: Mutex::new(vec![]),
+        });
+        let config = WebhookConfig {
+            max_batch_size: 1,
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(1),
+            dead_letter_capacity: 1,
+            ..WebhookConfig::default()
+        };
+        let sink = WebhookSink::new("http://localhost/hook", b"secret", config)
+            .with_http_client(endpoint.clone());
+        let report = SyncReport {
+            new_transactions: vec![transaction(11)?, transaction(12)?],
+            cursor: NULL_TRANSACTION_ID.clone(),
+            chain_break: None,
+        };
+        let delivery = sink
+            .deliver_report(&TonAddress::new(0, &[0x11; 32]), &report)
+            .await?;
+        assert_eq!(delivery.failed_batches, 2);
+        // only the newest payload is kept
+        let dead_letters = sink.dead_letters();
+        assert_eq!(dead_letters.len(), 1);
+        assert_eq!(dead_letters[0].checkpoint.last_lt, 12);
+
+        *endpoint.failures.lock().unwrap() = 0;
+        assert_eq!(sink.redeliver_dead_letters().await?, 1);
+        assert!(sink.dead_letters().is_empty());
+        Ok(())
+    }
+
+    /// HTTP server on a local port responding with `statuses` in turn, then with 200,
+    /// and recording bodies of the requests
+    struct LocalEndpoint {
+        url: String,
+        bodies: Arc<Mutex<Vec<Vec<u8>>>>,
+    }
+
+    impl LocalEndpoint {
+        fn start(statuses: Vec<u16>) -> anyhow::Result<LocalEndpoint> {
+            let listener = TcpListener::bind("127.0.0.1:0")?;
+            let url = format!("http://{}/hook", listener.local_addr()?);
+            let bodies = Arc::new(Mutex::new(vec![]));
+            let requests = bodies.clone();
+            thread::spawn(move || {
+                let mut statuses = VecDeque::from(statuses);
+                for stream in listener.incoming() {
+                    let Ok(mut stream) = stream else { return };
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        reader.read_line(&mut line).unwrap();
+                        let line = line.trim_end().to_ascii_lowercase();
+                        if line.is_empty() {
+                            break;
+                        }
+                        if let Some(length) = line.strip_prefix("content-length:") {
+                            content_length = length.trim().parse().unwrap();
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    reader.read_exact(&mut body).unwrap();
+                    requests.lock().unwrap().push(body);
+                    let status = statuses.pop_front().unwrap_or(200);
+                    let response = format!(
+                        "HTTP/1.1 {} Status\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                        status
+                    );
+                    stream.write_all(response.as_bytes()).unwrap();
+                }
+            });
+            Ok(LocalEndpoint { url, bodies })
+        }
+
+        fn last_lts(&self) -> Vec<u64> {
+            let bodies = self.bodies.lock().unwrap();
+            bodies
+                .iter()
+                .map(|body| {
+                    let payload: WebhookPayload = serde_json::from_slice(body).unwrap();
+                    payload.checkpoint.last_lt
+                })
+                .collect()
+        }
+    }
+
+    #[derive(Default)]
+    struct MemoryStore {
+        cursor: Mutex<Option<InternalTransactionId>>,
+    }
+
+    impl CheckpointStore for MemoryStore {
+        fn load(
+            &self,
+            _address: &TonAddress,
+        ) -> Result<Option<InternalTransactionId>, TonClientError> {
+            Ok(self.cursor.lock().unwrap().clone())
+        }
+
+        fn save(
+            &self,
+            _address: &TonAddress,
+            cursor: &InternalTransactionId,
+        ) -> Result<(), TonClientError> {
+            *self.cursor.lock().unwrap() = Some(cursor.clone());
+            Ok(())
+        }
+    }
+
+    /// Client serving the chain of transactions with lt 10, 20 ... `10 * count`
+    fn history_client(count: u64) -> anyhow::Result<(MockClient, Vec<RawTransaction>)> {
+        let address = TonAddress::new(0, &[0x11; 32]);
+        let mut transactions: Vec<RawTransaction> = vec![];
+        for lt in (1..=count).map(|i| i * 10) {
+            let prev = transactions
+                .last()
+                .map(|tx| tx.transaction_id.clone())
+                .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+            let fixture = TransactionFixture {
+                prev,
+                ..TransactionFixture::new(&address, lt)
+            };
+            transactions.push(fixture.raw()?);
+        }
+        let history = transactions.clone();
+        let client = MockClient::new(move |function| match function {
+            TonFunction::RawGetAccountState { .. } => {
+                Ok(TonResult::RawFullAccountState(RawFullAccountState {
+                    last_transaction_id: history.last().unwrap().transaction_id.clone(),
+                    ..account_state(&mc_block(1))
+                }))
+            }
+            TonFunction::RawGetTransactionsV2 {
+                from_transaction_id,
+                count,
+                ..
+            } => {
+                let page: Vec<_> = history
+                    .iter()
+                    .rev()
+                    .filter(|tx| tx.transaction_id.lt <= from_transaction_id.lt)
+                    .take(*count as usize)
+                    .cloned()
+                    .collect();
+                let previous_transaction_id = page
+                    .last()
+                    .and_then(|tx| {
+                        history
+                            .iter()
+                            .rev()
+                            .find(|p| p.transaction_id.lt < tx.transaction_id.lt)
+                    })
+                    .map(|tx| tx.transaction_id.clone())
+                    .unwrap_or_else(|| NULL_TRANSACTION_ID.clone());
+                Ok(TonResult::RawTransactions(RawTransactions {
+                    transactions: page,
+                    previous_transaction_id,
+                }))
+            }
+            f => panic!("Unexpected function: {:?}", f),
+        });
+        Ok((client, transactions))
+    }
+
+    #[tokio::test]
+    async fn test_deliver_once_stops_at_failed_batch() -> anyhow::Result<()> {
+        // the second batch fails on the first call, including its retry
+        let endpoint = LocalEndpoint::start(vec![200, 503, 503])?;
+        let config = WebhookConfig {
+            max_batch_size: 2,
+            max_retries: 1,
+            initial_backoff: Duration::from_millis(1),
+            ..WebhookConfig::default()
+        };
+        let sink = WebhookSink::new(&endpoint.url, b"secret", config);
+        let (client, transactions) = history_client(5)?;
+        let store = MemoryStore::default();
+        let syncer = AccountHistorySyncer::new(client, TonAddress::new(0, &[0x11; 32]), &store);
+
+        let delivery = sink.deliver_once(&syncer).await?;
+        assert_eq!(delivery.delivered_batches, 1);
+        assert_eq!(delivery.failed_batches, 1);
+        assert!(sink.dead_letters().is_empty());
+        // only the first batch is acknowledged
+        assert_eq!(
+            store.load(syncer.address())?,
+            Some(transactions[1].transaction_id.clone())
+        );
+        assert_eq!(endpoint.last_lts(), vec![20, 40, 40]);
+
+        // the failed batch is sent again, followed by the rest
+        let delivery = sink.deliver_once(&syncer).await?;
+        assert_eq!(delivery.delivered_batches, 2);
+        assert_eq!(delivery.failed_batches, 0);
+        assert_eq!(
+            store.load(syncer.address())?,
+            Some(transactions[4].transaction_id.clone())
+        );
+        assert_eq!(endpoint.last_lts(), vec![20, 40, 40, 40, 50]);
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+mod common;
+
+mod audit_tests {
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+    use tonlib_client::emulator::audit::{
+        external_replay_check, BodyField, ReplayAuditState, ReplayScenario, ReplayVerdict,
+    };
+    use tonlib_client::emulator::TvmEmulatorC7Builder;
+    use tonlib_core::cell::{BagOfCells, Cell, CellBuilder};
+    use tonlib_core::message::{CommonMsgInfo, InternalMessage, TonMessage, TransferMessage};
+    use tonlib_core::mnemonic::Mnemonic;
+    use tonlib_core::testkit::config_params_boc;
+    use tonlib_core::wallet::{TonWallet, WalletVersion, DEFAULT_WALLET_ID};
+    use tonlib_core::TonAddress;
+
+    use crate::common;
+
+    const NOW: u64 = 1_700_000_000;
+    const BALANCE: u64 = 1_000_000_000;
+    /// Contract accepting any external and incrementing the counter in its data:
+    /// `ACCEPT c4 PUSH CTOS 32 LDU ENDS INC NEWC 32 STU ENDC c4 POP`
+    const NAIVE_CODE: &str = "f800ed44d0d31fd1a4c8cb1fc9ed54";
+
+    fn wallet() -> anyhow::Result<TonWallet> {
+        let mnemonic = Mnemonic::from_str(
+            "mechanic sudden cannon bind monkey brown moment able street pride struggle team outdoor canyon coin tourist service second crazy tank sell regret sample attitude",
+            &None,
+        )?;
+        Ok(TonWallet::derive_default(
+            WalletVersion::V4R2,
+            &mnemonic.to_key_pair()?,
+        )?)
+    }
+
+    /// External message of the wallet with seqno 0 transferring 0.1 TON
+    fn external_boc(wallet: &TonWallet) -> anyhow::Result<Vec<u8>> {
+        let transfer = TransferMessage::new(CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: false,
+            bounced: false,
+            src: TonAddress::NULL,
+            dest: TonAddress::NULL,
+            value: BigUint::from(100_000_000u32),
+            ihr_fee: BigUint::default(),
+            fwd_fee: BigUint::default(),
+            created_lt: 0,
+            created_at: 0,
+        }))
+        .build()?;
+        let message =
+            wallet.create_external_message((NOW + 60) as u32, 0, [Arc::new(transfer)], false)?;
+        Ok(BagOfCells::from_root(message).serialize(true)?)
+    }
+
+    /// Scenarios of `WalletVersion::V4R2` body: `signature:bits512 subwallet_id:uint32
+    /// valid_until:uint32 seqno:uint32 ...`
+    fn scenarios() -> Vec<ReplayScenario> {
+        vec![
+            ReplayScenario::Replay,
+            ReplayScenario::Mutated {
+                field: BodyField::new("valid_until", 544, 32),
+                delta: 1,
+            },
+            ReplayScenario::Mutated {
+                field: BodyField::new("seqno", 576, 32),
+                delta: 1,
+            },
+        ]
+    }
+
+    fn serialize(cell: Cell) -> anyhow::Result<Vec<u8>> {
+        Ok(BagOfCells::from_root(cell).serialize(false)?)
+    }
+
+    #[test]
+    fn test_external_replay_check() -> anyhow::Result<()> {
+        common::init_logging();
+        let config = config_params_boc()?;
+        let wallet = wallet()?;
+        let external = external_boc(&wallet)?;
+        let c7 = TvmEmulatorC7Builder::new(&wallet.address, &config, BALANCE)
+            .with_unix_time(NOW)
+            .build();
+
+        let wallet_state = ReplayAuditState {
+            code: serialize(WalletVersion::V4R2.code()?.as_ref().clone())?,
+            data: serialize(
+                WalletVersion::V4R2
+                    .initial_data(&wallet.key_pair, DEFAULT_WALLET_ID)?
+                    .as_ref()
+                    .clone(),
+            )?,
+            c7: Some(c7.clone()),
+        };
+        let report = external_replay_check(&wallet_state, &external, &scenarios())?;
+        log::info!("Wallet: {:?}", report);
+        assert!(report.original.committed());
+        assert_eq!(report.outcomes.len(), 3);
+        assert!(report
+            .outcomes
+            .iter()
+            .all(|o| o.verdict == ReplayVerdict::Rejected && !o.run.accepted));
+        assert!(!report.is_vulnerable());
+
+        let naive_state = ReplayAuditState {
+            code: serialize(
+                CellBuilder::new()
+                    .store_slice(&hex::decode(NAIVE_CODE)?)?
+                    .build()?,
+            )?,
+            data: serialize(CellBuilder::new().store_u32(32, 0)?.build()?)?,
+            c7: Some(c7),
+        };
+        let report = external_replay_check(&naive_state, &external, &scenarios())?;
+        log::info!("Naive contract: {:?}", report);
+        assert!(report.original.committed());
+        assert!(report
+            .outcomes
+            .iter()
+            .all(|o| o.verdict == ReplayVerdict::Accepted && o.run.gas_used > 0));
+        assert!(report.is_vulnerable());
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use tokio_test::assert_ok;
 use tonlib_client::client::{
-    BlockStream, TonBlockFunctions, TonClientInterface, TonConnection, TonConnectionParams,
-    LOGGING_CONNECTION_CALLBACK,
+    BlockStream, BlockStreamEvent, TonBlockFunctions, TonClientInterface, TonConnection,
+    TonConnectionParams, LOGGING_CONNECTION_CALLBACK,
 };
 use tonlib_client::tl::InternalTransactionId;
 
@@ -107,3 +107,25 @@ async fn test_connection_hang_tx() {
             .await
     );
 }
+
+#[tokio::test]
+pub async fn block_listener_with_reorg_verification_works() {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let (_, mc_info) = assert_ok!(client.get_masterchain_info().await);
+    let seqno = mc_info.last.seqno - 5;
+    let mut listener = BlockStream::new(&client, seqno);
+    listener.with_reorg_verification(16);
+    for _ in 0..3 {
+        let event = assert_ok!(listener.next_event().await);
+        log::info!("{:?}", event);
+        assert!(matches!(event, BlockStreamEvent::Block(ref item) if !item.reemitted));
+    }
+    let checkpoint = listener.checkpoint().unwrap();
+    assert_eq!(checkpoint.seqno, seqno + 2);
+
+    let mut resumed = BlockStream::from_checkpoint(&client, &checkpoint);
+    resumed.with_reorg_verification(16);
+    let item = assert_ok!(resumed.next().await);
+    assert_eq!(item.master_shard.seqno, seqno + 3);
+}
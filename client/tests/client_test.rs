@@ -1,6 +1,7 @@
 use std::fs::create_dir_all;
 use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -11,13 +12,14 @@ use tokio::time::timeout;
 use tokio::{self};
 use tokio_test::assert_ok;
 use tonlib_client::client::{
-    TonBlockFunctions, TonClient, TonClientBuilder, TonClientInterface, TxId,
+    ConnectPhase, PoolParams, TonBlockFunctions, TonClient, TonClientBuilder, TonClientInterface,
+    TonConnectionCallback, TonValidatorFunctions, TxId, ELECTOR_ADDRESS,
 };
 use tonlib_client::config::{MAINNET_CONFIG, TESTNET_CONFIG};
 use tonlib_client::contract::{TonContractFactory, TonContractInterface};
 use tonlib_client::tl::{
     BlockId, BlockIdExt, BlocksShards, BlocksTransactions, BlocksTransactionsExt,
-    InternalTransactionId, LiteServerInfo, SmcLibraryQueryExt, TonLibraryId,
+    InternalTransactionId, LiteServerInfo, SmcLibraryQueryExt, TonFunction, TonLibraryId,
     NULL_BLOCKS_ACCOUNT_TRANSACTION_ID,
 };
 use tonlib_core::cell::dict::predefined_readers::{key_reader_256bit, val_reader_cell};
@@ -328,6 +330,40 @@ async fn test_client_lite_server_get_info() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_connect_report() -> anyhow::Result<()> {
+    common::init_logging();
+    let plain = common::new_testnet_client().await;
+    assert!(plain.connect_report().await.is_empty());
+    let report = assert_ok!(plain.connect_eagerly().await);
+    log::info!("{:?}", report);
+    let phases: Vec<_> = report.iter().map(|stats| stats.phase).collect();
+    assert_eq!(
+        phases,
+        vec![
+            ConnectPhase::KeystoreSetup,
+            ConnectPhase::InitOptions,
+            ConnectPhase::LiteserverHandshake,
+            ConnectPhase::ProofDownload,
+        ]
+    );
+    assert!(report.iter().all(|stats| stats.count == 2));
+
+    let healthy = common::new_mainnet_client().await;
+    let report = assert_ok!(healthy.connect_eagerly().await);
+    log::info!("{:?}", report);
+    let phases: Vec<_> = report.iter().map(|stats| stats.phase).collect();
+    assert_eq!(
+        phases,
+        vec![
+            ConnectPhase::KeystoreSetup,
+            ConnectPhase::InitOptions,
+            ConnectPhase::HealthCheck,
+        ]
+    );
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_config_param() -> anyhow::Result<()> {
     common::init_logging();
@@ -342,6 +378,18 @@ async fn test_get_config_param() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_get_network_addresses() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = &common::new_mainnet_client().await;
+    let addresses = assert_ok!(client.get_network_addresses().await);
+    assert_eq!(addresses.elector_address(), &ELECTOR_ADDRESS);
+    assert!(addresses.dns_root_address().is_some());
+    let cached = assert_ok!(client.get_network_addresses().await);
+    assert_eq!(cached, addresses);
+    Ok(())
+}
+
 #[tokio::test]
 pub async fn test_get_block_header() -> anyhow::Result<()> {
     common::init_logging();
@@ -365,6 +413,27 @@ async fn test_get_shard_tx_ids() -> anyhow::Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_get_last_transaction_block() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let address = ELECTOR_ADDRESS.clone();
+    let state = assert_ok!(client.get_raw_account_state(&address).await);
+    let block = assert_ok!(client.get_last_transaction_block(&address).await)
+        .expect("elector must have transactions");
+    log::info!("{:?}", block);
+    assert_eq!(block.workchain, -1);
+    let txs = assert_ok!(client.get_shard_tx_ids(&block).await);
+    assert!(txs.iter().any(
+        |tx| tx.internal_transaction_id.lt == state.last_transaction_id.lt && tx.address == address
+    ));
+
+    let unused = TonAddress::new(0, &[0x42; 32]);
+    let block = assert_ok!(client.get_last_transaction_block(&unused).await);
+    assert!(block.is_none());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_get_shard_transactions_works() -> anyhow::Result<()> {
     common::init_logging();
@@ -677,3 +746,152 @@ async fn archive_node_client_test() -> anyhow::Result<()> {
     log::info!("master_info: {:?}", master_info);
     Ok(())
 }
+
+#[tokio::test]
+async fn client_zero_pool_size_fails() {
+    let result = TonClient::builder()
+        .with_pool_size(0)
+        .with_config(MAINNET_CONFIG)
+        .build()
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn client_keystore_dir_fn_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let tonlib_work_dir = Path::new("./var/tonlib/keystore_dir_fn");
+    let client = TonClient::builder()
+        .with_pool_size(2)
+        .with_config(MAINNET_CONFIG)
+        .with_keystore_dir_fn(move |i| tonlib_work_dir.join(format!("conn_{}", i)))
+        .build()
+        .await?;
+    assert!(tonlib_work_dir.join("conn_0").is_dir());
+    assert!(tonlib_work_dir.join("conn_1").is_dir());
+    let (_, master_info) = client.get_masterchain_info().await?;
+    log::info!("master_info: {:?}", master_info);
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_get_past_validator_sets_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let sets = assert_ok!(client.get_past_validator_sets(2).await);
+    log::info!("{:?}", sets);
+    assert!(!sets.is_empty() && sets.len() <= 2);
+    assert!(!sets[0].validators.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_elastic_pool_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let mut pool_params = PoolParams::elastic(1, 3);
+    pool_params.grow_threshold = 2;
+    let client = assert_ok!(
+        TonClient::builder()
+            .with_pool_params(&pool_params)
+            .with_config(MAINNET_CONFIG)
+            .build()
+            .await
+    );
+    let futures = (0..20).map(|_| client.get_masterchain_info());
+    let results = join_all(futures).await;
+    assert!(results.iter().all(|r| r.is_ok()));
+
+    let invalid = TonClient::builder()
+        .with_pool_params(&PoolParams::elastic(2, 1))
+        .build()
+        .await;
+    assert!(invalid.is_err());
+    Ok(())
+}
+
+#[derive(Default)]
+struct MasterchainInfoCounter {
+    count: AtomicU32,
+}
+
+#[allow(unused_variables)]
+impl TonConnectionCallback for MasterchainInfoCounter {
+    fn on_invoke(&self, tag: &str, request_id: u32, function: &TonFunction) {
+        if let TonFunction::BlocksGetMasterchainInfo {} = function {
+            self.count.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+#[tokio::test]
+async fn client_masterchain_info_cached_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let counter = Arc::new(MasterchainInfoCounter::default());
+    let client = assert_ok!(
+        TonClient::builder()
+            .with_config(MAINNET_CONFIG)
+            .with_callback(counter.clone())
+            .with_masterchain_info_ttl(Duration::from_secs(60))
+            .build()
+            .await
+    );
+    let futures = (0..10).map(|_| client.masterchain_info_cached());
+    let results = join_all(futures).await;
+    assert!(results.iter().all(|r| r.is_ok()));
+    assert_eq!(counter.count.load(Ordering::SeqCst), 1);
+    let seqno = results[0].as_ref().unwrap().info.last.seqno;
+
+    // Wait for the next masterchain block and observe it via unrelated request
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    let address = TonAddress::from_base64_url("EQBw_0u4LyoweyLGjyAiGg0W_wozq4S5EAQwLIsx15a4U4ar")?;
+    let state = client.get_raw_account_state(&address).await?;
+    assert!(state.block_id.seqno > seqno);
+
+    let cached = client.masterchain_info_cached().await?;
+    assert_eq!(counter.count.load(Ordering::SeqCst), 2);
+    assert!(cached.info.last.seqno >= state.block_id.seqno);
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_invoke_raw_bytes_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let (_, raw) = client
+        .invoke_raw_bytes(&TonFunction::BlocksGetMasterchainInfo {})
+        .await?;
+    let value: serde_json::Value = serde_json::from_slice(&raw)?;
+    log::info!("{}", value);
+    assert_eq!(value["@type"], "blocks.masterchainInfo");
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_get_tps_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let tps = client.get_tps(5).await?;
+    log::info!("TPS: {}", tps);
+    assert!(tps > 0.0);
+    // blocks of the window are cached
+    let cached = timeout(Duration::from_secs(5), client.get_tps(5)).await??;
+    assert!(cached > 0.0);
+    assert!(client.get_tps(0).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn client_get_workchain_info_works() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let workchains = client.get_workchain_info().await?;
+    log::info!("Workchains: {:?}", workchains);
+    let basechain = workchains
+        .iter()
+        .find(|w| w.workchain_id == 0)
+        .ok_or_else(|| anyhow::anyhow!("Basechain is missing"))?;
+    assert!(basechain.active && basechain.accept_msgs);
+    assert!(basechain.min_split <= basechain.max_split);
+    assert!(workchains.iter().all(|w| w.active));
+    Ok(())
+}
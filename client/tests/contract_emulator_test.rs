@@ -1,12 +1,18 @@
 mod common;
 mod contract_emulator_tests {
+    use std::sync::Arc;
+
     use tokio_test::assert_ok;
+    use tonlib_client::client::TonClientInterface;
     use tonlib_client::contract::{
         JettonData, JettonMasterContract, TonContractFactory, TonContractInterface,
+        TonContractState,
     };
     use tonlib_client::emulator::{TvmEmulator, TvmEmulatorC7Builder};
     use tonlib_client::meta::MetaDataContent;
     use tonlib_client::tl::RawFullAccountState;
+    use tonlib_client::types::TvmStackEntry;
+    use tonlib_core::cell::{BagOfCells, Cell, CellBuilder};
     use tonlib_core::TonAddress;
 
     use crate::common;
@@ -67,6 +73,38 @@ mod contract_emulator_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_emulator_c7_from_account_state() {
+        common::init_logging();
+        let client = common::new_mainnet_client().await;
+
+        let address = assert_ok!(TonAddress::from_base64_url(
+            "EQDCJL0iQHofcBBvFBHdVG233Ri2V4kCNFgfRT-gqAd3Oc86"
+        )); //jetton master
+        let factory = assert_ok!(TonContractFactory::builder(&client).build().await);
+        let state = assert_ok!(factory.get_latest_account_state(&address).await);
+        let config = assert_ok!(factory.get_config_cell_serial().await);
+        let c7 = TvmEmulatorC7Builder::from_account_state(&address, config, &state).build();
+        assert_eq!(c7.balance, state.balance as u64);
+        assert_eq!(c7.unix_time, state.sync_utime as u64);
+        assert_eq!(
+            c7.seed.as_slice(),
+            state.last_transaction_id.hash.as_slice()
+        );
+
+        let mut emulator = assert_ok!(TvmEmulator::new(&state.code, &state.data));
+        assert!(emulator.set_balance(1).is_err());
+        assert_ok!(emulator.set_c7(&c7));
+        assert_ok!(emulator.set_balance(1));
+        assert_ok!(emulator.set_unix_time(1700000000));
+        let updated = assert_ok!(emulator.c7().ok_or("c7 is not set"));
+        assert_eq!(updated.balance, 1);
+        assert_eq!(updated.unix_time, 1700000000);
+        assert_eq!(updated.seed, c7.seed);
+        let result = assert_ok!(emulator.run_get_method(&"get_jetton_data".into(), &[]));
+        assert!(result.exit_success());
+    }
+
     #[tokio::test]
     async fn test_emulator_get_wallet_address() {
         common::init_logging();
@@ -104,4 +142,49 @@ mod contract_emulator_tests {
         assert_eq!(r1.vm_exit_code, r2.vm_exit_code);
         assert_eq!(r1.vm_exit_code, r3.vm_exit_code);
     }
+
+    #[tokio::test]
+    async fn test_emulator_frozen_time_and_seed() -> anyhow::Result<()> {
+        common::init_logging();
+        let client = common::new_mainnet_client().await;
+
+        let address =
+            TonAddress::from_base64_url("EQDCJL0iQHofcBBvFBHdVG233Ri2V4kCNFgfRT-gqAd3Oc86")?;
+        let factory = TonContractFactory::builder(&client).build().await?;
+        let mut account_state = (*factory.get_latest_account_state(&address).await?).clone();
+        // DROP NOW NOW RANDU256, returns the time twice and a random number for any method
+        let code = CellBuilder::new()
+            .store_slice(&[0x30, 0xf8, 0x23, 0xf8, 0x23, 0xf8, 0x10])?
+            .build()?;
+        account_state.code = BagOfCells::from_root(code).serialize(false)?;
+        account_state.data = BagOfCells::from_root(Cell::default()).serialize(false)?;
+        let block_id = account_state.block_id.clone();
+        let state = TonContractState::new(&factory, &address, &Arc::new(account_state))
+            .freeze_time()
+            .await?;
+
+        let stack: &[TvmStackEntry] = &[];
+        let first = state.emulate_get_method("now_and_random", stack).await?;
+        let second = state.emulate_get_method("now_and_random", stack).await?;
+        assert_eq!(first.stack.len(), 3);
+        assert_eq!(first.stack, second.stack);
+        assert_eq!(first.stack[0], first.stack[1]);
+        let header = client.get_block_header(&block_id).await?;
+        assert_eq!(first.stack[0].get_i64()?, header.gen_utime);
+
+        let reseeded = state
+            .clone()
+            .with_rand_seed([0x42; 32])
+            .emulate_get_method("now_and_random", stack)
+            .await?;
+        assert_eq!(reseeded.stack[0], first.stack[0]);
+        assert_ne!(reseeded.stack[2], first.stack[2]);
+
+        let shifted = state
+            .with_unixtime(1_700_000_000)
+            .emulate_get_method("now_and_random", stack)
+            .await?;
+        assert_eq!(shifted.stack[0].get_i64()?, 1_700_000_000);
+        Ok(())
+    }
 }
@@ -110,11 +110,7 @@ fn test_ton_client_error_output() {
     );
     log::error!(
         "{}",
-        TonClientError::TonlibError {
-            method: "some_get_method",
-            code: 300,
-            message: "Some error message".to_string(),
-        }
+        TonClientError::tonlib_error("some_get_method", 300, "Some error message")
     );
     log::error!(
         "{}",
@@ -163,11 +159,11 @@ fn test_ton_contract_error_output() {
 
     log::error!(
         "{}",
-        TonContractError::ClientError(TonClientError::TonlibError {
-            method: "some_get_method",
-            code: 300,
-            message: "Some error message".to_string(),
-        })
+        TonContractError::ClientError(TonClientError::tonlib_error(
+            "some_get_method",
+            300,
+            "Some error message"
+        ))
     );
 
     log::error!(
@@ -1,6 +1,8 @@
 use sha2::{Digest, Sha256};
 use tokio_test::assert_ok;
-use tonlib_client::contract::{JettonMasterContract, TonContractFactory};
+use tonlib_client::contract::{
+    ContractInterface, IntrospectionContract, JettonMasterContract, TonContractFactory,
+};
 use tonlib_client::meta::{JettonMetaLoader, LoadMeta, MetaDataContent};
 use tonlib_core::{TonAddress, TonHash};
 
@@ -185,3 +187,25 @@ async fn test_jetton_image_data() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn test_detect_jetton_interfaces() -> anyhow::Result<()> {
+    common::init_logging();
+    let client = common::new_mainnet_client().await;
+    let factory = TonContractFactory::builder(&client).build().await?;
+
+    let master = factory.get_contract(&"EQDk2VTvn04SUKJrW7rXahzdF8_Qi6utb0wj43InCu9vdjrR".parse()?);
+    let interfaces = master.detect_interfaces().await?;
+    log::info!("Master interfaces: {:?}", interfaces);
+    assert!(interfaces
+        .iter()
+        .any(|i| i.interface == ContractInterface::JettonMaster));
+
+    let wallet = factory.get_contract(&"EQCGY3OVLtD9KRcOsP2ldQDtuY0FMzV7wPoxjrFbayBXc23c".parse()?);
+    let interfaces = wallet.detect_interfaces().await?;
+    log::info!("Wallet interfaces: {:?}", interfaces);
+    assert!(interfaces
+        .iter()
+        .any(|i| i.interface == ContractInterface::JettonWallet));
+    Ok(())
+}
@@ -0,0 +1,124 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::StreamExt;
+use tonlib_client::client::RawTransactionStream;
+use tonlib_client::tl::RawTransactions;
+
+mod common;
+
+/// Allocator tracking the number of allocated bytes and its peak
+struct TrackingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::SeqCst);
+            let allocated = ALLOCATED.fetch_add(new_size, Ordering::SeqCst) + new_size;
+            PEAK.fetch_max(allocated, Ordering::SeqCst);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static GLOBAL: TrackingAllocator = TrackingAllocator;
+
+const BODY_SIZE: usize = 50 * 1024 * 1024;
+/// Allowed growth of memory while streaming on top of the buffered body, regardless of its size
+const MAX_STREAMING_OVERHEAD: usize = 4 * 1024 * 1024;
+
+/// Builds `raw.transactions` response of about `BODY_SIZE` bytes, returns it with the number
+/// of transactions
+fn synthetic_response() -> (Vec<u8>, usize) {
+    let data = STANDARD.encode([0xab; 1024]);
+    let hash = STANDARD.encode([0; 32]);
+    let mut body = String::with_capacity(BODY_SIZE + 4096);
+    body.push_str(r#"{"@type":"raw.transactions","transactions":["#);
+    let mut count = 0;
+    while body.len() < BODY_SIZE {
+        if count > 0 {
+            body.push(',');
+        }
+        let lt = 1_000_000 - count;
+        body.push_str(&format!(
+            r#"{{"@type":"raw.transaction","address":{{"@type":"accountAddress","account_address":"EQAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAM9c"}},"utime":1700000000,"data":"{data}","transaction_id":{{"@type":"internal.transactionId","lt":"{lt}","hash":"{hash}"}},"fee":"1","storage_fee":"1","other_fee":"0","in_msg":null,"out_msgs":[]}}"#
+        ));
+        count += 1;
+    }
+    body.push_str(&format!(
+        r#"],"previous_transaction_id":{{"@type":"internal.transactionId","lt":"{}","hash":"{hash}"}},"@extra":"1"}}"#,
+        1_000_000 - count
+    ));
+    (body.into_bytes(), count)
+}
+
+#[tokio::test]
+async fn test_streaming_overhead_is_bounded() -> anyhow::Result<()> {
+    common::init_logging();
+    let (body, count) = synthetic_response();
+    log::info!(
+        "Synthetic response: {} bytes, {} transactions",
+        body.len(),
+        count
+    );
+
+    let stream_body = body.clone();
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let start = Instant::now();
+    let mut stream = RawTransactionStream::from_body(stream_body);
+    let mut streamed = 0;
+    while let Some(tx) = stream.next().await {
+        tx?;
+        streamed += 1;
+    }
+    let streaming_overhead = PEAK.load(Ordering::SeqCst) - baseline;
+    log::info!(
+        "Streamed in {:?}, peak memory growth: {} bytes",
+        start.elapsed(),
+        streaming_overhead
+    );
+    assert_eq!(streamed, count);
+    assert_eq!(
+        stream.previous_transaction_id().map(|id| id.lt),
+        Some(1_000_000 - count as i64)
+    );
+    assert!(streaming_overhead < MAX_STREAMING_OVERHEAD);
+    drop(stream);
+
+    let baseline = ALLOCATED.load(Ordering::SeqCst);
+    PEAK.store(baseline, Ordering::SeqCst);
+    let start = Instant::now();
+    let parsed: RawTransactions = serde_json::from_slice(&body)?;
+    let parsing_overhead = PEAK.load(Ordering::SeqCst) - baseline;
+    log::info!(
+        "Parsed at once in {:?}, peak memory growth: {} bytes",
+        start.elapsed(),
+        parsing_overhead
+    );
+    assert_eq!(parsed.transactions.len(), count);
+    assert!(parsing_overhead > body.len() / 2);
+    Ok(())
+}
@@ -16,8 +16,14 @@ mod contract_emulator_tests {
     use tonlib_client::emulator::{TvmEmulator, TvmEmulatorC7Builder};
     use tonlib_client::meta::MetaDataContent;
     use tonlib_client::types::TvmStackEntry;
-    use tonlib_core::cell::{BagOfCells, CellBuilder, CellSlice};
-    use tonlib_core::message::{JettonTransferMessage, TonMessage};
+    use tonlib_core::cell::{BagOfCells, CellBuilder, CellSlice, StateInitBuilder};
+    use tonlib_core::message::{
+        CommonMsgInfo, InternalMessage, JettonTransferMessage, TonMessage, TransferMessage,
+        JETTON_INTERNAL_TRANSFER,
+    };
+    use tonlib_core::mnemonic::Mnemonic;
+    use tonlib_core::types::Coins;
+    use tonlib_core::wallet::{TonWallet, WalletVersion, DEFAULT_WALLET_ID};
     use tonlib_core::TonAddress;
 
     use crate::common;
@@ -507,4 +513,109 @@ mod contract_emulator_tests {
 
         assert_eq!(blockchain_data, emulated_data);
     }
+
+    #[tokio::test]
+    async fn test_execute_tvm() -> anyhow::Result<()> {
+        common::init_logging();
+        let client = common::new_mainnet_client().await;
+        let mnemonic = Mnemonic::from_str(
+            "mechanic sudden cannon bind monkey brown moment able street pride struggle team outdoor canyon coin tourist service second crazy tank sell regret sample attitude",
+            &None,
+        )?;
+        let key_pair = mnemonic.to_key_pair()?;
+        let code = WalletVersion::V4R2.code()?;
+        let data = WalletVersion::V4R2.initial_data(&key_pair, DEFAULT_WALLET_ID)?;
+        let message = CellBuilder::new().build()?;
+
+        let result = assert_ok!(
+            client
+                .execute_tvm(code, &data, &message, &Coins::from(1_000_000_000u64), 0)
+                .await
+        );
+        assert_eq!(result.exit_code, 0);
+        assert!(result.gas_used > 0);
+        assert!(result.out_messages.is_empty());
+        assert_eq!(&result.new_data, data.as_ref());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_emulate_internal_message() -> anyhow::Result<()> {
+        common::init_logging();
+        let client = common::new_mainnet_client().await;
+        let mnemonic = Mnemonic::from_str(
+            "mechanic sudden cannon bind monkey brown moment able street pride struggle team outdoor canyon coin tourist service second crazy tank sell regret sample attitude",
+            &None,
+        )?;
+        let wallet = TonWallet::derive_default(WalletVersion::V4R2, &mnemonic.to_key_pair()?)?;
+        let code = WalletVersion::V4R2.code()?;
+        let data = WalletVersion::V4R2.initial_data(&wallet.key_pair, DEFAULT_WALLET_ID)?;
+        let state_init = StateInitBuilder::new(code, &data).build()?;
+
+        // a plain transfer to the wallet, deploying it if needed
+        let message = TransferMessage::new(CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: false,
+            bounced: false,
+            src: TonAddress::NULL,
+            dest: wallet.address.clone(),
+            value: BigUint::from(100_000_000u32),
+            ihr_fee: BigUint::default(),
+            fwd_fee: BigUint::default(),
+            created_lt: 0,
+            created_at: 0,
+        }))
+        .with_state_init(state_init)
+        .build()?;
+
+        let result = assert_ok!(client.emulate_internal_message(&message).await);
+        log::info!("{:?}", result);
+        assert_eq!(result.vm_exit_code, 0);
+        assert!(result.gas_used > 0);
+        assert!(result.out_messages()?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_emulate_internal_message_hides_sender() -> anyhow::Result<()> {
+        common::init_logging();
+        let client = common::new_mainnet_client().await;
+        let minter_address: TonAddress =
+            "EQDk2VTvn04SUKJrW7rXahzdF8_Qi6utb0wj43InCu9vdjrR".parse()?;
+        let owner_address: TonAddress =
+            "EQB2BtXDXaQuIcMYW7JEWhHmwHfPPwa-eoCdefiAxOhU3pQg".parse()?;
+        let wallet_address: TonAddress =
+            "EQCGY3OVLtD9KRcOsP2ldQDtuY0FMzV7wPoxjrFbayBXc23c".parse()?;
+
+        // internal_transfer sent by the master, accepted by the jetton wallet from the master only
+        let body = CellBuilder::new()
+            .store_u32(32, JETTON_INTERNAL_TRANSFER)?
+            .store_u64(64, 0)?
+            .store_coins(&BigUint::from(1_000_000u32))?
+            .store_address(&TonAddress::NULL)?
+            .store_address(&owner_address)?
+            .store_coins(&BigUint::default())?
+            .store_bit(false)?
+            .build()?;
+        let message = TransferMessage::new(CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: true,
+            bounced: false,
+            src: minter_address,
+            dest: wallet_address,
+            value: BigUint::from(100_000_000u32),
+            ihr_fee: BigUint::default(),
+            fwd_fee: BigUint::default(),
+            created_lt: 0,
+            created_at: 0,
+        }))
+        .with_data(body.to_arc())
+        .build()?;
+
+        // the wallet doesn't see the master as the sender and rejects the transfer
+        let result = assert_ok!(client.emulate_internal_message(&message).await);
+        log::info!("{:?}", result);
+        assert_ne!(result.vm_exit_code, 0);
+        Ok(())
+    }
 }
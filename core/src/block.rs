@@ -0,0 +1,265 @@
+mod account_block;
+mod block_info;
+mod value_flow;
+
+pub use account_block::*;
+pub use block_info::*;
+pub use value_flow::*;
+
+use crate::cell::dict::AugmentedCellDictionary;
+use crate::cell::{ArcCell, Cell, CellParser, TonCellError};
+use crate::types::{CoinBalance, Transaction};
+use crate::{TonAddress, TonHash};
+
+const BLOCK_TAG: u32 = 0x11ef55aa;
+const BLOCK_EXTRA_TAG: u32 = 0x4a33f6fd;
+const ACCOUNT_BLOCKS_KEY_LEN: u16 = 256;
+
+/// Block contents: message descriptions and transactions grouped by accounts
+///
+/// ```raw
+/// block_extra in_msg_descr:^InMsgDescr out_msg_descr:^OutMsgDescr
+///   account_blocks:^ShardAccountBlocks
+///   rand_seed:bits256 created_by:bits256
+///   custom:(Maybe ^McBlockExtra) = BlockExtra;
+/// _ (HashmapAugE 256 AccountBlock CurrencyCollection) = ShardAccountBlocks;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockExtra {
+    pub in_msg_descr: ArcCell,
+    pub out_msg_descr: ArcCell,
+    /// Accounts with transactions in the block, ordered by address
+    pub account_blocks: Vec<AccountBlock>,
+    /// Total fees of all transactions of the block
+    pub total_fees: CoinBalance,
+    pub rand_seed: TonHash,
+    pub created_by: TonHash,
+    /// `McBlockExtra` of masterchain blocks
+    pub custom: Option<ArcCell>,
+}
+
+impl BlockExtra {
+    pub fn parse(parser: &mut CellParser, workchain: i32) -> Result<BlockExtra, TonCellError> {
+        let tag = parser.load_u32(32)?;
+        if tag != BLOCK_EXTRA_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid block extra tag: {:#x}",
+                tag
+            )));
+        }
+        let in_msg_descr = parser.next_reference()?;
+        let out_msg_descr = parser.next_reference()?;
+        let account_blocks = parser.next_reference()?;
+        let dict = AugmentedCellDictionary::<CoinBalance>::load_hashmap_aug_e(
+            &mut account_blocks.parser(),
+            ACCOUNT_BLOCKS_KEY_LEN,
+        )?;
+        let account_blocks = dict
+            .iter()
+            .map(|(_, _, value)| AccountBlock::parse(workchain, &value))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rand_seed = [0u8; 32];
+        parser.load_slice(&mut rand_seed)?;
+        let mut created_by = [0u8; 32];
+        parser.load_slice(&mut created_by)?;
+        Ok(BlockExtra {
+            in_msg_descr,
+            out_msg_descr,
+            account_blocks,
+            total_fees: dict.root_augment().clone(),
+            rand_seed,
+            created_by,
+            custom: parser.load_maybe_cell_ref()?,
+        })
+    }
+}
+
+/// Block parsed down to the transactions, as needed to extract account updates
+/// from full block BoCs without querying liteservers.
+///
+/// ```raw
+/// block#11ef55aa global_id:int32 info:^BlockInfo value_flow:^ValueFlow
+///   state_update:^(MERKLE_UPDATE ShardState) extra:^BlockExtra = Block;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ParsedBlock {
+    pub global_id: i32,
+    pub info: BlockInfo,
+    pub value_flow: ValueFlow,
+    /// Merkle update of the shard state, see `apply_merkle_update`
+    pub state_update: ArcCell,
+    pub extra: BlockExtra,
+}
+
+impl ParsedBlock {
+    /// Iterates over accounts with their transactions, ordered by address and lt.
+    pub fn account_transactions(&self) -> impl Iterator<Item = (&TonAddress, &[Transaction])> {
+        self.extra
+            .account_blocks
+            .iter()
+            .map(|block| (&block.address, block.transactions.as_slice()))
+    }
+
+    pub fn transactions_count(&self) -> usize {
+        self.extra
+            .account_blocks
+            .iter()
+            .map(|block| block.transactions.len())
+            .sum()
+    }
+}
+
+/// Parses the root cell of a block.
+pub fn parse_block(cell: &Cell) -> Result<ParsedBlock, TonCellError> {
+    let mut parser = cell.parser();
+    let tag = parser.load_u32(32)?;
+    if tag != BLOCK_TAG {
+        return Err(TonCellError::InvalidCellData(format!(
+            "Invalid block tag: {:#x}",
+            tag
+        )));
+    }
+    let global_id = parser.load_i32(32)?;
+    let info = BlockInfo::parse(&mut parser.next_reference()?.parser())?;
+    let value_flow = ValueFlow::parse(&mut parser.next_reference()?.parser())?;
+    let state_update = parser.next_reference()?;
+    let extra = BlockExtra::parse(
+        &mut parser.next_reference()?.parser(),
+        info.shard.workchain_id,
+    )?;
+    Ok(ParsedBlock {
+        global_id,
+        info,
+        value_flow,
+        state_update,
+        extra,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::BagOfCells;
+    use crate::types::Coins;
+
+    /// Mainnet block (0,8000000000000000,34703989)
+    const BASECHAIN_BLOCK: &str = include_str!("../resources/block/basechain_block.b64");
+    /// Proof with pruned masterchain block (-1,8000000000000000,36023210)
+    const BLOCK_PROOF: &str = include_str!("../resources/block/block_proof.b64");
+
+    fn single_root(base64: &str) -> Result<ArcCell, TonCellError> {
+        Ok(BagOfCells::parse_base64(base64.trim())?
+            .single_root()?
+            .clone())
+    }
+
+    #[test]
+    fn test_parse_block() -> Result<(), TonCellError> {
+        let root = single_root(BASECHAIN_BLOCK)?;
+        assert_eq!(
+            hex::encode(root.cell_hash()),
+            "84753a60efefc7169959fdf34ea21f3fa9f5a85c3a8690db77b1f141e0ff47ee"
+        );
+
+        let parsed = parse_block(&root)?;
+        assert_eq!(parsed.global_id, -239);
+        assert_eq!(parsed.info.seqno, 34703989);
+        assert_eq!(parsed.info.shard.workchain_id, 0);
+        assert_eq!(parsed.info.shard.shard_pfx_bits, 0);
+        assert_eq!(parsed.info.shard.shard as u64, 0x8000000000000000);
+        assert_eq!(parsed.info.gen_utime, 1682417974);
+        assert_eq!(parsed.info.start_lt, 37124585000000);
+        assert_eq!(parsed.info.end_lt, 37124585000011);
+        assert_eq!(
+            parsed.info.gen_software,
+            Some(GlobalVersion {
+                version: 3,
+                capabilities: 0x2e
+            })
+        );
+        assert_eq!(
+            parsed.info.master_ref.as_ref().map(|r| r.seqno),
+            Some(29045030)
+        );
+        assert!(
+            matches!(parsed.info.prev_ref, BlkPrevInfo::Single(ref prev) if prev.seqno == 34703988)
+        );
+        assert_eq!(
+            parsed.value_flow.fees_collected.grams,
+            Coins::from(1108394048u64)
+        );
+        assert_eq!(parsed.value_flow.created.grams, Coins::from(1000000000u64));
+        assert_eq!(
+            hex::encode(parsed.extra.created_by),
+            "5a6603d559ba5f1ea85acae7cab6dd4282fa34e5421438ba43ed832de7df905e"
+        );
+        assert_eq!(parsed.extra.total_fees.grams, Coins::from(71541098u64));
+
+        assert_eq!(parsed.transactions_count(), 7);
+        let accounts: Vec<_> = parsed
+            .account_transactions()
+            .map(|(address, txs)| {
+                let lts: Vec<_> = txs.iter().map(|tx| tx.lt).collect();
+                (address.to_string(), lts)
+            })
+            .collect();
+        assert_eq!(
+            accounts,
+            vec![
+                (
+                    "EQAt22V9_so4TG3HAIp3cGX4ccqvbyzBXb1sNkgndIoERtZ8".to_string(),
+                    vec![37124585000007]
+                ),
+                (
+                    "EQCjc483caXMwWw2kwl2afFquAPO0LX1VyZbnZUs3toMYkk9".to_string(),
+                    vec![37124585000005]
+                ),
+                (
+                    "EQC-Wm7UqQC0fS0tVfCrgd_NoTBMfH2Fq3qywjXUfybbpNTS".to_string(),
+                    vec![37124585000001, 37124585000006, 37124585000010]
+                ),
+                (
+                    "EQDkkoj7tybmTKCAX65FfD6ci-m1Ja_Dc5K0B-eqMAqAs1c4".to_string(),
+                    vec![37124585000003, 37124585000008]
+                ),
+            ]
+        );
+        let fees = parsed
+            .extra
+            .account_blocks
+            .iter()
+            .fold(Coins::zero(), |sum, block| {
+                sum.saturating_add(&block.total_fees.grams)
+            });
+        assert_eq!(fees, parsed.extra.total_fees.grams);
+        let account = &parsed.extra.account_blocks[2];
+        assert_eq!(account.total_fees.grams, Coins::from(29204592u64));
+        assert_eq!(
+            hex::encode(account.transaction_cells[0].cell_hash()),
+            "4541c69fde726ec0698be73496f63b6cd00d9383558291ba84ceee4d971055be"
+        );
+        assert_eq!(
+            account.transactions[0].hash(),
+            account.transaction_cells[0].cell_hash()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_masterchain_block_info() -> Result<(), TonCellError> {
+        let root = single_root(BLOCK_PROOF)?;
+        let block = root.reference(3)?.reference(0)?.reference(0)?;
+        assert_eq!(block.data()[..4], BLOCK_TAG.to_be_bytes());
+
+        let info = BlockInfo::parse(&mut block.reference(0)?.parser())?;
+        assert_eq!(info.seqno, 36023210);
+        assert!(info.shard.is_masterchain());
+        assert_eq!(info.shard.shard_pfx_bits, 0);
+        assert_eq!(info.shard.shard as u64, 0x8000000000000000);
+        assert_eq!(info.gen_utime, 1707725906);
+        assert_eq!(info.start_lt, 44552858000000);
+        assert_eq!(info.master_ref, None);
+        assert!(matches!(info.prev_ref, BlkPrevInfo::Single(ref prev) if prev.seqno == 36023209));
+        Ok(())
+    }
+}
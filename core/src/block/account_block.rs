@@ -0,0 +1,64 @@
+use crate::cell::dict::AugmentedCellDictionary;
+use crate::cell::{ArcCell, CellSlice, TonCellError};
+use crate::types::{CoinBalance, Transaction};
+use crate::{TonAddress, TonHash};
+
+const ACCOUNT_BLOCK_TAG: u8 = 0x5;
+const TRANSACTIONS_KEY_LEN: u16 = 64;
+
+/// Transactions of an account within a block
+///
+/// ```raw
+/// acc_trans#5 account_addr:bits256
+///   transactions:(HashmapAug 64 ^Transaction CurrencyCollection)
+///   state_update:^(HASH_UPDATE Account) = AccountBlock;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountBlock {
+    pub address: TonAddress,
+    /// Transactions ordered by lt
+    pub transactions: Vec<Transaction>,
+    /// Cells of `transactions`, e.g. to compute their hashes
+    pub transaction_cells: Vec<ArcCell>,
+    /// Total fees of the transactions
+    pub total_fees: CoinBalance,
+    /// Hashes of the account state before and after the block
+    pub state_update: ArcCell,
+}
+
+impl AccountBlock {
+    /// Parses the value of `ShardAccountBlocks` dictionary, `workchain` is the one of the block.
+    pub fn parse(workchain: i32, value: &CellSlice) -> Result<AccountBlock, TonCellError> {
+        let cell = value.into_cell()?;
+        let mut parser = cell.parser();
+        let tag = parser.load_u8(4)?;
+        if tag != ACCOUNT_BLOCK_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid account block tag: {:#x}",
+                tag
+            )));
+        }
+        let mut account_addr: TonHash = [0u8; 32];
+        parser.load_slice(&mut account_addr)?;
+        // the dictionary root is stored inline, followed by the reference to the state update
+        let rest = parser.load_remaining()?;
+        let state_update = rest.references().last().cloned().ok_or_else(|| {
+            TonCellError::InvalidCellData("Account block has no state update".to_string())
+        })?;
+        let dict = AugmentedCellDictionary::<CoinBalance>::from_cell(&rest, TRANSACTIONS_KEY_LEN)?;
+        let mut transactions = Vec::with_capacity(dict.len());
+        let mut transaction_cells = Vec::with_capacity(dict.len());
+        for (_, _, value) in dict.iter() {
+            let cell = value.reference(0)?.clone();
+            transactions.push(Transaction::parse(&cell)?);
+            transaction_cells.push(cell);
+        }
+        Ok(AccountBlock {
+            address: TonAddress::new(workchain, &account_addr),
+            transactions,
+            transaction_cells,
+            total_fees: dict.root_augment().clone(),
+            state_update,
+        })
+    }
+}
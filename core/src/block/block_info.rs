@@ -0,0 +1,257 @@
+use crate::cell::{CellParser, TonCellError};
+use crate::TonHash;
+
+const BLOCK_INFO_TAG: u32 = 0x9bc7a987;
+const GLOBAL_VERSION_TAG: u8 = 0xc4;
+const SHARD_IDENT_TAG: u8 = 0b00;
+const MAX_SHARD_PFX_BITS: u8 = 60;
+
+/// Shard of a block
+///
+/// ```raw
+/// shard_ident$00 shard_pfx_bits:(#<= 60) workchain_id:int32 shard_prefix:uint64 = ShardIdent;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ShardIdent {
+    pub shard_pfx_bits: u8,
+    pub workchain_id: i32,
+    /// Shard id with the tag bit, as in `BlockId`
+    pub shard: i64,
+}
+
+impl ShardIdent {
+    pub fn parse(parser: &mut CellParser) -> Result<ShardIdent, TonCellError> {
+        let tag = parser.load_u8(2)?;
+        if tag != SHARD_IDENT_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid shard ident tag: {:02b}",
+                tag
+            )));
+        }
+        let shard_pfx_bits = parser.load_u8(6)?;
+        if shard_pfx_bits > MAX_SHARD_PFX_BITS {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid shard prefix length: {}",
+                shard_pfx_bits
+            )));
+        }
+        let workchain_id = parser.load_i32(32)?;
+        let shard_prefix = parser.load_u64(64)?;
+        Ok(ShardIdent {
+            shard_pfx_bits,
+            workchain_id,
+            shard: (shard_prefix | 1 << (63 - shard_pfx_bits)) as i64,
+        })
+    }
+
+    pub fn is_masterchain(&self) -> bool {
+        self.workchain_id == -1
+    }
+}
+
+/// Reference to a block of the same workchain or the masterchain
+///
+/// ```raw
+/// ext_blk_ref$_ end_lt:uint64 seq_no:uint32 root_hash:bits256 file_hash:bits256 = ExtBlkRef;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExtBlkRef {
+    pub end_lt: u64,
+    pub seqno: u32,
+    pub root_hash: TonHash,
+    pub file_hash: TonHash,
+}
+
+impl ExtBlkRef {
+    pub fn parse(parser: &mut CellParser) -> Result<ExtBlkRef, TonCellError> {
+        let end_lt = parser.load_u64(64)?;
+        let seqno = parser.load_u32(32)?;
+        let mut root_hash = [0u8; 32];
+        parser.load_slice(&mut root_hash)?;
+        let mut file_hash = [0u8; 32];
+        parser.load_slice(&mut file_hash)?;
+        Ok(ExtBlkRef {
+            end_lt,
+            seqno,
+            root_hash,
+            file_hash,
+        })
+    }
+
+    fn parse_ref(parser: &mut CellParser) -> Result<ExtBlkRef, TonCellError> {
+        let cell = parser.next_reference()?;
+        Self::parse(&mut cell.parser())
+    }
+}
+
+/// Previous blocks: one, or two if the block is the result of a merge
+///
+/// ```raw
+/// prev_blk_info$_ prev:ExtBlkRef = BlkPrevInfo 0;
+/// prev_blks_info$_ prev1:^ExtBlkRef prev2:^ExtBlkRef = BlkPrevInfo 1;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum BlkPrevInfo {
+    Single(ExtBlkRef),
+    Merged(ExtBlkRef, ExtBlkRef),
+}
+
+impl BlkPrevInfo {
+    pub fn parse(parser: &mut CellParser, after_merge: bool) -> Result<BlkPrevInfo, TonCellError> {
+        if after_merge {
+            let prev1 = ExtBlkRef::parse_ref(parser)?;
+            let prev2 = ExtBlkRef::parse_ref(parser)?;
+            Ok(BlkPrevInfo::Merged(prev1, prev2))
+        } else {
+            Ok(BlkPrevInfo::Single(ExtBlkRef::parse(parser)?))
+        }
+    }
+}
+
+/// Version and capabilities of the software that generated the block
+///
+/// ```raw
+/// capabilities#c4 version:uint32 capabilities:uint64 = GlobalVersion;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GlobalVersion {
+    pub version: u32,
+    pub capabilities: u64,
+}
+
+impl GlobalVersion {
+    pub fn parse(parser: &mut CellParser) -> Result<GlobalVersion, TonCellError> {
+        let tag = parser.load_u8(8)?;
+        if tag != GLOBAL_VERSION_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid global version tag: {:#x}",
+                tag
+            )));
+        }
+        Ok(GlobalVersion {
+            version: parser.load_u32(32)?,
+            capabilities: parser.load_u64(64)?,
+        })
+    }
+}
+
+/// Block header
+///
+/// ```raw
+/// block_info#9bc7a987 version:uint32
+///   not_master:(## 1) after_merge:(## 1) before_split:(## 1) after_split:(## 1)
+///   want_split:Bool want_merge:Bool key_block:Bool vert_seqno_incr:(## 1)
+///   flags:(## 8) { flags <= 1 }
+///   seq_no:# vert_seq_no:# { vert_seq_no >= vert_seqno_incr }
+///   { prev_seq_no:# } { ~prev_seq_no + 1 = seq_no }
+///   shard:ShardIdent gen_utime:uint32
+///   start_lt:uint64 end_lt:uint64
+///   gen_validator_list_hash_short:uint32
+///   gen_catchain_seqno:uint32
+///   min_ref_mc_seqno:uint32
+///   prev_key_block_seqno:uint32
+///   gen_software:flags . 0?GlobalVersion
+///   master_ref:not_master?^BlkMasterInfo
+///   prev_ref:^(BlkPrevInfo after_merge)
+///   prev_vert_ref:vert_seqno_incr?^(BlkPrevInfo 0)
+///   = BlockInfo;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BlockInfo {
+    pub version: u32,
+    pub after_merge: bool,
+    pub before_split: bool,
+    pub after_split: bool,
+    pub want_split: bool,
+    pub want_merge: bool,
+    pub key_block: bool,
+    pub seqno: u32,
+    pub vert_seqno: u32,
+    pub shard: ShardIdent,
+    pub gen_utime: u32,
+    pub start_lt: u64,
+    pub end_lt: u64,
+    pub gen_validator_list_hash_short: u32,
+    pub gen_catchain_seqno: u32,
+    pub min_ref_mc_seqno: u32,
+    pub prev_key_block_seqno: u32,
+    pub gen_software: Option<GlobalVersion>,
+    /// Latest masterchain block known to the shard block, `None` for masterchain blocks
+    pub master_ref: Option<ExtBlkRef>,
+    pub prev_ref: BlkPrevInfo,
+    pub prev_vert_ref: Option<BlkPrevInfo>,
+}
+
+impl BlockInfo {
+    pub fn parse(parser: &mut CellParser) -> Result<BlockInfo, TonCellError> {
+        let tag = parser.load_u32(32)?;
+        if tag != BLOCK_INFO_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid block info tag: {:#x}",
+                tag
+            )));
+        }
+        let version = parser.load_u32(32)?;
+        let not_master = parser.load_bit()?;
+        let after_merge = parser.load_bit()?;
+        let before_split = parser.load_bit()?;
+        let after_split = parser.load_bit()?;
+        let want_split = parser.load_bit()?;
+        let want_merge = parser.load_bit()?;
+        let key_block = parser.load_bit()?;
+        let vert_seqno_incr = parser.load_bit()?;
+        let flags = parser.load_u8(8)?;
+        let seqno = parser.load_u32(32)?;
+        let vert_seqno = parser.load_u32(32)?;
+        let shard = ShardIdent::parse(parser)?;
+        let gen_utime = parser.load_u32(32)?;
+        let start_lt = parser.load_u64(64)?;
+        let end_lt = parser.load_u64(64)?;
+        let gen_validator_list_hash_short = parser.load_u32(32)?;
+        let gen_catchain_seqno = parser.load_u32(32)?;
+        let min_ref_mc_seqno = parser.load_u32(32)?;
+        let prev_key_block_seqno = parser.load_u32(32)?;
+        let gen_software = if flags & 1 != 0 {
+            Some(GlobalVersion::parse(parser)?)
+        } else {
+            None
+        };
+        let master_ref = if not_master {
+            Some(ExtBlkRef::parse_ref(parser)?)
+        } else {
+            None
+        };
+        let prev_ref = BlkPrevInfo::parse(&mut parser.next_reference()?.parser(), after_merge)?;
+        let prev_vert_ref = if vert_seqno_incr {
+            Some(BlkPrevInfo::parse(
+                &mut parser.next_reference()?.parser(),
+                false,
+            )?)
+        } else {
+            None
+        };
+        Ok(BlockInfo {
+            version,
+            after_merge,
+            before_split,
+            after_split,
+            want_split,
+            want_merge,
+            key_block,
+            seqno,
+            vert_seqno,
+            shard,
+            gen_utime,
+            start_lt,
+            end_lt,
+            gen_validator_list_hash_short,
+            gen_catchain_seqno,
+            min_ref_mc_seqno,
+            prev_key_block_seqno,
+            gen_software,
+            master_ref,
+            prev_ref,
+            prev_vert_ref,
+        })
+    }
+}
@@ -0,0 +1,70 @@
+use crate::cell::dict::AugValue;
+use crate::cell::{CellParser, TonCellError};
+use crate::types::CoinBalance;
+
+const VALUE_FLOW_TAG: u32 = 0xb8e48dfb;
+const VALUE_FLOW_V2_TAG: u32 = 0x3ebf98b7;
+
+/// Movement of funds within a block
+///
+/// ```raw
+/// value_flow#b8e48dfb ^[ from_prev_blk:CurrencyCollection to_next_blk:CurrencyCollection
+///   imported:CurrencyCollection exported:CurrencyCollection ]
+///   fees_collected:CurrencyCollection
+///   ^[ fees_imported:CurrencyCollection recovered:CurrencyCollection
+///   created:CurrencyCollection minted:CurrencyCollection ] = ValueFlow;
+///
+/// value_flow_v2#3ebf98b7 ^[ from_prev_blk:CurrencyCollection to_next_blk:CurrencyCollection
+///   imported:CurrencyCollection exported:CurrencyCollection ]
+///   fees_collected:CurrencyCollection burned:CurrencyCollection
+///   ^[ fees_imported:CurrencyCollection recovered:CurrencyCollection
+///   created:CurrencyCollection minted:CurrencyCollection ] = ValueFlow;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ValueFlow {
+    pub from_prev_blk: CoinBalance,
+    pub to_next_blk: CoinBalance,
+    pub imported: CoinBalance,
+    pub exported: CoinBalance,
+    pub fees_collected: CoinBalance,
+    /// Burned funds, `None` for `value_flow` blocks preceding the burning
+    pub burned: Option<CoinBalance>,
+    pub fees_imported: CoinBalance,
+    pub recovered: CoinBalance,
+    pub created: CoinBalance,
+    pub minted: CoinBalance,
+}
+
+impl ValueFlow {
+    pub fn parse(parser: &mut CellParser) -> Result<ValueFlow, TonCellError> {
+        let tag = parser.load_u32(32)?;
+        if tag != VALUE_FLOW_TAG && tag != VALUE_FLOW_V2_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid value flow tag: {:#x}",
+                tag
+            )));
+        }
+        let transfers = parser.next_reference()?;
+        let mut transfers = transfers.parser();
+        let fees_collected = CoinBalance::parse(parser)?;
+        let burned = if tag == VALUE_FLOW_V2_TAG {
+            Some(CoinBalance::parse(parser)?)
+        } else {
+            None
+        };
+        let issuance = parser.next_reference()?;
+        let mut issuance = issuance.parser();
+        Ok(ValueFlow {
+            from_prev_blk: CoinBalance::parse(&mut transfers)?,
+            to_next_blk: CoinBalance::parse(&mut transfers)?,
+            imported: CoinBalance::parse(&mut transfers)?,
+            exported: CoinBalance::parse(&mut transfers)?,
+            fees_collected,
+            burned,
+            fees_imported: CoinBalance::parse(&mut issuance)?,
+            recovered: CoinBalance::parse(&mut issuance)?,
+            created: CoinBalance::parse(&mut issuance)?,
+            minted: CoinBalance::parse(&mut issuance)?,
+        })
+    }
+}
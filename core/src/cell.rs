@@ -12,8 +12,10 @@ pub use builder::*;
 pub use error::*;
 use hmac::digest::Digest;
 use lazy_static::lazy_static;
+pub use merkle_update::*;
 pub use parser::*;
 pub use raw::*;
+pub use serializer::*;
 use sha2::Sha256;
 pub use slice::*;
 pub use state_init::*;
@@ -30,10 +32,13 @@ mod builder;
 mod cell_type;
 pub mod dict;
 mod error;
+mod json;
 mod level_mask;
+mod merkle_update;
 mod parser;
 mod raw;
 mod raw_boc_from_boc;
+mod serializer;
 mod slice;
 mod state_init;
 mod util;
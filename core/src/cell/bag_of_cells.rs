@@ -5,6 +5,21 @@ use base64::engine::general_purpose::STANDARD;
 use crate::cell::raw_boc_from_boc::convert_to_raw_boc;
 use crate::cell::*;
 
+/// Magic of the compressed BoC envelope ("LZBC")
+pub const COMPRESSED_BOC_MAGIC: u32 = 0x4c5a4243;
+/// Upper bound of the decompressed size accepted by `BagOfCells::from_compressed`
+pub const MAX_DECOMPRESSED_BOC_SIZE: usize = 64 << 20;
+const COMPRESSED_BOC_HEADER_LEN: usize = 8;
+
+/// Optional parts of serialized BoC
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash, Default)]
+pub struct BocSerializeOptions {
+    /// Store the index of cell offsets
+    pub has_idx: bool,
+    /// Append CRC32-C checksum
+    pub has_crc32c: bool,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone, Hash)]
 pub struct BagOfCells {
     pub roots: Vec<ArcCell>,
@@ -53,7 +68,16 @@ impl BagOfCells {
         }
     }
 
+    /// Parses serialized BoC, decompressing it first if it's in the compressed envelope.
     pub fn parse(serial: &[u8]) -> Result<BagOfCells, TonCellError> {
+        if Self::is_compressed(serial) {
+            Self::from_compressed(serial)
+        } else {
+            Self::parse_uncompressed(serial)
+        }
+    }
+
+    fn parse_uncompressed(serial: &[u8]) -> Result<BagOfCells, TonCellError> {
         let raw = RawBagOfCells::parse(serial)?;
         let num_cells = raw.cells.len();
         let mut cells: Vec<ArcCell> = Vec::with_capacity(num_cells);
@@ -101,18 +125,89 @@ impl BagOfCells {
     }
 
     pub fn serialize(&self, has_crc32: bool) -> Result<Vec<u8>, TonCellError> {
+        self.serialize_with_options(&BocSerializeOptions {
+            has_idx: false,
+            has_crc32c: has_crc32,
+        })
+    }
+
+    pub fn serialize_with_options(
+        &self,
+        options: &BocSerializeOptions,
+    ) -> Result<Vec<u8>, TonCellError> {
         let raw = convert_to_raw_boc(self)?;
-        raw.serialize(has_crc32)
+        raw.serialize(options)
+    }
+
+    /// Serializes the BoC and compresses it with LZ4.
+    ///
+    /// The envelope is `magic:uint32 original_size:uint32` (big-endian) followed by
+    /// the LZ4 block. Only send it to endpoints known to accept compressed BoCs.
+    pub fn to_compressed(&self, options: &BocSerializeOptions) -> Result<Vec<u8>, TonCellError> {
+        let serial = self.serialize_with_options(options)?;
+        let original_size = u32::try_from(serial.len()).map_err(|_| {
+            TonCellError::boc_serialization_error(format!(
+                "BoC is too large to compress: {} bytes",
+                serial.len()
+            ))
+        })?;
+        let block = lz4_flex::block::compress(&serial);
+        let mut result = Vec::with_capacity(COMPRESSED_BOC_HEADER_LEN + block.len());
+        result.extend_from_slice(&COMPRESSED_BOC_MAGIC.to_be_bytes());
+        result.extend_from_slice(&original_size.to_be_bytes());
+        result.extend_from_slice(&block);
+        Ok(result)
+    }
+
+    /// Parses BoC in the compressed envelope produced by `to_compressed`.
+    pub fn from_compressed(bytes: &[u8]) -> Result<BagOfCells, TonCellError> {
+        if !Self::is_compressed(bytes) || bytes.len() < COMPRESSED_BOC_HEADER_LEN {
+            return Err(TonCellError::boc_deserialization_error(
+                "Invalid compressed BoC header",
+            ));
+        }
+        let original_size = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+        if original_size > MAX_DECOMPRESSED_BOC_SIZE {
+            return Err(TonCellError::boc_deserialization_error(format!(
+                "Compressed BoC is too large: {} bytes",
+                original_size
+            )));
+        }
+        let serial =
+            lz4_flex::block::decompress(&bytes[COMPRESSED_BOC_HEADER_LEN..], original_size)
+                .map_boc_deserialization_error()?;
+        if serial.len() != original_size {
+            return Err(TonCellError::boc_deserialization_error(format!(
+                "Decompressed BoC size mismatch: expected {}, got {}",
+                original_size,
+                serial.len()
+            )));
+        }
+        Self::parse_uncompressed(&serial)
+    }
+
+    /// Checks whether `bytes` start with the compressed BoC magic.
+    pub fn is_compressed(bytes: &[u8]) -> bool {
+        bytes.len() >= 4 && bytes[0..4] == COMPRESSED_BOC_MAGIC.to_be_bytes()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
     use std::sync::Arc;
     use std::time::Instant;
 
+    use base64::engine::general_purpose::STANDARD;
+    use base64::Engine;
+    use proptest::prelude::*;
+
+    use crate::cell::dict::predefined_writers::val_writer_ref_cell;
     use crate::cell::raw_boc_from_boc::convert_to_raw_boc;
-    use crate::cell::{BagOfCells, CellBuilder, TonCellError};
+    use crate::cell::{
+        ArcCell, BagOfCells, BocSerializeOptions, Cell, CellBuilder, TonCellError,
+        COMPRESSED_BOC_MAGIC, CRC_32_ISCSI, MAX_CELL_BITS, MAX_CELL_REFERENCES,
+    };
     use crate::message::ZERO_COINS;
 
     #[test]
@@ -319,4 +414,164 @@ mod tests {
         let _raw = convert_to_raw_boc(&boc)?;
         Ok(())
     }
+
+    #[test]
+    fn test_serialize_with_options() -> anyhow::Result<()> {
+        let leaf = CellBuilder::new().store_byte(10)?.build()?.to_arc();
+        let root = CellBuilder::new()
+            .store_u32(32, 0xdeadbeef)?
+            .store_reference(&leaf)?
+            .store_reference(&leaf)?
+            .build()?;
+        let boc = BagOfCells::from_root(root.clone());
+        let plain = boc.serialize_with_options(&BocSerializeOptions::default())?;
+        assert_eq!(plain, boc.serialize(false)?);
+        for has_idx in [false, true] {
+            for has_crc32c in [false, true] {
+                let options = BocSerializeOptions {
+                    has_idx,
+                    has_crc32c,
+                };
+                let serial = boc.serialize_with_options(&options)?;
+                let flags = serial[4] >> 6;
+                assert_eq!(flags, ((has_idx as u8) << 1) | (has_crc32c as u8));
+                let parsed = BagOfCells::parse(&serial)?;
+                assert_eq!(parsed.single_root()?.as_ref(), &root);
+
+                // 2 cells, 1 offset byte each
+                let idx_len = if has_idx { 2 } else { 0 };
+                let crc_len = if has_crc32c { 4 } else { 0 };
+                assert_eq!(serial.len(), plain.len() + idx_len + crc_len);
+                if has_crc32c {
+                    let (data, crc) = serial.split_at(serial.len() - 4);
+                    assert_eq!(crc, CRC_32_ISCSI.checksum(data).to_le_bytes());
+                }
+            }
+        }
+
+        let indexed = boc.serialize_with_options(&BocSerializeOptions {
+            has_idx: true,
+            has_crc32c: false,
+        })?;
+        // header (10 bytes) and root list (1 byte) are followed by end offsets of the cells:
+        // root: 2 + 4 + 2 refs, leaf: 2 + 1
+        assert_eq!(&indexed[11..13], &[8, 11]);
+        Ok(())
+    }
+
+    /// External message carrying a highload-like batch of `count` internal messages
+    fn large_external(count: u16) -> anyhow::Result<Cell> {
+        let messages = (0..count)
+            .map(|i| -> anyhow::Result<(u16, ArcCell)> {
+                let message = CellBuilder::new()
+                    .store_u32(32, i as u32)?
+                    .store_slice(&[i as u8; 123])?
+                    .build()?;
+                Ok((i, message.to_arc()))
+            })
+            .collect::<anyhow::Result<HashMap<_, _>>>()?;
+        let batch = CellBuilder::new()
+            .store_dict(16, val_writer_ref_cell, messages)?
+            .build()?;
+        Ok(CellBuilder::new()
+            .store_u8(2, 0b10)?
+            .store_slice(&[0x55; 64])?
+            .store_child(batch)?
+            .build()?)
+    }
+
+    #[test]
+    fn test_compressed_boc_roundtrip() -> anyhow::Result<()> {
+        let root = large_external(1600)?;
+        let boc = BagOfCells::from_root(root.clone());
+        let options = BocSerializeOptions {
+            has_idx: false,
+            has_crc32c: true,
+        };
+        let serial = boc.serialize_with_options(&options)?;
+        assert!(serial.len() > 200_000);
+
+        let compressed = boc.to_compressed(&options)?;
+        assert_eq!(compressed[0..4], COMPRESSED_BOC_MAGIC.to_be_bytes());
+        assert_eq!(compressed[4..8], (serial.len() as u32).to_be_bytes());
+        assert!(compressed.len() < serial.len());
+        assert!(BagOfCells::is_compressed(&compressed));
+        assert!(!BagOfCells::is_compressed(&serial));
+
+        let parsed = BagOfCells::from_compressed(&compressed)?;
+        assert_eq!(parsed.single_root()?.as_ref(), &root);
+        // transparent detection
+        let parsed = BagOfCells::parse(&compressed)?;
+        assert_eq!(parsed.single_root()?.cell_hash(), root.cell_hash());
+        let parsed = BagOfCells::parse_base64(&STANDARD.encode(&compressed))?;
+        assert_eq!(parsed.single_root()?.as_ref(), &root);
+        assert!(BagOfCells::from_compressed(&serial).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_compressed_boc_is_rejected() -> anyhow::Result<()> {
+        let compressed = BagOfCells::from_root(large_external(100)?)
+            .to_compressed(&BocSerializeOptions::default())?;
+        for len in [2, 8, compressed.len() / 2, compressed.len() - 1] {
+            assert!(BagOfCells::from_compressed(&compressed[..len]).is_err());
+            assert!(BagOfCells::parse(&compressed[..len]).is_err());
+        }
+
+        let mut oversized = compressed.clone();
+        oversized[4..8].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert!(BagOfCells::from_compressed(&oversized).is_err());
+        Ok(())
+    }
+
+    fn arb_cell() -> impl Strategy<Value = Cell> {
+        let bit_len = prop_oneof![Just(0), Just(MAX_CELL_BITS), 0..=MAX_CELL_BITS];
+        let data = bit_len
+            .prop_flat_map(|bit_len| {
+                (
+                    Just(bit_len),
+                    prop::collection::vec(any::<u8>(), bit_len.div_ceil(8)),
+                )
+            })
+            .boxed();
+        let leaf = data.clone().prop_map(|(bit_len, data)| {
+            CellBuilder::new()
+                .store_bits(bit_len, &data)
+                .unwrap()
+                .build()
+                .unwrap()
+        });
+        leaf.prop_recursive(4, 64, MAX_CELL_REFERENCES as u32, move |inner| {
+            (
+                data.clone(),
+                prop::collection::vec(inner, 0..=MAX_CELL_REFERENCES),
+            )
+                .prop_map(|((bit_len, data), refs)| {
+                    let refs = refs.into_iter().map(Arc::new).collect::<Vec<_>>();
+                    CellBuilder::new()
+                        .store_bits(bit_len, &data)
+                        .unwrap()
+                        .store_references(&refs)
+                        .unwrap()
+                        .build()
+                        .unwrap()
+                })
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn cell_boc_roundtrip(
+            cell in arb_cell(),
+            has_idx in any::<bool>(),
+            has_crc32c in any::<bool>(),
+        ) {
+            let options = BocSerializeOptions { has_idx, has_crc32c };
+            let boc = BagOfCells::from_root(cell.clone()).serialize_with_options(&options)?;
+            let parsed = BagOfCells::parse(&boc)?;
+            let root = parsed.single_root()?;
+            prop_assert_eq!(root.as_ref(), &cell);
+            prop_assert_eq!(root.cell_hash(), cell.cell_hash());
+        }
+    }
 }
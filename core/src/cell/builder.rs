@@ -20,6 +20,7 @@ pub struct CellBuilder {
     bits_to_write: usize,
     references: Vec<ArcCell>,
     is_cell_exotic: bool,
+    label: Option<&'static str>,
 }
 
 #[derive(Clone, Debug, PartialEq, Copy)]
@@ -37,20 +38,30 @@ impl CellBuilder {
             bits_to_write: 0,
             references: Vec::new(),
             is_cell_exotic: false,
+            label: None,
         }
     }
 
+    /// Sets the label reported in `TonCellError::CellBuilderOverflow` by subsequent store calls,
+    /// until another label is set.
+    pub fn labeled(&mut self, label: &'static str) -> &mut Self {
+        self.label = Some(label);
+        self
+    }
+
     pub fn set_cell_is_exotic(&mut self, val: bool) {
         self.is_cell_exotic = val;
     }
 
     pub fn store_bit(&mut self, val: bool) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(1, 0)?;
         self.bit_writer.write_bit(val).map_cell_builder_error()?;
         self.bits_to_write += 1;
         Ok(self)
     }
 
     pub fn store_u8(&mut self, bit_len: usize, val: u8) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -59,6 +70,7 @@ impl CellBuilder {
     }
 
     pub fn store_i8(&mut self, bit_len: usize, val: i8) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -67,6 +79,7 @@ impl CellBuilder {
     }
 
     pub fn store_u32(&mut self, bit_len: usize, val: u32) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -75,6 +88,7 @@ impl CellBuilder {
     }
 
     pub fn store_i32(&mut self, bit_len: usize, val: i32) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -83,6 +97,7 @@ impl CellBuilder {
     }
 
     pub fn store_u64(&mut self, bit_len: usize, val: u64) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -91,6 +106,7 @@ impl CellBuilder {
     }
 
     pub fn store_i64(&mut self, bit_len: usize, val: i64) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         self.bit_writer
             .write(bit_len as u32, val)
             .map_cell_builder_error()?;
@@ -107,6 +123,8 @@ impl CellBuilder {
             )));
         }
 
+        self.ensure_capacity(bit_len, 0)?;
+
         let value_bytes = val.to_bytes_be();
         let first_byte_bit_size = bit_len - (value_bytes.len() - 1) * 8;
 
@@ -156,6 +174,7 @@ impl CellBuilder {
     }
 
     pub fn store_slice(&mut self, slice: &[u8]) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(slice.len() * 8, 0)?;
         for val in slice {
             self.store_byte(*val)?;
         }
@@ -163,6 +182,7 @@ impl CellBuilder {
     }
 
     pub fn store_bits(&mut self, bit_len: usize, slice: &[u8]) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(bit_len, 0)?;
         let full_bytes = bit_len / 8;
         self.store_slice(&slice[0..full_bytes])?;
         let last_byte_len = bit_len % 8;
@@ -182,17 +202,31 @@ impl CellBuilder {
             self.store_u8(4, 0)
         } else {
             let num_bytes = (val.bits() as usize + 7) / 8;
+            self.ensure_capacity(4 + num_bytes * 8, 0)?;
             self.store_u8(4, num_bytes as u8)?;
             self.store_uint(num_bytes * 8, val)
         }
     }
 
     /// Stores address without optimizing hole address
+    ///
+    /// Addresses in workchains beyond `int8` are stored as `addr_var` with 256-bit address.
     pub fn store_raw_address(&mut self, val: &TonAddress) -> Result<&mut Self, TonCellError> {
-        self.store_u8(2, 0b10u8)?;
-        self.store_bit(false)?;
-        let wc = (val.workchain & 0xff) as u8;
-        self.store_u8(8, wc)?;
+        if val.fits_addr_std() {
+            // addr_std$10 anycast:(Maybe Anycast) workchain_id:int8 address:bits256
+            self.store_u8(2, 0b10u8)?;
+            self.store_bit(false)?;
+            let wc = (val.workchain & 0xff) as u8;
+            self.store_u8(8, wc)?;
+        } else {
+            // addr_var$11 anycast:(Maybe Anycast) addr_len:(## 9) workchain_id:int32
+            //   address:(bits addr_len)
+            self.store_u8(2, 0b11u8)?;
+            self.store_bit(false)?;
+            self.store_u32(9, 256)?;
+            // stored as unsigned, writing negative values to unaligned writer corrupts preceding bits
+            self.store_u32(32, val.workchain as u32)?;
+        }
         self.store_slice(&val.hash_part)?;
         Ok(self)
     }
@@ -211,18 +245,13 @@ impl CellBuilder {
     ///
     /// The reference is passed as `ArcCell` so it might be references from other cells.
     pub fn store_reference(&mut self, cell: &ArcCell) -> Result<&mut Self, TonCellError> {
-        let ref_count = self.references.len() + 1;
-        if ref_count > 4 {
-            return Err(TonCellError::cell_builder_error(format!(
-                "Cell must contain at most 4 references, got {}",
-                ref_count
-            )));
-        }
+        self.ensure_capacity(0, 1)?;
         self.references.push(cell.clone());
         Ok(self)
     }
 
     pub fn store_references(&mut self, refs: &[ArcCell]) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(0, refs.len())?;
         for r in refs {
             self.store_reference(r)?;
         }
@@ -250,12 +279,14 @@ impl CellBuilder {
     }
 
     pub fn store_cell_data(&mut self, cell: &Cell) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(cell.bit_len(), 0)?;
         let mut parser = cell.parser();
         self.store_remaining_bits(&mut parser)?;
         Ok(self)
     }
 
     pub fn store_cell(&mut self, cell: &Cell) -> Result<&mut Self, TonCellError> {
+        self.ensure_capacity(cell.bit_len(), cell.references.len())?;
         self.store_cell_data(cell)?;
         self.store_references(cell.references.as_slice())?;
         Ok(self)
@@ -319,8 +350,77 @@ impl CellBuilder {
         self.store_cell(&dict_cell)
     }
 
+    /// Stores `data` in snake format: bytes which don't fit into this cell are stored in a chain
+    /// of cells, each one referencing the next one.
+    ///
+    /// No reference is added if `data` fits into this cell.
+    pub fn store_bytes_snake(&mut self, data: &[u8]) -> Result<&mut Self, TonCellError> {
+        self.split_to_ref_if_needed(data.len() * 8, data)
+    }
+
+    /// Stores `bit_len` bits of `data`. If they don't fit into this cell, the cell is filled
+    /// with whole bytes and the rest is spilled into a chain of referenced continuation cells,
+    /// each one referencing the next one (snake format).
+    pub fn split_to_ref_if_needed(
+        &mut self,
+        bit_len: usize,
+        data: &[u8],
+    ) -> Result<&mut Self, TonCellError> {
+        if bit_len > data.len() * 8 {
+            return Err(TonCellError::cell_builder_error(format!(
+                "Can't store {} bits from {} bytes",
+                bit_len,
+                data.len()
+            )));
+        }
+        if bit_len <= self.remaining_bits() {
+            return self.store_bits(bit_len, data);
+        }
+        self.ensure_capacity(0, 1)?;
+
+        let head_len = self.remaining_bits() / 8;
+        self.store_slice(&data[..head_len])?;
+        let tail = &data[head_len..];
+        let tail_bits = bit_len - head_len * 8;
+
+        let mut next: Option<Cell> = None;
+        let chunk_bits = MAX_CELL_BITS / 8 * 8;
+        let chunk_count = tail_bits.div_ceil(chunk_bits);
+        for i in (0..chunk_count).rev() {
+            let start = i * chunk_bits;
+            let mut builder = CellBuilder::new();
+            builder.label = self.label;
+            builder.store_bits(chunk_bits.min(tail_bits - start), &tail[start / 8..])?;
+            if let Some(cell) = next {
+                builder.store_child(cell)?;
+            }
+            next = Some(builder.build()?);
+        }
+        if let Some(cell) = next {
+            self.store_child(cell)?;
+        }
+        Ok(self)
+    }
+
     pub fn remaining_bits(&self) -> usize {
-        MAX_CELL_BITS - self.bits_to_write
+        MAX_CELL_BITS.saturating_sub(self.bits_to_write)
+    }
+
+    pub fn remaining_refs(&self) -> usize {
+        MAX_CELL_REFERENCES.saturating_sub(self.references.len())
+    }
+
+    fn ensure_capacity(&self, bits: usize, refs: usize) -> Result<(), TonCellError> {
+        if bits > self.remaining_bits() || refs > self.remaining_refs() {
+            return Err(TonCellError::CellBuilderOverflow {
+                label: self.label,
+                requested_bits: bits,
+                requested_refs: refs,
+                remaining_bits: self.remaining_bits(),
+                remaining_refs: self.remaining_refs(),
+            });
+        }
+        Ok(())
     }
 
     pub fn build(&mut self) -> Result<Cell, TonCellError> {
@@ -400,7 +500,7 @@ mod tests {
 
     use crate::cell::builder::extend_and_invert_bits;
     use crate::cell::dict::predefined_readers::{key_reader_u8, val_reader_uint};
-    use crate::cell::{CellBuilder, TonCellError};
+    use crate::cell::{Cell, CellBuilder, TonCellError};
     use crate::types::TonAddress;
 
     #[test]
@@ -686,4 +786,118 @@ mod tests {
         assert_eq!(data, parsed);
         Ok(())
     }
+
+    #[test]
+    fn test_store_bytes_snake() -> Result<(), TonCellError> {
+        let data: Vec<u8> = (0..=255).cycle().take(300).collect();
+        let cell = CellBuilder::new()
+            .store_u32(32, 0)?
+            .store_bytes_snake(&data)?
+            .build()?;
+        assert_eq!(cell.bit_len(), 1016);
+        assert_eq!(cell.references().len(), 1);
+        let child = cell.reference(0)?;
+        assert_eq!(child.bit_len(), 1016);
+        assert_eq!(child.references().len(), 1);
+        assert_eq!(child.reference(0)?.bit_len(), 50 * 8);
+        assert!(child.reference(0)?.references().is_empty());
+
+        let mut parser = cell.parser();
+        parser.load_u32(32)?;
+        assert_eq!(parser.load_bytes_snake()?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn test_store_bytes_snake_exact_boundary() -> Result<(), TonCellError> {
+        let data = vec![0xAB; 127 + 127];
+        let cell = CellBuilder::new().store_bytes_snake(&data)?.build()?;
+        assert_eq!(cell.references().len(), 1);
+        assert!(cell.reference(0)?.references().is_empty());
+        assert_eq!(cell.parser().load_bytes_snake()?, data);
+
+        let data = vec![0xCD; 123];
+        let cell = CellBuilder::new()
+            .store_u32(32, 0)?
+            .store_bytes_snake(&data)?
+            .build()?;
+        assert!(cell.references().is_empty());
+
+        let cell = CellBuilder::new().store_bytes_snake(&[])?.build()?;
+        assert_eq!(cell.bit_len(), 0);
+        assert!(cell.references().is_empty());
+        assert!(cell.parser().load_bytes_snake()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_overflow_label() -> Result<(), TonCellError> {
+        let mut builder = CellBuilder::new();
+        builder.store_slice(&[0; 120])?;
+        assert_eq!(builder.remaining_bits(), 63);
+        assert_eq!(builder.remaining_refs(), 4);
+        let err = builder.labeled("query_id").store_u64(64, 1).err().unwrap();
+        assert!(matches!(
+            err,
+            TonCellError::CellBuilderOverflow {
+                label: Some("query_id"),
+                requested_bits: 64,
+                requested_refs: 0,
+                remaining_bits: 63,
+                remaining_refs: 4,
+            }
+        ));
+        assert!(err.to_string().contains("query_id"));
+        // failed store doesn't change the builder
+        assert_eq!(builder.remaining_bits(), 63);
+
+        for _ in 0..4 {
+            builder.store_child(Cell::default())?;
+        }
+        assert_eq!(builder.remaining_refs(), 0);
+        let err = builder.labeled("payload").store_child(Cell::default());
+        assert!(matches!(
+            err,
+            Err(TonCellError::CellBuilderOverflow {
+                label: Some("payload"),
+                requested_refs: 1,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_to_ref_if_needed() -> Result<(), TonCellError> {
+        let data: Vec<u8> = (0..=255).cycle().take(300).collect();
+        let cell = CellBuilder::new()
+            .store_u32(32, 0)?
+            .split_to_ref_if_needed(300 * 8 - 3, &data)?
+            .build()?;
+
+        let last = CellBuilder::new()
+            .store_bits(50 * 8 - 3, &data[250..])?
+            .build()?;
+        let middle = CellBuilder::new()
+            .store_slice(&data[123..250])?
+            .store_child(last)?
+            .build()?;
+        let manual = CellBuilder::new()
+            .store_u32(32, 0)?
+            .store_slice(&data[..123])?
+            .store_child(middle)?
+            .build()?;
+        assert_eq!(cell, manual);
+
+        let mut builder = CellBuilder::new();
+        builder.store_u32(32, 0)?;
+        for _ in 0..4 {
+            builder.store_child(Cell::default())?;
+        }
+        assert!(matches!(
+            builder.split_to_ref_if_needed(300 * 8, &data),
+            Err(TonCellError::CellBuilderOverflow { .. })
+        ));
+        Ok(())
+    }
 }
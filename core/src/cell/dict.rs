@@ -1,11 +1,15 @@
+mod augmented_cell_dictionary;
 mod builder;
+mod cell_dictionary;
 mod leading_bit_utils;
 mod parser;
 pub mod predefined_readers;
 pub mod predefined_writers;
 mod types;
 
+pub use augmented_cell_dictionary::{AugValue, AugmentedCellDictionary};
 pub(crate) use builder::DictBuilder;
+pub use cell_dictionary::CellDictionary;
 pub(crate) use parser::DictParser;
 pub use types::{KeyReader, SnakeFormatDict, ValReader, ValWriter};
 
@@ -0,0 +1,165 @@
+use std::collections::BTreeMap;
+
+use super::cell_dictionary::read_label;
+use crate::cell::dict::CellDictionary;
+use crate::cell::{ArcCell, Cell, CellParser, CellSlice, TonCellError};
+
+/// Extra data stored at each node of an augmented dictionary
+pub trait AugValue: Sized + Clone {
+    fn parse(parser: &mut CellParser) -> Result<Self, TonCellError>;
+}
+
+/// Augmented dictionary (`HashmapAug n X Y`) with untyped values and extra data `A` at each node.
+///
+/// The extra data of a fork aggregates the extra data of its children, e.g. the total balance
+/// of accounts or the minimal lt of queued messages.
+///
+/// ```raw
+/// ahm_edge#_ label:(HmLabel ~l n) node:(HashmapAugNode m X Y) = HashmapAug n X Y;
+/// ahmn_leaf#_ extra:Y value:X = HashmapAugNode 0 X Y;
+/// ahmn_fork#_ left:^(HashmapAug n X Y) right:^(HashmapAug n X Y) extra:Y = HashmapAugNode (n + 1) X Y;
+/// ahme_empty$0 extra:Y = HashmapAugE n X Y;
+/// ahme_root$1 root:^(HashmapAug n X Y) extra:Y = HashmapAugE n X Y;
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct AugmentedCellDictionary<A: AugValue> {
+    dict: CellDictionary,
+    root_augment: A,
+    /// Extra data of nodes by their position: the key bits preceding the fork, or the full key of a leaf
+    augments: BTreeMap<Vec<bool>, A>,
+}
+
+impl<A: AugValue> AugmentedCellDictionary<A> {
+    /// Reads the dictionary from its root cell (`HashmapAug n X Y`).
+    pub fn from_cell(
+        cell: &Cell,
+        key_bits: u16,
+    ) -> Result<AugmentedCellDictionary<A>, TonCellError> {
+        let mut entries = BTreeMap::new();
+        let mut augments = BTreeMap::new();
+        let mut prefix = Vec::with_capacity(key_bits as usize);
+        let root_augment = read_node(
+            &ArcCell::new(cell.clone()),
+            key_bits as usize,
+            &mut prefix,
+            &mut entries,
+            &mut augments,
+        )?;
+        Ok(AugmentedCellDictionary {
+            dict: CellDictionary::from_entries(key_bits, entries),
+            root_augment,
+            augments,
+        })
+    }
+
+    /// Reads the dictionary from `HashmapAugE n X Y`: an optional reference to the root
+    /// followed by the extra data of the whole dictionary.
+    pub fn load_hashmap_aug_e(
+        parser: &mut CellParser,
+        key_bits: u16,
+    ) -> Result<AugmentedCellDictionary<A>, TonCellError> {
+        let root = parser.load_maybe_cell_ref()?;
+        let root_augment = A::parse(parser)?;
+        let (dict, augments) = match root {
+            Some(root) => {
+                let dict = Self::from_cell(&root, key_bits)?;
+                (dict.dict, dict.augments)
+            }
+            None => (CellDictionary::new(key_bits), BTreeMap::new()),
+        };
+        Ok(AugmentedCellDictionary {
+            dict,
+            root_augment,
+            augments,
+        })
+    }
+
+    /// Extra data of the whole dictionary
+    pub fn root_augment(&self) -> &A {
+        &self.root_augment
+    }
+
+    /// Extra data of the value at `key`
+    pub fn get_augment(&self, key: &[bool]) -> Option<&A> {
+        if key.len() != self.dict.key_bits() as usize {
+            return None;
+        }
+        self.augments.get(key)
+    }
+
+    /// Extra data of the node at `prefix`: a fork whose children diverge right after `prefix`,
+    /// or a leaf if `prefix` is a full key.
+    pub fn node_augment(&self, prefix: &[bool]) -> Option<&A> {
+        self.augments.get(prefix)
+    }
+
+    pub fn get(&self, key: &[bool]) -> Option<CellSlice> {
+        self.dict.get(key)
+    }
+
+    /// Iterates over entries with their extra data in the ascending order of keys.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<bool>, &A, CellSlice)> + '_ {
+        self.dict.iter().filter_map(|(key, value)| {
+            let augment = self.augments.get(&key)?;
+            Some((key, augment, value))
+        })
+    }
+
+    /// Values without extra data
+    pub fn as_dictionary(&self) -> &CellDictionary {
+        &self.dict
+    }
+
+    pub fn key_bits(&self) -> u16 {
+        self.dict.key_bits()
+    }
+
+    pub fn len(&self) -> usize {
+        self.dict.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dict.is_empty()
+    }
+}
+
+/// Reads `ahm_edge` with `remaining` key bits left after `prefix`, returns the extra data of the node.
+fn read_node<A: AugValue>(
+    cell: &ArcCell,
+    remaining: usize,
+    prefix: &mut Vec<bool>,
+    entries: &mut BTreeMap<Vec<bool>, CellSlice>,
+    augments: &mut BTreeMap<Vec<bool>, A>,
+) -> Result<A, TonCellError> {
+    let prefix_len = prefix.len();
+    let mut parser = cell.parser();
+    let label_len = read_label(&mut parser, remaining, prefix)?;
+    let remaining = remaining - label_len;
+    let augment = if remaining == 0 {
+        // ahmn_leaf#_ extra:Y value:X
+        let augment = A::parse(&mut parser)?;
+        let start_bit = cell.bit_len() - parser.remaining_bits();
+        let start_ref = cell.references().len() - parser.remaining_refs();
+        let value = CellSlice::new(
+            cell,
+            start_bit,
+            cell.bit_len(),
+            start_ref,
+            cell.references().len(),
+        )?;
+        entries.insert(prefix.clone(), value);
+        augment
+    } else {
+        // ahmn_fork#_ left:^(HashmapAug n X Y) right:^(HashmapAug n X Y) extra:Y
+        for bit in [false, true] {
+            let child = parser.next_reference()?;
+            prefix.push(bit);
+            read_node(&child, remaining - 1, prefix, entries, augments)?;
+            prefix.pop();
+        }
+        A::parse(&mut parser)?
+    };
+    augments.insert(prefix.clone(), augment.clone());
+    prefix.truncate(prefix_len);
+    Ok(augment)
+}
@@ -0,0 +1,276 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::cell::{ArcCell, Cell, CellBuilder, CellParser, CellSlice, TonCellError};
+
+/// Dictionary (`Hashmap n X`) with untyped values, read from the bit-patricia trie in cells.
+///
+/// Keys are sequences of `key_bits` bits, values are slices of the leaf cells following the labels.
+/// Unlike `CellParser::load_dict`, values aren't parsed, so the dictionary can be read before
+/// the value type is known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellDictionary {
+    key_bits: u16,
+    entries: BTreeMap<Vec<bool>, CellSlice>,
+}
+
+impl CellDictionary {
+    pub fn new(key_bits: u16) -> CellDictionary {
+        CellDictionary {
+            key_bits,
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Reads the dictionary from its root cell (`Hashmap n X`).
+    ///
+    /// An empty cell is read as an empty dictionary, the way `to_cell` serializes it.
+    pub fn from_cell(cell: &Cell, key_bits: u16) -> Result<CellDictionary, TonCellError> {
+        if cell.bit_len() == 0 && cell.references().is_empty() {
+            return Ok(CellDictionary::new(key_bits));
+        }
+        Self::from_root(&Arc::new(cell.clone()), key_bits)
+    }
+
+    /// Reads the dictionary from `HashmapE n X`: a bit followed by an optional reference to the root.
+    pub fn load_hashmap_e(
+        parser: &mut CellParser,
+        key_bits: u16,
+    ) -> Result<CellDictionary, TonCellError> {
+        match parser.load_maybe_cell_ref()? {
+            Some(root) => Self::from_root(&root, key_bits),
+            None => Ok(CellDictionary::new(key_bits)),
+        }
+    }
+
+    pub(super) fn from_entries(
+        key_bits: u16,
+        entries: BTreeMap<Vec<bool>, CellSlice>,
+    ) -> CellDictionary {
+        CellDictionary { key_bits, entries }
+    }
+
+    fn from_root(root: &ArcCell, key_bits: u16) -> Result<CellDictionary, TonCellError> {
+        let mut entries = BTreeMap::new();
+        let mut prefix = Vec::with_capacity(key_bits as usize);
+        read_node(root, key_bits as usize, &mut prefix, &mut entries)?;
+        Ok(CellDictionary { key_bits, entries })
+    }
+
+    pub fn key_bits(&self) -> u16 {
+        self.key_bits
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn get(&self, key: &[bool]) -> Option<CellSlice> {
+        self.entries.get(key).cloned()
+    }
+
+    /// Returns the value of unsigned integer `key`, `None` if it doesn't fit `key_bits`.
+    pub fn get_uint(&self, key: &BigUint) -> Option<CellSlice> {
+        let key_bits = self.key_bits as u64;
+        if key.bits() > key_bits {
+            return None;
+        }
+        let key: Vec<bool> = (0..key_bits).rev().map(|i| key.bit(i)).collect();
+        self.get(&key)
+    }
+
+    /// Sets the value of `key`, which must be exactly `key_bits` long.
+    pub fn set(&mut self, key: &[bool], value: Cell) -> Result<(), TonCellError> {
+        if key.len() != self.key_bits as usize {
+            return Err(TonCellError::InvalidInput(format!(
+                "Invalid key length: expected {}, got {}",
+                self.key_bits,
+                key.len()
+            )));
+        }
+        self.entries
+            .insert(key.to_vec(), CellSlice::full_cell(value)?);
+        Ok(())
+    }
+
+    /// Removes `key`, returns `false` if it wasn't present.
+    pub fn delete(&mut self, key: &[bool]) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    /// Serializes the dictionary as `Hashmap n X`.
+    ///
+    /// Labels use the shortest of `hml_short`, `hml_long` and `hml_same` encodings,
+    /// so cells of a dictionary read with `from_cell` are reproduced exactly.
+    /// An empty dictionary is serialized as an empty cell, as `CellBuilder::store_dict` does.
+    pub fn to_cell(&self) -> Result<Cell, TonCellError> {
+        let mut builder = CellBuilder::new();
+        if !self.entries.is_empty() {
+            let entries: Vec<_> = self.entries.iter().collect();
+            write_node(&mut builder, &entries, 0, self.key_bits as usize)?;
+        }
+        builder.build()
+    }
+
+    /// Iterates over entries in the ascending order of keys.
+    pub fn iter(&self) -> impl Iterator<Item = (Vec<bool>, CellSlice)> + '_ {
+        self.entries
+            .iter()
+            .map(|(key, value)| (key.clone(), value.clone()))
+    }
+}
+
+/// Reads `hm_edge` with `remaining` key bits left after `prefix`.
+fn read_node(
+    cell: &ArcCell,
+    remaining: usize,
+    prefix: &mut Vec<bool>,
+    entries: &mut BTreeMap<Vec<bool>, CellSlice>,
+) -> Result<(), TonCellError> {
+    let prefix_len = prefix.len();
+    let mut parser = cell.parser();
+    let label_len = read_label(&mut parser, remaining, prefix)?;
+    let remaining = remaining - label_len;
+    if remaining == 0 {
+        let start_bit = cell.bit_len() - parser.remaining_bits();
+        let value = CellSlice::new(cell, start_bit, cell.bit_len(), 0, cell.references().len())?;
+        entries.insert(prefix.clone(), value);
+    } else {
+        // hmn_fork#_ left:^(Hashmap n X) right:^(Hashmap n X)
+        for bit in [false, true] {
+            let child = parser.next_reference()?;
+            prefix.push(bit);
+            read_node(&child, remaining - 1, prefix, entries)?;
+            prefix.pop();
+        }
+    }
+    prefix.truncate(prefix_len);
+    Ok(())
+}
+
+/// Writes `hm_edge` for `entries` sorted by keys, which share the first `offset` bits.
+fn write_node(
+    builder: &mut CellBuilder,
+    entries: &[(&Vec<bool>, &CellSlice)],
+    offset: usize,
+    remaining: usize,
+) -> Result<(), TonCellError> {
+    let first = &entries[0].0[offset..];
+    if entries.len() == 1 {
+        write_label(builder, first, remaining)?;
+        builder.store_cell(&entries[0].1.into_cell()?)?;
+        return Ok(());
+    }
+    // keys are sorted, so the first and the last ones have the shortest common prefix
+    let last = &entries[entries.len() - 1].0[offset..];
+    let prefix_len = first.iter().zip(last).take_while(|(a, b)| a == b).count();
+    write_label(builder, &first[..prefix_len], remaining)?;
+    let fork_bit = offset + prefix_len;
+    let split = entries.partition_point(|(key, _)| !key[fork_bit]);
+    for children in [&entries[..split], &entries[split..]] {
+        let mut child = CellBuilder::new();
+        write_node(
+            &mut child,
+            children,
+            fork_bit + 1,
+            remaining - prefix_len - 1,
+        )?;
+        builder.store_child(child.build()?)?;
+    }
+    Ok(())
+}
+
+/// Writes `HmLabel ~n m` in its shortest form, preferring `hml_short` on ties.
+fn write_label(
+    builder: &mut CellBuilder,
+    label: &[bool],
+    max_len: usize,
+) -> Result<(), TonCellError> {
+    let len = label.len();
+    let len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    let short_size = 2 + 2 * len;
+    let long_size = 2 + len_bits + len;
+    let same_size = if len > 0 && label.iter().all(|bit| *bit == label[0]) {
+        3 + len_bits
+    } else {
+        usize::MAX
+    };
+    if same_size < short_size {
+        builder
+            .store_bit(true)?
+            .store_bit(true)?
+            .store_bit(label[0])?;
+        builder.store_u64(len_bits, len as u64)?;
+    } else if long_size < short_size {
+        builder.store_bit(true)?.store_bit(false)?;
+        builder.store_u64(len_bits, len as u64)?;
+        store_bits(builder, label)?;
+    } else {
+        builder.store_bit(false)?;
+        for _ in 0..len {
+            builder.store_bit(true)?;
+        }
+        builder.store_bit(false)?;
+        store_bits(builder, label)?;
+    }
+    Ok(())
+}
+
+fn store_bits(builder: &mut CellBuilder, bits: &[bool]) -> Result<(), TonCellError> {
+    for bit in bits {
+        builder.store_bit(*bit)?;
+    }
+    Ok(())
+}
+
+/// Reads `HmLabel ~n m` appending its bits to `prefix`, returns the label length.
+pub(super) fn read_label(
+    parser: &mut CellParser,
+    max_len: usize,
+    prefix: &mut Vec<bool>,
+) -> Result<usize, TonCellError> {
+    // bit length of `#<= m`
+    let len_bits = (usize::BITS - max_len.leading_zeros()) as usize;
+    let len = if !parser.load_bit()? {
+        // hml_short$0 len:(Unary ~n) s:(n * Bit)
+        let len = parser.load_unary_length()?;
+        for _ in 0..len {
+            prefix.push(parser.load_bit()?);
+        }
+        len
+    } else if !parser.load_bit()? {
+        // hml_long$10 n:(#<= m) s:(n * Bit)
+        let len = load_label_len(parser, len_bits)?;
+        for _ in 0..len {
+            prefix.push(parser.load_bit()?);
+        }
+        len
+    } else {
+        // hml_same$11 v:Bit n:(#<= m)
+        let bit = parser.load_bit()?;
+        let len = load_label_len(parser, len_bits)?;
+        prefix.extend(std::iter::repeat_n(bit, len));
+        len
+    };
+    if len > max_len {
+        return Err(TonCellError::InvalidCellData(format!(
+            "Dictionary label length {} exceeds remaining key length {}",
+            len, max_len
+        )));
+    }
+    Ok(len)
+}
+
+fn load_label_len(parser: &mut CellParser, len_bits: usize) -> Result<usize, TonCellError> {
+    if len_bits == 0 {
+        Ok(0)
+    } else {
+        Ok(parser.load_u64(len_bits)? as usize)
+    }
+}
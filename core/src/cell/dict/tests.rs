@@ -1,8 +1,9 @@
 // tests cover parser & builder together, so make sense to keep them in the same module
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::ops::Deref;
 
 use num_bigint::BigUint;
+use proptest::prelude::*;
 use tokio_test::assert_ok;
 
 use crate::cell::dict::predefined_readers::{
@@ -10,7 +11,9 @@ use crate::cell::dict::predefined_readers::{
     key_reader_uint, val_reader_ref_cell, val_reader_uint,
 };
 use crate::cell::dict::predefined_writers::{val_writer_ref_cell, val_writer_unsigned_min_size};
+use crate::cell::dict::{AugmentedCellDictionary, CellDictionary};
 use crate::cell::{ArcCell, BagOfCells, Cell, CellBuilder};
+use crate::types::{CoinBalance, Coins, MessageQueueInfo, StoragePrices, StoragePricesHistory};
 
 #[test]
 fn test_blockchain_data() -> anyhow::Result<()> {
@@ -228,3 +231,231 @@ fn test_reader_cell() -> anyhow::Result<()> {
     assert_eq!(data, parsed);
     Ok(())
 }
+
+#[test]
+fn test_cell_dictionary_storage_prices() -> anyhow::Result<()> {
+    let epochs = [
+        StoragePrices {
+            utime_since: 0,
+            bit_price_ps: 1,
+            cell_price_ps: 500,
+            mc_bit_price_ps: 1000,
+            mc_cell_price_ps: 500000,
+        },
+        StoragePrices {
+            utime_since: 1700000000,
+            bit_price_ps: 2,
+            cell_price_ps: 1000,
+            mc_bit_price_ps: 2000,
+            mc_cell_price_ps: 1000000,
+        },
+    ];
+    let writer = |builder: &mut CellBuilder, prices: StoragePrices| {
+        builder
+            .store_u8(8, 0xcc)?
+            .store_u32(32, prices.utime_since)?
+            .store_u64(64, prices.bit_price_ps)?
+            .store_u64(64, prices.cell_price_ps)?
+            .store_u64(64, prices.mc_bit_price_ps)?
+            .store_u64(64, prices.mc_cell_price_ps)?;
+        Ok(())
+    };
+    let data = HashMap::from([(0u32, epochs[0].clone()), (1u32, epochs[1].clone())]);
+    let mut builder = CellBuilder::new();
+    builder.store_dict(32, writer, data)?;
+    // config param 18
+    let param = builder.build()?;
+
+    let dict = CellDictionary::from_cell(&param, 32)?;
+    assert_eq!(dict.len(), 2);
+    for (i, (key, value)) in dict.iter().enumerate() {
+        assert_eq!(key.len(), 32);
+        assert_eq!(bits_to_uint(&key), BigUint::from(i));
+        let prices = StoragePrices::parse(&mut value.into_cell()?.parser())?;
+        assert_eq!(prices, epochs[i]);
+    }
+    let second = dict.get_uint(&BigUint::from(1u32)).unwrap();
+    assert_eq!(
+        dict.get(&[[false; 31].as_slice(), &[true]].concat()),
+        Some(second)
+    );
+    assert_eq!(dict.get_uint(&BigUint::from(2u32)), None);
+    assert_eq!(dict.get_uint(&(BigUint::from(1u32) << 32)), None);
+    assert_eq!(dict.get(&[true]), None);
+
+    let history = StoragePricesHistory::parse(&param)?;
+    assert_eq!(history.epochs(), epochs.as_slice());
+    Ok(())
+}
+
+#[test]
+fn test_cell_dictionary_matches_load_dict() -> anyhow::Result<()> {
+    let data: HashMap<BigUint, ArcCell> = [0u32, 1, 2, 6, 200, 255]
+        .into_iter()
+        .map(|key| -> anyhow::Result<(BigUint, ArcCell)> {
+            let value = CellBuilder::new().store_u32(16, key)?.build()?;
+            Ok((BigUint::from(key), ArcCell::new(value)))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let mut builder = CellBuilder::new();
+    builder.store_dict(8, val_writer_ref_cell, data.clone())?;
+    let dict_cell = builder.build()?;
+
+    let dict = CellDictionary::from_cell(&dict_cell, 8)?;
+    assert_eq!(dict.len(), data.len());
+    for (key, value) in data.iter() {
+        let slice = dict.get_uint(key).unwrap();
+        assert_eq!(slice.reference(0)?, value);
+    }
+    let keys: Vec<BigUint> = dict.iter().map(|(key, _)| bits_to_uint(&key)).collect();
+    let mut expected: Vec<BigUint> = data.into_keys().collect();
+    expected.sort();
+    assert_eq!(keys, expected);
+
+    let empty = CellBuilder::new().store_bit(false)?.build()?;
+    let dict = CellDictionary::load_hashmap_e(&mut empty.parser(), 8)?;
+    assert!(dict.is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_cell_dictionary_set_delete() -> anyhow::Result<()> {
+    let data: HashMap<u32, ArcCell> = [0u32, 1, 2, 6, 7, 200, 255]
+        .into_iter()
+        .map(|key| -> anyhow::Result<(u32, ArcCell)> {
+            let value = CellBuilder::new().store_u32(16, key)?.build()?;
+            Ok((key, ArcCell::new(value)))
+        })
+        .collect::<anyhow::Result<_>>()?;
+    let mut dict = CellDictionary::new(8);
+    for (key, value) in data.iter() {
+        let value = CellBuilder::new().store_reference(value)?.build()?;
+        dict.set(&uint_to_bits(*key as u64, 8), value)?;
+    }
+    let mut builder = CellBuilder::new();
+    builder.store_dict(8, val_writer_ref_cell, data.clone())?;
+    assert_eq!(dict.to_cell()?, builder.build()?);
+
+    assert!(dict.delete(&uint_to_bits(200, 8)));
+    assert!(!dict.delete(&uint_to_bits(200, 8)));
+    let mut remaining = data.clone();
+    remaining.remove(&200);
+    let mut builder = CellBuilder::new();
+    builder.store_dict(8, val_writer_ref_cell, remaining)?;
+    assert_eq!(dict.to_cell()?, builder.build()?);
+
+    assert!(dict.set(&uint_to_bits(1, 7), Cell::default()).is_err());
+    assert_eq!(CellDictionary::new(8).to_cell()?, Cell::default());
+    Ok(())
+}
+
+#[test]
+fn test_augmented_cell_dictionary() -> anyhow::Result<()> {
+    // leaves of 8-bit keys 0b00000000 and 0b00000001 with min_lt extra and a 16-bit value
+    let leaf = |min_lt: u64, value: u32| -> anyhow::Result<Cell> {
+        let mut builder = CellBuilder::new();
+        // empty hml_short$0 label, the fork consumed the last key bit
+        builder.store_bit(false)?.store_bit(false)?;
+        builder.store_u64(64, min_lt)?.store_u32(16, value)?;
+        Ok(builder.build()?)
+    };
+    // the keys differ in the last bit, so the root label is "0000000"
+    let fork = |left: Cell, right: Cell, min_lt: u64| -> anyhow::Result<Cell> {
+        let mut builder = CellBuilder::new();
+        // hml_same$11 v:0 n:7
+        builder.store_bit(true)?.store_bit(true)?.store_bit(false)?;
+        builder.store_u8(4, 7)?;
+        builder.store_child(left)?.store_child(right)?;
+        builder.store_u64(64, min_lt)?;
+        Ok(builder.build()?)
+    };
+    let root = fork(leaf(20, 0xaaaa)?, leaf(10, 0xbbbb)?, 10)?;
+
+    let dict = AugmentedCellDictionary::<MessageQueueInfo>::from_cell(&root, 8)?;
+    assert_eq!(dict.len(), 2);
+    assert_eq!(dict.root_augment().min_lt, 10);
+    let key0 = uint_to_bits(0, 8);
+    let key1 = uint_to_bits(1, 8);
+    assert_eq!(dict.get_augment(&key0).map(|a| a.min_lt), Some(20));
+    assert_eq!(dict.get_augment(&key1).map(|a| a.min_lt), Some(10));
+    assert_eq!(dict.node_augment(&key0[..7]).map(|a| a.min_lt), Some(10));
+    assert_eq!(
+        dict.get(&key1)
+            .unwrap()
+            .into_cell()?
+            .parser()
+            .load_u32(16)?,
+        0xbbbb
+    );
+    let keys: Vec<_> = dict.iter().map(|(key, _, _)| key).collect();
+    assert_eq!(keys, vec![key0, key1]);
+
+    // ahme_root$1 root:^(HashmapAug n X Y) extra:Y
+    let with_root = CellBuilder::new()
+        .store_bit(true)?
+        .store_child(root)?
+        .store_u64(64, 10)?
+        .build()?;
+    let dict = AugmentedCellDictionary::<MessageQueueInfo>::load_hashmap_aug_e(
+        &mut with_root.parser(),
+        8,
+    )?;
+    assert_eq!(dict.len(), 2);
+
+    // ahme_empty$0 extra:Y
+    let empty = CellBuilder::new()
+        .store_bit(false)?
+        .store_coins(&BigUint::from(5u32))?
+        .store_bit(false)?
+        .build()?;
+    let dict =
+        AugmentedCellDictionary::<CoinBalance>::load_hashmap_aug_e(&mut empty.parser(), 256)?;
+    assert!(dict.is_empty());
+    assert_eq!(dict.root_augment().grams, Coins::new(BigUint::from(5u32)));
+    assert_eq!(dict.root_augment().other, None);
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn cell_dictionary_set_get(
+        key_bits in 1u16..=64,
+        keys in prop::collection::vec(any::<u64>(), 1..40),
+        deleted in prop::collection::vec(any::<prop::sample::Index>(), 0..10),
+    ) {
+        let mut dict = CellDictionary::new(key_bits);
+        let mut expected = BTreeMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            let key = uint_to_bits(*key, key_bits as usize);
+            let value = CellBuilder::new().store_u32(32, i as u32)?.build()?;
+            dict.set(&key, value.clone())?;
+            prop_assert_eq!(dict.get(&key).map(|v| v.into_cell()).transpose()?, Some(value.clone()));
+            expected.insert(key, value);
+        }
+        for index in deleted {
+            let key = uint_to_bits(*index.get(&keys), key_bits as usize);
+            prop_assert_eq!(dict.delete(&key), expected.remove(&key).is_some());
+            prop_assert_eq!(dict.get(&key), None);
+        }
+
+        let parsed = CellDictionary::from_cell(&dict.to_cell()?, key_bits)?;
+        prop_assert_eq!(parsed.len(), expected.len());
+        for (key, value) in expected.iter() {
+            let parsed_value = parsed.get(key).map(|v| v.into_cell()).transpose()?;
+            prop_assert_eq!(parsed_value.as_ref(), Some(value));
+        }
+        prop_assert_eq!(parsed.to_cell()?, dict.to_cell()?);
+    }
+}
+
+fn uint_to_bits(value: u64, bits: usize) -> Vec<bool> {
+    (0..bits)
+        .rev()
+        .map(|i| i < 64 && (value >> i) & 1 == 1)
+        .collect()
+}
+
+fn bits_to_uint(bits: &[bool]) -> BigUint {
+    bits.iter()
+        .fold(BigUint::from(0u32), |acc, bit| (acc << 1) + u32::from(*bit))
+}
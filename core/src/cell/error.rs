@@ -11,6 +11,20 @@ pub enum TonCellError {
     #[error("Cell builder error ({0})")]
     CellBuilderError(String),
 
+    #[error(
+        "Cell builder overflow (Label: {}, requested bits: {requested_bits}, requested refs: {requested_refs}, \
+        remaining bits: {remaining_bits}, remaining refs: {remaining_refs})",
+        .label.unwrap_or("none")
+    )]
+    CellBuilderOverflow {
+        /// Label set by `CellBuilder::labeled`
+        label: Option<&'static str>,
+        requested_bits: usize,
+        requested_refs: usize,
+        remaining_bits: usize,
+        remaining_refs: usize,
+    },
+
     #[error("Cell parser error ({0})")]
     CellParserError(String),
 
@@ -0,0 +1,324 @@
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+use crate::cell::cell_type::CellType;
+use crate::cell::{Cell, TonCellError, MAX_CELL_BITS, MAX_CELL_REFERENCES};
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+
+impl Cell {
+    /// Converts the cell tree to JSON of the following form:
+    ///
+    /// `{"data": "<bits>", "refs": [<cell>, ...], "exotic": "<type>"}`
+    ///
+    /// `data` uses the fift/ton-core bit string notation: uppercase hex digits and, if the bit length
+    /// is not a multiple of 4, the last digit is completed with a `1` bit followed by zeros
+    /// and the string ends with `_`.
+    /// `exotic` is one of `pruned_branch`, `library`, `merkle_proof` or `merkle_update`
+    /// and is omitted for ordinary cells.
+    /// Cells referenced more than once are repeated in place.
+    pub fn to_json_tree(&self) -> Value {
+        let mut node = Map::new();
+        node.insert(
+            "data".to_string(),
+            Value::String(bits_to_hex(&self.data, self.bit_len)),
+        );
+        node.insert(
+            "refs".to_string(),
+            Value::Array(self.references.iter().map(|r| r.to_json_tree()).collect()),
+        );
+        if let Some(exotic) = exotic_name(self.cell_type) {
+            node.insert("exotic".to_string(), Value::String(exotic.to_string()));
+        }
+        Value::Object(node)
+    }
+
+    /// Builds the cell tree from JSON produced by [`Cell::to_json_tree`]
+    pub fn from_json_tree(json: &Value) -> Result<Cell, TonCellError> {
+        let node = json
+            .as_object()
+            .ok_or_else(|| invalid_json("cell must be an object"))?;
+        let data = node
+            .get("data")
+            .and_then(Value::as_str)
+            .ok_or_else(|| invalid_json("`data` must be a string"))?;
+        let (data, bit_len) = hex_to_bits(data)?;
+        if bit_len > MAX_CELL_BITS {
+            return Err(invalid_json(format!(
+                "cell can't have more than {} bits, got {}",
+                MAX_CELL_BITS, bit_len
+            )));
+        }
+        let refs = match node.get("refs") {
+            None | Some(Value::Null) => vec![],
+            Some(Value::Array(refs)) => refs
+                .iter()
+                .map(|r| Cell::from_json_tree(r).map(Arc::new))
+                .collect::<Result<Vec<_>, _>>()?,
+            Some(_) => return Err(invalid_json("`refs` must be an array")),
+        };
+        if refs.len() > MAX_CELL_REFERENCES {
+            return Err(invalid_json(format!(
+                "cell can't have more than {} refs, got {}",
+                MAX_CELL_REFERENCES,
+                refs.len()
+            )));
+        }
+        let exotic = match node.get("exotic") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(exotic)) => Some(exotic.as_str()),
+            Some(_) => return Err(invalid_json("`exotic` must be a string")),
+        };
+        let cell = Cell::new(data, bit_len, refs, exotic.is_some())?;
+        if exotic_name(cell.cell_type) != exotic {
+            return Err(invalid_json(format!(
+                "exotic type {:?} doesn't match cell data, expected {:?}",
+                exotic,
+                exotic_name(cell.cell_type)
+            )));
+        }
+        Ok(cell)
+    }
+}
+
+#[cfg(feature = "cell-json")]
+impl serde::Serialize for Cell {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serde::Serialize::serialize(&self.to_json_tree(), serializer)
+    }
+}
+
+#[cfg(feature = "cell-json")]
+impl<'de> serde::Deserialize<'de> for Cell {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = <Value as serde::Deserialize>::deserialize(deserializer)?;
+        Cell::from_json_tree(&json).map_err(serde::de::Error::custom)
+    }
+}
+
+fn exotic_name(cell_type: CellType) -> Option<&'static str> {
+    match cell_type {
+        CellType::Ordinary => None,
+        CellType::PrunedBranch => Some("pruned_branch"),
+        CellType::Library => Some("library"),
+        CellType::MerkleProof => Some("merkle_proof"),
+        CellType::MerkleUpdate => Some("merkle_update"),
+    }
+}
+
+fn invalid_json(msg: impl AsRef<str>) -> TonCellError {
+    TonCellError::InvalidInput(format!("Invalid cell JSON: {}", msg.as_ref()))
+}
+
+pub(crate) fn bits_to_hex(data: &[u8], bit_len: usize) -> String {
+    let nibble = |i: usize| (data[i / 2] >> if i.is_multiple_of(2) { 4 } else { 0 }) & 0x0f;
+    let full_nibbles = bit_len / 4;
+    let mut hex = String::with_capacity(full_nibbles + 2);
+    for i in 0..full_nibbles {
+        hex.push(HEX_DIGITS[nibble(i) as usize] as char);
+    }
+    let rem_bits = bit_len % 4;
+    if rem_bits != 0 {
+        let mask = (0x0f << (4 - rem_bits)) & 0x0f;
+        let last = (nibble(full_nibbles) & mask) | (1 << (3 - rem_bits));
+        hex.push(HEX_DIGITS[last as usize] as char);
+        hex.push('_');
+    }
+    hex
+}
+
+fn hex_to_bits(hex: &str) -> Result<(Vec<u8>, usize), TonCellError> {
+    let (digits, completed) = match hex.strip_suffix('_') {
+        Some(digits) => (digits, true),
+        None => (hex, false),
+    };
+    let mut data = vec![0u8; digits.len().div_ceil(2)];
+    for (i, c) in digits.chars().enumerate() {
+        let nibble = c
+            .to_digit(16)
+            .ok_or_else(|| invalid_json(format!("invalid hex digit {:?} in `data`", c)))?;
+        data[i / 2] |= (nibble as u8) << if i.is_multiple_of(2) { 4 } else { 0 };
+    }
+    let mut bit_len = digits.len() * 4;
+    if completed {
+        // drop trailing zeros and the completion bit
+        loop {
+            if bit_len == 0 {
+                return Err(invalid_json("completion bit is missing in `data`"));
+            }
+            bit_len -= 1;
+            let mask = 1 << (7 - bit_len % 8);
+            let bit = data[bit_len / 8] & mask;
+            data[bit_len / 8] &= !mask;
+            if bit != 0 {
+                break;
+            }
+        }
+        data.truncate(bit_len.div_ceil(8));
+    }
+    Ok((data, bit_len))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::{ArcCell, BagOfCells, CellBuilder};
+    use crate::TonHash;
+
+    fn pruned_branch(cell: &Cell) -> anyhow::Result<Cell> {
+        let mut builder = CellBuilder::new();
+        builder.set_cell_is_exotic(true);
+        Ok(builder
+            .store_u8(8, 1)?
+            .store_u8(8, 1)?
+            .store_slice(&cell.get_hash(0))?
+            .store_u32(16, cell.get_depth(0) as u32)?
+            .build()?)
+    }
+
+    fn merkle_proof(cell: &Cell) -> anyhow::Result<Cell> {
+        let mut builder = CellBuilder::new();
+        builder.set_cell_is_exotic(true);
+        Ok(builder
+            .store_u8(8, 3)?
+            .store_slice(&cell.get_hash(0))?
+            .store_u32(16, cell.get_depth(0) as u32)?
+            .store_reference(&Arc::new(cell.clone()))?
+            .build()?)
+    }
+
+    #[test]
+    fn test_bits_to_hex() -> anyhow::Result<()> {
+        for (bits, hex) in [
+            ("", ""),
+            ("0", "4_"),
+            ("1", "C_"),
+            ("1010", "A"),
+            ("10101", "AC_"),
+            ("1111111", "FF_"),
+            ("11111111", "FF"),
+        ] {
+            let mut builder = CellBuilder::new();
+            for bit in bits.chars() {
+                builder.store_bit(bit == '1')?;
+            }
+            let cell = builder.build()?;
+            assert_eq!(bits_to_hex(cell.data(), cell.bit_len()), hex);
+            assert_eq!(hex_to_bits(hex)?, (cell.data().to_vec(), cell.bit_len()));
+        }
+        assert_eq!(hex_to_bits("ff")?, (vec![0xff], 8));
+        assert!(hex_to_bits("0_").is_err());
+        assert!(hex_to_bits("XY").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_tree_roundtrip() -> anyhow::Result<()> {
+        let leaf = CellBuilder::new().store_u8(5, 0x13)?.build()?.to_arc();
+        let inner = CellBuilder::new()
+            .store_u32(32, 0xdeadbeef)?
+            .store_reference(&leaf)?
+            .store_reference(&leaf)?
+            .build()?;
+        let root = CellBuilder::new()
+            .store_slice(&[0xAB; 127])?
+            .store_u8(7, 0x55)?
+            .store_reference(&inner.clone().to_arc())?
+            .store_reference(&ArcCell::default())?
+            .store_reference(&leaf)?
+            .store_reference(&leaf)?
+            .build()?;
+        let json = root.to_json_tree();
+        assert_eq!(json["refs"][0]["data"], "DEADBEEF");
+        assert_eq!(json["refs"][0]["refs"][1]["data"], "9C_");
+        assert_eq!(json["refs"][1]["data"], "");
+        assert!(json.get("exotic").is_none());
+        let parsed = Cell::from_json_tree(&json)?;
+        assert_eq!(parsed, root);
+
+        let text = serde_json::to_string(&json)?;
+        let parsed = Cell::from_json_tree(&serde_json::from_str(&text)?)?;
+        assert_eq!(parsed.cell_hash(), root.cell_hash());
+
+        // exotic cells
+        let proof_root = CellBuilder::new()
+            .store_u32(32, 0xdeadbeef)?
+            .store_reference(&pruned_branch(&inner)?.to_arc())?
+            .build()?;
+        let proof = merkle_proof(&proof_root)?;
+        let json = proof.to_json_tree();
+        assert_eq!(json["exotic"], "merkle_proof");
+        assert_eq!(json["refs"][0]["refs"][0]["exotic"], "pruned_branch");
+        let parsed = Cell::from_json_tree(&json)?;
+        assert_eq!(parsed, proof);
+        assert_eq!(parsed.get_hash(0), proof.get_hash(0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_tree_invalid() -> anyhow::Result<()> {
+        let library_data = format!("02{}", "00".repeat(32));
+        let cases = [
+            serde_json::json!("00"),
+            serde_json::json!({"refs": []}),
+            serde_json::json!({"data": "00", "refs": {}}),
+            serde_json::json!({"data": "00", "exotic": 2}),
+            serde_json::json!({"data": "00", "refs": [{}, {}, {}, {}, {}]}),
+            serde_json::json!({"data": "F".repeat(256)}),
+            serde_json::json!({"data": "01", "exotic": "pruned_branch"}),
+            // exotic type doesn't match data
+            serde_json::json!({"data": library_data, "exotic": "pruned_branch"}),
+        ];
+        for case in cases.iter() {
+            assert!(Cell::from_json_tree(case).is_err(), "{}", case);
+        }
+
+        let library = serde_json::json!({"data": library_data, "exotic": "library"});
+        assert!(Cell::from_json_tree(&library)?.is_exotic());
+        let ordinary = serde_json::json!({"data": library_data, "exotic": null});
+        assert!(!Cell::from_json_tree(&ordinary)?.is_exotic());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_tree_fixture() -> anyhow::Result<()> {
+        // tree written in ton-core bit string notation, hash calculated independently
+        let fixture = include_str!("../../resources/cell/cell_tree.json");
+        let cell = Cell::from_json_tree(&serde_json::from_str(fixture)?)?;
+        let expected: TonHash =
+            hex::decode("482b73c2fbfbc707b1d395e2451f7c1d2518443ac84576c379a23fa49c7ac279")?
+                .as_slice()
+                .try_into()?;
+        assert_eq!(cell.cell_hash(), expected);
+        assert_eq!(cell.bit_len(), 99);
+        assert_eq!(cell.reference(1)?.reference(0)?.bit_len(), 1);
+
+        let boc = BagOfCells::from_root(cell.clone()).serialize(true)?;
+        let parsed = BagOfCells::parse(&boc)?.single_root()?.to_json_tree();
+        assert_eq!(Cell::from_json_tree(&parsed)?, cell);
+        Ok(())
+    }
+
+    #[cfg(feature = "cell-json")]
+    #[test]
+    fn test_cell_serde() -> anyhow::Result<()> {
+        #[derive(serde::Serialize, serde::Deserialize)]
+        struct Payload {
+            body: Cell,
+        }
+
+        let body = CellBuilder::new()
+            .store_u32(32, 0x0f8a7ea5)?
+            .store_reference(&CellBuilder::new().store_bit(true)?.build()?.to_arc())?
+            .build()?;
+        let text = serde_json::to_string(&Payload { body: body.clone() })?;
+        assert_eq!(
+            text,
+            r#"{"body":{"data":"0F8A7EA5","refs":[{"data":"C_","refs":[]}]}}"#
+        );
+        let payload: Payload = serde_json::from_str(&text)?;
+        assert_eq!(payload.body, body);
+        Ok(())
+    }
+}
@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::cell::{ArcCell, Cell, TonCellError};
+use crate::TonHash;
+
+const PRUNED_BRANCH_TYPE: u8 = 1;
+const MERKLE_UPDATE_TYPE: u8 = 4;
+
+/// Applies `update` (`MERKLE_UPDATE` cell, e.g. `state_update` of a block) to `base`,
+/// returns the new tree.
+///
+/// `base` must be the full tree the update was built for. Pruned branches of the new tree
+/// are replaced with the cells of `base`, so unchanged subtrees are shared with it.
+pub fn apply_merkle_update(base: &ArcCell, update: &Cell) -> Result<Cell, TonCellError> {
+    if !update.is_exotic() || update.data().first() != Some(&MERKLE_UPDATE_TYPE) {
+        return Err(TonCellError::InvalidInput(
+            "Cell is not a Merkle update".to_string(),
+        ));
+    }
+    let old = update.reference(0)?;
+    let new = update.reference(1)?;
+    if old.get_hash(0) != base.get_hash(0) {
+        return Err(TonCellError::InvalidInput(format!(
+            "Merkle update is built for {}, got {}",
+            hex::encode(old.get_hash(0)),
+            hex::encode(base.get_hash(0))
+        )));
+    }
+    let mut known = HashMap::new();
+    collect_known(old, base, &mut known)?;
+    let mut rebuilt = HashMap::new();
+    let result = rebuild(new, &known, &mut rebuilt)?;
+    Ok(result.as_ref().clone())
+}
+
+/// Indexes cells of `base` visited by the old tree of the update by their hashes.
+fn collect_known(
+    old: &ArcCell,
+    base: &ArcCell,
+    known: &mut HashMap<TonHash, ArcCell>,
+) -> Result<(), TonCellError> {
+    if known.insert(old.get_hash(0), base.clone()).is_some() || is_pruned(old) {
+        return Ok(());
+    }
+    if old.references().len() != base.references().len() {
+        return Err(TonCellError::InvalidInput(
+            "Merkle update doesn't match the base tree".to_string(),
+        ));
+    }
+    for (old_child, base_child) in old.references().iter().zip(base.references()) {
+        collect_known(old_child, base_child, known)?;
+    }
+    Ok(())
+}
+
+/// Replaces pruned branches of `new` with the known cells.
+fn rebuild(
+    new: &ArcCell,
+    known: &HashMap<TonHash, ArcCell>,
+    rebuilt: &mut HashMap<TonHash, ArcCell>,
+) -> Result<ArcCell, TonCellError> {
+    let hash = new.get_hash(0);
+    if is_pruned(new) {
+        return known.get(&hash).cloned().ok_or_else(|| {
+            TonCellError::InvalidInput(format!(
+                "Merkle update refers to cell {} missing in the base tree",
+                hex::encode(hash)
+            ))
+        });
+    }
+    if let Some(cell) = rebuilt.get(&hash) {
+        return Ok(cell.clone());
+    }
+    let references = new
+        .references()
+        .iter()
+        .map(|child| rebuild(child, known, rebuilt))
+        .collect::<Result<Vec<_>, _>>()?;
+    let cell = Arc::new(Cell::new(
+        new.data().to_vec(),
+        new.bit_len(),
+        references,
+        new.is_exotic(),
+    )?);
+    rebuilt.insert(hash, cell.clone());
+    Ok(cell)
+}
+
+fn is_pruned(cell: &Cell) -> bool {
+    cell.is_exotic() && cell.data().first() == Some(&PRUNED_BRANCH_TYPE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    fn exotic(data: Vec<u8>, references: Vec<ArcCell>) -> Result<ArcCell, TonCellError> {
+        let bit_len = data.len() * 8;
+        Ok(Arc::new(Cell::new(data, bit_len, references, true)?))
+    }
+
+    fn pruned(cell: &Cell) -> Result<ArcCell, TonCellError> {
+        let mut data = vec![PRUNED_BRANCH_TYPE, 1];
+        data.extend_from_slice(&cell.get_hash(0));
+        data.extend_from_slice(&cell.get_depth(0).to_be_bytes());
+        exotic(data, vec![])
+    }
+
+    fn merkle_update(old: ArcCell, new: ArcCell) -> Result<ArcCell, TonCellError> {
+        let mut data = vec![MERKLE_UPDATE_TYPE];
+        data.extend_from_slice(&old.get_hash(0));
+        data.extend_from_slice(&new.get_hash(0));
+        data.extend_from_slice(&old.get_depth(0).to_be_bytes());
+        data.extend_from_slice(&new.get_depth(0).to_be_bytes());
+        exotic(data, vec![old, new])
+    }
+
+    fn leaf(value: u32) -> Result<ArcCell, TonCellError> {
+        Ok(Arc::new(CellBuilder::new().store_u32(32, value)?.build()?))
+    }
+
+    fn node(value: u32, references: &[ArcCell]) -> Result<ArcCell, TonCellError> {
+        Ok(Arc::new(
+            CellBuilder::new()
+                .store_u32(32, value)?
+                .store_references(references)?
+                .build()?,
+        ))
+    }
+
+    #[test]
+    fn test_apply_merkle_update() -> Result<(), TonCellError> {
+        let unchanged = node(1, &[leaf(10)?, leaf(11)?])?;
+        let base = node(0, &[unchanged.clone(), leaf(2)?])?;
+        let expected = node(100, &[unchanged.clone(), leaf(3)?])?;
+
+        let old_tree = node(0, &[pruned(&unchanged)?, leaf(2)?])?;
+        let new_tree = node(100, &[pruned(&unchanged)?, leaf(3)?])?;
+        let update = merkle_update(old_tree, new_tree)?;
+
+        let state = apply_merkle_update(&base, &update)?;
+        assert_eq!(&state, expected.as_ref());
+        assert!(Arc::ptr_eq(state.reference(0)?, &unchanged));
+
+        assert!(apply_merkle_update(&expected, &update).is_err());
+        assert!(apply_merkle_update(&base, &base).is_err());
+        Ok(())
+    }
+}
@@ -180,9 +180,13 @@ impl<'a> CellParser<'a> {
         match tp {
             0 => Ok(TonAddress::null()),
             2 => {
+                // addr_std$10 anycast:(Maybe Anycast) workchain_id:int8 address:bits256
                 self.ensure_enough_bits(1 + 8 + 32 * 8)?;
                 let _res1 = self.bit_reader.read::<u8>(1).map_cell_parser_error()?;
-                let wc = self.bit_reader.read::<u8>(8).map_cell_parser_error()?;
+                let wc = self
+                    .bit_reader
+                    .read_signed::<i8>(8)
+                    .map_cell_parser_error()?;
                 let mut hash_part = [0_u8; 32];
                 self.bit_reader
                     .read_bytes(&mut hash_part)
@@ -190,6 +194,29 @@ impl<'a> CellParser<'a> {
                 let addr = TonAddress::new(wc as i32, &hash_part);
                 Ok(addr)
             }
+            3 => {
+                // addr_var$11 anycast:(Maybe Anycast) addr_len:(## 9) workchain_id:int32
+                //   address:(bits addr_len)
+                self.ensure_enough_bits(1 + 9 + 32)?;
+                let _res1 = self.bit_reader.read::<u8>(1).map_cell_parser_error()?;
+                let addr_len = self.bit_reader.read::<u16>(9).map_cell_parser_error()?;
+                if addr_len != 256 {
+                    return Err(TonCellError::InvalidCellData(format!(
+                        "Unsupported address length: {}",
+                        addr_len
+                    )));
+                }
+                self.ensure_enough_bits(32 + 32 * 8)?;
+                let wc = self
+                    .bit_reader
+                    .read_signed::<i32>(32)
+                    .map_cell_parser_error()?;
+                let mut hash_part = [0_u8; 32];
+                self.bit_reader
+                    .read_bytes(&mut hash_part)
+                    .map_cell_parser_error()?;
+                Ok(TonAddress::new(wc, &hash_part))
+            }
             _ => Err(TonCellError::InvalidAddressType(tp)),
         }
     }
@@ -224,6 +251,45 @@ impl<'a> CellParser<'a> {
         self.load_dict(256, key_reader_256bit, val_reader_snake_formatted_string)
     }
 
+    /// Loads bytes stored by `CellBuilder::store_bytes_snake`: remaining bytes of this cell
+    /// followed by data of the chain of cells in the next reference, if any.
+    pub fn load_bytes_snake(&mut self) -> Result<Vec<u8>, TonCellError> {
+        let mut result = self.load_remaining_bytes_strict()?;
+        if self.next_ref == self.references.len() {
+            return Ok(result);
+        }
+        let mut cell = self.next_reference()?;
+        loop {
+            let mut parser = cell.parser();
+            result.extend(parser.load_remaining_bytes_strict()?);
+            cell = match cell.references().len() {
+                0 => return Ok(result),
+                1 => cell.reference(0)?.clone(),
+                n => {
+                    return Err(TonCellError::cell_parser_error(format!(
+                        "Invalid snake format: found cell with {} references",
+                        n
+                    )))
+                }
+            };
+        }
+    }
+
+    fn load_remaining_bytes_strict(&mut self) -> Result<Vec<u8>, TonCellError> {
+        let remaining_bits = self.remaining_bits();
+        if remaining_bits % 8 != 0 {
+            return Err(TonCellError::cell_parser_error(format!(
+                "Invalid snake format: {} bits is not a whole number of bytes",
+                remaining_bits
+            )));
+        }
+        self.load_bytes(remaining_bits / 8)
+    }
+
+    pub fn remaining_refs(&self) -> usize {
+        self.references.len() - self.next_ref
+    }
+
     pub fn ensure_empty(&mut self) -> Result<(), TonCellError> {
         let remaining_bits = self.remaining_bits();
         let remaining_refs = self.references.len() - self.next_ref;
@@ -310,7 +376,7 @@ mod tests {
 
     use num_bigint::{BigInt, BigUint};
 
-    use crate::cell::{Cell, CellBuilder, EitherCellLayout};
+    use crate::cell::{Cell, CellBuilder, EitherCellLayout, TonCellError};
     use crate::TonAddress;
 
     #[test]
@@ -551,6 +617,31 @@ mod tests {
         assert!(parser.load_address().is_err());
     }
 
+    #[test]
+    fn test_load_address_workchains() -> Result<(), TonCellError> {
+        let addresses = [
+            TonAddress::new(-1, &[0x11; 32]),
+            TonAddress::new(1, &[0x22; 32]),
+            TonAddress::new(-129, &[0x33; 32]),
+            TonAddress::new(1 << 20, &[0x44; 32]),
+        ];
+        let expected_bits = [267, 267, 300, 300];
+        for (address, bits) in addresses.iter().zip(expected_bits) {
+            let cell = CellBuilder::new().store_address(address)?.build()?;
+            assert_eq!(cell.bit_len(), bits);
+            assert_eq!(&cell.parser().load_address()?, address);
+            // the workchain isn't byte-aligned after a prefix
+            let cell = CellBuilder::new()
+                .store_u8(6, 0)?
+                .store_address(address)?
+                .build()?;
+            let mut parser = cell.parser();
+            parser.load_u8(6)?;
+            assert_eq!(&parser.load_address()?, address);
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_ensure_empty() {
         let cell = Cell::new([0b10101010].to_vec(), 7, vec![], false).unwrap();
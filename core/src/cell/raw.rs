@@ -5,7 +5,7 @@ use crc::Crc;
 use lazy_static::lazy_static;
 
 use crate::cell::level_mask::LevelMask;
-use crate::cell::{MapTonCellError, TonCellError};
+use crate::cell::{BocSerializeOptions, MapTonCellError, TonCellError};
 
 lazy_static! {
     pub static ref CRC_32_ISCSI: Crc<u32> = Crc::<u32>::new(&crc::CRC_32_ISCSI);
@@ -125,13 +125,14 @@ impl RawBagOfCells {
         })
     }
 
-    pub(crate) fn serialize(&self, has_crc32: bool) -> Result<Vec<u8>, TonCellError> {
+    pub(crate) fn serialize(&self, options: &BocSerializeOptions) -> Result<Vec<u8>, TonCellError> {
         //Based on https://github.com/toncenter/tonweb/blob/c2d5d0fc23d2aec55a0412940ce6e580344a288c/src/boc/Cell.js#L198
 
         let root_count = self.roots.len();
         let num_ref_bits = 32 - (self.cells.len() as u32).leading_zeros();
         let num_ref_bytes = (num_ref_bits + 7) / 8;
-        let has_idx = false;
+        let has_idx = options.has_idx;
+        let has_crc32 = options.has_crc32c;
 
         let mut full_size = 0u32;
 
@@ -191,6 +192,17 @@ impl RawBagOfCells {
                 .map_boc_serialization_error()?;
         }
 
+        if has_idx {
+            // offsets of the end of each cell
+            let mut offset = 0u32;
+            for cell in &self.cells {
+                offset += raw_cell_size(cell, num_ref_bytes);
+                writer
+                    .write(8 * num_offset_bytes, offset)
+                    .map_boc_serialization_error()?;
+            }
+        }
+
         for cell in &self.cells {
             write_raw_cell(&mut writer, cell, num_ref_bytes)?;
         }
@@ -336,6 +348,6 @@ mod tests {
             cells: vec![raw_cell],
             roots: vec![0],
         };
-        assert!(raw_bag.serialize(false).is_ok());
+        assert!(raw_bag.serialize(&BocSerializeOptions::default()).is_ok());
     }
 }
@@ -0,0 +1,107 @@
+use std::fmt::Write;
+
+use crate::cell::json::bits_to_hex;
+use crate::cell::Cell;
+
+/// Indent of child cells used by fift
+pub const FIFT_INDENT: usize = 1;
+
+/// Prints cell trees in the format of fift `csr.`/`<s csr.`:
+///
+/// ```raw
+/// x{68_}
+///  x{0000000F}
+///   x{ABCD}
+/// ```
+///
+/// Each cell takes a line with its data in the fift bit string notation and its references
+/// follow it, indented by `indent` spaces per level. Exotic cells are prefixed with `SPECIAL `.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellSerializer {
+    indent: usize,
+}
+
+impl CellSerializer {
+    pub fn new(indent: usize) -> CellSerializer {
+        CellSerializer { indent }
+    }
+
+    pub fn serialize(&self, cell: &Cell) -> String {
+        let mut out = String::new();
+        self.write_cell(&mut out, cell, 0);
+        out
+    }
+
+    fn write_cell(&self, out: &mut String, cell: &Cell, level: usize) {
+        let special = if cell.is_exotic() { "SPECIAL " } else { "" };
+        let _ = writeln!(
+            out,
+            "{:indent$}{}x{{{}}}",
+            "",
+            special,
+            bits_to_hex(&cell.data, cell.bit_len),
+            indent = level * self.indent
+        );
+        for reference in cell.references.iter() {
+            self.write_cell(out, reference, level + 1);
+        }
+    }
+}
+
+impl Default for CellSerializer {
+    fn default() -> Self {
+        CellSerializer::new(FIFT_INDENT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cell::{BagOfCells, CellBuilder};
+
+    #[test]
+    fn test_serialize_tree() -> anyhow::Result<()> {
+        let leaf = CellBuilder::new().store_u32(16, 0xabcd)?.build()?;
+        let child = CellBuilder::new()
+            .store_u32(32, 15)?
+            .store_reference(&Arc::new(leaf))?
+            .build()?;
+        let root = CellBuilder::new()
+            .store_u8(7, 0x34)?
+            .store_reference(&Arc::new(child))?
+            .store_reference(&Arc::new(Cell::default()))?
+            .build()?;
+        assert_eq!(
+            CellSerializer::default().serialize(&root),
+            "x{69_}\n x{0000000F}\n  x{ABCD}\n x{}\n"
+        );
+        assert_eq!(
+            CellSerializer::new(2).serialize(&root),
+            "x{69_}\n  x{0000000F}\n    x{ABCD}\n  x{}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialize_fift_vector() -> anyhow::Result<()> {
+        // `<b 4 4 u, 1 1 u, <b x{CAFE} s, b> ref, b> <s csr.` in fift
+        let boc = BagOfCells::from_root(
+            CellBuilder::new()
+                .store_u8(4, 4)?
+                .store_bit(true)?
+                .store_reference(&Arc::new(
+                    CellBuilder::new().store_u32(16, 0xcafe)?.build()?,
+                ))?
+                .build()?,
+        )
+        .serialize(false)?;
+        let cell = BagOfCells::parse(&boc)?.single_root()?.as_ref().clone();
+        assert_eq!(
+            CellSerializer::default().serialize(&cell),
+            "x{4C_}\n x{CAFE}\n"
+        );
+        Ok(())
+    }
+}
@@ -1,10 +1,14 @@
+pub mod block;
 pub mod cell;
 pub mod constants;
 pub mod message;
 pub mod mnemonic;
+#[cfg(any(test, feature = "test-vectors"))]
+pub mod testkit;
+pub mod tlb;
 pub mod types;
 pub mod wallet;
 
 pub use crate::types::{
-    TonAddress, TonAddressParseError, TonHash, TonTxId, TransactionIdParseError,
+    TonAddress, TonAddressParseError, TonHash, TonHashExt, TonTxId, TransactionIdParseError,
 };
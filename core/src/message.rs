@@ -9,14 +9,18 @@ pub use common_msg_info::*;
 
 use crate::cell::{ArcCell, Cell};
 
+mod body_kind;
 mod common;
 mod jetton;
 mod nft;
+mod out_action;
 mod sbt;
 mod transfer;
+pub use body_kind::*;
 pub use common::*;
 pub use jetton::*;
 pub use nft::*;
+pub use out_action::*;
 pub use sbt::*;
 pub use transfer::*;
 
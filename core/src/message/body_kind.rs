@@ -0,0 +1,118 @@
+use super::{
+    JettonTransferMessage, JettonTransferNotificationMessage, NftOwnershipAssignedMessage,
+    NftTransferMessage, TonMessage, JETTON_TRANSFER, JETTON_TRANSFER_NOTIFICATION,
+    NFT_OWNERSHIP_ASSIGNED, NFT_TRANSFER,
+};
+use crate::cell::Cell;
+
+/// Op code of text comments, the comment follows as a snake-encoded string
+pub const TEXT_COMMENT: u32 = 0;
+
+/// Body of an internal message, classified by its op code
+#[derive(Clone, Debug, PartialEq)]
+pub enum MessageBodyKind {
+    /// Body is too short to contain an op code
+    Empty,
+    Comment(String),
+    JettonTransfer(JettonTransferMessage),
+    JettonTransferNotification(JettonTransferNotificationMessage),
+    NftTransfer(NftTransferMessage),
+    NftOwnershipAssigned(NftOwnershipAssignedMessage),
+    /// Op code is unknown or the body doesn't match the message of its op code
+    Unknown {
+        opcode: u32,
+    },
+}
+
+impl MessageBodyKind {
+    pub fn classify(body: &Cell) -> MessageBodyKind {
+        let mut parser = body.parser();
+        let opcode = match parser.load_u32(32) {
+            Ok(opcode) => opcode,
+            Err(_) => return MessageBodyKind::Empty,
+        };
+        let kind = match opcode {
+            TEXT_COMMENT => parser
+                .load_bytes_snake()
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .map(MessageBodyKind::Comment),
+            JETTON_TRANSFER => JettonTransferMessage::parse(body)
+                .ok()
+                .map(MessageBodyKind::JettonTransfer),
+            JETTON_TRANSFER_NOTIFICATION => JettonTransferNotificationMessage::parse(body)
+                .ok()
+                .map(MessageBodyKind::JettonTransferNotification),
+            NFT_TRANSFER => NftTransferMessage::parse(body)
+                .ok()
+                .map(MessageBodyKind::NftTransfer),
+            NFT_OWNERSHIP_ASSIGNED => NftOwnershipAssignedMessage::parse(body)
+                .ok()
+                .map(MessageBodyKind::NftOwnershipAssigned),
+            _ => None,
+        };
+        kind.unwrap_or(MessageBodyKind::Unknown { opcode })
+    }
+
+    /// Returns `None` for `Empty` bodies
+    pub fn opcode(&self) -> Option<u32> {
+        match self {
+            MessageBodyKind::Empty => None,
+            MessageBodyKind::Comment(_) => Some(TEXT_COMMENT),
+            MessageBodyKind::JettonTransfer(_) => Some(JETTON_TRANSFER),
+            MessageBodyKind::JettonTransferNotification(_) => Some(JETTON_TRANSFER_NOTIFICATION),
+            MessageBodyKind::NftTransfer(_) => Some(NFT_TRANSFER),
+            MessageBodyKind::NftOwnershipAssigned(_) => Some(NFT_OWNERSHIP_ASSIGNED),
+            MessageBodyKind::Unknown { opcode } => Some(*opcode),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    #[test]
+    fn test_classify_body() -> anyhow::Result<()> {
+        assert_eq!(
+            MessageBodyKind::classify(&Cell::default()),
+            MessageBodyKind::Empty
+        );
+        let comment = CellBuilder::new()
+            .store_u32(32, TEXT_COMMENT)?
+            .store_string("hello")?
+            .build()?;
+        assert_eq!(
+            MessageBodyKind::classify(&comment),
+            MessageBodyKind::Comment("hello".to_string())
+        );
+        let unknown = CellBuilder::new().store_u32(32, 0xdeadbeef)?.build()?;
+        assert_eq!(
+            MessageBodyKind::classify(&unknown).opcode(),
+            Some(0xdeadbeef)
+        );
+        let notification = JettonTransferNotificationMessage::new(
+            &crate::TonAddress::NULL,
+            &num_bigint::BigUint::from(100u32),
+        );
+        assert_eq!(
+            MessageBodyKind::classify(&notification.build()?),
+            MessageBodyKind::JettonTransferNotification(notification)
+        );
+        let assigned = NftOwnershipAssignedMessage::new(&crate::TonAddress::NULL);
+        assert_eq!(
+            MessageBodyKind::classify(&assigned.build()?).opcode(),
+            Some(NFT_OWNERSHIP_ASSIGNED)
+        );
+        // truncated jetton transfer
+        let truncated = CellBuilder::new().store_u32(32, JETTON_TRANSFER)?.build()?;
+        assert_eq!(
+            MessageBodyKind::classify(&truncated),
+            MessageBodyKind::Unknown {
+                opcode: JETTON_TRANSFER
+            }
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use num_bigint::BigUint;
 
 use super::ZERO_COINS;
+use crate::cell::{CellParser, TonCellError};
 use crate::TonAddress;
 
 #[derive(Clone, Debug, PartialEq)]
@@ -87,5 +88,77 @@ impl CommonMsgInfo {
         }
     }
 
+    /// Parses `CommonMsgInfo` of a message.
+    ///
+    /// External addresses (`addr_extern`) can't be represented by `TonAddress` and are returned as `TonAddress::NULL`.
+    /// Extra currencies are skipped.
+    pub fn parse(parser: &mut CellParser) -> Result<CommonMsgInfo, TonCellError> {
+        if !parser.load_bit()? {
+            // int_msg_info$0
+            let ihr_disabled = parser.load_bit()?;
+            let bounce = parser.load_bit()?;
+            let bounced = parser.load_bit()?;
+            let src = parser.load_address()?;
+            let dest = parser.load_address()?;
+            let value = parser.load_coins()?;
+            let _extra_currencies = parser.load_maybe_cell_ref()?;
+            let ihr_fee = parser.load_coins()?;
+            let fwd_fee = parser.load_coins()?;
+            let created_lt = parser.load_u64(64)?;
+            let created_at = parser.load_u32(32)?;
+            Ok(CommonMsgInfo::InternalMessage(InternalMessage {
+                ihr_disabled,
+                bounce,
+                bounced,
+                src,
+                dest,
+                value,
+                ihr_fee,
+                fwd_fee,
+                created_lt,
+                created_at,
+            }))
+        } else if !parser.load_bit()? {
+            // ext_in_msg_info$10
+            let src = load_external_address(parser)?;
+            let dest = parser.load_address()?;
+            let import_fee = parser.load_coins()?;
+            Ok(CommonMsgInfo::ExternalIncomingMessage(
+                ExternalIncomingMessage {
+                    src,
+                    dest,
+                    import_fee,
+                },
+            ))
+        } else {
+            // ext_out_msg_info$11
+            let src = parser.load_address()?;
+            let dest = load_external_address(parser)?;
+            let created_lt = parser.load_u64(64)?;
+            let created_at = parser.load_u32(32)?;
+            Ok(CommonMsgInfo::ExternalOutgoingMessage(
+                ExternalOutgoingMessage {
+                    src,
+                    dest,
+                    created_lt,
+                    created_at,
+                },
+            ))
+        }
+    }
+
     // todo impl others and think about better api
 }
+
+/// ```addr_none$00 = MsgAddressExt; addr_extern$01 len:(## 9) external_address:(bits len) = MsgAddressExt;```
+fn load_external_address(parser: &mut CellParser) -> Result<TonAddress, TonCellError> {
+    match parser.load_u8(2)? {
+        0b00 => Ok(TonAddress::NULL),
+        0b01 => {
+            let len = parser.load_u16(9)? as usize;
+            parser.skip_bits(len)?;
+            Ok(TonAddress::NULL)
+        }
+        tp => Err(TonCellError::InvalidAddressType(tp)),
+    }
+}
@@ -158,11 +158,7 @@ mod tests {
             .build()
             .unwrap();
 
-        let result_boc_serialized = BagOfCells::from_root(result_cell).serialize(false).unwrap();
-        let expected_boc_serialized =
-            hex::decode(JETTON_BURN_WITH_CUSTOM_PAYLOAD_INDICATOR_MSG).unwrap();
-
-        assert_eq!(expected_boc_serialized, result_boc_serialized);
+        crate::assert_golden!("jetton_burn", result_cell);
 
         let result_cell = JettonBurnMessage {
             query_id: 1,
@@ -176,9 +172,6 @@ mod tests {
         .build()
         .unwrap();
 
-        let result_boc_serialized = BagOfCells::from_root(result_cell).serialize(false).unwrap();
-        let expected_boc_serialized = hex::decode(NOT_BURN).unwrap();
-
-        assert_eq!(expected_boc_serialized, result_boc_serialized);
+        crate::assert_golden!("jetton_burn_without_custom_payload", result_cell);
     }
 }
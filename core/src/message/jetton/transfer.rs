@@ -78,14 +78,23 @@ impl TonMessage for JettonTransferMessage {
         }
 
         let mut builder = CellBuilder::new();
-        builder.store_u32(32, Self::opcode())?;
-        builder.store_u64(64, self.query_id)?;
-        builder.store_coins(&self.amount)?;
-        builder.store_address(&self.destination)?;
-        builder.store_address(&self.response_destination)?;
-        builder.store_maybe_cell_ref(&self.custom_payload)?;
-        builder.store_coins(&self.forward_ton_amount)?;
+        builder.labeled("opcode").store_u32(32, Self::opcode())?;
+        builder.labeled("query_id").store_u64(64, self.query_id)?;
+        builder.labeled("amount").store_coins(&self.amount)?;
         builder
+            .labeled("destination")
+            .store_address(&self.destination)?;
+        builder
+            .labeled("response_destination")
+            .store_address(&self.response_destination)?;
+        builder
+            .labeled("custom_payload")
+            .store_maybe_cell_ref(&self.custom_payload)?;
+        builder
+            .labeled("forward_ton_amount")
+            .store_coins(&self.forward_ton_amount)?;
+        builder
+            .labeled("forward_payload")
             .store_either_cell_or_cell_ref(&self.forward_payload, self.forward_payload_layout)?;
         Ok(builder.build()?)
     }
@@ -142,7 +151,9 @@ mod tests {
     use num_bigint::BigUint;
     use num_traits::Zero;
 
-    use crate::cell::{BagOfCells, Cell, CellBuilder, EitherCellLayout, EMPTY_ARC_CELL};
+    use crate::cell::{
+        BagOfCells, Cell, CellBuilder, EitherCellLayout, TonCellError, EMPTY_ARC_CELL,
+    };
     use crate::message::{JettonTransferMessage, TonMessage, TonMessageError, WithForwardPayload};
     use crate::TonAddress;
 
@@ -203,10 +214,7 @@ mod tests {
 
         let result_cell = jetton_transfer_msg.build()?;
 
-        let result_boc_serialized = BagOfCells::from_root(result_cell).serialize(false).unwrap();
-        let expected_boc_serialized = hex::decode(JETTON_TRANSFER_MSG).unwrap();
-
-        assert_eq!(expected_boc_serialized, result_boc_serialized);
+        crate::assert_golden!("jetton_transfer", result_cell);
         Ok(())
     }
 
@@ -231,4 +239,27 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_jetton_transfer_builder_overflow_label() -> anyhow::Result<()> {
+        let forward_payload = CellBuilder::new().store_slice(&[0xAA; 120])?.build()?;
+        let mut msg = JettonTransferMessage::new(
+            &TonAddress::from_str("EQB3ncyBUTjZUA5EnFKR5_EnOMI9V1tTEAAPaiU71gc4TiUt")?,
+            &BigUint::from(1000000000u64),
+        );
+        msg.set_forward_payload(Arc::new(forward_payload), BigUint::from(1u32));
+        msg.set_either_cell_layout(EitherCellLayout::ToCell);
+
+        let err = msg.build().unwrap_err();
+        assert!(matches!(
+            err,
+            TonMessageError::TonCellError(TonCellError::CellBuilderOverflow {
+                label: Some("forward_payload"),
+                requested_bits: 960,
+                ..
+            })
+        ));
+        assert!(err.to_string().contains("forward_payload"));
+        Ok(())
+    }
 }
@@ -164,10 +164,7 @@ mod tests {
 
         let result_cell = jetton_transfer_notification_msg.build()?;
 
-        let expected_boc_serialized = hex::decode(JETTON_TRANSFER_NOTIFICATION_MSG).unwrap();
-        let result_boc_serialized = BagOfCells::from_root(result_cell).serialize(false).unwrap();
-
-        assert_eq!(expected_boc_serialized, result_boc_serialized);
+        crate::assert_golden!("jetton_transfer_notification", result_cell);
         Ok(())
     }
 }
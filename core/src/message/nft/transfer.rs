@@ -200,14 +200,9 @@ mod tests {
             .unwrap(),
         )
         .with_forward_payload(BigUint::from(10000000u64), NFT_TRANSFER_PAYLOAD.clone())
-        .build();
+        .build()?;
 
-        let result_boc_serialized = BagOfCells::from_root(jetton_transfer_msg.unwrap())
-            .serialize(false)
-            .unwrap();
-        let expected_boc_serialized = hex::decode(NFT_TRANSFER_MSG).unwrap();
-
-        assert_eq!(expected_boc_serialized, result_boc_serialized);
+        crate::assert_golden!("nft_transfer", jetton_transfer_msg);
         Ok(())
     }
 }
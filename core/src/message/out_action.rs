@@ -0,0 +1,247 @@
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::cell::{ArcCell, Cell, CellBuilder, CellParser, TonCellError};
+use crate::TonHash;
+
+pub const ACTION_SEND_MSG: u32 = 0x0ec3c86d;
+pub const ACTION_SET_CODE: u32 = 0xad4de08e;
+pub const ACTION_RESERVE_CURRENCY: u32 = 0x36e6b809;
+pub const ACTION_CHANGE_LIBRARY: u32 = 0x26fa1dd4;
+
+/// Library referenced by `OutAction::ChangeLibrary`
+///
+/// ```raw
+/// libref_hash$0 lib_hash:bits256 = LibRef;
+/// libref_ref$1 library:^Cell = LibRef;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum LibRef {
+    Hash(TonHash),
+    Cell(ArcCell),
+}
+
+/// Action performed after the compute phase, an element of the action list in register c5
+///
+/// ```raw
+/// action_send_msg#0ec3c86d mode:(## 8) out_msg:^(MessageRelaxed Any) = OutAction;
+/// action_set_code#ad4de08e new_code:^Cell = OutAction;
+/// action_reserve_currency#36e6b809 mode:(## 8) currency:CurrencyCollection = OutAction;
+/// action_change_library#26fa1dd4 mode:(## 7) libref:LibRef = OutAction;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutAction {
+    SendMessage {
+        mode: u8,
+        message: ArcCell,
+    },
+    SetCode {
+        code: ArcCell,
+    },
+    /// Reserves `amount` nanotons, extra currencies are not supported
+    ReserveCurrency {
+        mode: u8,
+        amount: BigUint,
+    },
+    ChangeLibrary {
+        mode: u8,
+        library: LibRef,
+    },
+}
+
+impl OutAction {
+    pub fn store(&self, builder: &mut CellBuilder) -> Result<(), TonCellError> {
+        match self {
+            OutAction::SendMessage { mode, message } => {
+                builder
+                    .store_u32(32, ACTION_SEND_MSG)?
+                    .store_u8(8, *mode)?
+                    .store_reference(message)?;
+            }
+            OutAction::SetCode { code } => {
+                builder
+                    .store_u32(32, ACTION_SET_CODE)?
+                    .store_reference(code)?;
+            }
+            OutAction::ReserveCurrency { mode, amount } => {
+                builder
+                    .store_u32(32, ACTION_RESERVE_CURRENCY)?
+                    .store_u8(8, *mode)?
+                    .store_coins(amount)?
+                    .store_bit(false)?; // no extra currencies
+            }
+            OutAction::ChangeLibrary { mode, library } => {
+                builder
+                    .store_u32(32, ACTION_CHANGE_LIBRARY)?
+                    .store_u8(7, *mode)?;
+                match library {
+                    LibRef::Hash(hash) => {
+                        builder.store_bit(false)?.store_slice(hash)?;
+                    }
+                    LibRef::Cell(cell) => {
+                        builder.store_bit(true)?.store_reference(cell)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parse(parser: &mut CellParser) -> Result<OutAction, TonCellError> {
+        let tag = parser.load_u32(32)?;
+        let action = match tag {
+            ACTION_SEND_MSG => OutAction::SendMessage {
+                mode: parser.load_u8(8)?,
+                message: parser.next_reference()?,
+            },
+            ACTION_SET_CODE => OutAction::SetCode {
+                code: parser.next_reference()?,
+            },
+            ACTION_RESERVE_CURRENCY => {
+                let mode = parser.load_u8(8)?;
+                let amount = parser.load_coins()?;
+                if parser.load_maybe_cell_ref()?.is_some() {
+                    return Err(TonCellError::InvalidCellData(
+                        "Reserving extra currencies is not supported".to_string(),
+                    ));
+                }
+                OutAction::ReserveCurrency { mode, amount }
+            }
+            ACTION_CHANGE_LIBRARY => {
+                let mode = parser.load_u8(7)?;
+                let library = if parser.load_bit()? {
+                    LibRef::Cell(parser.next_reference()?)
+                } else {
+                    let mut hash = [0u8; 32];
+                    parser.load_slice(&mut hash)?;
+                    LibRef::Hash(hash)
+                };
+                OutAction::ChangeLibrary { mode, library }
+            }
+            tag => {
+                return Err(TonCellError::InvalidCellData(format!(
+                    "Unknown out action tag: {:#010x}",
+                    tag
+                )))
+            }
+        };
+        Ok(action)
+    }
+
+    /// Parses the action list (`OutList n`), returns actions in the order of execution.
+    pub fn parse_list(cell: &Cell) -> Result<Vec<OutAction>, TonCellError> {
+        let mut actions = vec![];
+        let mut cell = Arc::new(cell.clone());
+        // out_list_empty$_ = OutList 0;
+        while cell.bit_len() > 0 || !cell.references().is_empty() {
+            // out_list$_ {n:#} prev:^(OutList n) action:OutAction = OutList (n + 1);
+            let mut parser = cell.parser();
+            let prev = parser.next_reference()?;
+            actions.push(OutAction::parse(&mut parser)?);
+            parser.ensure_empty()?;
+            cell = prev;
+        }
+        actions.reverse();
+        Ok(actions)
+    }
+}
+
+/// Builder of the action list (`OutList n`), as put into register c5 by contracts.
+///
+/// The list is a chain of cells: each cell references the list of the preceding actions
+/// and stores the last one, so the root cell holds the action executed last.
+/// Actions are added in the order of execution.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ActionListBuilder {
+    actions: Vec<OutAction>,
+}
+
+impl ActionListBuilder {
+    pub fn new() -> ActionListBuilder {
+        ActionListBuilder::default()
+    }
+
+    pub fn push(&mut self, action: OutAction) -> &mut Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn send_message(&mut self, mode: u8, message: &ArcCell) -> &mut Self {
+        self.push(OutAction::SendMessage {
+            mode,
+            message: message.clone(),
+        })
+    }
+
+    pub fn reserve_currency(&mut self, mode: u8, amount: &BigUint) -> &mut Self {
+        self.push(OutAction::ReserveCurrency {
+            mode,
+            amount: amount.clone(),
+        })
+    }
+
+    pub fn set_code(&mut self, code: &ArcCell) -> &mut Self {
+        self.push(OutAction::SetCode { code: code.clone() })
+    }
+
+    pub fn change_library(&mut self, mode: u8, library: LibRef) -> &mut Self {
+        self.push(OutAction::ChangeLibrary { mode, library })
+    }
+
+    pub fn build(&self) -> Result<Cell, TonCellError> {
+        let mut list = Cell::default();
+        for action in &self.actions {
+            let mut builder = CellBuilder::new();
+            builder.store_child(list)?;
+            action.store(&mut builder)?;
+            list = builder.build()?;
+        }
+        Ok(list)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_list_order() -> Result<(), TonCellError> {
+        let message = Arc::new(CellBuilder::new().store_u32(32, 1)?.build()?);
+        let code = Arc::new(CellBuilder::new().store_u32(32, 2)?.build()?);
+        let list = ActionListBuilder::new()
+            .reserve_currency(2, &BigUint::from(1_000_000u32))
+            .send_message(128, &message)
+            .set_code(&code)
+            .change_library(2, LibRef::Hash([0xab; 32]))
+            .build()?;
+
+        // the last action is in the root, the first one is the deepest
+        let mut parser = list.parser();
+        let prev = parser.next_reference()?;
+        assert_eq!(parser.load_u32(32)?, ACTION_CHANGE_LIBRARY);
+        let first = prev.reference(0)?.reference(0)?;
+        assert_eq!(first.parser().load_u32(32)?, ACTION_RESERVE_CURRENCY);
+        assert_eq!(first.reference(0)?.as_ref(), &Cell::default());
+
+        let actions = OutAction::parse_list(&list)?;
+        assert_eq!(
+            actions,
+            vec![
+                OutAction::ReserveCurrency {
+                    mode: 2,
+                    amount: BigUint::from(1_000_000u32)
+                },
+                OutAction::SendMessage { mode: 128, message },
+                OutAction::SetCode { code },
+                OutAction::ChangeLibrary {
+                    mode: 2,
+                    library: LibRef::Hash([0xab; 32])
+                },
+            ]
+        );
+        assert_eq!(ActionListBuilder::new().build()?, Cell::default());
+        assert!(OutAction::parse_list(&Cell::default())?.is_empty());
+        Ok(())
+    }
+}
@@ -4,7 +4,7 @@ use super::{
     CommonMsgInfo, ExternalIncomingMessage, ExternalOutgoingMessage, InternalMessage, TonMessage,
     TonMessageError,
 };
-use crate::cell::{ArcCell, Cell, CellBuilder};
+use crate::cell::{ArcCell, Cell, CellBuilder, TonCellError};
 
 #[derive(Clone, Debug, PartialEq)]
 pub struct TransferMessage {
@@ -72,7 +72,16 @@ impl TonMessage for TransferMessage {
                 builder.store_u32(32, m.created_at)?; // created_at
             }
         }
-        builder.store_maybe_cell_ref(&self.state_init)?;
+        // init:(Maybe (Either StateInit ^StateInit)), always in a reference
+        match &self.state_init {
+            Some(state_init) => {
+                builder.store_bit(true)?.store_bit(true)?;
+                builder.store_reference(state_init)?;
+            }
+            None => {
+                builder.store_bit(false)?;
+            }
+        }
 
         builder.store_maybe_cell_ref(&self.data)?;
 
@@ -137,7 +146,17 @@ impl TonMessage for TransferMessage {
                 })
             }
         };
-        let state_init = parser.load_maybe_cell_ref()?;
+        let state_init = if parser.load_bit()? {
+            if !parser.load_bit()? {
+                return Err(TonCellError::InvalidCellData(
+                    "Inline state init is not supported".to_string(),
+                )
+                .into());
+            }
+            Some(parser.next_reference()?)
+        } else {
+            None
+        };
         let data = parser.load_maybe_cell_ref()?;
 
         parser.ensure_empty()?;
@@ -0,0 +1,351 @@
+//! Deterministic test vectors and golden files of cell & message builders.
+//!
+//! Goldens are hex-encoded BoCs (without CRC) stored in `resources/golden/<name>.hex`.
+//! `assert_golden!` compares a cell with its golden and fails with a hex diff, so any change
+//! of serialization shows up in tests. After an intended change, regenerate goldens with
+//!
+//! ```sh
+//! UPDATE_GOLDEN=1 cargo test -p tonlib-core
+//! ```
+//!
+//! and review the diff of `resources/golden` before committing it.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use num_bigint::BigUint;
+
+use crate::cell::dict::predefined_writers::val_writer_unsigned_min_size;
+use crate::cell::dict::CellDictionary;
+use crate::cell::{ArcCell, BagOfCells, Cell, CellBuilder, StateInitBuilder, TonCellError};
+use crate::message::{
+    CommonMsgInfo, HasOpcode, InternalMessage, JettonBurnMessage, JettonTransferMessage,
+    NftTransferMessage, TonMessage, TonMessageError, TransferMessage, WithForwardPayload,
+    TEXT_COMMENT,
+};
+use crate::mnemonic::KeyPair;
+use crate::wallet::{TonWallet, WalletVersion, DEFAULT_WALLET_ID};
+use crate::TonAddress;
+
+/// Set to any value to write actual cells to goldens instead of comparing them
+pub const UPDATE_GOLDEN_ENV: &str = "UPDATE_GOLDEN";
+pub const GOLDEN_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/golden");
+
+/// Bytes per line of hex diffs
+const DIFF_LINE_BYTES: usize = 16;
+
+pub const CANONICAL_EXPIRE_AT: u32 = 1_700_000_060;
+pub const CANONICAL_SEQNO: u32 = 7;
+pub const CANONICAL_QUERY_ID: u64 = 0x0123_4567_89ab_cdef;
+pub const CANONICAL_COMMENT: &str = "tonlib-rs golden";
+
+/// Config params (`Hashmap 32 ^Cell`) of the masterchain state, as returned by `getConfigAll`.
+///
+/// Extracted from the config proof of a mainnet key block, so emulations can run offline.
+pub const CONFIG_PARAMS_B64: &str = include_str!("../resources/config/config_params.b64");
+
+/// Compares `actual` with golden `name`, panicking with a hex diff on mismatch.
+///
+/// Writes `actual` to the golden instead if `UPDATE_GOLDEN` is set.
+#[macro_export]
+macro_rules! assert_golden {
+    ($name:expr, $cell:expr) => {
+        if let Err(e) = $crate::testkit::check_golden($name, &$cell) {
+            panic!("{}", e);
+        }
+    };
+}
+
+/// Serialized BoC of `CONFIG_PARAMS_B64`, as expected by `TvmEmulatorC7Builder`
+pub fn config_params_boc() -> Result<Vec<u8>, TonCellError> {
+    BagOfCells::parse_base64(CONFIG_PARAMS_B64.trim())?.serialize(false)
+}
+
+pub fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(GOLDEN_DIR).join(format!("{}.hex", name))
+}
+
+/// Returns hex-encoded BoC of golden `name`, `None` if it doesn't exist
+pub fn golden_hex(name: &str) -> Option<String> {
+    fs::read_to_string(golden_path(name))
+        .ok()
+        .map(|hex| hex.trim().to_string())
+}
+
+/// Serializes `cell` as it is stored in goldens
+pub fn golden_boc_hex(cell: &Cell) -> Result<String, TonCellError> {
+    let boc = BagOfCells::from_root(cell.clone()).serialize(false)?;
+    Ok(hex::encode(boc))
+}
+
+/// Checks `actual` against golden `name`, returns a readable description of the mismatch.
+pub fn check_golden(name: &str, actual: &Cell) -> Result<(), String> {
+    let actual = golden_boc_hex(actual).map_err(|e| format!("Failed to serialize cell: {}", e))?;
+    let path = golden_path(name);
+    if std::env::var_os(UPDATE_GOLDEN_ENV).is_some() {
+        fs::create_dir_all(GOLDEN_DIR).map_err(|e| e.to_string())?;
+        return fs::write(&path, format!("{}\n", actual))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e));
+    }
+    let expected = golden_hex(name).ok_or_else(|| {
+        format!(
+            "Golden {} is missing, run with {}=1 to create it",
+            path.display(),
+            UPDATE_GOLDEN_ENV
+        )
+    })?;
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "Golden {} doesn't match, run with {}=1 to update it\n{}",
+            name,
+            UPDATE_GOLDEN_ENV,
+            hex_diff(&expected, &actual)
+        ))
+    }
+}
+
+/// Lists lines of `DIFF_LINE_BYTES` bytes that differ, prefixed with their offset
+fn hex_diff(expected: &str, actual: &str) -> String {
+    let line_len = DIFF_LINE_BYTES * 2;
+    let line = |hex: &str, start: usize| -> String {
+        hex.get(start..(start + line_len).min(hex.len()))
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut diff = format!(
+        "expected {} bytes, got {} bytes\n",
+        expected.len() / 2,
+        actual.len() / 2
+    );
+    for start in (0..expected.len().max(actual.len())).step_by(line_len) {
+        let (expected_line, actual_line) = (line(expected, start), line(actual, start));
+        if expected_line != actual_line {
+            diff += &format!("-{:06x}: {}\n", start / 2, expected_line);
+            diff += &format!("+{:06x}: {}\n", start / 2, actual_line);
+        }
+    }
+    diff
+}
+
+/// Key pair of Ed25519 seed `[1; 32]`
+pub fn canonical_key_pair() -> KeyPair {
+    let key_pair = nacl::sign::generate_keypair(&[1; 32]);
+    KeyPair {
+        public_key: key_pair.pkey.to_vec(),
+        secret_key: key_pair.skey.to_vec(),
+    }
+}
+
+/// Basechain address with all bytes of the account id equal to `byte`
+pub fn canonical_address(byte: u8) -> TonAddress {
+    TonAddress::new(0, &[byte; 32])
+}
+
+pub fn canonical_wallet(version: WalletVersion) -> Result<TonWallet, TonCellError> {
+    TonWallet::derive_default(version, &canonical_key_pair())
+}
+
+/// Internal message transferring `value` nanotons to `dest` with `body`
+pub fn canonical_internal_message(
+    dest: &TonAddress,
+    value: u64,
+    body: Cell,
+) -> Result<Cell, TonMessageError> {
+    let info = CommonMsgInfo::InternalMessage(InternalMessage {
+        ihr_disabled: true,
+        bounce: true,
+        bounced: false,
+        src: TonAddress::NULL,
+        dest: dest.clone(),
+        value: BigUint::from(value),
+        ihr_fee: BigUint::from(0u32),
+        fwd_fee: BigUint::from(0u32),
+        created_lt: 0,
+        created_at: 0,
+    });
+    TransferMessage::new(info).with_data(Arc::new(body)).build()
+}
+
+pub fn canonical_comment(text: &str) -> Result<Cell, TonCellError> {
+    CellBuilder::new()
+        .store_u32(32, TEXT_COMMENT)?
+        .store_bytes_snake(text.as_bytes())?
+        .build()
+}
+
+/// Cell with a canonical input, serialized as golden `name`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoldenVector {
+    pub name: &'static str,
+    pub cell: Cell,
+}
+
+/// Builds cells of all canonical inputs
+pub fn golden_vectors() -> Result<Vec<GoldenVector>, TonMessageError> {
+    let mut vectors = vec![];
+    let mut push = |name: &'static str, cell: Cell| vectors.push(GoldenVector { name, cell });
+
+    // StateInit derivation
+    for (name, version) in [
+        ("state_init_wallet_v3r2", WalletVersion::V3R2),
+        ("state_init_wallet_v4r2", WalletVersion::V4R2),
+        (
+            "state_init_wallet_highload_v2r2",
+            WalletVersion::HighloadV2R2,
+        ),
+    ] {
+        let data = version.initial_data(&canonical_key_pair(), DEFAULT_WALLET_ID)?;
+        push(name, StateInitBuilder::new(version.code()?, &data).build()?);
+    }
+
+    // wallet externals
+    let transfer: ArcCell = Arc::new(canonical_internal_message(
+        &canonical_address(0x11),
+        1_500_000_000,
+        canonical_comment(CANONICAL_COMMENT)?,
+    )?);
+    for (body_name, external_name, version, state_init) in [
+        (
+            "wallet_v3r2_external_body",
+            "wallet_v3r2_external",
+            WalletVersion::V3R2,
+            false,
+        ),
+        (
+            "wallet_v4r2_external_body",
+            "wallet_v4r2_external_deploy",
+            WalletVersion::V4R2,
+            true,
+        ),
+    ] {
+        let wallet = canonical_wallet(version)?;
+        let messages = [transfer.clone()];
+        push(
+            body_name,
+            wallet.create_external_body(CANONICAL_EXPIRE_AT, CANONICAL_SEQNO, &messages)?,
+        );
+        push(
+            external_name,
+            wallet.create_external_message(
+                CANONICAL_EXPIRE_AT,
+                CANONICAL_SEQNO,
+                &messages,
+                state_init,
+            )?,
+        );
+    }
+    push("internal_message_ton_transfer", transfer.as_ref().clone());
+
+    // jettons & NFTs
+    let forward_payload = Arc::new(canonical_comment("forward")?);
+    push(
+        "jetton_transfer_canonical",
+        JettonTransferMessage::new(&canonical_address(0x22), &BigUint::from(10_500_000u64))
+            .with_query_id(CANONICAL_QUERY_ID)
+            .with_response_destination(&canonical_address(0x33))
+            .with_custom_payload(Arc::new(CellBuilder::new().store_u8(8, 0xcc)?.build()?))
+            .with_forward_payload(BigUint::from(1u32), forward_payload.clone())
+            .build()?,
+    );
+    push(
+        "jetton_burn_canonical",
+        JettonBurnMessage::new(&BigUint::from(1_000_000_000u64))
+            .with_query_id(CANONICAL_QUERY_ID)
+            .with_response_destination(&canonical_address(0x33))
+            .build()?,
+    );
+    push(
+        "nft_transfer_canonical",
+        NftTransferMessage::new(&canonical_address(0x44))
+            .with_query_id(CANONICAL_QUERY_ID)
+            .with_response_destination(&canonical_address(0x33))
+            .with_forward_payload(BigUint::from(10_000_000u64), forward_payload)
+            .build()?,
+    );
+
+    // snake strings spanning several cells
+    push(
+        "text_comment_snake",
+        canonical_comment(&"golden ".repeat(40))?,
+    );
+
+    // dictionaries
+    let values: HashMap<u8, u64> = (0u8..16).map(|key| (key, key as u64 * 1000)).collect();
+    push(
+        "dict_8bit_keys",
+        CellBuilder::new()
+            .store_dict(8, val_writer_unsigned_min_size, values)?
+            .build()?,
+    );
+    let values: HashMap<BigUint, u32> = (1u8..=5)
+        .map(|byte| (BigUint::from_bytes_be(&[byte; 32]), byte as u32))
+        .collect();
+    push(
+        "dict_256bit_keys",
+        CellBuilder::new()
+            .store_dict(256, val_writer_unsigned_min_size, values)?
+            .build()?,
+    );
+    let mut dict = CellDictionary::new(32);
+    for key in [0u32, 1, 2, 1000, 0xffff_fffe, 0xffff_ffff] {
+        let key_bits: Vec<bool> = (0..32).rev().map(|i| (key >> i) & 1 == 1).collect();
+        dict.set(&key_bits, CellBuilder::new().store_u32(32, key)?.build()?)?;
+    }
+    push("cell_dictionary_32bit_keys", dict.to_cell()?);
+
+    Ok(vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_golden_vectors() -> Result<(), TonMessageError> {
+        let vectors = golden_vectors()?;
+        assert!(vectors.len() >= 15);
+        for vector in vectors {
+            assert_golden!(vector.name, vector.cell);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_params_boc() -> Result<(), TonCellError> {
+        let boc = BagOfCells::parse(&config_params_boc()?)?;
+        let root = boc.single_root()?;
+        assert_eq!(
+            hex::encode(root.cell_hash()),
+            "659376b78765feb8f10c3a0b4caa4017b3bbe62be8b42ab8ca5fd077583d16c2"
+        );
+        let params = root.parser().load_dict(
+            32,
+            crate::cell::dict::predefined_readers::key_reader_u32,
+            crate::cell::dict::predefined_readers::val_reader_ref_cell,
+        )?;
+        // config address, gas prices & validator sets
+        for param in [0, 20, 21, 34] {
+            assert!(params.contains_key(&param), "param {}", param);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_hex_diff() {
+        let diff = hex_diff(&"ab".repeat(40), &("ab".repeat(20) + &"cd".repeat(21)));
+        assert_eq!(
+            diff,
+            format!(
+                "expected 40 bytes, got 41 bytes\n-000010: {0}\n+000010: {1}\n-000020: {2}\n+000020: {3}\n",
+                "ab".repeat(4) + &"ab".repeat(12),
+                "ab".repeat(4) + &"cd".repeat(12),
+                "ab".repeat(8),
+                "cd".repeat(9),
+            )
+        );
+    }
+}
@@ -0,0 +1,12 @@
+mod codec;
+mod error;
+mod schema;
+mod value;
+
+pub use codec::*;
+pub use error::*;
+pub use schema::*;
+pub use value::*;
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,253 @@
+use std::sync::Arc;
+
+use num_bigint::{BigInt, BigUint, Sign};
+use num_traits::One;
+
+use super::{TlbConstructor, TlbError, TlbSchema, TlbType, TlbValue};
+use crate::cell::{Cell, CellBuilder, CellParser};
+
+/// Decodes the cell as the root type of the schema.
+///
+/// The cell and every cell referenced as `^T` must be consumed completely.
+pub fn decode(cell: &Cell, schema: &TlbSchema) -> Result<TlbValue, TlbError> {
+    let mut parser = cell.parser();
+    let value = decode_named(&mut parser, schema, schema.root())?;
+    parser.ensure_empty()?;
+    Ok(value)
+}
+
+/// Encodes the value as the root type of the schema.
+pub fn encode(value: &TlbValue, schema: &TlbSchema) -> Result<Cell, TlbError> {
+    let mut builder = CellBuilder::new();
+    encode_named(&mut builder, value, schema, schema.root())?;
+    Ok(builder.build()?)
+}
+
+fn decode_named(
+    parser: &mut CellParser,
+    schema: &TlbSchema,
+    type_name: &str,
+) -> Result<TlbValue, TlbError> {
+    let constructors = schema
+        .constructors(type_name)
+        .ok_or_else(|| TlbError::UnknownType(type_name.to_string()))?;
+    let constructor =
+        match_constructor(parser, constructors).ok_or_else(|| TlbError::NoMatchingConstructor {
+            type_name: type_name.to_string(),
+        })?;
+    let mut fields = Vec::with_capacity(constructor.fields.len());
+    for field in &constructor.fields {
+        let value = decode_type(parser, schema, &field.ty)?;
+        fields.push((field.name.clone(), value));
+    }
+    Ok(TlbValue::Struct {
+        constructor: constructor.name.clone(),
+        fields,
+    })
+}
+
+/// Reads the tag bit by bit, constructor tags of a type are prefix-free.
+fn match_constructor<'a>(
+    parser: &mut CellParser,
+    constructors: &'a [TlbConstructor],
+) -> Option<&'a TlbConstructor> {
+    let mut tag = vec![];
+    loop {
+        if let Some(constructor) = constructors.iter().find(|c| c.tag == tag) {
+            return Some(constructor);
+        }
+        if !constructors.iter().any(|c| c.tag.starts_with(&tag)) {
+            return None;
+        }
+        tag.push(parser.load_bit().ok()?);
+    }
+}
+
+fn decode_type(
+    parser: &mut CellParser,
+    schema: &TlbSchema,
+    ty: &TlbType,
+) -> Result<TlbValue, TlbError> {
+    let value = match ty {
+        TlbType::Uint(bit_len) => TlbValue::Uint(parser.load_uint(*bit_len)?),
+        TlbType::Int(bit_len) => {
+            let value = parser.load_uint(*bit_len)?;
+            TlbValue::Int(from_twos_complement(value, *bit_len))
+        }
+        TlbType::Bits(bit_len) => TlbValue::Bits(parser.load_bits(*bit_len)?),
+        TlbType::Bool => TlbValue::Bool(parser.load_bit()?),
+        TlbType::Coins => TlbValue::Coins(parser.load_coins()?),
+        TlbType::Address => TlbValue::Address(parser.load_address()?),
+        TlbType::Cell => {
+            return Err(TlbError::ValueMismatch(
+                "inline Cell can't be decoded".to_string(),
+            ))
+        }
+        TlbType::Maybe(ty) => {
+            let value = if parser.load_bit()? {
+                Some(Box::new(decode_type(parser, schema, ty)?))
+            } else {
+                None
+            };
+            TlbValue::Maybe(value)
+        }
+        TlbType::Either(left, right) => {
+            if parser.load_bit()? {
+                TlbValue::Right(Box::new(decode_type(parser, schema, right)?))
+            } else {
+                TlbValue::Left(Box::new(decode_type(parser, schema, left)?))
+            }
+        }
+        TlbType::Ref(ty) => {
+            let cell = parser.next_reference()?;
+            if **ty == TlbType::Cell {
+                TlbValue::Cell(cell)
+            } else {
+                let mut ref_parser = cell.parser();
+                let value = decode_type(&mut ref_parser, schema, ty)?;
+                ref_parser.ensure_empty()?;
+                value
+            }
+        }
+        TlbType::Named(type_name) => decode_named(parser, schema, type_name)?,
+    };
+    Ok(value)
+}
+
+fn encode_named(
+    builder: &mut CellBuilder,
+    value: &TlbValue,
+    schema: &TlbSchema,
+    type_name: &str,
+) -> Result<(), TlbError> {
+    let (constructor_name, fields) = match value {
+        TlbValue::Struct {
+            constructor,
+            fields,
+        } => (constructor, fields),
+        _ => return Err(mismatch(type_name, value)),
+    };
+    let constructors = schema
+        .constructors(type_name)
+        .ok_or_else(|| TlbError::UnknownType(type_name.to_string()))?;
+    let constructor = constructors
+        .iter()
+        .find(|c| &c.name == constructor_name)
+        .ok_or_else(|| {
+            TlbError::ValueMismatch(format!(
+                "{} has no constructor `{}`",
+                type_name, constructor_name
+            ))
+        })?;
+    if fields.len() != constructor.fields.len() {
+        return Err(TlbError::ValueMismatch(format!(
+            "constructor `{}` expects {} fields, got {}",
+            constructor_name,
+            constructor.fields.len(),
+            fields.len()
+        )));
+    }
+    for bit in &constructor.tag {
+        builder.store_bit(*bit)?;
+    }
+    for ((name, value), field) in fields.iter().zip(&constructor.fields) {
+        if name != &field.name {
+            return Err(TlbError::ValueMismatch(format!(
+                "constructor `{}` expects field `{}`, got `{}`",
+                constructor_name, field.name, name
+            )));
+        }
+        encode_type(builder, value, schema, &field.ty)?;
+    }
+    Ok(())
+}
+
+fn encode_type(
+    builder: &mut CellBuilder,
+    value: &TlbValue,
+    schema: &TlbSchema,
+    ty: &TlbType,
+) -> Result<(), TlbError> {
+    match (ty, value) {
+        (TlbType::Uint(bit_len), TlbValue::Uint(v)) => {
+            builder.store_uint(*bit_len, v)?;
+        }
+        (TlbType::Int(bit_len), TlbValue::Int(v)) => {
+            builder.store_uint(*bit_len, &to_twos_complement(v, *bit_len)?)?;
+        }
+        (TlbType::Bits(bit_len), TlbValue::Bits(data)) => {
+            if data.len() != bit_len.div_ceil(8) {
+                return Err(TlbError::ValueMismatch(format!(
+                    "{} expects {} bytes, got {}",
+                    ty,
+                    bit_len.div_ceil(8),
+                    data.len()
+                )));
+            }
+            builder.store_bits(*bit_len, data)?;
+        }
+        (TlbType::Bool, TlbValue::Bool(v)) => {
+            builder.store_bit(*v)?;
+        }
+        (TlbType::Coins, TlbValue::Coins(v)) => {
+            builder.store_coins(v)?;
+        }
+        (TlbType::Address, TlbValue::Address(v)) => {
+            builder.store_address(v)?;
+        }
+        (TlbType::Maybe(ty), TlbValue::Maybe(v)) => {
+            builder.store_bit(v.is_some())?;
+            if let Some(v) = v {
+                encode_type(builder, v, schema, ty)?;
+            }
+        }
+        (TlbType::Either(left, _), TlbValue::Left(v)) => {
+            builder.store_bit(false)?;
+            encode_type(builder, v, schema, left)?;
+        }
+        (TlbType::Either(_, right), TlbValue::Right(v)) => {
+            builder.store_bit(true)?;
+            encode_type(builder, v, schema, right)?;
+        }
+        (TlbType::Ref(ty), TlbValue::Cell(cell)) if **ty == TlbType::Cell => {
+            builder.store_reference(cell)?;
+        }
+        (TlbType::Ref(ty), v) if **ty != TlbType::Cell => {
+            let mut ref_builder = CellBuilder::new();
+            encode_type(&mut ref_builder, v, schema, ty)?;
+            builder.store_reference(&Arc::new(ref_builder.build()?))?;
+        }
+        (TlbType::Named(type_name), v) => encode_named(builder, v, schema, type_name)?,
+        _ => return Err(mismatch(ty, value)),
+    }
+    Ok(())
+}
+
+fn mismatch(expected: impl std::fmt::Display, value: &TlbValue) -> TlbError {
+    TlbError::ValueMismatch(format!("expected {}, got {}", expected, value.kind()))
+}
+
+fn from_twos_complement(value: BigUint, bit_len: usize) -> BigInt {
+    if value.bit(bit_len as u64 - 1) {
+        BigInt::from(value) - (BigInt::one() << bit_len)
+    } else {
+        BigInt::from(value)
+    }
+}
+
+fn to_twos_complement(value: &BigInt, bit_len: usize) -> Result<BigUint, TlbError> {
+    let limit = BigInt::one() << (bit_len - 1);
+    if value >= &limit || value < &-&limit {
+        return Err(TlbError::ValueMismatch(format!(
+            "{} doesn't fit in int{}",
+            value, bit_len
+        )));
+    }
+    let unsigned = if value.sign() == Sign::Minus {
+        (BigInt::one() << bit_len) + value
+    } else {
+        value.clone()
+    };
+    // non-negative after the adjustment above
+    Ok(unsigned.to_biguint().unwrap_or_default())
+}
@@ -0,0 +1,24 @@
+use thiserror::Error;
+
+use crate::cell::TonCellError;
+
+#[derive(Error, Debug)]
+pub enum TlbError {
+    #[error("TL-B schema parse error in declaration {declaration} ({message})")]
+    SchemaParse { declaration: usize, message: String },
+
+    #[error("Unsupported TL-B construct in declaration {declaration} ({message})")]
+    Unsupported { declaration: usize, message: String },
+
+    #[error("Unknown TL-B type ({0})")]
+    UnknownType(String),
+
+    #[error("No constructor of {type_name} matches the data")]
+    NoMatchingConstructor { type_name: String },
+
+    #[error("Value does not match the schema ({0})")]
+    ValueMismatch(String),
+
+    #[error("TonCellError ({0})")]
+    TonCellError(#[from] TonCellError),
+}
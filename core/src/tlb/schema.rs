@@ -0,0 +1,507 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use super::TlbError;
+
+const MAX_UINT_BITS: usize = 256;
+const MAX_INT_BITS: usize = 257;
+const MAX_BITS: usize = 1023;
+
+/// Field type of the supported TL-B subset
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TlbType {
+    /// `uintN`, `## N`, `#`
+    Uint(usize),
+    /// `intN`
+    Int(usize),
+    /// `bitsN`
+    Bits(usize),
+    /// `Bool`, `Bit`
+    Bool,
+    /// `Coins`, `Grams`, `VarUInteger 16`
+    Coins,
+    /// `MsgAddress`, `MsgAddressInt`
+    Address,
+    /// `Cell`, only allowed behind a reference
+    Cell,
+    Maybe(Box<TlbType>),
+    Either(Box<TlbType>, Box<TlbType>),
+    Ref(Box<TlbType>),
+    /// Type declared in the same schema
+    Named(String),
+}
+
+impl fmt::Display for TlbType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TlbType::Uint(n) => write!(f, "uint{}", n),
+            TlbType::Int(n) => write!(f, "int{}", n),
+            TlbType::Bits(n) => write!(f, "bits{}", n),
+            TlbType::Bool => write!(f, "Bool"),
+            TlbType::Coins => write!(f, "Coins"),
+            TlbType::Address => write!(f, "MsgAddress"),
+            TlbType::Cell => write!(f, "Cell"),
+            TlbType::Maybe(t) => write!(f, "(Maybe {})", t),
+            TlbType::Either(l, r) => write!(f, "(Either {} {})", l, r),
+            TlbType::Ref(t) => write!(f, "^{}", t),
+            TlbType::Named(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlbField {
+    /// Field name, `_` for anonymous fields
+    pub name: String,
+    pub ty: TlbType,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlbConstructor {
+    /// Constructor name, `_` for anonymous constructors
+    pub name: String,
+    /// Constructor tag bits, empty for `$_`, `#_` and anonymous constructors
+    pub tag: Vec<bool>,
+    pub fields: Vec<TlbField>,
+}
+
+/// Schema parsed at runtime from a constrained subset of TL-B.
+///
+/// Supported: fixed-size integers (`uintN`, `intN`, `## N`, `#`), `bitsN`, `Bool`/`Bit`,
+/// `Maybe`, `Either`, references (`^T`, `^Cell`), `Coins`/`Grams`/`VarUInteger 16`,
+/// `MsgAddress`/`MsgAddressInt`, explicit `#hex` and `$binary` constructor tags
+/// and types declared in the same schema.
+///
+/// Type parameters, implicit fields, conditional fields, dictionaries and constructors
+/// with implicit CRC32 tags are rejected with an error.
+///
+/// The root type is the type of the last declaration, see [`TlbSchema::with_root`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TlbSchema {
+    types: HashMap<String, Vec<TlbConstructor>>,
+    root: String,
+}
+
+impl TlbSchema {
+    pub fn parse(source: &str) -> Result<TlbSchema, TlbError> {
+        let source = strip_comments(source)?;
+        let mut types: HashMap<String, Vec<TlbConstructor>> = HashMap::new();
+        let mut root = None;
+
+        let mut declarations: Vec<&str> = source.split(';').collect();
+        let tail = declarations.pop().unwrap_or_default();
+        if !tail.trim().is_empty() {
+            return Err(TlbError::SchemaParse {
+                declaration: declarations.len() + 1,
+                message: "missing `;` at the end of declaration".to_string(),
+            });
+        }
+        for (idx, declaration) in declarations.iter().enumerate() {
+            if declaration.trim().is_empty() {
+                continue;
+            }
+            let tokens = tokenize(declaration);
+            let (type_name, constructor) = DeclarationParser::new(&tokens, idx + 1).parse()?;
+            let constructors = types.entry(type_name.clone()).or_default();
+            if constructors
+                .iter()
+                .any(|c| c.tag.starts_with(&constructor.tag) || constructor.tag.starts_with(&c.tag))
+            {
+                return Err(TlbError::SchemaParse {
+                    declaration: idx + 1,
+                    message: format!(
+                        "tag of constructor `{}` is ambiguous with other constructors of {}",
+                        constructor.name, type_name
+                    ),
+                });
+            }
+            constructors.push(constructor);
+            root = Some(type_name);
+        }
+
+        let root = root.ok_or_else(|| TlbError::SchemaParse {
+            declaration: 0,
+            message: "schema has no declarations".to_string(),
+        })?;
+        let schema = TlbSchema { types, root };
+        schema.validate()?;
+        Ok(schema)
+    }
+
+    pub fn root(&self) -> &str {
+        &self.root
+    }
+
+    /// Uses another declared type as the root for `decode` and `encode`.
+    pub fn with_root(mut self, type_name: &str) -> Result<TlbSchema, TlbError> {
+        if !self.types.contains_key(type_name) {
+            return Err(TlbError::UnknownType(type_name.to_string()));
+        }
+        self.root = type_name.to_string();
+        Ok(self)
+    }
+
+    pub fn constructors(&self, type_name: &str) -> Option<&[TlbConstructor]> {
+        self.types.get(type_name).map(|c| c.as_slice())
+    }
+
+    fn validate(&self) -> Result<(), TlbError> {
+        for constructors in self.types.values() {
+            for field in constructors.iter().flat_map(|c| c.fields.iter()) {
+                self.validate_type(&field.ty)?;
+            }
+        }
+        for type_name in self.types.keys() {
+            self.ensure_not_inline_recursive(type_name, &mut vec![])?;
+        }
+        Ok(())
+    }
+
+    fn validate_type(&self, ty: &TlbType) -> Result<(), TlbError> {
+        match ty {
+            TlbType::Named(name) if !self.types.contains_key(name) => {
+                Err(TlbError::UnknownType(name.clone()))
+            }
+            TlbType::Maybe(t) | TlbType::Ref(t) => self.validate_type(t),
+            TlbType::Either(l, r) => {
+                self.validate_type(l)?;
+                self.validate_type(r)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decoding a type that contains itself without a reference would never terminate.
+    fn ensure_not_inline_recursive(
+        &self,
+        type_name: &str,
+        path: &mut Vec<String>,
+    ) -> Result<(), TlbError> {
+        if path.iter().any(|p| p == type_name) {
+            return Err(TlbError::Unsupported {
+                declaration: 0,
+                message: format!(
+                    "type {} contains itself without a reference ({} -> {})",
+                    type_name,
+                    path.join(" -> "),
+                    type_name
+                ),
+            });
+        }
+        path.push(type_name.to_string());
+        let mut inline_types = vec![];
+        for field in self.types[type_name].iter().flat_map(|c| c.fields.iter()) {
+            collect_inline_types(&field.ty, &mut inline_types);
+        }
+        for inline_type in inline_types {
+            self.ensure_not_inline_recursive(inline_type, path)?;
+        }
+        path.pop();
+        Ok(())
+    }
+}
+
+fn collect_inline_types<'a>(ty: &'a TlbType, result: &mut Vec<&'a str>) {
+    match ty {
+        TlbType::Named(name) => result.push(name),
+        TlbType::Maybe(t) => collect_inline_types(t, result),
+        TlbType::Either(l, r) => {
+            collect_inline_types(l, result);
+            collect_inline_types(r, result);
+        }
+        _ => {}
+    }
+}
+
+fn strip_comments(source: &str) -> Result<String, TlbError> {
+    let mut result = String::with_capacity(source.len());
+    let mut rest = source;
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix("//") {
+            rest = tail.find('\n').map(|pos| &tail[pos..]).unwrap_or_default();
+        } else if let Some(tail) = rest.strip_prefix("/*") {
+            match tail.find("*/") {
+                Some(pos) => {
+                    result.push(' ');
+                    rest = &tail[pos + 2..];
+                }
+                None => {
+                    return Err(TlbError::SchemaParse {
+                        declaration: result.matches(';').count() + 1,
+                        message: "unterminated comment".to_string(),
+                    })
+                }
+            }
+        } else {
+            let mut chars = rest.chars();
+            if let Some(c) = chars.next() {
+                result.push(c);
+            }
+            rest = chars.as_str();
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Colon,
+    LParen,
+    RParen,
+    Caret,
+    Equals,
+    Other(char),
+}
+
+fn tokenize(declaration: &str) -> Vec<Token> {
+    let mut tokens = vec![];
+    let mut chars = declaration.chars().peekable();
+    while let Some(c) = chars.next() {
+        let token = match c {
+            c if c.is_whitespace() => continue,
+            ':' => Token::Colon,
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '^' => Token::Caret,
+            '=' => Token::Equals,
+            c if is_ident_char(c) => {
+                let mut ident = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !is_ident_char(next) {
+                        break;
+                    }
+                    ident.push(next);
+                    chars.next();
+                }
+                Token::Ident(ident)
+            }
+            c => Token::Other(c),
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '#' || c == '$'
+}
+
+enum Arg {
+    Nat(usize),
+    Type(TlbType),
+}
+
+struct DeclarationParser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    declaration: usize,
+}
+
+impl<'a> DeclarationParser<'a> {
+    fn new(tokens: &'a [Token], declaration: usize) -> Self {
+        DeclarationParser {
+            tokens,
+            pos: 0,
+            declaration,
+        }
+    }
+
+    fn parse(mut self) -> Result<(String, TlbConstructor), TlbError> {
+        let equals = self
+            .tokens
+            .iter()
+            .position(|t| *t == Token::Equals)
+            .ok_or_else(|| self.parse_error("expected `=`"))?;
+        let type_name = match &self.tokens[equals + 1..] {
+            [Token::Ident(name)] => name.clone(),
+            [] => return Err(self.parse_error("missing type name after `=`")),
+            [Token::Ident(name), ..] => {
+                return Err(self.unsupported(format!("parameterized type `{}`", name)))
+            }
+            _ => return Err(self.parse_error("expected type name after `=`")),
+        };
+        self.tokens = &self.tokens[..equals];
+
+        let (name, tag) = match self.next() {
+            Some(Token::Ident(ident)) => self.parse_constructor(ident)?,
+            _ => return Err(self.parse_error("expected constructor name")),
+        };
+        let mut fields = vec![];
+        while self.peek().is_some() {
+            fields.push(self.parse_field()?);
+        }
+        Ok((type_name, TlbConstructor { name, tag, fields }))
+    }
+
+    fn parse_constructor(&self, ident: &str) -> Result<(String, Vec<bool>), TlbError> {
+        let split = match ident.find(['#', '$']) {
+            Some(split) => split,
+            None if ident == "_" => return Ok(("_".to_string(), vec![])),
+            None => {
+                return Err(self.unsupported(format!(
+                    "constructor `{0}` has no explicit tag, implicit CRC32 tags are not supported; use `{0}$_` or an explicit tag",
+                    ident
+                )))
+            }
+        };
+        let name = match &ident[..split] {
+            "" => "_".to_string(),
+            name => name.to_string(),
+        };
+        let (radix, digits) = ident[split..].split_at(1);
+        if digits == "_" {
+            return Ok((name, vec![]));
+        }
+        let bits_per_digit = if radix == "#" { 4 } else { 1 };
+        let mut tag = vec![];
+        for digit in digits.chars() {
+            let value = digit
+                .to_digit(1 << bits_per_digit)
+                .ok_or_else(|| self.parse_error(&format!("invalid constructor tag `{}`", ident)))?;
+            for bit in (0..bits_per_digit).rev() {
+                tag.push(value & (1 << bit) != 0);
+            }
+        }
+        if tag.is_empty() {
+            return Err(self.parse_error(&format!("empty constructor tag `{}`", ident)));
+        }
+        Ok((name, tag))
+    }
+
+    fn parse_field(&mut self) -> Result<TlbField, TlbError> {
+        let name = match (self.peek(), self.tokens.get(self.pos + 1)) {
+            (Some(Token::Ident(name)), Some(Token::Colon)) => {
+                let name = name.clone();
+                self.pos += 2;
+                name
+            }
+            _ => "_".to_string(),
+        };
+        let ty = self.parse_term(false)?;
+        Ok(TlbField { name, ty })
+    }
+
+    fn parse_term(&mut self, in_ref: bool) -> Result<TlbType, TlbError> {
+        match self.next() {
+            Some(Token::Caret) => Ok(TlbType::Ref(Box::new(self.parse_term(true)?))),
+            Some(Token::LParen) => {
+                let ty = self.parse_application(in_ref)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(ty),
+                    _ => Err(self.parse_error("expected `)`")),
+                }
+            }
+            Some(Token::Ident(ident)) => self.simple_type(ident, in_ref),
+            Some(Token::Other('{')) => {
+                Err(self.unsupported("implicit fields and type parameters (`{...}`)".to_string()))
+            }
+            Some(Token::Other('.')) | Some(Token::Other('?')) => {
+                Err(self.unsupported("conditional fields (`flags.N?T`)".to_string()))
+            }
+            Some(Token::Other(c)) => Err(self.unsupported(format!("`{}`", c))),
+            Some(token) => Err(self.parse_error(&format!("unexpected {:?}", token))),
+            None => Err(self.parse_error("unexpected end of declaration")),
+        }
+    }
+
+    fn parse_application(&mut self, in_ref: bool) -> Result<TlbType, TlbError> {
+        let head = match self.peek() {
+            Some(Token::Ident(head)) => head.clone(),
+            _ => return self.parse_term(in_ref),
+        };
+        self.pos += 1;
+        let mut args = vec![];
+        while !matches!(self.peek(), Some(Token::RParen) | None) {
+            let arg = match self.peek() {
+                Some(Token::Ident(nat)) if nat.chars().all(|c| c.is_ascii_digit()) => {
+                    let nat = nat
+                        .parse()
+                        .map_err(|_| self.parse_error(&format!("invalid number `{}`", nat)))?;
+                    self.pos += 1;
+                    Arg::Nat(nat)
+                }
+                _ => Arg::Type(self.parse_term(false)?),
+            };
+            args.push(arg);
+        }
+        match (head.as_str(), args.as_slice()) {
+            (_, []) => self.simple_type(&head, in_ref),
+            ("Maybe", [Arg::Type(t)]) => Ok(TlbType::Maybe(Box::new(t.clone()))),
+            ("Either", [Arg::Type(l), Arg::Type(r)]) => {
+                Ok(TlbType::Either(Box::new(l.clone()), Box::new(r.clone())))
+            }
+            ("##", [Arg::Nat(n)]) => self.sized(TlbType::Uint(*n), *n, MAX_UINT_BITS),
+            ("VarUInteger", [Arg::Nat(16)]) => Ok(TlbType::Coins),
+            ("VarUInteger", _) => Err(self.unsupported("VarUInteger other than 16".to_string())),
+            _ => Err(self.unsupported(format!("type application `({} ...)`", head))),
+        }
+    }
+
+    fn simple_type(&self, ident: &str, in_ref: bool) -> Result<TlbType, TlbError> {
+        let ty = match ident {
+            "#" => TlbType::Uint(32),
+            "Bool" | "Bit" => TlbType::Bool,
+            "Coins" | "Grams" => TlbType::Coins,
+            "MsgAddress" | "MsgAddressInt" => TlbType::Address,
+            "Cell" if in_ref => TlbType::Cell,
+            "Cell" => return Err(self.unsupported("inline `Cell`, use `^Cell`".to_string())),
+            "##" => return Err(self.parse_error("`##` requires a bit length: `(## N)`")),
+            _ => {
+                if let Some(n) = parse_sized(ident, "uint") {
+                    self.sized(TlbType::Uint(n), n, MAX_UINT_BITS)?
+                } else if let Some(n) = parse_sized(ident, "int") {
+                    self.sized(TlbType::Int(n), n, MAX_INT_BITS)?
+                } else if let Some(n) = parse_sized(ident, "bits") {
+                    self.sized(TlbType::Bits(n), n, MAX_BITS)?
+                } else if ident.starts_with(|c: char| c.is_ascii_digit()) {
+                    return Err(self.parse_error(&format!("unexpected number `{}`", ident)));
+                } else {
+                    TlbType::Named(ident.to_string())
+                }
+            }
+        };
+        Ok(ty)
+    }
+
+    fn sized(&self, ty: TlbType, bit_len: usize, max_bits: usize) -> Result<TlbType, TlbError> {
+        if bit_len == 0 || bit_len > max_bits {
+            return Err(
+                self.parse_error(&format!("bit length of {} must be in 1..={}", ty, max_bits))
+            );
+        }
+        Ok(ty)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_error(&self, message: &str) -> TlbError {
+        TlbError::SchemaParse {
+            declaration: self.declaration,
+            message: message.to_string(),
+        }
+    }
+
+    fn unsupported(&self, message: String) -> TlbError {
+        TlbError::Unsupported {
+            declaration: self.declaration,
+            message: format!("{} is not supported", message),
+        }
+    }
+}
+
+fn parse_sized(ident: &str, prefix: &str) -> Option<usize> {
+    let digits = ident.strip_prefix(prefix)?;
+    if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok()
+}
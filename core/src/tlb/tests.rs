@@ -0,0 +1,241 @@
+use std::sync::Arc;
+
+use num_bigint::{BigInt, BigUint};
+
+use super::*;
+use crate::cell::{ArcCell, Cell, CellBuilder, TonCellError};
+use crate::TonAddress;
+
+const JETTON_WALLET_DATA: &str = "
+    // https://github.com/ton-blockchain/token-contract
+    _ balance:Coins owner_address:MsgAddressInt
+      jetton_master_address:MsgAddressInt jetton_wallet_code:^Cell = JettonWalletData;
+";
+
+const SHAPE: &str = "
+    point#01 x:int16 y:int16 = Point;
+    red$00 = Color;
+    green$01 = Color;
+    blue$1 = Color;
+    /* root type */
+    shape$_ origin:Point size:(## 10) color:Color fill:(Maybe ^Cell)
+      kind:(Either uint8 bits16) next:(Maybe ^Point) flag:Bool = Shape;
+";
+
+fn point_cell(x: i16, y: i16) -> Result<Cell, TonCellError> {
+    CellBuilder::new()
+        .store_u8(8, 0x01)?
+        .store_u32(16, x as u16 as u32)?
+        .store_u32(16, y as u16 as u32)?
+        .build()
+}
+
+fn point_value(x: i64, y: i64) -> TlbValue {
+    TlbValue::Struct {
+        constructor: "point".to_string(),
+        fields: vec![
+            ("x".to_string(), TlbValue::Int(BigInt::from(x))),
+            ("y".to_string(), TlbValue::Int(BigInt::from(y))),
+        ],
+    }
+}
+
+#[test]
+fn test_jetton_wallet_data() -> anyhow::Result<()> {
+    let owner = TonAddress::new(0, &[0x11; 32]);
+    let master = TonAddress::new(0, &[0x22; 32]);
+    let code: ArcCell = Arc::new(CellBuilder::new().store_u32(32, 0xdeadbeef)?.build()?);
+    let cell = CellBuilder::new()
+        .store_coins(&BigUint::from(1_000_000u32))?
+        .store_address(&owner)?
+        .store_address(&master)?
+        .store_reference(&code)?
+        .build()?;
+
+    let schema = TlbSchema::parse(JETTON_WALLET_DATA)?;
+    assert_eq!(schema.root(), "JettonWalletData");
+    let value = decode(&cell, &schema)?;
+    assert_eq!(
+        value.field("balance"),
+        Some(&TlbValue::Coins(BigUint::from(1_000_000u32)))
+    );
+    assert_eq!(
+        value.field("owner_address"),
+        Some(&TlbValue::Address(owner))
+    );
+    assert_eq!(
+        value.field("jetton_master_address"),
+        Some(&TlbValue::Address(master))
+    );
+    assert_eq!(
+        value.field("jetton_wallet_code"),
+        Some(&TlbValue::Cell(code))
+    );
+
+    let encoded = encode(&value, &schema)?;
+    assert_eq!(encoded.cell_hash(), cell.cell_hash());
+    Ok(())
+}
+
+#[test]
+fn test_custom_struct() -> anyhow::Result<()> {
+    let fill: ArcCell = Arc::new(CellBuilder::new().store_u8(8, 0xff)?.build()?);
+    let cell = CellBuilder::new()
+        .store_cell(&point_cell(-5, 300)?)?
+        .store_u32(10, 1000)?
+        .store_bit(true)? // blue
+        .store_bit(true)?
+        .store_reference(&fill)?
+        .store_bit(true)?
+        .store_bits(16, &[0xab, 0xcd])?
+        .store_bit(true)?
+        .store_child(point_cell(1, -2)?)?
+        .store_bit(false)?
+        .build()?;
+
+    let schema = TlbSchema::parse(SHAPE)?;
+    assert_eq!(schema.root(), "Shape");
+    assert_eq!(schema.constructors("Color").map(|c| c.len()), Some(3));
+    let value = decode(&cell, &schema)?;
+    assert_eq!(value.field("origin"), Some(&point_value(-5, 300)));
+    assert_eq!(
+        value.field("size"),
+        Some(&TlbValue::Uint(BigUint::from(1000u32)))
+    );
+    assert_eq!(
+        value.field("color"),
+        Some(&TlbValue::Struct {
+            constructor: "blue".to_string(),
+            fields: vec![]
+        })
+    );
+    assert_eq!(
+        value.field("fill"),
+        Some(&TlbValue::Maybe(Some(Box::new(TlbValue::Cell(fill)))))
+    );
+    assert_eq!(
+        value.field("kind"),
+        Some(&TlbValue::Right(Box::new(TlbValue::Bits(vec![0xab, 0xcd]))))
+    );
+    assert_eq!(
+        value.field("next"),
+        Some(&TlbValue::Maybe(Some(Box::new(point_value(1, -2)))))
+    );
+    assert_eq!(value.field("flag"), Some(&TlbValue::Bool(false)));
+
+    let encoded = encode(&value, &schema)?;
+    assert_eq!(encoded.cell_hash(), cell.cell_hash());
+    Ok(())
+}
+
+#[test]
+fn test_encode_decode_value() -> anyhow::Result<()> {
+    let schema = TlbSchema::parse(SHAPE)?;
+    let value = TlbValue::Struct {
+        constructor: "shape".to_string(),
+        fields: vec![
+            ("origin".to_string(), point_value(-32768, 32767)),
+            ("size".to_string(), TlbValue::Uint(BigUint::from(0u32))),
+            (
+                "color".to_string(),
+                TlbValue::Struct {
+                    constructor: "green".to_string(),
+                    fields: vec![],
+                },
+            ),
+            ("fill".to_string(), TlbValue::Maybe(None)),
+            (
+                "kind".to_string(),
+                TlbValue::Left(Box::new(TlbValue::Uint(BigUint::from(7u32)))),
+            ),
+            ("next".to_string(), TlbValue::Maybe(None)),
+            ("flag".to_string(), TlbValue::Bool(true)),
+        ],
+    };
+    let cell = encode(&value, &schema)?;
+    assert_eq!(cell.bit_len(), 8 + 32 + 10 + 2 + 1 + 1 + 8 + 1 + 1);
+    assert_eq!(decode(&cell, &schema)?, value);
+
+    let point_schema = schema.with_root("Point")?;
+    let cell = encode(&point_value(-1, 0), &point_schema)?;
+    assert_eq!(cell.data(), &[0x01, 0xff, 0xff, 0x00, 0x00]);
+
+    let overflow = encode(&point_value(40000, 0), &point_schema);
+    assert!(matches!(overflow, Err(TlbError::ValueMismatch(_))));
+    let wrong_kind = encode(&TlbValue::Bool(true), &point_schema);
+    assert!(matches!(wrong_kind, Err(TlbError::ValueMismatch(_))));
+    Ok(())
+}
+
+#[test]
+fn test_decode_errors() -> anyhow::Result<()> {
+    let schema = TlbSchema::parse("red$00 = Color; green$01 = Color;")?;
+    let cell = CellBuilder::new().store_u8(2, 0b10)?.build()?;
+    assert!(matches!(
+        decode(&cell, &schema),
+        Err(TlbError::NoMatchingConstructor { .. })
+    ));
+
+    let schema = TlbSchema::parse("_ x:uint8 = T;")?;
+    let cell = CellBuilder::new().store_u32(16, 1)?.build()?;
+    assert!(matches!(
+        decode(&cell, &schema),
+        Err(TlbError::TonCellError(_))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_schema_errors() {
+    let unsupported = [
+        "_ {n:#} x:(## n) = T;",
+        "_ flags:(## 8) x:flags.0?uint32 = T;",
+        "_ d:(HashmapE 32 ^Cell) = T;",
+        "_ x:Cell = T;",
+        "_ x:(Either Cell ^Cell) = T;",
+        "point x:uint8 = Point;",
+        "_ x:uint8 = List X;",
+        "_ x:(VarUInteger 32) = T;",
+        "_ x:uint8 next:T = T;",
+    ];
+    for schema in unsupported {
+        let result = TlbSchema::parse(schema);
+        assert!(
+            matches!(result, Err(TlbError::Unsupported { .. })),
+            "{}: {:?}",
+            schema,
+            result
+        );
+    }
+
+    let invalid = [
+        "_ x:uint8 = T",
+        "_ x:uint300 = T;",
+        "_ x:bits0 = T;",
+        "a$0 = A; b$01 = A;",
+        "a$0 = A; b$_ = A;",
+        "_ x:uint8 T;",
+        "t#xyz = T;",
+        "_ x:## = T;",
+        "/* comment",
+        "",
+    ];
+    for schema in invalid {
+        let result = TlbSchema::parse(schema);
+        assert!(
+            matches!(result, Err(TlbError::SchemaParse { .. })),
+            "{}: {:?}",
+            schema,
+            result
+        );
+    }
+
+    assert!(matches!(
+        TlbSchema::parse("_ x:Foo = T;"),
+        Err(TlbError::UnknownType(name)) if name == "Foo"
+    ));
+    assert!(matches!(
+        TlbSchema::parse("_ x:uint8 = T;").and_then(|s| s.with_root("U")),
+        Err(TlbError::UnknownType(_))
+    ));
+}
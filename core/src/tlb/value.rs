@@ -0,0 +1,54 @@
+use num_bigint::{BigInt, BigUint};
+
+use crate::cell::ArcCell;
+use crate::TonAddress;
+
+/// Dynamic value tree produced by [`decode`](super::decode) and consumed by [`encode`](super::encode).
+///
+/// `^T` fields hold the value of `T` directly, except `^Cell` which is kept as [`TlbValue::Cell`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum TlbValue {
+    Bool(bool),
+    Uint(BigUint),
+    Int(BigInt),
+    /// `bitsN` value, padded with zeros to whole bytes
+    Bits(Vec<u8>),
+    Coins(BigUint),
+    Address(TonAddress),
+    Cell(ArcCell),
+    Maybe(Option<Box<TlbValue>>),
+    Left(Box<TlbValue>),
+    Right(Box<TlbValue>),
+    Struct {
+        constructor: String,
+        fields: Vec<(String, TlbValue)>,
+    },
+}
+
+impl TlbValue {
+    /// Returns the value of the named field of a `Struct`.
+    pub fn field(&self, name: &str) -> Option<&TlbValue> {
+        match self {
+            TlbValue::Struct { fields, .. } => {
+                fields.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            TlbValue::Bool(_) => "Bool",
+            TlbValue::Uint(_) => "Uint",
+            TlbValue::Int(_) => "Int",
+            TlbValue::Bits(_) => "Bits",
+            TlbValue::Coins(_) => "Coins",
+            TlbValue::Address(_) => "Address",
+            TlbValue::Cell(_) => "Cell",
+            TlbValue::Maybe(_) => "Maybe",
+            TlbValue::Left(_) => "Left",
+            TlbValue::Right(_) => "Right",
+            TlbValue::Struct { .. } => "Struct",
+        }
+    }
+}
@@ -1,15 +1,58 @@
+use base64::engine::general_purpose::{STANDARD, URL_SAFE};
+use base64::Engine;
+
+mod account_state;
 mod address;
+mod augment;
+mod coins;
+mod config_validator_set;
 mod error;
+mod extra_currency;
+mod storage_prices;
+mod transaction;
 mod tx_id;
 
+pub use account_state::*;
 pub use address::*;
+pub use augment::*;
+pub use coins::*;
+pub use config_validator_set::*;
 pub use error::*;
+pub use extra_currency::*;
+pub use storage_prices::*;
+pub use transaction::*;
 pub use tx_id::*;
 
 pub const TON_HASH_BYTES: usize = 32;
 pub const ZERO_HASH: TonHash = [0; 32];
 pub type TonHash = [u8; TON_HASH_BYTES];
 
+/// Text forms of `TonHash` used by explorers
+pub trait TonHashExt {
+    /// Lowercase hex
+    fn to_hex(&self) -> String;
+
+    /// Standard base64 with padding
+    fn to_base64(&self) -> String;
+
+    /// URL-safe base64 with padding, as used in explorer links
+    fn to_base64_url(&self) -> String;
+}
+
+impl TonHashExt for TonHash {
+    fn to_hex(&self) -> String {
+        hex::encode(self)
+    }
+
+    fn to_base64(&self) -> String {
+        STANDARD.encode(self)
+    }
+
+    fn to_base64_url(&self) -> String {
+        URL_SAFE.encode(self)
+    }
+}
+
 pub const DEFAULT_CELL_HASH: TonHash = [
     150, 162, 150, 210, 36, 242, 133, 198, 123, 238, 147, 195, 15, 138, 48, 145, 87, 240, 218, 163,
     93, 197, 184, 126, 65, 11, 120, 99, 10, 9, 207, 199,
@@ -0,0 +1,279 @@
+use num_bigint::BigUint;
+
+use crate::cell::{ArcCell, Cell, CellParser, TonCellError};
+use crate::types::{Coins, ExtraCurrencyCollection};
+use crate::{TonAddress, TonHash};
+
+/// Storage used by an account
+///
+/// ```storage_used$_ cells:(VarUInteger 7) bits:(VarUInteger 7) public_cells:(VarUInteger 7) = StorageUsed;```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageUsed {
+    pub cells: u64,
+    pub bits: u64,
+    pub public_cells: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AccountStatus {
+    Uninit,
+    Active {
+        code: Option<ArcCell>,
+        data: Option<ArcCell>,
+    },
+    Frozen {
+        state_hash: TonHash,
+    },
+}
+
+/// Tick-tock flags of a special masterchain account (elector, config, etc.)
+///
+/// ```tick_tock$_ tick:Bool tock:Bool = TickTock;```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TickTock {
+    pub tick: bool,
+    pub tock: bool,
+}
+
+/// Parsed `Account` structure of the blockchain state
+///
+/// ```account$1 addr:MsgAddressInt storage_stat:StorageInfo storage:AccountStorage = Account;```
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountState {
+    pub address: TonAddress,
+    pub storage_used: StorageUsed,
+    /// Unix time of the last storage fee payment
+    pub last_paid: u32,
+    /// Storage fees that the account failed to pay, zero if there is no debt.
+    pub storage_due: Coins,
+    pub last_trans_lt: u64,
+    pub balance: Coins,
+    pub extra_currencies: ExtraCurrencyCollection,
+    pub status: AccountStatus,
+    /// Tick-tock flags from the state init of an active special account, `None` for ordinary accounts.
+    pub special: Option<TickTock>,
+}
+
+impl AccountState {
+    /// Parses `Account` from the cell, returns `None` for `account_none$0`.
+    pub fn parse(cell: &Cell) -> Result<Option<AccountState>, TonCellError> {
+        let mut parser = cell.parser();
+        if !parser.load_bit()? {
+            return Ok(None);
+        }
+        let address = parser.load_address()?;
+        // storage_info$_ used:StorageUsed last_paid:uint32 due_payment:(Maybe Grams) = StorageInfo;
+        let storage_used = StorageUsed {
+            cells: load_var_uint7(&mut parser)?,
+            bits: load_var_uint7(&mut parser)?,
+            public_cells: load_var_uint7(&mut parser)?,
+        };
+        let last_paid = parser.load_u32(32)?;
+        let storage_due = if parser.load_bit()? {
+            parser.load_coins()?.into()
+        } else {
+            Coins::zero()
+        };
+        // account_storage$_ last_trans_lt:uint64 balance:CurrencyCollection state:AccountState = AccountStorage;
+        let last_trans_lt = parser.load_u64(64)?;
+        let balance = parser.load_coins()?.into();
+        let extra_currencies = ExtraCurrencyCollection::parse(&mut parser)?;
+        let mut special = None;
+        let status = if parser.load_bit()? {
+            // account_active$1 _:StateInit = AccountState;
+            if parser.load_bit()? {
+                parser.skip_bits(5)?; // split_depth
+            }
+            if parser.load_bit()? {
+                special = Some(TickTock {
+                    tick: parser.load_bit()?,
+                    tock: parser.load_bit()?,
+                });
+            }
+            let code = parser.load_maybe_cell_ref()?;
+            let data = parser.load_maybe_cell_ref()?;
+            let _library = parser.load_maybe_cell_ref()?;
+            AccountStatus::Active { code, data }
+        } else if parser.load_bit()? {
+            // account_frozen$01 state_hash:bits256 = AccountState;
+            let mut state_hash = [0u8; 32];
+            parser.load_slice(&mut state_hash)?;
+            AccountStatus::Frozen { state_hash }
+        } else {
+            AccountStatus::Uninit
+        };
+
+        Ok(Some(AccountState {
+            address,
+            storage_used,
+            last_paid,
+            storage_due,
+            last_trans_lt,
+            balance,
+            extra_currencies,
+            status,
+            special,
+        }))
+    }
+
+    /// Returns `true` if the account was frozen because of unpaid storage fees.
+    ///
+    /// Non-zero `storage_due` of an active account means it is accruing debt and will be frozen
+    /// once the debt exceeds the freeze limit.
+    pub fn is_frozen_for_storage(&self) -> bool {
+        matches!(self.status, AccountStatus::Frozen { .. })
+    }
+
+    /// Returns the balance of extra currency `currency_id`, `None` if the account doesn't hold it
+    pub fn get_extra_currency_balance(&self, currency_id: u32) -> Option<BigUint> {
+        self.extra_currencies.get(currency_id).cloned()
+    }
+}
+
+fn load_var_uint7(parser: &mut CellParser) -> Result<u64, TonCellError> {
+    let len = parser.load_u8(3)? as usize;
+    if len == 0 {
+        Ok(0)
+    } else {
+        parser.load_u64(len * 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    fn build_account(due_payment: Option<u64>, frozen: bool) -> Result<Cell, TonCellError> {
+        build_account_with_special(due_payment, frozen, None, None)
+    }
+
+    fn build_account_with_special(
+        due_payment: Option<u64>,
+        frozen: bool,
+        special: Option<TickTock>,
+        extra_currencies: Option<ArcCell>,
+    ) -> Result<Cell, TonCellError> {
+        let address = TonAddress::new(0, &[0x11; 32]);
+        let mut builder = CellBuilder::new();
+        builder
+            .store_bit(true)?
+            .store_address(&address)?
+            .store_u8(3, 1)?
+            .store_u8(8, 5)? // cells
+            .store_u8(3, 2)?
+            .store_u32(16, 1000)? // bits
+            .store_u8(3, 0)? // public_cells
+            .store_u32(32, 1700000000)?;
+        match due_payment {
+            Some(due) => builder.store_bit(true)?.store_coins(&BigUint::from(due))?,
+            None => builder.store_bit(false)?,
+        };
+        builder
+            .store_u64(64, 42)?
+            .store_coins(&BigUint::from(1_000_000_000u64))?
+            .store_maybe_cell_ref(&extra_currencies)?;
+        if frozen {
+            builder.store_u8(2, 0b01)?.store_slice(&[0x22; 32])?;
+        } else {
+            builder.store_bit(true)?.store_bit(false)?; // no split_depth
+            match special {
+                Some(tick_tock) => builder
+                    .store_bit(true)?
+                    .store_bit(tick_tock.tick)?
+                    .store_bit(tick_tock.tock)?,
+                None => builder.store_bit(false)?,
+            };
+            builder
+                .store_maybe_cell_ref(&None)?
+                .store_maybe_cell_ref(&None)?
+                .store_bit(false)?;
+        }
+        builder.build()
+    }
+
+    #[test]
+    fn test_parse_active_account_without_due() -> Result<(), TonCellError> {
+        let account = AccountState::parse(&build_account(None, false)?)?.unwrap();
+        assert_eq!(account.address, TonAddress::new(0, &[0x11; 32]));
+        assert_eq!(account.storage_used.cells, 5);
+        assert_eq!(account.storage_used.bits, 1000);
+        assert_eq!(account.storage_used.public_cells, 0);
+        assert_eq!(account.last_paid, 1700000000);
+        assert_eq!(account.storage_due, Coins::zero());
+        assert_eq!(account.last_trans_lt, 42);
+        assert_eq!(account.balance, Coins::from(1_000_000_000u64));
+        assert!(!account.is_frozen_for_storage());
+        assert_eq!(account.special, None);
+
+        let account = AccountState::parse(&build_account(Some(0), false)?)?.unwrap();
+        assert!(account.storage_due.is_zero());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_special_account() -> Result<(), TonCellError> {
+        let tick_tock = TickTock {
+            tick: true,
+            tock: false,
+        };
+        let cell = build_account_with_special(None, false, Some(tick_tock), None)?;
+        let account = AccountState::parse(&cell)?.unwrap();
+        assert_eq!(account.special, Some(tick_tock));
+        assert_eq!(
+            account.status,
+            AccountStatus::Active {
+                code: None,
+                data: None
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_extra_currencies() -> Result<(), TonCellError> {
+        let account = AccountState::parse(&build_account(None, false)?)?.unwrap();
+        assert!(account.extra_currencies.is_empty());
+        assert_eq!(account.get_extra_currency_balance(100), None);
+
+        let writer = |builder: &mut CellBuilder, amount: u64| {
+            builder.store_u8(5, 8)?.store_u64(64, amount)?;
+            Ok(())
+        };
+        let dict = CellBuilder::new()
+            .store_dict(32, writer, HashMap::from([(100u32, 777u64)]))?
+            .build()?;
+        let cell = build_account_with_special(None, false, None, Some(Arc::new(dict)))?;
+        let account = AccountState::parse(&cell)?.unwrap();
+        assert_eq!(
+            account.get_extra_currency_balance(100),
+            Some(BigUint::from(777u32))
+        );
+        assert_eq!(account.get_extra_currency_balance(101), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_frozen_account() -> Result<(), TonCellError> {
+        let account = AccountState::parse(&build_account(Some(12345), true)?)?.unwrap();
+        assert_eq!(account.storage_due, Coins::from(12345u64));
+        assert_eq!(
+            account.status,
+            AccountStatus::Frozen {
+                state_hash: [0x22; 32]
+            }
+        );
+        assert!(account.is_frozen_for_storage());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_account_none() -> Result<(), TonCellError> {
+        let cell = CellBuilder::new().store_bit(false)?.build()?;
+        assert_eq!(AccountState::parse(&cell)?, None);
+        Ok(())
+    }
+}
@@ -14,6 +14,12 @@ lazy_static! {
     pub static ref CRC_16_XMODEM: Crc<u16> = Crc::<u16>::new(&crc::CRC_16_XMODEM);
 }
 
+/// Address of an account: workchain and hash of the account id.
+///
+/// Bounceable and testnet flags of user-friendly forms aren't part of the address: parsing
+/// drops them and formatting takes them as arguments. So equality and hashing compare
+/// the on-chain identity only, and all forms of the same account are equal map keys.
+/// Use `from_base64_url_flags`/`from_base64_std_flags` if the flags matter.
 #[derive(PartialEq, Eq, Clone, Hash)]
 pub struct TonAddress {
     pub workchain: i32,
@@ -37,6 +43,29 @@ impl TonAddress {
         TonAddress::NULL.clone()
     }
 
+    /// Returns `true` if the workchain fits `int8`, as required by `addr_std` and user-friendly formats.
+    ///
+    /// Other workchains are valid `int32` ids, but such addresses are stored as `addr_var`
+    /// and can only be formatted as raw `workchain:hex`.
+    pub fn fits_addr_std(&self) -> bool {
+        i8::try_from(self.workchain).is_ok()
+    }
+
+    /// Returns `true` if the address belongs to the shard `shard` of `workchain`.
+    ///
+    /// `shard` is the shard id as in `BlockIdExt`: the shard prefix followed by a single `1` bit.
+    pub fn is_in_shard(&self, workchain: i32, shard: i64) -> bool {
+        let shard = shard as u64;
+        if self.workchain != workchain || shard == 0 {
+            return false;
+        }
+        let tag_bit = shard & shard.wrapping_neg();
+        let mask = !(tag_bit | (tag_bit - 1));
+        let account_prefix =
+            u64::from_be_bytes(self.hash_part[0..8].try_into().unwrap_or_default());
+        account_prefix & mask == shard & mask
+    }
+
     pub fn from_hex_str(s: &str) -> Result<TonAddress, TonAddressParseError> {
         let parts: Vec<&str> = s.split(':').collect();
 
@@ -209,6 +238,9 @@ impl TonAddress {
         format!("{}:{}", self.workchain, hex::encode(self.hash_part))
     }
 
+    /// Formats the address in url-safe base64.
+    ///
+    /// The format stores the workchain in a single byte, so it's only applicable if `fits_addr_std`.
     pub fn to_base64_url(&self) -> String {
         self.to_base64_url_flags(false, false)
     }
@@ -219,6 +251,7 @@ impl TonAddress {
         URL_SAFE_NO_PAD.encode(buf)
     }
 
+    /// Formats the address in standard base64, only applicable if `fits_addr_std`.
     pub fn to_base64_std(&self) -> String {
         self.to_base64_std_flags(false, false)
     }
@@ -247,13 +280,17 @@ impl TonAddress {
 
 impl Display for TonAddress {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.to_base64_url().as_str())
+        if self.fits_addr_std() {
+            f.write_str(self.to_base64_url().as_str())
+        } else {
+            f.write_str(self.to_hex().as_str())
+        }
     }
 }
 
 impl Debug for TonAddress {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.to_base64_url().as_str())
+        Display::fmt(self, f)
     }
 }
 
@@ -287,7 +324,7 @@ impl Serialize for TonAddress {
     where
         S: Serializer,
     {
-        serializer.serialize_str(self.to_base64_url().as_str())
+        serializer.serialize_str(self.to_string().as_str())
     }
 }
 
@@ -319,6 +356,7 @@ impl<'de> Deserialize<'de> for TonAddress {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashSet;
 
     use serde_json::Value;
 
@@ -483,4 +521,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn custom_workchain_works() -> anyhow::Result<()> {
+        let hash_part = [0xa5; 32];
+        let addr = TonAddress::new(1, &hash_part);
+        assert!(addr.fits_addr_std());
+        let b64 = addr.to_base64_url();
+        assert_eq!(TonAddress::from_base64_url(&b64)?, addr);
+        assert_eq!(addr.to_string(), b64);
+        assert_eq!(addr.to_hex(), format!("1:{}", hex::encode(hash_part)));
+
+        let mc_addr = TonAddress::new(-1, &hash_part);
+        assert_eq!(
+            TonAddress::from_base64_url(&mc_addr.to_base64_url())?,
+            mc_addr
+        );
+
+        // beyond int8 only the raw form is available
+        let addr = TonAddress::new(1000, &hash_part);
+        assert!(!addr.fits_addr_std());
+        assert_eq!(addr.to_string(), addr.to_hex());
+        assert_eq!(addr.to_string().parse::<TonAddress>()?, addr);
+        Ok(())
+    }
+
+    #[test]
+    fn is_in_shard_works() {
+        let mut hash_part = [0; 32];
+        hash_part[0] = 0b1010_0000;
+        let addr = TonAddress::new(1, &hash_part);
+        // full shard
+        assert!(addr.is_in_shard(1, i64::MIN));
+        assert!(!addr.is_in_shard(0, i64::MIN));
+        // prefixes 1 and 10
+        assert!(addr.is_in_shard(1, 0xc000_0000_0000_0000u64 as i64));
+        assert!(!addr.is_in_shard(1, 0x4000_0000_0000_0000));
+        assert!(addr.is_in_shard(1, 0xa000_0000_0000_0000u64 as i64));
+        assert!(!addr.is_in_shard(1, 0xe000_0000_0000_0000u64 as i64));
+        assert!(!addr.is_in_shard(1, 0));
+    }
+
+    #[test]
+    fn eq_ignores_flags() -> Result<(), TonAddressParseError> {
+        let addr = TonAddress::new(0, &[0xab; 32]);
+        let forms = [
+            addr.to_hex(),
+            addr.to_base64_url_flags(false, false),
+            addr.to_base64_url_flags(true, false),
+            addr.to_base64_std_flags(false, true),
+            addr.to_base64_std_flags(true, true),
+        ];
+        let mut set = HashSet::new();
+        for form in forms {
+            let parsed = form.parse::<TonAddress>()?;
+            assert_eq!(parsed, addr);
+            set.insert(parsed);
+        }
+        assert_eq!(set.len(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,64 @@
+use crate::cell::dict::AugValue;
+use crate::cell::{ArcCell, CellParser, TonCellError};
+use crate::types::Coins;
+
+/// Amount of nanotons with extra currencies (`CurrencyCollection`), the extra data
+/// of dictionaries aggregating balances or fees
+///
+/// ```raw
+/// currencies$_ grams:Grams other:ExtraCurrencyCollection = CurrencyCollection;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct CoinBalance {
+    pub grams: Coins,
+    /// Root of `HashmapE 32 (VarUInteger 32)` with extra currencies, `None` if there are none
+    pub other: Option<ArcCell>,
+}
+
+impl AugValue for CoinBalance {
+    fn parse(parser: &mut CellParser) -> Result<CoinBalance, TonCellError> {
+        Ok(CoinBalance {
+            grams: parser.load_coins()?.into(),
+            other: parser.load_maybe_cell_ref()?,
+        })
+    }
+}
+
+/// Extra data of account dictionaries of shard states
+///
+/// ```raw
+/// depth_balance$_ split_depth:(#<= 30) balance:CurrencyCollection = DepthBalanceInfo;
+/// _ (HashmapAugE 256 ShardAccount DepthBalanceInfo) = ShardAccounts;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct DepthBalanceInfo {
+    pub split_depth: u8,
+    pub balance: CoinBalance,
+}
+
+impl AugValue for DepthBalanceInfo {
+    fn parse(parser: &mut CellParser) -> Result<DepthBalanceInfo, TonCellError> {
+        Ok(DepthBalanceInfo {
+            split_depth: parser.load_u8(5)?,
+            balance: CoinBalance::parse(parser)?,
+        })
+    }
+}
+
+/// Extra data of the outbound message queue: the minimal lt of the queued messages
+///
+/// ```raw
+/// _ (HashmapAugE 352 EnqueuedMsg uint64) = OutMsgQueue;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct MessageQueueInfo {
+    pub min_lt: u64,
+}
+
+impl AugValue for MessageQueueInfo {
+    fn parse(parser: &mut CellParser) -> Result<MessageQueueInfo, TonCellError> {
+        Ok(MessageQueueInfo {
+            min_lt: parser.load_u64(64)?,
+        })
+    }
+}
@@ -0,0 +1,167 @@
+use std::fmt::{Display, Formatter};
+
+use num_bigint::BigUint;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+
+/// `Grams` take at most 15 bytes
+const MAX_COINS_BITS: u64 = 120;
+
+/// Number of fractional digits of TON amounts
+pub const TON_DECIMALS: u32 = 9;
+
+/// Formats `amount` of elementary units as a decimal number with up to `decimals` fractional digits,
+/// e.g. `1.5` for 1500000000 units with 9 decimals. Trailing zeros of the fraction are dropped.
+pub fn format_units(amount: &BigUint, decimals: u32) -> String {
+    let digits = amount.to_string();
+    let decimals = decimals as usize;
+    if decimals == 0 {
+        return digits;
+    }
+    let padded = format!("{:0>width$}", digits, width = decimals + 1);
+    let (integer, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        integer.to_string()
+    } else {
+        format!("{}.{}", integer, fraction)
+    }
+}
+
+/// Amount of nanotons, serialized in cells as `Grams` (`VarUInteger 16`).
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coins(BigUint);
+
+impl Coins {
+    pub fn new(nanotons: BigUint) -> Coins {
+        Coins(nanotons)
+    }
+
+    pub fn zero() -> Coins {
+        Coins(BigUint::zero())
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.0.is_zero()
+    }
+
+    pub fn nanotons(&self) -> &BigUint {
+        &self.0
+    }
+
+    /// The largest amount serializable as `Grams`, 2^120 - 1 nanotons
+    pub fn max_value() -> Coins {
+        Coins((BigUint::from(1u32) << MAX_COINS_BITS) - 1u32)
+    }
+
+    /// Returns `None` if the sum exceeds `Coins::max_value()`.
+    pub fn checked_add(&self, other: &Coins) -> Option<Coins> {
+        Self::within_bounds(&self.0 + &other.0)
+    }
+
+    /// Returns `None` if `other` is greater than `self`.
+    pub fn checked_sub(&self, other: &Coins) -> Option<Coins> {
+        if self.0 < other.0 {
+            None
+        } else {
+            Some(Coins(&self.0 - &other.0))
+        }
+    }
+
+    /// Returns `None` if the product exceeds `Coins::max_value()`.
+    pub fn checked_mul(&self, factor: u64) -> Option<Coins> {
+        Self::within_bounds(&self.0 * factor)
+    }
+
+    pub fn saturating_add(&self, other: &Coins) -> Coins {
+        self.checked_add(other).unwrap_or_else(Coins::max_value)
+    }
+
+    pub fn saturating_sub(&self, other: &Coins) -> Coins {
+        self.checked_sub(other).unwrap_or_else(Coins::zero)
+    }
+
+    pub fn saturating_mul(&self, factor: u64) -> Coins {
+        self.checked_mul(factor).unwrap_or_else(Coins::max_value)
+    }
+
+    /// Formats the amount in TON, e.g. `1.5` for 1500000000 nanotons
+    pub fn to_ton_string(&self) -> String {
+        format_units(&self.0, TON_DECIMALS)
+    }
+
+    fn within_bounds(nanotons: BigUint) -> Option<Coins> {
+        if nanotons.bits() > MAX_COINS_BITS {
+            None
+        } else {
+            Some(Coins(nanotons))
+        }
+    }
+}
+
+impl From<BigUint> for Coins {
+    fn from(value: BigUint) -> Self {
+        Coins(value)
+    }
+}
+
+impl From<u64> for Coins {
+    fn from(value: u64) -> Self {
+        Coins(BigUint::from(value))
+    }
+}
+
+impl From<Coins> for BigUint {
+    fn from(value: Coins) -> Self {
+        value.0
+    }
+}
+
+impl Display for Coins {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checked_arithmetic() {
+        let fee = Coins::from(10u64);
+        assert_eq!(
+            Coins::from(15u64).checked_sub(&fee),
+            Some(Coins::from(5u64))
+        );
+        assert_eq!(Coins::from(10u64).checked_sub(&fee), Some(Coins::zero()));
+        assert_eq!(Coins::from(9u64).checked_sub(&fee), None);
+        assert_eq!(
+            Coins::zero().checked_sub(&Coins::zero()),
+            Some(Coins::zero())
+        );
+        assert_eq!(Coins::from(9u64).saturating_sub(&fee), Coins::zero());
+
+        let max = Coins::max_value();
+        assert_eq!(max.checked_add(&Coins::zero()), Some(max.clone()));
+        assert_eq!(max.checked_add(&Coins::from(1u64)), None);
+        assert_eq!(max.saturating_add(&fee), max);
+        assert_eq!(fee.checked_add(&fee), Some(Coins::from(20u64)));
+
+        assert_eq!(fee.checked_mul(3), Some(Coins::from(30u64)));
+        assert_eq!(fee.checked_mul(0), Some(Coins::zero()));
+        assert_eq!(max.checked_mul(1), Some(max.clone()));
+        assert_eq!(max.checked_mul(2), None);
+        assert_eq!(max.saturating_mul(2), max);
+    }
+
+    #[test]
+    fn test_format_units() {
+        assert_eq!(format_units(&BigUint::from(1_500_000_000u64), 9), "1.5");
+        assert_eq!(format_units(&BigUint::from(1_000_000_000u64), 9), "1");
+        assert_eq!(format_units(&BigUint::from(1u32), 9), "0.000000001");
+        assert_eq!(format_units(&BigUint::from(0u32), 6), "0");
+        assert_eq!(format_units(&BigUint::from(1234u32), 0), "1234");
+        assert_eq!(Coins::from(25_000_000u64).to_ton_string(), "0.025");
+    }
+}
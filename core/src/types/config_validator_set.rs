@@ -0,0 +1,180 @@
+use crate::cell::dict::predefined_readers::key_reader_u16;
+use crate::cell::{Cell, CellParser, TonCellError};
+use crate::types::TonHash;
+
+const VALIDATORS_EXT_TAG: u8 = 0x12;
+const ED25519_PUBKEY_TAG: u32 = 0x8e81278a;
+const VALIDATOR_TAG: u8 = 0x53;
+const VALIDATOR_ADDR_TAG: u8 = 0x73;
+
+/// Validator of a validator set from the config
+///
+/// ```raw
+/// ed25519_pubkey#8e81278a pubkey:bits256 = SigPubKey;
+/// validator#53 public_key:SigPubKey weight:uint64 = ValidatorDescr;
+/// validator_addr#73 public_key:SigPubKey weight:uint64 adnl_addr:bits256 = ValidatorDescr;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ValidatorDescr {
+    pub public_key: TonHash,
+    pub weight: u64,
+    pub adnl_addr: Option<TonHash>,
+}
+
+impl ValidatorDescr {
+    pub fn parse(parser: &mut CellParser) -> Result<ValidatorDescr, TonCellError> {
+        let tag = parser.load_u8(8)?;
+        if tag != VALIDATOR_TAG && tag != VALIDATOR_ADDR_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid validator descr tag: {:#x}",
+                tag
+            )));
+        }
+        let key_tag = parser.load_u32(32)?;
+        if key_tag != ED25519_PUBKEY_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid public key tag: {:#x}",
+                key_tag
+            )));
+        }
+        let mut public_key = [0u8; 32];
+        parser.load_slice(&mut public_key)?;
+        let weight = parser.load_u64(64)?;
+        let adnl_addr = if tag == VALIDATOR_ADDR_TAG {
+            let mut adnl_addr = [0u8; 32];
+            parser.load_slice(&mut adnl_addr)?;
+            Some(adnl_addr)
+        } else {
+            None
+        };
+        Ok(ValidatorDescr {
+            public_key,
+            weight,
+            adnl_addr,
+        })
+    }
+}
+
+/// Validator set from config params 32, 34 or 36 (previous, current & next sets),
+/// validators are ordered by their index in the set
+///
+/// ```raw
+/// validators_ext#12 utime_since:uint32 utime_until:uint32
+///   total:(## 16) main:(## 16) { main <= total } { main >= 1 }
+///   total_weight:uint64 list:(HashmapE 16 ValidatorDescr) = ValidatorSet;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ConfigValidatorSet {
+    pub utime_since: u32,
+    pub utime_until: u32,
+    pub total: u16,
+    pub main: u16,
+    pub total_weight: u64,
+    pub validators: Vec<ValidatorDescr>,
+}
+
+impl ConfigValidatorSet {
+    /// Parses the value of config param 32, 34 or 36.
+    pub fn parse(cell: &Cell) -> Result<ConfigValidatorSet, TonCellError> {
+        let mut parser = cell.parser();
+        let tag = parser.load_u8(8)?;
+        if tag != VALIDATORS_EXT_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid validator set tag: {:#x}",
+                tag
+            )));
+        }
+        let utime_since = parser.load_u32(32)?;
+        let utime_until = parser.load_u32(32)?;
+        let total = parser.load_u16(16)?;
+        let main = parser.load_u16(16)?;
+        let total_weight = parser.load_u64(64)?;
+        let mut list = parser.load_dict(16, key_reader_u16, ValidatorDescr::parse)?;
+        let validators = (0..total)
+            .map(|index| {
+                list.remove(&index).ok_or_else(|| {
+                    TonCellError::InvalidCellData(format!("Validator {} is missing", index))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ConfigValidatorSet {
+            utime_since,
+            utime_until,
+            total,
+            main,
+            total_weight,
+            validators,
+        })
+    }
+
+    /// Returns the validator with index `idx`, as referred to by votes & signatures
+    pub fn validator(&self, idx: u16) -> Option<&ValidatorDescr> {
+        self.validators.get(idx as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    fn store_validator(
+        builder: &mut CellBuilder,
+        validator: ValidatorDescr,
+    ) -> Result<(), TonCellError> {
+        let tag = match validator.adnl_addr {
+            Some(_) => VALIDATOR_ADDR_TAG,
+            None => VALIDATOR_TAG,
+        };
+        builder
+            .store_u8(8, tag)?
+            .store_u32(32, ED25519_PUBKEY_TAG)?
+            .store_slice(&validator.public_key)?
+            .store_u64(64, validator.weight)?;
+        if let Some(adnl_addr) = validator.adnl_addr {
+            builder.store_slice(&adnl_addr)?;
+        }
+        Ok(())
+    }
+
+    fn validator_set_cell(validators: &[ValidatorDescr], total: u16) -> Result<Cell, TonCellError> {
+        let list: HashMap<u16, ValidatorDescr> = validators
+            .iter()
+            .enumerate()
+            .map(|(index, v)| (index as u16, v.clone()))
+            .collect();
+        CellBuilder::new()
+            .store_u8(8, VALIDATORS_EXT_TAG)?
+            .store_u32(32, 1700000000)?
+            .store_u32(32, 1700065536)?
+            .store_u32(16, total as u32)?
+            .store_u32(16, total as u32)?
+            .store_u64(64, validators.iter().map(|v| v.weight).sum())?
+            .store_dict(16, store_validator, list)?
+            .build()
+    }
+
+    #[test]
+    fn test_parse_config_param_34() -> Result<(), TonCellError> {
+        let validators: Vec<_> = (0u8..20)
+            .map(|i| ValidatorDescr {
+                public_key: [i; 32],
+                weight: 100 + i as u64,
+                adnl_addr: (i % 2 == 0).then_some([i + 100; 32]),
+            })
+            .collect();
+        let set = ConfigValidatorSet::parse(&validator_set_cell(&validators, 20)?)?;
+        assert_eq!(set.utime_since, 1700000000);
+        assert_eq!(set.total, 20);
+        assert_eq!(set.total_weight, 100 * 20 + 190);
+        assert_eq!(set.validators, validators);
+        assert_eq!(set.validator(13), Some(&validators[13]));
+        assert_eq!(set.validator(20), None);
+
+        // the list is shorter than declared
+        assert!(ConfigValidatorSet::parse(&validator_set_cell(&validators, 21)?).is_err());
+        Ok(())
+    }
+}
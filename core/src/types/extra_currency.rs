@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use crate::cell::dict::predefined_readers::key_reader_u32;
+use crate::cell::{ArcCell, CellParser, TonCellError};
+
+/// Amounts of extra currencies keyed by currency id
+///
+/// ```raw
+/// extra_currencies$_ dict:(HashmapE 32 (VarUInteger 32)) = ExtraCurrencyCollection;
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ExtraCurrencyCollection {
+    pub amounts: HashMap<u32, BigUint>,
+}
+
+impl ExtraCurrencyCollection {
+    pub fn parse(parser: &mut CellParser) -> Result<ExtraCurrencyCollection, TonCellError> {
+        Self::from_root(parser.load_maybe_cell_ref()?.as_ref())
+    }
+
+    /// Parses the collection from the root of its dictionary, `None` is an empty collection
+    pub fn from_root(root: Option<&ArcCell>) -> Result<ExtraCurrencyCollection, TonCellError> {
+        let amounts = match root {
+            Some(root) => root
+                .parser()
+                .load_dict(32, key_reader_u32, val_reader_var_uint32)?,
+            None => HashMap::new(),
+        };
+        Ok(ExtraCurrencyCollection { amounts })
+    }
+
+    pub fn get(&self, currency_id: u32) -> Option<&BigUint> {
+        self.amounts.get(&currency_id)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.amounts.is_empty()
+    }
+}
+
+/// Extra currency registered in the network
+///
+/// ```raw
+/// _ to_mint:ExtraCurrencyCollection = ConfigParam 7;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct ExtraCurrency {
+    pub id: u32,
+    /// Amount the validators mint up to
+    pub to_mint: BigUint,
+}
+
+/// Reads `VarUInteger 32`
+fn val_reader_var_uint32(parser: &mut CellParser) -> Result<BigUint, TonCellError> {
+    let len = parser.load_u8(5)? as usize;
+    if len == 0 {
+        Ok(BigUint::from(0u32))
+    } else {
+        parser.load_uint(len * 8)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    #[test]
+    fn test_parse_extra_currency_collection() -> Result<(), TonCellError> {
+        let writer = |builder: &mut CellBuilder, amount: BigUint| {
+            let len = amount.bits().div_ceil(8) as usize;
+            builder
+                .store_u8(5, len as u8)?
+                .store_uint(len * 8, &amount)?;
+            Ok(())
+        };
+        let amounts = HashMap::from([
+            (100u32, BigUint::from(5_000_000_000u64)),
+            (239, BigUint::from(1u32) << 200),
+        ]);
+        let cell = CellBuilder::new()
+            .store_bit(true)?
+            .store_child(
+                CellBuilder::new()
+                    .store_dict(32, writer, amounts.clone())?
+                    .build()?,
+            )?
+            .build()?;
+        let collection = ExtraCurrencyCollection::parse(&mut cell.parser())?;
+        assert_eq!(collection.amounts, amounts);
+        assert_eq!(collection.get(239), Some(&(BigUint::from(1u32) << 200)));
+        assert_eq!(collection.get(1), None);
+
+        let empty = CellBuilder::new().store_bit(false)?.build()?;
+        assert!(ExtraCurrencyCollection::parse(&mut empty.parser())?.is_empty());
+        Ok(())
+    }
+}
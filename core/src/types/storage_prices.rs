@@ -0,0 +1,220 @@
+use num_bigint::BigUint;
+
+use crate::cell::dict::predefined_readers::key_reader_u32;
+use crate::cell::{Cell, CellParser, TonCellError};
+use crate::types::Coins;
+
+const STORAGE_PRICES_TAG: u8 = 0xcc;
+/// Prices are set in nanotons per 2^16 seconds
+const PRICE_SHIFT: usize = 16;
+
+/// Storage prices valid since `utime_since`, in nanotons per bit or cell per 2^16 seconds
+///
+/// ```raw
+/// storage_prices#cc utime_since:uint32 bit_price_ps:uint64 cell_price_ps:uint64
+///   mc_bit_price_ps:uint64 mc_cell_price_ps:uint64 = StoragePrices;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StoragePrices {
+    pub utime_since: u32,
+    pub bit_price_ps: u64,
+    pub cell_price_ps: u64,
+    pub mc_bit_price_ps: u64,
+    pub mc_cell_price_ps: u64,
+}
+
+impl StoragePrices {
+    pub fn parse(parser: &mut CellParser) -> Result<StoragePrices, TonCellError> {
+        let tag = parser.load_u8(8)?;
+        if tag != STORAGE_PRICES_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid storage prices tag: {:#x}",
+                tag
+            )));
+        }
+        Ok(StoragePrices {
+            utime_since: parser.load_u32(32)?,
+            bit_price_ps: parser.load_u64(64)?,
+            cell_price_ps: parser.load_u64(64)?,
+            mc_bit_price_ps: parser.load_u64(64)?,
+            mc_cell_price_ps: parser.load_u64(64)?,
+        })
+    }
+
+    /// Computes the fee for storing `cells` and `bits` during `duration` seconds at these prices,
+    /// rounded up to a nanoton.
+    pub fn compute_storage_fee(
+        &self,
+        cells: u64,
+        bits: u64,
+        duration: u32,
+        is_masterchain: bool,
+    ) -> Coins {
+        ceil_shift(self.fee_shifted(cells, bits, duration, is_masterchain))
+    }
+
+    /// Fee multiplied by 2^16, as accumulated by the validator before rounding
+    fn fee_shifted(&self, cells: u64, bits: u64, duration: u32, is_masterchain: bool) -> BigUint {
+        let (bit_price, cell_price) = if is_masterchain {
+            (self.mc_bit_price_ps, self.mc_cell_price_ps)
+        } else {
+            (self.bit_price_ps, self.cell_price_ps)
+        };
+        let per_second = BigUint::from(cells) * cell_price + BigUint::from(bits) * bit_price;
+        per_second * duration
+    }
+}
+
+/// History of storage prices from config param 18, ordered by `utime_since`
+///
+/// ```raw
+/// _ (Hashmap 32 StoragePrices) = ConfigParam 18;
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct StoragePricesHistory {
+    epochs: Vec<StoragePrices>,
+}
+
+impl StoragePricesHistory {
+    /// Parses the value of config param 18.
+    pub fn parse(cell: &Cell) -> Result<StoragePricesHistory, TonCellError> {
+        let dict = cell
+            .parser()
+            .load_dict(32, key_reader_u32, StoragePrices::parse)?;
+        let mut epochs: Vec<StoragePrices> = dict.into_values().collect();
+        epochs.sort_by_key(|prices| prices.utime_since);
+        Ok(StoragePricesHistory { epochs })
+    }
+
+    pub fn epochs(&self) -> &[StoragePrices] {
+        &self.epochs
+    }
+
+    /// Returns prices applied at `utime`, `None` if it precedes all epochs.
+    pub fn prices_at(&self, utime: u32) -> Option<&StoragePrices> {
+        self.epochs
+            .iter()
+            .rev()
+            .find(|prices| prices.utime_since <= utime)
+    }
+
+    /// Computes the fee for storing `cells` and `bits` from `last_paid` to `now`,
+    /// applying the prices of each epoch to its part of the period, as validators do.
+    ///
+    /// Time before the first epoch is free.
+    pub fn compute_storage_fee(
+        &self,
+        cells: u64,
+        bits: u64,
+        last_paid: u32,
+        now: u32,
+        is_masterchain: bool,
+    ) -> Coins {
+        let mut total = BigUint::from(0u32);
+        let mut upto = now;
+        for prices in self.epochs.iter().rev() {
+            if upto <= last_paid {
+                break;
+            }
+            if prices.utime_since < upto {
+                let from = prices.utime_since.max(last_paid);
+                total += prices.fee_shifted(cells, bits, upto - from, is_masterchain);
+                upto = prices.utime_since;
+            }
+        }
+        ceil_shift(total)
+    }
+}
+
+fn ceil_shift(fee_shifted: BigUint) -> Coins {
+    let round_up = (BigUint::from(1u32) << PRICE_SHIFT) - 1u32;
+    Coins::new((fee_shifted + round_up) >> PRICE_SHIFT)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::cell::CellBuilder;
+
+    fn store_prices(builder: &mut CellBuilder, prices: StoragePrices) -> Result<(), TonCellError> {
+        builder
+            .store_u8(8, STORAGE_PRICES_TAG)?
+            .store_u32(32, prices.utime_since)?
+            .store_u64(64, prices.bit_price_ps)?
+            .store_u64(64, prices.cell_price_ps)?
+            .store_u64(64, prices.mc_bit_price_ps)?
+            .store_u64(64, prices.mc_cell_price_ps)?;
+        Ok(())
+    }
+
+    fn prices(utime_since: u32, bit_price_ps: u64, cell_price_ps: u64) -> StoragePrices {
+        StoragePrices {
+            utime_since,
+            bit_price_ps,
+            cell_price_ps,
+            mc_bit_price_ps: bit_price_ps * 1000,
+            mc_cell_price_ps: cell_price_ps * 1000,
+        }
+    }
+
+    #[test]
+    fn test_parse_config_param_18() -> Result<(), TonCellError> {
+        let epochs = vec![prices(0, 1, 500), prices(1700000000, 2, 1000)];
+        let dict: HashMap<u32, StoragePrices> = epochs
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (i as u32, p.clone()))
+            .collect();
+        let cell = CellBuilder::new()
+            .store_dict(32, store_prices, dict)?
+            .build()?;
+
+        let history = StoragePricesHistory::parse(&cell)?;
+        assert_eq!(history.epochs(), epochs.as_slice());
+        assert_eq!(history.prices_at(1699999999), Some(&epochs[0]));
+        assert_eq!(history.prices_at(1700000000), Some(&epochs[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_compute_storage_fee() {
+        let current = prices(0, 1, 500);
+        // (10 * 500 + 1000 * 1) * 65536 / 2^16
+        assert_eq!(
+            current.compute_storage_fee(10, 1000, 65536, false),
+            Coins::from(6000u64)
+        );
+        assert_eq!(
+            current.compute_storage_fee(10, 1000, 65536, true),
+            Coins::from(6_000_000u64)
+        );
+        // 6000 / 2^16 is rounded up
+        assert_eq!(
+            current.compute_storage_fee(10, 1000, 1, false),
+            Coins::from(1u64)
+        );
+        assert_eq!(
+            current.compute_storage_fee(10, 1000, 0, false),
+            Coins::zero()
+        );
+    }
+
+    #[test]
+    fn test_compute_storage_fee_across_epochs() {
+        let history = StoragePricesHistory {
+            epochs: vec![prices(1000, 1, 0), prices(2000, 3, 0)],
+        };
+        // 500s before the first epoch are free, then 1000s at price 1 and 500s at price 3
+        let fee = history.compute_storage_fee(0, 65536, 500, 2500, false);
+        assert_eq!(fee, Coins::from(1000u64 + 500 * 3));
+        // period within a single epoch
+        let fee = history.compute_storage_fee(0, 65536, 2100, 2200, false);
+        assert_eq!(fee, Coins::from(300u64));
+        assert_eq!(
+            history.compute_storage_fee(0, 65536, 2200, 2100, false),
+            Coins::zero()
+        );
+    }
+}
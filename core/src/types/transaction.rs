@@ -0,0 +1,424 @@
+use thiserror::Error;
+
+use crate::cell::dict::predefined_readers::{key_reader_u16, val_reader_ref_cell};
+use crate::cell::{ArcCell, Cell, TonCellError};
+use crate::message::CommonMsgInfo;
+use crate::types::Coins;
+use crate::TonHash;
+
+const TRANSACTION_TAG: u8 = 0b0111;
+const OUT_MSGS_KEY_LEN: usize = 15;
+
+/// Outgoing message of a transaction
+#[derive(Clone, Debug, PartialEq)]
+pub struct OutMessage {
+    /// Key of the message in the `out_msgs` dictionary
+    pub index: u16,
+    pub message: ArcCell,
+    /// Decoded message info, `None` if the message is beyond the decoding limit
+    pub info: Option<CommonMsgInfo>,
+}
+
+/// Parsed `Transaction` structure, without the state update and transaction description
+///
+/// ```raw
+/// transaction$0111 account_addr:bits256 lt:uint64
+///   prev_trans_hash:bits256 prev_trans_lt:uint64 now:uint32
+///   outmsg_cnt:uint15
+///   orig_status:AccountStatus end_status:AccountStatus
+///   ^[ in_msg:(Maybe ^(Message Any)) out_msgs:(HashmapE 15 ^(Message Any)) ]
+///   total_fees:CurrencyCollection state_update:^(HASH_UPDATE Account)
+///   description:^TransactionDescr = Transaction;
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transaction {
+    hash: TonHash,
+    pub account_addr: TonHash,
+    pub lt: u64,
+    pub prev_trans_hash: TonHash,
+    pub prev_trans_lt: u64,
+    pub now: u32,
+    pub in_msg: Option<ArcCell>,
+    /// Decoded info of `in_msg`
+    pub in_msg_info: Option<CommonMsgInfo>,
+    /// Total number of out messages, regardless of the decoding limit
+    pub out_msgs_count: usize,
+    /// Out messages ordered by index
+    pub out_msgs: Vec<OutMessage>,
+    pub total_fees: Coins,
+}
+
+impl Transaction {
+    /// Returns the hash identifying the transaction in explorers and liteserver APIs,
+    /// use `TonHashExt` to format it as hex or base64.
+    pub fn hash(&self) -> TonHash {
+        self.hash
+    }
+
+    /// Parses the transaction, decoding info of all out messages.
+    pub fn parse(cell: &Cell) -> Result<Transaction, TonCellError> {
+        Self::parse_with_limit(cell, usize::MAX)
+    }
+
+    /// Parses the transaction, decoding info of at most `max_decoded_out_msgs` out messages
+    /// with the lowest indexes.
+    ///
+    /// All out messages are still returned as cells, so the limit only bounds the decoding work
+    /// for transactions with a large fan-out (e.g. from highload wallets).
+    pub fn parse_with_limit(
+        cell: &Cell,
+        max_decoded_out_msgs: usize,
+    ) -> Result<Transaction, TonCellError> {
+        let mut parser = cell.parser();
+        let tag = parser.load_u8(4)?;
+        if tag != TRANSACTION_TAG {
+            return Err(TonCellError::InvalidCellData(format!(
+                "Invalid transaction tag: {:04b}",
+                tag
+            )));
+        }
+        let mut account_addr = [0u8; 32];
+        parser.load_slice(&mut account_addr)?;
+        let lt = parser.load_u64(64)?;
+        let mut prev_trans_hash = [0u8; 32];
+        parser.load_slice(&mut prev_trans_hash)?;
+        let prev_trans_lt = parser.load_u64(64)?;
+        let now = parser.load_u32(32)?;
+        let outmsg_cnt = parser.load_u16(15)? as usize;
+        parser.skip_bits(4)?; // orig_status, end_status
+
+        let msgs = parser.next_reference()?;
+        let mut msgs_parser = msgs.parser();
+        let in_msg = msgs_parser.load_maybe_cell_ref()?;
+        let in_msg_info = in_msg
+            .as_ref()
+            .map(|msg| CommonMsgInfo::parse(&mut msg.parser()))
+            .transpose()?;
+        let mut out_msgs: Vec<(u16, ArcCell)> = match msgs_parser.load_maybe_cell_ref()? {
+            Some(root) => root
+                .parser()
+                .load_dict(OUT_MSGS_KEY_LEN, key_reader_u16, val_reader_ref_cell)?
+                .into_iter()
+                .collect(),
+            None => vec![],
+        };
+        if out_msgs.len() != outmsg_cnt {
+            return Err(TonCellError::InvalidCellData(format!(
+                "outmsg_cnt is {}, but out_msgs contains {} messages",
+                outmsg_cnt,
+                out_msgs.len()
+            )));
+        }
+        out_msgs.sort_by_key(|(index, _)| *index);
+        let out_msgs = out_msgs
+            .into_iter()
+            .enumerate()
+            .map(|(pos, (index, message))| {
+                let info = if pos < max_decoded_out_msgs {
+                    Some(CommonMsgInfo::parse(&mut message.parser())?)
+                } else {
+                    None
+                };
+                Ok(OutMessage {
+                    index,
+                    message,
+                    info,
+                })
+            })
+            .collect::<Result<Vec<_>, TonCellError>>()?;
+
+        let total_fees = parser.load_coins()?.into();
+        let _extra_currencies = parser.load_maybe_cell_ref()?;
+
+        Ok(Transaction {
+            hash: cell.cell_hash(),
+            account_addr,
+            lt,
+            prev_trans_hash,
+            prev_trans_lt,
+            now,
+            in_msg,
+            in_msg_info,
+            out_msgs_count: outmsg_cnt,
+            out_msgs,
+            total_fees,
+        })
+    }
+}
+
+/// Break of a transaction chain found by `verify_tx_chain`
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ChainError {
+    /// Transaction at `index` belongs to another account than the first one
+    #[error("Transaction #{index} belongs to another account")]
+    AccountMismatch { index: usize },
+    /// `prev_trans_lt` & `prev_trans_hash` of the transaction at `index`
+    /// don't point to the transaction at `index + 1`
+    #[error(
+        "Chain breaks at transaction #{index}: prev is {}:{}, but next is {}:{}",
+        expected_lt,
+        hex::encode(expected_hash),
+        found_lt,
+        hex::encode(found_hash)
+    )]
+    Broken {
+        index: usize,
+        expected_lt: u64,
+        expected_hash: TonHash,
+        found_lt: u64,
+        found_hash: TonHash,
+    },
+}
+
+/// Checks that `txs` of a single account form a continuous chain.
+///
+/// Transactions are expected from the newest to the oldest, as liteservers return them,
+/// so the prev pointer of each transaction must match the one following it.
+/// The prev pointer of the last transaction isn't checked.
+pub fn verify_tx_chain(txs: &[Transaction]) -> Result<(), ChainError> {
+    let account_addr = match txs.first() {
+        Some(tx) => tx.account_addr,
+        None => return Ok(()),
+    };
+    for (index, pair) in txs.windows(2).enumerate() {
+        let (tx, prev) = (&pair[0], &pair[1]);
+        if prev.account_addr != account_addr {
+            return Err(ChainError::AccountMismatch { index: index + 1 });
+        }
+        if tx.prev_trans_lt != prev.lt || tx.prev_trans_hash != prev.hash {
+            return Err(ChainError::Broken {
+                index,
+                expected_lt: tx.prev_trans_lt,
+                expected_hash: tx.prev_trans_hash,
+                found_lt: prev.lt,
+                found_hash: prev.hash,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use num_bigint::BigUint;
+
+    use super::*;
+    use crate::cell::dict::predefined_writers::val_writer_ref_cell;
+    use crate::cell::{BagOfCells, CellBuilder};
+    use crate::message::{InternalMessage, TonMessage, TransferMessage};
+    use crate::types::TonHashExt;
+    use crate::TonAddress;
+
+    fn out_message(created_lt: u64) -> Result<ArcCell, TonCellError> {
+        let info = CommonMsgInfo::InternalMessage(InternalMessage {
+            ihr_disabled: true,
+            bounce: false,
+            bounced: false,
+            src: TonAddress::new(0, &[0x11; 32]),
+            dest: TonAddress::new(0, &[0x22; 32]),
+            value: BigUint::from(created_lt),
+            ihr_fee: BigUint::from(0u32),
+            fwd_fee: BigUint::from(0u32),
+            created_lt,
+            created_at: 1700000000,
+        });
+        let cell = TransferMessage::new(info)
+            .build()
+            .map_err(|e| TonCellError::InternalError(e.to_string()))?;
+        Ok(Arc::new(cell))
+    }
+
+    fn build_transaction(out_msgs_count: u16) -> Result<Cell, TonCellError> {
+        let mut msgs = CellBuilder::new();
+        msgs.store_bit(false)?; // no in_msg
+        if out_msgs_count == 0 {
+            msgs.store_bit(false)?;
+        } else {
+            let mut dict = HashMap::new();
+            for index in 0..out_msgs_count {
+                dict.insert(index, out_message(1000 + index as u64)?);
+            }
+            let mut root = CellBuilder::new();
+            root.store_dict(OUT_MSGS_KEY_LEN, val_writer_ref_cell, dict)?;
+            msgs.store_bit(true)?.store_child(root.build()?)?;
+        }
+        let empty = Arc::new(Cell::default());
+        CellBuilder::new()
+            .store_u8(4, TRANSACTION_TAG)?
+            .store_slice(&[0x11; 32])?
+            .store_u64(64, 42)?
+            .store_slice(&[0x33; 32])?
+            .store_u64(64, 41)?
+            .store_u32(32, 1700000000)?
+            .store_u32(15, out_msgs_count as u32)?
+            .store_u8(4, 0b1010)? // active -> active
+            .store_child(msgs.build()?)?
+            .store_coins(&BigUint::from(12345u32))?
+            .store_bit(false)?
+            .store_reference(&empty)?
+            .store_reference(&empty)?
+            .build()
+    }
+
+    #[test]
+    fn test_parse_transaction_with_large_fan_out() -> Result<(), TonCellError> {
+        let cell = build_transaction(300)?;
+
+        let tx = Transaction::parse_with_limit(&cell, 10)?;
+        assert_eq!(tx.account_addr, [0x11; 32]);
+        assert_eq!(tx.lt, 42);
+        assert_eq!(tx.prev_trans_hash, [0x33; 32]);
+        assert_eq!(tx.prev_trans_lt, 41);
+        assert_eq!(tx.now, 1700000000);
+        assert_eq!(tx.in_msg, None);
+        assert_eq!(tx.in_msg_info, None);
+        assert_eq!(tx.total_fees, Coins::from(12345u64));
+        assert_eq!(tx.out_msgs_count, 300);
+        assert_eq!(tx.out_msgs.len(), 300);
+        assert!(tx
+            .out_msgs
+            .iter()
+            .enumerate()
+            .all(|(i, m)| m.index as usize == i));
+        assert_eq!(tx.out_msgs.iter().filter(|m| m.info.is_some()).count(), 10);
+        match &tx.out_msgs[9].info {
+            Some(CommonMsgInfo::InternalMessage(info)) => assert_eq!(info.created_lt, 1009),
+            info => panic!("unexpected info: {:?}", info),
+        }
+        assert_eq!(tx.out_msgs[10].info, None);
+
+        let tx = Transaction::parse(&cell)?;
+        assert!(tx.out_msgs.iter().all(|m| m.info.is_some()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_transaction_without_out_msgs() -> Result<(), TonCellError> {
+        let tx = Transaction::parse(&build_transaction(0)?)?;
+        assert_eq!(tx.out_msgs_count, 0);
+        assert!(tx.out_msgs.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_transaction_hash() -> Result<(), TonCellError> {
+        let cell = build_transaction(2)?;
+        let tx = Transaction::parse(&cell)?;
+        assert_eq!(tx.hash(), cell.cell_hash());
+        // the hash is the same as of the root of the transaction BoC
+        let boc = BagOfCells::from_root(cell).serialize(true)?;
+        let root = BagOfCells::parse(&boc)?.single_root()?.clone();
+        assert_eq!(Transaction::parse(&root)?.hash(), tx.hash());
+        assert_eq!(tx.hash().to_hex(), hex::encode(tx.hash()));
+
+        let mut hash = [0xfb; 32];
+        hash[31] = 0xff;
+        assert_eq!(
+            hash.to_hex(),
+            "fbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbfbff"
+        );
+        assert_eq!(
+            hash.to_base64(),
+            "+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/v7+/8="
+        );
+        assert_eq!(
+            hash.to_base64_url(),
+            "-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_v7-_8="
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_explorer_transaction_hash() -> Result<(), TonCellError> {
+        // transaction of EQC-Wm7UqQC0fS0tVfCrgd_NoTBMfH2Fq3qywjXUfybbpNTS at lt 37124585000001
+        // in mainnet block (0,8000000000000000,34703989)
+        let block = include_str!("../../resources/block/basechain_block.b64");
+        let root = BagOfCells::parse_base64(block.trim())?
+            .single_root()?
+            .clone();
+        let parsed = crate::block::parse_block(&root)?;
+        let tx = parsed
+            .account_transactions()
+            .flat_map(|(_, txs)| txs.iter())
+            .find(|tx| tx.lt == 37124585000001)
+            .ok_or_else(|| TonCellError::InternalError("transaction not found".to_string()))?;
+        assert_eq!(
+            tx.hash().to_hex(),
+            "4541c69fde726ec0698be73496f63b6cd00d9383558291ba84ceee4d971055be"
+        );
+        assert_eq!(
+            tx.hash().to_base64_url(),
+            "RUHGn95ybsBpi-c0lvY7bNANk4NVgpG6hM7uTZcQVb4="
+        );
+        Ok(())
+    }
+
+    fn chain(len: u64) -> Vec<Transaction> {
+        let mut txs: Vec<Transaction> = (1..=len)
+            .rev()
+            .map(|lt| Transaction {
+                hash: [lt as u8; 32],
+                account_addr: [0x11; 32],
+                lt: lt * 10,
+                prev_trans_hash: [lt as u8 - 1; 32],
+                prev_trans_lt: (lt - 1) * 10,
+                now: 1700000000 + lt as u32,
+                in_msg: None,
+                in_msg_info: None,
+                out_msgs_count: 0,
+                out_msgs: vec![],
+                total_fees: Coins::from(0u64),
+            })
+            .collect();
+        // the oldest transaction of the batch points outside of it
+        txs.last_mut().unwrap().prev_trans_hash = [0xff; 32];
+        txs
+    }
+
+    #[test]
+    fn test_verify_tx_chain() {
+        assert_eq!(verify_tx_chain(&[]), Ok(()));
+        assert_eq!(verify_tx_chain(&chain(1)), Ok(()));
+        assert_eq!(verify_tx_chain(&chain(5)), Ok(()));
+
+        // a node skipped a transaction
+        let mut txs = chain(5);
+        txs.remove(2);
+        assert_eq!(
+            verify_tx_chain(&txs),
+            Err(ChainError::Broken {
+                index: 1,
+                expected_lt: 30,
+                expected_hash: [3; 32],
+                found_lt: 20,
+                found_hash: [2; 32],
+            })
+        );
+
+        // same lt, but another transaction
+        let mut txs = chain(5);
+        txs[4].hash = [0xaa; 32];
+        assert!(matches!(
+            verify_tx_chain(&txs),
+            Err(ChainError::Broken { index: 3, .. })
+        ));
+
+        // oldest first
+        let mut txs = chain(3);
+        txs.reverse();
+        assert!(matches!(
+            verify_tx_chain(&txs),
+            Err(ChainError::Broken { index: 0, .. })
+        ));
+
+        let mut txs = chain(3);
+        txs[2].account_addr = [0x22; 32];
+        assert_eq!(
+            verify_tx_chain(&txs),
+            Err(ChainError::AccountMismatch { index: 2 })
+        );
+    }
+}
@@ -1,6 +1,7 @@
 mod types;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use lazy_static::lazy_static;
 use nacl::sign::signature;
@@ -14,6 +15,9 @@ use crate::mnemonic::KeyPair;
 use crate::{TonAddress, TonHash};
 
 pub const DEFAULT_WALLET_ID: i32 = 0x29a9a317;
+/// Pay transfer fees separately from the message value, ignore errors
+pub const DEFAULT_SEND_MODE: u8 = 3;
+pub const DEFAULT_VALID_UNTIL_WINDOW: Duration = Duration::from_secs(60);
 
 lazy_static! {
     pub static ref WALLET_V1R1_CODE: BagOfCells = {
@@ -74,7 +78,7 @@ lazy_static! {
     };
 }
 
-#[derive(PartialEq, Eq, Clone, Hash)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum WalletVersion {
     V1R1,
     V1R2,
@@ -170,12 +174,42 @@ impl WalletVersion {
     }
 }
 
+/// Defaults applied to external messages created by `TonWallet`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub struct WalletConfig {
+    /// Send mode of internal messages
+    pub send_mode: u8,
+    /// Lifetime of external messages, see `TonWallet::valid_until`
+    pub valid_until_window: Duration,
+    /// Send external messages as compressed BoCs, see `BagOfCells::to_compressed`.
+    ///
+    /// Enable only if the target liteserver accepts compressed BoCs.
+    pub compress_boc: bool,
+}
+
+impl Default for WalletConfig {
+    fn default() -> Self {
+        WalletConfig {
+            send_mode: DEFAULT_SEND_MODE,
+            valid_until_window: DEFAULT_VALID_UNTIL_WINDOW,
+            compress_boc: false,
+        }
+    }
+}
+
+/// Keys, address & config of a wallet, building and signing its external messages.
+///
+/// `TonWallet` is immutable and doesn't track the wallet seqno: all the methods take `&self`
+/// and are pure, so a single instance can be shared across tasks as is.
+/// The caller provides seqno of each message, so concurrent senders must go through
+/// `WalletSequencer` of `tonlib-client` to avoid building messages with the same seqno.
 #[derive(PartialEq, Eq, Clone, Hash)]
 pub struct TonWallet {
     pub key_pair: KeyPair,
     pub version: WalletVersion,
     pub address: TonAddress,
     pub wallet_id: i32,
+    config: WalletConfig,
 }
 
 impl TonWallet {
@@ -202,32 +236,44 @@ impl TonWallet {
             version,
             address: addr,
             wallet_id,
+            config: WalletConfig::default(),
         })
     }
 
+    /// Derives the wallet in the basechain with `DEFAULT_WALLET_ID`.
     pub fn derive_default(
         version: WalletVersion,
         key_pair: &KeyPair,
     ) -> Result<TonWallet, TonCellError> {
-        let wallet_id = DEFAULT_WALLET_ID;
-        let data = version.initial_data(key_pair, wallet_id)?;
-        let code = version.code()?;
-        let state_init_hash = StateInit::create_account_id(code, &data)?;
-        let hash_part = match state_init_hash.as_slice().try_into() {
-            Ok(hash_part) => hash_part,
-            Err(_) => {
-                return Err(TonCellError::InternalError(
-                    "StateInit returned hash pof wrong size".to_string(),
-                ))
-            }
-        };
-        let addr = TonAddress::new(0, &hash_part);
-        Ok(TonWallet {
-            key_pair: key_pair.clone(),
-            version,
-            address: addr,
-            wallet_id,
-        })
+        Self::derive(0, version, key_pair, DEFAULT_WALLET_ID)
+    }
+
+    /// Derives the wallet in `workchain` with the default wallet id of that workchain,
+    /// see `default_wallet_id`.
+    pub fn derive_default_in_workchain(
+        workchain: i32,
+        version: WalletVersion,
+        key_pair: &KeyPair,
+    ) -> Result<TonWallet, TonCellError> {
+        Self::derive(workchain, version, key_pair, default_wallet_id(workchain))
+    }
+
+    pub fn with_config(mut self, config: WalletConfig) -> TonWallet {
+        self.config = config;
+        self
+    }
+
+    /// Send mode, message lifetime & BoC options used when building messages of the wallet.
+    pub fn config(&self) -> &WalletConfig {
+        &self.config
+    }
+
+    /// Returns expiration time of the message created at `now` (unix time, preferably of the node).
+    ///
+    /// The result is clamped to `u32::MAX`, the max value of `valid_until` field of the message.
+    pub fn valid_until(&self, now: u64) -> u32 {
+        now.saturating_add(self.config.valid_until_window.as_secs())
+            .min(u32::MAX as u64) as u32
     }
 
     pub fn create_external_message<T: AsRef<[ArcCell]>>(
@@ -248,6 +294,21 @@ impl TonWallet {
         expire_at: u32,
         seqno: u32,
         internal_messages: T,
+    ) -> Result<Cell, TonCellError> {
+        self.create_external_body_with_send_mode(
+            expire_at,
+            seqno,
+            internal_messages,
+            self.config.send_mode,
+        )
+    }
+
+    pub fn create_external_body_with_send_mode<T: AsRef<[ArcCell]>>(
+        &self,
+        expire_at: u32,
+        seqno: u32,
+        internal_messages: T,
+        send_mode: u8,
     ) -> Result<Cell, TonCellError> {
         let mut builder = CellBuilder::new();
         builder
@@ -258,7 +319,7 @@ impl TonWallet {
             builder.store_u8(8, 0)?;
         }
         for internal_message in internal_messages.as_ref() {
-            builder.store_u8(8, 3)?; // send_mode
+            builder.store_u8(8, send_mode)?;
             builder.store_reference(internal_message)?;
         }
         builder.build()
@@ -301,10 +362,22 @@ impl TonWallet {
     }
 }
 
+/// Returns the wallet id used by default in `workchain`: `DEFAULT_WALLET_ID` shifted by the workchain id,
+/// as wallet apps do, so the same key gets distinct wallets in different workchains.
+pub fn default_wallet_id(workchain: i32) -> i32 {
+    DEFAULT_WALLET_ID.wrapping_add(workchain)
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::mnemonic::{Mnemonic, MnemonicError};
-    use crate::wallet::{TonWallet, WalletVersion};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::cell::Cell;
+    use crate::mnemonic::{KeyPair, Mnemonic, MnemonicError};
+    use crate::wallet::{
+        default_wallet_id, TonWallet, WalletConfig, WalletVersion, DEFAULT_WALLET_ID,
+    };
     use crate::TonAddress;
 
     #[test]
@@ -331,4 +404,61 @@ mod tests {
         assert_eq!(wallet_v4r2.address, expected_v4r2);
         Ok(())
     }
+
+    #[test]
+    fn derive_wallet_in_custom_workchain_works() -> anyhow::Result<()> {
+        let mnemonic_str = "fancy carpet hello mandate penalty trial consider \
+        property top vicious exit rebuild tragic profit urban major total month holiday \
+        sudden rib gather media vicious";
+        let key_pair = Mnemonic::from_str(mnemonic_str, &None)?.to_key_pair()?;
+        let basechain = TonWallet::derive_default_in_workchain(0, WalletVersion::V4R2, &key_pair)?;
+        assert_eq!(
+            basechain.address,
+            TonWallet::derive_default(WalletVersion::V4R2, &key_pair)?.address
+        );
+
+        let wallet = TonWallet::derive_default_in_workchain(1, WalletVersion::V4R2, &key_pair)?;
+        assert_eq!(wallet.wallet_id, DEFAULT_WALLET_ID + 1);
+        assert_eq!(wallet.address.workchain, 1);
+        let same_id = TonWallet::derive(1, WalletVersion::V4R2, &key_pair, default_wallet_id(1))?;
+        assert_eq!(wallet.address, same_id.address);
+        assert_ne!(wallet.address.hash_part, basechain.address.hash_part);
+        let formatted = wallet.address.to_base64_url();
+        assert_eq!(formatted.parse::<TonAddress>()?, wallet.address);
+        Ok(())
+    }
+
+    #[test]
+    fn wallet_config_works() -> anyhow::Result<()> {
+        let mnemonic_str = "fancy carpet hello mandate penalty trial consider \
+        property top vicious exit rebuild tragic profit urban major total month holiday \
+        sudden rib gather media vicious";
+        let key_pair = Mnemonic::from_str(mnemonic_str, &None)?.to_key_pair()?;
+        let wallet = TonWallet::derive_default(WalletVersion::V4R2, &key_pair)?;
+        assert_eq!(wallet.valid_until(1_700_000_000), 1_700_000_060);
+
+        let wallet = wallet.with_config(WalletConfig {
+            send_mode: 1,
+            valid_until_window: Duration::from_secs(600),
+            compress_boc: false,
+        });
+        assert_eq!(wallet.valid_until(1_700_000_000), 1_700_000_600);
+        assert_eq!(wallet.valid_until(u32::MAX as u64 - 1), u32::MAX);
+
+        let message = Arc::new(Cell::default());
+        let body = wallet.create_external_body(1_700_000_600, 1, [message])?;
+        let mut parser = body.parser();
+        parser.skip_bits(32 + 32 + 32 + 8)?;
+        assert_eq!(parser.load_u8(8)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_wallet_is_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<TonWallet>();
+        assert_send_sync::<KeyPair>();
+        assert_send_sync::<WalletVersion>();
+        assert_send_sync::<WalletConfig>();
+    }
 }
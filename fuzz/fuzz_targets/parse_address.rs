@@ -0,0 +1,10 @@
+#![no_main]
+
+use std::str::FromStr;
+
+use libfuzzer_sys::fuzz_target;
+use tonlib_core::TonAddress;
+
+fuzz_target!(|data: &str| {
+    let _ = TonAddress::from_str(data);
+});
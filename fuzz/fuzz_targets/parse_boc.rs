@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use tonlib_core::cell::BagOfCells;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(boc) = BagOfCells::parse(data) {
+        for root in boc.roots.iter() {
+            let _ = root.cell_hash();
+            let _ = root.parser().load_bytes_snake();
+        }
+        let _ = boc.serialize(true);
+    }
+});